@@ -0,0 +1,47 @@
+//! Throughput of grouped and global aggregations.
+
+mod harness;
+
+use declarative_dataflow::plan::{Aggregate, AggregationFn, Join, Project};
+use declarative_dataflow::Plan;
+
+fn main() {
+    let config = harness::Config::from_args();
+    let (e, a, h) = (1, 2, 3);
+
+    harness::run(
+        "[:find (count ?e) :where [?e :age ?a]]",
+        Plan::Aggregate(Aggregate {
+            variables: vec![e],
+            plan: Box::new(Plan::Project(Project {
+                variables: vec![e],
+                plan: Box::new(Plan::match_a(e, ":age", a)),
+            })),
+            aggregation_fns: vec![AggregationFn::COUNT],
+            key_variables: vec![],
+            aggregation_variables: vec![e],
+            with_variables: vec![],
+        }),
+        &config,
+    );
+
+    harness::run(
+        "[:find ?a (max ?h) :where [?e :age ?a] [?e :height ?h]]",
+        Plan::Aggregate(Aggregate {
+            variables: vec![a, h],
+            plan: Box::new(Plan::Project(Project {
+                variables: vec![a, h],
+                plan: Box::new(Plan::Join(Join {
+                    variables: vec![e],
+                    left_plan: Box::new(Plan::match_a(e, ":age", a)),
+                    right_plan: Box::new(Plan::match_a(e, ":height", h)),
+                })),
+            })),
+            aggregation_fns: vec![AggregationFn::MAX],
+            key_variables: vec![a],
+            aggregation_variables: vec![h],
+            with_variables: vec![e],
+        }),
+        &config,
+    );
+}
@@ -0,0 +1,53 @@
+//! Throughput of Filter stages over high-rate attribute inputs.
+
+mod harness;
+
+use declarative_dataflow::binding::Binding;
+use declarative_dataflow::plan::{Filter, Hector, Predicate};
+use declarative_dataflow::{Plan, Value};
+use Value::Number;
+
+fn main() {
+    let config = harness::Config::from_args();
+    let (e, a, b) = (1, 2, 3);
+
+    harness::run(
+        "[?e :age ?a] [(< ?a 50)]",
+        Plan::Filter(Filter {
+            variables: vec![a],
+            predicate: Predicate::LT,
+            plan: Box::new(Plan::match_a(e, ":age", a)),
+            constants: vec![None, Some(Number(50))],
+        }),
+        &config,
+    );
+
+    harness::run(
+        "[?e :age ?a] [(< 50 ?a)]",
+        Plan::Filter(Filter {
+            variables: vec![a],
+            predicate: Predicate::LT,
+            plan: Box::new(Plan::match_a(e, ":age", a)),
+            constants: vec![Some(Number(50)), None],
+        }),
+        &config,
+    );
+
+    harness::run(
+        "[?e :age ?a] [?e :height ?b] [(< ?a ?b)]",
+        Plan::Filter(Filter {
+            variables: vec![a, b],
+            predicate: Predicate::LT,
+            plan: Box::new(Plan::Hector(Hector {
+                variables: vec![e, a, b],
+                bindings: vec![
+                    Binding::attribute(e, ":age", a),
+                    Binding::attribute(e, ":height", b),
+                ],
+                order: Default::default(),
+            })),
+            constants: vec![None, None],
+        }),
+        &config,
+    );
+}
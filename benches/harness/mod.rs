@@ -0,0 +1,132 @@
+//! A small harness shared by all plan benchmarks. Each benchmark
+//! loads a generated dataset of people, their age, height, and a
+//! friend, and then applies rounds of updates to randomly chosen
+//! people, reporting how long it took for results to catch up.
+//!
+//! Sizes are taken from the command line, as in `cargo bench --bench
+//! join -- [ENTITIES] [UPDATES] [ROUNDS]`, where `UPDATES` is the
+//! number of people changed per round.
+
+use std::time::{Duration, Instant};
+
+use declarative_dataflow::server::Server;
+use declarative_dataflow::{Aid, AttributeConfig, Datom, InputSemantics, Plan, Rule, Value};
+use Value::{Eid, Number};
+
+/// Attributes of generated people.
+pub const ATTRIBUTES: [&str; 3] = [":age", ":height", ":friend"];
+
+/// Dataset sizes and update rates.
+pub struct Config {
+    /// Number of people loaded initially.
+    pub entities: usize,
+    /// Number of people updated per round.
+    pub updates: usize,
+    /// Number of update rounds.
+    pub rounds: usize,
+}
+
+impl Config {
+    /// Reads sizes from the command line, falling back to defaults
+    /// for those missing.
+    pub fn from_args() -> Self {
+        let sizes: Vec<usize> = std::env::args()
+            .skip(1)
+            .filter_map(|arg| arg.parse().ok())
+            .collect();
+
+        Config {
+            entities: sizes.get(0).cloned().unwrap_or(100_000),
+            updates: sizes.get(1).cloned().unwrap_or(1_000),
+            rounds: sizes.get(2).cloned().unwrap_or(10),
+        }
+    }
+}
+
+/// All datoms describing the specified person, at the specified
+/// version.
+fn person(e: u64, version: u64, entities: u64) -> Vec<(&'static str, Value)> {
+    vec![
+        (ATTRIBUTES[0], Number(((e + version) % 100) as i64)),
+        (ATTRIBUTES[1], Number(((3 * e + version) % 73) as i64)),
+        (ATTRIBUTES[2], Eid((31 * e + 7 * version + 1) % entities)),
+    ]
+}
+
+fn seconds(duration: Duration) -> f64 {
+    duration.as_secs() as f64 + f64::from(duration.subsec_nanos()) * 1e-9
+}
+
+/// Loads the dataset, applies all update rounds, and reports timings
+/// for the specified plan.
+pub fn run(description: &'static str, plan: Plan<Aid>, config: &Config) {
+    let (entities, updates, rounds) = (config.entities, config.updates, config.rounds);
+
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, u64>::new(Default::default());
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            for name in ATTRIBUTES.iter() {
+                server
+                    .create_attribute(scope, *name, AttributeConfig::tx_time(InputSemantics::Raw))
+                    .unwrap();
+            }
+
+            server.test_single(scope, Rule::named("bench", plan));
+        });
+
+        let mut versions = vec![0; entities];
+        let timer = Instant::now();
+
+        let mut tx_data = Vec::with_capacity(ATTRIBUTES.len() * entities);
+        for e in 0..entities as u64 {
+            for (a, v) in person(e, 0, entities as u64) {
+                tx_data.push(Datom::add(e, a, v));
+            }
+        }
+
+        server.transact(tx_data, 0, 0).unwrap();
+        server.advance_domain(None, 1).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        let loaded = timer.elapsed();
+        let timer = Instant::now();
+
+        for round in 0..rounds {
+            let mut tx_data = Vec::with_capacity(2 * ATTRIBUTES.len() * updates);
+
+            for i in 0..updates {
+                // A cheap, deterministic spread over all people.
+                let e = ((round * updates + i) * 7919) % entities;
+                let version = versions[e];
+
+                for (a, v) in person(e as u64, version, entities as u64) {
+                    tx_data.push(Datom::retract(e as u64, a, v));
+                }
+                for (a, v) in person(e as u64, version + 1, entities as u64) {
+                    tx_data.push(Datom::add(e as u64, a, v));
+                }
+
+                versions[e] += 1;
+            }
+
+            server.transact(tx_data, 0, 0).unwrap();
+            server.advance_domain(None, round as u64 + 2).unwrap();
+            worker.step_while(|| server.is_any_outdated());
+        }
+
+        let updated = timer.elapsed();
+
+        println!(
+            "{}\n\tload: {} people in {:?} ({:.0} people/s)\n\tupdates: {} rounds in {:?} ({:.0} people/s, {:?} per round)",
+            description,
+            entities,
+            loaded,
+            entities as f64 / seconds(loaded),
+            rounds,
+            updated,
+            (rounds * updates) as f64 / seconds(updated),
+            updated / (rounds.max(1) as u32),
+        );
+    });
+}
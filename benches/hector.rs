@@ -0,0 +1,40 @@
+//! Throughput of worst-case optimal joins.
+
+mod harness;
+
+use declarative_dataflow::binding::Binding;
+use declarative_dataflow::plan::Hector;
+use declarative_dataflow::Plan;
+
+fn main() {
+    let config = harness::Config::from_args();
+    let (e, a, h, f, fa) = (1, 2, 3, 4, 5);
+
+    harness::run(
+        "[?e :age ?a] [?e :height ?h] [?e :friend ?f]",
+        Plan::Hector(Hector {
+            variables: vec![e, a, h, f],
+            bindings: vec![
+                Binding::attribute(e, ":age", a),
+                Binding::attribute(e, ":height", h),
+                Binding::attribute(e, ":friend", f),
+            ],
+            order: Default::default(),
+        }),
+        &config,
+    );
+
+    harness::run(
+        "[?e :friend ?f] [?f :age ?fa] [?e :age ?fa]",
+        Plan::Hector(Hector {
+            variables: vec![e, f, fa],
+            bindings: vec![
+                Binding::attribute(e, ":friend", f),
+                Binding::attribute(f, ":age", fa),
+                Binding::attribute(e, ":age", fa),
+            ],
+            order: Default::default(),
+        }),
+        &config,
+    );
+}
@@ -0,0 +1,34 @@
+//! Throughput of binary equijoins between attributes.
+
+mod harness;
+
+use declarative_dataflow::plan::{Join, Project};
+use declarative_dataflow::Plan;
+
+fn main() {
+    let config = harness::Config::from_args();
+    let (e, a, h, f, fa) = (1, 2, 3, 4, 5);
+
+    harness::run(
+        "[?e :age ?a] [?e :height ?h]",
+        Plan::Join(Join {
+            variables: vec![e],
+            left_plan: Box::new(Plan::match_a(e, ":age", a)),
+            right_plan: Box::new(Plan::match_a(e, ":height", h)),
+        }),
+        &config,
+    );
+
+    harness::run(
+        "[?e :friend ?f] [?f :age ?fa]",
+        Plan::Project(Project {
+            variables: vec![e, fa],
+            plan: Box::new(Plan::Join(Join {
+                variables: vec![f],
+                left_plan: Box::new(Plan::match_a(e, ":friend", f)),
+                right_plan: Box::new(Plan::match_a(f, ":age", fa)),
+            })),
+        }),
+        &config,
+    );
+}
@@ -0,0 +1,25 @@
+//! Throughput of pull expressions.
+
+mod harness;
+
+use declarative_dataflow::plan::PullLevel;
+use declarative_dataflow::Plan;
+
+fn main() {
+    let config = harness::Config::from_args();
+    let (e, f) = (1, 2);
+
+    harness::run(
+        "[(pull ?e [:age :height]) :where [?e :friend ?f]]",
+        Plan::PullLevel(PullLevel {
+            variables: vec![],
+            pull_variable: e,
+            plan: Box::new(Plan::match_a(e, ":friend", f)),
+            pull_attributes: vec![":age".to_string(), ":height".to_string()],
+            pull_rules: vec![],
+            path_attributes: vec![],
+            cardinality_many: false,
+        }),
+        &config,
+    );
+}
@@ -134,8 +134,9 @@ fn main() {
                     granularity: None,
                     sink: Some(Sink::AssocIn(AssocIn {
                         stateful: granularity,
+                        cardinality_one: vec![],
                     })),
-                    disable_logging: None,
+                    ..Default::default()
                 }),
             ])
             .expect("failed to serialize requests");
@@ -162,7 +163,7 @@ fn handle_message(msg: ws::Message) -> ws::Result<()> {
                         let pprinted = serde_json::to_string_pretty(&v).expect("failed to pprint");
                         info!("{}@{:?}\n{}", diff, t, pprinted);
                     }
-                    Output::Error(_, err, tx_id) => error!("{:?} @ {}", err, tx_id),
+                    Output::Error(_, err, tx_id, _) => error!("{:?} @ {}", err, tx_id),
                     _ => info!("{:?}", out),
                 },
             }
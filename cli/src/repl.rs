@@ -0,0 +1,636 @@
+//! 3DF REPL. Accepts Datalog queries and transactions interactively,
+//! and keeps the results of all running queries up to date as
+//! tables. Connects to a server, or runs one in-process.
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::io::{self, BufRead, Write};
+use std::rc::Rc;
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+use clap::{App, Arg};
+
+use timely::communication::allocator::Thread;
+use timely::worker::Worker;
+
+use declarative_dataflow::client::{Client, Subscription};
+use declarative_dataflow::frontends::datalog;
+use declarative_dataflow::frontends::edn::{self, Edn};
+use declarative_dataflow::server::{CreateAttribute, RegisterQuery, Request, Server};
+use declarative_dataflow::{Aid, AttributeConfig, Datom, Error, InputSemantics, Output, Value};
+
+const HELP: &str = "\
+[:find ...]                      run a Datalog query and keep its results up to date
+.attr <name> [raw|lww|distinct]  create an attribute
+.tx [:db/add e a v] ...          transact datoms, retracting via :db/retract
+.drop <name>                     stop a running query
+.show [<name>]                   print the results of one or all running queries
+.help                            print this message
+.quit                            exit";
+
+/// A change to the results of a named query.
+type Change = (String, Vec<Value>, isize);
+
+/// Wherever queries are actually run.
+trait Backend {
+    fn create_attribute(&mut self, name: &str, semantics: InputSemantics) -> Result<(), Error>;
+    fn transact(&mut self, tx_data: Vec<Datom<Aid>>) -> Result<(), Error>;
+    fn query(&mut self, name: &str, query: &str) -> Result<(), Error>;
+    fn cancel(&mut self, name: &str) -> Result<(), Error>;
+    /// Returns all changes that arrived since the last call.
+    fn poll(&mut self) -> Vec<Change>;
+}
+
+/// Runs queries on a server.
+struct Remote {
+    client: Client,
+    subscriptions: HashMap<String, Subscription>,
+}
+
+impl Backend for Remote {
+    fn create_attribute(&mut self, name: &str, semantics: InputSemantics) -> Result<(), Error> {
+        self.client
+            .request(vec![Request::CreateAttribute(CreateAttribute {
+                name: name.to_string(),
+                config: AttributeConfig::tx_time(semantics),
+                backfill: None,
+            })])
+            .map(|_tx| ())
+    }
+
+    fn transact(&mut self, tx_data: Vec<Datom<Aid>>) -> Result<(), Error> {
+        self.client.transact(tx_data).map(|_tx| ())
+    }
+
+    fn query(&mut self, name: &str, query: &str) -> Result<(), Error> {
+        self.client
+            .request(vec![Request::RegisterQuery(RegisterQuery {
+                name: name.to_string(),
+                query: query.to_string(),
+                language: Default::default(),
+            })])?;
+
+        let subscription = self.client.subscribe(name)?;
+        self.subscriptions.insert(name.to_string(), subscription);
+
+        Ok(())
+    }
+
+    fn cancel(&mut self, name: &str) -> Result<(), Error> {
+        // Dropping the subscription uninterests it.
+        match self.subscriptions.remove(name) {
+            None => Err(Error::not_found(format!("No query named {}.", name))),
+            Some(_subscription) => Ok(()),
+        }
+    }
+
+    fn poll(&mut self) -> Vec<Change> {
+        let mut changes = Vec::new();
+
+        for (name, subscription) in self.subscriptions.iter() {
+            while let Some(output) = subscription.recv_timeout(Duration::from_millis(0)) {
+                if let Output::QueryDiff(_, results) = output {
+                    for (tuple, _time, diff) in results {
+                        changes.push((name.clone(), tuple, diff));
+                    }
+                }
+            }
+        }
+
+        changes
+    }
+}
+
+/// Runs queries on a single in-process worker, which is stepped to
+/// completion after every request.
+struct Local {
+    worker: Worker<Thread>,
+    server: Server<Aid, u64, ()>,
+    next_tx: u64,
+    changes: Rc<RefCell<Vec<Change>>>,
+}
+
+impl Local {
+    fn new() -> Self {
+        Local {
+            worker: Worker::new(Thread::new()),
+            server: Server::new(Default::default()),
+            next_tx: 0,
+            changes: Default::default(),
+        }
+    }
+
+    fn step(&mut self) {
+        let Local {
+            ref mut worker,
+            ref server,
+            ..
+        } = *self;
+
+        worker.step_while(|| server.is_any_outdated());
+    }
+}
+
+impl Backend for Local {
+    fn create_attribute(&mut self, name: &str, semantics: InputSemantics) -> Result<(), Error> {
+        let Local {
+            ref mut worker,
+            ref mut server,
+            ..
+        } = *self;
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server.create_attribute(scope, name, AttributeConfig::tx_time(semantics))
+        })
+    }
+
+    fn transact(&mut self, tx_data: Vec<Datom<Aid>>) -> Result<(), Error> {
+        self.server.transact(tx_data, 0, 0)?;
+
+        self.next_tx += 1;
+        self.server.advance_domain(None, self.next_tx)?;
+
+        self.step();
+
+        Ok(())
+    }
+
+    fn query(&mut self, name: &str, query: &str) -> Result<(), Error> {
+        self.server.register_query(RegisterQuery {
+            name: name.to_string(),
+            query: query.to_string(),
+            language: Default::default(),
+        })?;
+
+        {
+            let Local {
+                ref mut worker,
+                ref mut server,
+                ref changes,
+                ..
+            } = *self;
+
+            let name = name.to_string();
+            let changes = changes.clone();
+
+            worker.dataflow::<u64, _, _>(|scope| {
+                server.interest(name.clone(), scope).map(|relation| {
+                    relation
+                        .inspect(move |(tuple, _time, diff)| {
+                            changes
+                                .borrow_mut()
+                                .push((name.clone(), tuple.clone(), *diff))
+                        })
+                        .probe_with(&mut server.probe);
+                })
+            })?;
+        }
+
+        self.step();
+
+        Ok(())
+    }
+
+    fn cancel(&mut self, name: &str) -> Result<(), Error> {
+        self.server.cancel(&name.to_string())
+    }
+
+    fn poll(&mut self) -> Vec<Change> {
+        self.changes.borrow_mut().drain(..).collect()
+    }
+}
+
+enum Command {
+    Query(String),
+    Attribute(String, InputSemantics),
+    Transact(Vec<Datom<Aid>>),
+    Drop(String),
+    Show(Option<String>),
+    Help,
+    Quit,
+}
+
+fn parse_command(input: &str) -> Result<Command, Error> {
+    let input = input.trim();
+
+    if input.starts_with('[') {
+        return Ok(Command::Query(input.to_string()));
+    }
+
+    let mut words = input.split_whitespace();
+    let command = words.next().unwrap_or("");
+    let argument = words.next();
+
+    match command {
+        ".attr" => {
+            let name = argument.ok_or_else(|| Error::incorrect("Expected an attribute name."))?;
+            let semantics = match words.next() {
+                None | Some("raw") => InputSemantics::Raw,
+                Some("lww") => InputSemantics::LastWriteWins,
+                Some("distinct") => InputSemantics::Distinct,
+                Some(other) => {
+                    return Err(Error::incorrect(format!(
+                        "Unknown input semantics {}.",
+                        other
+                    )));
+                }
+            };
+
+            Ok(Command::Attribute(name.to_string(), semantics))
+        }
+        ".tx" => parse_tx_data(input[command.len()..].trim()).map(Command::Transact),
+        ".drop" => argument
+            .map(|name| Command::Drop(name.to_string()))
+            .ok_or_else(|| Error::incorrect("Expected a query name.")),
+        ".show" => Ok(Command::Show(argument.map(|name| name.to_string()))),
+        ".help" => Ok(Command::Help),
+        ".quit" | ".exit" => Ok(Command::Quit),
+        other => Err(Error::incorrect(format!(
+            "Unknown command {}, try .help",
+            other
+        ))),
+    }
+}
+
+/// Reads datoms of the form `[:db/add e a v]` or `[:db/retract e a
+/// v]`, where e is either an entity id or a lookup ref `[a' v']`.
+fn parse_tx_data(input: &str) -> Result<Vec<Datom<Aid>>, Error> {
+    let mut tx_data = Vec::new();
+
+    for form in edn::read(input)? {
+        let datom = match form {
+            Edn::Vector(ref items) => match items.as_slice() {
+                [Edn::Keyword(op), e, Edn::Keyword(a), v] => {
+                    let diff = match op.as_str() {
+                        "db/add" => 1,
+                        "db/retract" => -1,
+                        _ => return Err(Error::incorrect(format!("Unknown operation :{}.", op))),
+                    };
+
+                    Datom(
+                        entity(e)?,
+                        format!(":{}", a),
+                        datalog::constant(v)?,
+                        None,
+                        diff,
+                    )
+                }
+                _ => return Err(Error::incorrect(format!("Invalid datom {:?}.", form))),
+            },
+            _ => return Err(Error::incorrect(format!("Invalid datom {:?}.", form))),
+        };
+
+        tx_data.push(datom);
+    }
+
+    Ok(tx_data)
+}
+
+fn entity(form: &Edn) -> Result<Value, Error> {
+    match form {
+        Edn::Integer(e) if *e >= 0 => Ok(Value::Eid(*e as u64)),
+        Edn::BigInt(e) => Ok(Value::Eid(*e)),
+        Edn::Vector(lookup) => match lookup.as_slice() {
+            [Edn::Keyword(a), v] => Ok(Value::LookupRef(
+                format!(":{}", a),
+                Box::new(datalog::constant(v)?),
+            )),
+            _ => Err(Error::incorrect(format!("Invalid lookup ref {:?}.", form))),
+        },
+        _ => Err(Error::incorrect(format!(
+            "{:?} is not a valid entity position.",
+            form
+        ))),
+    }
+}
+
+/// The nesting depth at the end of the input, s.t. queries can be
+/// spread across several lines.
+fn depth(input: &str) -> isize {
+    let mut depth = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for c in input.chars() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+        } else {
+            match c {
+                '"' => in_string = true,
+                '[' | '(' | '{' => depth += 1,
+                ']' | ')' | '}' => depth -= 1,
+                _ => {}
+            }
+        }
+    }
+
+    depth
+}
+
+/// Column headers for the find specification of a query, e.g.
+/// `?name` or `(count ?e)`.
+fn columns(query: &str) -> Vec<String> {
+    match edn::read_one(query) {
+        Ok(Edn::Vector(forms)) => forms
+            .iter()
+            .skip_while(|form| **form != Edn::Keyword("find".to_string()))
+            .skip(1)
+            .take_while(|form| match form {
+                Edn::Keyword(_) => false,
+                _ => true,
+            })
+            .map(render_edn)
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn render_edn(form: &Edn) -> String {
+    match form {
+        Edn::Symbol(symbol) => symbol.clone(),
+        Edn::List(items) => {
+            let items: Vec<String> = items.iter().map(render_edn).collect();
+            format!("({})", items.join(" "))
+        }
+        other => format!("{:?}", other),
+    }
+}
+
+fn render(value: &Value) -> String {
+    match value {
+        Value::Aid(aid) => aid.clone(),
+        Value::String(string) => format!("{:?}", string),
+        Value::Bool(boolean) => boolean.to_string(),
+        Value::Number(number) => number.to_string(),
+        Value::Eid(eid) => eid.to_string(),
+        Value::Instant(instant) => format!("#inst {}", instant),
+        Value::Uuid(uuid) => format!("#uuid \"{}\"", uuid),
+        other => format!("{:?}", other),
+    }
+}
+
+/// The current results of a running query.
+struct Table {
+    columns: Vec<String>,
+    rows: BTreeMap<Vec<Value>, isize>,
+}
+
+impl Table {
+    fn update(&mut self, tuple: Vec<Value>, diff: isize) {
+        let count = {
+            let count = self.rows.entry(tuple.clone()).or_insert(0);
+            *count += diff;
+            *count
+        };
+
+        if count == 0 {
+            self.rows.remove(&tuple);
+        }
+    }
+
+    fn print(&self, name: &str) {
+        let rows: Vec<Vec<String>> = self
+            .rows
+            .iter()
+            .map(|(tuple, count)| {
+                let mut row: Vec<String> = tuple.iter().map(render).collect();
+                if *count != 1 {
+                    row.push(format!("x{}", count));
+                }
+                row
+            })
+            .collect();
+
+        let mut widths: Vec<usize> = self.columns.iter().map(|column| column.len()).collect();
+        for row in rows.iter() {
+            for (i, cell) in row.iter().enumerate() {
+                if i == widths.len() {
+                    widths.push(0);
+                }
+                widths[i] = widths[i].max(cell.len());
+            }
+        }
+
+        let separator: Vec<String> = widths.iter().map(|width| "-".repeat(*width + 2)).collect();
+        let separator = format!("+{}+", separator.join("+"));
+
+        let format_row = |cells: &[String]| {
+            let cells: Vec<String> = widths
+                .iter()
+                .enumerate()
+                .map(|(i, width)| {
+                    let cell = cells.get(i).map(|cell| cell.as_str()).unwrap_or("");
+                    format!(" {:width$} ", cell, width = width)
+                })
+                .collect();
+
+            format!("|{}|", cells.join("|"))
+        };
+
+        println!("{} ({} rows)", name, self.rows.len());
+        println!("{}", separator);
+        println!("{}", format_row(&self.columns));
+        println!("{}", separator);
+        for row in rows.iter() {
+            println!("{}", format_row(row));
+        }
+        println!("{}", separator);
+    }
+}
+
+struct Repl {
+    backend: Box<dyn Backend>,
+    tables: BTreeMap<String, Table>,
+    next_query: usize,
+}
+
+impl Repl {
+    /// Executes a command, returning false iff the REPL should exit.
+    fn execute(&mut self, command: Command) -> Result<bool, Error> {
+        match command {
+            Command::Query(query) => {
+                let name = format!("q{}", self.next_query);
+                self.next_query += 1;
+
+                self.backend.query(&name, &query)?;
+                self.tables.insert(
+                    name.clone(),
+                    Table {
+                        columns: columns(&query),
+                        rows: BTreeMap::new(),
+                    },
+                );
+
+                println!("Running as {}.", name);
+            }
+            Command::Attribute(name, semantics) => {
+                self.backend.create_attribute(&name, semantics)?
+            }
+            Command::Transact(tx_data) => self.backend.transact(tx_data)?,
+            Command::Drop(name) => {
+                self.backend.cancel(&name)?;
+                self.tables.remove(&name);
+            }
+            Command::Show(None) => {
+                for (name, table) in self.tables.iter() {
+                    table.print(name);
+                }
+            }
+            Command::Show(Some(name)) => match self.tables.get(&name) {
+                None => return Err(Error::not_found(format!("No query named {}.", name))),
+                Some(table) => table.print(&name),
+            },
+            Command::Help => println!("{}", HELP),
+            Command::Quit => return Ok(false),
+        }
+
+        Ok(true)
+    }
+
+    /// Applies all changes that arrived in the meantime and prints
+    /// the affected tables. Returns true iff anything was printed.
+    fn refresh(&mut self) -> bool {
+        let mut changed = BTreeSet::new();
+
+        for (name, tuple, diff) in self.backend.poll() {
+            // Results might still trickle in for dropped queries.
+            if let Some(table) = self.tables.get_mut(&name) {
+                table.update(tuple, diff);
+                changed.insert(name);
+            }
+        }
+
+        for name in changed.iter() {
+            self.tables[name].print(name);
+        }
+
+        !changed.is_empty()
+    }
+}
+
+/// Reads stdin on a separate thread, s.t. results can be printed
+/// while waiting for input.
+fn read_lines() -> Receiver<String> {
+    let (send_lines, lines) = channel();
+
+    thread::spawn(move || {
+        let stdin = io::stdin();
+        for line in stdin.lock().lines() {
+            match line {
+                Err(_) => break,
+                Ok(line) => {
+                    if send_lines.send(line).is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    });
+
+    lines
+}
+
+fn prompt(continued: bool) {
+    if continued {
+        print!("   ... ");
+    } else {
+        print!("3df> ");
+    }
+
+    io::stdout().flush().expect("failed to flush stdout");
+}
+
+fn main() {
+    env_logger::init();
+
+    let matches = App::new("3df-repl")
+        .about("Interactive shell for Declarative Dataflow.")
+        .arg(
+            Arg::with_name("host")
+                .long("host")
+                .takes_value(true)
+                .help("Server host, defaults to 127.0.0.1."),
+        )
+        .arg(
+            Arg::with_name("port")
+                .long("port")
+                .takes_value(true)
+                .help("Server port, defaults to 6262."),
+        )
+        .arg(
+            Arg::with_name("local")
+                .long("local")
+                .help("Runs a server in-process, instead of connecting to one."),
+        )
+        .get_matches();
+
+    let backend: Box<dyn Backend> = if matches.is_present("local") {
+        Box::new(Local::new())
+    } else {
+        let host = matches.value_of("host").unwrap_or("127.0.0.1");
+        let port = matches.value_of("port").unwrap_or("6262");
+
+        match Client::connect(&format!("ws://{}:{}", host, port)) {
+            Err(error) => {
+                eprintln!("{}", error.message);
+                std::process::exit(1);
+            }
+            Ok(client) => Box::new(Remote {
+                client,
+                subscriptions: HashMap::new(),
+            }),
+        }
+    };
+
+    let mut repl = Repl {
+        backend,
+        tables: BTreeMap::new(),
+        next_query: 0,
+    };
+
+    let lines = read_lines();
+    let mut buffer = String::new();
+
+    println!("Type .help for a list of commands.");
+    prompt(false);
+
+    loop {
+        match lines.recv_timeout(Duration::from_millis(100)) {
+            Err(RecvTimeoutError::Disconnected) => break,
+            Err(RecvTimeoutError::Timeout) => {}
+            Ok(line) => {
+                buffer.push_str(&line);
+                buffer.push('\n');
+
+                if depth(&buffer) > 0 {
+                    prompt(true);
+                    continue;
+                }
+
+                let input = std::mem::replace(&mut buffer, String::new());
+
+                if !input.trim().is_empty() {
+                    match parse_command(&input).and_then(|command| repl.execute(command)) {
+                        Err(error) => eprintln!("{}", error.message),
+                        Ok(false) => break,
+                        Ok(true) => {}
+                    }
+                }
+
+                repl.refresh();
+                prompt(false);
+            }
+        }
+
+        if repl.refresh() {
+            prompt(!buffer.is_empty());
+        }
+    }
+}
@@ -0,0 +1,42 @@
+//! Macros for embedding Declarative Dataflow queries in Rust code.
+
+#![forbid(missing_docs)]
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{parse_macro_input, LitStr};
+
+use declarative_dataflow::frontends::datalog;
+use declarative_dataflow::Aid;
+
+/// Compiles a Datalog query literal into a `Plan<Aid>`. The query is
+/// parsed while compiling the crate it is used in, s.t. syntax errors
+/// and unbound variables are reported by the compiler.
+///
+/// ```ignore
+/// use declarative_dataflow_macros::q;
+///
+/// let plan = q!("[:find ?n :where [?e :name ?n] [?e :age ?age] [(>= ?age 18)]]");
+/// ```
+#[proc_macro]
+pub fn q(input: TokenStream) -> TokenStream {
+    let query = parse_macro_input!(input as LitStr);
+
+    match datalog::parse::<Aid>(&query.value()) {
+        Err(error) => syn::Error::new(query.span(), error.message)
+            .to_compile_error()
+            .into(),
+        Ok(_plan) => {
+            // Plans are rebuilt from the literal at runtime, which
+            // can't fail anymore at that point.
+            let expanded = quote! {
+                ::declarative_dataflow::frontends::datalog::parse::<::declarative_dataflow::Aid>(#query)
+                    .expect("query was validated at compile time")
+            };
+
+            expanded.into()
+        }
+    }
+}
@@ -0,0 +1,13 @@
+use declarative_dataflow::frontends::datalog;
+use declarative_dataflow::{Aid, Plan};
+use declarative_dataflow_macros::q;
+
+#[test]
+fn compile_time_query() {
+    let plan: Plan<Aid> = q!("[:find ?n :where [?e :name ?n] [?e :age ?age] [(>= ?age 18)]]");
+
+    assert_eq!(
+        plan,
+        datalog::parse("[:find ?n :where [?e :name ?n] [?e :age ?age] [(>= ?age 18)]]").unwrap()
+    );
+}
@@ -0,0 +1,24 @@
+import declarative
+from declarative import Plan
+
+engine = declarative.Engine()
+engine.create_attribute(":person/name")
+engine.create_attribute(":person/age", "last-write-wins")
+
+adults = (Plan.match_a(0, ":person/name", 1)
+          .join(Plan.match_a(0, ":person/age", 2))
+          .filter_value(">=", 2, 18)
+          .project([1]))
+
+engine.register("adults", adults)
+subscription = engine.subscribe("adults")
+
+engine.transact([
+    (1, ":person/name", "Dipper"),
+    (1, ":person/age", 12),
+    (2, ":person/name", "Stan"),
+    (2, ":person/age", 68),
+])
+
+for change in subscription.poll():
+    print(change)
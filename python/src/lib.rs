@@ -0,0 +1,446 @@
+//! Python bindings for embedding Declarative Dataflow.
+//!
+//! An `Engine` runs a single timely worker in-process. It is stepped
+//! to completion after every change to its inputs, s.t. results are
+//! ready to be polled from subscriptions right away.
+//!
+//! ```python
+//! import declarative
+//!
+//! engine = declarative.Engine()
+//! engine.create_attribute(":person/name")
+//! engine.register_query("names", "[:find ?n :where [?e :person/name ?n]]")
+//!
+//! names = engine.subscribe("names")
+//! engine.transact([(1, ":person/name", "Dipper")])
+//! names.poll() # [{'tuple': ['Dipper'], 'time': 0, 'diff': 1}]
+//! ```
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use pyo3::create_exception;
+use pyo3::exceptions;
+use pyo3::prelude::*;
+use pyo3::types::{PyAny, PyBool, PyDict, PyList, PyTuple};
+
+use timely::communication::allocator::Thread;
+use timely::worker::Worker;
+
+use declarative_dataflow::frontends::datalog;
+use declarative_dataflow::plan::PlanBuilder;
+use declarative_dataflow::server::{Register, RegisterQuery, Server};
+use declarative_dataflow::{Aid, AttributeConfig, Datom, Error, InputSemantics, Rule, Value};
+
+create_exception!(declarative, DataflowError, exceptions::Exception);
+
+/// Raised with the category and message of the underlying error.
+fn dataflow_error(error: Error) -> PyErr {
+    DataflowError::py_err((error.category, error.message))
+}
+
+type Results = Rc<RefCell<Vec<(Vec<Value>, u64, isize)>>>;
+
+/// An in-process engine, maintaining registered relations as
+/// transactions come in.
+#[pyclass]
+struct Engine {
+    worker: Worker<Thread>,
+    server: Server<Aid, u64, ()>,
+    next_tx: u64,
+}
+
+impl Engine {
+    fn step(&mut self) {
+        let Engine {
+            ref mut worker,
+            ref server,
+            ..
+        } = *self;
+
+        worker.step_while(|| server.is_any_outdated());
+    }
+}
+
+#[pymethods]
+impl Engine {
+    #[new]
+    fn new(obj: &PyRawObject) {
+        obj.init(Engine {
+            worker: Worker::new(Thread::new()),
+            server: Server::new(Default::default()),
+            next_tx: 0,
+        });
+    }
+
+    /// Creates an attribute with the specified input semantics, one
+    /// of "raw", "last-write-wins", or "distinct".
+    #[args(semantics = "\"raw\"")]
+    fn create_attribute(&mut self, name: &str, semantics: &str) -> PyResult<()> {
+        let input_semantics = match semantics {
+            "raw" => InputSemantics::Raw,
+            "last-write-wins" => InputSemantics::LastWriteWins,
+            "distinct" => InputSemantics::Distinct,
+            other => {
+                return Err(exceptions::ValueError::py_err(format!(
+                    "Unknown input semantics {}.",
+                    other
+                )));
+            }
+        };
+
+        let Engine {
+            ref mut worker,
+            ref mut server,
+            ..
+        } = *self;
+
+        worker
+            .dataflow::<u64, _, _>(|scope| {
+                server.create_attribute(scope, name, AttributeConfig::tx_time(input_semantics))
+            })
+            .map_err(dataflow_error)
+    }
+
+    /// Transacts a list of `(e, a, v)` or `(e, a, v, diff)` tuples
+    /// and returns the transaction they were assigned to, once all
+    /// subscriptions reflect them.
+    fn transact(&mut self, tx_data: &PyList) -> PyResult<u64> {
+        let mut datoms = Vec::with_capacity(tx_data.len());
+        for datom in tx_data.iter() {
+            datoms.push(datom_from_py(datom)?);
+        }
+
+        let tx = self.next_tx;
+        self.server.transact(datoms, 0, 0).map_err(dataflow_error)?;
+
+        self.next_tx += 1;
+        self.server
+            .advance_domain(None, self.next_tx)
+            .map_err(dataflow_error)?;
+
+        self.step();
+
+        Ok(tx)
+    }
+
+    /// Registers and publishes a named relation.
+    fn register(&mut self, name: String, plan: &Plan) -> PyResult<()> {
+        let plan = plan.builder.clone().build().map_err(dataflow_error)?;
+
+        self.server
+            .register(Register {
+                rules: vec![Rule::named(name.clone(), plan)],
+                publish: vec![name],
+            })
+            .map_err(dataflow_error)
+    }
+
+    /// Registers and publishes a named relation, defined by a Datalog
+    /// query.
+    fn register_query(&mut self, name: String, query: String) -> PyResult<()> {
+        self.server
+            .register_query(RegisterQuery {
+                name,
+                query,
+                language: Default::default(),
+            })
+            .map_err(dataflow_error)
+    }
+
+    /// Subscribes to changes to a registered relation, starting with
+    /// its current contents.
+    fn subscribe(&mut self, py: Python, name: String) -> PyResult<Py<Subscription>> {
+        let results: Results = Default::default();
+
+        {
+            let Engine {
+                ref mut worker,
+                ref mut server,
+                ..
+            } = *self;
+
+            let results = results.clone();
+
+            worker
+                .dataflow::<u64, _, _>(|scope| {
+                    server.interest(name.clone(), scope).map(|relation| {
+                        relation
+                            .inspect(move |x| results.borrow_mut().push(x.clone()))
+                            .probe_with(&mut server.probe);
+                    })
+                })
+                .map_err(dataflow_error)?;
+        }
+
+        self.step();
+
+        Py::new(py, Subscription { name, results })
+    }
+}
+
+/// Changes to a single relation, buffered until they are polled.
+#[pyclass]
+struct Subscription {
+    name: String,
+    results: Results,
+}
+
+#[pymethods]
+impl Subscription {
+    /// The name of the relation this subscription is on.
+    #[getter]
+    fn name(&self) -> PyResult<String> {
+        Ok(self.name.clone())
+    }
+
+    /// Returns all changes since the last poll, as a list of dicts
+    /// holding the tuple, time, and diff of each change.
+    fn poll(&self, py: Python) -> PyResult<PyObject> {
+        let changes = PyList::empty(py);
+
+        for (tuple, time, diff) in self.results.borrow_mut().drain(..) {
+            let change = PyDict::new(py);
+            change.set_item("tuple", tuple_to_py(py, &tuple))?;
+            change.set_item("time", time)?;
+            change.set_item("diff", diff)?;
+            changes.append(change)?;
+        }
+
+        Ok(changes.to_object(py))
+    }
+
+    /// Returns all changes since the last poll, as a `pyarrow.Table`
+    /// with one column per tuple field, named by position, followed
+    /// by `time` and `diff` columns.
+    fn poll_arrow(&self, py: Python) -> PyResult<PyObject> {
+        let changes: Vec<_> = self.results.borrow_mut().drain(..).collect();
+        let arity = changes
+            .first()
+            .map(|(tuple, _, _)| tuple.len())
+            .unwrap_or(0);
+
+        let columns = PyDict::new(py);
+
+        for field in 0..arity {
+            let column: Vec<PyObject> = changes
+                .iter()
+                .map(|(tuple, _, _)| value_to_py(py, &tuple[field]))
+                .collect();
+
+            columns.set_item(field.to_string(), column)?;
+        }
+
+        let times: Vec<u64> = changes.iter().map(|(_, time, _)| *time).collect();
+        let diffs: Vec<isize> = changes.iter().map(|(_, _, diff)| *diff).collect();
+        columns.set_item("time", times)?;
+        columns.set_item("diff", diffs)?;
+
+        let table = py.import("pyarrow")?.call1("table", (columns,))?;
+
+        Ok(table.to_object(py))
+    }
+}
+
+/// Builds up a plan one operator at a time, checking that each step
+/// only refers to variables bound so far.
+#[pyclass]
+struct Plan {
+    builder: PlanBuilder<Aid>,
+}
+
+impl Plan {
+    fn wrap(py: Python, builder: PlanBuilder<Aid>) -> PyResult<Py<Plan>> {
+        Py::new(py, Plan { builder })
+    }
+}
+
+#[pymethods]
+impl Plan {
+    /// Starts from a data pattern of the form [?e a ?v].
+    #[staticmethod]
+    fn match_a(py: Python, e: u32, a: String, v: u32) -> PyResult<Py<Plan>> {
+        Plan::wrap(py, PlanBuilder::match_a(e, a, v))
+    }
+
+    /// Starts from a data pattern of the form [e a ?v].
+    #[staticmethod]
+    fn match_ea(py: Python, e: u64, a: String, v: u32) -> PyResult<Py<Plan>> {
+        Plan::wrap(py, PlanBuilder::match_ea(e, a, v))
+    }
+
+    /// Starts from a data pattern of the form [?e a v].
+    #[staticmethod]
+    fn match_av(py: Python, e: u32, a: String, v: &PyAny) -> PyResult<Py<Plan>> {
+        Plan::wrap(py, PlanBuilder::match_av(e, a, value_from_py(v)?))
+    }
+
+    /// Starts from another named relation, binding its fields to the
+    /// specified variables.
+    #[staticmethod]
+    fn name_expr(py: Python, variables: Vec<u32>, name: String) -> PyResult<Py<Plan>> {
+        Plan::wrap(py, PlanBuilder::name_expr(variables, name))
+    }
+
+    /// The variables bound so far, in the order in which they appear
+    /// in result tuples.
+    fn variables(&self) -> PyResult<Vec<u32>> {
+        self.builder
+            .variables()
+            .map(|variables| variables.to_vec())
+            .map_err(|error| dataflow_error(error.clone()))
+    }
+
+    /// Joins with another plan on all shared variables.
+    fn join(&self, py: Python, other: &Plan) -> PyResult<Py<Plan>> {
+        let builder = self.builder.clone().join(other.builder.clone());
+        Plan::wrap(py, builder)
+    }
+
+    /// Removes all tuples matching the other plan on the specified
+    /// variables.
+    fn antijoin(&self, py: Python, other: &Plan, variables: Vec<u32>) -> PyResult<Py<Plan>> {
+        let builder = self
+            .builder
+            .clone()
+            .antijoin(other.builder.clone(), variables);
+        Plan::wrap(py, builder)
+    }
+
+    /// Combines all tuples of this and another plan.
+    fn union(&self, py: Python, other: &Plan) -> PyResult<Py<Plan>> {
+        let builder = self.builder.clone().union(other.builder.clone());
+        Plan::wrap(py, builder)
+    }
+
+    /// Keeps only tuples for which the predicate, e.g. "<", holds on
+    /// the values of two variables.
+    fn filter(&self, py: Python, predicate: &str, x: u32, y: u32) -> PyResult<Py<Plan>> {
+        let predicate = datalog::predicate(predicate).ok_or_else(|| unknown(predicate))?;
+        Plan::wrap(py, self.builder.clone().filter(predicate, x, y))
+    }
+
+    /// Keeps only tuples for which the predicate, e.g. "<", holds
+    /// between the value of a variable and a constant.
+    fn filter_value(
+        &self,
+        py: Python,
+        predicate: &str,
+        x: u32,
+        value: &PyAny,
+    ) -> PyResult<Py<Plan>> {
+        let predicate = datalog::predicate(predicate).ok_or_else(|| unknown(predicate))?;
+        let value = value_from_py(value)?;
+        Plan::wrap(py, self.builder.clone().filter_value(predicate, x, value))
+    }
+
+    /// Restricts tuples to the specified variables.
+    fn project(&self, py: Python, variables: Vec<u32>) -> PyResult<Py<Plan>> {
+        Plan::wrap(py, self.builder.clone().project(variables))
+    }
+
+    /// Groups tuples by the key variables and applies each of the
+    /// `(function, variable)` aggregations, e.g. `("count", 2)`.
+    #[args(with_variables = "Vec::new()")]
+    fn aggregate(
+        &self,
+        py: Python,
+        key_variables: Vec<u32>,
+        aggregations: Vec<(String, u32)>,
+        with_variables: Vec<u32>,
+    ) -> PyResult<Py<Plan>> {
+        let mut resolved = Vec::with_capacity(aggregations.len());
+        for (symbol, variable) in aggregations.iter() {
+            let aggregation_fn = datalog::aggregation_fn(symbol).ok_or_else(|| unknown(symbol))?;
+            resolved.push((aggregation_fn, *variable));
+        }
+
+        let builder = self
+            .builder
+            .clone()
+            .aggregate(key_variables, resolved, with_variables);
+        Plan::wrap(py, builder)
+    }
+}
+
+fn unknown(symbol: &str) -> PyErr {
+    exceptions::ValueError::py_err(format!("Unknown function {}.", symbol))
+}
+
+fn datom_from_py(datom: &PyAny) -> PyResult<Datom<Aid>> {
+    let datom = datom
+        .cast_as::<PyTuple>()
+        .map_err(|_| exceptions::TypeError::py_err("Expected (e, a, v) or (e, a, v, diff)."))?;
+
+    let diff = match datom.len() {
+        3 => 1,
+        4 => datom.get_item(3).extract::<isize>()?,
+        _ => {
+            return Err(exceptions::TypeError::py_err(
+                "Expected (e, a, v) or (e, a, v, diff).",
+            ));
+        }
+    };
+
+    // Integers in entity position refer to entities, not numbers.
+    let e = match datom.get_item(0).extract::<u64>() {
+        Ok(eid) => Value::Eid(eid),
+        Err(_) => value_from_py(datom.get_item(0))?,
+    };
+
+    Ok(Datom(
+        e,
+        datom.get_item(1).extract::<String>()?,
+        value_from_py(datom.get_item(2))?,
+        None,
+        diff,
+    ))
+}
+
+fn value_from_py(value: &PyAny) -> PyResult<Value> {
+    // Python booleans are integers as well.
+    if let Ok(boolean) = value.cast_as::<PyBool>() {
+        Ok(Value::Bool(boolean.is_true()))
+    } else if let Ok(number) = value.extract::<i64>() {
+        Ok(Value::Number(number))
+    } else if let Ok(string) = value.extract::<String>() {
+        Ok(Value::String(string))
+    } else {
+        Err(exceptions::TypeError::py_err(format!(
+            "Values of type {} are not supported.",
+            value.get_type().name()
+        )))
+    }
+}
+
+fn value_to_py(py: Python, value: &Value) -> PyObject {
+    match *value {
+        Value::Aid(ref aid) => aid.to_object(py),
+        Value::String(ref string) => string.to_object(py),
+        Value::Bool(boolean) => boolean.to_object(py),
+        Value::Number(number) => number.to_object(py),
+        Value::Rational32(ref rational) => {
+            (f64::from(*rational.numer()) / f64::from(*rational.denom())).to_object(py)
+        }
+        Value::Eid(eid) => eid.to_object(py),
+        Value::Instant(instant) => instant.to_object(py),
+        Value::Uuid(ref uuid) => uuid.to_string().to_object(py),
+        #[allow(unreachable_patterns)]
+        _ => format!("{:?}", value).to_object(py),
+    }
+}
+
+fn tuple_to_py(py: Python, tuple: &[Value]) -> PyObject {
+    let values: Vec<PyObject> = tuple.iter().map(|value| value_to_py(py, value)).collect();
+    values.to_object(py)
+}
+
+#[pymodule]
+fn declarative(py: Python, m: &PyModule) -> PyResult<()> {
+    m.add_class::<Engine>()?;
+    m.add_class::<Subscription>()?;
+    m.add_class::<Plan>()?;
+    m.add("DataflowError", py.get_type::<DataflowError>())?;
+
+    Ok(())
+}
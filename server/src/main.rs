@@ -6,29 +6,36 @@ extern crate serde_derive;
 #[macro_use]
 extern crate log;
 
+use std::cell::RefCell;
+#[cfg(feature = "store")]
+use std::collections::HashMap;
 use std::collections::{HashSet, VecDeque};
-use std::fs::File;
-use std::io::{BufRead, BufReader, Read};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
-use timely::dataflow::channels::pact::{Exchange, Pipeline};
+use timely::dataflow::channels::pact::Exchange;
 use timely::dataflow::operators::generic::OutputHandle;
 use timely::dataflow::operators::{Operator, Probe};
-use timely::logging::{Logger, TimelyEvent};
 use timely::synchronization::Sequencer;
 
-use differential_dataflow::logging::DifferentialEvent;
-use differential_dataflow::operators::Consolidate;
-
+use declarative_dataflow::ids::IdAllocator;
 use declarative_dataflow::scheduling::{AsScheduler, SchedulingEvent};
 use declarative_dataflow::server;
-use declarative_dataflow::server::{CreateAttribute, Request, Server, TxId};
-use declarative_dataflow::sinks::{Sinkable, SinkingContext};
+use declarative_dataflow::server::backpressure::{Backpressure, OverflowPolicy};
+use declarative_dataflow::server::handlers::Handling;
+use declarative_dataflow::server::{CreateAttribute, Excise, Request, RequestId, Server, TxId, Visibility};
+use declarative_dataflow::sources::Sourceable;
+#[cfg(feature = "store")]
+use declarative_dataflow::store::Store;
 use declarative_dataflow::timestamp::{Coarsen, Time};
-use declarative_dataflow::{Output, ResultDiff};
+use declarative_dataflow::{Error, Output, ResultDiff};
 
 mod networking;
-use crate::networking::{DomainEvent, Token, IO, SYSTEM};
+mod serializer;
+use crate::networking::{DomainEvent, Token, IO, LIBRARY, SYSTEM};
 
 /// Server attribute identifier type.
 type Aid = String;
@@ -63,6 +70,34 @@ struct Configuration {
     pub timely_pid: usize,
     /// Whether to report connection progress.
     pub report: bool,
+    /// Bounds on the outputs buffered for each client, if any.
+    pub backpressure: Option<Backpressure>,
+    /// Maximum time to spend on delivering outstanding results
+    /// during shutdown.
+    pub shutdown_timeout: Duration,
+    /// Number of threads per worker serializing its outputs. None at
+    /// all leaves serialization to the worker itself.
+    pub serializer_threads: usize,
+    /// Path of state exported by another server, to start from.
+    pub restore: Option<String>,
+    /// Files of requests to handle at startup, e.g. to set up
+    /// attributes, sources, and rules.
+    pub libraries: Vec<String>,
+    /// File to append replicated requests to, for standbys to
+    /// consume.
+    pub journal: Option<String>,
+    /// Journal of the primary to replicate, if running as a standby.
+    pub standby: Option<String>,
+    /// Address of the primary, to redirect writing clients to while
+    /// running as a standby.
+    pub primary: Option<String>,
+    /// Whether to serve reads off the standby journal for good,
+    /// rather than waiting to be promoted.
+    pub read_replica: bool,
+    /// Cores to pin this process' workers to, in order of their
+    /// local index. Listing the cores of one socket before those of
+    /// the next keeps neighbouring workers on the same NUMA node.
+    pub pin_cores: Option<Vec<usize>>,
 }
 
 impl Default for Configuration {
@@ -75,6 +110,16 @@ impl Default for Configuration {
             addresses: vec!["localhost:2101".to_string()],
             timely_pid: 0,
             report: false,
+            backpressure: None,
+            shutdown_timeout: Duration::from_secs(10),
+            serializer_threads: 0,
+            restore: None,
+            libraries: Vec::new(),
+            journal: None,
+            standby: None,
+            primary: None,
+            read_replica: false,
+            pin_cores: None,
         }
     }
 }
@@ -105,6 +150,77 @@ impl Configuration {
         );
         opts.optflag("r", "report", "reports connection progress");
 
+        // Networking arguments.
+        opts.optopt(
+            "",
+            "max-pending",
+            "maximum number of outputs buffered per client",
+            "NUM",
+        );
+        opts.optopt(
+            "",
+            "overflow",
+            "what to do about clients exceeding max-pending (disconnect, coalesce, pause)",
+            "POLICY",
+        );
+        opts.optopt(
+            "",
+            "shutdown-timeout",
+            "seconds to spend on delivering outstanding results during shutdown",
+            "SECS",
+        );
+        opts.optopt(
+            "",
+            "serializer-threads",
+            "number of threads per worker serializing outputs",
+            "NUM",
+        );
+        opts.optopt(
+            "",
+            "restore",
+            "start from the state exported to this path via ExportState",
+            "PATH",
+        );
+        opts.optmulti(
+            "",
+            "library",
+            "handle the requests listed in this JSON file at startup",
+            "FILE",
+        );
+
+        // Replication arguments.
+        opts.optopt(
+            "",
+            "journal",
+            "append replicated requests to this file",
+            "FILE",
+        );
+        opts.optopt(
+            "",
+            "standby",
+            "replicate the primary writing this journal until promoted",
+            "FILE",
+        );
+        opts.optopt(
+            "",
+            "primary",
+            "address to redirect writes to while on standby",
+            "HOST:PORT",
+        );
+        opts.optflag(
+            "",
+            "read-replica",
+            "serve queries off the standby journal, without ever being promoted",
+        );
+
+        // Deployment arguments.
+        opts.optopt(
+            "",
+            "pin-cores",
+            "comma-separated cores to pin workers to, in order of their local index",
+            "LIST",
+        );
+
         opts
     }
 
@@ -160,6 +276,36 @@ impl Configuration {
 
         let report = matches.opt_present("report");
 
+        let backpressure = matches
+            .opt_str("max-pending")
+            .map(|x| Backpressure {
+                max_pending: x.parse().expect("failed to parse max-pending"),
+                policy: matches
+                    .opt_str("overflow")
+                    .map(|x| x.parse().expect("failed to parse overflow policy"))
+                    .unwrap_or(OverflowPolicy::Disconnect),
+            });
+
+        let serializer_threads = matches
+            .opt_str("serializer-threads")
+            .map(|x| x.parse().expect("failed to parse serializer-threads"))
+            .unwrap_or(default.serializer_threads);
+
+        let pin_cores = matches.opt_str("pin-cores").map(|x| {
+            x.split(',')
+                .map(|core| core.trim().parse().expect("failed to parse pin-cores"))
+                .collect::<Vec<usize>>()
+        });
+
+        if let Some(ref cores) = pin_cores {
+            assert!(!cores.is_empty(), "pin-cores lists no cores");
+        }
+
+        let shutdown_timeout = matches
+            .opt_str("shutdown-timeout")
+            .map(|x| Duration::from_secs(x.parse().expect("failed to parse shutdown-timeout")))
+            .unwrap_or(default.shutdown_timeout);
+
         Self {
             port,
             config: matches.opt_str("config"),
@@ -168,6 +314,16 @@ impl Configuration {
             addresses,
             timely_pid,
             report,
+            backpressure,
+            shutdown_timeout,
+            serializer_threads,
+            restore: matches.opt_str("restore"),
+            libraries: matches.opt_strs("library"),
+            journal: matches.opt_str("journal"),
+            standby: matches.opt_str("standby"),
+            primary: matches.opt_str("primary"),
+            read_replica: matches.opt_present("read-replica"),
+            pin_cores,
         }
     }
 }
@@ -209,6 +365,24 @@ impl Into<timely::Configuration> for Configuration {
     }
 }
 
+/// Transactions held back, to be committed together at a single
+/// timestamp.
+struct Group {
+    /// When the first transaction was added.
+    pub started: Instant,
+    /// Sequence number all transactions are acknowledged at.
+    pub tx: TxId,
+    /// Number of datoms transacted so far.
+    pub datoms: usize,
+    /// Number of transactions held back so far.
+    pub transactions: usize,
+    /// Acknowledgements to send once the group is committed.
+    pub acks: Vec<(usize, RequestId)>,
+    /// Whether the first worker already asked for the group to be
+    /// closed.
+    pub closing: bool,
+}
+
 /// A mutation of server state.
 #[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Serialize, Deserialize, Debug)]
 struct Command {
@@ -218,6 +392,8 @@ struct Command {
     /// The client token that issued the command. Only relevant to the
     /// owning worker, as no one else has the connection.
     pub client: usize,
+    /// An optional, client-chosen id referenced by all replies.
+    pub id: Option<RequestId>,
     /// Requests issued by the client.
     pub requests: Vec<Request<Aid>>,
 }
@@ -225,14 +401,106 @@ struct Command {
 fn main() {
     env_logger::init();
 
-    let config = Configuration::from_args(std::env::args());
+    let mut config = Configuration::from_args(std::env::args());
+    assert!(
+        config.standby.is_some() || !config.read_replica,
+        "--read-replica requires --standby"
+    );
+
+    // Rescaling starts a new computation within this process, on a
+    // different number of workers, from the state of the previous
+    // one.
+    let mut restored = Vec::new();
+    loop {
+        let handoff = match run(config.clone(), restored) {
+            Err(error) => {
+                error!("failed to start: {}", error.message);
+                std::process::exit(1);
+            }
+            Ok(None) => break,
+            Ok(Some(handoff)) => handoff,
+        };
+
+        info!("rescaling onto {} workers", handoff.threads);
+
+        // Whatever was restored or loaded at startup is part of the
+        // handed off state by now.
+        config.threads = handoff.threads;
+        config.restore = None;
+        config.libraries.clear();
+        restored = handoff.shards;
+    }
+}
+
+/// State handed from one computation to the next, when rescaling.
+struct Handoff {
+    /// Number of worker threads to run on next.
+    pub threads: usize,
+    /// The state exported by each worker, by worker index.
+    pub shards: Vec<Vec<Request<Aid>>>,
+}
+
+/// Opens the stores of all workers in this process, by worker index.
+#[cfg(feature = "store")]
+fn open_stores(config: &Configuration, server_config: &server::Configuration) -> Result<HashMap<usize, Store>, Error> {
+    let mut stores = HashMap::new();
+
+    if let Some(ref path) = server_config.store {
+        for local_index in 0..config.threads {
+            let worker_index = config.timely_pid * config.threads + local_index;
+            stores.insert(worker_index, Store::open_worker(path, worker_index)?);
+        }
+    }
+
+    Ok(stores)
+}
+
+/// Runs a computation until it shuts down, returning the state to
+/// start the next one from, if it was asked to rescale. Fails if
+/// the computation can't be started.
+fn run(config: Configuration, restored: Vec<Vec<Request<Aid>>>) -> Result<Option<Handoff>, Error> {
     let timely_config: timely::Configuration = config.clone().into();
     let server_config: server::Configuration = config.clone().into();
 
+    // Stores are opened before any worker starts, s.t. failing to
+    // open one stops the server, rather than a single worker.
+    #[cfg(feature = "store")]
+    let stores = Arc::new(Mutex::new(open_stores(&config, &server_config)?));
+
+    // Libraries are read up-front as well, s.t. a missing or malformed
+    // one stops the server before it starts accepting clients.
+    let libraries = load_libraries(&config.libraries)?;
+
+    let handoff: Arc<Mutex<Option<Handoff>>> = Arc::new(Mutex::new(None));
+    let handed_off = handoff.clone();
+
+    // The first request of a library that failed, if any.
+    let failure: Arc<Mutex<Option<Error>>> = Arc::new(Mutex::new(None));
+    let failed_library = failure.clone();
+
     timely::execute(timely_config, move |worker| {
+        // Workers are numbered process by process.
+        if let Some(ref cores) = config.pin_cores {
+            let local_index = worker.index() % config.threads;
+            let core = cores[local_index % cores.len()];
+
+            core_affinity::set_for_current(core_affinity::CoreId { id: core });
+            info!("[W{}] pinned to core {}", worker.index(), core);
+        }
+
         // Initialize server state (no networking).
         let mut server = Server::<Aid, T, Token>::new_at(server_config.clone(), worker.timer());
 
+        #[cfg(feature = "store")]
+        {
+            if let Some(store) = stores.lock().unwrap().remove(&worker.index()) {
+                server.use_store(store);
+            }
+        }
+        server.ids = IdAllocator::new(server_config.id_strategy, worker.index(), worker.peers());
+        server.peers = worker.peers();
+        server.index = worker.index();
+
         if server_config.enable_logging {
             #[cfg(feature = "real-time")]
             server.enable_logging(worker).unwrap();
@@ -242,23 +510,66 @@ fn main() {
         // setting-up built-in arrangements. We serialize those here
         // and pre-load the sequencer with them, such that they will
         // flow through the regular request handling.
-        let builtins = Server::<Aid, T, Token>::builtins();
-        let preload_command = Command {
+        let mut builtins = Server::<Aid, T, Token>::builtins();
+
+        // State exported by a server with any number of workers is
+        // restored by the first one, in the order it was exported
+        // in. Transacted state is then spread out across all workers
+        // as usual.
+        if worker.index() == 0 {
+            for shard in restored.iter() {
+                builtins.extend(shard.iter().cloned());
+            }
+
+            if let Some(ref path) = config.restore {
+                let mut shard = 0;
+                while let Ok(file) = File::open(format!("{}.{}", path, shard)) {
+                    let requests: Vec<Request<Aid>> = serde_json::from_reader(BufReader::new(file))
+                        .expect("failed to parse exported state");
+
+                    info!("[W{}] restoring {} requests from shard {}", worker.index(), requests.len(), shard);
+
+                    builtins.extend(requests);
+                    shard += 1;
+                }
+            }
+        }
+
+        let mut preloaded = VecDeque::new();
+        preloaded.push_back(Command {
             owner: worker.index(),
             client: SYSTEM.0,
+            id: None,
             requests: builtins,
-        };
+        });
+
+        // Libraries describe a deployment in the same requests
+        // clients would send, handled in the order given, after any
+        // restored state. They are issued by a client of their own,
+        // s.t. a failing request stops the server.
+        if worker.index() == 0 && !libraries.is_empty() {
+            info!("[W{}] loading {} requests from libraries", worker.index(), libraries.len());
+
+            preloaded.push_back(Command {
+                owner: 0,
+                client: LIBRARY.0,
+                id: None,
+                requests: libraries.clone(),
+            });
+        }
 
         // Setup serializing command stream between all workers.
         let mut sequencer: Sequencer<Command> =
-            Sequencer::preloaded(worker, Instant::now(), VecDeque::from(vec![preload_command]));
+            Sequencer::preloaded(worker, Instant::now(), preloaded);
 
         // Kickoff ticking, if configured. We only want to issue ticks
-        // from a single worker, to avoid redundant ticking.
-        if worker.index() == 0 && server_config.tick.is_some() {
+        // from a single worker, to avoid redundant ticking. Standbys
+        // follow their primary's epochs until promoted.
+        if worker.index() == 0 && server_config.tick.is_some() && config.standby.is_none() {
             sequencer.push(Command {
                 owner: 0,
                 client: SYSTEM.0,
+                id: None,
                 requests: vec![Request::Tick],
             });
         }
@@ -270,7 +581,7 @@ fn main() {
             // let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), config.port);
             let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(0,0,0,0)), config.port);
 
-            IO::new(addr)
+            IO::new(addr, config.backpressure, config.serializer_threads)
         };
 
         info!(
@@ -291,6 +602,54 @@ fn main() {
 
         let mut shutdown = false;
 
+        // Once a shutdown was requested, the frontier up to which
+        // results must still be delivered, and the time by which to
+        // give up on that.
+        let mut draining: Option<(Vec<T>, Instant)> = None;
+
+        // Number of workers to move onto, once drained.
+        let mut rescaling: Option<usize> = None;
+
+        // Names of dataflows to cancel, as reported by their sinks.
+        let cancellations: Rc<RefCell<Vec<String>>> = Rc::new(RefCell::new(Vec::new()));
+
+        // Transactions whose acknowledgement waits on constraint
+        // checks, by the time they will have been checked at, and
+        // whether they have to become visible afterwards.
+        let mut awaiting: Vec<(T, Option<Visibility>, usize, Option<RequestId>, TxId)> = Vec::new();
+
+        // Transactions whose acknowledgement waits on them becoming
+        // visible, by the time they were introduced at.
+        let mut syncing: Vec<(T, Visibility, usize, RequestId, TxId)> = Vec::new();
+
+        // Transactions held back to be committed together, if group
+        // commit is configured.
+        let mut group: Option<Group> = None;
+        // How long the next group is held back.
+        let mut group_delay = server.config.group_commit
+            .map(|limits| limits.initial_delay())
+            .unwrap_or_default();
+
+        // Replicated requests are journaled by the first worker, as
+        // all workers see all commands.
+        let mut journal = match config.journal {
+            Some(ref path) if worker.index() == 0 => {
+                let file = OpenOptions::new().create(true).append(true).open(path).expect("failed to open journal");
+                Some(BufWriter::new(file))
+            }
+            _ => None,
+        };
+
+        // Standbys reject writes by clients, the first worker tails
+        // the primary's journal instead.
+        let mut standby = config.standby.is_some();
+        let mut replica = match config.standby {
+            Some(ref path) if worker.index() == 0 => {
+                Some(JournalTail::open(path).expect("failed to open primary journal"))
+            }
+            _ => None,
+        };
+
         while !shutdown {
             // each worker has to...
             //
@@ -317,6 +676,7 @@ fn main() {
                                 sequencer.push(Command {
                                     owner: worker.index(),
                                     client: SYSTEM.0,
+                                    id: None,
                                     requests: vec![Request::Tick],
                                 });
                             }
@@ -330,29 +690,188 @@ fn main() {
             }
 
             // Transform low-level I/O events into domain events.
-            io.step(next_tx, &server.interests);
+            io.step(next_tx, &server.interests, &server.output_names);
 
             while let Some(event) = io.next() {
                 match event {
-                    DomainEvent::Requests(token, requests) => {
+                    DomainEvent::Requests(token, id, _) if draining.is_some() => {
+                        let error = Error::interrupted("Server is shutting down.");
+                        io.send.send(Output::Error(token.into(), error, next_tx, id)).unwrap();
+                    }
+                    DomainEvent::Requests(token, id, requests) => {
                         trace!("[IO] command");
                         sequencer.push(Command {
                             owner: worker.index(),
                             client: token.into(),
+                            id,
                             requests,
                         });
                     }
+                    DomainEvent::Connect(token) => {
+                        // Sessions outlive their connections, so
+                        // whatever the token's previous holder left
+                        // behind must not be inherited.
+                        if server.config.session_timeout.is_some() {
+                            sequencer.push(Command {
+                                owner: worker.index(),
+                                client: token.into(),
+                                id: None,
+                                requests: vec![Request::EndSession],
+                            });
+                        }
+                    }
                     DomainEvent::Disconnect(token) => {
                         info!("[IO] token={:?} disconnected", token);
                         sequencer.push(Command {
                             owner: worker.index(),
                             client: token.into(),
+                            id: None,
                             requests: vec![Request::Disconnect],
                         });
                     }
                 }
             }
 
+            // Dataflows that exceeded their limits must be torn down
+            // on all workers.
+            for name in cancellations.borrow_mut().drain(..) {
+                sequencer.push(Command {
+                    owner: worker.index(),
+                    client: SYSTEM.0,
+                    id: None,
+                    requests: vec![Request::Cancel(name)],
+                });
+            }
+
+            // Clients that went silent are forgotten on all workers.
+            for token in server.expired_sessions(worker.index()) {
+                info!("[W{}] session of {:?} expired", worker.index(), token);
+                sequencer.push(Command {
+                    owner: worker.index(),
+                    client: token.into(),
+                    id: None,
+                    requests: vec![Request::EndSession],
+                });
+            }
+
+            // Dataflows without connected subscribers are torn down on
+            // all workers.
+            for name in server.idle_dataflows(worker.index()) {
+                info!("[W{}] {} is idle", worker.index(), name);
+                sequencer.push(Command {
+                    owner: worker.index(),
+                    client: SYSTEM.0,
+                    id: None,
+                    requests: vec![Request::Hibernate(name)],
+                });
+            }
+
+            // Budgets apply to the memory held across all workers.
+            if let Some(usage) = server.memory_usage(worker.index()) {
+                sequencer.push(Command {
+                    owner: worker.index(),
+                    client: SYSTEM.0,
+                    id: None,
+                    requests: vec![Request::MemoryUsage(usage)],
+                });
+            }
+
+            // Dataflows exceeding their memory budget are torn down on
+            // all workers.
+            for name in server.exceeded_budgets(worker.index()) {
+                warn!("[W{}] {} exceeded its memory budget", worker.index(), name);
+                sequencer.push(Command {
+                    owner: worker.index(),
+                    client: SYSTEM.0,
+                    id: None,
+                    requests: vec![Request::Evict(name)],
+                });
+            }
+
+            // Replicated commands are sequenced like any other, on
+            // behalf of the system.
+            let tailed = replica.as_mut().map(|replica| replica.read());
+            match tailed {
+                None => {}
+                Some(Err(error)) => {
+                    // Applying anything past what failed would
+                    // diverge from the primary.
+                    error!("[W{}] stopped following the journal: {:?}", worker.index(), error);
+                    replica = None;
+                }
+                Some(Ok(commands)) => {
+                    for requests in commands {
+                        sequencer.push(Command {
+                            owner: worker.index(),
+                            client: SYSTEM.0,
+                            id: None,
+                            requests,
+                        });
+                    }
+                }
+            }
+
+            // New versions of rules that have caught up are swapped in
+            // on all workers.
+            for name in server.caught_up_swaps(worker.index()) {
+                if standby {
+                    continue;
+                }
+
+                sequencer.push(Command {
+                    owner: worker.index(),
+                    client: SYSTEM.0,
+                    id: None,
+                    requests: vec![Request::CompleteSwap(name)],
+                });
+            }
+
+            // Interests held back until their causality tokens have
+            // passed are issued again by their owners.
+            for (owner, client, id, interest) in server.caught_up_interests() {
+                if owner == worker.index() {
+                    sequencer.push(Command {
+                        owner,
+                        client: client.0,
+                        id,
+                        requests: vec![Request::Interest(interest)],
+                    });
+                }
+            }
+
+            // Transactions fired by triggers are sequenced by the
+            // worker holding the triggering results. Standbys receive
+            // them from their primary instead.
+            for tx_data in server.fired_transactions() {
+                if standby {
+                    continue;
+                }
+
+                sequencer.push(Command {
+                    owner: worker.index(),
+                    client: SYSTEM.0,
+                    id: None,
+                    requests: vec![Request::Transact(tx_data)],
+                });
+            }
+
+            // Groups are closed by a command like any other, s.t. all
+            // workers commit them at the same point in the sequence.
+            if worker.index() == 0 {
+                if let (Some(open), Some(limits)) = (group.as_mut(), server.config.group_commit) {
+                    if !open.closing && limits.is_due_after(group_delay, open.started.elapsed(), open.datoms) {
+                        open.closing = true;
+
+                        sequencer.push(Command {
+                            owner: 0,
+                            client: SYSTEM.0,
+                            id: None,
+                            requests: vec![Request::Heartbeat],
+                        });
+                    }
+                }
+            }
+
             // handle commands
 
             while let Some(mut command) = sequencer.next() {
@@ -364,17 +883,88 @@ fn main() {
 
                 let owner = command.owner;
                 let client = command.client;
+                let id = command.id;
                 let last_tx = next_tx - 1;
 
-                for req in command.requests.drain(..) {
+                server.note_tx(last_tx);
+
+                // Only the owning worker has the client's connection.
+                let is_owner = owner == worker.index();
+                if client != SYSTEM.0 && client != LIBRARY.0 {
+                    server.touch_session(Token(client), owner);
+                }
+
+                let mut failed = false;
+
+                // Requests concerning the server's own state are
+                // handled just like in simulations.
+                let mut handling = Handling::new(
+                    owner,
+                    Token(client),
+                    last_tx,
+                    id,
+                    {
+                        let send = io.send.clone();
+                        move |out: Output| send.send(out).expect("internal channel send failed")
+                    },
+                    cancellations.clone(),
+                );
+
+                // Replicated requests that succeeded, to journal.
+                let mut journaled: Vec<Request<Aid>> = Vec::new();
+
+                // Commands consisting of nothing but transactions may
+                // join a group.
+                let tx_datoms = if server.config.group_commit.is_some() && !server.config.manual_advance {
+                    command.requests.iter().try_fold(0, |datoms, req| match req {
+                        Request::Transact(tx_data) => Some(datoms + tx_data.len()),
+                        _ => None,
+                    })
+                } else {
+                    None
+                };
+
+                let mut requests: VecDeque<Request<Aid>> = command.requests.drain(..).collect();
+
+                while let Some(req) = requests.pop_front() {
 
                     // @TODO only create a single dataflow, but only if req != Transact
 
                     trace!("[W{}] {:?}", worker.index(), req);
 
+                    // Read replicas still let clients register queries
+                    // of their own.
+                    let is_rejected = if config.read_replica {
+                        req.is_write()
+                    } else {
+                        req.is_replicated()
+                    };
+
+                    if standby && client != SYSTEM.0 && is_rejected {
+                        failed = true;
+
+                        if is_owner {
+                            let error = match config.primary {
+                                None => Error::unsupported("This server is a standby and doesn't accept writes."),
+                                Some(ref primary) => Error::unsupported(format!("This server is a standby, send writes to the primary at {}.", primary)),
+                            };
+
+                            io.send.send(Output::Error(client, error, last_tx, id)).unwrap();
+                        }
+
+                        continue;
+                    }
+
+                    let replicated = if journal.is_some() && req.is_replicated() {
+                        Some(req.clone())
+                    } else {
+                        None
+                    };
+
                     let result = match req {
-                        Request::Transact(req) => server.transact(req, owner, worker.index()),
-                        Request::Subscribe(aid) => {
+                        Request::Subscribe(aid) => if server.internal.forward_propose(&aid).is_none() {
+                            Err(Error::not_found(format!("Unknown attribute {}.", aid)))
+                        } else {
                             let interests = server.interests
                                 .entry(aid.clone())
                                 .or_insert_with(HashSet::new);
@@ -445,146 +1035,53 @@ fn main() {
 
                             Ok(())
                         }
-                        Request::Interest(req) => {
-                            let interests = server.interests
-                                .entry(req.name.clone())
-                                .or_insert_with(HashSet::new);
-
-                            // We need to check this, because we only want to setup
-                            // the dataflow on the first interest.
-                            let was_first = interests.is_empty();
-
-                            // All workers keep track of every client's interests, s.t. they
-                            // know when to clean up unused dataflows.
-                            interests.insert(Token(client));
-
-                            if was_first {
-                                let send_results = io.send.clone();
-
-                                let disable_logging = req.disable_logging.unwrap_or(false);
-                                let mut timely_logger = None;
-                                let mut differential_logger = None;
-
-                                if disable_logging {
-                                    info!("Disabling logging");
-                                    timely_logger = worker.log_register().remove("timely");
-                                    differential_logger = worker.log_register().remove("differential/arrange");
-                                }
-
-                                let result = worker.dataflow::<T, _, _>(|scope| {
-                                    let sink_context: SinkingContext = (&req).into();
-
-                                    let relation = match server.interest(req.name, scope) {
-                                        Err(error) => { return Err(error); }
-                                        Ok(relation) => relation,
-                                    };
-
-                                    let delayed = match req.granularity {
-                                        None => relation.consolidate(),
-                                        Some(granularity) => {
-                                            let granularity: T = granularity.into();
-                                            relation
-                                                .delay(move |t| t.coarsen(&granularity))
-                                                .consolidate()
-                                        }
-                                    };
-
-                                    let pact = Exchange::new(move |_| owner as u64);
-
-                                    match req.sink {
-                                        Some(sink) => {
-                                            let sunk = match sink.sink(&delayed.inner, pact, &mut server.probe, sink_context) {
-                                                Err(error) => { return Err(error); }
-                                                Ok(sunk) => sunk,
-                                            };
-
-                                            if let Some(sunk) = sunk {
-                                                let mut vector = Vec::new();
-                                                sunk
-                                                    .unary(Pipeline, "SinkResults", move |_cap, _info| {
-                                                        move |input, _output: &mut OutputHandle<_, ResultDiff<T>, _>| {
-                                                            input.for_each(|_time, data| {
-                                                                data.swap(&mut vector);
-
-                                                                for out in vector.drain(..) {
-                                                                    send_results.send(out)
-                                                                        .expect("internal channel send failed");
-                                                                }
-                                                            });
-                                                        }
-                                                    })
-                                                    .probe_with(&mut server.probe);
-                                            }
-
-                                            Ok(())
-                                        }
-                                        None => {
-                                            delayed
-                                                .inner
-                                                .unary(pact, "ResultsRecv", move |_cap, _info| {
-                                                    move |input, _output: &mut OutputHandle<_, ResultDiff<T>, _>| {
-                                                        // due to the exchange pact, this closure is only
-                                                        // executed by the owning worker
-
-                                                        // @TODO only forward inputs up to the frontier!
-
-                                                        input.for_each(|_time, data| {
-                                                            let data = data.iter()
-                                                                .map(|(tuple, t, diff)| (tuple.clone(), t.clone().into(), *diff))
-                                                                .collect::<Vec<ResultDiff<Time>>>();
-
-                                                            send_results
-                                                                .send(Output::QueryDiff(sink_context.name.clone(), data))
-                                                                .expect("internal channel send failed");
-                                                        });
-                                                    }
-                                                })
-                                                .probe_with(&mut server.probe);
-
-                                            Ok(())
-                                        }
-                                    }
-                                });
-
-                                if disable_logging {
-                                    if let Some(logger) = timely_logger {
-                                        if let Ok(logger) = logger.downcast::<Logger<TimelyEvent>>() {
-                                            worker
-                                                .log_register()
-                                                .insert_logger::<TimelyEvent>("timely", *logger);
-                                        }
-                                    }
-
-                                    if let Some(logger) = differential_logger {
-                                        if let Ok(logger) = logger.downcast::<Logger<DifferentialEvent>>() {
-                                            worker
-                                                .log_register()
-                                                .insert_logger::<DifferentialEvent>("differential/arrange", *logger);
-                                        }
-                                    }
-                                }
-
-                                result
-                            } else {
-                                Ok(())
-                            }
+                        #[cfg(feature = "graphql")]
+                        Request::SubscribeGraphQl(req) => {
+                            // Results are delivered just like for any
+                            // other interest, via an AssocIn sink.
+                            server.subscribe_graphql(req).map(|interest| {
+                                requests.push_front(Request::Interest(interest));
+                            })
                         }
-                        Request::Uninterest(name) => server.uninterest(Token(command.client), &name),
-                        Request::Register(req) => server.register(req),
                         Request::RegisterSource(source) => {
                             worker.dataflow::<T, _, _>(|scope| {
                                 server.register_source(Box::new(source), scope)
                             })
                         }
-                        Request::CreateAttribute(CreateAttribute { name, config }) => {
+                        Request::CreateAttribute(CreateAttribute { name, config, backfill }) => {
                             worker.dataflow::<T, _, _>(|scope| {
-                                server.create_attribute(scope, name, config)
+                                let backfill = backfill.map(|source| Box::new(source) as Box<dyn Sourceable<Aid, _>>);
+                                server.create_attribute_from(scope, name, config, backfill)
                             })
                         }
-                        Request::AdvanceDomain(name, next) => server.advance_domain(name, next.into()),
-                        Request::CloseInput(name) => server.internal.close_input(name),
-                        Request::Disconnect => server.disconnect_client(Token(command.client)),
-                        Request::Setup => unimplemented!(),
+                        Request::ExportState(path) => server.export_path(&path).and_then(|path| {
+                            let requests = server.export_state(worker.index());
+                            let path = format!("{}.{}", path.display(), worker.index());
+
+                            File::create(&path)
+                                .map_err(|e| Error::fault(format!("Failed to create {}: {}", path, e)))
+                                .and_then(|file| {
+                                    serde_json::to_writer(file, &requests)
+                                        .map_err(|e| Error::fault(format!("Failed to export state: {}", e)))
+                                })
+                        }),
+                        Request::Rescale(threads) => {
+                            if config.processes > 1 {
+                                Err(Error::unsupported("Rescaling servers spanning multiple processes."))
+                            } else if threads == 0 {
+                                Err(Error::incorrect("Servers need at least one worker."))
+                            } else {
+                                if draining.is_none() {
+                                    info!("[W{}] draining before rescaling onto {} workers", worker.index(), threads);
+
+                                    let deadline = Instant::now() + config.shutdown_timeout;
+                                    draining = Some((server.drain(), deadline));
+                                    rescaling = Some(threads);
+                                }
+
+                                Ok(())
+                            }
+                        }
                         Request::Tick => {
                             // We don't actually have to do any actual worker here, because we are
                             // ticking the domain on each command anyways. We do have to schedule
@@ -593,7 +1090,7 @@ fn main() {
                             // We only want to issue ticks from a single worker, to avoid
                             // redundant ticking.
                             if worker.index() == 0 {
-                                if let Some(tick) = server_config.tick {
+                                if let Some(tick) = server.config.tick {
                                     let interval_end = Instant::now().duration_since(worker.timer()).coarsen(&tick);
                                     let at = worker.timer() + interval_end;
                                     server.scheduler.borrow_mut().realtime.event_at(at, SchedulingEvent::Tick);
@@ -602,36 +1099,227 @@ fn main() {
 
                             Ok(())
                         }
-                        Request::Status => {
-                            let status = serde_json::json!({
-                                "category": "df/status",
-                                "message": "running",
-                            });
+                        Request::Inspect(server::Inspect::Clients) => {
+                            if is_owner {
+                                let message = serde_json::json!({
+                                    "category": "df/inspect",
+                                    "worker": worker.index(),
+                                    "report": {"Clients": io.throughput()},
+                                });
+
+                                io.send.send(Output::Message(client, message)).unwrap();
+                            }
+
+                            Ok(())
+                        }
+                        Request::Configure(req) => {
+                            let was_ticking = server.config.tick.is_some();
+
+                            server.configure(req).map(|()| {
+                                // Ticking has to be kicked off again, if it was disabled.
+                                if !was_ticking && server.config.tick.is_some() && worker.index() == 0 {
+                                    sequencer.push(Command {
+                                        owner: 0,
+                                        client: SYSTEM.0,
+                                        id: None,
+                                        requests: vec![Request::Tick],
+                                    });
+                                }
+                            })
+                        }
+                        Request::Promote if config.read_replica => {
+                            Err(Error::unsupported("Read replicas can't be promoted."))
+                        }
+                        Request::Promote => {
+                            if standby {
+                                info!("[W{}] promoted to primary", worker.index());
+                                standby = false;
+
+                                // Whatever the primary managed to
+                                // journal is still applied.
+                                if let Some(mut replica) = replica.take() {
+                                    for requests in replica.read().unwrap_or_else(|error| {
+                                        error!("[W{}] failed to apply the rest of the journal: {:?}", worker.index(), error);
+                                        Vec::new()
+                                    }) {
+                                        sequencer.push(Command {
+                                            owner: worker.index(),
+                                            client: SYSTEM.0,
+                                            id: None,
+                                            requests,
+                                        });
+                                    }
+                                }
 
-                            io.send.send(Output::Message(client, status)).unwrap();
+                                if worker.index() == 0 && server.config.tick.is_some() {
+                                    sequencer.push(Command {
+                                        owner: 0,
+                                        client: SYSTEM.0,
+                                        id: None,
+                                        requests: vec![Request::Tick],
+                                    });
+                                }
+                            }
 
                             Ok(())
                         }
                         Request::Shutdown => {
-                            shutdown = true;
+                            if draining.is_none() {
+                                info!("[W{}] draining before shutdown", worker.index());
+
+                                let deadline = Instant::now() + config.shutdown_timeout;
+                                draining = Some((server.drain(), deadline));
+                            }
+
                             Ok(())
                         }
+                        req => server.handle(worker, &mut handling, req),
                     };
 
+                    if let (Some(req), true) = (replicated, result.is_ok()) {
+                        journaled.push(req);
+                    }
+
                     if let Err(error) = result {
-                        io.send.send(Output::Error(client, error, last_tx)).unwrap();
+                        failed = true;
+
+                        if client == LIBRARY.0 {
+                            // Libraries are part of starting up, so the
+                            // rest of them is skipped, and the server
+                            // shuts down.
+                            error!("[W{}] failed to load library: {}", worker.index(), error.message);
+                            requests.clear();
+
+                            if is_owner {
+                                sequencer.push(Command {
+                                    owner,
+                                    client: SYSTEM.0,
+                                    id: None,
+                                    requests: vec![Request::Shutdown],
+                                });
+                            }
+
+                            failed_library.lock().unwrap().get_or_insert(error);
+                        } else if is_owner {
+                            io.send.send(Output::Error(client, error, last_tx, id)).unwrap();
+                        }
                     }
                 }
 
-                if !server_config.manual_advance {
-                    #[cfg(all(not(feature = "real-time"), not(feature = "bitemporal")))]
-                    let next = next_tx as u64;
-                    #[cfg(feature = "real-time")]
-                    let next = Instant::now().duration_since(worker.timer());
-                    #[cfg(feature = "bitemporal")]
-                    let next = Pair::new(Instant::now().duration_since(worker.timer()), next_tx as u64);
+                // Requests are journaled once they succeeded. Excised
+                // data is compacted away from what was journaled before.
+                if let (Some(writer), Some(path), false) = (journal.as_mut(), config.journal.as_ref(), journaled.is_empty()) {
+                    let mut written = Ok(());
+
+                    for index in 0..journaled.len() {
+                        if let Request::Excise(excise) = journaled[index].clone() {
+                            written = written
+                                .and_then(|()| server.excise_requests(&excise, &mut journaled[..index]))
+                                .and_then(|_| compact_journal(path, writer, &mut server, &excise));
+                        }
+                    }
+
+                    if let Err(error) = written.and_then(|()| append_journal(writer, &journaled)) {
+                        error!("[W{}] failed to journal requests: {:?}", worker.index(), error);
+                        failed = true;
 
-                    server.internal.advance_epoch(next).expect("failed to advance epoch");
+                        if is_owner {
+                            io.send.send(Output::Error(client, error, last_tx, id)).unwrap();
+                        }
+                    }
+                }
+
+                // Subscribers of swapped rules are switched over by
+                // issuing their interests again, on their behalf.
+                for (subscriber, interest) in handling.reissued.drain(..) {
+                    if subscriber == worker.index() {
+                        sequencer.push(Command {
+                            owner: subscriber,
+                            client: SYSTEM.0,
+                            id: None,
+                            requests: vec![Request::Interest(interest)],
+                        });
+                    }
+                }
+
+                // Deferred interests that can now be bound are handled as
+                // if their clients had just sent them.
+                for (owner, client, interest) in server.bound_interests() {
+                    if owner == worker.index() {
+                        sequencer.push(Command {
+                            owner,
+                            client: client.0,
+                            id: None,
+                            requests: vec![Request::Interest(interest)],
+                        });
+                    }
+                }
+
+                if let Some(datoms) = tx_datoms {
+                    let open = group.get_or_insert_with(|| Group {
+                        started: Instant::now(),
+                        tx: last_tx,
+                        datoms: 0,
+                        transactions: 0,
+                        acks: Vec::new(),
+                        closing: false,
+                    });
+
+                    open.datoms += datoms;
+                    open.transactions += 1;
+                }
+
+                let synced = handling.synced.take();
+
+                if let Some(t) = handling.deferred.take() {
+                    if is_owner && !failed {
+                        let visibility = synced.map(|(_t, visibility)| visibility);
+                        awaiting.push((t, visibility, client, id, last_tx));
+                    }
+                } else if handling.held_back {
+                    // Acknowledged once the interest is issued again.
+                } else if let (Some((t, visibility)), Some(id)) = (synced, id) {
+                    if is_owner && !failed {
+                        syncing.push((t, visibility, client, id, last_tx));
+                    }
+                } else if let Some(id) = id {
+                    if is_owner && !failed {
+                        let held = if tx_datoms.is_some() { group.as_mut() } else { None };
+                        match held {
+                            Some(open) => open.acks.push((client, id)),
+                            None => io.send.send(Output::Ack(client, id, last_tx)).unwrap(),
+                        }
+                    }
+                }
+
+                // Only the number of datoms may commit a group here,
+                // as all workers have to agree on when that happens.
+                let is_held = match (group.as_ref(), server.config.group_commit) {
+                    (Some(open), Some(limits)) if tx_datoms.is_some() => !limits.is_due(Duration::from_secs(0), open.datoms),
+                    _ => false,
+                };
+
+                if !is_held {
+                    if !server.config.manual_advance {
+                        #[cfg(all(not(feature = "real-time"), not(feature = "bitemporal")))]
+                        let next = next_tx as u64;
+                        #[cfg(feature = "real-time")]
+                        let next = Instant::now().duration_since(worker.timer());
+                        #[cfg(feature = "bitemporal")]
+                        let next = Pair::new(Instant::now().duration_since(worker.timer()), next_tx as u64);
+
+                        server.internal.advance_epoch(next).expect("failed to advance epoch");
+                    }
+
+                    if let Some(open) = group.take() {
+                        if let Some(limits) = server.config.group_commit {
+                            group_delay = limits.next_delay(group_delay, open.transactions);
+                        }
+
+                        for (client, id) in open.acks {
+                            io.send.send(Output::Ack(client, id, open.tx)).unwrap();
+                        }
+                    }
                 }
             }
 
@@ -644,20 +1332,86 @@ fn main() {
                 worker.step();
             }
 
+            // Transactions are acknowledged once they passed all
+            // constraints, or rejected.
+            for (t, result) in server.checked_transactions() {
+                awaiting.retain(|(awaited, visibility, client, id, tx)| {
+                    if *awaited != t {
+                        return true;
+                    }
+
+                    match result {
+                        Ok(()) => if let Some(id) = id {
+                            match visibility {
+                                Some(visibility) => syncing.push((t.clone(), *visibility, *client, *id, *tx)),
+                                None => io.send.send(Output::Ack(*client, *id, *tx)).unwrap(),
+                            }
+                        }
+                        Err(ref error) => {
+                            io.send.send(Output::Error(*client, error.clone(), *tx, *id)).unwrap();
+                        }
+                    }
+
+                    false
+                });
+            }
+
+            // Synchronous transactions are acknowledged once they
+            // have become visible.
+            syncing.retain(|(t, visibility, client, id, tx)| {
+                if !server.is_visible(t, *visibility) {
+                    return true;
+                }
+
+                io.send.send(Output::Ack(*client, *id, *tx)).unwrap();
+
+                false
+            });
+
             // We advance before `step_or_park`, because advancing
             // might take a decent amount of time, in case traces get
             // compacted. If that happens, we can park less before
             // scheduling the next activator.
             server.internal.advance().expect("failed to advance domain");
+            server.compact_results();
 
             // Finally, we give the CPU a chance to chill, if no work
             // remains.
-            let delay = server.scheduler.borrow().realtime.until_next().unwrap_or(Duration::from_millis(100));
+            let mut delay = server.scheduler.borrow().realtime.until_next().unwrap_or(Duration::from_millis(100));
+            if let Some(open) = group.as_ref() {
+                let remaining = group_delay.checked_sub(open.started.elapsed()).unwrap_or_default();
+                delay = std::cmp::min(delay, remaining);
+            }
             worker.step_or_park(Some(delay));
+
+            if let Some((ref upper, deadline)) = draining {
+                if server.is_drained(upper) {
+                    shutdown = true;
+                } else if Instant::now() >= deadline {
+                    warn!("[W{}] giving up on outstanding results", worker.index());
+                    shutdown = true;
+                }
+            }
         }
 
         info!("[W{}] shutting down", worker.index());
 
+        // Inputs are closed by now, so the state exported here is
+        // all there is.
+        if let Some(threads) = rescaling {
+            let requests = server.export_state(worker.index());
+            let mut handoff = handed_off.lock().unwrap();
+            let handoff = handoff.get_or_insert_with(|| Handoff { threads, shards: Vec::new() });
+
+            while handoff.shards.len() <= worker.index() {
+                handoff.shards.push(Vec::new());
+            }
+            handoff.shards[worker.index()] = requests;
+        }
+
+        // Deliver everything computed so far and let clients know.
+        io.shutdown(next_tx, &server.interests, &server.output_names, config.shutdown_timeout);
+
         drop(sequencer);
 
         // Shutdown loggers s.t. logging dataflows can shut down.
@@ -665,4 +1419,214 @@ fn main() {
         server.shutdown_logging(worker).unwrap();
 
     }).expect("Timely computation did not exit cleanly");
+
+    if let Some(error) = failure.lock().unwrap().take() {
+        return Err(error);
+    }
+
+    let handoff = handoff.lock().unwrap().take();
+    Ok(handoff)
+}
+
+/// Reads the requests listed in the specified libraries, in order.
+fn load_libraries(paths: &[String]) -> Result<Vec<Request<Aid>>, Error> {
+    let mut requests = Vec::new();
+
+    for path in paths {
+        let file = File::open(path)
+            .map_err(|e| Error::not_found(format!("Failed to open library {}: {}", path, e)))?;
+
+        let library: Vec<Request<Aid>> = serde_json::from_reader(BufReader::new(file))
+            .map_err(|e| Error::incorrect(format!("Failed to parse library {}: {}", path, e)))?;
+
+        info!("read {} requests from library {}", library.len(), path);
+
+        requests.extend(library);
+    }
+
+    Ok(requests)
+}
+
+/// Appends a batch of requests to a journal, as a line of its own.
+/// Batches are flushed right away, s.t. standbys see them as soon as
+/// possible.
+fn append_journal(journal: &mut BufWriter<File>, requests: &[Request<Aid>]) -> Result<(), Error> {
+    serde_json::to_writer(&mut *journal, requests)
+        .map_err(|e| Error::fault(format!("Failed to journal requests: {}", e)))?;
+
+    journal
+        .write_all(b"\n")
+        .and_then(|()| journal.flush())
+        .map_err(|e| Error::fault(format!("Failed to journal requests: {}", e)))
+}
+
+/// Rewrites the journal at the specified path without the datoms
+/// covered by an excision, and continues appending to the rewritten
+/// one. Every line is kept, s.t. standbys can tell how far they got.
+fn compact_journal(
+    path: &str,
+    journal: &mut BufWriter<File>,
+    server: &mut Server<Aid, T, Token>,
+    excise: &Excise,
+) -> Result<(), Error> {
+    let fault = |e: std::io::Error| Error::fault(format!("Failed to compact journal: {}", e));
+    let compacted_path = format!("{}.compacting", path);
+
+    journal.flush().map_err(fault)?;
+
+    let mut excised = 0;
+    {
+        let mut compacted = BufWriter::new(File::create(&compacted_path).map_err(fault)?);
+
+        for line in BufReader::new(File::open(path).map_err(fault)?).lines() {
+            let mut requests: Vec<Request<Aid>> = serde_json::from_str(&line.map_err(fault)?)
+                .map_err(|e| Error::fault(format!("Failed to parse journal: {}", e)))?;
+
+            excised += server.excise_requests(excise, &mut requests)?;
+            append_journal(&mut compacted, &requests)?;
+        }
+
+        compacted.get_ref().sync_all().map_err(fault)?;
+    }
+
+    std::fs::rename(&compacted_path, path).map_err(fault)?;
+
+    let file = OpenOptions::new().append(true).open(path).map_err(fault)?;
+    *journal = BufWriter::new(file);
+
+    info!("excised {} datoms from the journal", excised);
+
+    Ok(())
+}
+
+/// Follows a journal as it is being written by a primary, including
+/// across compactions, which replace it with a file holding the same
+/// number of lines.
+struct JournalTail {
+    /// The path the journal is written to.
+    pub path: String,
+    /// The file currently followed.
+    pub reader: BufReader<File>,
+    /// A line that hasn't been written completely yet.
+    pub partial: String,
+    /// Number of complete lines read so far.
+    pub lines: usize,
+}
+
+impl JournalTail {
+    /// Starts following the journal at the specified path.
+    fn open(path: &str) -> std::io::Result<Self> {
+        Ok(JournalTail {
+            path: path.to_string(),
+            reader: BufReader::new(File::open(path)?),
+            partial: String::new(),
+            lines: 0,
+        })
+    }
+
+    /// Reads all requests that were completely written to the journal
+    /// so far. Incomplete lines are kept around until they are
+    /// completed.
+    fn read(&mut self) -> Result<Vec<Vec<Request<Aid>>>, Error> {
+        let fault = |e: std::io::Error| Error::fault(format!("Failed to read journal: {}", e));
+        let mut commands = Vec::new();
+
+        loop {
+            if self.reader.read_line(&mut self.partial).map_err(fault)? == 0 {
+                if self.is_replaced().map_err(fault)? {
+                    self.reopen().map_err(fault)?;
+                    continue;
+                }
+
+                break;
+            }
+
+            if self.partial.ends_with('\n') {
+                let requests = serde_json::from_str(&self.partial)
+                    .map_err(|e| Error::fault(format!("Failed to parse journal: {}", e)))?;
+
+                commands.push(requests);
+                self.partial.clear();
+                self.lines += 1;
+            }
+        }
+
+        Ok(commands)
+    }
+
+    /// Returns true iff the journal was compacted since it was
+    /// opened.
+    #[cfg(unix)]
+    fn is_replaced(&self) -> std::io::Result<bool> {
+        use std::os::unix::fs::MetadataExt;
+
+        let current = std::fs::metadata(&self.path)?;
+        let followed = self.reader.get_ref().metadata()?;
+
+        Ok(current.dev() != followed.dev() || current.ino() != followed.ino())
+    }
+
+    /// Returns true iff the journal was compacted since it was
+    /// opened. Without inodes to tell files apart, a journal is taken
+    /// to be compacted once it is shorter than the one followed.
+    #[cfg(not(unix))]
+    fn is_replaced(&self) -> std::io::Result<bool> {
+        let current = std::fs::metadata(&self.path)?;
+        let followed = self.reader.get_ref().metadata()?;
+
+        Ok(current.len() < followed.len())
+    }
+
+    /// Opens the compacted journal, skipping the lines that have
+    /// been read already.
+    fn reopen(&mut self) -> std::io::Result<()> {
+        let mut reader = BufReader::new(File::open(&self.path)?);
+        let mut line = String::new();
+
+        for _ in 0..self.lines {
+            line.clear();
+            reader.read_line(&mut line)?;
+        }
+
+        self.reader = reader;
+        self.partial.clear();
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+
+    use declarative_dataflow::server::Request;
+
+    use super::load_libraries;
+
+    fn path(path: &Path) -> String {
+        path.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn libraries() {
+        let dir = std::env::temp_dir();
+        let valid = dir.join("declarative-library-valid.json");
+        let malformed = dir.join("declarative-library-malformed.json");
+        let missing = dir.join("declarative-library-missing.json");
+
+        fs::write(&valid, r#"[{"Uninterest": "names"}]"#).unwrap();
+        fs::write(&malformed, r#"[{"Fly": {}}]"#).unwrap();
+        let _ = fs::remove_file(&missing);
+
+        // Libraries are read in the order given.
+        assert_eq!(
+            load_libraries(&[path(&valid), path(&valid)]).unwrap(),
+            vec![Request::Uninterest("names".to_string()); 2]
+        );
+
+        // Any library that can't be read fails all of them.
+        assert!(load_libraries(&[path(&valid), path(&malformed)]).is_err());
+        assert!(load_libraries(&[path(&missing)]).is_err());
+    }
 }
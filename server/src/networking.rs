@@ -1,5 +1,6 @@
 use std::collections::{HashMap, HashSet, VecDeque};
 use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
 use slab::Slab;
@@ -9,27 +10,72 @@ pub use mio::Token;
 use mio::*;
 use mio_extras::channel;
 
+use serde::de::IgnoredAny;
+
 use ws::connection::{ConnEvent, Connection};
 
-use declarative_dataflow::server::Request;
-use declarative_dataflow::{Error, Output};
+use declarative_dataflow::frontends::edn::{read_one, Edn};
+use declarative_dataflow::server::backpressure::{Backpressure, Overflow};
+use declarative_dataflow::server::{edn, versioning};
+use declarative_dataflow::server::{BulkTransact, Request, RequestId};
+use declarative_dataflow::{Error, Output, ProtocolError};
 
+use crate::serializer::{Outlet, Pool, Written};
 use crate::Aid;
 
 const SERVER: Token = Token(std::usize::MAX - 1);
 const RESULTS: Token = Token(std::usize::MAX - 2);
 pub const SYSTEM: Token = Token(std::usize::MAX - 3);
+const WRITTEN: Token = Token(std::usize::MAX - 4);
+// Issues the requests loaded from libraries at startup.
+pub const LIBRARY: Token = Token(std::usize::MAX - 5);
 
 /// A high-level event devoid of I/O details.
 pub enum DomainEvent {
-    /// A client sent one or more requests.
-    Requests(Token, Vec<Request<Aid>>),
+    /// A client sent one or more requests, optionally identified.
+    Requests(Token, Option<RequestId>, Vec<Request<Aid>>),
+    /// A client has connected, via a token that might have been
+    /// held by another client before.
+    Connect(Token),
     /// A client has went away.
     Disconnect(Token),
 }
 
 use DomainEvent::*;
 
+/// Traffic counters for a single client connection.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Throughput {
+    /// Number of request batches received.
+    pub requests: usize,
+    /// Number of outputs sent.
+    pub outputs: usize,
+    /// Number of serialized bytes sent.
+    pub bytes: usize,
+}
+
+/// Text formats clients may speak. Each connection receives outputs
+/// in the format of the last message it sent, JSON by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// JSON, as understood by most clients.
+    Json,
+    /// EDN, preserving keywords and numeric types for Clojure clients.
+    Edn,
+}
+
+impl Format {
+    /// Serializes an output in this format.
+    pub fn serialize(self, out: &Output) -> String {
+        match self {
+            Format::Json => {
+                serde_json::to_string::<Output>(out).expect("failed to serialize output")
+            }
+            Format::Edn => edn::to_string(out).expect("failed to serialize output"),
+        }
+    }
+}
+
 /// State for translating low-level I/O events into domain events.
 pub struct IO {
     // Event loop.
@@ -46,18 +92,36 @@ pub struct IO {
     pub recv: channel::Receiver<Output>,
     // TCP server socket.
     server_socket: TcpListener,
-    // Client connections.
-    connections: Slab<Connection>,
+    // Client connections, shared with the serializer pool.
+    connections: Slab<Arc<Mutex<Connection>>>,
     next_connection_id: u32,
     // WebSocket settings.
     ws_settings: ws::Settings,
+    // Bounds on per-connection output buffers, if any.
+    backpressure: Option<Backpressure>,
+    // Outputs waiting for their connection to finish writing.
+    pending: HashMap<Token, VecDeque<Output>>,
+    // Traffic counters for all live connections.
+    throughput: HashMap<Token, Throughput>,
+    // Text format spoken by each connection that sent text messages.
+    formats: HashMap<Token, Format>,
+    // Threads serializing and writing outputs, if not done by the
+    // worker itself.
+    serializer: Option<Pool<Connection>>,
+    // Outputs written by the pool.
+    written: channel::Receiver<Written>,
 }
 
 impl IO {
-    pub fn new(address: SocketAddr) -> Self {
+    pub fn new(
+        address: SocketAddr,
+        backpressure: Option<Backpressure>,
+        serializer_threads: usize,
+    ) -> Self {
         let poll = Poll::new().expect("failed to setup event loop");
 
         let (send, recv) = channel::channel::<Output>();
+        let (send_written, written) = channel::channel::<Written>();
 
         let server_socket = TcpListener::bind(&address).expect("failed to create server socket");
 
@@ -69,6 +133,14 @@ impl IO {
         )
         .expect("failed to register result channel");
 
+        poll.register(
+            &written,
+            WRITTEN,
+            Ready::readable(),
+            PollOpt::edge() | PollOpt::oneshot(),
+        )
+        .expect("failed to register serialization channel");
+
         poll.register(&server_socket, SERVER, Ready::readable(), PollOpt::level())
             .expect("failed to register server socket");
 
@@ -88,11 +160,41 @@ impl IO {
             connections: Slab::with_capacity(ws_settings.max_connections),
             next_connection_id: 0,
             ws_settings,
+            backpressure,
+            pending: HashMap::new(),
+            throughput: HashMap::new(),
+            formats: HashMap::new(),
+            serializer: if serializer_threads > 0 {
+                Some(Pool::new(serializer_threads, send_written))
+            } else {
+                None
+            },
+            written,
         }
     }
 
-    /// Handle networking events.
-    pub fn step(&mut self, t: u64, interests: &HashMap<String, HashSet<Token>>) {
+    /// Reports traffic counters for all connections managed by this
+    /// worker.
+    pub fn throughput(&self) -> Vec<(usize, Throughput)> {
+        let mut throughput: Vec<(usize, Throughput)> = self
+            .throughput
+            .iter()
+            .map(|(token, stats)| (token.0, *stats))
+            .collect();
+
+        throughput.sort_by_key(|(token, _)| *token);
+
+        throughput
+    }
+
+    /// Handle networking events. Clients that know a shared relation
+    /// under another name receive its outputs under that name.
+    pub fn step(
+        &mut self,
+        t: u64,
+        interests: &HashMap<String, HashSet<Token>>,
+        output_names: &HashMap<String, HashMap<Token, String>>,
+    ) {
         // We mustn't timeout here, we are not in charge of blocking.
         self.poll
             .poll(&mut self.events, Some(Duration::from_millis(0)))
@@ -116,19 +218,20 @@ impl IO {
                                     self.next_connection_id =
                                         self.next_connection_id.wrapping_add(1);
 
-                                    entry.insert(Connection::new(
+                                    entry.insert(Arc::new(Mutex::new(Connection::new(
                                         token,
                                         socket,
                                         self.ws_settings,
                                         connection_id,
-                                    ));
+                                    ))));
 
                                     token
                                 };
 
                                 info!("[IO] new tcp connection from {} (token {:?})", addr, token);
+                                self.domain_events.push_back(Connect(token));
 
-                                let conn = &mut self.connections[token.into()];
+                                let mut conn = self.connections[token.into()].lock().unwrap();
 
                                 conn.as_server().unwrap();
 
@@ -158,6 +261,17 @@ impl IO {
                                     Some(tokens) => Box::new(tokens.iter().cloned()),
                                 }
                             }
+                            &Output::KeyedDiff(ref name, ref changes) => {
+                                info!("[IO] {} {} keyed changes", name, changes.len());
+
+                                match interests.get(name) {
+                                    None => {
+                                        warn!("result on query {} w/o interested clients", name);
+                                        Box::new(std::iter::empty())
+                                    }
+                                    Some(tokens) => Box::new(tokens.iter().cloned()),
+                                }
+                            }
                             &Output::Json(ref name, _, _, _) => {
                                 info!("[IO] json on query {}", name);
 
@@ -169,43 +283,66 @@ impl IO {
                                     Some(tokens) => Box::new(tokens.iter().cloned()),
                                 }
                             }
+                            &Output::InitialComplete(ref name, _) => {
+                                info!("[IO] initial snapshot complete on query {}", name);
+
+                                match interests.get(name) {
+                                    None => {
+                                        warn!("result on query {} w/o interested clients", name);
+                                        Box::new(std::iter::empty())
+                                    }
+                                    Some(tokens) => Box::new(tokens.iter().cloned()),
+                                }
+                            }
+                            &Output::Progress(ref name, _) => {
+                                trace!("[IO] progress on query {}", name);
+
+                                match interests.get(name) {
+                                    None => {
+                                        warn!("result on query {} w/o interested clients", name);
+                                        Box::new(std::iter::empty())
+                                    }
+                                    Some(tokens) => Box::new(tokens.iter().cloned()),
+                                }
+                            }
+                            &Output::GroupDiff(ref name, _, _) | &Output::Commit(ref name, _) => {
+                                trace!("[IO] transaction on group {}", name);
+
+                                match interests.get(name) {
+                                    None => {
+                                        warn!("result on group {} w/o interested clients", name);
+                                        Box::new(std::iter::empty())
+                                    }
+                                    Some(tokens) => Box::new(tokens.iter().cloned()),
+                                }
+                            }
                             &Output::Message(client, ref msg) => {
                                 info!("[IO] {:?}", msg);
                                 Box::new(std::iter::once(client.into()))
                             }
-                            &Output::Error(client, ref error, _) => {
+                            &Output::Ack(client, id, _) => {
+                                trace!("[IO] ack {}", id);
+                                Box::new(std::iter::once(client.into()))
+                            }
+                            &Output::Error(client, ref error, _, _) => {
                                 error!("[IO] {:?}", error);
                                 Box::new(std::iter::once(client.into()))
                             }
                         };
 
-                        let serialized = serde_json::to_string::<Output>(&out)
-                            .expect("failed to serialize output");
+                        let tokens: Vec<Token> = tokens.collect();
 
-                        let msg = ws::Message::text(serialized);
+                        let renames = subscription(&out).and_then(|name| output_names.get(name));
+                        let (renamed, tokens): (Vec<Token>, Vec<Token>) = tokens
+                            .into_iter()
+                            .partition(|token| renames.map_or(false, |renames| renames.contains_key(token)));
 
-                        for token in tokens {
-                            match self.connections.get_mut(token.into()) {
-                                None => {
-                                    // @TODO we need to clean up the connection here
-                                    warn!("client {:?} has gone away undetected", token);
-                                    self.domain_events.push_back(Disconnect(token));
-                                }
-                                Some(conn) => {
-                                    conn.send_message(msg.clone())
-                                        .expect("failed to send message");
-
-                                    self.poll
-                                        .reregister(
-                                            conn.socket(),
-                                            conn.token(),
-                                            conn.events(),
-                                            PollOpt::edge() | PollOpt::oneshot(),
-                                        )
-                                        .unwrap();
-                                }
-                            }
+                        for token in renamed {
+                            let name = renames.unwrap()[&token].clone();
+                            self.deliver(vec![token], out.clone().renamed(name), t);
                         }
+
+                        self.deliver(tokens, out, t);
                     }
 
                     self.poll
@@ -217,30 +354,66 @@ impl IO {
                         )
                         .unwrap();
                 }
+                WRITTEN => {
+                    while let Ok((tokens, bytes)) = self.written.try_recv() {
+                        if let Some(ref mut pool) = self.serializer {
+                            pool.written(&tokens);
+                        }
+
+                        for token in tokens {
+                            if !self.connections.contains(token.into()) {
+                                continue;
+                            }
+
+                            let stats = self.throughput.entry(token).or_default();
+                            stats.outputs += 1;
+                            stats.bytes += bytes;
+
+                            let conn_readiness = self.connections[token.into()].lock().unwrap().events();
+                            if conn_readiness.is_readable() || conn_readiness.is_writable() {
+                                self.resume(token);
+                            } else {
+                                self.disconnect(token);
+                            }
+                        }
+                    }
+
+                    self.poll
+                        .reregister(
+                            &self.written,
+                            WRITTEN,
+                            Ready::readable(),
+                            PollOpt::edge() | PollOpt::oneshot(),
+                        )
+                        .unwrap();
+                }
                 _ => {
                     let token = event.token();
                     let active = {
                         let event_readiness = event.readiness();
 
-                        let conn_readiness = self.connections[token.into()].events();
-                        if (event_readiness & conn_readiness).is_readable() {
-                            if let Err(err) =
-                                self.connections[token.into()].read(&mut self.conn_events)
-                            {
-                                trace!("[IO] error while reading: {}", err);
-                                // @TODO error handling
-                                self.connections[token.into()].error(err)
+                        {
+                            // Shared with the serializer pool, if any.
+                            let mut conn = self.connections[token.into()].lock().unwrap();
+
+                            let conn_readiness = conn.events();
+                            if (event_readiness & conn_readiness).is_readable() {
+                                if let Err(err) = conn.read(&mut self.conn_events) {
+                                    trace!("[IO] error while reading: {}", err);
+                                    // @TODO error handling
+                                    conn.error(err)
+                                }
                             }
-                        }
 
-                        let conn_readiness = self.connections[token.into()].events();
-                        if (event_readiness & conn_readiness).is_writable() {
-                            if let Err(err) =
-                                self.connections[token.into()].write(&mut self.conn_events)
-                            {
-                                trace!("[IO] error while writing: {}", err);
-                                // @TODO error handling
-                                self.connections[token.into()].error(err)
+                            // The pool only writes what the socket takes
+                            // right away, the rest is written from here.
+                            let conn_readiness = conn.events();
+                            if (event_readiness & conn_readiness).is_writable() {
+                                if let Err(err) = conn.write(&mut self.conn_events) {
+                                    trace!("[IO] error while writing: {}", err);
+                                    // @TODO error handling
+                                    conn.error(err)
+                                }
                             }
                         }
 
@@ -252,23 +425,64 @@ impl IO {
                                     trace!("[WS] ConnEvent::Message");
                                     match msg {
                                         ws::Message::Text(string) => {
-                                            match serde_json::from_str::<Vec<Request<Aid>>>(&string) {
-                                                Err(serde_error) => {
+                                            let current =
+                                                self.formats.get(&token).cloned().unwrap_or(Format::Json);
+                                            let (format, parsed) = parse_text(&string, current);
+                                            self.formats.insert(token, format);
+
+                                            match parsed {
+                                                Err((error, id)) => {
                                                     self.send
                                                         .send(Output::Error(
                                                             token.into(),
-                                                            Error::incorrect(serde_error),
+                                                            error,
                                                             t,
+                                                            id,
                                                         ))
                                                         .unwrap();
                                                 }
-                                                Ok(requests) => {
+                                                Ok((id, requests)) => {
+                                                    self.throughput
+                                                        .entry(token)
+                                                        .or_default()
+                                                        .requests += 1;
+
                                                     self.domain_events
-                                                        .push_back(Requests(token, requests));
+                                                        .push_back(Requests(token, id, requests));
+                                                }
+                                            }
+                                        }
+                                        ws::Message::Binary(bytes) => {
+                                            match bincode::deserialize::<BulkTransact<Aid>>(&bytes)
+                                            {
+                                                Err(bincode_error) => {
+                                                    self.send
+                                                        .send(Output::Error(
+                                                            token.into(),
+                                                            Error::from(
+                                                                ProtocolError::MalformedBulk(
+                                                                    bincode_error.to_string(),
+                                                                ),
+                                                            ),
+                                                            t,
+                                                            None,
+                                                        ))
+                                                        .unwrap();
+                                                }
+                                                Ok(chunk) => {
+                                                    self.throughput
+                                                        .entry(token)
+                                                        .or_default()
+                                                        .requests += 1;
+
+                                                    self.domain_events.push_back(Requests(
+                                                        token,
+                                                        Some(chunk.id),
+                                                        vec![Request::Transact(chunk.tx_data)],
+                                                    ));
                                                 }
                                             }
                                         }
-                                        ws::Message::Binary(_) => unimplemented!(),
                                     }
                                 }
                                 ConnEvent::Close(code, reason) => {
@@ -285,26 +499,17 @@ impl IO {
                         }
 
                         // connection events may have changed
-                        self.connections[token.into()].events().is_readable()
-                            || self.connections[token.into()].events().is_writable()
+                        let conn_readiness = self.connections[token.into()].lock().unwrap().events();
+                        conn_readiness.is_readable() || conn_readiness.is_writable()
                     };
 
                     // NOTE: Closing state only applies after a ws connection was successfully
                     // established. It's possible that we may go inactive while in a connecting
                     // state if the handshake fails.
                     if !active {
-                        self.domain_events.push_back(Disconnect(token.clone()));
-                        self.connections.remove(token.into());
+                        self.disconnect(token);
                     } else {
-                        let conn = &self.connections[token.into()];
-                        self.poll
-                            .reregister(
-                                conn.socket(),
-                                conn.token(),
-                                conn.events(),
-                                PollOpt::edge() | PollOpt::oneshot(),
-                            )
-                            .unwrap();
+                        self.resume(token);
                     }
                 }
             }
@@ -312,6 +517,358 @@ impl IO {
     }
 }
 
+impl IO {
+    /// Notifies all clients about the impending shutdown, flushes any
+    /// outputs still waiting to be sent, and closes all connections.
+    /// Gives up on clients that aren't done reading within the
+    /// specified timeout.
+    pub fn shutdown(
+        &mut self,
+        t: u64,
+        interests: &HashMap<String, HashSet<Token>>,
+        output_names: &HashMap<String, HashMap<Token, String>>,
+        timeout: Duration,
+    ) {
+        let deadline = std::time::Instant::now() + timeout;
+
+        for (token, _conn) in self.connections.iter() {
+            let message = serde_json::json!({
+                "category": "df/shutdown",
+                "message": "Server is shutting down.",
+            });
+
+            self.send.send(Output::Message(token, message)).unwrap();
+        }
+
+        // The result channel signals readiness as soon as anything is
+        // sent, so once a step leaves nothing pending, all outputs
+        // have been handed to the sockets.
+        loop {
+            self.step(t, interests, output_names);
+
+            let idle = self.pending.is_empty()
+                && self.serializer.as_ref().map_or(true, |pool| pool.is_idle())
+                && self
+                    .connections
+                    .iter()
+                    .all(|(_token, conn)| !conn.lock().unwrap().events().is_writable());
+
+            if idle || std::time::Instant::now() >= deadline {
+                break;
+            }
+
+            std::thread::sleep(Duration::from_millis(1));
+        }
+
+        for (_token, conn) in self.connections.iter() {
+            let mut conn = conn.lock().unwrap();
+
+            if let Err(err) = conn.send_close(ws::CloseCode::Away, "shutdown") {
+                warn!("[IO] failed to close connection {:?}", err);
+            }
+
+            if let Err(err) = conn.write(&mut self.conn_events) {
+                trace!("[IO] error while closing: {}", err);
+            }
+        }
+    }
+
+    /// Sends an output to the specified clients.
+    fn deliver(&mut self, tokens: Vec<Token>, out: Output, t: u64) {
+        if let Some(backpressure) = self.backpressure {
+            for token in tokens {
+                self.enqueue(token, out.clone(), backpressure, t);
+            }
+
+            return;
+        }
+
+        self.write(tokens, out);
+    }
+
+    /// Serializes an output for the specified clients, in the formats
+    /// they speak.
+    fn write(&mut self, tokens: Vec<Token>, out: Output) {
+        let (as_edn, as_json): (Vec<Token>, Vec<Token>) = {
+            let formats = &self.formats;
+            tokens
+                .into_iter()
+                .partition(|token| formats.get(token) == Some(&Format::Edn))
+        };
+
+        if as_edn.is_empty() {
+            self.write_as(Format::Json, as_json, out);
+        } else if as_json.is_empty() {
+            self.write_as(Format::Edn, as_edn, out);
+        } else {
+            self.write_as(Format::Json, as_json, out.clone());
+            self.write_as(Format::Edn, as_edn, out);
+        }
+    }
+
+    /// Serializes an output in the specified format, and writes it to
+    /// the specified connections. Both happen on the serializer pool,
+    /// if there is one.
+    fn write_as(&mut self, format: Format, tokens: Vec<Token>, out: Output) {
+        if self.serializer.is_none() {
+            let serialized = format.serialize(&out);
+            self.send_serialized(tokens, serialized);
+            return;
+        }
+
+        let mut targets = Vec::with_capacity(tokens.len());
+        for token in tokens {
+            match self.connections.get(token.into()) {
+                None => {
+                    // @TODO we need to clean up the connection here
+                    warn!("client {:?} has gone away undetected", token);
+                    self.domain_events.push_back(Disconnect(token));
+                }
+                Some(conn) => targets.push((token, conn.clone())),
+            }
+        }
+
+        if !targets.is_empty() {
+            self.serializer.as_mut().unwrap().submit(targets, out, format);
+        }
+    }
+
+    /// Hands a serialized output to the specified connections.
+    fn send_serialized(&mut self, tokens: Vec<Token>, serialized: String) {
+        let bytes = serialized.len();
+        let msg = ws::Message::text(serialized);
+
+        for token in tokens {
+            match self.connections.get_mut(token.into()) {
+                None => {
+                    // @TODO we need to clean up the connection here
+                    warn!("client {:?} has gone away undetected", token);
+                    self.domain_events.push_back(Disconnect(token));
+                }
+                Some(conn) => {
+                    let mut conn = conn.lock().unwrap();
+
+                    conn.send_message(msg.clone())
+                        .expect("failed to send message");
+
+                    let stats = self.throughput.entry(token).or_default();
+                    stats.outputs += 1;
+                    stats.bytes += bytes;
+
+                    self.poll
+                        .reregister(
+                            conn.socket(),
+                            conn.token(),
+                            conn.events(),
+                            PollOpt::edge() | PollOpt::oneshot(),
+                        )
+                        .unwrap();
+                }
+            }
+        }
+    }
+
+    /// Queues an output for the specified client, applying the
+    /// overflow policy if the client isn't keeping up.
+    fn enqueue(&mut self, token: Token, out: Output, backpressure: Backpressure, t: u64) {
+        let queue = self.pending.entry(token).or_insert_with(VecDeque::new);
+
+        match backpressure.enqueue(queue, out, token.into(), t) {
+            Overflow::Within(paused) => {
+                for name in paused {
+                    warn!("[IO] pausing {} for client {:?}", name, token);
+
+                    self.domain_events.push_back(Requests(
+                        token,
+                        None,
+                        vec![Request::Uninterest(name)],
+                    ));
+                }
+            }
+            Overflow::Exceeded => {
+                warn!("[IO] disconnecting client {:?}, which can't keep up", token);
+
+                self.disconnect(token);
+
+                return;
+            }
+        }
+
+        self.flush(token);
+    }
+
+    /// Hands all pending outputs to the specified connection, once it
+    /// has finished writing whatever it was handed before. Outputs
+    /// still with the serializer pool count as well, s.t. overflow
+    /// policies apply before outputs are handed to it.
+    fn flush(&mut self, token: Token) {
+        match self.connections.get(token.into()) {
+            None => {
+                warn!("client {:?} has gone away undetected", token);
+                self.pending.remove(&token);
+                self.domain_events.push_back(Disconnect(token));
+            }
+            Some(conn) => {
+                let is_writing = self
+                    .serializer
+                    .as_ref()
+                    .map_or(false, |pool| pool.is_writing(token));
+
+                if is_writing || conn.lock().unwrap().events().is_writable() {
+                    return;
+                }
+
+                let outputs: Vec<Output> = match self.pending.get_mut(&token) {
+                    None => Vec::new(),
+                    Some(queue) => queue.drain(..).collect(),
+                };
+
+                for out in outputs {
+                    self.write(vec![token], out);
+                }
+            }
+        }
+    }
+
+    /// Registers interest in whatever the specified connection has
+    /// left to do, and hands it pending outputs if it is done writing.
+    fn resume(&mut self, token: Token) {
+        {
+            let conn = self.connections[token.into()].lock().unwrap();
+
+            self.poll
+                .reregister(
+                    conn.socket(),
+                    conn.token(),
+                    conn.events(),
+                    PollOpt::edge() | PollOpt::oneshot(),
+                )
+                .unwrap();
+        }
+
+        // The connection might have finished writing.
+        if self.pending.contains_key(&token) {
+            self.flush(token);
+        }
+    }
+
+    /// Forgets about a connection that has gone away, or had to be
+    /// dropped.
+    fn disconnect(&mut self, token: Token) {
+        self.domain_events.push_back(Disconnect(token));
+        self.pending.remove(&token);
+        self.throughput.remove(&token);
+        self.formats.remove(&token);
+
+        if self.connections.contains(token.into()) {
+            self.connections.remove(token.into());
+        }
+    }
+}
+
+impl Outlet for Connection {
+    fn send(&mut self, serialized: String) {
+        let mut events = Vec::new();
+
+        // Whatever the socket doesn't take right away is written by
+        // the worker, once it is told about the socket's readiness.
+        let written = self
+            .send_message(ws::Message::text(serialized))
+            .and_then(|_| self.write(&mut events));
+
+        if let Err(err) = written {
+            trace!("[IO] error while writing: {}", err);
+            self.error(err);
+        }
+    }
+}
+
+/// The outcome of parsing a text message, along with the id of the
+/// envelope the requests came in, if any.
+type Parsed = Result<(Option<RequestId>, Vec<Request<Aid>>), (Error, Option<RequestId>)>;
+
+/// Parses a text message, which is read as EDN if it isn't JSON.
+/// Messages that are neither are answered in the format the client
+/// has spoken so far.
+fn parse_text(string: &str, current: Format) -> (Format, Parsed) {
+    let json_error = match parse_requests(string) {
+        Ok(parsed) => return (Format::Json, Ok(parsed)),
+        Err(error) => error,
+    };
+
+    // Only messages that aren't valid requests pay for telling the
+    // formats apart.
+    if serde_json::from_str::<IgnoredAny>(string).is_ok() {
+        return (Format::Json, Err(json_error));
+    }
+
+    match read_one(string) {
+        Ok(form) => (Format::Edn, parse_edn_requests(form)),
+        Err(error) => match current {
+            Format::Json => (current, Err(json_error)),
+            Format::Edn => {
+                let error = ProtocolError::Malformed(error.message);
+                (current, Err((Error::from(error), None)))
+            }
+        },
+    }
+}
+
+/// Parses a batch of requests sent as EDN, either bare or wrapped in
+/// an identified envelope.
+fn parse_edn_requests(form: Edn) -> Parsed {
+    let (is_envelope, id) = match form {
+        Edn::Map(ref entries) => {
+            let id = entries.iter().find_map(|entry| match entry {
+                (Edn::Keyword(key), Edn::Integer(id)) if key == "id" && *id >= 0 => {
+                    Some(*id as RequestId)
+                }
+                _ => None,
+            });
+
+            (true, id)
+        }
+        _ => (false, None),
+    };
+
+    let parsed = if is_envelope {
+        edn::parse_envelope::<Aid>(form).map(|envelope| (Some(envelope.id), envelope.requests))
+    } else {
+        edn::from_edn::<Vec<Request<Aid>>>(form).map(|requests| (None, requests))
+    };
+
+    parsed.map_err(|error| (error.into(), id))
+}
+
+/// Parses a batch of requests, which clients may send either bare or
+/// wrapped in an identified envelope. Errors reference the envelope's
+/// id, if it could be determined. Requests are parsed into owned
+/// values right away, as they are sequenced to all workers before
+/// being handled, which rules out borrowing from the message.
+fn parse_requests(
+    string: &str,
+) -> Result<(Option<RequestId>, Vec<Request<Aid>>), (Error, Option<RequestId>)> {
+    if string.trim_start().starts_with('[') {
+        serde_json::from_str::<Vec<Request<Aid>>>(string)
+            .map(|requests| (None, requests))
+            .map_err(|serde_error| {
+                let error = ProtocolError::Malformed(serde_error.to_string());
+                (Error::from(error), None)
+            })
+    } else {
+        // Envelopes may hold requests in an older format.
+        versioning::parse_envelope::<Aid>(string)
+            .map(|envelope| (Some(envelope.id), envelope.requests))
+            .map_err(|error| {
+                let id = serde_json::from_str::<serde_json::Value>(string)
+                    .ok()
+                    .and_then(|value| value.get("id").and_then(|id| id.as_u64()));
+
+                (error.into(), id)
+            })
+    }
+}
+
 impl Iterator for IO {
     type Item = DomainEvent;
     fn next(&mut self) -> Option<DomainEvent> {
@@ -0,0 +1,258 @@
+//! A pool of threads serializing outputs on behalf of a worker, and
+//! writing them to the connections they are addressed to.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::mpsc::{self, sync_channel, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+use mio_extras::channel;
+
+use declarative_dataflow::Output;
+
+use crate::networking::{Format, Token};
+
+/// Number of outputs that may wait for serialization, before the
+/// worker handing over more of them blocks.
+pub const QUEUE_CAPACITY: usize = 1024;
+
+/// Something serialized outputs can be written to, e.g. a client's
+/// connection. Outlets are shared between the worker and the pool.
+pub trait Outlet: Send + 'static {
+    /// Writes a serialized output, as far as that is possible without
+    /// blocking.
+    fn send(&mut self, serialized: String);
+}
+
+/// An outlet an output is to be written to, along with the token of
+/// its connection.
+pub type Target<O> = (Token, Arc<Mutex<O>>);
+
+/// The recipients of a written output, along with its size in bytes.
+pub type Written = (Vec<Token>, usize);
+
+// An output to serialize, along with its position among all outputs
+// handed to the pool.
+type Job<O> = (u64, Vec<Target<O>>, Output, Format);
+
+/// Threads serializing outputs and writing them out, s.t. large
+/// result batches don't hold up the worker. Outputs are numbered in
+/// the order they are handed over, for them to be written in that
+/// same order, by a single thread.
+pub struct Pool<O: Outlet> {
+    jobs: Option<SyncSender<Job<O>>>,
+    threads: Vec<JoinHandle<()>>,
+    submitted: u64,
+    written: u64,
+    in_flight: HashMap<Token, usize>,
+}
+
+impl<O: Outlet> Pool<O> {
+    /// Starts the specified number of threads, reporting written
+    /// outputs back via `done`. The calling thread is woken up
+    /// whenever one has been written, in case it is parked.
+    pub fn new(threads: usize, done: channel::Sender<Written>) -> Self {
+        let (send, recv) = sync_channel::<Job<O>>(QUEUE_CAPACITY);
+        let recv = Arc::new(Mutex::new(recv));
+        let (send_serialized, serialized) = mpsc::channel::<(u64, Vec<Target<O>>, String)>();
+        let worker = thread::current();
+
+        let mut handles: Vec<JoinHandle<()>> = (0..threads)
+            .map(|index| {
+                let recv = recv.clone();
+                let send_serialized = send_serialized.clone();
+
+                thread::Builder::new()
+                    .name(format!("serializer-{}", index))
+                    .spawn(move || loop {
+                        let job = recv.lock().expect("serializer queue poisoned").recv();
+
+                        let (position, targets, out, format) = match job {
+                            Err(_) => break,
+                            Ok(job) => job,
+                        };
+
+                        let serialized = format.serialize(&out);
+
+                        if send_serialized
+                            .send((position, targets, serialized))
+                            .is_err()
+                        {
+                            break;
+                        }
+                    })
+                    .expect("failed to spawn serializer thread")
+            })
+            .collect();
+
+        // The writer stops once all serializers have.
+        drop(send_serialized);
+
+        let writer = thread::Builder::new()
+            .name("writer".to_string())
+            .spawn(move || {
+                let mut reordered = BTreeMap::new();
+                let mut next = 0;
+
+                for (position, targets, serialized) in serialized.iter() {
+                    reordered.insert(position, (targets, serialized));
+
+                    while let Some((targets, serialized)) = reordered.remove(&next) {
+                        next += 1;
+
+                        let bytes = serialized.len();
+                        let tokens = targets.iter().map(|(token, _outlet)| *token).collect();
+
+                        for (_token, outlet) in targets {
+                            outlet
+                                .lock()
+                                .expect("outlet poisoned")
+                                .send(serialized.clone());
+                        }
+
+                        if done.send((tokens, bytes)).is_err() {
+                            return;
+                        }
+
+                        worker.unpark();
+                    }
+                }
+            })
+            .expect("failed to spawn writer thread");
+
+        handles.push(writer);
+
+        Pool {
+            jobs: Some(send),
+            threads: handles,
+            submitted: 0,
+            written: 0,
+            in_flight: HashMap::new(),
+        }
+    }
+
+    /// Hands an output to the pool, to be serialized in the specified
+    /// format and written to the specified outlets. Blocks while the
+    /// queue is full.
+    pub fn submit(&mut self, targets: Vec<Target<O>>, out: Output, format: Format) {
+        let position = self.submitted;
+        self.submitted += 1;
+
+        for (token, _outlet) in targets.iter() {
+            *self.in_flight.entry(*token).or_insert(0) += 1;
+        }
+
+        self.jobs
+            .as_ref()
+            .unwrap()
+            .send((position, targets, out, format))
+            .expect("serializer threads have gone away");
+    }
+
+    /// Notes that an output reported via `done` has been written.
+    pub fn written(&mut self, tokens: &[Token]) {
+        self.written += 1;
+
+        for token in tokens {
+            let is_done = match self.in_flight.get_mut(token) {
+                None => false,
+                Some(count) => {
+                    *count -= 1;
+                    *count == 0
+                }
+            };
+
+            if is_done {
+                self.in_flight.remove(token);
+            }
+        }
+    }
+
+    /// Returns true iff outputs handed to the pool for the specified
+    /// connection haven't all been written yet.
+    pub fn is_writing(&self, token: Token) -> bool {
+        self.in_flight.contains_key(&token)
+    }
+
+    /// Returns true iff all outputs handed to the pool have been
+    /// written.
+    pub fn is_idle(&self) -> bool {
+        self.submitted == self.written
+    }
+}
+
+impl<O: Outlet> Drop for Pool<O> {
+    fn drop(&mut self) {
+        // Threads stop once the queue is closed and drained.
+        self.jobs = None;
+
+        for handle in self.threads.drain(..) {
+            if handle.join().is_err() {
+                error!("[IO] serializer thread panicked");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+
+    use mio_extras::channel;
+
+    use declarative_dataflow::Output;
+
+    use super::{Outlet, Pool};
+    use crate::networking::{Format, Token};
+
+    struct Recorder(Vec<String>);
+
+    impl Outlet for Recorder {
+        fn send(&mut self, serialized: String) {
+            self.0.push(serialized);
+        }
+    }
+
+    #[test]
+    fn writes_in_order() {
+        let (done, written) = channel::channel();
+        let recorder = Arc::new(Mutex::new(Recorder(Vec::new())));
+        let mut pool = Pool::new(4, done);
+
+        let outputs: Vec<Output> = (0..100)
+            .map(|i| Output::Message(1, serde_json::json!(i)))
+            .collect();
+
+        for out in outputs.iter() {
+            pool.submit(
+                vec![(Token(1), recorder.clone())],
+                out.clone(),
+                Format::Json,
+            );
+        }
+
+        // Connections count as busy until the worker has been told
+        // about all their outputs, s.t. further ones are held back.
+        assert!(pool.is_writing(Token(1)));
+        assert!(!pool.is_writing(Token(2)));
+
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while !pool.is_idle() && Instant::now() < deadline {
+            match written.try_recv() {
+                Ok((tokens, _bytes)) => pool.written(&tokens),
+                Err(_) => std::thread::sleep(Duration::from_millis(1)),
+            }
+        }
+
+        assert!(pool.is_idle());
+        assert!(!pool.is_writing(Token(1)));
+
+        let expected: Vec<String> = outputs
+            .iter()
+            .map(|out| Format::Json.serialize(out))
+            .collect();
+
+        assert_eq!(recorder.lock().unwrap().0, expected);
+    }
+}
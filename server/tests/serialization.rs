@@ -26,3 +26,24 @@ fn test_serialization() {
         "{\"Uuid\":\"71828aae-4fc8-421b-82ca-68c5f4981d74\"}".to_string(),
     );
 }
+
+#[test]
+fn bulk_transact_roundtrip() {
+    use declarative_dataflow::server::BulkTransact;
+    use declarative_dataflow::Datom;
+
+    let chunk = BulkTransact {
+        id: 7,
+        tx_data: vec![
+            Datom::add(1, ":name", String("Dipper".to_string())),
+            Datom::add(1, ":age", Number(12)),
+        ],
+    };
+
+    let bytes = bincode::serialize(&chunk).unwrap();
+
+    assert_eq!(
+        bincode::deserialize::<BulkTransact<std::string::String>>(&bytes).unwrap(),
+        chunk
+    );
+}
@@ -0,0 +1,166 @@
+//! Pre-built rules for common graph algorithms, over attributes
+//! relating entities to one another, e.g. ref-typed attributes. Each
+//! algorithm returns all rules it consists of, ready to be
+//! registered. Results can be subscribed to under the given name,
+//! helper rules are namespaced beneath it.
+
+use crate::plan::{AggregationFn, Function, PlanBuilder, Predicate};
+use crate::{AsAid, Rule, Value};
+
+fn rule<A: AsAid>(name: A, plan: PlanBuilder<A>) -> Rule<A> {
+    Rule {
+        name,
+        plan: plan.build().expect("algorithm plan is malformed"),
+    }
+}
+
+fn helper<A: AsAid>(name: &A, helper: &str) -> A {
+    A::from(helper.to_string()).with_namespace(name.clone())
+}
+
+/// Relates each entity to all entities reachable from it via one or
+/// more edges, as `[?from ?to]`.
+pub fn transitive_closure<A, N, E>(name: N, edge: E) -> Vec<Rule<A>>
+where
+    A: AsAid,
+    N: Into<A>,
+    E: Into<A>,
+{
+    let (name, edge) = (name.into(), edge.into());
+    let (from, to, via) = (0, 1, 2);
+
+    let reachable = PlanBuilder::match_a(from, edge.clone(), to).union(
+        PlanBuilder::match_a(from, edge, via)
+            .join(PlanBuilder::name_expr(vec![via, to], name.clone()))
+            .project(vec![from, to]),
+    );
+
+    vec![rule(name, reachable)]
+}
+
+/// Labels each entity with the smallest entity in its connected
+/// component, as `[?e ?label]`. Edges are followed in both
+/// directions. Entities without any edges are not labeled.
+pub fn connected_components<A, N, E>(name: N, edge: E) -> Vec<Rule<A>>
+where
+    A: AsAid,
+    N: Into<A>,
+    E: Into<A>,
+{
+    let (name, edge) = (name.into(), edge.into());
+    let (e, neighbour, label) = (0, 1, 2);
+
+    let edges = helper(&name, "edges");
+    let undirected = PlanBuilder::match_a(e, edge.clone(), neighbour)
+        .union(PlanBuilder::match_a(neighbour, edge, e));
+
+    // Every entity is its neighbour's neighbour, which is why
+    // labelling each with its neighbours is enough to start out.
+    let labels = PlanBuilder::name_expr(vec![e, label], edges.clone())
+        .union(
+            PlanBuilder::name_expr(vec![e, neighbour], edges.clone())
+                .join(PlanBuilder::name_expr(vec![neighbour, label], name.clone()))
+                .project(vec![e, label]),
+        )
+        .aggregate(vec![e], vec![(AggregationFn::MIN, label)], vec![]);
+
+    vec![rule(edges, undirected), rule(name, labels)]
+}
+
+/// Relates each entity to all entities reachable from it in at most
+/// `max_hops` edges, along with the smallest number of edges needed
+/// to get there, as `[?from ?to ?hops]`.
+pub fn shortest_paths<A, N, E>(name: N, edge: E, max_hops: i64) -> Vec<Rule<A>>
+where
+    A: AsAid,
+    N: Into<A>,
+    E: Into<A>,
+{
+    let (name, edge) = (name.into(), edge.into());
+    let (from, to, via, hops, prior) = (0, 1, 2, 3, 4);
+
+    let one = || vec![None, Some(Value::Number(1))];
+
+    let direct =
+        PlanBuilder::match_a(from, edge.clone(), to).transform(Function::ADD, vec![], one(), hops);
+    let indirect = PlanBuilder::name_expr(vec![from, via, prior], name.clone())
+        .join(PlanBuilder::match_a(via, edge, to))
+        .transform(Function::ADD, vec![prior], one(), hops)
+        .filter_value(Predicate::LTE, hops, Value::Number(max_hops))
+        .project(vec![from, to, hops]);
+
+    let paths =
+        direct
+            .union(indirect)
+            .aggregate(vec![from, to], vec![(AggregationFn::MIN, hops)], vec![]);
+
+    vec![rule(name, paths)]
+}
+
+/// Counts the walks of up to `rounds` edges ending in each entity,
+/// as `[?e ?walks]`, including the empty walk. Each round, the count
+/// of an entity becomes one plus the sum of the counts of all
+/// entities linking to it.
+///
+/// This is not PageRank: counts are neither damped nor split up
+/// between outgoing edges, because plans can't divide. Counts grow
+/// quickly with the number of rounds, so these should be kept small.
+pub fn walk_counts<A, N, E>(name: N, edge: E, rounds: usize) -> Vec<Rule<A>>
+where
+    A: AsAid,
+    N: Into<A>,
+    E: Into<A>,
+{
+    let (name, edge) = (name.into(), edge.into());
+    let (e, source, walks) = (0, 1, 2);
+
+    let constant = |x| vec![None, Some(Value::Number(x))];
+    let round_name = |round| {
+        if round == rounds {
+            name.clone()
+        } else {
+            helper(&name, &round.to_string())
+        }
+    };
+
+    let nodes = helper(&name, "nodes");
+    let mut rules = vec![rule(
+        nodes.clone(),
+        PlanBuilder::match_a(e, edge.clone(), source)
+            .project(vec![e])
+            .union(PlanBuilder::match_a(source, edge.clone(), e).project(vec![e])),
+    )];
+
+    rules.push(rule(
+        round_name(0),
+        PlanBuilder::name_expr(vec![e], nodes.clone()).transform(
+            Function::ADD,
+            vec![],
+            constant(1),
+            walks,
+        ),
+    ));
+
+    for round in 1..=rounds {
+        // Each entity contributes to its own count, which is told
+        // apart from contributions of other entities by a source
+        // that can't be an entity.
+        let own = PlanBuilder::name_expr(vec![e], nodes.clone())
+            .transform(Function::ADD, vec![], constant(0), source)
+            .transform(Function::ADD, vec![], constant(1), walks);
+        let incoming = PlanBuilder::match_a(source, edge.clone(), e)
+            .join(PlanBuilder::name_expr(
+                vec![source, walks],
+                round_name(round - 1),
+            ))
+            .project(vec![e, source, walks]);
+
+        let counts =
+            own.union(incoming)
+                .aggregate(vec![e], vec![(AggregationFn::SUM, walks)], vec![source]);
+
+        rules.push(rule(round_name(round), counts));
+    }
+
+    rules
+}
@@ -89,6 +89,26 @@ impl<A: AsAid> Binding<A> {
             binding: Box::new(binding),
         })
     }
+
+    /// Returns the same binding, with its variables renamed via the
+    /// specified function.
+    pub fn renamed<F: Fn(Var) -> Var>(&self, rename: &F) -> Self {
+        match *self {
+            Binding::Attribute(ref binding) => Binding::Attribute(AttributeBinding {
+                variables: (rename(binding.variables.0), rename(binding.variables.1)),
+                source_attribute: binding.source_attribute.clone(),
+            }),
+            Binding::Not(ref binding) => Binding::not(binding.binding.renamed(rename)),
+            Binding::Constant(ref binding) => {
+                Binding::constant(rename(binding.variable), binding.value.clone())
+            }
+            Binding::BinaryPredicate(ref binding) => Binding::binary_predicate(
+                binding.predicate.clone(),
+                rename(binding.variables.0),
+                rename(binding.variables.1),
+            ),
+        }
+    }
 }
 
 impl<A: AsAid> AsBinding for Binding<A> {
@@ -296,6 +316,20 @@ pub enum BinaryPredicate {
     NEQ,
 }
 
+impl BinaryPredicate {
+    /// Checks whether the predicate holds between two values.
+    pub fn holds(&self, a: &Value, b: &Value) -> bool {
+        match *self {
+            BinaryPredicate::LT => a < b,
+            BinaryPredicate::GT => a > b,
+            BinaryPredicate::LTE => a <= b,
+            BinaryPredicate::GTE => a >= b,
+            BinaryPredicate::EQ => a == b,
+            BinaryPredicate::NEQ => a != b,
+        }
+    }
+}
+
 /// Describe a binary predicate constraint.
 #[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Serialize, Deserialize)]
 pub struct BinaryPredicateBinding {
@@ -0,0 +1,372 @@
+//! A client for talking to Declarative Dataflow servers over
+//! WebSockets, without hand-rolling the wire protocol.
+//!
+//! All requests are sent in identified envelopes and block until the
+//! server has acknowledged them. Results arrive on a background
+//! thread and are routed to the `Subscription` they belong to.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::server::versioning::CURRENT_VERSION;
+use crate::server::{BulkTransact, Envelope, Interest, Register, Request, RequestId, TxId};
+use crate::{Aid, Datom, Error, Output, Plan, Rule};
+
+type Replies = Arc<Mutex<HashMap<RequestId, Sender<Result<TxId, Error>>>>>;
+type Routes = Arc<Mutex<HashMap<String, Sender<Output>>>>;
+
+/// A connection to a server.
+pub struct Client {
+    out: ws::Sender,
+    next_id: RequestId,
+    replies: Replies,
+    routes: Routes,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Client {
+    /// Connects to the server at the specified address, e.g.
+    /// `ws://127.0.0.1:6262`.
+    pub fn connect(address: &str) -> Result<Self, Error> {
+        let replies: Replies = Default::default();
+        let routes: Routes = Default::default();
+
+        let (send_out, recv_out) = channel();
+
+        let thread = {
+            let address = address.to_string();
+            let replies = replies.clone();
+            let routes = routes.clone();
+
+            thread::spawn(move || {
+                let result = ws::connect(address, |out| {
+                    send_out.send(out).expect("client went away");
+
+                    Handler {
+                        replies: replies.clone(),
+                        routes: routes.clone(),
+                    }
+                });
+
+                if let Err(error) = result {
+                    error!("[client] {}", error);
+                }
+
+                // Dropping all senders wakes up anyone still waiting
+                // on the connection.
+                replies.lock().unwrap().clear();
+                routes.lock().unwrap().clear();
+            })
+        };
+
+        match recv_out.recv() {
+            Err(_) => Err(Error::fault(format!("Failed to connect to {}.", address))),
+            Ok(out) => Ok(Client {
+                out,
+                next_id: 0,
+                replies,
+                routes,
+                thread: Some(thread),
+            }),
+        }
+    }
+
+    /// Sends a batch of requests and waits for the server to handle
+    /// them, returning the transaction they were handled at.
+    pub fn request(&mut self, requests: Vec<Request<Aid>>) -> Result<TxId, Error> {
+        let (id, recv_reply) = self.expect_reply();
+
+        let envelope = Envelope {
+            id,
+            version: Some(CURRENT_VERSION),
+            requests,
+        };
+
+        let message = serde_json::to_string(&envelope)
+            .map(ws::Message::text)
+            .map_err(Error::fault);
+
+        self.send(id, message)?;
+        self.await_reply(id, recv_reply)
+    }
+
+    /// Loads a large number of datoms, in binary chunks of the
+    /// specified size. At most `window` chunks are sent ahead of the
+    /// last one acknowledged, s.t. the server isn't flooded with more
+    /// than it can handle. Returns the transaction at which the final
+    /// chunk was handled.
+    pub fn bulk_transact<I>(
+        &mut self,
+        tx_data: I,
+        chunk_size: usize,
+        window: usize,
+    ) -> Result<TxId, Error>
+    where
+        I: IntoIterator<Item = Datom<Aid>>,
+    {
+        let chunk_size = chunk_size.max(1);
+        let window = window.max(1);
+
+        let mut in_flight = VecDeque::new();
+        let mut last_tx = None;
+        let mut chunk = Vec::with_capacity(chunk_size);
+
+        let mut datoms = tx_data.into_iter().peekable();
+
+        while let Some(datom) = datoms.next() {
+            chunk.push(datom);
+
+            if chunk.len() == chunk_size || datoms.peek().is_none() {
+                let tx_data = std::mem::replace(&mut chunk, Vec::with_capacity(chunk_size));
+                let (id, recv_reply) = self.expect_reply();
+
+                let message = bincode::serialize(&BulkTransact { id, tx_data })
+                    .map(ws::Message::binary)
+                    .map_err(Error::fault);
+
+                if let Err(error) = self.send(id, message) {
+                    self.forget(in_flight);
+                    return Err(error);
+                }
+
+                in_flight.push_back((id, recv_reply));
+            }
+
+            // Once the last chunk is out, we wait for all of them.
+            while in_flight.len() >= window || (datoms.peek().is_none() && !in_flight.is_empty()) {
+                let (id, recv_reply) = in_flight.pop_front().unwrap();
+
+                match self.await_reply(id, recv_reply) {
+                    Ok(tx) => last_tx = Some(tx),
+                    Err(error) => {
+                        self.forget(in_flight);
+                        return Err(error);
+                    }
+                }
+            }
+        }
+
+        last_tx.ok_or_else(|| Error::incorrect("Nothing to transact."))
+    }
+
+    fn expect_reply(&mut self) -> (RequestId, Receiver<Result<TxId, Error>>) {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let (send_reply, recv_reply) = channel();
+        self.replies.lock().unwrap().insert(id, send_reply);
+
+        (id, recv_reply)
+    }
+
+    fn send(&self, id: RequestId, message: Result<ws::Message, Error>) -> Result<(), Error> {
+        let result = message.and_then(|message| self.out.send(message).map_err(Error::fault));
+
+        if result.is_err() {
+            self.replies.lock().unwrap().remove(&id);
+        }
+
+        result
+    }
+
+    /// Stops waiting on replies to the specified requests.
+    fn forget(&self, in_flight: VecDeque<(RequestId, Receiver<Result<TxId, Error>>)>) {
+        let mut replies = self.replies.lock().unwrap();
+        for (id, _recv_reply) in in_flight {
+            replies.remove(&id);
+        }
+    }
+
+    fn await_reply(
+        &self,
+        id: RequestId,
+        recv_reply: Receiver<Result<TxId, Error>>,
+    ) -> Result<TxId, Error> {
+        let result = recv_reply
+            .recv()
+            .unwrap_or_else(|_| Err(Error::fault("Connection closed.")));
+
+        self.replies.lock().unwrap().remove(&id);
+
+        result
+    }
+
+    /// Sends inputs via one or more registered attributes.
+    pub fn transact(&mut self, tx_data: Vec<Datom<Aid>>) -> Result<TxId, Error> {
+        self.request(vec![Request::Transact(tx_data)])
+    }
+
+    /// Registers and publishes a named relation.
+    pub fn register(&mut self, name: &str, plan: Plan<Aid>) -> Result<TxId, Error> {
+        self.request(vec![Request::Register(Register {
+            rules: vec![Rule::named(name, plan)],
+            publish: vec![name.to_string()],
+        })])
+    }
+
+    /// Expresses interest in a named relation, returning a stream of
+    /// all outputs on it.
+    pub fn interest(&mut self, interest: Interest) -> Result<Subscription, Error> {
+        let name = interest.name.clone();
+
+        // Results might arrive before the acknowledgement does.
+        let (send_outputs, recv_outputs) = channel();
+        self.routes
+            .lock()
+            .unwrap()
+            .insert(name.clone(), send_outputs);
+
+        match self.request(vec![Request::Interest(interest)]) {
+            Err(error) => {
+                self.routes.lock().unwrap().remove(&name);
+                Err(error)
+            }
+            Ok(_tx) => Ok(Subscription {
+                name,
+                outputs: recv_outputs,
+                out: self.out.clone(),
+            }),
+        }
+    }
+
+    /// Expresses interest in a named relation, using the default
+    /// delivery options.
+    pub fn subscribe(&mut self, name: &str) -> Result<Subscription, Error> {
+        self.interest(Interest {
+            name: name.to_string(),
+            ..Default::default()
+        })
+    }
+
+    /// Expresses interest in a named relation, whose results are held
+    /// back until they reflect the specified transaction, e.g. one
+    /// returned by `transact`. Blocks until that is the case.
+    pub fn subscribe_after(&mut self, name: &str, tx: TxId) -> Result<Subscription, Error> {
+        self.interest(Interest {
+            name: name.to_string(),
+            at_least: Some(tx),
+            ..Default::default()
+        })
+    }
+}
+
+impl Drop for Client {
+    fn drop(&mut self) {
+        if let Err(error) = self.out.close(ws::CloseCode::Normal) {
+            warn!("[client] failed to close connection {}", error);
+        }
+
+        if let Some(thread) = self.thread.take() {
+            thread.join().expect("client thread panicked");
+        }
+    }
+}
+
+/// A stream of outputs on a single named relation or interest
+/// group. Interest in it ends when the subscription is dropped.
+pub struct Subscription {
+    name: String,
+    outputs: Receiver<Output>,
+    out: ws::Sender,
+}
+
+impl Subscription {
+    /// The name of the relation this subscription is on.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Waits for the next output for at most the specified duration.
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<Output> {
+        match self.outputs.recv_timeout(timeout) {
+            Ok(out) => Some(out),
+            Err(RecvTimeoutError::Timeout) | Err(RecvTimeoutError::Disconnected) => None,
+        }
+    }
+}
+
+impl Iterator for Subscription {
+    type Item = Output;
+
+    fn next(&mut self) -> Option<Output> {
+        self.outputs.recv().ok()
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        let requests: Vec<Request<Aid>> = vec![Request::Uninterest(self.name.clone())];
+        let serialized = serde_json::to_string(&requests).expect("failed to serialize requests");
+
+        // The connection might already be gone, in which case there
+        // is nothing left to clean up.
+        self.out.send(serialized).ok();
+    }
+}
+
+/// Routes incoming outputs to whoever is waiting on them.
+struct Handler {
+    replies: Replies,
+    routes: Routes,
+}
+
+impl Handler {
+    fn reply(&self, id: RequestId, result: Result<TxId, Error>) {
+        match self.replies.lock().unwrap().get(&id) {
+            None => warn!("[client] unexpected reply to request {}", id),
+            Some(send_reply) => {
+                send_reply.send(result).ok();
+            }
+        }
+    }
+
+    fn route(&self, name: String, out: Output) {
+        let mut routes = self.routes.lock().unwrap();
+
+        let is_closed = match routes.get(&name) {
+            None => {
+                trace!("[client] output on {} w/o subscription", name);
+                false
+            }
+            Some(send_outputs) => send_outputs.send(out).is_err(),
+        };
+
+        if is_closed {
+            routes.remove(&name);
+        }
+    }
+}
+
+impl ws::Handler for Handler {
+    fn on_message(&mut self, msg: ws::Message) -> ws::Result<()> {
+        let out = match serde_json::from_str::<Output>(msg.as_text()?) {
+            Err(error) => {
+                error!("[client] failed to parse output {}", error);
+                return Ok(());
+            }
+            Ok(out) => out,
+        };
+
+        match out {
+            Output::Ack(_, id, tx) => self.reply(id, Ok(tx)),
+            Output::Error(_, error, _, Some(id)) => self.reply(id, Err(error)),
+            Output::Error(_, error, tx, None) => error!("[client] {:?} @ {}", error, tx),
+            Output::Message(_, msg) => info!("[client] {}", msg),
+            Output::QueryDiff(ref name, _)
+            | Output::KeyedDiff(ref name, _)
+            | Output::Json(ref name, _, _, _)
+            | Output::InitialComplete(ref name, _)
+            | Output::Progress(ref name, _)
+            | Output::GroupDiff(ref name, _, _)
+            | Output::Commit(ref name, _) => {
+                let name = name.clone();
+                self.route(name, out);
+            }
+        }
+
+        Ok(())
+    }
+}
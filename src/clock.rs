@@ -0,0 +1,105 @@
+//! Wall-clock time. Targets without a system clock, such as wasm32,
+//! must inject one, because `std::time::Instant` isn't available to
+//! them.
+
+#[cfg(not(feature = "wasm"))]
+pub use std::time::Instant;
+
+#[cfg(feature = "wasm")]
+pub use self::injected::{set_clock, set_unix_clock, since_unix_epoch, Instant};
+
+/// Returns the time elapsed since the Unix epoch, or zero, if the
+/// system clock is set to an earlier date.
+#[cfg(not(feature = "wasm"))]
+pub fn since_unix_epoch() -> std::time::Duration {
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+}
+
+#[cfg(feature = "wasm")]
+mod injected {
+    use std::cell::Cell;
+    use std::ops::{Add, Sub};
+    use std::time::Duration;
+
+    thread_local! {
+        static CLOCK: Cell<fn() -> Duration> = Cell::new(frozen);
+        static UNIX_CLOCK: Cell<fn() -> Duration> = Cell::new(frozen);
+    }
+
+    // Until a clock is installed, time stands still. Everything not
+    // driven by wall-clock time, such as plan evaluation, works just
+    // the same.
+    fn frozen() -> Duration {
+        Duration::from_secs(0)
+    }
+
+    /// Installs the function reporting the time elapsed since some
+    /// fixed point, e.g. one backed by `performance.now()`.
+    pub fn set_clock(clock: fn() -> Duration) {
+        CLOCK.with(|current| current.set(clock));
+    }
+
+    /// Installs the function reporting the time elapsed since the
+    /// Unix epoch, e.g. one backed by `Date.now()`.
+    pub fn set_unix_clock(clock: fn() -> Duration) {
+        UNIX_CLOCK.with(|current| current.set(clock));
+    }
+
+    /// Returns the time elapsed since the Unix epoch, as reported by
+    /// the injected clock.
+    pub fn since_unix_epoch() -> Duration {
+        UNIX_CLOCK.with(|clock| (clock.get())())
+    }
+
+    /// A point in time, as reported by the injected clock.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    pub struct Instant(Duration);
+
+    impl Instant {
+        /// Returns the current time.
+        pub fn now() -> Self {
+            Instant(CLOCK.with(|clock| (clock.get())()))
+        }
+
+        /// Returns the time elapsed since an earlier point in time,
+        /// or zero, if it was in fact later.
+        pub fn duration_since(&self, earlier: Instant) -> Duration {
+            self.0
+                .checked_sub(earlier.0)
+                .unwrap_or_else(|| Duration::from_secs(0))
+        }
+
+        /// Returns the time elapsed since this point in time.
+        pub fn elapsed(&self) -> Duration {
+            Instant::now().duration_since(*self)
+        }
+    }
+
+    impl Add<Duration> for Instant {
+        type Output = Instant;
+
+        fn add(self, other: Duration) -> Instant {
+            Instant(self.0 + other)
+        }
+    }
+
+    impl Sub<Duration> for Instant {
+        type Output = Instant;
+
+        fn sub(self, other: Duration) -> Instant {
+            Instant(self.0 - other)
+        }
+    }
+
+    impl Sub<Instant> for Instant {
+        type Output = Duration;
+
+        fn sub(self, other: Instant) -> Duration {
+            self.duration_since(other)
+        }
+    }
+}
@@ -19,8 +19,8 @@ use crate::binding::{AsBinding, Binding};
 use crate::plan::{gensym, Dependencies, Implementable};
 use crate::plan::{Hector, Plan};
 use crate::timestamp::Rewind;
+use crate::{ArrangementMap, Relation, ShutdownHandle, VariableMap};
 use crate::{AsAid, Value, Var};
-use crate::{Relation, ShutdownHandle, VariableMap};
 
 use crate::domain::{AsSingletonDomain, Domain};
 
@@ -66,6 +66,7 @@ impl<A: AsAid, P: Implementable<A = A>> PullLevel<A, P> {
         nested: &mut Iterative<'b, S, u64>,
         domain: &mut Domain<P::A, S::Timestamp>,
         local_arrangements: &VariableMap<P::A, Iterative<'b, S, u64>>,
+        arrangements: &mut ArrangementMap<Iterative<'b, S, u64>>,
     ) -> (
         HashMap<PathId<P::A>, Stream<S, ((Value, Value), S::Timestamp, isize)>>,
         ShutdownHandle,
@@ -81,7 +82,9 @@ impl<A: AsAid, P: Implementable<A = A>> PullLevel<A, P> {
 
         assert_eq!(self.pull_attributes.is_empty(), false);
 
-        let (input, mut shutdown_handle) = self.plan.implement(nested, domain, local_arrangements);
+        let (input, mut shutdown_handle) =
+            self.plan
+                .implement(nested, domain, local_arrangements, arrangements);
 
         // Arrange input entities by eid.
         let e_offset = input
@@ -172,6 +175,7 @@ impl<A: AsAid> PullAll<A> {
         nested: &mut Iterative<'b, S, u64>,
         domain: &mut Domain<A, S::Timestamp>,
         _local_arrangements: &VariableMap<A, Iterative<'b, S, u64>>,
+        _arrangements: &mut ArrangementMap<Iterative<'b, S, u64>>,
     ) -> (
         HashMap<PathId<A>, Stream<S, ((Value, Value), S::Timestamp, isize)>>,
         ShutdownHandle,
@@ -248,6 +252,7 @@ impl<A: AsAid> Pull<A> {
         nested: &mut Iterative<'b, S, u64>,
         domain: &mut Domain<A, S::Timestamp>,
         local_arrangements: &VariableMap<A, Iterative<'b, S, u64>>,
+        arrangements: &mut ArrangementMap<Iterative<'b, S, u64>>,
     ) -> (
         HashMap<PathId<A>, Stream<S, ((Value, Value), S::Timestamp, isize)>>,
         ShutdownHandle,
@@ -257,8 +262,10 @@ impl<A: AsAid> Pull<A> {
         S::Timestamp: Timestamp + Lattice + Rewind,
     {
         match self {
-            Pull::All(ref pull) => pull.implement(nested, domain, local_arrangements),
-            Pull::Level(ref pull) => pull.implement(nested, domain, local_arrangements),
+            Pull::All(ref pull) => pull.implement(nested, domain, local_arrangements, arrangements),
+            Pull::Level(ref pull) => {
+                pull.implement(nested, domain, local_arrangements, arrangements)
+            }
         }
     }
 }
@@ -297,7 +304,9 @@ impl<A: AsAid + From<String>> GraphQl<A> {
         let ast = parse_query(&query).expect("graphQL ast parsing failed");
         let paths = ast.into_paths(Hector {
             variables: root_plan.variables(),
-            bindings: root_plan.into_bindings(),
+            bindings: root_plan
+                .into_bindings()
+                .expect("root plan can't be implemented via Hector"),
         });
 
         GraphQl {
@@ -0,0 +1,66 @@
+//! Tokenization shared by full-text indices and the searches run
+//! against them.
+
+use crate::Value;
+
+/// Splits text into lowercase alphanumeric tokens, each returned
+/// once, in order of first appearance.
+pub fn tokenize(text: &str) -> Vec<String> {
+    let mut tokens: Vec<String> = Vec::new();
+
+    for token in text.split(|c: char| !c.is_alphanumeric()) {
+        if !token.is_empty() {
+            let token = token.to_lowercase();
+            if !tokens.contains(&token) {
+                tokens.push(token);
+            }
+        }
+    }
+
+    tokens
+}
+
+/// Returns the (token, e) pairs making up a datom's entries in a
+/// full-text index. Only string values are indexed.
+pub fn index_entries(e: Value, v: Value) -> Vec<(Value, Value)> {
+    match v {
+        Value::String(text) => tokenize(&text)
+            .into_iter()
+            .map(|token| (Value::String(token), e.clone()))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// A single term of a search query.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Term {
+    /// Matches the token exactly.
+    Token(String),
+    /// Matches all tokens starting with the prefix.
+    Prefix(String),
+}
+
+/// Parses a search query into its terms. Terms ending in `*` match
+/// by prefix, all others match whole tokens.
+pub fn terms(query: &str) -> Vec<Term> {
+    let mut terms = Vec::new();
+
+    for word in query.split_whitespace() {
+        let is_prefix = word.ends_with('*');
+        for token in tokenize(word) {
+            terms.push(Term::Token(token));
+        }
+
+        // Only the last token of a word can be a prefix, e.g. in
+        // "dipper's*".
+        if is_prefix {
+            if let Some(Term::Token(token)) = terms.pop() {
+                terms.push(Term::Prefix(token));
+            }
+        }
+    }
+
+    terms.dedup();
+    terms
+}
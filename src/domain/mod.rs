@@ -1,7 +1,7 @@
 //! Logic for working with attributes under a shared timestamp
 //! semantics.
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::ops::{Add, AddAssign};
 
 use timely::dataflow::operators::unordered_input::{ActivateCapability, UnorderedHandle};
@@ -12,13 +12,16 @@ use timely::progress::Timestamp;
 
 use differential_dataflow::lattice::Lattice;
 use differential_dataflow::operators::arrange::Arrange;
-use differential_dataflow::trace::TraceReader;
+use differential_dataflow::trace::{BatchReader, Cursor, TraceReader};
 use differential_dataflow::{AsCollection, Collection};
 
-use crate::{AsAid, Datom, Error, Rewind, Rule, Value};
-use crate::{AttributeConfig, QuerySupport};
+use crate::binding::BinaryPredicate;
+use crate::{AsAid, Datom, Error, Rewind, Rule, TxError, Value};
+use crate::{AttributeConfig, IndexDirection, QuerySupport};
 use crate::{ShutdownHandle, TraceKeyHandle, TraceValHandle};
 
+pub mod fulltext;
+pub mod spatial;
 mod unordered_session;
 use unordered_session::UnorderedSession;
 
@@ -57,6 +60,9 @@ where
     last_advance: Vec<T>,
     /// Input handles to attributes in this domain.
     input_sessions: HashMap<A, UnorderedSession<T, (Value, Value), isize>>,
+    /// Inputs closed by draining the domain. Their contents are
+    /// still exported as those of transactable attributes.
+    drained_inputs: HashSet<A>,
     /// The probe keeping track of source progress in this domain.
     domain_probe: ProbeHandle<T>,
     /// Maintaining the number of probed sources allows us to
@@ -65,6 +71,18 @@ where
     probed_source_count: usize,
     /// Configurations for attributes in this domain.
     pub attributes: HashMap<A, AttributeConfig>,
+    /// Alternative names, mapping to the attribute they refer to.
+    aliases: HashMap<A, A>,
+    /// Attributes with excised data, along with the time at which it
+    /// was retracted.
+    excisions: Vec<(A, T)>,
+    /// Excisions waiting for the data transacted before them to be
+    /// indexed completely.
+    pending_excisions: Vec<Excision<A, T>>,
+    /// Net number of datoms transacted into each attribute. These are
+    /// tallied before transactions are partitioned, s.t. all workers
+    /// agree on them.
+    datom_counts: HashMap<A, isize>,
     /// Forward count traces.
     pub forward_count: HashMap<A, TraceKeyHandle<Value, T, isize>>,
     /// Forward propose traces.
@@ -77,6 +95,10 @@ where
     pub reverse_propose: HashMap<A, TraceValHandle<Value, Value, T, isize>>,
     /// Reverse validate traces.
     pub reverse_validate: HashMap<A, TraceKeyHandle<(Value, Value), T, isize>>,
+    /// Full-text traces, from tokens to the entities holding them.
+    pub fulltext: HashMap<A, TraceValHandle<Value, Value, T, isize>>,
+    /// Spatial traces, from grid cells to the (e, v) pairs inside them.
+    pub spatial: HashMap<A, TraceValHandle<Value, (Value, Value), T, isize>>,
     /// Representation of named rules.
     pub rules: HashMap<A, Rule<A>>,
     /// Mapping from query names to their shutdown handles.
@@ -118,6 +140,11 @@ where
         }
 
         self.attributes.extend(other.attributes.into_iter());
+        self.aliases.extend(other.aliases.into_iter());
+        self.excisions.extend(other.excisions.into_iter());
+        self.pending_excisions
+            .extend(other.pending_excisions.into_iter());
+        self.datom_counts.extend(other.datom_counts.into_iter());
 
         self.forward_count.extend(other.forward_count.into_iter());
         self.forward_propose
@@ -131,6 +158,9 @@ where
         self.reverse_validate
             .extend(other.reverse_validate.into_iter());
 
+        self.fulltext.extend(other.fulltext.into_iter());
+        self.spatial.extend(other.spatial.into_iter());
+
         self.rules.extend(other.rules.into_iter());
 
         self.shutdown_handles
@@ -164,15 +194,22 @@ where
             now_at: start_at,
             last_advance: vec![<T as Lattice>::minimum()],
             input_sessions: HashMap::new(),
+            drained_inputs: HashSet::new(),
             domain_probe: ProbeHandle::new(),
             probed_source_count: 0,
             attributes: HashMap::new(),
+            aliases: HashMap::new(),
+            excisions: Vec::new(),
+            pending_excisions: Vec::new(),
+            datom_counts: HashMap::new(),
             forward_count: HashMap::new(),
             forward_propose: HashMap::new(),
             forward_validate: HashMap::new(),
             reverse_count: HashMap::new(),
             reverse_propose: HashMap::new(),
             reverse_validate: HashMap::new(),
+            fulltext: HashMap::new(),
+            spatial: HashMap::new(),
             rules: HashMap::new(),
             shutdown_handles: HashMap::new(),
         }
@@ -185,26 +222,73 @@ where
             now_at: base.now_at.clone(),
             last_advance: base.last_advance.clone(),
             input_sessions: HashMap::new(),
+            drained_inputs: HashSet::new(),
             domain_probe: ProbeHandle::new(),
             probed_source_count: 0,
             attributes: HashMap::new(),
+            aliases: HashMap::new(),
+            excisions: Vec::new(),
+            pending_excisions: Vec::new(),
+            datom_counts: HashMap::new(),
             forward_count: HashMap::new(),
             forward_propose: HashMap::new(),
             forward_validate: HashMap::new(),
             reverse_count: HashMap::new(),
             reverse_propose: HashMap::new(),
             reverse_validate: HashMap::new(),
+            fulltext: HashMap::new(),
+            spatial: HashMap::new(),
             rules: HashMap::new(),
             shutdown_handles: HashMap::new(),
         }
     }
 
-    /// Transact data into one or more inputs.
+    /// Transact data into one or more inputs. Lookup refs in entity
+    /// position are resolved first, failing the whole transaction if
+    /// any of them doesn't identify exactly one entity. Transactions
+    /// retracting from monotonic or append-only attributes are
+    /// rejected as a whole.
     pub fn transact(&mut self, tx_data: Vec<Datom<A>>) -> Result<(), Error> {
+        for Datom(_, a, _, _, diff) in tx_data.iter() {
+            let monotonic = self
+                .attribute_config(a)
+                .map(|config| config.is_monotonic())
+                .unwrap_or(false);
+
+            if *diff < 0 && monotonic {
+                return Err(TxError::AppendOnly(a.to_string()).into());
+            }
+        }
+
+        self.introduce(tx_data)
+    }
+
+    /// Retracts the data of a transaction that violated constraints.
+    /// Monotonic attributes accept these retractions, because the
+    /// additions they undo never became visible.
+    pub fn roll_back(&mut self, tx_data: Vec<Datom<A>>) -> Result<(), Error> {
+        let retractions = tx_data
+            .into_iter()
+            .map(|Datom(e, a, v, _t, diff)| Datom(e, a, v, None, -diff))
+            .collect();
+
+        self.introduce(retractions)
+    }
+
+    fn introduce(&mut self, mut tx_data: Vec<Datom<A>>) -> Result<(), Error> {
+        // Lookup refs are resolved in place, before anything is
+        // introduced, s.t. failing ones abort the whole transaction.
+        for datom in tx_data.iter_mut() {
+            if let Value::LookupRef(_, _) = datom.0 {
+                let e = std::mem::replace(&mut datom.0, Value::Bool(false));
+                datom.0 = self.resolve(e)?;
+            }
+        }
+
         for Datom(e, a, v, t, diff) in tx_data {
-            match self.input_sessions.get_mut(&a) {
+            match self.input_sessions.get_mut(canonical(&self.aliases, &a)) {
                 None => {
-                    return Err(Error::not_found(format!("Attribute {} does not exist.", a)));
+                    return Err(TxError::UnknownAttribute(a.to_string()).into());
                 }
                 Some(handle) => match t {
                     None => handle.update((e, v), diff),
@@ -216,9 +300,340 @@ where
         Ok(())
     }
 
+    /// Tallies the datoms of a transaction, before it is split up
+    /// between workers.
+    pub fn count_datoms(&mut self, tx_data: &[Datom<A>]) {
+        for Datom(_, a, _, _, diff) in tx_data.iter() {
+            let a = canonical(&self.aliases, a);
+
+            // Attributes are only cloned for their first datom.
+            match self.datom_counts.get_mut(a) {
+                Some(count) => *count += diff,
+                None => {
+                    self.datom_counts.insert(a.clone(), *diff);
+                }
+            }
+        }
+    }
+
+    /// Returns the net number of datoms transacted into the specified
+    /// attribute so far, across all workers. Data introduced by
+    /// sources isn't accounted for.
+    pub fn datom_count(&self, name: &A) -> isize {
+        self.datom_counts
+            .get(canonical(&self.aliases, name))
+            .cloned()
+            .unwrap_or(0)
+    }
+
+    /// Resolves a lookup ref to the entity holding the referenced
+    /// value, via the reverse index of its attribute. Only data that
+    /// has already been indexed by this worker is taken into account.
+    /// Other values are returned as they are.
+    pub fn resolve(&mut self, e: Value) -> Result<Value, Error> {
+        match e {
+            Value::LookupRef(a, v) => {
+                let a: A = a.into();
+                let trace = self
+                    .reverse_propose
+                    .get_mut(canonical(&self.aliases, &a))
+                    .ok_or_else(|| {
+                        TxError::UnresolvedLookup(format!("Attribute {} has no reverse index.", a))
+                    })?;
+
+                let mut eids = Vec::new();
+                let (mut cursor, storage) = trace.cursor();
+
+                cursor.seek_key(&storage, &v);
+                if cursor.get_key(&storage) == Some(&v) {
+                    while let Some(eid) = cursor.get_val(&storage) {
+                        let mut count = 0;
+                        cursor.map_times(&storage, |_t, diff| count += diff);
+
+                        if count > 0 {
+                            eids.push(eid.clone());
+                        }
+
+                        cursor.step_val(&storage);
+                    }
+                }
+
+                match eids.len() {
+                    0 => Err(TxError::UnresolvedLookup(format!(
+                        "No entity has {:?} for {}.",
+                        v, a
+                    ))
+                    .into()),
+                    1 => Ok(eids.pop().unwrap()),
+                    _ => Err(TxError::AmbiguousLookup(format!(
+                        "Several entities have {:?} for {}.",
+                        v, a
+                    ))
+                    .into()),
+                }
+            }
+            e => Ok(e),
+        }
+    }
+
+    /// Retracts all datoms matching the specified entity, attribute,
+    /// and value constraints, and makes sure that they are compacted
+    /// away from all indices, rather than only being retracted
+    /// logically. Without a specific attribute, all transactable
+    /// attributes are considered. Lookup refs have to be resolved
+    /// beforehand. Each worker excises the data it holds. Whatever
+    /// has been indexed already is retracted right away, anything
+    /// else transacted up to and including the current epoch once it
+    /// has been indexed, see `advance_epoch`. Returns the number of
+    /// datoms excised on this worker right away.
+    pub fn excise(
+        &mut self,
+        entity: Option<&Value>,
+        attribute: Option<&A>,
+        value: Option<&(BinaryPredicate, Value)>,
+    ) -> Result<usize, Error> {
+        match entity {
+            None if attribute.is_none() => {
+                return Err(Error::incorrect(
+                    "Excisions require an entity, an attribute, or both.",
+                ));
+            }
+            Some(Value::LookupRef(_, _)) => {
+                return Err(Error::incorrect(
+                    "Lookup refs have to be resolved before excising.",
+                ));
+            }
+            _ => {}
+        }
+
+        let attributes: Vec<A> = match attribute {
+            None => self.input_sessions.keys().cloned().collect(),
+            Some(a) => {
+                let a = canonical(&self.aliases, a).clone();
+
+                if !self.attributes.contains_key(&a) {
+                    return Err(Error::not_found(format!("Attribute {} does not exist.", a)));
+                } else if !self.input_sessions.contains_key(&a) {
+                    return Err(Error::unsupported(format!(
+                        "Attribute {} is sourced externally and can't be excised.",
+                        a
+                    )));
+                }
+
+                vec![a]
+            }
+        };
+
+        let excision = Excision {
+            entity: entity.cloned(),
+            attributes,
+            value: value.cloned(),
+            at: self.now_at.clone(),
+        };
+
+        let excised = self.retract_excised(&excision, None);
+        self.pending_excisions.push(excision);
+
+        Ok(excised)
+    }
+
+    /// Retracts the indexed datoms matching an excision, as far as
+    /// they happened at or before the specified time, if any.
+    fn retract_excised(&mut self, excision: &Excision<A, T>, until: Option<&T>) -> usize {
+        let mut excised = 0;
+
+        for aid in excision.attributes.iter() {
+            let mut data = Vec::new();
+
+            if let Some(trace) = self.forward_propose.get_mut(aid) {
+                let (mut cursor, storage) = trace.cursor();
+
+                if let Some(ref e) = excision.entity {
+                    cursor.seek_key(&storage, e);
+                }
+
+                while let Some(e) = cursor.get_key(&storage) {
+                    if excision.entity.as_ref().map(|x| x != e).unwrap_or(false) {
+                        break;
+                    }
+
+                    while let Some(v) = cursor.get_val(&storage) {
+                        let matches = excision
+                            .value
+                            .as_ref()
+                            .map(|&(ref predicate, ref constant)| predicate.holds(v, constant))
+                            .unwrap_or(true);
+
+                        if matches {
+                            let mut count = 0;
+                            cursor.map_times(&storage, |t, diff| {
+                                if until.map(|until| t.less_equal(until)).unwrap_or(true) {
+                                    count += diff;
+                                }
+                            });
+
+                            if count > 0 {
+                                data.push(((e.clone(), v.clone()), count));
+                            }
+                        }
+
+                        cursor.step_val(&storage);
+                    }
+
+                    cursor.step_key(&storage);
+                }
+            }
+
+            if data.is_empty() {
+                continue;
+            }
+
+            // Attributes might have been migrated in the meantime.
+            if let Some(handle) = self.input_sessions.get_mut(aid) {
+                for (datum, count) in data {
+                    handle.update(datum, -count);
+                    excised += 1;
+                }
+
+                self.excisions.push((aid.clone(), self.now_at.clone()));
+            }
+        }
+
+        excised
+    }
+
+    /// Completes all pending excisions, whose data has been indexed
+    /// by now. Returns the number of datoms excised.
+    fn complete_excisions(&mut self) -> usize {
+        let mut excised = 0;
+
+        let pending = std::mem::replace(&mut self.pending_excisions, Vec::new());
+        for excision in pending {
+            let is_indexed = excision.attributes.iter().all(|aid| {
+                self.forward_propose
+                    .get_mut(aid)
+                    .map(|trace| !AntichainRef::new(&trace_upper(trace)).less_equal(&excision.at))
+                    .unwrap_or(true)
+            });
+
+            if is_indexed {
+                // Earlier retractions happened at the time of the
+                // excision and are accounted for.
+                excised += self.retract_excised(&excision, Some(&excision.at));
+            } else {
+                self.pending_excisions.push(excision);
+            }
+        }
+
+        excised
+    }
+
+    /// Introduces updates to an attribute at their original times,
+    /// e.g. as exported from another differential computation. All
+    /// times must be at or beyond the current epoch. Nothing is
+    /// applied if any of them isn't.
+    pub fn ingest(
+        &mut self,
+        name: &A,
+        updates: Vec<((Value, Value), T, isize)>,
+    ) -> Result<(), Error> {
+        if let Some((_, t, _)) = updates.iter().find(|(_, t, _)| !self.now_at.less_equal(t)) {
+            return Err(Error::conflict(format!(
+                "Domain is at {:?}, you attempted to ingest updates at {:?}.",
+                &self.now_at, t
+            )));
+        }
+
+        match self.input_sessions.get_mut(canonical(&self.aliases, name)) {
+            None => Err(Error::not_found(format!(
+                "Attribute {} does not exist.",
+                name
+            ))),
+            Some(handle) => {
+                for (datum, t, diff) in updates {
+                    handle.update_at(datum, t, diff);
+                }
+
+                Ok(())
+            }
+        }
+    }
+
+    /// Returns the contents of all transactable attributes, as far
+    /// as they have been indexed by this worker, with their history
+    /// collapsed into a single addition per datom.
+    pub fn contents(&mut self) -> Vec<Datom<A>> {
+        let mut names: Vec<A> = self
+            .input_sessions
+            .keys()
+            .chain(self.drained_inputs.iter())
+            .cloned()
+            .collect();
+        names.sort();
+
+        let mut contents = Vec::new();
+
+        for aid in names {
+            if let Ok(datoms) = self.contents_of(&aid) {
+                contents.extend(datoms);
+            }
+        }
+
+        contents
+    }
+
+    /// Returns the contents of the specified attribute, like
+    /// `contents`. Fails for attributes without a forward index.
+    pub fn contents_of(&mut self, name: &A) -> Result<Vec<Datom<A>>, Error> {
+        let aid = canonical(&self.aliases, name).clone();
+
+        let trace = match self.forward_propose.get_mut(&aid) {
+            None => {
+                return Err(Error::not_found(format!(
+                    "Attribute {} has no forward index.",
+                    name
+                )));
+            }
+            Some(trace) => trace,
+        };
+
+        let mut contents = Vec::new();
+        let (mut cursor, storage) = trace.cursor();
+
+        while let Some(e) = cursor.get_key(&storage) {
+            while let Some(v) = cursor.get_val(&storage) {
+                let mut count = 0;
+                cursor.map_times(&storage, |_t, diff| count += diff);
+
+                if count > 0 {
+                    contents.push(Datom(e.clone(), aid.clone(), v.clone(), None, count));
+                }
+
+                cursor.step_val(&storage);
+            }
+
+            cursor.step_key(&storage);
+        }
+
+        Ok(contents)
+    }
+
+    /// Returns the name of the attribute the specified name refers
+    /// to, which differs from it for aliases.
+    pub fn canonical_name<'a>(&'a self, name: &'a A) -> &'a A {
+        canonical(&self.aliases, name)
+    }
+
+    /// Returns true iff the specified attribute is fed by
+    /// transactions, rather than by a source or a rule.
+    pub fn is_transactable(&self, name: &A) -> bool {
+        self.input_sessions
+            .contains_key(canonical(&self.aliases, name))
+    }
+
     /// Closes and drops an existing input.
     pub fn close_input(&mut self, name: A) -> Result<(), Error> {
-        match self.input_sessions.remove(&name) {
+        match self.input_sessions.remove(canonical(&self.aliases, &name)) {
             None => Err(Error::not_found(format!("Input {} does not exist.", name))),
             Some(handle) => {
                 handle.close();
@@ -227,6 +642,32 @@ where
         }
     }
 
+    /// Closes all input handles, s.t. no further transactions are
+    /// accepted.
+    pub fn close_inputs(&mut self) {
+        for (name, handle) in self.input_sessions.drain() {
+            handle.close();
+            self.drained_inputs.insert(name);
+        }
+    }
+
+    /// Returns true iff the specified attribute was fed by
+    /// transactions up until the domain was drained.
+    pub fn is_drained(&self, name: &A) -> bool {
+        self.drained_inputs.contains(canonical(&self.aliases, name))
+    }
+
+    /// Reports the frontier of all domain inputs, i.e. the times at
+    /// which new inputs might still arrive.
+    pub fn frontier(&self) -> Vec<T> {
+        if self.probed_source_count() == 0 {
+            vec![self.epoch().clone()]
+        } else {
+            self.domain_probe()
+                .with_frontier(|frontier| frontier.to_vec())
+        }
+    }
+
     /// Advances the domain to the current domain frontier, thus
     /// allowing traces to compact. All domain input handles are
     /// forwarded up to the frontier, so as not to stall progress.
@@ -273,6 +714,11 @@ where
         } else if !self.now_at.eq(&next) {
             trace!("Advancing domain epoch to {:?} ", next);
 
+            let excised = self.complete_excisions();
+            if excised > 0 {
+                info!("Excised {} more datoms", excised);
+            }
+
             for handle in self.input_sessions.values_mut() {
                 handle.advance_to(next.clone());
                 handle.flush();
@@ -285,6 +731,50 @@ where
         }
     }
 
+    /// Allows all traces of the specified attribute to be compacted
+    /// up to the specified frontier.
+    fn compact_traces(&mut self, aid: &A, frontier: &[T]) {
+        if let Some(trace) = self.forward_count.get_mut(aid) {
+            trace.advance_by(frontier);
+            trace.distinguish_since(frontier);
+        }
+
+        if let Some(trace) = self.forward_propose.get_mut(aid) {
+            trace.advance_by(frontier);
+            trace.distinguish_since(frontier);
+        }
+
+        if let Some(trace) = self.forward_validate.get_mut(aid) {
+            trace.advance_by(frontier);
+            trace.distinguish_since(frontier);
+        }
+
+        if let Some(trace) = self.reverse_count.get_mut(aid) {
+            trace.advance_by(frontier);
+            trace.distinguish_since(frontier);
+        }
+
+        if let Some(trace) = self.reverse_propose.get_mut(aid) {
+            trace.advance_by(frontier);
+            trace.distinguish_since(frontier);
+        }
+
+        if let Some(trace) = self.reverse_validate.get_mut(aid) {
+            trace.advance_by(frontier);
+            trace.distinguish_since(frontier);
+        }
+
+        if let Some(trace) = self.fulltext.get_mut(aid) {
+            trace.advance_by(frontier);
+            trace.distinguish_since(frontier);
+        }
+
+        if let Some(trace) = self.spatial.get_mut(aid) {
+            trace.advance_by(frontier);
+            trace.distinguish_since(frontier);
+        }
+    }
+
     /// Advances domain traces up to the specified frontier minus
     /// their configured slack.
     pub fn advance_traces(&mut self, frontier: &[T]) -> Result<(), Error> {
@@ -297,41 +787,83 @@ where
             let frontier = AntichainRef::new(frontier);
 
             for (aid, config) in self.attributes.iter() {
-                if let Some(ref trace_slack) = config.trace_slack {
-                    let slacking_frontier = frontier
+                // Pending excisions count what happened up to their
+                // time, which compaction would obscure.
+                if self
+                    .pending_excisions
+                    .iter()
+                    .any(|excision| excision.attributes.contains(aid))
+                {
+                    continue;
+                }
+
+                let slacking_frontier = match config.trace_slack {
+                    Some(ref trace_slack) => frontier
                         .iter()
                         .map(|t| t.rewind(trace_slack.clone().into()))
-                        .collect::<Vec<T>>();;
+                        .collect::<Vec<T>>(),
+                    // Holding on to the history of append-only
+                    // attributes buys little, as every earlier state
+                    // is contained in the current one.
+                    None if config.append_only => frontier.to_vec(),
+                    None => continue,
+                };
+
+                if let Some(trace) = self.forward_count.get_mut(aid) {
+                    trace.advance_by(&slacking_frontier);
+                    trace.distinguish_since(&slacking_frontier);
+                }
 
-                    if let Some(trace) = self.forward_count.get_mut(aid) {
-                        trace.advance_by(&slacking_frontier);
-                        trace.distinguish_since(&slacking_frontier);
-                    }
+                if let Some(trace) = self.forward_propose.get_mut(aid) {
+                    trace.advance_by(&slacking_frontier);
+                    trace.distinguish_since(&slacking_frontier);
+                }
 
-                    if let Some(trace) = self.forward_propose.get_mut(aid) {
-                        trace.advance_by(&slacking_frontier);
-                        trace.distinguish_since(&slacking_frontier);
-                    }
+                if let Some(trace) = self.forward_validate.get_mut(aid) {
+                    trace.advance_by(&slacking_frontier);
+                    trace.distinguish_since(&slacking_frontier);
+                }
 
-                    if let Some(trace) = self.forward_validate.get_mut(aid) {
-                        trace.advance_by(&slacking_frontier);
-                        trace.distinguish_since(&slacking_frontier);
-                    }
+                if let Some(trace) = self.reverse_count.get_mut(aid) {
+                    trace.advance_by(&slacking_frontier);
+                    trace.distinguish_since(&slacking_frontier);
+                }
 
-                    if let Some(trace) = self.reverse_count.get_mut(aid) {
-                        trace.advance_by(&slacking_frontier);
-                        trace.distinguish_since(&slacking_frontier);
-                    }
+                if let Some(trace) = self.reverse_propose.get_mut(aid) {
+                    trace.advance_by(&slacking_frontier);
+                    trace.distinguish_since(&slacking_frontier);
+                }
 
-                    if let Some(trace) = self.reverse_propose.get_mut(aid) {
-                        trace.advance_by(&slacking_frontier);
-                        trace.distinguish_since(&slacking_frontier);
-                    }
+                if let Some(trace) = self.reverse_validate.get_mut(aid) {
+                    trace.advance_by(&slacking_frontier);
+                    trace.distinguish_since(&slacking_frontier);
+                }
 
-                    if let Some(trace) = self.reverse_validate.get_mut(aid) {
-                        trace.advance_by(&slacking_frontier);
-                        trace.distinguish_since(&slacking_frontier);
-                    }
+                if let Some(trace) = self.fulltext.get_mut(aid) {
+                    trace.advance_by(&slacking_frontier);
+                    trace.distinguish_since(&slacking_frontier);
+                }
+
+                if let Some(trace) = self.spatial.get_mut(aid) {
+                    trace.advance_by(&slacking_frontier);
+                    trace.distinguish_since(&slacking_frontier);
+                }
+            }
+
+            // Excised data only disappears once traces have been
+            // compacted past its retraction. Attributes without slack
+            // aren't compacted otherwise, so we do it just for that.
+            let excisions = std::mem::replace(&mut self.excisions, Vec::new());
+            for (aid, t) in excisions {
+                if frontier.less_equal(&t) {
+                    self.excisions.push((aid, t));
+                } else if self
+                    .attributes
+                    .get(&aid)
+                    .map(|config| config.trace_slack.is_none())
+                    .unwrap_or(false)
+                {
+                    self.compact_traces(&aid, &frontier);
                 }
             }
         }
@@ -379,12 +911,103 @@ where
 
     /// Checks whether an attribute of that name exists.
     pub fn has_attribute(&self, name: &A) -> bool {
-        self.attributes.contains_key(name)
+        self.attributes.contains_key(canonical(&self.aliases, name))
+    }
+
+    /// Returns the configuration of the attribute of that name.
+    pub fn attribute_config(&self, name: &A) -> Option<&AttributeConfig> {
+        self.attributes.get(canonical(&self.aliases, name))
+    }
+
+    /// Makes an existing attribute available under another name as
+    /// well. Both names refer to the same inputs and indices.
+    pub fn alias(&mut self, alias: A, name: A) -> Result<(), Error> {
+        let name = canonical(&self.aliases, &name).clone();
+
+        if !self.attributes.contains_key(&name) {
+            Err(Error::not_found(format!(
+                "Attribute {} does not exist.",
+                name
+            )))
+        } else if self.has_attribute(&alias) {
+            Err(Error::conflict(format!(
+                "Attribute {} already exists.",
+                alias
+            )))
+        } else {
+            self.aliases.insert(alias, name);
+            Ok(())
+        }
+    }
+
+    /// Retires an attribute in favour of another, previously created
+    /// one and leaves the old name as an alias of the new one. The
+    /// old input is closed and its indices are dropped, s.t.
+    /// dataflows implemented on top of them keep their data, but
+    /// don't see any changes made afterwards. Carrying the data over
+    /// is up to the caller, see `Server::migrate_attribute`.
+    pub fn migrate(&mut self, from: &A, to: &A) -> Result<(), Error> {
+        let from = canonical(&self.aliases, from).clone();
+
+        if from == *to {
+            return Err(Error::incorrect(format!(
+                "Can't migrate {} onto itself.",
+                to
+            )));
+        } else if !self.attributes.contains_key(&from) {
+            return Err(Error::not_found(format!(
+                "Attribute {} does not exist.",
+                from
+            )));
+        } else if !self.input_sessions.contains_key(to) {
+            return Err(Error::not_found(format!(
+                "Attribute {} is not transactable.",
+                to
+            )));
+        }
+
+        if let Some(handle) = self.input_sessions.remove(&from) {
+            handle.close();
+        }
+
+        self.attributes.remove(&from);
+        self.forward_count.remove(&from);
+        self.forward_propose.remove(&from);
+        self.forward_validate.remove(&from);
+        self.reverse_count.remove(&from);
+        self.reverse_propose.remove(&from);
+        self.reverse_validate.remove(&from);
+        self.fulltext.remove(&from);
+        self.spatial.remove(&from);
+
+        // Existing aliases of the old name move along.
+        for name in self.aliases.values_mut() {
+            if *name == from {
+                *name = to.clone();
+            }
+        }
+
+        self.aliases.insert(from, to.clone());
+
+        Ok(())
+    }
+
+    /// Retrieves the full-text trace for the specified aid.
+    pub fn fulltext(&mut self, name: &A) -> Option<&mut TraceValHandle<Value, Value, T, isize>> {
+        self.fulltext.get_mut(canonical(&self.aliases, name))
+    }
+
+    /// Retrieves the spatial trace for the specified aid.
+    pub fn spatial(
+        &mut self,
+        name: &A,
+    ) -> Option<&mut TraceValHandle<Value, (Value, Value), T, isize>> {
+        self.spatial.get_mut(canonical(&self.aliases, name))
     }
 
     /// Retrieves the forward count trace for the specified aid.
     pub fn forward_count(&mut self, name: &A) -> Option<&mut TraceKeyHandle<Value, T, isize>> {
-        self.forward_count.get_mut(name)
+        self.forward_count.get_mut(canonical(&self.aliases, name))
     }
 
     /// Retrieves the forward propose trace for the specified aid.
@@ -392,7 +1015,7 @@ where
         &mut self,
         name: &A,
     ) -> Option<&mut TraceValHandle<Value, Value, T, isize>> {
-        self.forward_propose.get_mut(name)
+        self.forward_propose.get_mut(canonical(&self.aliases, name))
     }
 
     /// Retrieves the forward validate trace for the specified aid.
@@ -400,12 +1023,13 @@ where
         &mut self,
         name: &A,
     ) -> Option<&mut TraceKeyHandle<(Value, Value), T, isize>> {
-        self.forward_validate.get_mut(name)
+        self.forward_validate
+            .get_mut(canonical(&self.aliases, name))
     }
 
     /// Retrieves the reverse count trace for the specified aid.
     pub fn reverse_count(&mut self, name: &A) -> Option<&mut TraceKeyHandle<Value, T, isize>> {
-        self.reverse_count.get_mut(name)
+        self.reverse_count.get_mut(canonical(&self.aliases, name))
     }
 
     /// Retrieves the reverse propose trace for the specified aid.
@@ -413,7 +1037,7 @@ where
         &mut self,
         name: &A,
     ) -> Option<&mut TraceValHandle<Value, Value, T, isize>> {
-        self.reverse_propose.get_mut(name)
+        self.reverse_propose.get_mut(canonical(&self.aliases, name))
     }
 
     /// Retrieves the reverse validate trace for the specified aid.
@@ -421,8 +1045,201 @@ where
         &mut self,
         name: &A,
     ) -> Option<&mut TraceKeyHandle<(Value, Value), T, isize>> {
-        self.reverse_validate.get_mut(name)
+        self.reverse_validate
+            .get_mut(canonical(&self.aliases, name))
+    }
+
+    /// Reports the sizes of all indices maintained for the specified
+    /// aid, keyed by index name.
+    pub fn index_stats(&mut self, name: &A) -> BTreeMap<String, TraceStats> {
+        let mut stats = BTreeMap::new();
+
+        if let Some(trace) = self.forward_count.get_mut(name) {
+            stats.insert("forward_count".to_string(), trace_stats(trace));
+        }
+        if let Some(trace) = self.forward_propose.get_mut(name) {
+            stats.insert("forward_propose".to_string(), trace_stats(trace));
+        }
+        if let Some(trace) = self.forward_validate.get_mut(name) {
+            stats.insert("forward_validate".to_string(), trace_stats(trace));
+        }
+        if let Some(trace) = self.reverse_count.get_mut(name) {
+            stats.insert("reverse_count".to_string(), trace_stats(trace));
+        }
+        if let Some(trace) = self.reverse_propose.get_mut(name) {
+            stats.insert("reverse_propose".to_string(), trace_stats(trace));
+        }
+        if let Some(trace) = self.reverse_validate.get_mut(name) {
+            stats.insert("reverse_validate".to_string(), trace_stats(trace));
+        }
+        if let Some(trace) = self.fulltext.get_mut(name) {
+            stats.insert("fulltext".to_string(), trace_stats(trace));
+        }
+        if let Some(trace) = self.spatial.get_mut(name) {
+            stats.insert("spatial".to_string(), trace_stats(trace));
+        }
+
+        stats
     }
+
+    /// Ensures the full set of count, propose, and validate indices
+    /// is maintained for the specified aid, in both directions, as is
+    /// required by worst-case optimal joins. Missing indices are
+    /// derived from the forward propose trace once, in the given
+    /// scope, and then shared by all queries.
+    pub fn complete_indices<S>(&mut self, scope: &mut S, name: &A) -> Result<(), Error>
+    where
+        S: Scope<Timestamp = T>,
+    {
+        let aid = canonical(&self.aliases, name).clone();
+
+        let complete = self.forward_count.contains_key(&aid)
+            && self.forward_validate.contains_key(&aid)
+            && self.reverse_count.contains_key(&aid)
+            && self.reverse_propose.contains_key(&aid)
+            && self.reverse_validate.contains_key(&aid);
+
+        if complete {
+            return Ok(());
+        }
+
+        let (raw, shutdown_button) = match self.forward_propose.get_mut(&aid) {
+            None => {
+                return Err(Error::not_found(format!(
+                    "Attribute {} does not exist.",
+                    aid
+                )));
+            }
+            Some(trace) => {
+                let (arranged, shutdown_button) =
+                    trace.import_frontier(scope, &format!("Propose({})", aid));
+
+                (
+                    arranged.as_collection(|e, v| (e.clone(), v.clone())),
+                    shutdown_button,
+                )
+            }
+        };
+
+        if !self.forward_count.contains_key(&aid) {
+            self.forward_count.insert(
+                aid.clone(),
+                raw.map(|(k, _v)| (k, ()))
+                    .arrange_named(&format!("->Count({})", aid))
+                    .trace,
+            );
+        }
+
+        if !self.forward_validate.contains_key(&aid) {
+            self.forward_validate.insert(
+                aid.clone(),
+                raw.map(|t| (t, ()))
+                    .arrange_named(&format!("->Validate({})", aid))
+                    .trace,
+            );
+        }
+
+        if !self.reverse_count.contains_key(&aid) {
+            self.reverse_count.insert(
+                aid.clone(),
+                raw.map(|(_e, v)| (v, ()))
+                    .arrange_named(&format!("->_Count({})", aid))
+                    .trace,
+            );
+        }
+
+        if !self.reverse_propose.contains_key(&aid) {
+            self.reverse_propose.insert(
+                aid.clone(),
+                raw.map(|(e, v)| (v, e))
+                    .arrange_named(&format!("->_Propose({})", aid))
+                    .trace,
+            );
+        }
+
+        if !self.reverse_validate.contains_key(&aid) {
+            self.reverse_validate.insert(
+                aid.clone(),
+                raw.map(|pair| (pair, ()))
+                    .arrange_named(&format!("->_Validate({})", aid))
+                    .trace,
+            );
+        }
+
+        if let Some(config) = self.attributes.get_mut(&aid) {
+            config.query_support = QuerySupport::AdaptiveWCO;
+            config.index_direction = IndexDirection::Both;
+        }
+
+        self.shutdown_handles.insert(
+            format!("Indices({})", aid),
+            ShutdownHandle::from_button(shutdown_button),
+        );
+
+        Ok(())
+    }
+}
+
+/// Resolves aliases to the name of the attribute they refer to.
+fn canonical<'a, A: AsAid>(aliases: &'a HashMap<A, A>, name: &'a A) -> &'a A {
+    aliases.get(name).unwrap_or(name)
+}
+
+/// An excision, as far as it concerns a single domain.
+struct Excision<A, T> {
+    entity: Option<Value>,
+    attributes: Vec<A>,
+    value: Option<(BinaryPredicate, Value)>,
+    /// The epoch the excision was requested in.
+    at: T,
+}
+
+/// Returns the frontier up to which a trace is complete.
+fn trace_upper<Tr>(trace: &mut Tr) -> Vec<Tr::Time>
+where
+    Tr: TraceReader,
+    Tr::Batch: BatchReader<Tr::Key, Tr::Val, Tr::Time, Tr::R>,
+    Tr::Time: Lattice,
+{
+    let mut upper = vec![<Tr::Time as Lattice>::minimum()];
+
+    trace.map_batches(|batch| upper = batch.upper().to_vec());
+
+    upper
+}
+
+/// Size information on a single trace.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TraceStats {
+    /// Number of batches currently making up the trace.
+    pub batches: usize,
+    /// Number of updates across all batches. Batches are not
+    /// consolidated with one another, so for the propose traces this
+    /// is an upper bound on the number of datoms.
+    pub updates: usize,
+    /// Estimated memory held by all updates, in bytes. Heap
+    /// allocations of individual values, e.g. strings, are not
+    /// accounted for.
+    #[serde(default)]
+    pub bytes: usize,
+}
+
+pub(crate) fn trace_stats<Tr>(trace: &mut Tr) -> TraceStats
+where
+    Tr: TraceReader,
+    Tr::Batch: BatchReader<Tr::Key, Tr::Val, Tr::Time, Tr::R>,
+{
+    let mut stats = TraceStats::default();
+
+    let update_size = std::mem::size_of::<(Tr::Key, Tr::Val, Tr::Time, Tr::R)>();
+
+    trace.map_batches(|batch| {
+        stats.batches += 1;
+        stats.updates += batch.len();
+        stats.bytes += batch.len() * update_size;
+    });
+
+    stats
 }
 
 /// A domain that is still under construction in a specific scope.
@@ -533,6 +1350,36 @@ where
 
         self
     }
+
+    /// Installs full-text indices for all attributes in the domain.
+    pub fn with_fulltext_indices(mut self) -> Self {
+        for aid in self.raw.keys() {
+            self.domain.fulltext.insert(
+                aid.clone(),
+                self.raw[aid]
+                    .flat_map(|(e, v)| fulltext::index_entries(e, v))
+                    .arrange_named(&format!("->Fulltext({})", aid))
+                    .trace,
+            );
+        }
+
+        self
+    }
+
+    /// Installs spatial indices for all attributes in the domain.
+    pub fn with_spatial_indices(mut self) -> Self {
+        for aid in self.raw.keys() {
+            self.domain.spatial.insert(
+                aid.clone(),
+                self.raw[aid]
+                    .flat_map(|(e, v)| spatial::index_entries(e, v))
+                    .arrange_named(&format!("->Spatial({})", aid))
+                    .trace,
+            );
+        }
+
+        self
+    }
 }
 
 impl<A, S> ScopedDomain<A, S>
@@ -0,0 +1,77 @@
+//! Grid cells backing spatial indices. Points are indexed by the cell
+//! they fall into, s.t. regions can be looked up via the cells
+//! covering them.
+
+use crate::Value;
+
+/// Coordinate units per degree of latitude or longitude.
+pub const UNITS_PER_DEGREE: i64 = 10_000_000;
+
+/// Width and height of a cell, i.e. a tenth of a degree.
+const CELL_SIZE: i64 = UNITS_PER_DEGREE / 10;
+
+/// Number of cells along a circle of latitude.
+const LON_CELLS: i64 = 360 * UNITS_PER_DEGREE / CELL_SIZE;
+
+fn lat_cell(lat: i64) -> i64 {
+    let lat = lat
+        .max(-90 * UNITS_PER_DEGREE)
+        .min(90 * UNITS_PER_DEGREE - 1);
+    (lat + 90 * UNITS_PER_DEGREE) / CELL_SIZE
+}
+
+fn lon_cell(lon: i64) -> i64 {
+    let circle = 360 * UNITS_PER_DEGREE;
+    let lon = ((lon + 180 * UNITS_PER_DEGREE) % circle + circle) % circle;
+    lon / CELL_SIZE
+}
+
+fn cell_id(lat_cell: i64, lon_cell: i64) -> Value {
+    Value::Number(lat_cell * LON_CELLS + lon_cell)
+}
+
+/// Returns the cell holding the specified point.
+pub fn cell(lat: i32, lon: i32) -> Value {
+    cell_id(lat_cell(i64::from(lat)), lon_cell(i64::from(lon)))
+}
+
+/// Returns the (cell, (e, v)) entry making up a datom's entry in a
+/// spatial index. Only geo points are indexed.
+pub fn index_entries(e: Value, v: Value) -> Option<(Value, (Value, Value))> {
+    match v {
+        Value::GeoPoint(lat, lon) => Some((cell(lat, lon), (e, v))),
+        _ => None,
+    }
+}
+
+/// Returns the cells covering the box between the specified bounds,
+/// wrapping around the antimeridian if `west` lies east of
+/// `east`. Returns `None` if more than `limit` cells would be needed.
+pub fn cover(south: i64, west: i64, north: i64, east: i64, limit: usize) -> Option<Vec<Value>> {
+    let rows = lat_cell(south)..=lat_cell(north);
+
+    let columns: Vec<i64> = if west <= east && east - west >= 360 * UNITS_PER_DEGREE {
+        (0..LON_CELLS).collect()
+    } else {
+        let (first, last) = (lon_cell(west), lon_cell(east));
+        if west > east || first > last {
+            (first..LON_CELLS).chain(0..=last).collect()
+        } else {
+            (first..=last).collect()
+        }
+    };
+
+    let count = (rows.end() - rows.start() + 1) as usize * columns.len();
+    if count > limit {
+        return None;
+    }
+
+    let mut cells = Vec::with_capacity(count);
+    for row in rows {
+        for column in columns.iter() {
+            cells.push(cell_id(row, *column));
+        }
+    }
+
+    Some(cells)
+}
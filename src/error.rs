@@ -0,0 +1,155 @@
+//! Errors raised by the individual stages a request passes through,
+//! from parsing to the implementation of a dataflow. All of them map
+//! onto the client-facing `Error`, which is what ends up on the wire,
+//! s.t. they can be propagated via `?` wherever an `Error` is
+//! expected. Nothing in here should be reason to take down a worker.
+
+use std::fmt;
+
+use crate::{Error, Var};
+
+/// Errors raised while implementing a plan.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PlanError {
+    /// The plan references an attribute that doesn't exist.
+    UnknownAttribute(String),
+    /// The plan requires an index the attribute doesn't maintain,
+    /// e.g. a reverse index.
+    MissingIndex(String, &'static str),
+    /// The plan references a relation that isn't defined in the
+    /// scope it is implemented in.
+    UnknownRelation(String),
+    /// The plan references a variable that its inputs don't bind.
+    UnboundVariable(Var),
+    /// A constant argument is not of the type the plan requires.
+    InvalidArgument(String),
+    /// The plan can't be implemented in its current form.
+    Unsupported(String),
+}
+
+impl fmt::Display for PlanError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PlanError::UnknownAttribute(a) => write!(f, "Attribute {} does not exist.", a),
+            PlanError::MissingIndex(a, index) => {
+                write!(f, "Attribute {} has no {} index.", a, index)
+            }
+            PlanError::UnknownRelation(name) => write!(f, "Relation {} is not defined.", name),
+            PlanError::UnboundVariable(var) => write!(f, "Variable {} is not bound.", var),
+            PlanError::InvalidArgument(msg) | PlanError::Unsupported(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl From<PlanError> for Error {
+    fn from(error: PlanError) -> Error {
+        match error {
+            PlanError::UnknownAttribute(_)
+            | PlanError::MissingIndex(_, _)
+            | PlanError::UnknownRelation(_) => Error::not_found(error),
+            PlanError::UnboundVariable(_) | PlanError::InvalidArgument(_) => {
+                Error::incorrect(error)
+            }
+            PlanError::Unsupported(_) => Error::unsupported(error),
+        }
+    }
+}
+
+/// Errors raised while processing a transaction. Transactions failing
+/// with any of these are rejected as a whole.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TxError {
+    /// A datom references an attribute that doesn't exist.
+    UnknownAttribute(String),
+    /// A lookup ref doesn't identify any entity.
+    UnresolvedLookup(String),
+    /// A lookup ref identifies more than one entity.
+    AmbiguousLookup(String),
+    /// A datom retracts data from a monotonic or append-only
+    /// attribute.
+    AppendOnly(String),
+}
+
+impl fmt::Display for TxError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TxError::UnknownAttribute(a) => write!(f, "Attribute {} does not exist.", a),
+            TxError::UnresolvedLookup(msg) | TxError::AmbiguousLookup(msg) => write!(f, "{}", msg),
+            TxError::AppendOnly(a) => write!(f, "Attribute {} doesn't accept retractions.", a),
+        }
+    }
+}
+
+impl From<TxError> for Error {
+    fn from(error: TxError) -> Error {
+        match error {
+            TxError::UnknownAttribute(_) | TxError::UnresolvedLookup(_) => Error::not_found(error),
+            TxError::AmbiguousLookup(_) => Error::conflict(error),
+            TxError::AppendOnly(_) => Error::incorrect(error),
+        }
+    }
+}
+
+/// Errors raised while configuring a source.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SourceError {
+    /// The underlying resource the configuration refers to could
+    /// not be opened.
+    Io(String),
+    /// The source configuration is invalid.
+    Misconfigured(String),
+    /// The source is not available in this build.
+    Unsupported(String),
+}
+
+impl fmt::Display for SourceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SourceError::Io(msg) => write!(f, "Failed to open source: {}", msg),
+            SourceError::Misconfigured(msg) | SourceError::Unsupported(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl From<SourceError> for Error {
+    fn from(error: SourceError) -> Error {
+        match error {
+            SourceError::Io(_) => Error::not_found(error),
+            SourceError::Misconfigured(_) => Error::incorrect(error),
+            SourceError::Unsupported(_) => Error::unsupported(error),
+        }
+    }
+}
+
+/// Errors raised while parsing messages received from clients.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ProtocolError {
+    /// A text message is not a valid batch of requests.
+    Malformed(String),
+    /// A binary message is not a valid bulk transaction.
+    MalformedBulk(String),
+    /// A message states a version of the request format this server
+    /// doesn't understand.
+    UnsupportedVersion(u32),
+}
+
+impl fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ProtocolError::Malformed(msg) => write!(f, "Malformed request: {}", msg),
+            ProtocolError::MalformedBulk(msg) => write!(f, "Malformed bulk transaction: {}", msg),
+            ProtocolError::UnsupportedVersion(version) => {
+                write!(f, "Unsupported request format version {}", version)
+            }
+        }
+    }
+}
+
+impl From<ProtocolError> for Error {
+    fn from(error: ProtocolError) -> Error {
+        match error {
+            ProtocolError::UnsupportedVersion(_) => Error::unsupported(error),
+            _ => Error::incorrect(error),
+        }
+    }
+}
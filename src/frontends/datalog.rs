@@ -0,0 +1,623 @@
+//! Datomic-style Datalog frontend.
+//!
+//! Compiles query strings such as
+//!
+//! ```text
+//! [:find ?name (count ?friend)
+//!  :where [?e :person/name ?name] [?e :person/friend ?friend]]
+//! ```
+//!
+//! into an equivalent `Plan`. Supported are data patterns, rule
+//! invocations, binary predicates, `not`, `or` / `and`, aggregations
+//! in the find specification, and `:with`.
+//!
+//! Keywords in attribute position keep their leading colon, s.t.
+//! `:person/name` refers to the attribute named `":person/name"`.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::frontends::edn::{self, Edn};
+use crate::plan::{Aggregate, AggregationFn, Antijoin, Filter, Join, Predicate, Project, Union};
+use crate::{AsAid, Error, Plan, Value, Var};
+
+/// Parses and compiles a single Datalog query into a plan.
+pub fn parse<A: AsAid>(query: &str) -> Result<Plan<A>, Error> {
+    match edn::read_one(query)? {
+        Edn::Vector(forms) => compile(&forms),
+        _ => Err(Error::incorrect("Queries must be given as a vector.")),
+    }
+}
+
+/// Compiles the forms making up a query vector into a plan.
+pub(crate) fn compile<A: AsAid>(forms: &[Edn]) -> Result<Plan<A>, Error> {
+    Compiler::default().query(forms)
+}
+
+/// A compiled clause, together with the variables it binds.
+struct Bound<A: AsAid> {
+    variables: Vec<Var>,
+    plan: Plan<A>,
+}
+
+/// An element of the find specification.
+enum FindElement {
+    Variable(Var),
+    Aggregate(AggregationFn, Var),
+}
+
+impl FindElement {
+    fn variable(&self) -> Var {
+        match *self {
+            FindElement::Variable(variable) => variable,
+            FindElement::Aggregate(_, variable) => variable,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Compiler {
+    variables: HashMap<String, Var>,
+    // Variables that were introduced by the compiler itself,
+    // e.g. for wildcards. These are never made visible to users.
+    anonymous: HashSet<Var>,
+    next_variable: Var,
+}
+
+fn is_variable(form: &Edn) -> bool {
+    match form {
+        Edn::Symbol(symbol) => symbol.starts_with('?') || symbol == "_",
+        _ => false,
+    }
+}
+
+/// Interprets a form as a constant value, e.g. `"Dipper"` or
+/// `:person/name`.
+pub fn constant(form: &Edn) -> Result<Value, Error> {
+    match form {
+        Edn::String(string) => Ok(Value::String(string.clone())),
+        Edn::Integer(number) => Ok(Value::Number(*number)),
+        Edn::Bool(boolean) => Ok(Value::Bool(*boolean)),
+        Edn::Keyword(keyword) => Ok(Value::Aid(format!(":{}", keyword))),
+        Edn::Tagged(tag, inner) => match (tag.as_str(), &**inner) {
+            ("uuid", Edn::String(uuid)) => crate::Uuid::parse_str(uuid)
+                .map(Value::Uuid)
+                .map_err(Error::incorrect),
+            _ => Err(Error::unsupported(format!("Tagged literal #{}.", tag))),
+        },
+        other => Err(Error::incorrect(format!(
+            "{:?} is not a valid constant.",
+            other
+        ))),
+    }
+}
+
+/// Looks up the binary predicate denoted by a symbol such as `<=`.
+pub fn predicate(symbol: &str) -> Option<Predicate> {
+    match symbol {
+        "<" => Some(Predicate::LT),
+        "<=" => Some(Predicate::LTE),
+        ">" => Some(Predicate::GT),
+        ">=" => Some(Predicate::GTE),
+        "=" => Some(Predicate::EQ),
+        "!=" | "not=" => Some(Predicate::NEQ),
+        _ => None,
+    }
+}
+
+/// Looks up the aggregation function denoted by a symbol such as
+/// `count`.
+pub fn aggregation_fn(symbol: &str) -> Option<AggregationFn> {
+    match symbol {
+        "min" => Some(AggregationFn::MIN),
+        "max" => Some(AggregationFn::MAX),
+        "median" => Some(AggregationFn::MEDIAN),
+        "count" => Some(AggregationFn::COUNT),
+        "sum" => Some(AggregationFn::SUM),
+        "avg" => Some(AggregationFn::AVG),
+        "variance" => Some(AggregationFn::VARIANCE),
+        _ => None,
+    }
+}
+
+/// Joins two relations on all of their shared variables.
+fn join<A: AsAid>(left: Bound<A>, right: Bound<A>) -> Bound<A> {
+    let shared: Vec<Var> = left
+        .variables
+        .iter()
+        .filter(|x| right.variables.contains(x))
+        .cloned()
+        .collect();
+
+    let variables = shared
+        .iter()
+        .chain(left.variables.iter().filter(|x| !shared.contains(x)))
+        .chain(right.variables.iter().filter(|x| !shared.contains(x)))
+        .cloned()
+        .collect();
+
+    Bound {
+        variables,
+        plan: Plan::Join(Join {
+            variables: shared,
+            left_plan: Box::new(left.plan),
+            right_plan: Box::new(right.plan),
+        }),
+    }
+}
+
+impl Compiler {
+    fn fresh(&mut self) -> Var {
+        // Variables are numbered starting from one, because
+        // aggregations use zero as a placeholder internally.
+        self.next_variable += 1;
+        self.anonymous.insert(self.next_variable);
+        self.next_variable
+    }
+
+    fn variable(&mut self, form: &Edn) -> Result<Var, Error> {
+        match form {
+            Edn::Symbol(symbol) if symbol == "_" => Ok(self.fresh()),
+            Edn::Symbol(symbol) if symbol.starts_with('?') => {
+                if let Some(variable) = self.variables.get(symbol) {
+                    Ok(*variable)
+                } else {
+                    self.next_variable += 1;
+                    self.variables.insert(symbol.clone(), self.next_variable);
+                    Ok(self.next_variable)
+                }
+            }
+            other => Err(Error::incorrect(format!("{:?} is not a variable.", other))),
+        }
+    }
+
+    fn bound_variable(&self, form: &Edn, bound: &[Var]) -> Result<Var, Error> {
+        let variable = match form {
+            Edn::Symbol(symbol) => self.variables.get(symbol).cloned(),
+            _ => None,
+        };
+
+        match variable {
+            Some(variable) if bound.contains(&variable) => Ok(variable),
+            _ => Err(Error::incorrect(format!(
+                "{:?} is not bound by the query.",
+                form
+            ))),
+        }
+    }
+
+    fn query<A: AsAid>(mut self, forms: &[Edn]) -> Result<Plan<A>, Error> {
+        let mut find = Vec::new();
+        let mut with = Vec::new();
+        let mut clauses = Vec::new();
+
+        let mut section: Option<&str> = None;
+        for form in forms.iter() {
+            match form {
+                Edn::Keyword(keyword) => match keyword.as_str() {
+                    "find" | "with" | "where" => section = Some(keyword.as_str()),
+                    other => {
+                        return Err(Error::unsupported(format!(":{} clauses", other)));
+                    }
+                },
+                form => match section {
+                    Some("find") => find.push(form),
+                    Some("with") => with.push(form),
+                    Some("where") => clauses.push(form.clone()),
+                    _ => {
+                        return Err(Error::incorrect("Queries must begin with :find."));
+                    }
+                },
+            }
+        }
+
+        if find.is_empty() {
+            return Err(Error::incorrect(
+                "Queries must specify at least one find element.",
+            ));
+        }
+
+        let body: Bound<A> = self.conjunction(&clauses)?;
+
+        let mut elements = Vec::with_capacity(find.len());
+        for form in find.into_iter() {
+            match form {
+                Edn::List(items) => match items.as_slice() {
+                    [Edn::Symbol(symbol), argument] => match aggregation_fn(symbol) {
+                        None => {
+                            return Err(Error::unsupported(format!("Aggregation {}.", symbol)));
+                        }
+                        Some(aggregation_fn) => {
+                            let variable = self.bound_variable(argument, &body.variables)?;
+                            elements.push(FindElement::Aggregate(aggregation_fn, variable));
+                        }
+                    },
+                    _ => {
+                        return Err(Error::incorrect(format!(
+                            "Invalid find element {:?}.",
+                            form
+                        )))
+                    }
+                },
+                form => {
+                    let variable = self.bound_variable(form, &body.variables)?;
+                    elements.push(FindElement::Variable(variable));
+                }
+            }
+        }
+
+        let with_variables = with
+            .into_iter()
+            .map(|form| self.bound_variable(form, &body.variables))
+            .collect::<Result<Vec<Var>, Error>>()?;
+
+        let variables: Vec<Var> = elements.iter().map(FindElement::variable).collect();
+
+        let mut key_variables = Vec::new();
+        let mut aggregation_variables = Vec::new();
+        let mut aggregation_fns = Vec::new();
+
+        for element in elements.into_iter() {
+            match element {
+                FindElement::Variable(variable) => key_variables.push(variable),
+                FindElement::Aggregate(aggregation_fn, variable) => {
+                    aggregation_fns.push(aggregation_fn);
+                    aggregation_variables.push(variable);
+                }
+            }
+        }
+
+        if aggregation_fns.is_empty() {
+            if !with_variables.is_empty() {
+                return Err(Error::incorrect(":with requires an aggregation."));
+            }
+
+            Ok(Plan::Project(Project {
+                variables,
+                plan: Box::new(body.plan),
+            }))
+        } else {
+            // Aggregations expect their source to bind key variables
+            // first, followed by aggregation arguments, followed by
+            // the :with variables.
+            let mut source_variables = key_variables.clone();
+            for variable in aggregation_variables.iter().chain(with_variables.iter()) {
+                if !source_variables.contains(variable) {
+                    source_variables.push(*variable);
+                }
+            }
+
+            Ok(Plan::Aggregate(Aggregate {
+                variables,
+                plan: Box::new(Plan::Project(Project {
+                    variables: source_variables,
+                    plan: Box::new(body.plan),
+                })),
+                aggregation_fns,
+                key_variables,
+                aggregation_variables,
+                with_variables,
+                key_universe: None,
+                defaults: vec![],
+            }))
+        }
+    }
+
+    /// Compiles a sequence of clauses which must all hold.
+    fn conjunction<A: AsAid>(&mut self, clauses: &[Edn]) -> Result<Bound<A>, Error> {
+        let mut positive = Vec::new();
+        let mut predicates = Vec::new();
+        let mut negations = Vec::new();
+
+        for clause in clauses.iter() {
+            match clause {
+                Edn::Vector(items) => match items.as_slice() {
+                    [Edn::List(expression)] => predicates.push(expression),
+                    _ => positive.push(self.pattern(items)?),
+                },
+                Edn::List(items) => match items.split_first() {
+                    Some((Edn::Symbol(head), rest)) => match head.as_str() {
+                        "not" => negations.push(rest),
+                        "and" => positive.push(self.conjunction(rest)?),
+                        "or" => positive.push(self.disjunction(rest)?),
+                        _ => positive.push(self.invocation(head, rest)?),
+                    },
+                    _ => return Err(Error::incorrect(format!("Invalid clause {:?}.", clause))),
+                },
+                _ => return Err(Error::incorrect(format!("Invalid clause {:?}.", clause))),
+            }
+        }
+
+        // Positive clauses are joined greedily, always picking the
+        // next clause that shares variables with what we have so far.
+        if positive.is_empty() {
+            return Err(Error::incorrect("Expected at least one positive clause."));
+        }
+
+        let mut bound = positive.remove(0);
+        while !positive.is_empty() {
+            let next = positive
+                .iter()
+                .position(|x| x.variables.iter().any(|v| bound.variables.contains(v)));
+
+            match next {
+                None => {
+                    return Err(Error::unsupported(
+                        "Clauses without shared variables (cross products).",
+                    ));
+                }
+                Some(index) => {
+                    let right = positive.remove(index);
+                    bound = join(bound, right);
+                }
+            }
+        }
+
+        // Constraints are applied once all positive clauses are
+        // joined, s.t. all of their variables are guaranteed to be
+        // bound.
+        for expression in predicates.into_iter() {
+            bound = self.filter(expression, bound)?;
+        }
+
+        for negated in negations.into_iter() {
+            let negated: Bound<A> = self.conjunction(negated)?;
+
+            let variables: Vec<Var> = negated
+                .variables
+                .iter()
+                .filter(|x| !self.anonymous.contains(x))
+                .cloned()
+                .collect();
+
+            if variables.iter().any(|x| !bound.variables.contains(x)) {
+                return Err(Error::incorrect(
+                    "All variables in a not clause must be bound by the enclosing query.",
+                ));
+            }
+
+            let mut output_variables = variables.clone();
+            output_variables.extend(bound.variables.iter().filter(|x| !variables.contains(x)));
+
+            bound = Bound {
+                variables: output_variables,
+                plan: Plan::Antijoin(Antijoin {
+                    variables,
+                    left_plan: Box::new(bound.plan),
+                    right_plan: Box::new(negated.plan),
+                }),
+            };
+        }
+
+        Ok(bound)
+    }
+
+    /// Compiles a set of alternatives, at least one of which must hold.
+    fn disjunction<A: AsAid>(&mut self, branches: &[Edn]) -> Result<Bound<A>, Error> {
+        let mut compiled = Vec::with_capacity(branches.len());
+        for branch in branches.iter() {
+            compiled.push(self.conjunction(std::slice::from_ref(branch))?);
+        }
+
+        if compiled.is_empty() {
+            return Err(Error::incorrect(
+                "Expected at least one branch in or clause.",
+            ));
+        }
+
+        let variables: Vec<Var> = compiled[0]
+            .variables
+            .iter()
+            .filter(|x| !self.anonymous.contains(x))
+            .cloned()
+            .collect();
+
+        for branch in compiled.iter() {
+            if variables.iter().any(|x| !branch.variables.contains(x)) {
+                return Err(Error::incorrect(
+                    "All branches of an or clause must bind the same variables.",
+                ));
+            }
+        }
+
+        Ok(Bound {
+            variables: variables.clone(),
+            plan: Plan::Union(Union {
+                variables,
+                plans: compiled.into_iter().map(|branch| branch.plan).collect(),
+                mappings: vec![],
+            }),
+        })
+    }
+
+    /// Compiles a data pattern of the form [e a v].
+    fn pattern<A: AsAid>(&mut self, items: &[Edn]) -> Result<Bound<A>, Error> {
+        let (e, a, v) = match items {
+            [e, Edn::Keyword(a), v] => (e, format!(":{}", a), v),
+            [_, Edn::Keyword(_)] => {
+                return Err(Error::unsupported(
+                    "Data patterns without a value position.",
+                ));
+            }
+            _ => {
+                return Err(Error::incorrect(format!(
+                    "Invalid data pattern {:?}.",
+                    items
+                )))
+            }
+        };
+
+        if let Edn::Vector(lookup) = e {
+            return self.lookup_pattern(lookup, a, v);
+        }
+
+        let e_constant = match e {
+            Edn::Integer(eid) if *eid >= 0 => Some(*eid as u64),
+            Edn::BigInt(eid) => Some(*eid),
+            e if is_variable(e) => None,
+            other => {
+                return Err(Error::incorrect(format!(
+                    "{:?} is not a valid entity position.",
+                    other
+                )))
+            }
+        };
+
+        match e_constant {
+            None => {
+                let e = self.variable(e)?;
+
+                if is_variable(v) {
+                    let v = self.variable(v)?;
+                    Ok(Bound {
+                        variables: vec![e, v],
+                        plan: Plan::match_a(e, a, v),
+                    })
+                } else {
+                    Ok(Bound {
+                        variables: vec![e],
+                        plan: Plan::match_av(e, a, constant(v)?),
+                    })
+                }
+            }
+            Some(eid) => {
+                if is_variable(v) {
+                    let v = self.variable(v)?;
+                    Ok(Bound {
+                        variables: vec![v],
+                        plan: Plan::match_ea(eid, a, v),
+                    })
+                } else {
+                    // Fully constant patterns are checked via an
+                    // additional equality constraint.
+                    let sym = self.fresh();
+                    Ok(Bound {
+                        variables: vec![sym],
+                        plan: Plan::Filter(Filter {
+                            variables: vec![sym],
+                            predicate: Predicate::EQ,
+                            plan: Box::new(Plan::match_ea(eid, a, sym)),
+                            constants: vec![None, Some(constant(v)?)],
+                        }),
+                    })
+                }
+            }
+        }
+    }
+
+    /// Compiles a data pattern whose entity is given by a lookup ref
+    /// of the form [a' v'].
+    fn lookup_pattern<A: AsAid>(
+        &mut self,
+        lookup: &[Edn],
+        a: String,
+        v: &Edn,
+    ) -> Result<Bound<A>, Error> {
+        let lookup = match lookup {
+            [Edn::Keyword(lookup_a), lookup_v] => (format!(":{}", lookup_a), constant(lookup_v)?),
+            _ => {
+                return Err(Error::incorrect(format!(
+                    "Invalid lookup ref {:?}.",
+                    lookup
+                )))
+            }
+        };
+
+        if is_variable(v) {
+            let v = self.variable(v)?;
+            Ok(Bound {
+                variables: vec![v],
+                plan: Plan::match_lookup_a(lookup, a, v),
+            })
+        } else {
+            let sym = self.fresh();
+            Ok(Bound {
+                variables: vec![sym],
+                plan: Plan::Filter(Filter {
+                    variables: vec![sym],
+                    predicate: Predicate::EQ,
+                    plan: Box::new(Plan::match_lookup_a(lookup, a, sym)),
+                    constants: vec![None, Some(constant(v)?)],
+                }),
+            })
+        }
+    }
+
+    /// Compiles an invocation of a previously registered rule.
+    fn invocation<A: AsAid>(&mut self, name: &str, arguments: &[Edn]) -> Result<Bound<A>, Error> {
+        let mut variables = Vec::with_capacity(arguments.len());
+        let mut constants = Vec::new();
+
+        for argument in arguments.iter() {
+            if is_variable(argument) {
+                variables.push(self.variable(argument)?);
+            } else {
+                let sym = self.fresh();
+                variables.push(sym);
+                constants.push((sym, constant(argument)?));
+            }
+        }
+
+        let mut bound = Bound {
+            variables: variables.clone(),
+            plan: Plan::NameExpr(variables, A::from(name.to_string())),
+        };
+
+        for (sym, value) in constants.into_iter() {
+            bound = Bound {
+                variables: bound.variables,
+                plan: Plan::Filter(Filter {
+                    variables: vec![sym],
+                    predicate: Predicate::EQ,
+                    plan: Box::new(bound.plan),
+                    constants: vec![None, Some(value)],
+                }),
+            };
+        }
+
+        Ok(bound)
+    }
+
+    /// Compiles a predicate expression such as (< ?a ?b).
+    fn filter<A: AsAid>(&self, expression: &[Edn], bound: Bound<A>) -> Result<Bound<A>, Error> {
+        let (predicate, arguments) = match expression {
+            [Edn::Symbol(symbol), left, right] => match predicate(symbol) {
+                None => return Err(Error::unsupported(format!("Predicate {}.", symbol))),
+                Some(predicate) => (predicate, [left, right]),
+            },
+            _ => {
+                return Err(Error::incorrect(format!(
+                    "Invalid predicate expression {:?}.",
+                    expression
+                )))
+            }
+        };
+
+        let mut variables = Vec::with_capacity(2);
+        let mut constants = Vec::with_capacity(2);
+
+        for argument in arguments.iter() {
+            if is_variable(argument) {
+                variables.push(self.bound_variable(argument, &bound.variables)?);
+                constants.push(None);
+            } else {
+                constants.push(Some(constant(argument)?));
+            }
+        }
+
+        if variables.is_empty() {
+            return Err(Error::incorrect(
+                "Predicates must refer to at least one variable.",
+            ));
+        }
+
+        Ok(Bound {
+            variables: bound.variables,
+            plan: Plan::Filter(Filter {
+                variables,
+                predicate,
+                plan: Box::new(bound.plan),
+                constants,
+            }),
+        })
+    }
+}
@@ -0,0 +1,389 @@
+//! A minimal reader and printer for the subset of EDN used by textual
+//! frontends and the EDN wire format.
+
+use std::fmt;
+use std::iter::Peekable;
+use std::str::Chars;
+
+use crate::Error;
+
+/// A single EDN form.
+#[derive(PartialEq, Clone, Debug)]
+pub enum Edn {
+    /// nil
+    Nil,
+    /// true or false
+    Bool(bool),
+    /// A 64 bit signed integer
+    Integer(i64),
+    /// A natural number beyond the range of integers, such as an
+    /// allocated entity id, printed with an `N` suffix
+    BigInt(u64),
+    /// A ratio of two integers, such as `1/3`
+    Ratio(i64, i64),
+    /// A 64 bit floating point number
+    Float(f64),
+    /// A string literal
+    String(String),
+    /// A symbol, such as `?e` or `count`
+    Symbol(String),
+    /// A keyword, stored without its leading colon
+    Keyword(String),
+    /// A tagged literal, such as `#uuid "..."`
+    Tagged(String, Box<Edn>),
+    /// A list `( ... )`
+    List(Vec<Edn>),
+    /// A vector `[ ... ]`
+    Vector(Vec<Edn>),
+    /// A map `{ ... }`, in the order the entries were written
+    Map(Vec<(Edn, Edn)>),
+}
+
+/// Reads all top-level forms from the provided string.
+pub fn read(input: &str) -> Result<Vec<Edn>, Error> {
+    let mut reader = Reader {
+        chars: input.chars().peekable(),
+    };
+
+    let mut forms = Vec::new();
+    while let Some(form) = reader.next_form()? {
+        forms.push(form);
+    }
+
+    Ok(forms)
+}
+
+/// Reads exactly one form from the provided string.
+pub fn read_one(input: &str) -> Result<Edn, Error> {
+    let mut forms = read(input)?;
+
+    if forms.len() == 1 {
+        Ok(forms.remove(0))
+    } else {
+        Err(Error::incorrect(format!(
+            "Expected a single form, but found {}.",
+            forms.len()
+        )))
+    }
+}
+
+struct Reader<'a> {
+    chars: Peekable<Chars<'a>>,
+}
+
+pub(crate) fn is_delimiter(c: char) -> bool {
+    c.is_whitespace()
+        || c == ','
+        || c == ';'
+        || c == '('
+        || c == ')'
+        || c == '['
+        || c == ']'
+        || c == '{'
+        || c == '}'
+        || c == '"'
+}
+
+impl<'a> Reader<'a> {
+    fn skip_whitespace(&mut self) {
+        while let Some(&c) = self.chars.peek() {
+            if c.is_whitespace() || c == ',' {
+                self.chars.next();
+            } else if c == ';' {
+                // Comments extend until the end of the line.
+                for c in self.chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn next_form(&mut self) -> Result<Option<Edn>, Error> {
+        self.skip_whitespace();
+
+        match self.chars.peek().cloned() {
+            None => Ok(None),
+            Some(')') | Some(']') | Some('}') => {
+                let c = self.chars.next().unwrap();
+                Err(Error::incorrect(format!("Unexpected delimiter {}.", c)))
+            }
+            Some(_) => self.form().map(Some),
+        }
+    }
+
+    fn form(&mut self) -> Result<Edn, Error> {
+        self.skip_whitespace();
+
+        match self.chars.next() {
+            None => Err(Error::incorrect("Unexpected end of input.")),
+            Some('(') => Ok(Edn::List(self.sequence(')')?)),
+            Some('[') => Ok(Edn::Vector(self.sequence(']')?)),
+            Some('{') => {
+                let items = self.sequence('}')?;
+
+                if items.len() % 2 != 0 {
+                    return Err(Error::incorrect(
+                        "Map literal must contain an even number of forms.",
+                    ));
+                }
+
+                let entries = items
+                    .chunks(2)
+                    .map(|pair| (pair[0].clone(), pair[1].clone()))
+                    .collect();
+
+                Ok(Edn::Map(entries))
+            }
+            Some('"') => self.string(),
+            Some('#') => {
+                if self.chars.peek() == Some(&'#') {
+                    self.chars.next();
+                    return symbolic_value(self.token());
+                }
+
+                let tag = self.token();
+                if tag.is_empty() {
+                    return Err(Error::unsupported(
+                        "Dispatch forms other than tagged literals.",
+                    ));
+                }
+
+                let value = self.form()?;
+                Ok(Edn::Tagged(tag, Box::new(value)))
+            }
+            Some(':') => {
+                let name = self.token();
+                if name.is_empty() {
+                    Err(Error::incorrect("Empty keyword."))
+                } else {
+                    Ok(Edn::Keyword(name))
+                }
+            }
+            Some(c) => {
+                let mut token = c.to_string();
+                token.push_str(&self.token());
+
+                atom(token)
+            }
+        }
+    }
+
+    fn sequence(&mut self, close: char) -> Result<Vec<Edn>, Error> {
+        let mut items = Vec::new();
+
+        loop {
+            self.skip_whitespace();
+
+            match self.chars.peek().cloned() {
+                None => {
+                    return Err(Error::incorrect(format!(
+                        "Expected {} before end of input.",
+                        close
+                    )));
+                }
+                Some(c) if c == close => {
+                    self.chars.next();
+                    return Ok(items);
+                }
+                Some(')') | Some(']') | Some('}') => {
+                    let c = self.chars.next().unwrap();
+                    return Err(Error::incorrect(format!(
+                        "Mismatched delimiter {}, expected {}.",
+                        c, close
+                    )));
+                }
+                Some(_) => items.push(self.form()?),
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<Edn, Error> {
+        let mut string = String::new();
+
+        loop {
+            match self.chars.next() {
+                None => return Err(Error::incorrect("Unterminated string literal.")),
+                Some('"') => return Ok(Edn::String(string)),
+                Some('\\') => match self.chars.next() {
+                    Some('n') => string.push('\n'),
+                    Some('t') => string.push('\t'),
+                    Some('r') => string.push('\r'),
+                    Some('"') => string.push('"'),
+                    Some('\\') => string.push('\\'),
+                    Some(c) => {
+                        return Err(Error::incorrect(format!(
+                            "Unknown escape sequence \\{}.",
+                            c
+                        )));
+                    }
+                    None => return Err(Error::incorrect("Unterminated string literal.")),
+                },
+                Some(c) => string.push(c),
+            }
+        }
+    }
+
+    fn token(&mut self) -> String {
+        let mut token = String::new();
+
+        while let Some(&c) = self.chars.peek() {
+            if is_delimiter(c) {
+                break;
+            } else {
+                token.push(c);
+                self.chars.next();
+            }
+        }
+
+        token
+    }
+}
+
+fn atom(token: String) -> Result<Edn, Error> {
+    match token.as_ref() {
+        "nil" => Ok(Edn::Nil),
+        "true" => Ok(Edn::Bool(true)),
+        "false" => Ok(Edn::Bool(false)),
+        _ => {
+            let mut chars = token.chars();
+            let first = chars.next().unwrap();
+            let second = chars.next();
+
+            let numeric = first.is_ascii_digit()
+                || ((first == '-' || first == '+')
+                    && second.map(|c| c.is_ascii_digit()).unwrap_or(false));
+
+            if numeric {
+                number(&token)
+                    .ok_or_else(|| Error::unsupported(format!("Number literal {}.", token)))
+            } else {
+                Ok(Edn::Symbol(token))
+            }
+        }
+    }
+}
+
+fn number(token: &str) -> Option<Edn> {
+    if token.ends_with('M') {
+        // Arbitrary precision decimals can't be represented exactly.
+        None
+    } else if token.ends_with('N') {
+        integer(&token[..token.len() - 1])
+    } else if let Some(slash) = token.find('/') {
+        let numerator = token[..slash].parse::<i64>().ok()?;
+        let denominator = token[slash + 1..].parse::<i64>().ok()?;
+
+        if denominator > 0 {
+            Some(Edn::Ratio(numerator, denominator))
+        } else {
+            None
+        }
+    } else if token.contains(|c| c == '.' || c == 'e' || c == 'E') {
+        token.parse::<f64>().ok().map(Edn::Float)
+    } else {
+        integer(token)
+    }
+}
+
+// Integers are only read as big integers if they don't fit into 64
+// bit signed ones.
+fn integer(token: &str) -> Option<Edn> {
+    match token.parse::<i64>() {
+        Ok(number) => Some(Edn::Integer(number)),
+        Err(_) => token.parse::<u64>().ok().map(Edn::BigInt),
+    }
+}
+
+fn symbolic_value(token: String) -> Result<Edn, Error> {
+    match token.as_ref() {
+        "Inf" => Ok(Edn::Float(std::f64::INFINITY)),
+        "-Inf" => Ok(Edn::Float(std::f64::NEG_INFINITY)),
+        "NaN" => Ok(Edn::Float(std::f64::NAN)),
+        _ => Err(Error::incorrect(format!(
+            "Unknown symbolic value ##{}.",
+            token
+        ))),
+    }
+}
+
+fn write_sequence(f: &mut fmt::Formatter, forms: &[Edn]) -> fmt::Result {
+    for (index, form) in forms.iter().enumerate() {
+        if index > 0 {
+            write!(f, " ")?;
+        }
+
+        write!(f, "{}", form)?;
+    }
+
+    Ok(())
+}
+
+/// Prints forms s.t. reading them back results in the same form.
+impl fmt::Display for Edn {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Edn::Nil => write!(f, "nil"),
+            Edn::Bool(boolean) => write!(f, "{}", boolean),
+            Edn::Integer(number) => write!(f, "{}", number),
+            Edn::BigInt(number) => write!(f, "{}N", number),
+            Edn::Ratio(numerator, denominator) => write!(f, "{}/{}", numerator, denominator),
+            Edn::Float(number) => {
+                if number.is_nan() {
+                    write!(f, "##NaN")
+                } else if number.is_infinite() {
+                    write!(f, "{}", if *number > 0.0 { "##Inf" } else { "##-Inf" })
+                } else {
+                    // Debug formatting always includes a decimal point
+                    // or an exponent, telling floats from integers.
+                    write!(f, "{:?}", number)
+                }
+            }
+            Edn::String(string) => {
+                write!(f, "\"")?;
+
+                for c in string.chars() {
+                    match c {
+                        '"' => write!(f, "\\\"")?,
+                        '\\' => write!(f, "\\\\")?,
+                        '\n' => write!(f, "\\n")?,
+                        '\t' => write!(f, "\\t")?,
+                        '\r' => write!(f, "\\r")?,
+                        c => write!(f, "{}", c)?,
+                    }
+                }
+
+                write!(f, "\"")
+            }
+            Edn::Symbol(symbol) => write!(f, "{}", symbol),
+            Edn::Keyword(keyword) => write!(f, ":{}", keyword),
+            Edn::Tagged(tag, form) => write!(f, "#{} {}", tag, form),
+            Edn::List(forms) => {
+                write!(f, "(")?;
+                write_sequence(f, forms)?;
+                write!(f, ")")
+            }
+            Edn::Vector(forms) => {
+                write!(f, "[")?;
+                write_sequence(f, forms)?;
+                write!(f, "]")
+            }
+            Edn::Map(entries) => {
+                write!(f, "{{")?;
+
+                for (index, (key, value)) in entries.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+
+                    write!(f, "{} {}", key, value)?;
+                }
+
+                write!(f, "}}")
+            }
+        }
+    }
+}
@@ -0,0 +1,30 @@
+//! Textual query languages, compiling down to plans.
+
+pub mod datalog;
+pub mod edn;
+pub mod sql;
+
+use crate::{AsAid, Error, Plan};
+
+/// Supported textual query languages.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum Language {
+    /// Datomic-style Datalog.
+    Datalog,
+    /// A subset of SQL.
+    Sql,
+}
+
+impl Default for Language {
+    fn default() -> Self {
+        Language::Datalog
+    }
+}
+
+/// Parses and compiles a query written in the specified language.
+pub fn parse<A: AsAid>(language: Language, query: &str) -> Result<Plan<A>, Error> {
+    match language {
+        Language::Datalog => datalog::parse(query),
+        Language::Sql => sql::parse(query),
+    }
+}
@@ -0,0 +1,815 @@
+//! SQL frontend.
+//!
+//! Supports a small subset of SQL, namely
+//!
+//! ```text
+//! SELECT [DISTINCT] item, ...
+//! FROM table [alias] [JOIN table [alias] ON condition [AND ...]] ...
+//! [WHERE condition [AND ...]]
+//! [GROUP BY column, ...]
+//! [HAVING aggregate op constant [AND ...]]
+//! ```
+//!
+//! Tables come in two flavours. A plain table name such as `person`
+//! is treated as an entity-attribute-value table: each of its rows is
+//! an entity, column `id` refers to the entity id, and any other
+//! column `c` refers to the attribute `:person/c`. A table given with
+//! an explicit column list, such as `FROM edges AS e(src, dst)`,
+//! refers to a registered relation, whose columns are named
+//! positionally.
+//!
+//! Queries are translated into the equivalent Datalog and compiled
+//! from there.
+
+use std::collections::HashMap;
+
+use crate::frontends::datalog;
+use crate::frontends::edn::Edn;
+use crate::plan::{Filter, Project, Union};
+use crate::{AsAid, Error, Plan};
+
+/// Parses and compiles a single SQL query into a plan.
+pub fn parse<A: AsAid>(query: &str) -> Result<Plan<A>, Error> {
+    let tokens = tokenize(query)?;
+    let select = Parser {
+        tokens,
+        position: 0,
+    }
+    .select()?;
+
+    compile(select)
+}
+
+#[derive(PartialEq, Clone, Debug)]
+enum Token {
+    Identifier(String),
+    Number(i64),
+    String(String),
+    Symbol(String),
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, Error> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let mut identifier = String::new();
+            while let Some(&c) = chars.peek() {
+                if c.is_ascii_alphanumeric() || c == '_' {
+                    identifier.push(c);
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            tokens.push(Token::Identifier(identifier));
+        } else if c.is_ascii_digit() || c == '-' {
+            let mut number = String::new();
+            number.push(c);
+            chars.next();
+            while let Some(&c) = chars.peek() {
+                if c.is_ascii_digit() {
+                    number.push(c);
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            let number = number
+                .parse::<i64>()
+                .map_err(|_| Error::incorrect(format!("Invalid number {}.", number)))?;
+            tokens.push(Token::Number(number));
+        } else if c == '\'' {
+            chars.next();
+            let mut string = String::new();
+            loop {
+                match chars.next() {
+                    None => return Err(Error::incorrect("Unterminated string literal.")),
+                    Some('\'') => {
+                        // Quotes are escaped by doubling them.
+                        if chars.peek() == Some(&'\'') {
+                            chars.next();
+                            string.push('\'');
+                        } else {
+                            break;
+                        }
+                    }
+                    Some(c) => string.push(c),
+                }
+            }
+            tokens.push(Token::String(string));
+        } else {
+            chars.next();
+            let symbol = match (c, chars.peek().cloned()) {
+                ('<', Some('=')) | ('>', Some('=')) | ('!', Some('=')) | ('<', Some('>')) => {
+                    let mut symbol = c.to_string();
+                    symbol.push(chars.next().unwrap());
+                    symbol
+                }
+                ('(', _)
+                | (')', _)
+                | (',', _)
+                | ('.', _)
+                | ('*', _)
+                | ('=', _)
+                | ('<', _)
+                | ('>', _)
+                | (';', _) => c.to_string(),
+                _ => return Err(Error::incorrect(format!("Unexpected character {}.", c))),
+            };
+            tokens.push(Token::Symbol(symbol));
+        }
+    }
+
+    Ok(tokens)
+}
+
+#[derive(PartialEq, Clone, Debug)]
+struct ColumnRef {
+    table: Option<String>,
+    column: String,
+}
+
+#[derive(Clone, Debug)]
+enum Operand {
+    Column(ColumnRef),
+    Constant(Edn),
+}
+
+#[derive(Clone, Debug)]
+struct Condition {
+    left: Operand,
+    operator: String,
+    right: Operand,
+}
+
+#[derive(PartialEq, Clone, Debug)]
+enum SelectItem {
+    Column(ColumnRef),
+    /// An aggregation function applied to a column, or to all rows
+    /// in the case of `COUNT(*)`.
+    Aggregate(String, Option<ColumnRef>),
+}
+
+#[derive(Clone, Debug)]
+struct TableRef {
+    name: String,
+    alias: String,
+    columns: Option<Vec<String>>,
+}
+
+#[derive(Clone, Debug)]
+struct Select {
+    distinct: bool,
+    items: Vec<SelectItem>,
+    tables: Vec<TableRef>,
+    conditions: Vec<Condition>,
+    group_by: Vec<ColumnRef>,
+    having: Vec<(SelectItem, String, Edn)>,
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+const RESERVED: &[&str] = &[
+    "select", "distinct", "from", "as", "join", "inner", "on", "where", "and", "or", "not",
+    "group", "by", "having", "order", "limit", "true", "false",
+];
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn advance(&mut self) -> Result<Token, Error> {
+        match self.tokens.get(self.position).cloned() {
+            None => Err(Error::incorrect("Unexpected end of query.")),
+            Some(token) => {
+                self.position += 1;
+                Ok(token)
+            }
+        }
+    }
+
+    fn peek_keyword(&self, keyword: &str) -> bool {
+        match self.peek() {
+            Some(Token::Identifier(identifier)) => identifier.eq_ignore_ascii_case(keyword),
+            _ => false,
+        }
+    }
+
+    fn accept_keyword(&mut self, keyword: &str) -> bool {
+        if self.peek_keyword(keyword) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), Error> {
+        if self.accept_keyword(keyword) {
+            Ok(())
+        } else {
+            Err(Error::incorrect(format!(
+                "Expected {}, found {:?}.",
+                keyword.to_uppercase(),
+                self.peek()
+            )))
+        }
+    }
+
+    fn accept_symbol(&mut self, symbol: &str) -> bool {
+        match self.peek() {
+            Some(Token::Symbol(s)) if s == symbol => {
+                self.position += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn expect_symbol(&mut self, symbol: &str) -> Result<(), Error> {
+        if self.accept_symbol(symbol) {
+            Ok(())
+        } else {
+            Err(Error::incorrect(format!(
+                "Expected {}, found {:?}.",
+                symbol,
+                self.peek()
+            )))
+        }
+    }
+
+    fn identifier(&mut self) -> Result<String, Error> {
+        match self.advance()? {
+            Token::Identifier(identifier) => {
+                if RESERVED.contains(&identifier.to_lowercase().as_str()) {
+                    Err(Error::incorrect(format!(
+                        "Unexpected keyword {}.",
+                        identifier.to_uppercase()
+                    )))
+                } else {
+                    Ok(identifier)
+                }
+            }
+            other => Err(Error::incorrect(format!(
+                "Expected an identifier, found {:?}.",
+                other
+            ))),
+        }
+    }
+
+    fn column(&mut self) -> Result<ColumnRef, Error> {
+        let first = self.identifier()?;
+
+        if self.accept_symbol(".") {
+            Ok(ColumnRef {
+                table: Some(first),
+                column: self.identifier()?,
+            })
+        } else {
+            Ok(ColumnRef {
+                table: None,
+                column: first,
+            })
+        }
+    }
+
+    fn select_item(&mut self) -> Result<SelectItem, Error> {
+        let is_call = match self.tokens.get(self.position + 1) {
+            Some(Token::Symbol(s)) => s == "(",
+            _ => false,
+        };
+
+        let item = if is_call {
+            let function = self.identifier()?.to_lowercase();
+            self.expect_symbol("(")?;
+            let argument = if self.accept_symbol("*") {
+                None
+            } else {
+                Some(self.column()?)
+            };
+            self.expect_symbol(")")?;
+
+            SelectItem::Aggregate(function, argument)
+        } else if self.accept_symbol("*") {
+            return Err(Error::unsupported("SELECT *"));
+        } else {
+            SelectItem::Column(self.column()?)
+        };
+
+        // Output names carry no meaning for plans.
+        if self.accept_keyword("as") {
+            self.identifier()?;
+        }
+
+        Ok(item)
+    }
+
+    fn table(&mut self) -> Result<TableRef, Error> {
+        let name = self.identifier()?;
+        let mut alias = name.clone();
+        let mut columns = None;
+
+        let has_alias = self.accept_keyword("as")
+            || match self.peek() {
+                Some(Token::Identifier(identifier)) => {
+                    !RESERVED.contains(&identifier.to_lowercase().as_str())
+                }
+                _ => false,
+            };
+
+        if has_alias {
+            alias = self.identifier()?;
+
+            if self.accept_symbol("(") {
+                let mut names = vec![self.identifier()?];
+                while self.accept_symbol(",") {
+                    names.push(self.identifier()?);
+                }
+                self.expect_symbol(")")?;
+
+                columns = Some(names);
+            }
+        }
+
+        Ok(TableRef {
+            name,
+            alias,
+            columns,
+        })
+    }
+
+    fn operand(&mut self) -> Result<Operand, Error> {
+        match self.peek().cloned() {
+            Some(Token::Number(number)) => {
+                self.position += 1;
+                Ok(Operand::Constant(Edn::Integer(number)))
+            }
+            Some(Token::String(string)) => {
+                self.position += 1;
+                Ok(Operand::Constant(Edn::String(string)))
+            }
+            Some(Token::Identifier(ref identifier)) if identifier.eq_ignore_ascii_case("true") => {
+                self.position += 1;
+                Ok(Operand::Constant(Edn::Bool(true)))
+            }
+            Some(Token::Identifier(ref identifier)) if identifier.eq_ignore_ascii_case("false") => {
+                self.position += 1;
+                Ok(Operand::Constant(Edn::Bool(false)))
+            }
+            _ => Ok(Operand::Column(self.column()?)),
+        }
+    }
+
+    fn operator(&mut self) -> Result<String, Error> {
+        match self.advance()? {
+            Token::Symbol(ref symbol) if symbol == "<>" || symbol == "!=" => Ok("!=".to_string()),
+            Token::Symbol(ref symbol)
+                if symbol == "="
+                    || symbol == "<"
+                    || symbol == "<="
+                    || symbol == ">"
+                    || symbol == ">=" =>
+            {
+                Ok(symbol.clone())
+            }
+            other => Err(Error::incorrect(format!(
+                "Expected a comparison operator, found {:?}.",
+                other
+            ))),
+        }
+    }
+
+    fn conditions(&mut self) -> Result<Vec<Condition>, Error> {
+        let mut conditions = Vec::new();
+
+        loop {
+            if self.peek_keyword("not") {
+                return Err(Error::unsupported("NOT in conditions"));
+            }
+
+            let left = self.operand()?;
+            let operator = self.operator()?;
+            let right = self.operand()?;
+
+            conditions.push(Condition {
+                left,
+                operator,
+                right,
+            });
+
+            if self.peek_keyword("or") {
+                return Err(Error::unsupported("OR in conditions"));
+            } else if !self.accept_keyword("and") {
+                return Ok(conditions);
+            }
+        }
+    }
+
+    fn select(mut self) -> Result<Select, Error> {
+        self.expect_keyword("select")?;
+        let distinct = self.accept_keyword("distinct");
+
+        let mut items = vec![self.select_item()?];
+        while self.accept_symbol(",") {
+            items.push(self.select_item()?);
+        }
+
+        self.expect_keyword("from")?;
+
+        let mut tables = vec![self.table()?];
+        let mut conditions = Vec::new();
+
+        loop {
+            if self.accept_symbol(",") {
+                tables.push(self.table()?);
+            } else if self.accept_keyword("join") {
+                tables.push(self.table()?);
+                self.expect_keyword("on")?;
+                conditions.append(&mut self.conditions()?);
+            } else if self.accept_keyword("inner") {
+                self.expect_keyword("join")?;
+                tables.push(self.table()?);
+                self.expect_keyword("on")?;
+                conditions.append(&mut self.conditions()?);
+            } else {
+                break;
+            }
+        }
+
+        if self.accept_keyword("where") {
+            conditions.append(&mut self.conditions()?);
+        }
+
+        let mut group_by = Vec::new();
+        if self.accept_keyword("group") {
+            self.expect_keyword("by")?;
+            group_by.push(self.column()?);
+            while self.accept_symbol(",") {
+                group_by.push(self.column()?);
+            }
+        }
+
+        let mut having = Vec::new();
+        if self.accept_keyword("having") {
+            loop {
+                let item = self.select_item()?;
+                let operator = self.operator()?;
+                let constant = match self.operand()? {
+                    Operand::Constant(constant) => constant,
+                    Operand::Column(_) => {
+                        return Err(Error::unsupported(
+                            "HAVING conditions comparing against columns",
+                        ));
+                    }
+                };
+
+                having.push((item, operator, constant));
+
+                if !self.accept_keyword("and") {
+                    break;
+                }
+            }
+        }
+
+        if self.peek_keyword("order") || self.peek_keyword("limit") {
+            return Err(Error::unsupported(
+                "ORDER BY and LIMIT on incrementally maintained views",
+            ));
+        }
+
+        self.accept_symbol(";");
+
+        if let Some(token) = self.peek() {
+            return Err(Error::incorrect(format!("Unexpected {:?}.", token)));
+        }
+
+        Ok(Select {
+            distinct,
+            items,
+            tables,
+            conditions,
+            group_by,
+            having,
+        })
+    }
+}
+
+/// Keeps track of which columns denote the same variable.
+struct Columns<'a> {
+    tables: &'a [TableRef],
+    // Union-find style mapping from column keys to their
+    // representative.
+    parents: HashMap<String, String>,
+    referenced: Vec<(String, String)>,
+}
+
+impl<'a> Columns<'a> {
+    fn resolve(&mut self, column: &ColumnRef) -> Result<(String, String), Error> {
+        let table = match column.table {
+            Some(ref alias) => match self.tables.iter().find(|t| &t.alias == alias) {
+                None => return Err(Error::not_found(format!("Unknown table {}.", alias))),
+                Some(table) => table,
+            },
+            None => {
+                let candidates: Vec<&TableRef> = self
+                    .tables
+                    .iter()
+                    .filter(|t| match t.columns {
+                        None => true,
+                        Some(ref columns) => columns.contains(&column.column),
+                    })
+                    .collect();
+
+                if candidates.len() == 1 {
+                    candidates[0]
+                } else {
+                    return Err(Error::incorrect(format!(
+                        "Column {} is ambiguous or unknown, qualify it with a table name.",
+                        column.column
+                    )));
+                }
+            }
+        };
+
+        if let Some(ref columns) = table.columns {
+            if !columns.contains(&column.column) {
+                return Err(Error::not_found(format!(
+                    "Unknown column {}.{}.",
+                    table.alias, column.column
+                )));
+            }
+        }
+
+        let resolved = (table.alias.clone(), column.column.clone());
+        if !self.referenced.contains(&resolved) {
+            self.referenced.push(resolved.clone());
+        }
+
+        Ok(resolved)
+    }
+
+    fn key(alias: &str, column: &str) -> String {
+        format!("{}.{}", alias, column)
+    }
+
+    fn representative(&self, key: &str) -> String {
+        let mut key = key.to_string();
+        while let Some(parent) = self.parents.get(&key) {
+            key = parent.clone();
+        }
+        key
+    }
+
+    fn unify(&mut self, left: &(String, String), right: &(String, String)) {
+        let left = self.representative(&Self::key(&left.0, &left.1));
+        let right = self.representative(&Self::key(&right.0, &right.1));
+
+        if left != right {
+            self.parents.insert(right, left);
+        }
+    }
+
+    fn symbol(&self, column: &(String, String)) -> Edn {
+        let key = self.representative(&Self::key(&column.0, &column.1));
+        Edn::Symbol(format!("?{}", key))
+    }
+}
+
+fn compile<A: AsAid>(select: Select) -> Result<Plan<A>, Error> {
+    let Select {
+        distinct,
+        items,
+        tables,
+        conditions,
+        group_by,
+        having,
+    } = select;
+
+    for (index, table) in tables.iter().enumerate() {
+        if tables[..index].iter().any(|t| t.alias == table.alias) {
+            return Err(Error::incorrect(format!(
+                "Table name {} is used more than once, use an alias.",
+                table.alias
+            )));
+        }
+    }
+
+    let mut columns = Columns {
+        tables: &tables,
+        parents: HashMap::new(),
+        referenced: Vec::new(),
+    };
+
+    // Select items, resolved to columns. COUNT(*) counts rows via
+    // the first column of the first table.
+    let row_column = match tables[0].columns {
+        None => ColumnRef {
+            table: Some(tables[0].alias.clone()),
+            column: "id".to_string(),
+        },
+        Some(ref names) => ColumnRef {
+            table: Some(tables[0].alias.clone()),
+            column: names[0].clone(),
+        },
+    };
+
+    let mut resolved_items = Vec::with_capacity(items.len());
+    for item in items.iter() {
+        match item {
+            SelectItem::Column(column) => {
+                resolved_items.push((None, columns.resolve(column)?));
+            }
+            SelectItem::Aggregate(function, argument) => {
+                let argument = argument.as_ref().unwrap_or(&row_column);
+                resolved_items.push((Some(function.clone()), columns.resolve(argument)?));
+            }
+        }
+    }
+
+    let mut resolved_group_by = Vec::with_capacity(group_by.len());
+    for column in group_by.iter() {
+        resolved_group_by.push(columns.resolve(column)?);
+    }
+
+    // Equalities between columns are expressed by sharing a variable,
+    // all other conditions turn into predicates.
+    let mut predicates = Vec::new();
+    for condition in conditions.into_iter() {
+        match (condition.left, condition.right) {
+            (Operand::Column(left), Operand::Column(right)) => {
+                let left = columns.resolve(&left)?;
+                let right = columns.resolve(&right)?;
+
+                if condition.operator == "=" {
+                    columns.unify(&left, &right);
+                } else {
+                    predicates.push((condition.operator, Ok(left), Ok(right)));
+                }
+            }
+            (Operand::Column(left), Operand::Constant(right)) => {
+                let left = columns.resolve(&left)?;
+                predicates.push((condition.operator, Ok(left), Err(right)));
+            }
+            (Operand::Constant(left), Operand::Column(right)) => {
+                let right = columns.resolve(&right)?;
+                predicates.push((condition.operator, Err(left), Ok(right)));
+            }
+            (Operand::Constant(_), Operand::Constant(_)) => {
+                return Err(Error::incorrect(
+                    "Conditions must refer to at least one column.",
+                ));
+            }
+        }
+    }
+
+    let mut clauses = Vec::new();
+
+    for table in tables.iter() {
+        match table.columns {
+            None => {
+                let entity = columns.symbol(&(table.alias.clone(), "id".to_string()));
+                let attributes: Vec<(String, String)> = columns
+                    .referenced
+                    .iter()
+                    .filter(|(alias, column)| alias == &table.alias && column != "id")
+                    .cloned()
+                    .collect();
+
+                if attributes.is_empty() {
+                    return Err(Error::incorrect(format!(
+                        "Table {} must be used with at least one column other than id.",
+                        table.alias
+                    )));
+                }
+
+                for column in attributes.iter() {
+                    clauses.push(Edn::Vector(vec![
+                        entity.clone(),
+                        Edn::Keyword(format!("{}/{}", table.name, column.1)),
+                        columns.symbol(column),
+                    ]));
+                }
+            }
+            Some(ref names) => {
+                let mut invocation = vec![Edn::Symbol(table.name.clone())];
+                for name in names.iter() {
+                    invocation.push(columns.symbol(&(table.alias.clone(), name.clone())));
+                }
+
+                clauses.push(Edn::List(invocation));
+            }
+        }
+    }
+
+    for (operator, left, right) in predicates.into_iter() {
+        let operand = |x: Result<(String, String), Edn>| match x {
+            Ok(column) => columns.symbol(&column),
+            Err(constant) => constant,
+        };
+
+        clauses.push(Edn::Vector(vec![Edn::List(vec![
+            Edn::Symbol(operator),
+            operand(left),
+            operand(right),
+        ])]));
+    }
+
+    // Assemble the find specification.
+    let is_aggregate = resolved_items
+        .iter()
+        .any(|(function, _)| function.is_some());
+
+    if !is_aggregate && !resolved_group_by.is_empty() {
+        return Err(Error::unsupported("GROUP BY without aggregations"));
+    }
+
+    if !is_aggregate && !having.is_empty() {
+        return Err(Error::incorrect("HAVING requires an aggregation."));
+    }
+
+    let mut find = Vec::with_capacity(resolved_items.len());
+    for (function, column) in resolved_items.iter() {
+        match function {
+            None => {
+                if is_aggregate
+                    && !resolved_group_by
+                        .iter()
+                        .any(|x| columns.symbol(x) == columns.symbol(column))
+                {
+                    return Err(Error::incorrect(format!(
+                        "Column {}.{} must appear in GROUP BY.",
+                        column.0, column.1
+                    )));
+                }
+
+                find.push(columns.symbol(column));
+            }
+            Some(function) => {
+                find.push(Edn::List(vec![
+                    Edn::Symbol(function.clone()),
+                    columns.symbol(column),
+                ]));
+            }
+        }
+    }
+
+    // Grouping by columns that aren't selected requires them to be
+    // part of the aggregation key, we project them away afterwards.
+    let output_arity = find.len();
+    for column in resolved_group_by.iter() {
+        let symbol = columns.symbol(column);
+        if !find.contains(&symbol) {
+            find.push(symbol);
+        }
+    }
+
+    let mut query = vec![Edn::Keyword("find".to_string())];
+    query.append(&mut find);
+    query.push(Edn::Keyword("where".to_string()));
+    query.append(&mut clauses);
+
+    let mut plan: Plan<A> = datalog::compile(&query)?;
+
+    let output_variables = plan.variables();
+
+    for (item, operator, constant) in having.into_iter() {
+        let position = items
+            .iter()
+            .position(|x| x == &item)
+            .ok_or_else(|| Error::unsupported("HAVING on aggregations that are not selected"))?;
+
+        let predicate = datalog::predicate(&operator).unwrap();
+
+        plan = Plan::Filter(Filter {
+            variables: vec![output_variables[position]],
+            predicate,
+            plan: Box::new(plan),
+            constants: vec![None, Some(datalog::constant(&constant)?)],
+        });
+    }
+
+    if output_variables.len() > output_arity {
+        plan = Plan::Project(Project {
+            variables: output_variables[..output_arity].to_vec(),
+            plan: Box::new(plan),
+        });
+    }
+
+    if distinct {
+        plan = Plan::Union(Union {
+            variables: output_variables[..output_arity].to_vec(),
+            plans: vec![plan],
+            mappings: vec![],
+        });
+    }
+
+    Ok(plan)
+}
@@ -0,0 +1,231 @@
+//! Allocation of entity ids. By default, clients choose their own
+//! ids. Deployments with many workers can instead have the server
+//! hand out ids, from ranges that never overlap between workers.
+//!
+//! Server-allocated ids always have their highest bit set, leaving
+//! the lower half of the id space to clients.
+
+use crate::clock;
+use crate::{Eid, Error};
+
+/// Marks ids handed out by the server.
+const ALLOCATED: Eid = 1 << 63;
+
+/// Partitioned ids carry the partition in these many bits, right
+/// below the allocation marker.
+const PARTITION_BITS: u32 = 15;
+const COUNTER_BITS: u32 = 63 - PARTITION_BITS;
+
+/// Squuids are laid out as milliseconds since the Unix epoch,
+/// followed by the partition, followed by a sequence number.
+const SQUUID_PARTITION_BITS: u32 = 10;
+const SQUUID_SEQUENCE_BITS: u32 = 12;
+const SQUUID_MILLIS_BITS: u32 = 63 - SQUUID_PARTITION_BITS - SQUUID_SEQUENCE_BITS;
+
+/// How entity ids come about.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum IdStrategy {
+    /// Clients supply all ids themselves. Ids in the range reserved
+    /// for allocation are rejected, s.t. a deployment can switch
+    /// strategies later on.
+    ClientSupplied,
+    /// Each worker allocates sequentially from its own range.
+    Partitioned,
+    /// Ids are prefixed by the time of their allocation, s.t. they
+    /// sort roughly in order of creation and stay unique across
+    /// restarts.
+    Squuid,
+}
+
+impl Default for IdStrategy {
+    fn default() -> Self {
+        IdStrategy::ClientSupplied
+    }
+}
+
+impl std::str::FromStr for IdStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "client" => Ok(IdStrategy::ClientSupplied),
+            "partitioned" => Ok(IdStrategy::Partitioned),
+            "squuid" => Ok(IdStrategy::Squuid),
+            _ => Err(format!("Unknown id strategy {}.", s)),
+        }
+    }
+}
+
+/// Hands out entity ids on behalf of a single worker, out of the
+/// partition identified by the worker's index.
+#[derive(Clone, Debug)]
+pub struct IdAllocator {
+    strategy: IdStrategy,
+    partition: usize,
+    partitions: usize,
+    next: Eid,
+    last_millis: Eid,
+}
+
+impl IdAllocator {
+    /// Creates an allocator for the specified partition, out of the
+    /// specified total number, e.g. the worker index and peers.
+    pub fn new(strategy: IdStrategy, partition: usize, partitions: usize) -> Self {
+        assert!(partition < partitions);
+
+        IdAllocator {
+            strategy,
+            partition,
+            partitions,
+            next: 0,
+            last_millis: 0,
+        }
+    }
+
+    /// The strategy ids are allocated by.
+    pub fn strategy(&self) -> IdStrategy {
+        self.strategy
+    }
+
+    /// Allocates the specified number of fresh ids.
+    pub fn allocate(&mut self, count: usize) -> Result<Vec<Eid>, Error> {
+        (0..count).map(|_| self.allocate_one()).collect()
+    }
+
+    fn allocate_one(&mut self) -> Result<Eid, Error> {
+        match self.strategy {
+            IdStrategy::ClientSupplied => Err(Error::unsupported(
+                "Ids are supplied by clients in this deployment.",
+            )),
+            IdStrategy::Partitioned => {
+                if self.partition >= 1 << PARTITION_BITS {
+                    return Err(Error::unsupported(format!(
+                        "At most {} partitions are supported.",
+                        1 << PARTITION_BITS
+                    )));
+                }
+
+                if self.next >= 1 << COUNTER_BITS {
+                    return Err(Error::fault("Id range exhausted."));
+                }
+
+                let eid = ALLOCATED | ((self.partition as Eid) << COUNTER_BITS) | self.next;
+                self.next += 1;
+
+                Ok(eid)
+            }
+            IdStrategy::Squuid => {
+                if self.partition >= 1 << SQUUID_PARTITION_BITS {
+                    return Err(Error::unsupported(format!(
+                        "At most {} partitions are supported.",
+                        1 << SQUUID_PARTITION_BITS
+                    )));
+                }
+
+                let millis = now_millis();
+
+                // The clock might go backwards, in which case we keep
+                // counting from where we were.
+                if millis > self.last_millis {
+                    self.last_millis = millis;
+                    self.next = 0;
+                }
+
+                if self.next >= 1 << SQUUID_SEQUENCE_BITS {
+                    // Borrow from the next millisecond.
+                    self.last_millis += 1;
+                    self.next = 0;
+                }
+
+                let eid = ALLOCATED
+                    | (self.last_millis << (SQUUID_PARTITION_BITS + SQUUID_SEQUENCE_BITS))
+                    | ((self.partition as Eid) << SQUUID_SEQUENCE_BITS)
+                    | self.next;
+                self.next += 1;
+
+                Ok(eid)
+            }
+        }
+    }
+
+    /// Takes note of an id found in the data, s.t. ids this worker
+    /// handed out before a restart, which are part of its restored
+    /// state, aren't handed out again.
+    pub fn observe(&mut self, eid: Eid) {
+        if eid & ALLOCATED == 0 {
+            return;
+        }
+
+        match self.strategy {
+            IdStrategy::ClientSupplied => {}
+            IdStrategy::Partitioned => {
+                let partition = (eid & !ALLOCATED) >> COUNTER_BITS;
+
+                if partition == self.partition as Eid {
+                    let counter = eid & ((1 << COUNTER_BITS) - 1);
+                    self.next = std::cmp::max(self.next, counter + 1);
+                }
+            }
+            IdStrategy::Squuid => {
+                let millis = (eid & !ALLOCATED) >> (SQUUID_PARTITION_BITS + SQUUID_SEQUENCE_BITS);
+                let partition = (eid >> SQUUID_SEQUENCE_BITS) & ((1 << SQUUID_PARTITION_BITS) - 1);
+                let sequence = eid & ((1 << SQUUID_SEQUENCE_BITS) - 1);
+
+                if partition == self.partition as Eid {
+                    if millis > self.last_millis {
+                        self.last_millis = millis;
+                        self.next = sequence + 1;
+                    } else if millis == self.last_millis {
+                        self.next = std::cmp::max(self.next, sequence + 1);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Checks that a client-supplied id could have come about under
+    /// this strategy.
+    pub fn validate(&self, eid: Eid) -> Result<(), Error> {
+        if eid & ALLOCATED == 0 {
+            return Ok(());
+        }
+
+        let partition = match self.strategy {
+            IdStrategy::ClientSupplied => {
+                return Err(Error::incorrect(format!(
+                    "Id {} lies in the range reserved for allocation.",
+                    eid
+                )));
+            }
+            IdStrategy::Partitioned => (eid & !ALLOCATED) >> COUNTER_BITS,
+            IdStrategy::Squuid => {
+                let millis = (eid & !ALLOCATED) >> (SQUUID_PARTITION_BITS + SQUUID_SEQUENCE_BITS);
+
+                // Allow for some clock skew between processes.
+                if millis > now_millis() + 60_000 {
+                    return Err(Error::incorrect(format!(
+                        "Id {} was not allocated yet.",
+                        eid
+                    )));
+                }
+
+                (eid >> SQUUID_SEQUENCE_BITS) & ((1 << SQUUID_PARTITION_BITS) - 1)
+            }
+        };
+
+        if partition as usize >= self.partitions {
+            Err(Error::incorrect(format!(
+                "Id {} belongs to an unknown partition.",
+                eid
+            )))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+fn now_millis() -> Eid {
+    let millis = clock::since_unix_epoch().as_millis();
+
+    (millis as Eid) & ((1 << SQUUID_MILLIS_BITS) - 1)
+}
@@ -11,9 +11,16 @@ extern crate log;
 #[macro_use]
 extern crate serde_derive;
 
+pub mod algorithms;
 pub mod binding;
+#[cfg(feature = "client")]
+pub mod client;
+pub mod clock;
 pub mod derive;
 pub mod domain;
+pub mod error;
+pub mod frontends;
+pub mod ids;
 pub mod logging;
 pub mod operators;
 pub mod plan;
@@ -21,6 +28,9 @@ pub mod scheduling;
 pub mod server;
 pub mod sinks;
 pub mod sources;
+#[cfg(feature = "store")]
+pub mod store;
+pub mod testing;
 pub mod timestamp;
 
 use std::collections::{HashMap, HashSet, VecDeque};
@@ -33,7 +43,7 @@ use timely::order::Product;
 use timely::progress::Timestamp;
 
 use differential_dataflow::lattice::Lattice;
-use differential_dataflow::operators::arrange::{ShutdownButton, TraceAgent};
+use differential_dataflow::operators::arrange::{Arranged, ShutdownButton, TraceAgent};
 use differential_dataflow::operators::iterate::Variable;
 #[cfg(not(feature = "set-semantics"))]
 use differential_dataflow::operators::Consolidate;
@@ -48,6 +58,7 @@ pub use num_rational::Rational32;
 
 pub use binding::{AsBinding, AttributeBinding, Binding};
 pub use domain::Domain;
+pub use error::{PlanError, ProtocolError, SourceError, TxError};
 pub use plan::{Hector, Implementable, Plan};
 pub use timestamp::{Rewind, Time};
 
@@ -103,6 +114,13 @@ pub enum Value {
     /// A fixed-precision real number.
     #[cfg(feature = "real")]
     Real(fixed::types::I16F16),
+    /// Refers to the entity holding the specified value of an
+    /// attribute. Only valid in transaction entity positions, where
+    /// it is resolved before any data is introduced.
+    LookupRef(Aid, Box<Value>),
+    /// A point on the earth's surface, as latitude and longitude in
+    /// units of 10^-7 degrees.
+    GeoPoint(i32, i32),
 }
 
 impl Value {
@@ -111,6 +129,19 @@ impl Value {
         Value::Aid(v.to_string())
     }
 
+    /// Helper to create a lookup ref, identifying an entity by its
+    /// value for the specified attribute.
+    pub fn lookup_ref<V: Into<Value>>(a: &str, v: V) -> Self {
+        Value::LookupRef(a.to_string(), Box::new(v.into()))
+    }
+
+    /// Helper to create a geo point from its latitude and longitude
+    /// in degrees.
+    pub fn geo_point(lat: f64, lon: f64) -> Self {
+        let units = domain::spatial::UNITS_PER_DEGREE as f64;
+        Value::GeoPoint((lat * units).round() as i32, (lon * units).round() as i32)
+    }
+
     /// Helper to create a UUID value from a string representation.
     pub fn uuid_str(v: &str) -> Self {
         let uuid = Uuid::parse_str(v).expect("failed to parse UUID");
@@ -143,7 +174,23 @@ impl std::convert::From<Value> for serde_json::Value {
             Value::String(v) => serde_json::Value::String(v),
             Value::Bool(v) => serde_json::Value::Bool(v),
             Value::Number(v) => serde_json::Value::Number(serde_json::Number::from(v)),
-            _ => unimplemented!(),
+            Value::Rational32(v) => {
+                serde_json::Value::from(f64::from(*v.numer()) / f64::from(*v.denom()))
+            }
+            Value::Instant(v) => serde_json::Value::Number(serde_json::Number::from(v)),
+            Value::Uuid(v) => serde_json::Value::String(v.to_hyphenated().to_string()),
+            #[cfg(feature = "real")]
+            Value::Real(v) => serde_json::Value::from(v.to_float::<f64>()),
+            Value::LookupRef(a, v) => {
+                serde_json::Value::Array(vec![serde_json::Value::String(a), (*v).into()])
+            }
+            Value::GeoPoint(lat, lon) => {
+                let units = domain::spatial::UNITS_PER_DEGREE as f64;
+                serde_json::Value::Array(vec![
+                    serde_json::Value::from(f64::from(lat) / units),
+                    serde_json::Value::from(f64::from(lon) / units),
+                ])
+            }
         }
     }
 }
@@ -209,6 +256,14 @@ impl Error {
             message: error.to_string(),
         }
     }
+
+    /// Stop, worker was interrupted.
+    pub fn interrupted<E: std::string::ToString>(error: E) -> Error {
+        Error {
+            category: "df.error.category/interrupted".to_string(),
+            message: error.to_string(),
+        }
+    }
 }
 
 /// Transaction data.
@@ -251,14 +306,53 @@ pub enum Output {
     /// A batch of (tuple, time, diff) triples as returned by Datalog
     /// queries.
     QueryDiff(String, Vec<ResultDiff<Time>>),
+    /// A batch of results, as changes to the tuples identified by
+    /// the interest's key fields.
+    KeyedDiff(String, Vec<sinks::KeyedChange>),
     /// A JSON object, e.g. as returned by GraphQL queries.
     #[cfg(feature = "serde_json")]
     Json(String, serde_json::Value, Time, isize),
     /// A message forwarded to a specific client.
     #[cfg(feature = "serde_json")]
     Message(Client, serde_json::Value),
-    /// An error forwarded to a specific client.
-    Error(Client, Error, server::TxId),
+    /// Signals that the complete initial snapshot of the named
+    /// relation, as of the specified time, has been sent. Everything
+    /// that follows is an incremental diff.
+    InitialComplete(String, Time),
+    /// Signals that all results of the named relation at times before
+    /// the specified frontier have been sent, i.e. that together they
+    /// constitute a consistent view.
+    Progress(String, Vec<Time>),
+    /// A batch of results of a single member relation within the
+    /// named interest group.
+    GroupDiff(String, String, Vec<ResultDiff<Time>>),
+    /// Signals that all results of the named interest group at the
+    /// specified time have been sent and may be applied atomically.
+    Commit(String, Time),
+    /// Acknowledges that all requests of the identified batch have
+    /// been handled, as of the specified transaction.
+    Ack(Client, server::RequestId, server::TxId),
+    /// An error forwarded to a specific client, referencing the
+    /// request batch that caused it, if it was identified.
+    Error(Client, Error, server::TxId, Option<server::RequestId>),
+}
+
+impl Output {
+    /// Returns the same output on the relation of the specified
+    /// name. Outputs addressed to individual clients are unchanged.
+    pub fn renamed(self, name: String) -> Output {
+        match self {
+            Output::QueryDiff(_, results) => Output::QueryDiff(name, results),
+            Output::KeyedDiff(_, changes) => Output::KeyedDiff(name, changes),
+            #[cfg(feature = "serde_json")]
+            Output::Json(_, value, t, diff) => Output::Json(name, value, t, diff),
+            Output::InitialComplete(_, t) => Output::InitialComplete(name, t),
+            Output::Progress(_, frontier) => Output::Progress(name, frontier),
+            Output::GroupDiff(_, member, results) => Output::GroupDiff(name, member, results),
+            Output::Commit(_, t) => Output::Commit(name, t),
+            other => other,
+        }
+    }
 }
 
 /// A trace of values indexed by self.
@@ -271,6 +365,18 @@ pub type TraceValHandle<K, V, T, R> = TraceAgent<OrdValSpine<K, V, T, R>>;
 // synthesized (i.e. that are not fully defined yet).
 type VariableMap<A, S> = HashMap<A, Variable<S, Vec<Value>, isize>>;
 
+// A map for sharing arrangements of sub-plans within a single
+// implementation, keyed by the sub-plan and the variables it is
+// arranged by. Along with each arrangement, the variables bound by
+// its values are kept.
+type ArrangementMap<S> = HashMap<
+    (plan::join::SharedPlan, Vec<Var>),
+    (
+        Vec<Var>,
+        Arranged<S, TraceValHandle<Vec<Value>, Vec<Value>, <S as ScopeParent>::Timestamp, isize>>,
+    ),
+>;
+
 trait Shutdownable {
     fn press(&mut self);
 }
@@ -385,6 +491,19 @@ pub enum QuerySupport {
     AdaptiveWCO = 2,
 }
 
+/// Lifetimes after which datoms are retracted automatically, keeping
+/// attributes such as sessions or caches bounded.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub enum TimeToLive {
+    /// Datoms are retracted once the specified amount of time has
+    /// passed since they were transacted.
+    TxTime(Time),
+    /// Datoms are retracted once the specified amount of time has
+    /// passed since the instant they hold as their value. Only
+    /// supported by attributes in real time domains.
+    Instant(Duration),
+}
+
 /// Per-attribute semantics.
 #[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
 pub struct AttributeConfig {
@@ -398,6 +517,37 @@ pub struct AttributeConfig {
     pub index_direction: IndexDirection,
     /// Query capabilities supported by this attribute.
     pub query_support: QuerySupport,
+    /// How long datoms remain, before they are retracted
+    /// automatically.
+    #[serde(default)]
+    pub time_to_live: Option<TimeToLive>,
+    /// Whether the attribute is kept in the server's store, rather
+    /// than in memory only. Stored attributes are indexed only once a
+    /// query depends on them.
+    #[serde(default)]
+    pub stored: bool,
+    /// Whether to maintain a tokenized full-text index for string
+    /// values, in addition to the regular ones. Required by `Search`
+    /// plans.
+    #[serde(default)]
+    pub fulltext: bool,
+    /// Whether to maintain a spatial index for geo point values, in
+    /// addition to the regular ones. Speeds up `Within` plans on the
+    /// attribute.
+    #[serde(default)]
+    pub spatial: bool,
+    /// Whether datoms are only ever added to the attribute.
+    /// Transactions retracting from monotonic attributes are
+    /// rejected, and retractions by their sources dropped. Counts and
+    /// sums over plans only ever growing can then be maintained
+    /// without retaining their inputs.
+    #[serde(default)]
+    pub monotonic: bool,
+    /// Whether transactions retracting datoms from the attribute are
+    /// rejected. Append-only attributes are monotonic, and their
+    /// traces are compacted even without any slack.
+    #[serde(default)]
+    pub append_only: bool,
 }
 
 impl Default for AttributeConfig {
@@ -407,6 +557,12 @@ impl Default for AttributeConfig {
             trace_slack: None,
             index_direction: IndexDirection::Forward,
             query_support: QuerySupport::Basic,
+            time_to_live: None,
+            stored: false,
+            fulltext: false,
+            spatial: false,
+            monotonic: false,
+            append_only: false,
         }
     }
 }
@@ -447,6 +603,20 @@ impl AttributeConfig {
             ..Default::default()
         }
     }
+
+    /// Returns true iff the attribute holds at most one value per
+    /// entity at any given time.
+    pub fn is_cardinality_one(&self) -> bool {
+        self.input_semantics == InputSemantics::LastWriteWins
+    }
+
+    /// Returns true iff the attribute's values are never retracted,
+    /// neither by its sources nor by its input semantics.
+    pub fn is_monotonic(&self) -> bool {
+        (self.monotonic || self.append_only)
+            && self.input_semantics != InputSemantics::LastWriteWins
+            && self.time_to_live.is_none()
+    }
 }
 
 /// A variable used in a query.
@@ -871,6 +1041,7 @@ pub fn q<A: AsAid + timely::ExchangeData>(
     Plan::Hector(Hector {
         variables: target_variables,
         bindings,
+        order: Default::default(),
     })
 }
 
@@ -881,12 +1052,35 @@ where
     A: AsAid + timely::ExchangeData,
     T: Timestamp + Lattice + Rewind,
 {
+    collect_dependencies_with(domain, names, &[])
+}
+
+/// Returns the rules used in the definition of the specified names,
+/// like `collect_dependencies`, as if the specified candidate rules
+/// had been registered, replacing any registered ones of the same
+/// name.
+pub fn collect_dependencies_with<A, T>(
+    domain: &Domain<A, T>,
+    names: &[A],
+    candidates: &[Rule<A>],
+) -> Result<Vec<Rule<A>>, Error>
+where
+    A: AsAid + timely::ExchangeData,
+    T: Timestamp + Lattice + Rewind,
+{
+    let lookup = |name: &A| {
+        candidates
+            .iter()
+            .find(|rule| rule.name == *name)
+            .or_else(|| domain.rule(name))
+    };
+
     let mut seen = HashSet::new();
     let mut rules = Vec::new();
     let mut queue = VecDeque::new();
 
     for name in names {
-        match domain.rule(name) {
+        match lookup(name) {
             None => {
                 return Err(Error::not_found(format!("Unknown rule {}.", name)));
             }
@@ -901,7 +1095,7 @@ where
         let dependencies = next.plan.dependencies();
         for dep_name in dependencies.names.into_iter() {
             if !seen.contains(&dep_name) {
-                match domain.rule(&dep_name) {
+                match lookup(&dep_name) {
                     None => {
                         return Err(Error::not_found(format!("Unknown rule {}", dep_name)));
                     }
@@ -935,6 +1129,23 @@ pub fn implement<A, S>(
     domain: &mut Domain<A, S::Timestamp>,
     name: A,
 ) -> Result<(HashMap<A, Collection<S, Vec<Value>, isize>>, ShutdownHandle), Error>
+where
+    A: AsAid + timely::ExchangeData,
+    S: Scope,
+    S::Timestamp: Timestamp + Lattice + Rewind + Default,
+{
+    implement_with(scope, domain, name, &[])
+}
+
+/// Takes a query plan and turns it into a differential dataflow, like
+/// `implement`, as if the specified candidate rules had been
+/// registered.
+pub fn implement_with<A, S>(
+    scope: &mut S,
+    domain: &mut Domain<A, S::Timestamp>,
+    name: A,
+    candidates: &[Rule<A>],
+) -> Result<(HashMap<A, Collection<S, Vec<Value>, isize>>, ShutdownHandle), Error>
 where
     A: AsAid + timely::ExchangeData,
     S: Scope,
@@ -942,9 +1153,10 @@ where
 {
     scope.iterative::<u64, _, _>(|nested| {
         let publish = vec![name.clone()];
-        let mut rules = collect_dependencies(domain, &publish[..])?;
+        let mut rules = collect_dependencies_with(domain, &publish[..], candidates)?;
 
         let mut local_arrangements = VariableMap::new();
+        let mut arrangements = ArrangementMap::new();
         let mut result_map = HashMap::new();
 
         // Step 0: Canonicalize, check uniqueness of bindings.
@@ -990,7 +1202,9 @@ where
         let mut shutdown_handle = ShutdownHandle::empty();
         for rule in rules.iter() {
             info!("planning {:?}", rule.name);
-            let (relation, shutdown) = rule.plan.implement(nested, domain, &local_arrangements);
+            let (relation, shutdown) =
+                rule.plan
+                    .implement(nested, domain, &local_arrangements, &mut arrangements)?;
 
             executions.push(relation);
             shutdown_handle.merge_with(shutdown);
@@ -1028,6 +1242,22 @@ pub fn implement_neu<A, S>(
     domain: &mut Domain<A, S::Timestamp>,
     name: A,
 ) -> Result<(HashMap<A, Collection<S, Vec<Value>, isize>>, ShutdownHandle), Error>
+where
+    A: AsAid + timely::ExchangeData,
+    S: Scope,
+    S::Timestamp: Timestamp + Lattice + Rewind + Default,
+{
+    implement_neu_with(scope, domain, name, &[])
+}
+
+/// Like `implement_neu`, as if the specified candidate rules had been
+/// registered.
+pub fn implement_neu_with<A, S>(
+    scope: &mut S,
+    domain: &mut Domain<A, S::Timestamp>,
+    name: A,
+    candidates: &[Rule<A>],
+) -> Result<(HashMap<A, Collection<S, Vec<Value>, isize>>, ShutdownHandle), Error>
 where
     A: AsAid + timely::ExchangeData,
     S: Scope,
@@ -1035,9 +1265,10 @@ where
 {
     scope.iterative::<u64, _, _>(move |nested| {
         let publish = vec![name.clone()];
-        let mut rules = collect_dependencies(domain, &publish[..])?;
+        let mut rules = collect_dependencies_with(domain, &publish[..], candidates)?;
 
         let mut local_arrangements = VariableMap::new();
+        let mut arrangements = ArrangementMap::new();
         let mut result_map = HashMap::new();
 
         // Step 0: Canonicalize, check uniqueness of bindings.
@@ -1092,9 +1323,10 @@ where
         for rule in rules.iter() {
             info!("neu_planning {:?}", rule.name);
 
-            let plan = q(rule.plan.variables(), rule.plan.into_bindings());
+            let plan = q(rule.plan.variables(), rule.plan.into_bindings()?);
 
-            let (relation, shutdown) = plan.implement(nested, domain, &local_arrangements);
+            let (relation, shutdown) =
+                plan.implement(nested, domain, &local_arrangements, &mut arrangements)?;
 
             executions.push(relation);
             shutdown_handle.merge_with(shutdown);
@@ -0,0 +1,125 @@
+//! Operator folding the values of each key into a single one,
+//! without retaining them.
+
+use std::collections::HashMap;
+
+use timely::dataflow::channels::pact::Exchange;
+use timely::dataflow::operators::generic::operator::Operator;
+use timely::dataflow::operators::Capability;
+use timely::dataflow::Scope;
+
+use differential_dataflow::consolidation::consolidate;
+use differential_dataflow::hashable::Hashable;
+use differential_dataflow::lattice::Lattice;
+use differential_dataflow::{AsCollection, Collection};
+
+use crate::Value;
+
+/// Provides the `accumulate` method.
+pub trait Accumulate<S: Scope> {
+    /// Maintains a single value per key, by folding each change to
+    /// the key's values into the previous result via the provided
+    /// logic. Unlike a reduction, only the current result is kept
+    /// around, which is only sound for folds able to account for
+    /// retractions, or for inputs that never retract anything.
+    /// Keys left without values disappear.
+    fn accumulate<F>(&self, logic: F) -> Collection<S, (Vec<Value>, Value), isize>
+    where
+        F: Fn(Option<&Value>, &Value, isize) -> Value + 'static;
+}
+
+impl<S> Accumulate<S> for Collection<S, (Vec<Value>, Value), isize>
+where
+    S: Scope,
+    S::Timestamp: Lattice + Ord,
+{
+    fn accumulate<F>(&self, logic: F) -> Collection<S, (Vec<Value>, Value), isize>
+    where
+        F: Fn(Option<&Value>, &Value, isize) -> Value + 'static,
+    {
+        let mut stash: HashMap<S::Timestamp, (Capability<S::Timestamp>, Vec<_>)> = HashMap::new();
+        let mut buffer = Vec::new();
+
+        // Number of values and current result, per key.
+        let mut results: HashMap<Vec<Value>, (isize, Value)> = HashMap::new();
+
+        let exchange = Exchange::new(|update: &((Vec<Value>, Value), S::Timestamp, isize)| {
+            (update.0).0.hashed()
+        });
+
+        self.inner
+            .unary_frontier(exchange, "Accumulate", move |_cap, _info| {
+                move |input, output| {
+                    input.for_each(|cap, data| {
+                        data.swap(&mut buffer);
+                        for (update, time, diff) in buffer.drain(..) {
+                            stash
+                                .entry(time.clone())
+                                .or_insert_with(|| (cap.delayed(&time), Vec::new()))
+                                .1
+                                .push((update, diff));
+                        }
+                    });
+
+                    let frontier = input.frontier.frontier();
+                    let mut complete: Vec<S::Timestamp> = stash
+                        .keys()
+                        .filter(|t| !frontier.less_equal(t))
+                        .cloned()
+                        .collect();
+
+                    // Results depend on all earlier changes, so times
+                    // must be folded in order.
+                    complete.sort();
+
+                    for time in complete.into_iter() {
+                        let (cap, mut updates) = stash.remove(&time).unwrap();
+                        consolidate(&mut updates);
+
+                        let mut session = output.session(&cap);
+                        let mut start = 0;
+                        while start < updates.len() {
+                            let key = &(updates[start].0).0;
+                            let end = updates[start..]
+                                .iter()
+                                .position(|((other, _v), _diff)| other != key)
+                                .map(|offset| start + offset)
+                                .unwrap_or_else(|| updates.len());
+
+                            let previous = results.remove(key);
+                            let mut count = previous.as_ref().map(|p| p.0).unwrap_or(0);
+                            let mut result = previous.as_ref().map(|p| p.1.clone());
+
+                            for ((_key, value), diff) in updates[start..end].iter() {
+                                count += diff;
+                                result = Some(logic(result.as_ref(), value, *diff));
+                            }
+
+                            let result = if count > 0 { result } else { None };
+
+                            if previous.as_ref().map(|p| &p.1) != result.as_ref() {
+                                if let Some((_count, ref previous)) = previous {
+                                    session.give((
+                                        (key.clone(), previous.clone()),
+                                        time.clone(),
+                                        -1,
+                                    ));
+                                }
+
+                                if let Some(ref result) = result {
+                                    session.give(((key.clone(), result.clone()), time.clone(), 1));
+                                }
+                            }
+
+                            if let Some(result) = result {
+                                results.insert(key.clone(), (count, result));
+                            }
+
+                            start = end;
+                        }
+                    }
+                }
+            })
+            .as_collection()
+    }
+}
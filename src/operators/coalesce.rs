@@ -0,0 +1,152 @@
+//! Operator consolidating chatty result diffs into fewer batches.
+
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+use std::time::Duration;
+
+use timely::dataflow::channels::pact::Pipeline;
+use timely::dataflow::operators::generic::operator::Operator;
+use timely::dataflow::{Scope, Stream};
+use timely::order::PartialOrder;
+
+use differential_dataflow::consolidation::consolidate;
+use differential_dataflow::lattice::Lattice;
+
+use crate::clock::Instant;
+use crate::scheduling::Scheduler;
+use crate::{ResultDiff, Value};
+
+/// Policies for coalescing the results of a subscription.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub enum CoalescePolicy {
+    /// Consolidate whatever became complete whenever the frontier
+    /// advances.
+    Frontier,
+    /// Consolidate whatever became complete within windows of the
+    /// specified wall-clock duration, i.e. release at most once per
+    /// window. The first release happens right away.
+    Window(Duration),
+}
+
+/// Provides the `coalesce` method.
+pub trait Coalesce<S: Scope> {
+    /// Consolidates all diffs that became complete since the last
+    /// release according to the specified policy, reporting each
+    /// tuple at most once per release, at the latest time involved.
+    fn coalesce(
+        &self,
+        policy: &CoalescePolicy,
+        scheduler: Weak<RefCell<Scheduler<S::Timestamp>>>,
+    ) -> Stream<S, ResultDiff<S::Timestamp>>;
+}
+
+impl<S> Coalesce<S> for Stream<S, ResultDiff<S::Timestamp>>
+where
+    S: Scope,
+    S::Timestamp: Lattice,
+{
+    fn coalesce(
+        &self,
+        policy: &CoalescePolicy,
+        scheduler: Weak<RefCell<Scheduler<S::Timestamp>>>,
+    ) -> Stream<S, ResultDiff<S::Timestamp>> {
+        let window = match *policy {
+            CoalescePolicy::Frontier => None,
+            CoalescePolicy::Window(window) => Some(window),
+        };
+
+        let scope = self.scope();
+
+        let mut pending: Vec<ResultDiff<S::Timestamp>> = Vec::new();
+        let mut buffer = Vec::new();
+        let mut cap = None;
+        let mut released_at: Option<Instant> = None;
+
+        self.unary_frontier(Pipeline, "Coalesce", move |_cap, info| {
+            let activator = Rc::new(scope.activator_for(&info.address[..]));
+
+            move |input, output| {
+                input.for_each(|time, data| {
+                    data.swap(&mut buffer);
+                    pending.append(&mut buffer);
+
+                    cap = match cap.take() {
+                        None => Some(time.retain()),
+                        Some(cap) => {
+                            if time.time().less_than(cap.time()) {
+                                Some(time.retain())
+                            } else {
+                                Some(cap)
+                            }
+                        }
+                    };
+                });
+
+                if cap.is_none() {
+                    return;
+                }
+
+                if let (Some(window), Some(released_at)) = (window, released_at) {
+                    let elapsed = released_at.elapsed();
+
+                    if elapsed < window {
+                        // Make sure we get to release eventually, even
+                        // if nothing else happens in the meantime.
+                        if let Some(scheduler) = scheduler.upgrade() {
+                            scheduler
+                                .borrow_mut()
+                                .realtime
+                                .schedule_after(window - elapsed, Rc::downgrade(&activator));
+                        }
+
+                        return;
+                    }
+                }
+
+                let frontier = input.frontier.frontier();
+                let (complete, incomplete): (Vec<_>, Vec<_>) = pending
+                    .drain(..)
+                    .partition(|(_, t, _)| !frontier.less_equal(t));
+
+                pending = incomplete;
+
+                if let Some(time) = complete
+                    .iter()
+                    .map(|(_, t, _)| t.clone())
+                    .fold(None, |acc: Option<S::Timestamp>, t| {
+                        Some(acc.map(|acc| acc.join(&t)).unwrap_or(t))
+                    })
+                {
+                    let mut consolidated: Vec<(Vec<Value>, isize)> = complete
+                        .into_iter()
+                        .map(|(tuple, _, diff)| (tuple, diff))
+                        .collect();
+
+                    consolidate(&mut consolidated);
+
+                    let cap = cap.as_ref().unwrap();
+                    output.session(&cap.delayed(&time)).give_iterator(
+                        consolidated
+                            .drain(..)
+                            .map(|(tuple, diff)| (tuple, time.clone(), diff)),
+                    );
+
+                    released_at = Some(Instant::now());
+                }
+
+                // Hold on to a capability covering whatever remains.
+                let lower = pending
+                    .iter()
+                    .map(|(_, t, _)| t.clone())
+                    .fold(None, |acc: Option<S::Timestamp>, t| {
+                        Some(acc.map(|acc| acc.meet(&t)).unwrap_or(t))
+                    });
+
+                match lower {
+                    None => cap = None,
+                    Some(lower) => cap.as_mut().unwrap().downgrade(&lower),
+                }
+            }
+        })
+    }
+}
@@ -0,0 +1,43 @@
+//! Operator replacing results by their number.
+
+use timely::dataflow::{Scope, Stream};
+
+use differential_dataflow::lattice::Lattice;
+use differential_dataflow::operators::Count;
+use differential_dataflow::AsCollection;
+
+use crate::{ResultDiff, Value};
+
+/// Provides the `count_by` method.
+pub trait CountBy<S: Scope> {
+    /// Maintains the number of results per distinct combination of
+    /// values in the specified tuple fields, as tuples of those
+    /// values followed by the count. No fields at all count the
+    /// results overall. Groups without results disappear. Fields a
+    /// tuple doesn't have are skipped.
+    fn count_by(&self, fields: &[usize]) -> Stream<S, ResultDiff<S::Timestamp>>;
+}
+
+impl<S> CountBy<S> for Stream<S, ResultDiff<S::Timestamp>>
+where
+    S: Scope,
+    S::Timestamp: Lattice + Ord,
+{
+    fn count_by(&self, fields: &[usize]) -> Stream<S, ResultDiff<S::Timestamp>> {
+        let fields = fields.to_vec();
+
+        self.as_collection()
+            .map(move |tuple| {
+                fields
+                    .iter()
+                    .filter_map(|field| tuple.get(*field).cloned())
+                    .collect::<Vec<Value>>()
+            })
+            .count()
+            .map(|(mut key, count)| {
+                key.push(Value::Number(count as i64));
+                key
+            })
+            .inner
+    }
+}
@@ -0,0 +1,62 @@
+//! Operator retracting attribute pairs after their time to live.
+
+use std::time::Duration;
+
+use timely::dataflow::operators::Map;
+use timely::dataflow::Scope;
+
+use differential_dataflow::lattice::Lattice;
+use differential_dataflow::operators::Threshold;
+use differential_dataflow::{AsCollection, Collection};
+
+use crate::timestamp::{Rewind, Time};
+use crate::{TimeToLive, Value};
+
+/// Provides the `expire` method.
+pub trait Expire<S: Scope> {
+    /// Retracts each pair once its time to live has passed. Instants
+    /// are related to domain times via the specified epoch, the Unix
+    /// time corresponding to the domain's zero. Pairs that have been
+    /// retracted in the meantime stay retracted.
+    fn expire(&self, ttl: &TimeToLive, epoch: Duration) -> Collection<S, (Value, Value), isize>;
+}
+
+impl<S> Expire<S> for Collection<S, (Value, Value), isize>
+where
+    S: Scope,
+    S::Timestamp: Lattice + Ord + Rewind,
+{
+    fn expire(&self, ttl: &TimeToLive, epoch: Duration) -> Collection<S, (Value, Value), isize> {
+        let expired = match *ttl {
+            TimeToLive::TxTime(ref ttl) => {
+                let ttl: S::Timestamp = ttl.clone().into();
+
+                self.inner
+                    .map(move |(datum, t, diff)| (datum, t.fast_forward(ttl.clone()), -diff))
+                    .as_collection()
+            }
+            TimeToLive::Instant(ttl) => self
+                .inner
+                .flat_map(move |((e, v), t, diff)| {
+                    let expires_at = match v {
+                        Value::Instant(millis) => Duration::from_millis(millis) + ttl,
+                        _ => return None,
+                    };
+
+                    // Instants before the epoch have already expired.
+                    let expires_at: S::Timestamp = match expires_at.checked_sub(epoch) {
+                        None => t.clone(),
+                        Some(expires_at) => t.join(&Time::Real(expires_at).into()),
+                    };
+
+                    Some(((e, v), expires_at, -diff))
+                })
+                .as_collection(),
+        };
+
+        // Scheduled retractions of pairs that were retracted early
+        // would otherwise leave negative multiplicities behind.
+        self.concat(&expired)
+            .threshold(|_datum, count| if *count > 0 { *count } else { 0 })
+    }
+}
@@ -1,6 +1,22 @@
 //! Extension traits for `Stream` implementing various
 //! declarative-specific operators.
 
+mod accumulate;
+mod coalesce;
+mod count_by;
+mod expire;
 mod last_write_wins;
+mod snapshot;
+mod summarize;
+mod transitions;
+mod validated;
 
+pub use accumulate::Accumulate;
+pub use coalesce::{Coalesce, CoalescePolicy};
+pub use count_by::CountBy;
+pub use expire::Expire;
 pub use last_write_wins::LastWriteWins;
+pub use snapshot::Snapshot;
+pub use summarize::Summarize;
+pub use transitions::Transitions;
+pub use validated::{Validated, Validation};
@@ -0,0 +1,78 @@
+//! Operator collapsing historical updates into a single snapshot.
+
+use timely::dataflow::channels::pact::Pipeline;
+use timely::dataflow::operators::generic::operator::Operator;
+use timely::dataflow::{Scope, Stream};
+use timely::order::PartialOrder;
+
+use differential_dataflow::consolidation::consolidate;
+use differential_dataflow::lattice::Lattice;
+
+use crate::{ResultDiff, Value};
+
+/// Provides the `snapshot` method.
+pub trait Snapshot<S: Scope> {
+    /// Consolidates all updates at times not beyond `as_of` into a
+    /// single batch at `as_of`, which is released once no more such
+    /// updates can arrive. Later updates are held back until then,
+    /// and are forwarded unchanged afterwards.
+    fn snapshot(&self, as_of: S::Timestamp) -> Stream<S, ResultDiff<S::Timestamp>>;
+}
+
+impl<S> Snapshot<S> for Stream<S, ResultDiff<S::Timestamp>>
+where
+    S: Scope,
+    S::Timestamp: Lattice,
+{
+    fn snapshot(&self, as_of: S::Timestamp) -> Stream<S, ResultDiff<S::Timestamp>> {
+        let mut snapshot: Vec<(Vec<Value>, isize)> = Vec::new();
+        let mut pending = Vec::new();
+        let mut buffer = Vec::new();
+
+        self.unary_frontier(Pipeline, "Snapshot", move |cap, _info| {
+            // We hold on to a capability at `as_of` for as long as
+            // the snapshot is incomplete.
+            let mut cap = Some(cap.delayed(&as_of));
+
+            move |input, output| {
+                input.for_each(|time, data| {
+                    data.swap(&mut buffer);
+
+                    if cap.is_some() {
+                        for (tuple, t, diff) in buffer.drain(..) {
+                            if as_of.less_equal(&t) {
+                                pending.push((tuple, t, diff));
+                            } else {
+                                snapshot.push((tuple, diff));
+                            }
+                        }
+                    } else {
+                        output.session(&time).give_vec(&mut buffer);
+                    }
+                });
+
+                let is_complete = input
+                    .frontier
+                    .frontier()
+                    .iter()
+                    .all(|t| as_of.less_equal(t));
+
+                if is_complete {
+                    if let Some(cap) = cap.take() {
+                        consolidate(&mut snapshot);
+
+                        output.session(&cap).give_iterator(
+                            snapshot
+                                .drain(..)
+                                .map(|(tuple, diff)| (tuple, as_of.clone(), diff)),
+                        );
+
+                        for (tuple, t, diff) in pending.drain(..) {
+                            output.session(&cap.delayed(&t)).give((tuple, t, diff));
+                        }
+                    }
+                }
+            }
+        })
+    }
+}
@@ -0,0 +1,79 @@
+//! Operator summarizing a collection into a single statistics tuple.
+
+use timely::dataflow::channels::pact::Pipeline;
+use timely::dataflow::operators::generic::operator::Operator;
+use timely::dataflow::Scope;
+
+use differential_dataflow::lattice::Lattice;
+use differential_dataflow::operators::{Count, Join, Reduce, Threshold};
+use differential_dataflow::{AsCollection, Collection};
+
+use crate::clock;
+use crate::Value;
+
+/// Provides the `summarize` method.
+pub trait Summarize<S: Scope> {
+    /// Maintains a single tuple of the specified name, followed by
+    /// the number of values, the number of distinct values, and the
+    /// wall-clock instant at which the values were last seen
+    /// changing. Empty collections aren't summarized at all.
+    fn summarize(&self, name: Value) -> Collection<S, Vec<Value>, isize>;
+}
+
+impl<S> Summarize<S> for Collection<S, Value, isize>
+where
+    S: Scope,
+    S::Timestamp: Lattice + Ord,
+{
+    fn summarize(&self, name: Value) -> Collection<S, Vec<Value>, isize> {
+        let count = {
+            let name = name.clone();
+            self.map(move |_| name.clone()).count()
+        };
+
+        let distinct = {
+            let name = name.clone();
+            self.distinct().map(move |_| name.clone()).count()
+        };
+
+        // Each worker replaces the instant at which it last saw an
+        // update, the latest of which is the one reported.
+        let mut last: Option<Value> = None;
+        let last_update: Collection<S, (Value, Value), isize> = self
+            .inner
+            .unary(Pipeline, "LastUpdate", move |_cap, _info| {
+                move |input, output| {
+                    input.for_each(|cap, _data| {
+                        let millis = clock::since_unix_epoch().as_millis();
+                        let now = Value::Instant(millis as u64);
+
+                        let time = cap.time().clone();
+                        let mut session = output.session(&cap);
+
+                        if let Some(previous) = last.replace(now.clone()) {
+                            session.give(((name.clone(), previous), time.clone(), -1));
+                        }
+
+                        session.give(((name.clone(), now), time, 1));
+                    });
+                }
+            })
+            .as_collection()
+            .reduce(|_name, input, output| {
+                let (latest, _count) = input[input.len() - 1];
+                output.push((latest.clone(), 1));
+            });
+
+        count
+            .join(&distinct)
+            .join(&last_update)
+            .map(|(name, ((count, distinct), last))| {
+                vec![
+                    name,
+                    Value::Number(count as i64),
+                    Value::Number(distinct as i64),
+                    last,
+                ]
+            })
+    }
+}
@@ -0,0 +1,102 @@
+//! Operator turning changes of keyed values into transition events.
+
+use std::collections::HashMap;
+
+use timely::dataflow::channels::pact::Exchange;
+use timely::dataflow::operators::generic::operator::Operator;
+use timely::dataflow::operators::Capability;
+use timely::dataflow::Scope;
+
+use differential_dataflow::consolidation::consolidate;
+use differential_dataflow::hashable::Hashable;
+use differential_dataflow::lattice::Lattice;
+use differential_dataflow::{AsCollection, Collection};
+
+use crate::Value;
+
+/// Provides the `transitions` method.
+pub trait Transitions<S: Scope> {
+    /// Emits an (old, new) transition whenever a key's value is
+    /// replaced by another one, at the time it is replaced. Keys
+    /// appearing or disappearing entirely don't transition. Keys
+    /// replacing several values at once transition from each of them
+    /// to each of the new ones. Each transition counts as often as
+    /// both of its values changed, i.e. with the smaller of the
+    /// magnitudes of their diffs.
+    ///
+    /// Transitions are never retracted, i.e. the output accumulates
+    /// the history of all transitions.
+    fn transitions(&self) -> Collection<S, (Vec<Value>, (Value, Value)), isize>;
+}
+
+impl<S> Transitions<S> for Collection<S, (Vec<Value>, Value), isize>
+where
+    S: Scope,
+    S::Timestamp: Lattice,
+{
+    fn transitions(&self) -> Collection<S, (Vec<Value>, (Value, Value)), isize> {
+        let mut stash: HashMap<S::Timestamp, (Capability<S::Timestamp>, Vec<_>)> = HashMap::new();
+        let mut buffer = Vec::new();
+
+        let exchange = Exchange::new(|update: &((Vec<Value>, Value), S::Timestamp, isize)| {
+            (update.0).0.hashed()
+        });
+
+        self.inner
+            .unary_frontier(exchange, "Transitions", move |_cap, _info| {
+                move |input, output| {
+                    input.for_each(|cap, data| {
+                        data.swap(&mut buffer);
+                        for (update, time, diff) in buffer.drain(..) {
+                            stash
+                                .entry(time.clone())
+                                .or_insert_with(|| (cap.delayed(&time), Vec::new()))
+                                .1
+                                .push((update, diff));
+                        }
+                    });
+
+                    let frontier = input.frontier.frontier();
+                    let complete: Vec<S::Timestamp> = stash
+                        .keys()
+                        .filter(|t| !frontier.less_equal(t))
+                        .cloned()
+                        .collect();
+
+                    for time in complete.into_iter() {
+                        let (cap, mut updates) = stash.remove(&time).unwrap();
+                        consolidate(&mut updates);
+
+                        // Consolidated updates are sorted by key, s.t.
+                        // each key's changes form a single run.
+                        let mut session = output.session(&cap);
+                        let mut start = 0;
+                        while start < updates.len() {
+                            let key = &(updates[start].0).0;
+                            let end = updates[start..]
+                                .iter()
+                                .position(|((other, _v), _diff)| other != key)
+                                .map(|offset| start + offset)
+                                .unwrap_or_else(|| updates.len());
+
+                            let run = &updates[start..end];
+                            for ((_key, old), retracted) in run.iter().filter(|(_, diff)| *diff < 0)
+                            {
+                                for ((_key, new), added) in run.iter().filter(|(_, diff)| *diff > 0)
+                                {
+                                    session.give((
+                                        (key.clone(), (old.clone(), new.clone())),
+                                        time.clone(),
+                                        std::cmp::min(-retracted, *added),
+                                    ));
+                                }
+                            }
+
+                            start = end;
+                        }
+                    }
+                }
+            })
+            .as_collection()
+    }
+}
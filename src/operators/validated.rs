@@ -0,0 +1,150 @@
+//! Operator holding back results until the transactions they stem
+//! from have been checked against all constraints.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use timely::dataflow::channels::pact::Exchange;
+use timely::dataflow::operators::generic::operator::Operator;
+use timely::dataflow::operators::Capability;
+use timely::dataflow::{Scope, Stream};
+use timely::order::PartialOrder;
+use timely::progress::frontier::AntichainRef;
+use timely::scheduling::Activator;
+
+use differential_dataflow::consolidation::consolidate;
+use differential_dataflow::lattice::Lattice;
+
+use crate::{ResultDiff, Value};
+
+/// The outcome of constraint checks, as far as they are known on the
+/// worker rolling back violating transactions.
+pub struct Validation<T> {
+    /// Times up to which all transactions have been checked.
+    pub decided: Vec<T>,
+    /// Times of transactions that were rolled back, along with the
+    /// epoch at which the rollback happened.
+    pub rollbacks: Vec<(T, T)>,
+    /// Operators to wake up once more is known.
+    pub activators: Vec<Activator>,
+}
+
+impl<T: Lattice> Validation<T> {
+    /// Creates a validation with nothing decided yet.
+    pub fn new() -> Self {
+        Validation {
+            decided: vec![<T as Lattice>::minimum()],
+            rollbacks: Vec::new(),
+            activators: Vec::new(),
+        }
+    }
+
+    /// Returns the time at which updates happening at the specified
+    /// time become visible. These are moved past the rollback of any
+    /// violating transaction they might depend upon, s.t. the
+    /// violation and its rollback cancel out.
+    pub fn visible_at(&self, t: &T) -> T {
+        let mut visible_at = t.clone();
+
+        loop {
+            let rollback = self.rollbacks.iter().find(|(violated, rolled_back)| {
+                violated.less_equal(&visible_at) && !rolled_back.less_equal(&visible_at)
+            });
+
+            match rollback {
+                None => return visible_at,
+                Some((_, rolled_back)) => visible_at = visible_at.join(rolled_back),
+            }
+        }
+    }
+
+    /// Wakes up all operators waiting on the outcome of checks.
+    pub fn notify(&self) {
+        for activator in self.activators.iter() {
+            activator.activate();
+        }
+    }
+}
+
+/// Provides the `validated` method.
+pub trait Validated<S: Scope> {
+    /// Holds back results until the outcome of all transactions they
+    /// might depend upon is known. Results at times of transactions
+    /// that were rolled back are moved up to the time of the
+    /// rollback, and consolidated there, s.t. violating data never
+    /// becomes visible. Results are gathered on the first worker,
+    /// which is the one rolling back transactions.
+    fn validated(
+        &self,
+        validation: Rc<RefCell<Validation<S::Timestamp>>>,
+    ) -> Stream<S, ResultDiff<S::Timestamp>>;
+}
+
+impl<S> Validated<S> for Stream<S, ResultDiff<S::Timestamp>>
+where
+    S: Scope,
+    S::Timestamp: Lattice,
+{
+    fn validated(
+        &self,
+        validation: Rc<RefCell<Validation<S::Timestamp>>>,
+    ) -> Stream<S, ResultDiff<S::Timestamp>> {
+        let mut stash: Vec<(Capability<S::Timestamp>, Vec<ResultDiff<S::Timestamp>>)> = Vec::new();
+        let mut buffer = Vec::new();
+
+        let scope = self.scope();
+        let pact = Exchange::new(|_| 0);
+
+        self.unary_frontier(pact, "Validated", move |_cap, info| {
+            let activator = scope.activator_for(&info.address[..]);
+            validation.borrow_mut().activators.push(activator);
+
+            move |input, output| {
+                input.for_each(|cap, data| {
+                    data.swap(&mut buffer);
+                    stash.push((cap.retain(), buffer.drain(..).collect()));
+                });
+
+                let validation = validation.borrow();
+                let decided = AntichainRef::new(&validation.decided);
+                let frontier = input.frontier.frontier();
+
+                let mut ready: Vec<((Vec<Value>, S::Timestamp), isize)> = Vec::new();
+                let mut caps: Vec<Capability<S::Timestamp>> = Vec::new();
+
+                for (cap, updates) in stash.iter_mut() {
+                    let mut held = Vec::new();
+
+                    for (tuple, t, diff) in updates.drain(..) {
+                        let visible_at = validation.visible_at(&t);
+
+                        if decided.less_equal(&visible_at) || frontier.less_equal(&visible_at) {
+                            held.push((tuple, t, diff));
+                        } else {
+                            if !caps.iter().any(|cap| *cap.time() == visible_at) {
+                                caps.push(cap.delayed(&visible_at));
+                            }
+
+                            ready.push(((tuple, visible_at), diff));
+                        }
+                    }
+
+                    *updates = held;
+                }
+
+                stash.retain(|(_cap, updates)| !updates.is_empty());
+
+                consolidate(&mut ready);
+
+                for cap in caps.iter() {
+                    output.session(cap).give_iterator(
+                        ready
+                            .iter()
+                            .filter(|((_tuple, t), _diff)| t == cap.time())
+                            .map(|((tuple, t), diff)| (tuple.clone(), t.clone(), *diff)),
+                    );
+                }
+            }
+        })
+    }
+}
@@ -7,13 +7,18 @@ use timely::progress::Timestamp;
 use differential_dataflow::difference::DiffPair;
 use differential_dataflow::lattice::Lattice;
 use differential_dataflow::operators::Join as JoinMap;
-use differential_dataflow::operators::{Count, Reduce};
+use differential_dataflow::operators::{Count, Reduce, Threshold};
 
 use crate::binding::{AsBinding, Binding};
 use crate::domain::Domain;
-use crate::plan::{Dependencies, Implementable};
+use crate::operators::Accumulate as _;
+use crate::plan::{ensure_bound, Dependencies, Implementable};
 use crate::timestamp::Rewind;
-use crate::{CollectionRelation, Implemented, Relation, ShutdownHandle, Value, Var, VariableMap};
+use crate::{
+    ArrangementMap, CollectionRelation, Implemented, Relation, ShutdownHandle, Value, Var,
+    VariableMap,
+};
+use crate::{Error, PlanError};
 
 use num_rational::{Ratio, Rational32};
 
@@ -38,6 +43,44 @@ pub enum AggregationFn {
     // STDDEV,
 }
 
+/// Folds a change to a group's values into its previous result.
+type Fold = fn(Option<&Value>, &Value, isize) -> Value;
+
+fn fold_count(count: Option<&Value>, _value: &Value, diff: isize) -> Value {
+    match count {
+        Some(Value::Number(count)) => Value::Number(count + diff as i64),
+        _ => Value::Number(diff as i64),
+    }
+}
+
+fn fold_sum(sum: Option<&Value>, value: &Value, diff: isize) -> Value {
+    let sum = match sum {
+        Some(Value::Number(sum)) => *sum,
+        _ => 0,
+    };
+
+    match *value {
+        Value::Number(num) => Value::Number(sum + num * diff as i64),
+        ref other => {
+            error!("SUM can only be applied on type Number, not {:?}.", other);
+            Value::Number(sum)
+        }
+    }
+}
+
+/// Returns a fold maintaining the specified aggregation on inputs
+/// that only ever grow, if there is one. Folds account for
+/// retractions as well, s.t. excisions and rolled back transactions
+/// are reflected exactly. MIN and MAX can't recover from their
+/// current result being retracted, and are reduced as usual.
+fn monotonic_fold(aggregation_fn: &AggregationFn) -> Option<Fold> {
+    match *aggregation_fn {
+        AggregationFn::COUNT => Some(fold_count),
+        AggregationFn::SUM => Some(fold_sum),
+        _ => None,
+    }
+}
+
 /// [WIP] A plan stage applying the specified aggregation functions to
 /// bindings for the specified variables. Given multiple aggregations
 /// we iterate and n-1 joins are applied to the results.
@@ -55,17 +98,34 @@ pub struct Aggregate<P: Implementable> {
     pub aggregation_variables: Vec<Var>,
     /// With variables
     pub with_variables: Vec<Var>,
+    /// Plan binding the key variables for all groups to report,
+    /// including those without any tuples.
+    #[serde(default)]
+    pub key_universe: Option<Box<P>>,
+    /// Values reported for groups without any tuples, one per
+    /// aggregation function.
+    #[serde(default)]
+    pub defaults: Vec<Value>,
 }
 
 impl<P: Implementable> Implementable for Aggregate<P> {
     type A = P::A;
 
     fn dependencies(&self) -> Dependencies<Self::A> {
-        self.plan.dependencies()
+        match self.key_universe {
+            None => self.plan.dependencies(),
+            Some(ref key_universe) => self.plan.dependencies() + key_universe.dependencies(),
+        }
     }
 
-    fn into_bindings(&self) -> Vec<Binding<Self::A>> {
-        self.plan.into_bindings()
+    fn into_bindings(&self) -> Result<Vec<Binding<Self::A>>, PlanError> {
+        let mut bindings = self.plan.into_bindings()?;
+
+        if let Some(ref key_universe) = self.key_universe {
+            bindings.append(&mut key_universe.into_bindings()?);
+        }
+
+        Ok(bindings)
     }
 
     fn implement<'b, S>(
@@ -73,13 +133,36 @@ impl<P: Implementable> Implementable for Aggregate<P> {
         nested: &mut Iterative<'b, S, u64>,
         domain: &mut Domain<Self::A, S::Timestamp>,
         local_arrangements: &VariableMap<Self::A, Iterative<'b, S, u64>>,
-    ) -> (Implemented<'b, Self::A, S>, ShutdownHandle)
+        arrangements: &mut ArrangementMap<Iterative<'b, S, u64>>,
+    ) -> Result<(Implemented<'b, Self::A, S>, ShutdownHandle), Error>
     where
         S: Scope,
         S::Timestamp: Timestamp + Lattice + Rewind,
     {
+        if self.key_universe.is_some() && self.defaults.len() != self.aggregation_fns.len() {
+            return Err(PlanError::InvalidArgument(format!(
+                "Expected {} default values, one per aggregate.",
+                self.aggregation_fns.len()
+            ))
+            .into());
+        }
+
+        // Aggregations over plans that only ever grow don't have to
+        // retain group contents.
+        let monotonic = self.plan.is_monotonic(&|aid: &Self::A| {
+            domain
+                .attribute_config(aid)
+                .map(|config| config.is_monotonic())
+                .unwrap_or(false)
+        });
+
         let (relation, mut shutdown_handle) =
-            self.plan.implement(nested, domain, local_arrangements);
+            self.plan
+                .implement(nested, domain, local_arrangements, arrangements)?;
+
+        ensure_bound(&relation, &self.key_variables)?;
+        ensure_bound(&relation, &self.aggregation_variables)?;
+        ensure_bound(&relation, &self.with_variables)?;
 
         // We split the incoming tuples into their (key, value) parts.
         let tuples = {
@@ -113,7 +196,8 @@ impl<P: Implementable> Implementable for Aggregate<P> {
         let mut output_offsets = Vec::new();
 
         for variable in self.aggregation_variables.iter() {
-            let output_index = AsBinding::binds(&variables, *variable).unwrap();
+            let output_index = AsBinding::binds(&variables, *variable)
+                .ok_or_else(|| PlanError::UnboundVariable(*variable))?;
             output_offsets.push(output_index);
 
             variables[output_index] = 0;
@@ -142,6 +226,18 @@ impl<P: Implementable> Implementable for Aggregate<P> {
                 (key, v)
             };
 
+            if monotonic {
+                if let Some(fold) = monotonic_fold(aggregation_fn) {
+                    let tuples = tuples
+                        .map(prepare_unary)
+                        .map(|(key, mut v)| (key, v.swap_remove(0)))
+                        .accumulate(fold)
+                        .map(|(key, result)| (key, vec![result]));
+                    collections.push(tuples);
+                    continue;
+                }
+            }
+
             match aggregation_fn {
                 AggregationFn::MIN => {
                     let tuples = tuples.map(prepare_unary).reduce(|_key, vals, output| {
@@ -181,7 +277,13 @@ impl<P: Implementable> Implementable for Aggregate<P> {
                         .explode(|(key, val)| {
                             let v = match val[0] {
                                 Value::Number(num) => num,
-                                _ => panic!("SUM can only be applied on type Number."),
+                                ref other => {
+                                    error!(
+                                        "SUM can only be applied on type Number, not {:?}.",
+                                        other
+                                    );
+                                    return None;
+                                }
                             };
                             Some((key, v as isize))
                         })
@@ -195,7 +297,13 @@ impl<P: Implementable> Implementable for Aggregate<P> {
                         .explode(move |(key, val)| {
                             let v = match val[0] {
                                 Value::Number(num) => num,
-                                _ => panic!("AVG can only be applied on type Number."),
+                                ref other => {
+                                    error!(
+                                        "AVG can only be applied on type Number, not {:?}.",
+                                        other
+                                    );
+                                    return None;
+                                }
                             };
                             Some((key, DiffPair::new(v as isize, 1)))
                         })
@@ -217,7 +325,13 @@ impl<P: Implementable> Implementable for Aggregate<P> {
                         .explode(move |(key, val)| {
                             let v = match val[0] {
                                 Value::Number(num) => num,
-                                _ => panic!("VARIANCE can only be applied on type Number."),
+                                ref other => {
+                                    error!(
+                                        "VARIANCE can only be applied on type Number, not {:?}.",
+                                        other
+                                    );
+                                    return None;
+                                }
                             };
                             Some((
                                 key,
@@ -244,42 +358,52 @@ impl<P: Implementable> Implementable for Aggregate<P> {
             };
         }
 
-        if collections.len() == 1 {
-            let output_index = output_offsets[0];
-            let relation = CollectionRelation {
-                variables: self.variables.to_vec(),
-                tuples: collections[0].map(move |(key, val)| {
-                    let mut k = key.clone();
-                    let v = val[0].clone();
-                    k.insert(output_index, v);
-                    k
-                }),
-            };
+        // @TODO replace this with a join application
+        let left = collections.remove(0);
+        let mut aggregated = collections.iter().fold(left, |coll, next| {
+            coll.join_map(&next, |key, v1, v2| {
+                let mut val = v1.clone();
+                val.append(&mut v2.clone());
+                (key.clone(), val)
+            })
+        });
 
-            (Implemented::Collection(relation), shutdown_handle)
-        } else {
-            // @TODO replace this with a join application
-            let left = collections.remove(0);
-            let tuples = collections.iter().fold(left, |coll, next| {
-                coll.join_map(&next, |key, v1, v2| {
-                    let mut val = v1.clone();
-                    val.append(&mut v2.clone());
-                    (key.clone(), val)
-                })
-            });
-
-            let relation = CollectionRelation {
-                variables: self.variables.to_vec(),
-                tuples: tuples.map(move |(key, vals)| {
-                    let mut v = key.clone();
-                    for (i, val) in vals.iter().enumerate() {
-                        v.insert(output_offsets[i], val.clone())
-                    }
-                    v
-                }),
+        if let Some(ref key_universe) = self.key_universe {
+            let (universe, shutdown) =
+                key_universe.implement(nested, domain, local_arrangements, arrangements)?;
+            shutdown_handle.merge_with(shutdown);
+
+            ensure_bound(&universe, &self.key_variables)?;
+
+            let keys = {
+                let (keys, shutdown) = universe.projected(nested, domain, &self.key_variables);
+                shutdown_handle.merge_with(shutdown);
+                keys
             };
 
-            (Implemented::Collection(relation), shutdown_handle)
+            // Groups in the universe without any aggregates are the
+            // empty ones, which are reported with default values.
+            let defaults = self.defaults.clone();
+            let empty = keys
+                .distinct()
+                .map(|key| (key, ()))
+                .antijoin(&aggregated.map(|(key, _vals)| key).distinct())
+                .map(move |(key, ())| (key, defaults.clone()));
+
+            aggregated = aggregated.concat(&empty);
         }
+
+        let relation = CollectionRelation {
+            variables: self.variables.to_vec(),
+            tuples: aggregated.map(move |(key, vals)| {
+                let mut v = key.clone();
+                for (i, val) in vals.iter().enumerate() {
+                    v.insert(output_offsets[i], val.clone())
+                }
+                v
+            }),
+        };
+
+        Ok((Implemented::Collection(relation), shutdown_handle))
     }
 }
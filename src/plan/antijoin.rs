@@ -1,21 +1,33 @@
 //! Antijoin expression plan.
 
+use std::hash::Hash;
+
 use timely::dataflow::scopes::child::Iterative;
 use timely::dataflow::Scope;
+use timely::order::Product;
 use timely::progress::Timestamp;
 
 use differential_dataflow::lattice::Lattice;
-use differential_dataflow::operators::{Join, Threshold};
+use differential_dataflow::operators::arrange::{ArrangeBySelf, Arranged};
+use differential_dataflow::operators::{JoinCore, Reduce};
 
-use crate::binding::{AsBinding, Binding};
+use crate::binding::Binding;
 use crate::domain::Domain;
-use crate::plan::{Dependencies, Implementable};
+use crate::plan::join::{arrange_relation, ArrangementKey};
+use crate::plan::{ensure_bound, Dependencies, Implementable};
 use crate::timestamp::Rewind;
-use crate::{CollectionRelation, Implemented, Relation, ShutdownHandle, Var, VariableMap};
+use crate::{ArrangementMap, CollectionRelation, Implemented, ShutdownHandle, Var, VariableMap};
+use crate::{Error, PlanError, TraceValHandle, Value};
 
 /// A plan stage anti-joining both its sources on the specified
-/// variables. Throws if the sources are not union-compatible, i.e. bind
+/// variables. Fails if the sources are not union-compatible, i.e. bind
 /// all of the same variables in the same order.
+///
+/// Matches are subtracted from the left input, rather than computing
+/// the antijoin from scratch. Both inputs are arranged by the antijoin
+/// variables like they would be for a join, s.t. arrangements of the
+/// same sub-plans elsewhere in the rule are shared. Multiplicities of
+/// the left input are preserved.
 #[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
 pub struct Antijoin<P1: Implementable, P2: Implementable> {
     /// TODO
@@ -26,23 +38,26 @@ pub struct Antijoin<P1: Implementable, P2: Implementable> {
     pub right_plan: Box<P2>,
 }
 
-impl<P1: Implementable, P2: Implementable<A = P1::A>> Implementable for Antijoin<P1, P2> {
+impl<P1, P2> Implementable for Antijoin<P1, P2>
+where
+    P1: Implementable + Hash + Eq + Clone + 'static,
+    P2: Implementable<A = P1::A> + Hash + Eq + Clone + 'static,
+{
     type A = P1::A;
 
     fn dependencies(&self) -> Dependencies<Self::A> {
         self.left_plan.dependencies() + self.right_plan.dependencies()
     }
 
-    fn into_bindings(&self) -> Vec<Binding<Self::A>> {
-        unimplemented!();
-        // let mut left_bindings = self.left_plan.into_bindings();
-        // let mut right_bindings = self.right_plan.into_bindings();
+    fn into_bindings(&self) -> Result<Vec<Binding<Self::A>>, PlanError> {
+        let mut left_bindings = self.left_plan.into_bindings()?;
+        let mut right_bindings = self.right_plan.into_bindings()?;
 
-        // let mut bindings = Vec::with_capacity(left_bindings.len() + right_bindings.len());
-        // bindings.append(&mut left_bindings);
-        // bindings.append(&mut right_bindings);
+        let mut bindings = Vec::with_capacity(left_bindings.len() + right_bindings.len());
+        bindings.append(&mut left_bindings);
+        bindings.append(&mut right_bindings);
 
-        // bindings
+        Ok(bindings)
     }
 
     fn implement<'b, S>(
@@ -50,55 +65,90 @@ impl<P1: Implementable, P2: Implementable<A = P1::A>> Implementable for Antijoin
         nested: &mut Iterative<'b, S, u64>,
         domain: &mut Domain<Self::A, S::Timestamp>,
         local_arrangements: &VariableMap<Self::A, Iterative<'b, S, u64>>,
-    ) -> (Implemented<'b, Self::A, S>, ShutdownHandle)
+        arrangements: &mut ArrangementMap<Iterative<'b, S, u64>>,
+    ) -> Result<(Implemented<'b, Self::A, S>, ShutdownHandle), Error>
     where
         S: Scope,
         S::Timestamp: Timestamp + Lattice + Rewind,
     {
         let mut shutdown_handle = ShutdownHandle::empty();
-        let left = {
-            let (left, shutdown) = self.left_plan.implement(nested, domain, local_arrangements);
-            shutdown_handle.merge_with(shutdown);
-            left
+
+        let left_key = ArrangementKey::new(&*self.left_plan, &self.variables);
+        let (left_variables, left_arranged) = match left_key.cached(arrangements) {
+            Some(cached) => cached,
+            None => {
+                let (left, shutdown) =
+                    self.left_plan
+                        .implement(nested, domain, local_arrangements, arrangements)?;
+                shutdown_handle.merge_with(shutdown);
+                ensure_bound(&left, &self.variables)?;
+
+                let (variables, arranged, shutdown) = arrange_relation(
+                    nested,
+                    domain,
+                    arrangements,
+                    left_key,
+                    &self.variables,
+                    left,
+                );
+                shutdown_handle.merge_with(shutdown);
+
+                (variables, arranged)
+            }
         };
-        let right = {
-            let (right, shutdown) = self
-                .right_plan
-                .implement(nested, domain, local_arrangements);
-            shutdown_handle.merge_with(shutdown);
-            right
+
+        let right_key = ArrangementKey::new(&*self.right_plan, &self.variables);
+        let right_arranged = match right_key.cached(arrangements) {
+            Some((_variables, arranged)) => arranged,
+            None => {
+                let (right, shutdown) =
+                    self.right_plan
+                        .implement(nested, domain, local_arrangements, arrangements)?;
+                shutdown_handle.merge_with(shutdown);
+                ensure_bound(&right, &self.variables)?;
+
+                let (_variables, arranged, shutdown) = arrange_relation(
+                    nested,
+                    domain,
+                    arrangements,
+                    right_key,
+                    &self.variables,
+                    right,
+                );
+                shutdown_handle.merge_with(shutdown);
+
+                arranged
+            }
         };
 
+        // Every key the right input holds counts once, no matter how
+        // many tuples it holds under it.
+        let right_keys: Arranged<
+            Iterative<'b, S, u64>,
+            TraceValHandle<Vec<Value>, (), Product<S::Timestamp, u64>, isize>,
+        > = right_arranged
+            .reduce(|_key, _tuples, output: &mut Vec<((), isize)>| output.push(((), 1)))
+            .map(|(key, ())| key)
+            .arrange_by_self();
+
+        let matched = left_arranged.join_core(&right_keys, |key, tuple, &()| {
+            Some((key.clone(), tuple.clone()))
+        });
+
         let variables = self
             .variables
             .iter()
             .cloned()
-            .chain(
-                left.variables()
-                    .drain(..)
-                    .filter(|x| !self.variables.contains(x)),
-            )
+            .chain(left_variables.into_iter())
             .collect();
 
-        let right_projected = {
-            let (projected, shutdown) = right.projected(nested, domain, &self.variables);
-            shutdown_handle.merge_with(shutdown);
-            projected
-        };
-
-        let left_arranged = {
-            let (arranged, shutdown) = left.tuples_by_variables(nested, domain, &self.variables);
-            shutdown_handle.merge_with(shutdown);
-            arranged
-        };
-
         let tuples = left_arranged
-            .distinct()
-            .antijoin(&right_projected.distinct())
-            .map(|(key, tuple)| key.iter().cloned().chain(tuple.iter().cloned()).collect());
+            .as_collection(|key, tuple| (key.clone(), tuple.clone()))
+            .concat(&matched.negate())
+            .map(|(key, tuple)| key.into_iter().chain(tuple.into_iter()).collect());
 
         let relation = CollectionRelation { variables, tuples };
 
-        (Implemented::Collection(relation), shutdown_handle)
+        Ok((Implemented::Collection(relation), shutdown_handle))
     }
 }
@@ -0,0 +1,410 @@
+//! A fluent API for constructing plans, which keeps track of the
+//! variables bound along the way.
+
+use crate::plan::{Aggregate, AggregationFn, Antijoin, Filter, Join, Plan, Predicate};
+use crate::plan::{Function, Project, Region, Transform, Union, Within};
+use crate::{AsAid, Eid, Error, Value, Var};
+
+/// Builds up a plan one operator at a time. Each step checks that
+/// the variables it refers to are actually bound by what was built
+/// so far. The first violation is reported by `build`.
+///
+/// ```
+/// use declarative_dataflow::plan::{PlanBuilder, Predicate};
+/// use declarative_dataflow::{Aid, Plan, Value};
+///
+/// let plan: Plan<Aid> = PlanBuilder::match_a(0, ":person/name", 1)
+///     .join(PlanBuilder::match_a(0, ":person/age", 2))
+///     .filter_value(Predicate::GTE, 2, Value::Number(18))
+///     .project(vec![1])
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Clone, Debug)]
+pub struct PlanBuilder<A: AsAid> {
+    bound: Result<(Vec<Var>, Plan<A>), Error>,
+}
+
+impl<A: AsAid> PlanBuilder<A> {
+    fn new(variables: Vec<Var>, plan: Plan<A>) -> Self {
+        PlanBuilder {
+            bound: Ok((variables, plan)),
+        }
+    }
+
+    /// Starts from a data pattern of the form [?e a ?v].
+    pub fn match_a<X: Into<A>>(e: Var, a: X, v: Var) -> Self {
+        Self::new(vec![e, v], Plan::match_a(e, a, v))
+    }
+
+    /// Starts from a data pattern of the form [e a ?v].
+    pub fn match_ea<X: Into<A>>(e: Eid, a: X, v: Var) -> Self {
+        Self::new(vec![v], Plan::match_ea(e, a, v))
+    }
+
+    /// Starts from a data pattern of the form [?e a v].
+    pub fn match_av<X: Into<A>, V: Into<Value>>(e: Var, a: X, v: V) -> Self {
+        Self::new(vec![e], Plan::match_av(e, a, v))
+    }
+
+    /// Starts from a data pattern of the form [[a' v'] a ?v], on the
+    /// entity whose value for a' is v'.
+    pub fn match_lookup_a<RX: Into<A>, RV: Into<Value>, X: Into<A>>(
+        lookup: (RX, RV),
+        a: X,
+        v: Var,
+    ) -> Self {
+        Self::new(vec![v], Plan::match_lookup_a(lookup, a, v))
+    }
+
+    /// Starts from another named relation, binding its fields to
+    /// the specified variables.
+    pub fn name_expr<X: Into<A>>(variables: Vec<Var>, name: X) -> Self {
+        Self::new(variables.clone(), Plan::NameExpr(variables, name.into()))
+    }
+
+    /// Returns the variables bound so far, in the order in which
+    /// they appear in result tuples.
+    pub fn variables(&self) -> Result<&[Var], &Error> {
+        match self.bound {
+            Ok((ref variables, _)) => Ok(variables),
+            Err(ref error) => Err(error),
+        }
+    }
+
+    /// Returns the resulting plan, or the first mistake made while
+    /// building it.
+    pub fn build(self) -> Result<Plan<A>, Error> {
+        self.bound.map(|(_variables, plan)| plan)
+    }
+
+    fn and_then<F>(self, f: F) -> Self
+    where
+        F: FnOnce(Vec<Var>, Plan<A>) -> Result<(Vec<Var>, Plan<A>), Error>,
+    {
+        PlanBuilder {
+            bound: self.bound.and_then(|(variables, plan)| f(variables, plan)),
+        }
+    }
+
+    /// Joins with another relation on all shared variables.
+    pub fn join(self, other: Self) -> Self {
+        self.and_then(|left_variables, left_plan| {
+            let (right_variables, right_plan) = other.bound?;
+
+            let shared: Vec<Var> = left_variables
+                .iter()
+                .filter(|x| right_variables.contains(x))
+                .cloned()
+                .collect();
+
+            if shared.is_empty() {
+                return Err(Error::incorrect(
+                    "Join inputs must share at least one variable.",
+                ));
+            }
+
+            let variables = joined_variables(&shared, &left_variables, &right_variables);
+
+            Ok((
+                variables,
+                Plan::Join(Join {
+                    variables: shared,
+                    left_plan: Box::new(left_plan),
+                    right_plan: Box::new(right_plan),
+                }),
+            ))
+        })
+    }
+
+    /// Removes all tuples matching the other relation on the
+    /// specified variables, which both sides must bind.
+    pub fn antijoin(self, other: Self, variables: Vec<Var>) -> Self {
+        self.and_then(|left_variables, left_plan| {
+            let (right_variables, right_plan) = other.bound?;
+
+            check_bound(&variables, &left_variables)?;
+            check_bound(&variables, &right_variables)?;
+
+            Ok((
+                joined_variables(&variables, &left_variables, &[]),
+                Plan::Antijoin(Antijoin {
+                    variables,
+                    left_plan: Box::new(left_plan),
+                    right_plan: Box::new(right_plan),
+                }),
+            ))
+        })
+    }
+
+    /// Combines all tuples of this and another relation, on the
+    /// variables bound here. Both must bind the same variables.
+    pub fn union(self, other: Self) -> Self {
+        self.and_then(|variables, plan| {
+            let (other_variables, other_plan) = other.bound?;
+
+            if other_variables.len() != variables.len() {
+                return Err(Error::incorrect(
+                    "Union inputs must bind the same variables.",
+                ));
+            }
+
+            check_bound(&variables, &other_variables)?;
+
+            let plans = match plan {
+                Plan::Union(Union {
+                    variables: ref union_variables,
+                    ref plans,
+                    ref mappings,
+                }) if *union_variables == variables && mappings.is_empty() => {
+                    let mut plans = plans.clone();
+                    plans.push(other_plan);
+                    plans
+                }
+                plan => vec![plan, other_plan],
+            };
+
+            Ok((
+                variables.clone(),
+                Plan::Union(Union {
+                    variables,
+                    plans,
+                    mappings: vec![],
+                }),
+            ))
+        })
+    }
+
+    /// Keeps only tuples for which the predicate holds on the values
+    /// of two bound variables.
+    pub fn filter(self, predicate: Predicate, x: Var, y: Var) -> Self {
+        self.and_then(|variables, plan| {
+            check_bound(&[x, y], &variables)?;
+
+            Ok((
+                variables,
+                Plan::Filter(Filter {
+                    variables: vec![x, y],
+                    predicate,
+                    plan: Box::new(plan),
+                    constants: vec![None, None],
+                }),
+            ))
+        })
+    }
+
+    /// Keeps only tuples for which the predicate holds between the
+    /// value of a bound variable and a constant.
+    pub fn filter_value<V: Into<Value>>(self, predicate: Predicate, x: Var, value: V) -> Self {
+        let value = value.into();
+
+        self.and_then(|variables, plan| {
+            check_bound(&[x], &variables)?;
+
+            Ok((
+                variables,
+                Plan::Filter(Filter {
+                    variables: vec![x],
+                    predicate,
+                    plan: Box::new(plan),
+                    constants: vec![None, Some(value)],
+                }),
+            ))
+        })
+    }
+
+    /// Keeps only tuples whose geo point bound to a variable lies
+    /// within the specified region.
+    pub fn within(self, x: Var, region: Region) -> Self {
+        self.and_then(|variables, plan| {
+            check_bound(&[x], &variables)?;
+
+            Ok((
+                variables,
+                Plan::Within(Within {
+                    variable: x,
+                    region,
+                    plan: Box::new(plan),
+                }),
+            ))
+        })
+    }
+
+    /// Binds a new variable to the result of applying a function to
+    /// bound variables, and to constants in the positions not taken
+    /// by a variable.
+    pub fn transform(
+        self,
+        function: Function,
+        variables: Vec<Var>,
+        constants: Vec<Option<Value>>,
+        result: Var,
+    ) -> Self {
+        self.and_then(|mut bound, plan| {
+            check_bound(&variables, &bound)?;
+
+            if bound.contains(&result) {
+                return Err(Error::incorrect(format!(
+                    "Variable {} is already bound.",
+                    result
+                )));
+            }
+
+            bound.push(result);
+
+            Ok((
+                bound,
+                Plan::Transform(Transform {
+                    variables,
+                    result_variable: result,
+                    plan: Box::new(plan),
+                    function,
+                    constants,
+                }),
+            ))
+        })
+    }
+
+    /// Restricts tuples to the specified bound variables.
+    pub fn project(self, variables: Vec<Var>) -> Self {
+        self.and_then(|bound, plan| {
+            check_bound(&variables, &bound)?;
+
+            Ok((
+                variables.clone(),
+                Plan::Project(Project {
+                    variables,
+                    plan: Box::new(plan),
+                }),
+            ))
+        })
+    }
+
+    /// Groups tuples by the key variables and aggregates each of the
+    /// aggregation variables, using the respective function. Values
+    /// are aggregated as a multiset over the key, aggregation, and
+    /// with variables.
+    pub fn aggregate(
+        self,
+        key_variables: Vec<Var>,
+        aggregations: Vec<(AggregationFn, Var)>,
+        with_variables: Vec<Var>,
+    ) -> Self {
+        self.and_then(|bound, plan| {
+            let (aggregation_fns, aggregation_variables): (Vec<AggregationFn>, Vec<Var>) =
+                aggregations.into_iter().unzip();
+
+            if aggregation_fns.is_empty() {
+                return Err(Error::incorrect(
+                    "Expected at least one aggregation function.",
+                ));
+            }
+
+            // Aggregations expect their source to bind key variables
+            // first, followed by aggregation arguments, followed by
+            // the with variables.
+            let mut source_variables = key_variables.clone();
+            for variable in aggregation_variables.iter().chain(with_variables.iter()) {
+                if !source_variables.contains(variable) {
+                    source_variables.push(*variable);
+                }
+            }
+
+            check_bound(&source_variables, &bound)?;
+
+            let mut variables = key_variables.clone();
+            variables.extend(aggregation_variables.iter().cloned());
+
+            Ok((
+                variables.clone(),
+                Plan::Aggregate(Aggregate {
+                    variables,
+                    plan: Box::new(Plan::Project(Project {
+                        variables: source_variables,
+                        plan: Box::new(plan),
+                    })),
+                    aggregation_fns,
+                    key_variables,
+                    aggregation_variables,
+                    with_variables,
+                    key_universe: None,
+                    defaults: vec![],
+                }),
+            ))
+        })
+    }
+}
+
+#[cfg(feature = "serde_json")]
+impl<A> PlanBuilder<A>
+where
+    A: AsAid + serde::Serialize + serde::de::DeserializeOwned,
+{
+    /// Continues from a plan in its serialized form.
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        serde_json::from_str::<Plan<A>>(json)
+            .map(Self::from)
+            .map_err(Error::incorrect)
+    }
+
+    /// Returns the serialized form of the resulting plan.
+    pub fn to_json(self) -> Result<String, Error> {
+        self.build()
+            .and_then(|plan| serde_json::to_string(&plan).map_err(Error::fault))
+    }
+}
+
+impl<A: AsAid> From<Plan<A>> for PlanBuilder<A> {
+    fn from(plan: Plan<A>) -> Self {
+        let variables = bound_variables(&plan);
+        Self::new(variables, plan)
+    }
+}
+
+/// Orders the variables of a join result: shared variables first,
+/// then the remaining variables of either side.
+fn joined_variables(shared: &[Var], left: &[Var], right: &[Var]) -> Vec<Var> {
+    shared
+        .iter()
+        .chain(left.iter().filter(|x| !shared.contains(x)))
+        .chain(right.iter().filter(|x| !shared.contains(x)))
+        .cloned()
+        .collect()
+}
+
+fn check_bound(variables: &[Var], bound: &[Var]) -> Result<(), Error> {
+    match variables.iter().find(|x| !bound.contains(x)) {
+        None => Ok(()),
+        Some(unbound) => Err(Error::incorrect(format!(
+            "Variable {} is not bound.",
+            unbound
+        ))),
+    }
+}
+
+/// The variables bound by an existing plan, in the layout assumed by
+/// the builder.
+fn bound_variables<A: AsAid>(plan: &Plan<A>) -> Vec<Var> {
+    match *plan {
+        Plan::Join(ref join) => joined_variables(
+            &join.variables,
+            &bound_variables(&join.left_plan),
+            &bound_variables(&join.right_plan),
+        ),
+        Plan::Antijoin(ref antijoin) => joined_variables(
+            &antijoin.variables,
+            &bound_variables(&antijoin.left_plan),
+            &[],
+        ),
+        Plan::Filter(ref filter) => bound_variables(&filter.plan),
+        Plan::Negate(ref plan) => bound_variables(plan),
+        Plan::Transform(ref transform) => {
+            let mut variables = bound_variables(&transform.plan);
+            variables.push(transform.result_variable);
+            variables
+        }
+        #[cfg(feature = "graphql")]
+        Plan::GraphQl(_) => Vec::new(),
+        _ => plan.variables(),
+    }
+}
@@ -0,0 +1,136 @@
+//! Coalescing plan.
+
+use timely::dataflow::operators::Concatenate;
+use timely::dataflow::scopes::child::Iterative;
+use timely::dataflow::Scope;
+use timely::progress::Timestamp;
+
+use differential_dataflow::lattice::Lattice;
+use differential_dataflow::operators::Reduce;
+use differential_dataflow::AsCollection;
+
+use crate::binding::Binding;
+use crate::domain::Domain;
+use crate::plan::{ensure_bound, Dependencies, Implementable};
+use crate::timestamp::Rewind;
+use crate::{
+    ArrangementMap, CollectionRelation, Implemented, Relation, ShutdownHandle, Value, Var,
+    VariableMap,
+};
+use crate::{Error, PlanError};
+
+/// A plan stage binding, for each key, the values of the first
+/// alternative holding any for it. Alternatives are tried in order,
+/// e.g. to fall back to a user's name whenever they have no display
+/// name.
+///
+/// All alternatives must bind the key variables and the value
+/// variable. Whenever an earlier alternative gains or loses values
+/// for a key, the output switches over accordingly.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct Coalesce<P: Implementable> {
+    /// Variables identifying what values belong to.
+    pub key_variables: Vec<Var>,
+    /// Variable bound to the values of each alternative.
+    pub value_variable: Var,
+    /// Plans for the alternatives, in order of preference.
+    pub plans: Vec<P>,
+}
+
+impl<P: Implementable> Coalesce<P> {
+    /// Returns the variables bound by this stage.
+    pub fn variables(&self) -> Vec<Var> {
+        let mut variables = self.key_variables.clone();
+        variables.push(self.value_variable);
+        variables
+    }
+}
+
+impl<P: Implementable> Implementable for Coalesce<P> {
+    type A = P::A;
+
+    fn dependencies(&self) -> Dependencies<Self::A> {
+        self.plans.iter().map(|plan| plan.dependencies()).sum()
+    }
+
+    fn into_bindings(&self) -> Result<Vec<Binding<Self::A>>, PlanError> {
+        let mut bindings = Vec::new();
+
+        for plan in self.plans.iter() {
+            bindings.append(&mut plan.into_bindings()?);
+        }
+
+        Ok(bindings)
+    }
+
+    fn implement<'b, S>(
+        &self,
+        nested: &mut Iterative<'b, S, u64>,
+        domain: &mut Domain<Self::A, S::Timestamp>,
+        local_arrangements: &VariableMap<Self::A, Iterative<'b, S, u64>>,
+        arrangements: &mut ArrangementMap<Iterative<'b, S, u64>>,
+    ) -> Result<(Implemented<'b, Self::A, S>, ShutdownHandle), Error>
+    where
+        S: Scope,
+        S::Timestamp: Timestamp + Lattice + Rewind,
+    {
+        if self.plans.is_empty() {
+            return Err(PlanError::InvalidArgument("Nothing to coalesce.".into()).into());
+        }
+
+        let variables = self.variables();
+
+        let mut scope = nested.clone();
+        let mut shutdown_handle = ShutdownHandle::empty();
+
+        let mut streams = Vec::with_capacity(self.plans.len());
+        for (preference, plan) in self.plans.iter().enumerate() {
+            let relation = {
+                let (relation, shutdown) =
+                    plan.implement(&mut scope, domain, local_arrangements, arrangements)?;
+                shutdown_handle.merge_with(shutdown);
+                relation
+            };
+
+            ensure_bound(&relation, &variables)?;
+
+            let projected = {
+                let (projected, shutdown) = relation.projected(&mut scope, domain, &variables);
+                shutdown_handle.merge_with(shutdown);
+                projected
+            };
+
+            let preference = preference as u64;
+            let alternative = projected.map(move |mut tuple| {
+                let value = tuple.pop().unwrap();
+                (tuple, (preference, value))
+            });
+
+            streams.push(alternative.inner);
+        }
+
+        let tuples = nested
+            .concatenate(streams)
+            .as_collection()
+            .reduce(|_key, alternatives, output| {
+                // Alternatives arrive ordered by preference.
+                let preferred = (alternatives[0].0).0;
+
+                for &(&(preference, ref value), _count) in alternatives.iter() {
+                    if preference != preferred {
+                        break;
+                    }
+
+                    output.push((value.clone(), 1));
+                }
+            })
+            .map(|(mut key, value): (Vec<Value>, Value)| {
+                key.push(value);
+                key
+            });
+
+        let relation = CollectionRelation { variables, tuples };
+
+        Ok((Implemented::Collection(relation), shutdown_handle))
+    }
+}
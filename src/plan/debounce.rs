@@ -0,0 +1,89 @@
+//! Debouncing plan.
+
+use timely::dataflow::operators::Map;
+use timely::dataflow::scopes::child::Iterative;
+use timely::dataflow::Scope;
+use timely::progress::Timestamp;
+
+use differential_dataflow::lattice::Lattice;
+use differential_dataflow::operators::{Join, Threshold};
+use differential_dataflow::AsCollection;
+
+use crate::binding::Binding;
+use crate::domain::Domain;
+use crate::plan::delay::{delayed, ensure_convertible};
+use crate::plan::{Dependencies, Implementable};
+use crate::timestamp::{Rewind, Time};
+use crate::{
+    ArrangementMap, CollectionRelation, Implemented, Relation, ShutdownHandle, VariableMap,
+};
+use crate::{Error, PlanError};
+
+/// A plan stage holding back source tuples until they have remained
+/// unchanged for the specified period. Tuples that keep appearing and
+/// disappearing within the period are suppressed entirely, while
+/// retractions of stable tuples pass through right away.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct Debounce<P: Implementable> {
+    /// Plan for the data source.
+    pub plan: Box<P>,
+    /// How long tuples have to remain unchanged, in domain time.
+    pub period: Time,
+}
+
+impl<P: Implementable> Implementable for Debounce<P> {
+    type A = P::A;
+
+    fn dependencies(&self) -> Dependencies<Self::A> {
+        self.plan.dependencies()
+    }
+
+    fn into_bindings(&self) -> Result<Vec<Binding<Self::A>>, PlanError> {
+        self.plan.into_bindings()
+    }
+
+    fn implement<'b, S>(
+        &self,
+        nested: &mut Iterative<'b, S, u64>,
+        domain: &mut Domain<Self::A, S::Timestamp>,
+        local_arrangements: &VariableMap<Self::A, Iterative<'b, S, u64>>,
+        arrangements: &mut ArrangementMap<Iterative<'b, S, u64>>,
+    ) -> Result<(Implemented<'b, Self::A, S>, ShutdownHandle), Error>
+    where
+        S: Scope,
+        S::Timestamp: Timestamp + Lattice + Rewind,
+    {
+        ensure_convertible::<S::Timestamp>(&self.period)?;
+
+        let (relation, mut shutdown_handle) =
+            self.plan
+                .implement(nested, domain, local_arrangements, arrangements)?;
+
+        let variables = relation.variables();
+        let tuples = {
+            let (projected, shutdown) = relation.projected(nested, domain, &variables);
+            shutdown_handle.merge_with(shutdown);
+            projected
+        };
+
+        // Every change to a tuple, no matter its sign, marks it as
+        // unstable until the period has passed.
+        let changes = tuples
+            .inner
+            .map(|(tuple, t, _diff)| (tuple, t, 1))
+            .as_collection();
+
+        let unstable = changes
+            .concat(&delayed(&changes, &self.period).negate())
+            .distinct();
+
+        let tuples = tuples
+            .map(|tuple| (tuple, ()))
+            .antijoin(&unstable)
+            .map(|(tuple, ())| tuple);
+
+        let debounced = CollectionRelation { variables, tuples };
+
+        Ok((Implemented::Collection(debounced), shutdown_handle))
+    }
+}
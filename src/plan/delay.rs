@@ -0,0 +1,110 @@
+//! Time-shifting plan.
+
+use timely::dataflow::operators::Map;
+use timely::dataflow::scopes::child::Iterative;
+use timely::dataflow::Scope;
+use timely::order::Product;
+use timely::progress::Timestamp;
+
+use differential_dataflow::lattice::Lattice;
+use differential_dataflow::{AsCollection, Collection};
+
+use crate::binding::Binding;
+use crate::domain::Domain;
+use crate::plan::{Dependencies, Implementable};
+use crate::timestamp::{Rewind, Time};
+use crate::{
+    ArrangementMap, CollectionRelation, Implemented, Relation, ShutdownHandle, Value, VariableMap,
+};
+use crate::{Error, PlanError};
+
+/// Ensures that an offset can be expressed in the domain's
+/// timestamps.
+pub(crate) fn ensure_convertible<T: Rewind>(offset: &Time) -> Result<(), Error> {
+    if T::is_convertible(offset) {
+        Ok(())
+    } else {
+        Err(PlanError::InvalidArgument(format!(
+            "Offset {:?} doesn't match the domain's timestamps.",
+            offset
+        ))
+        .into())
+    }
+}
+
+/// Moves all updates of a collection forward in domain time by the
+/// specified offset, which must be convertible.
+pub(crate) fn delayed<'b, S>(
+    tuples: &Collection<Iterative<'b, S, u64>, Vec<Value>, isize>,
+    offset: &Time,
+) -> Collection<Iterative<'b, S, u64>, Vec<Value>, isize>
+where
+    S: Scope,
+    S::Timestamp: Timestamp + Lattice + Rewind,
+{
+    let offset: S::Timestamp = offset.clone().into();
+
+    tuples
+        .inner
+        .map(move |(tuple, t, diff)| {
+            let shifted = Product::new(t.outer.fast_forward(offset.clone()), t.inner);
+            (tuple, shifted, diff)
+        })
+        .as_collection()
+}
+
+/// A plan stage shifting source tuples forward in time, s.t. each
+/// tuple appears and disappears the specified offset after it did in
+/// the source. Joining a relation with a delayed copy of itself
+/// relates current values to the ones at an earlier time.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct Delay<P: Implementable> {
+    /// Plan for the data source.
+    pub plan: Box<P>,
+    /// How far to shift tuples, in domain time.
+    pub offset: Time,
+}
+
+impl<P: Implementable> Implementable for Delay<P> {
+    type A = P::A;
+
+    fn dependencies(&self) -> Dependencies<Self::A> {
+        self.plan.dependencies()
+    }
+
+    fn into_bindings(&self) -> Result<Vec<Binding<Self::A>>, PlanError> {
+        self.plan.into_bindings()
+    }
+
+    fn implement<'b, S>(
+        &self,
+        nested: &mut Iterative<'b, S, u64>,
+        domain: &mut Domain<Self::A, S::Timestamp>,
+        local_arrangements: &VariableMap<Self::A, Iterative<'b, S, u64>>,
+        arrangements: &mut ArrangementMap<Iterative<'b, S, u64>>,
+    ) -> Result<(Implemented<'b, Self::A, S>, ShutdownHandle), Error>
+    where
+        S: Scope,
+        S::Timestamp: Timestamp + Lattice + Rewind,
+    {
+        ensure_convertible::<S::Timestamp>(&self.offset)?;
+
+        let (relation, mut shutdown_handle) =
+            self.plan
+                .implement(nested, domain, local_arrangements, arrangements)?;
+
+        let variables = relation.variables();
+        let tuples = {
+            let (projected, shutdown) = relation.projected(nested, domain, &variables);
+            shutdown_handle.merge_with(shutdown);
+            projected
+        };
+
+        let relation = CollectionRelation {
+            variables,
+            tuples: delayed(&tuples, &self.offset),
+        };
+
+        Ok((Implemented::Collection(relation), shutdown_handle))
+    }
+}
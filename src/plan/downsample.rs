@@ -0,0 +1,216 @@
+//! Time-series downsampling plan.
+
+use timely::dataflow::scopes::child::Iterative;
+use timely::dataflow::Scope;
+use timely::progress::Timestamp;
+
+use differential_dataflow::lattice::Lattice;
+use differential_dataflow::operators::Reduce;
+
+use crate::binding::Binding;
+use crate::domain::Domain;
+use crate::plan::{ensure_bound, Dependencies, Implementable};
+use crate::timestamp::Rewind;
+use crate::{
+    ArrangementMap, CollectionRelation, Implemented, Relation, ShutdownHandle, Value, Var,
+    VariableMap,
+};
+use crate::{Error, PlanError};
+
+use num_rational::Ratio;
+
+/// Permitted per-bucket aggregates.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub enum DownsampleFn {
+    /// Minimum
+    MIN,
+    /// Maximum
+    MAX,
+    /// Average, of Number values only
+    AVG,
+    /// Value at the latest instant within the bucket
+    LAST,
+}
+
+/// A plan stage bucketing (instant, value) pairs into fixed intervals
+/// and summarizing each bucket via the specified aggregates. Buckets
+/// are kept per key, and only re-computed when their inputs change.
+///
+/// The stage binds the key variables, then the time variable to the
+/// first instant of each bucket, then one result variable per
+/// aggregate.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct Downsample<P: Implementable> {
+    /// Plan for the data source.
+    pub plan: Box<P>,
+    /// Variables identifying a single time series.
+    pub key_variables: Vec<Var>,
+    /// Variable bound to the instant of each value.
+    pub time_variable: Var,
+    /// Variable bound to the values to summarize.
+    pub value_variable: Var,
+    /// Length of each bucket, in the units of the time variable,
+    /// i.e. milliseconds for instants.
+    pub interval: u64,
+    /// Aggregates to compute per bucket.
+    pub downsample_fns: Vec<DownsampleFn>,
+    /// Variables to bind the aggregates to.
+    pub result_variables: Vec<Var>,
+}
+
+impl<P: Implementable> Downsample<P> {
+    /// Returns the variables bound by this stage.
+    pub fn variables(&self) -> Vec<Var> {
+        let mut variables = self.key_variables.clone();
+        variables.push(self.time_variable);
+        variables.extend(self.result_variables.iter().cloned());
+        variables
+    }
+}
+
+/// Truncates a time value to the start of its bucket.
+fn bucket(time: &Value, interval: u64) -> Option<Value> {
+    match *time {
+        Value::Instant(t) => Some(Value::Instant(t - (t % interval))),
+        Value::Number(t) => {
+            let interval = interval as i64;
+            Some(Value::Number(t - (((t % interval) + interval) % interval)))
+        }
+        _ => None,
+    }
+}
+
+/// Computes an aggregate over a bucket's (instant, value) pairs, in
+/// ascending order. Returns `None` if the bucket holds no suitable
+/// values.
+fn summarize(downsample_fn: &DownsampleFn, samples: &[(&(Value, Value), isize)]) -> Option<Value> {
+    match *downsample_fn {
+        DownsampleFn::MIN => samples.iter().map(|((_t, v), _count)| v).min().cloned(),
+        DownsampleFn::MAX => samples.iter().map(|((_t, v), _count)| v).max().cloned(),
+        DownsampleFn::AVG => {
+            let mut sum = 0;
+            let mut total = 0;
+
+            for ((_t, v), count) in samples.iter() {
+                match *v {
+                    Value::Number(number) => {
+                        sum += number * *count as i64;
+                        total += *count as i64;
+                    }
+                    ref other => {
+                        error!("AVG can only be applied on type Number, not {:?}.", other);
+                    }
+                }
+            }
+
+            if total == 0 {
+                None
+            } else {
+                Some(Value::Rational32(Ratio::new(sum as i32, total as i32)))
+            }
+        }
+        DownsampleFn::LAST => samples.last().map(|((_t, v), _count)| v.clone()),
+    }
+}
+
+impl<P: Implementable> Implementable for Downsample<P> {
+    type A = P::A;
+
+    fn dependencies(&self) -> Dependencies<Self::A> {
+        self.plan.dependencies()
+    }
+
+    fn into_bindings(&self) -> Result<Vec<Binding<Self::A>>, PlanError> {
+        self.plan.into_bindings()
+    }
+
+    fn implement<'b, S>(
+        &self,
+        nested: &mut Iterative<'b, S, u64>,
+        domain: &mut Domain<Self::A, S::Timestamp>,
+        local_arrangements: &VariableMap<Self::A, Iterative<'b, S, u64>>,
+        arrangements: &mut ArrangementMap<Iterative<'b, S, u64>>,
+    ) -> Result<(Implemented<'b, Self::A, S>, ShutdownHandle), Error>
+    where
+        S: Scope,
+        S::Timestamp: Timestamp + Lattice + Rewind,
+    {
+        if self.interval == 0 {
+            return Err(PlanError::InvalidArgument(
+                "Downsampling intervals must not be zero.".into(),
+            )
+            .into());
+        }
+
+        // Numbers are bucketed by signed intervals.
+        if self.interval > std::i64::MAX as u64 {
+            return Err(PlanError::InvalidArgument(format!(
+                "Downsampling intervals must not exceed {}.",
+                std::i64::MAX
+            ))
+            .into());
+        }
+
+        if self.downsample_fns.len() != self.result_variables.len() {
+            return Err(PlanError::InvalidArgument(format!(
+                "Expected {} result variables, one per aggregate.",
+                self.downsample_fns.len()
+            ))
+            .into());
+        }
+
+        let (relation, mut shutdown_handle) =
+            self.plan
+                .implement(nested, domain, local_arrangements, arrangements)?;
+
+        ensure_bound(&relation, &self.key_variables)?;
+        ensure_bound(&relation, &[self.time_variable, self.value_variable])?;
+
+        let mut variables = self.key_variables.clone();
+        variables.push(self.time_variable);
+        variables.push(self.value_variable);
+
+        let tuples = {
+            let (projected, shutdown) = relation.projected(nested, domain, &variables);
+            shutdown_handle.merge_with(shutdown);
+            projected
+        };
+
+        let interval = self.interval;
+        let downsample_fns = self.downsample_fns.clone();
+
+        // Tuples are grouped by their key and bucket, keeping their
+        // instant around for LAST.
+        let tuples = tuples
+            .flat_map(move |mut tuple| {
+                let value = tuple.pop().unwrap();
+                let time = tuple.pop().unwrap();
+
+                bucket(&time, interval).map(|bucket| {
+                    tuple.push(bucket);
+                    (tuple, (time, value))
+                })
+            })
+            .reduce(move |_group, samples, output| {
+                let summary: Option<Vec<Value>> = downsample_fns
+                    .iter()
+                    .map(|downsample_fn| summarize(downsample_fn, samples))
+                    .collect();
+
+                if let Some(summary) = summary {
+                    output.push((summary, 1));
+                }
+            })
+            .map(|(mut group, mut summary): (Vec<Value>, Vec<Value>)| {
+                group.append(&mut summary);
+                group
+            });
+
+        let relation = CollectionRelation {
+            variables: self.variables(),
+            tuples,
+        };
+
+        Ok((Implemented::Collection(relation), shutdown_handle))
+    }
+}
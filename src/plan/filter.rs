@@ -10,9 +10,13 @@ pub use crate::binding::{
     AsBinding, BinaryPredicate as Predicate, BinaryPredicateBinding, Binding,
 };
 use crate::domain::Domain;
-use crate::plan::{Dependencies, Implementable};
+use crate::plan::{ensure_bound, Dependencies, Implementable};
 use crate::timestamp::Rewind;
-use crate::{CollectionRelation, Implemented, Relation, ShutdownHandle, Value, Var, VariableMap};
+use crate::{
+    ArrangementMap, CollectionRelation, Implemented, Relation, ShutdownHandle, Value, Var,
+    VariableMap,
+};
+use crate::{Error, PlanError};
 
 #[inline(always)]
 fn lt(a: &Value, b: &Value) -> bool {
@@ -61,11 +65,13 @@ impl<P: Implementable> Implementable for Filter<P> {
         self.plan.dependencies()
     }
 
-    fn into_bindings(&self) -> Vec<Binding<Self::A>> {
+    fn into_bindings(&self) -> Result<Vec<Binding<Self::A>>, PlanError> {
         // let mut bindings = self.plan.into_bindings();
         // let variables = self.variables.clone();
 
-        unimplemented!();
+        Err(PlanError::Unsupported(
+            "Filters can't be implemented via Hector yet.".to_string(),
+        ))
         // bindings.push(Binding::BinaryPredicate(BinaryPredicateBinding {
         //     variables: (variables[0], variables[1]),
         //     predicate: self.predicate.clone(),
@@ -79,18 +85,22 @@ impl<P: Implementable> Implementable for Filter<P> {
         nested: &mut Iterative<'b, S, u64>,
         domain: &mut Domain<Self::A, S::Timestamp>,
         local_arrangements: &VariableMap<Self::A, Iterative<'b, S, u64>>,
-    ) -> (Implemented<'b, Self::A, S>, ShutdownHandle)
+        arrangements: &mut ArrangementMap<Iterative<'b, S, u64>>,
+    ) -> Result<(Implemented<'b, Self::A, S>, ShutdownHandle), Error>
     where
         S: Scope,
         S::Timestamp: Timestamp + Lattice + Rewind,
     {
         let (relation, mut shutdown_handle) =
-            self.plan.implement(nested, domain, local_arrangements);
+            self.plan
+                .implement(nested, domain, local_arrangements, arrangements)?;
+
+        ensure_bound(&relation, &self.variables)?;
 
         let key_offsets: Vec<usize> = self
             .variables
             .iter()
-            .map(|variable| relation.binds(*variable).expect("variable not found"))
+            .map(|variable| relation.binds(*variable).unwrap())
             .collect();
 
         let binary_predicate = match self.predicate {
@@ -109,27 +119,19 @@ impl<P: Implementable> Implementable for Filter<P> {
             projected
         };
 
-        let filtered = if let Some(constant) = self.constants[0].clone() {
-            CollectionRelation {
-                variables,
-                tuples: projected
-                    .filter(move |tuple| binary_predicate(&constant, &tuple[key_offsets[0]])),
-            }
+        let tuples = if let Some(constant) = self.constants[0].clone() {
+            let offset = key_offsets[0];
+            projected.filter(move |tuple| binary_predicate(&constant, &tuple[offset]))
         } else if let Some(constant) = self.constants[1].clone() {
-            CollectionRelation {
-                variables,
-                tuples: projected
-                    .filter(move |tuple| binary_predicate(&tuple[key_offsets[0]], &constant)),
-            }
+            let offset = key_offsets[0];
+            projected.filter(move |tuple| binary_predicate(&tuple[offset], &constant))
         } else {
-            CollectionRelation {
-                variables,
-                tuples: projected.filter(move |tuple| {
-                    binary_predicate(&tuple[key_offsets[0]], &tuple[key_offsets[1]])
-                }),
-            }
+            let (left, right) = (key_offsets[0], key_offsets[1]);
+            projected.filter(move |tuple| binary_predicate(&tuple[left], &tuple[right]))
         };
 
-        (Implemented::Collection(filtered), shutdown_handle)
+        let filtered = CollectionRelation { variables, tuples };
+
+        Ok((Implemented::Collection(filtered), shutdown_handle))
     }
 }
@@ -1,5 +1,7 @@
 //! GraphQL expression plan.
 
+use std::collections::HashMap;
+
 use timely::dataflow::scopes::child::Iterative;
 use timely::dataflow::Scope;
 use timely::progress::Timestamp;
@@ -8,15 +10,15 @@ use differential_dataflow::lattice::Lattice;
 
 use graphql_parser::parse_query;
 use graphql_parser::query::{Definition, Document, OperationDefinition, Selection, SelectionSet};
-use graphql_parser::query::{Name, Value};
+use graphql_parser::query::{Field, Name, Value};
 
 use crate::binding::Binding;
 use crate::domain::Domain;
 use crate::plan::{gensym, Dependencies, Implementable};
 use crate::plan::{Hector, Plan, Pull, PullAll, PullLevel};
 use crate::timestamp::Rewind;
-use crate::{AsAid, Var};
-use crate::{Implemented, ShutdownHandle, VariableMap};
+use crate::{ArrangementMap, Implemented, ShutdownHandle, VariableMap};
+use crate::{AsAid, Error, Var};
 
 /// A plan for GraphQL queries, e.g. `{ Heroes { name age weight } }`.
 #[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
@@ -31,33 +33,51 @@ impl<A: AsAid + From<String>> GraphQl<A> {
     /// Creates a new GraphQl instance by parsing the AST obtained
     /// from the provided query.
     pub fn new(query: String) -> Self {
-        let ast = parse_query(&query).expect("graphQL ast parsing failed");
+        Self::parse(query).expect("graphQL ast parsing failed")
+    }
 
+    /// Creates a new GraphQl instance from the provided query,
+    /// reporting malformed or unsupported documents as errors.
+    pub fn parse(query: String) -> Result<Self, Error> {
         let empty_plan = Hector {
             variables: vec![0],
             bindings: vec![],
+            order: Default::default(),
         };
 
-        GraphQl {
-            query,
-            paths: ast.into_paths(empty_plan),
-        }
+        Self::parse_with(empty_plan, query)
     }
 
     /// Creates a new GraphQl starting from the specified root plan.
     pub fn with_plan(root_plan: Plan<A>, query: String) -> Self {
-        let ast = parse_query(&query).expect("graphQL ast parsing failed");
-        let paths = ast.into_paths(Hector {
+        let root_plan = Hector {
             variables: root_plan.variables(),
-            bindings: root_plan.into_bindings(),
-        });
+            bindings: root_plan
+                .into_bindings()
+                .expect("root plan can't be implemented via Hector"),
+            order: Default::default(),
+        };
 
-        GraphQl { query, paths }
+        Self::parse_with(root_plan, query).expect("graphQL ast parsing failed")
+    }
+
+    fn parse_with(root_plan: Hector<A>, query: String) -> Result<Self, Error> {
+        let paths = parse_query(&query)
+            .map_err(|error| Error::incorrect(format!("Failed to parse GraphQL: {}", error)))?
+            .into_paths(root_plan)?;
+
+        Ok(GraphQl { query, paths })
     }
 }
 
+/// Fragment definitions by name, so that spreads can be inlined.
+type Fragments<'a> = HashMap<&'a str, &'a SelectionSet>;
+
 trait IntoPaths {
-    fn into_paths<A: AsAid + From<String>>(&self, root_plan: Hector<A>) -> Vec<Plan<A>>;
+    fn into_paths<A: AsAid + From<String>>(
+        &self,
+        root_plan: Hector<A>,
+    ) -> Result<Vec<Plan<A>>, Error>;
 }
 
 impl IntoPaths for Document {
@@ -79,53 +99,94 @@ impl IntoPaths for Document {
     ///   ]
     /// }
     /// ```
-    fn into_paths<A: AsAid + From<String>>(&self, root_plan: Hector<A>) -> Vec<Plan<A>> {
-        self.definitions
+    ///
+    /// Queries and subscriptions are treated alike, as every query
+    /// is maintained incrementally anyways. Fragment definitions are
+    /// inlined wherever they are spread.
+    fn into_paths<A: AsAid + From<String>>(
+        &self,
+        root_plan: Hector<A>,
+    ) -> Result<Vec<Plan<A>>, Error> {
+        let fragments: Fragments = self
+            .definitions
             .iter()
-            .flat_map(|definition| definition.into_paths(root_plan.clone()))
-            .collect()
-    }
-}
-
-impl IntoPaths for Definition {
-    fn into_paths<A: AsAid + From<String>>(&self, root_plan: Hector<A>) -> Vec<Plan<A>> {
-        match self {
-            Definition::Operation(operation) => operation.into_paths(root_plan),
-            Definition::Fragment(_) => unimplemented!(),
+            .filter_map(|definition| match definition {
+                Definition::Fragment(fragment) => {
+                    Some((fragment.name.as_str(), &fragment.selection_set))
+                }
+                Definition::Operation(_) => None,
+            })
+            .collect();
+
+        let mut paths = Vec::new();
+
+        for definition in self.definitions.iter() {
+            if let Definition::Operation(operation) = definition {
+                let selection_set = match operation {
+                    OperationDefinition::SelectionSet(selection_set) => selection_set,
+                    OperationDefinition::Query(query) => &query.selection_set,
+                    OperationDefinition::Subscription(subscription) => &subscription.selection_set,
+                    OperationDefinition::Mutation(_) => {
+                        return Err(Error::unsupported("GraphQL mutations."));
+                    }
+                };
+
+                let fields = fields(selection_set, &fragments)?;
+
+                paths.extend(selection_set_to_paths(
+                    &fields,
+                    root_plan.clone(),
+                    &[],
+                    &[],
+                    &fragments,
+                )?);
+            }
         }
+
+        Ok(paths)
     }
 }
 
-impl IntoPaths for OperationDefinition {
-    fn into_paths<A: AsAid + From<String>>(&self, root_plan: Hector<A>) -> Vec<Plan<A>> {
-        use OperationDefinition::{Query, SelectionSet};
-
-        match self {
-            Query(_) => unimplemented!(),
-            SelectionSet(selection_set) => {
-                selection_set_to_paths(&selection_set, root_plan, &[], &[])
+/// Flattens a selection set into the fields it selects, by inlining
+/// fragment spreads and inline fragments.
+fn fields<'a>(
+    selection_set: &'a SelectionSet,
+    fragments: &Fragments<'a>,
+) -> Result<Vec<&'a Field>, Error> {
+    let mut fields = Vec::new();
+
+    for item in selection_set.items.iter() {
+        match item {
+            Selection::Field(field) => fields.push(field),
+            Selection::InlineFragment(fragment) => {
+                fields.extend(self::fields(&fragment.selection_set, fragments)?);
+            }
+            Selection::FragmentSpread(spread) => {
+                match fragments.get(spread.fragment_name.as_str()) {
+                    None => {
+                        return Err(Error::not_found(format!(
+                            "Unknown fragment {}.",
+                            spread.fragment_name
+                        )));
+                    }
+                    Some(&selection_set) => {
+                        fields.extend(self::fields(selection_set, fragments)?);
+                    }
+                }
             }
-            _ => unimplemented!(),
         }
     }
+
+    Ok(fields)
 }
 
 /// Gathers the fields that we want to pull at a specific level. These
 /// only include fields that do not refer to nested entities.
-fn pull_attributes<A: AsAid + From<String>>(selection_set: &SelectionSet) -> Vec<A> {
-    selection_set
-        .items
+fn pull_attributes<A: AsAid + From<String>>(fields: &[&Field]) -> Vec<A> {
+    fields
         .iter()
-        .flat_map(|item| match item {
-            Selection::Field(field) => {
-                if field.selection_set.items.is_empty() {
-                    Some(A::from(field.name.to_string()))
-                } else {
-                    None
-                }
-            }
-            _ => unimplemented!(),
-        })
+        .filter(|field| field.selection_set.items.is_empty())
+        .map(|field| A::from(field.name.to_string()))
         .collect::<Vec<A>>()
 }
 
@@ -141,11 +202,12 @@ fn pull_attributes<A: AsAid + From<String>>(selection_set: &SelectionSet) -> Vec
 /// ("horizontal"); only attributes at the lowest nesting level can be
 /// part of a `PullLevel`'s `pull_attributes`.
 fn selection_set_to_paths<A: AsAid + From<String>>(
-    selection_set: &SelectionSet,
+    fields: &[&Field],
     mut plan: Hector<A>,
     arguments: &[(Name, Value)],
     parent_path: &[A],
-) -> Vec<Plan<A>> {
+    fragments: &Fragments,
+) -> Result<Vec<Plan<A>>, Error> {
     // We must first construct the correct plan for this level,
     // starting from that for the parent level. We do this even if no
     // attributes are actually pulled at this level. In that case we
@@ -182,33 +244,25 @@ fn selection_set_to_paths<A: AsAid + From<String>>(
     // We will first gather the attributes that need to be retrieved
     // at this level. These are the fields that do not refer to a
     // nested entity. This is the easy part.
-    let pull_attributes = pull_attributes(selection_set);
+    let pull_attributes = pull_attributes(fields);
 
     // Now we process nested levels.
-    let nested_levels = selection_set
-        .items
-        .iter()
-        .flat_map(|item| match item {
-            Selection::Field(field) => {
-                if !field.selection_set.items.is_empty() {
-                    let mut parent_path = parent_path.to_vec();
-                    parent_path.push(A::from(field.name.to_string()));
-
-                    selection_set_to_paths(
-                        &field.selection_set,
-                        plan.clone(),
-                        &field.arguments,
-                        &parent_path,
-                    )
-                } else {
-                    vec![]
-                }
-            }
-            _ => unimplemented!(),
-        })
-        .collect::<Vec<Plan<A>>>();
-
-    let mut levels = nested_levels;
+    let mut levels = Vec::new();
+
+    for field in fields.iter() {
+        if !field.selection_set.items.is_empty() {
+            let mut parent_path = parent_path.to_vec();
+            parent_path.push(A::from(field.name.to_string()));
+
+            levels.extend(selection_set_to_paths(
+                &self::fields(&field.selection_set, fragments)?,
+                plan.clone(),
+                &field.arguments,
+                &parent_path,
+                fragments,
+            )?);
+        }
+    }
 
     // Here we don't actually want to include the current plan, if
     // we're not interested in any attributes at this level.
@@ -221,6 +275,7 @@ fn selection_set_to_paths<A: AsAid + From<String>>(
         } else {
             levels.push(Plan::PullLevel(PullLevel {
                 pull_attributes,
+                pull_rules: vec![],
                 path_attributes: parent_path.to_vec(),
                 pull_variable: this,
                 variables: vec![],
@@ -230,7 +285,7 @@ fn selection_set_to_paths<A: AsAid + From<String>>(
         }
     }
 
-    levels
+    Ok(levels)
 }
 
 impl<A: AsAid + From<String>> Implementable for GraphQl<A> {
@@ -245,7 +300,8 @@ impl<A: AsAid + From<String>> Implementable for GraphQl<A> {
         nested: &mut Iterative<'b, S, u64>,
         domain: &mut Domain<Self::A, S::Timestamp>,
         local_arrangements: &VariableMap<Self::A, Iterative<'b, S, u64>>,
-    ) -> (Implemented<'b, Self::A, S>, ShutdownHandle)
+        arrangements: &mut ArrangementMap<Iterative<'b, S, u64>>,
+    ) -> Result<(Implemented<'b, Self::A, S>, ShutdownHandle), Error>
     where
         S: Scope,
         S::Timestamp: Timestamp + Lattice + Rewind,
@@ -255,7 +311,7 @@ impl<A: AsAid + From<String>> Implementable for GraphQl<A> {
             paths: self.paths.clone(),
         };
 
-        parsed.implement(nested, domain, local_arrangements)
+        parsed.implement(nested, domain, local_arrangements, arrangements)
     }
 }
 
@@ -28,7 +28,7 @@ use crate::plan::{gensym, Dependencies, Implementable};
 use crate::plan::{Hector, Plan};
 use crate::timestamp::{Rewind, Time};
 use crate::ShutdownHandle;
-use crate::{Aid, Output, Value, Var, VariableMap};
+use crate::{Aid, ArrangementMap, Output, Value, Var, VariableMap};
 
 /// A plan for GraphQL queries, e.g. `{ Heroes { name age weight } }`.
 #[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
@@ -275,6 +275,7 @@ impl GraphQl {
         nested: &mut Iterative<'b, S, u64>,
         domain: &mut Domain<Aid, S::Timestamp>,
         _local_arrangements: &VariableMap<Self::A, Iterative<'b, S, u64>>,
+        _arrangements: &mut ArrangementMap<Iterative<'b, S, u64>>,
     ) -> (Stream<S, Output>, ShutdownHandle)
     where
         S: Scope,
@@ -6,6 +6,7 @@
 //! The overall structure and the CollectionExtender implementation is adapted from:
 //! https://github.com/frankmcsherry/differential-dataflow/tree/master/dogsdogsdogs
 
+use std::collections::hash_map::Entry;
 use std::collections::{HashMap, HashSet, VecDeque};
 use std::hash::Hash;
 use std::rc::Rc;
@@ -15,6 +16,7 @@ use timely::dataflow::operators::{Concatenate, Operator, Partition};
 use timely::dataflow::scopes::child::Iterative;
 use timely::dataflow::Scope;
 use timely::order::Product;
+use timely::progress::frontier::MutableAntichain;
 use timely::progress::Timestamp;
 use timely::worker::AsWorker;
 use timely::PartialOrder;
@@ -33,8 +35,8 @@ use crate::domain::Domain;
 use crate::logging::DeclarativeEvent;
 use crate::plan::{Dependencies, Implementable};
 use crate::timestamp::{altneu::AltNeu, Rewind};
-use crate::{AsAid, Value, Var};
-use crate::{CollectionRelation, Implemented, ShutdownHandle, VariableMap};
+use crate::{ArrangementMap, CollectionRelation, Implemented, ShutdownHandle, VariableMap};
+use crate::{AsAid, Error, PlanError, Value, Var};
 
 type Extender<'a, S, P, V> = Box<(dyn PrefixExtender<S, Prefix = P, Extension = V> + 'a)>;
 
@@ -131,6 +133,30 @@ pub struct Hector<A: AsAid> {
     pub variables: Vec<Var>,
     /// Bindings to join.
     pub bindings: Vec<Binding<A>>,
+    /// The order in which delta pipelines extend their prefixes.
+    #[serde(default)]
+    pub order: VariableOrder,
+}
+
+/// Strategies for choosing the order in which variables are bound.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub enum VariableOrder {
+    /// Extend prefixes in whatever order `plan_order` arrives at.
+    Default,
+    /// Extend prefixes to the variables in the given order, as far as
+    /// bindings allow. Whenever the next variable can't be bound yet,
+    /// the earliest one that can is extended to instead. Variables
+    /// not listed come last.
+    Explicit(Vec<Var>),
+    /// Extend prefixes through the smallest attributes first, going by
+    /// the number of datoms transacted into them.
+    Statistics,
+}
+
+impl Default for VariableOrder {
+    fn default() -> Self {
+        VariableOrder::Default
+    }
 }
 
 enum Direction {
@@ -173,13 +199,13 @@ where
 pub fn source_conflicts<A: AsAid>(
     source_index: usize,
     bindings: &[Binding<A>],
-) -> Vec<&Binding<A>> {
+) -> Result<Vec<&Binding<A>>, PlanError> {
     match bindings[source_index] {
         Binding::Attribute(ref source) => {
             let prefix_0 = vec![source.variables.0];
             let prefix_1 = vec![source.variables.1];
 
-            bindings
+            Ok(bindings
                 .iter()
                 .enumerate()
                 .flat_map(|(index, binding)| {
@@ -193,9 +219,11 @@ pub fn source_conflicts<A: AsAid>(
                         None
                     }
                 })
-                .collect()
+                .collect())
         }
-        _ => panic!("Source must be an AttributeBinding."),
+        _ => Err(PlanError::InvalidArgument(
+            "Source binding must be an attribute.".to_string(),
+        )),
     }
 }
 
@@ -209,7 +237,7 @@ pub fn source_conflicts<A: AsAid>(
 pub fn plan_order<A: AsAid>(
     source_index: usize,
     bindings: &[Binding<A>],
-) -> (Vec<Var>, Vec<Binding<A>>) {
+) -> Result<(Vec<Var>, Vec<Binding<A>>), PlanError> {
     let mut variables = bindings
         .iter()
         .flat_map(AsBinding::variables)
@@ -230,7 +258,11 @@ pub fn plan_order<A: AsAid>(
             prefix.push(source.variables.0);
             prefix.push(source.variables.1);
         }
-        _ => panic!("Source binding must be an attribute."),
+        _ => {
+            return Err(PlanError::InvalidArgument(
+                "Source binding must be an attribute.".to_string(),
+            ))
+        }
     }
 
     let candidates_for = |bindings: &[Binding<A>], target: Var| {
@@ -305,7 +337,50 @@ pub fn plan_order<A: AsAid>(
         ordered_bindings.push(candidate);
     }
 
-    (prefix, ordered_bindings)
+    Ok((prefix, ordered_bindings))
+}
+
+/// Orders the variables as `plan_order` does, but always extends to
+/// the cheapest variable next. The cost of a variable is given by the
+/// cheapest binding ready to extend the prefix to it, and ties are
+/// broken by variable. Returns the chosen variable order.
+pub fn plan_order_by<A, F>(
+    source_index: usize,
+    bindings: &[Binding<A>],
+    cost: F,
+) -> Result<Vec<Var>, PlanError>
+where
+    A: AsAid,
+    F: Fn(&Binding<A>, Var) -> usize,
+{
+    let mut prefix: Vec<Var> = match bindings[source_index] {
+        Binding::Attribute(ref source) => vec![source.variables.0, source.variables.1],
+        _ => {
+            return Err(PlanError::InvalidArgument(
+                "Source binding must be an attribute.".to_string(),
+            ))
+        }
+    };
+
+    loop {
+        let next = bindings
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| *index != source_index)
+            .filter_map(|(_, binding)| {
+                binding
+                    .ready_to_extend(&prefix)
+                    .map(|target| (cost(binding, target), target))
+            })
+            .min();
+
+        match next {
+            None => break,
+            Some((_, target)) => prefix.push(target),
+        }
+    }
+
+    Ok(prefix)
 }
 
 trait IndexNode<V> {
@@ -343,6 +418,48 @@ impl IndexNode<Value> for Vec<Value> {
     }
 }
 
+/// Checks that all attributes bound in a query maintain the indices
+/// delta pipelines import, before any pipeline is constructed.
+fn ensure_indices<A, T>(domain: &mut Domain<A, T>, bindings: &[Binding<A>]) -> Result<(), Error>
+where
+    A: AsAid,
+    T: Timestamp + Lattice + Rewind,
+{
+    for binding in bindings.iter() {
+        let attribute = match binding {
+            Binding::Attribute(binding) => binding,
+            Binding::Not(antijoin) => match *antijoin.binding {
+                Binding::Attribute(ref binding) => binding,
+                _ => continue,
+            },
+            _ => continue,
+        };
+
+        let a = &attribute.source_attribute;
+        let missing = if domain.forward_count(a).is_none() {
+            Some("forward count")
+        } else if domain.forward_propose(a).is_none() {
+            Some("forward propose")
+        } else if domain.forward_validate(a).is_none() {
+            Some("forward validate")
+        } else if domain.reverse_count(a).is_none() {
+            Some("reverse count")
+        } else if domain.reverse_propose(a).is_none() {
+            Some("reverse propose")
+        } else if domain.reverse_validate(a).is_none() {
+            Some("reverse validate")
+        } else {
+            None
+        };
+
+        if let Some(index) = missing {
+            return Err(PlanError::MissingIndex(a.to_string(), index).into());
+        }
+    }
+
+    Ok(())
+}
+
 impl<A: AsAid> Hector<A> {
     // @TODO pass single binding as argument?
     // @TODO make these static and take variables as well?
@@ -352,7 +469,8 @@ impl<A: AsAid> Hector<A> {
         nested: &mut Iterative<'b, S, u64>,
         domain: &mut Domain<A, S::Timestamp>,
         _local_arrangements: &VariableMap<A, Iterative<'b, S, u64>>,
-    ) -> (Implemented<'b, A, S>, ShutdownHandle)
+        _arrangements: &mut ArrangementMap<Iterative<'b, S, u64>>,
+    ) -> Result<(Implemented<'b, A, S>, ShutdownHandle), Error>
     where
         S: Scope,
         S::Timestamp: Timestamp + Lattice + Rewind,
@@ -363,7 +481,9 @@ impl<A: AsAid> Hector<A> {
         match self.bindings.first().unwrap() {
             Binding::Attribute(binding) => {
                 match domain.forward_propose(&binding.source_attribute) {
-                    None => panic!("Unknown attribute {}", &binding.source_attribute),
+                    None => Err(
+                        PlanError::UnknownAttribute(binding.source_attribute.to_string()).into(),
+                    ),
                     Some(forward_trace) => {
                         let name = format!("Propose({})", &binding.source_attribute);
                         let (forward, shutdown_forward) =
@@ -386,16 +506,17 @@ impl<A: AsAid> Hector<A> {
                             tuples,
                         };
 
-                        (
+                        Ok((
                             Implemented::Collection(relation),
                             ShutdownHandle::from_button(shutdown_forward),
-                        )
+                        ))
                     }
                 }
             }
-            _ => {
-                panic!("Passed a single, non-sourceable binding.");
-            }
+            _ => Err(PlanError::Unsupported(
+                "Passed a single, non-sourceable binding.".to_string(),
+            )
+            .into()),
         }
     }
 
@@ -490,8 +611,8 @@ where
         }
     }
 
-    fn into_bindings(&self) -> Vec<Binding<Self::A>> {
-        self.bindings.clone()
+    fn into_bindings(&self) -> Result<Vec<Binding<Self::A>>, PlanError> {
+        Ok(self.bindings.clone())
     }
 
     fn implement<'b, S>(
@@ -499,20 +620,66 @@ where
         nested: &mut Iterative<'b, S, u64>,
         domain: &mut Domain<A, S::Timestamp>,
         local_arrangements: &VariableMap<A, Iterative<'b, S, u64>>,
-    ) -> (Implemented<'b, Self::A, S>, ShutdownHandle)
+        arrangements: &mut ArrangementMap<Iterative<'b, S, u64>>,
+    ) -> Result<(Implemented<'b, Self::A, S>, ShutdownHandle), Error>
     where
         S: Scope,
         S::Timestamp: Timestamp + Lattice + Rewind,
     {
         if self.bindings.is_empty() {
-            panic!("No bindings passed.");
+            Err(PlanError::Unsupported("No bindings passed.".to_string()).into())
         } else if self.variables.is_empty() {
-            panic!("No variables requested.");
+            Err(PlanError::Unsupported("No variables requested.".to_string()).into())
         } else if self.bindings.len() == 1 {
-            self.implement_single_binding(nested, domain, local_arrangements)
+            self.implement_single_binding(nested, domain, local_arrangements, arrangements)
         // } else if self.bindings.len() == 2 {
         //     Hector::two_way(domain, local_arrangements, self.bindings[0].clone(), self.bindings[1].clone())
         } else {
+            // Delta pipelines import indices of all attributes in
+            // both directions, which must be there before we start.
+            ensure_indices(domain, &self.bindings)?;
+
+            // Costs are looked up up-front, because the delta
+            // pipelines below hold on to the domain.
+            let mut datom_counts = HashMap::new();
+            match self.order {
+                VariableOrder::Default => {}
+                VariableOrder::Explicit(ref order) => {
+                    let bound: HashSet<Var> = self
+                        .bindings
+                        .iter()
+                        .flat_map(AsBinding::variables)
+                        .collect();
+                    if let Some(var) = order.iter().find(|var| !bound.contains(*var)) {
+                        let msg = format!("Variable {} is not bound by any binding.", var);
+                        return Err(PlanError::InvalidArgument(msg).into());
+                    }
+                }
+                VariableOrder::Statistics => {
+                    for binding in self.bindings.iter() {
+                        if let Binding::Attribute(binding) = binding {
+                            let a = &binding.source_attribute;
+                            datom_counts.insert(a.clone(), domain.datom_count(a).max(0) as usize);
+                        }
+                    }
+                }
+            }
+
+            let cost = |binding: &Binding<A>, target: Var| match self.order {
+                VariableOrder::Default => 0,
+                VariableOrder::Explicit(ref order) => order
+                    .iter()
+                    .position(|var| *var == target)
+                    .unwrap_or(order.len()),
+                VariableOrder::Statistics => match binding {
+                    Binding::Attribute(binding) => datom_counts[&binding.source_attribute],
+                    // Constants propose a single value, everything
+                    // else only ever narrows down proposals.
+                    Binding::Constant(_) => 0,
+                    _ => std::usize::MAX,
+                },
+            };
+
             // In order to avoid delta pipelines looking at each
             // other's data in naughty ways, we need to run them all
             // inside a scope with lexicographic times.
@@ -545,16 +712,24 @@ where
                 // driven by changes to that binding.
 
                 let changes = self.bindings.iter().enumerate()
-                    .flat_map(|(idx, delta_binding)| match delta_binding {
+                    .filter_map(|(idx, delta_binding)| match delta_binding {
                         Binding::Attribute(delta_binding) => {
 
                             // We need to determine an order on the attributes
                             // that ensures that each is bound by preceeding
-                            // attributes. For now, we will take the requested order.
+                            // attributes.
 
                             // @TODO use binding order returned here?
                             // might be problematic to ensure ordering is maintained?
-                            let (variables, _) = plan_order(idx, &self.bindings);
+                            let variables = match self.order {
+                                VariableOrder::Default => plan_order(idx, &self.bindings).map(|order| order.0),
+                                _ => plan_order_by(idx, &self.bindings, &cost),
+                            };
+
+                            let variables = match variables {
+                                Err(error) => return Some(Err(Error::from(error))),
+                                Ok(variables) => variables,
+                            };
 
                             let mut prefix = Vec::with_capacity(variables.len());
 
@@ -568,25 +743,34 @@ where
                             // But to get away with that we need to check for single-variable
                             // bindings in conflict with the source binding.
 
-                            let propose = forward_proposes
-                                .entry(delta_binding.source_attribute.to_string())
-                                .or_insert_with(|| {
-                                    let (arranged, shutdown) = domain
-                                        .forward_propose(&delta_binding.source_attribute)
-                                        .expect("forward propose trace doesn't exist")
-                                        .import_frontier(&scope.parent.parent, &format!("Counts({:?})", &delta_binding.source_attribute));
-
-                                    shutdown_handle.add_button(shutdown);
+                            let propose = match forward_proposes.entry(delta_binding.source_attribute.to_string()) {
+                                Entry::Occupied(entry) => entry.into_mut(),
+                                Entry::Vacant(entry) => match domain.forward_propose(&delta_binding.source_attribute) {
+                                    None => {
+                                        let error = PlanError::MissingIndex(delta_binding.source_attribute.to_string(), "forward propose");
+                                        return Some(Err(Error::from(error)));
+                                    }
+                                    Some(trace) => {
+                                        let (arranged, shutdown) = trace.import_frontier(&scope.parent.parent, &format!("Counts({:?})", &delta_binding.source_attribute));
+                                        shutdown_handle.add_button(shutdown);
 
-                                    arranged
-                                });
+                                        entry.insert(arranged)
+                                    }
+                                },
+                            };
 
-                            let mut source_conflicts = source_conflicts(idx, &self.bindings);
+                            let mut source_conflicts = match source_conflicts(idx, &self.bindings) {
+                                Err(error) => return Some(Err(Error::from(error))),
+                                Ok(source_conflicts) => source_conflicts,
+                            };
 
                             let mut source = if !source_conflicts.is_empty() {
                                 // @TODO there can be more than one conflict
                                 // @TODO Not just constant bindings can cause issues here!
-                                assert_eq!(source_conflicts.len(), 1);
+                                if source_conflicts.len() > 1 {
+                                    let msg = format!("Can't resolve more than one conflict on {:?}", delta_binding);
+                                    return Some(Err(Error::from(PlanError::Unsupported(msg))));
+                                }
 
                                 let conflict = source_conflicts.pop().unwrap();
                                 // for conflict in source_conflicts.drain(..) {
@@ -618,7 +802,10 @@ where
                                                 }
                                             }
                                         }
-                                        _ => panic!("Can't resolve conflicts on {:?} bindings", conflict),
+                                        _ => {
+                                            let msg = format!("Can't resolve conflicts on {:?} bindings", conflict);
+                                            return Some(Err(Error::from(PlanError::Unsupported(msg))));
+                                        }
                                     // }
                                 }
                             } else {
@@ -683,11 +870,17 @@ where
                                             debug!("\t...using {:?}", other);
 
                                             match other {
-                                                Binding::Not(_other) => {
+                                                Binding::Not(other) => {
                                                     // Due to the way we enqueued the bindings above, we can now
                                                     // rely on the internal exteneder being available as the last
                                                     // extender on the stack.
-                                                    let internal_extender = extenders.pop().expect("No internal extender available on stack.");
+                                                    let internal_extender = match extenders.pop() {
+                                                        None => {
+                                                            let msg = format!("Can't negate {:?}, which doesn't extend {:?}", other, prefix);
+                                                            return Some(Err(Error::from(PlanError::Unsupported(msg))));
+                                                        }
+                                                        Some(extender) => extender,
+                                                    };
 
                                                     extenders.push(
                                                         Box::new(AntijoinExtender {
@@ -704,23 +897,26 @@ where
                                                 }
                                                 Binding::Attribute(other) => {
                                                     match direction(&prefix, other.variables) {
-                                                        Err(msg) => panic!(msg),
+                                                        Err(msg) => return Some(Err(Error::from(PlanError::Unsupported(msg.to_string())))),
                                                         Ok(direction) => match direction {
                                                             Direction::Forward(offset) => {
                                                                 let count = {
                                                                     let name = format!("Counts({:?})", &delta_binding.source_attribute);
-                                                                    let count = forward_counts
-                                                                        .entry(other.source_attribute.to_string())
-                                                                        .or_insert_with(|| {
-                                                                            let (arranged, shutdown) =
-                                                                                domain.forward_count(&other.source_attribute)
-                                                                                .expect("forward count doesn't exist")
-                                                                                .import_frontier(&scope.parent.parent, &name);
-
-                                                                            shutdown_handle.add_button(shutdown);
-
-                                                                            arranged
-                                                                        });
+                                                                    let count = match forward_counts.entry(other.source_attribute.to_string()) {
+                                                                        Entry::Occupied(entry) => entry.into_mut(),
+                                                                        Entry::Vacant(entry) => match domain.forward_count(&other.source_attribute) {
+                                                                            None => {
+                                                                                let error = PlanError::MissingIndex(other.source_attribute.to_string(), "forward count");
+                                                                                return Some(Err(Error::from(error)));
+                                                                            }
+                                                                            Some(trace) => {
+                                                                                let (arranged, shutdown) = trace.import_frontier(&scope.parent.parent, &name);
+                                                                                shutdown_handle.add_button(shutdown);
+
+                                                                                entry.insert(arranged)
+                                                                            }
+                                                                        },
+                                                                    };
 
                                                                     let neu = is_neu;
 
@@ -730,19 +926,22 @@ where
                                                                 };
 ;
                                                                 let propose = {
-                                                                    let propose = forward_proposes
-                                                                        .entry(other.source_attribute.to_string())
-                                                                        .or_insert_with(|| {
-                                                                            let name = format!("Propose({:?})", &delta_binding.source_attribute);
-                                                                            let (arranged, shutdown) = domain
-                                                                                .forward_propose(&other.source_attribute)
-                                                                                .expect("forward propose doesn't exist")
-                                                                                .import_frontier(&scope.parent.parent, &name);
-
-                                                                            shutdown_handle.add_button(shutdown);
-
-                                                                            arranged
-                                                                        });
+                                                                    let name = format!("Propose({:?})", &delta_binding.source_attribute);
+                                                                    let propose = match forward_proposes.entry(other.source_attribute.to_string()) {
+                                                                        Entry::Occupied(entry) => entry.into_mut(),
+                                                                        Entry::Vacant(entry) => match domain.forward_propose(&other.source_attribute) {
+                                                                            None => {
+                                                                                let error = PlanError::MissingIndex(other.source_attribute.to_string(), "forward propose");
+                                                                                return Some(Err(Error::from(error)));
+                                                                            }
+                                                                            Some(trace) => {
+                                                                                let (arranged, shutdown) = trace.import_frontier(&scope.parent.parent, &name);
+                                                                                shutdown_handle.add_button(shutdown);
+
+                                                                                entry.insert(arranged)
+                                                                            }
+                                                                        },
+                                                                    };
 
                                                                     let neu = is_neu;
 
@@ -752,19 +951,22 @@ where
                                                                 };
 
                                                                 let validate = {
-                                                                    let validate = forward_validates
-                                                                        .entry(other.source_attribute.to_string())
-                                                                        .or_insert_with(|| {
-                                                                            let name = format!("Validate({:?})", &delta_binding.source_attribute);
-                                                                            let (arranged, shutdown) = domain
-                                                                                .forward_validate(&other.source_attribute)
-                                                                                .expect("forward validate doesn't exist")
-                                                                                .import_frontier(&scope.parent.parent, &name);
-
-                                                                            shutdown_handle.add_button(shutdown);
-
-                                                                            arranged
-                                                                        });
+                                                                    let name = format!("Validate({:?})", &delta_binding.source_attribute);
+                                                                    let validate = match forward_validates.entry(other.source_attribute.to_string()) {
+                                                                        Entry::Occupied(entry) => entry.into_mut(),
+                                                                        Entry::Vacant(entry) => match domain.forward_validate(&other.source_attribute) {
+                                                                            None => {
+                                                                                let error = PlanError::MissingIndex(other.source_attribute.to_string(), "forward validate");
+                                                                                return Some(Err(Error::from(error)));
+                                                                            }
+                                                                            Some(trace) => {
+                                                                                let (arranged, shutdown) = trace.import_frontier(&scope.parent.parent, &name);
+                                                                                shutdown_handle.add_button(shutdown);
+
+                                                                                entry.insert(arranged)
+                                                                            }
+                                                                        },
+                                                                    };
 
                                                                     let neu = is_neu;
 
@@ -785,19 +987,22 @@ where
                                                             },
                                                             Direction::Reverse(offset) => {
                                                                 let count = {
-                                                                    let count = reverse_counts
-                                                                        .entry(other.source_attribute.to_string())
-                                                                        .or_insert_with(|| {
-                                                                            let name = format!("_Counts({:?})", &delta_binding.source_attribute);
-                                                                            let (arranged, shutdown) = domain
-                                                                                .reverse_count(&other.source_attribute)
-                                                                                .expect("reverse count doesn't exist")
-                                                                                .import_frontier(&scope.parent.parent, &name);
-
-                                                                            shutdown_handle.add_button(shutdown);
-
-                                                                            arranged
-                                                                        });
+                                                                    let name = format!("_Counts({:?})", &delta_binding.source_attribute);
+                                                                    let count = match reverse_counts.entry(other.source_attribute.to_string()) {
+                                                                        Entry::Occupied(entry) => entry.into_mut(),
+                                                                        Entry::Vacant(entry) => match domain.reverse_count(&other.source_attribute) {
+                                                                            None => {
+                                                                                let error = PlanError::MissingIndex(other.source_attribute.to_string(), "reverse count");
+                                                                                return Some(Err(Error::from(error)));
+                                                                            }
+                                                                            Some(trace) => {
+                                                                                let (arranged, shutdown) = trace.import_frontier(&scope.parent.parent, &name);
+                                                                                shutdown_handle.add_button(shutdown);
+
+                                                                                entry.insert(arranged)
+                                                                            }
+                                                                        },
+                                                                    };
 
                                                                     let neu = is_neu;
 
@@ -807,19 +1012,22 @@ where
                                                                 };
 ;
                                                                 let propose = {
-                                                                    let propose = reverse_proposes
-                                                                        .entry(other.source_attribute.to_string())
-                                                                        .or_insert_with(|| {
-                                                                            let name = format!("_Propose({:?})", &delta_binding.source_attribute);
-                                                                            let (arranged, shutdown) = domain
-                                                                                .reverse_propose(&other.source_attribute)
-                                                                                .expect("reverse propose doesn't exist")
-                                                                                .import_frontier(&scope.parent.parent, &name);
-
-                                                                            shutdown_handle.add_button(shutdown);
-
-                                                                            arranged
-                                                                        });
+                                                                    let name = format!("_Propose({:?})", &delta_binding.source_attribute);
+                                                                    let propose = match reverse_proposes.entry(other.source_attribute.to_string()) {
+                                                                        Entry::Occupied(entry) => entry.into_mut(),
+                                                                        Entry::Vacant(entry) => match domain.reverse_propose(&other.source_attribute) {
+                                                                            None => {
+                                                                                let error = PlanError::MissingIndex(other.source_attribute.to_string(), "reverse propose");
+                                                                                return Some(Err(Error::from(error)));
+                                                                            }
+                                                                            Some(trace) => {
+                                                                                let (arranged, shutdown) = trace.import_frontier(&scope.parent.parent, &name);
+                                                                                shutdown_handle.add_button(shutdown);
+
+                                                                                entry.insert(arranged)
+                                                                            }
+                                                                        },
+                                                                    };
 
                                                                     let neu = is_neu;
 
@@ -829,19 +1037,22 @@ where
                                                                 };
 
                                                                 let validate = {
-                                                                    let validate = reverse_validates
-                                                                        .entry(other.source_attribute.to_string())
-                                                                        .or_insert_with(|| {
-                                                                            let name = format!("_Validate({:?})", &delta_binding.source_attribute);
-                                                                            let (arranged, shutdown) = domain
-                                                                                .reverse_validate(&other.source_attribute)
-                                                                                .expect("reverse validate doesn't exist")
-                                                                                .import_frontier(&scope.parent.parent, &name);
-
-                                                                            shutdown_handle.add_button(shutdown);
-
-                                                                            arranged
-                                                                        });
+                                                                    let name = format!("_Validate({:?})", &delta_binding.source_attribute);
+                                                                    let validate = match reverse_validates.entry(other.source_attribute.to_string()) {
+                                                                        Entry::Occupied(entry) => entry.into_mut(),
+                                                                        Entry::Vacant(entry) => match domain.reverse_validate(&other.source_attribute) {
+                                                                            None => {
+                                                                                let error = PlanError::MissingIndex(other.source_attribute.to_string(), "reverse validate");
+                                                                                return Some(Err(Error::from(error)));
+                                                                            }
+                                                                            Some(trace) => {
+                                                                                let (arranged, shutdown) = trace.import_frontier(&scope.parent.parent, &name);
+                                                                                shutdown_handle.add_button(shutdown);
+
+                                                                                entry.insert(arranged)
+                                                                            }
+                                                                        },
+                                                                    };
 
                                                                     let neu = is_neu;
 
@@ -869,7 +1080,10 @@ where
                                         prefix.push(*target);
 
                                         // @TODO impl ProposeExtensionMethod for Arranged
-                                        let extended = source.extend(&mut extenders[..]);
+                                        let extended = match source.extend(&mut extenders[..]) {
+                                            Err(error) => return Some(Err(Error::from(error))),
+                                            Ok(extended) => extended,
+                                        };
 
                                         if logger.is_some() {
                                             let worker_index = scope.index();
@@ -898,31 +1112,32 @@ where
                             }
 
                             if self.variables == prefix {
-                                Some(source.inner)
+                                Some(Ok(source.inner))
                             } else {
                                 let target_variables = self.variables.clone();
 
-                                Some(source
+                                Some(Ok(source
                                      .map(move |tuple| {
                                          target_variables.iter()
                                              .flat_map(|x| Some(tuple.index(AsBinding::binds(&prefix, *x).unwrap())))
                                              .collect()
                                      })
-                                     .inner)
+                                     .inner))
                             }
                         }
                         _ => None
-                    });
+                    })
+                    .collect::<Result<Vec<_>, Error>>()?;
 
-                (inner.concatenate(changes).as_collection().leave(), shutdown_handle)
-            });
+                Ok((inner.concatenate(changes).as_collection().leave(), shutdown_handle))
+            })?;
 
             let relation = CollectionRelation {
                 variables: self.variables.clone(),
                 tuples: joined,
             };
 
-            (Implemented::Collection(relation), shutdown_handle)
+            Ok((Implemented::Collection(relation), shutdown_handle))
         }
     }
 }
@@ -935,19 +1150,20 @@ trait ProposeExtensionMethod<'a, S: Scope, P: ExchangeData + Ord> {
     fn extend<E: ExchangeData + Ord>(
         &self,
         extenders: &mut [Extender<'a, S, P, E>],
-    ) -> Collection<S, (P, E)>;
+    ) -> Result<Collection<S, (P, E)>, PlanError>;
 }
 
 impl<'a, S: Scope, P: ExchangeData + Ord> ProposeExtensionMethod<'a, S, P> for Collection<S, P> {
     fn extend<E: ExchangeData + Ord>(
         &self,
         extenders: &mut [Extender<'a, S, P, E>],
-    ) -> Collection<S, (P, E)> {
+    ) -> Result<Collection<S, (P, E)>, PlanError> {
         if extenders.is_empty() {
-            // @TODO don't panic
-            panic!("No extenders specified.");
+            Err(PlanError::Unsupported(
+                "No extenders specified.".to_string(),
+            ))
         } else if extenders.len() == 1 {
-            extenders[0].propose(&self.clone())
+            Ok(extenders[0].propose(&self.clone()))
         } else {
             let mut counts = self.map(|p| (p, 1 << 31, 0));
             for (index, extender) in extenders.iter_mut().enumerate() {
@@ -972,7 +1188,7 @@ impl<'a, S: Scope, P: ExchangeData + Ord> ProposeExtensionMethod<'a, S, P> for C
                 results.push(extensions.inner); // save extensions
             }
 
-            self.scope().concatenate(results).as_collection()
+            Ok(self.scope().concatenate(results).as_collection())
         }
     }
 }
@@ -1090,6 +1306,44 @@ where
     }
 }
 
+/// Visits runs of consecutive requests that look up the same key.
+fn for_each_run<R, K, F, G>(requests: &mut [R], key_of: F, mut visit: G)
+where
+    K: Eq,
+    F: Fn(&R) -> K,
+    G: FnMut(K, &mut [R]),
+{
+    let mut start = 0;
+    while start < requests.len() {
+        let key = key_of(&requests[start]);
+        let mut end = start + 1;
+        while end < requests.len() && key_of(&requests[end]) == key {
+            end += 1;
+        }
+
+        visit(key, &mut requests[start..end]);
+        start = end;
+    }
+}
+
+/// Accumulates the updates visible at the specified time.
+fn accumulate<T: PartialOrder>(times: &[(T, isize)], time: &T) -> isize {
+    times
+        .iter()
+        .filter(|(t, _)| t.less_equal(time))
+        .map(|(_, diff)| diff)
+        .sum()
+}
+
+/// Marks all requests at complete times as processed.
+fn retire<P, T: Timestamp>(run: &mut [(P, T, isize)], frontier: &MutableAntichain<T>) {
+    for &mut (_, ref time, ref mut diff) in run.iter_mut() {
+        if !frontier.less_equal(time) {
+            *diff = 0;
+        }
+    }
+}
+
 struct CollectionExtender<S, K, V, P, F, TrCount, TrPropose, TrValidate>
 where
     S: Scope,
@@ -1171,6 +1425,7 @@ where
 
         let mut buffer1 = Vec::new();
         let mut buffer2 = Vec::new();
+        let mut times = Vec::new();
 
         // TODO: This should be a custom operator with no connection from the second input to the output.
         Some(
@@ -1204,49 +1459,60 @@ where
                                 if !input2.frontier.less_equal(capability.time()) {
                                     let mut session = output.session(capability);
 
-                                    // sort requests for in-order cursor traversal. could consolidate?
+                                    // sort requests for in-order cursor traversal, s.t. each key is only visited once.
                                     prefixes
                                         .sort_by(|x, y| logic2(&(x.0).0).cmp(&logic2(&(y.0).0)));
 
                                     let (mut cursor, storage) = trace.cursor();
 
-                                    for &mut (
-                                        (ref prefix, old_count, old_index),
-                                        ref time,
-                                        ref mut diff,
-                                    ) in prefixes.iter_mut()
-                                    {
-                                        if !input2.frontier.less_equal(time) {
-                                            let key = logic2(prefix);
+                                    let frontier = &input2.frontier;
+                                    for_each_run(
+                                        prefixes,
+                                        |x| logic2(&(x.0).0),
+                                        |key, run| {
                                             cursor.seek_key(&storage, &key);
                                             if cursor.get_key(&storage) == Some(&key) {
-                                                let mut count = 0;
+                                                times.clear();
                                                 cursor.map_times(&storage, |t, d| {
-                                                    if t.less_equal(time) {
-                                                        count += d;
-                                                    }
+                                                    times.push((t.clone(), *d))
                                                 });
-                                                // assert!(count >= 0);
-                                                let count = count as usize;
-                                                if count > 0 {
-                                                    if count < old_count {
-                                                        session.give((
-                                                            (prefix.clone(), count, index),
-                                                            time.clone(),
-                                                            *diff,
-                                                        ));
-                                                    } else {
-                                                        session.give((
-                                                            (prefix.clone(), old_count, old_index),
-                                                            time.clone(),
-                                                            *diff,
-                                                        ));
+
+                                                for &(
+                                                    (ref prefix, old_count, old_index),
+                                                    ref time,
+                                                    diff,
+                                                ) in run.iter()
+                                                {
+                                                    if !frontier.less_equal(time) {
+                                                        // assert!(count >= 0);
+                                                        let count =
+                                                            accumulate(&times, time) as usize;
+                                                        if count > 0 {
+                                                            if count < old_count {
+                                                                session.give((
+                                                                    (prefix.clone(), count, index),
+                                                                    time.clone(),
+                                                                    diff,
+                                                                ));
+                                                            } else {
+                                                                session.give((
+                                                                    (
+                                                                        prefix.clone(),
+                                                                        old_count,
+                                                                        old_index,
+                                                                    ),
+                                                                    time.clone(),
+                                                                    diff,
+                                                                ));
+                                                            }
+                                                        }
                                                     }
                                                 }
                                             }
-                                            *diff = 0;
-                                        }
-                                    }
+
+                                            retire(run, frontier);
+                                        },
+                                    );
 
                                     prefixes.retain(|ptd| ptd.2 != 0);
                                 }
@@ -1280,6 +1546,7 @@ where
 
         let mut buffer1 = Vec::new();
         let mut buffer2 = Vec::new();
+        let mut times = Vec::new();
 
         let exchange = Exchange::new(move |update: &(P, S::Timestamp, isize)| {
             logic1(&update.0).hashed().as_u64()
@@ -1320,40 +1587,45 @@ where
                                 if !input2.frontier.less_equal(capability.time()) {
                                     let mut session = output.session(capability);
 
-                                    // sort requests for in-order cursor traversal. could consolidate?
+                                    // sort requests for in-order cursor traversal, s.t. each key is only visited once.
                                     prefixes.sort_by(|x, y| logic2(&x.0).cmp(&logic2(&y.0)));
 
                                     let (mut cursor, storage) = trace.cursor();
 
-                                    for &mut (ref prefix, ref time, ref mut diff) in
-                                        prefixes.iter_mut()
-                                    {
-                                        if !input2.frontier.less_equal(time) {
-                                            let key = logic2(prefix);
+                                    let frontier = &input2.frontier;
+                                    for_each_run(
+                                        prefixes,
+                                        |x| logic2(&x.0),
+                                        |key, run| {
                                             cursor.seek_key(&storage, &key);
                                             if cursor.get_key(&storage) == Some(&key) {
                                                 while let Some(value) = cursor.get_val(&storage) {
-                                                    let mut count = 0;
+                                                    times.clear();
                                                     cursor.map_times(&storage, |t, d| {
-                                                        if t.less_equal(time) {
-                                                            count += d;
-                                                        }
+                                                        times.push((t.clone(), *d))
                                                     });
-                                                    // assert!(count >= 0);
-                                                    if count > 0 {
-                                                        session.give((
-                                                            (prefix.clone(), value.clone()),
-                                                            time.clone(),
-                                                            *diff,
-                                                        ));
+
+                                                    for &(ref prefix, ref time, diff) in run.iter()
+                                                    {
+                                                        // assert!(count >= 0);
+                                                        if !frontier.less_equal(time)
+                                                            && accumulate(&times, time) > 0
+                                                        {
+                                                            session.give((
+                                                                (prefix.clone(), value.clone()),
+                                                                time.clone(),
+                                                                diff,
+                                                            ));
+                                                        }
                                                     }
+
                                                     cursor.step_val(&storage);
                                                 }
-                                                cursor.rewind_vals(&storage);
                                             }
-                                            *diff = 0;
-                                        }
-                                    }
+
+                                            retire(run, frontier);
+                                        },
+                                    );
 
                                     prefixes.retain(|ptd| ptd.2 != 0);
                                 }
@@ -1392,6 +1664,7 @@ where
 
         let mut buffer1 = Vec::new();
         let mut buffer2 = Vec::new();
+        let mut times = Vec::new();
 
         let exchange = Exchange::new(move |update: &((P, V), S::Timestamp, isize)| {
             (logic1(&(update.0).0).clone(), ((update.0).1).clone())
@@ -1434,7 +1707,7 @@ where
                                 if !input2.frontier.less_equal(capability.time()) {
                                     let mut session = output.session(capability);
 
-                                    // sort requests for in-order cursor traversal. could consolidate?
+                                    // sort requests for in-order cursor traversal, s.t. each key is only visited once.
                                     prefixes.sort_by(|x, y| {
                                         (logic2(&(x.0).0), &((x.0).1))
                                             .cmp(&(logic2(&(y.0).0), &((y.0).1)))
@@ -1442,31 +1715,35 @@ where
 
                                     let (mut cursor, storage) = trace.cursor();
 
-                                    for &mut (ref prefix, ref time, ref mut diff) in
-                                        prefixes.iter_mut()
-                                    {
-                                        if !input2.frontier.less_equal(time) {
-                                            let key = (logic2(&prefix.0), (prefix.1).clone());
+                                    let frontier = &input2.frontier;
+                                    for_each_run(
+                                        prefixes,
+                                        |x| (logic2(&(x.0).0), ((x.0).1).clone()),
+                                        |key, run| {
                                             cursor.seek_key(&storage, &key);
                                             if cursor.get_key(&storage) == Some(&key) {
-                                                let mut count = 0;
+                                                times.clear();
                                                 cursor.map_times(&storage, |t, d| {
-                                                    if t.less_equal(time) {
-                                                        count += d;
-                                                    }
+                                                    times.push((t.clone(), *d))
                                                 });
-                                                // assert!(count >= 0);
-                                                if count > 0 {
-                                                    session.give((
-                                                        prefix.clone(),
-                                                        time.clone(),
-                                                        *diff,
-                                                    ));
+
+                                                for &(ref prefix, ref time, diff) in run.iter() {
+                                                    // assert!(count >= 0);
+                                                    if !frontier.less_equal(time)
+                                                        && accumulate(&times, time) > 0
+                                                    {
+                                                        session.give((
+                                                            prefix.clone(),
+                                                            time.clone(),
+                                                            diff,
+                                                        ));
+                                                    }
                                                 }
                                             }
-                                            *diff = 0;
-                                        }
-                                    }
+
+                                            retire(run, frontier);
+                                        },
+                                    );
 
                                     prefixes.retain(|ptd| ptd.2 != 0);
                                 }
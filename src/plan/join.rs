@@ -1,5 +1,10 @@
 //! Equijoin expression plan.
 
+use std::any::Any;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::rc::Rc;
+
 use timely::dataflow::scopes::child::Iterative;
 use timely::dataflow::Scope;
 use timely::order::Product;
@@ -11,16 +16,16 @@ use differential_dataflow::operators::JoinCore;
 
 use crate::binding::{AsBinding, Binding};
 use crate::domain::Domain;
-use crate::plan::{Dependencies, Implementable};
+use crate::plan::{ensure_bound, Dependencies, Implementable};
 use crate::timestamp::Rewind;
-use crate::{AsAid, Value, Var};
 use crate::{
-    AttributeBinding, CollectionRelation, Implemented, Relation, ShutdownHandle, TraceValHandle,
-    VariableMap,
+    ArrangementMap, AttributeBinding, CollectionRelation, Implemented, Relation, ShutdownHandle,
+    TraceValHandle, VariableMap,
 };
+use crate::{AsAid, Error, PlanError, Value, Var};
 
 /// A plan stage joining two source relations on the specified
-/// variables. Throws if any of the join variables isn't bound by both
+/// variables. Fails if any of the join variables isn't bound by both
 /// sources.
 #[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
 pub struct Join<P1: Implementable, P2: Implementable> {
@@ -38,7 +43,7 @@ fn attribute_attribute<'b, A, S>(
     target: Var,
     left: AttributeBinding<A>,
     right: AttributeBinding<A>,
-) -> (Implemented<'b, A, S>, ShutdownHandle)
+) -> Result<(Implemented<'b, A, S>, ShutdownHandle), Error>
 where
     A: AsAid,
     S: Scope,
@@ -47,56 +52,56 @@ where
     let mut variables = Vec::with_capacity(3);
     variables.push(target);
 
-    let (left_arranged, shutdown_left) = {
-        let (index, shutdown_button) = if target == left.variables.0 {
-            variables.push(left.variables.1);
-            domain
-                .forward_propose(&left.source_attribute)
-                .expect("forward propose trace does not exist")
-                .import_frontier(
-                    &nested.parent,
-                    &format!("Propose({:?})", left.source_attribute),
-                )
-        } else if target == left.variables.1 {
-            variables.push(left.variables.0);
-            domain
-                .reverse_propose(&left.source_attribute)
-                .expect("reverse propose trace does not exist")
-                .import_frontier(
-                    &nested.parent,
-                    &format!("_Propose({:?})", left.source_attribute),
-                )
+    let import = |domain: &mut Domain<A, S::Timestamp>,
+                  nested: &mut Iterative<'b, S, u64>,
+                  binding: &AttributeBinding<A>|
+     -> Result<_, Error> {
+        let attribute = &binding.source_attribute;
+
+        if target == binding.variables.0 {
+            let (index, shutdown_button) = domain
+                .forward_propose(attribute)
+                .ok_or_else(|| PlanError::MissingIndex(attribute.to_string(), "forward"))?
+                .import_frontier(&nested.parent, &format!("Propose({:?})", attribute));
+
+            Ok((index.enter(nested), shutdown_button, binding.variables.1))
+        } else if target == binding.variables.1 {
+            let (index, shutdown_button) = domain
+                .reverse_propose(attribute)
+                .ok_or_else(|| PlanError::MissingIndex(attribute.to_string(), "reverse"))?
+                .import_frontier(&nested.parent, &format!("_Propose({:?})", attribute));
+
+            Ok((index.enter(nested), shutdown_button, binding.variables.0))
         } else {
-            panic!("Unbound target variable in Attribute<->Attribute join.");
-        };
-
-        (index.enter(nested), shutdown_button)
+            Err(Error::from(PlanError::UnboundVariable(target)))
+        }
     };
 
-    let (right_arranged, shutdown_right) = {
-        let (index, shutdown_button) = if target == right.variables.0 {
+    let (left_arranged, shutdown_left, left_variable) = import(domain, nested, &left)?;
+    variables.push(left_variable);
+
+    let mut shutdown_handle = ShutdownHandle::from_button(shutdown_left);
+
+    // Self-joins go through the same index on both sides, which is
+    // then only imported once.
+    let is_self_join = left.source_attribute == right.source_attribute
+        && ((target == left.variables.0 && target == right.variables.0)
+            || (target == left.variables.1 && target == right.variables.1));
+
+    let right_arranged = if is_self_join {
+        if target == right.variables.0 {
             variables.push(right.variables.1);
-            domain
-                .forward_propose(&right.source_attribute)
-                .expect("forward propose trace does not exist")
-                .import_frontier(
-                    &nested.parent,
-                    &format!("Propose({:?})", right.source_attribute),
-                )
-        } else if target == right.variables.1 {
-            variables.push(right.variables.0);
-            domain
-                .reverse_propose(&right.source_attribute)
-                .expect("reverse propose trace does not exist")
-                .import_frontier(
-                    &nested.parent,
-                    &format!("_Propose({:?})", right.source_attribute),
-                )
         } else {
-            panic!("Unbound target variable in Attribute<->Attribute join.");
-        };
+            variables.push(right.variables.0);
+        }
 
-        (index.enter(nested), shutdown_button)
+        left_arranged.clone()
+    } else {
+        let (right_arranged, shutdown_right, right_variable) = import(domain, nested, &right)?;
+        variables.push(right_variable);
+        shutdown_handle.add_button(shutdown_right);
+
+        right_arranged
     };
 
     let tuples = left_arranged.join_core(&right_arranged, move |key: &Value, v1, v2| {
@@ -108,62 +113,223 @@ where
         Some(out)
     });
 
-    let mut shutdown_handle = ShutdownHandle::from_button(shutdown_left);
-    shutdown_handle.add_button(shutdown_right);
-
     let relation = CollectionRelation { variables, tuples };
 
-    (Implemented::Collection(relation), shutdown_handle)
+    Ok((Implemented::Collection(relation), shutdown_handle))
 }
 
-fn collection_collection<'b, A, S>(
+/// Returns the specified join variables, followed by all other
+/// variables bound by both sides. Joining on all of them at once
+/// (via composite keys), rather than only on those specified, avoids
+/// producing the cross product of all matches on the specified ones.
+fn shared_variables(variables: &[Var], left: &[Var], right: &[Var]) -> Vec<Var> {
+    let mut shared = variables.to_vec();
+
+    for x in left.iter() {
+        if right.contains(x) && !shared.contains(x) {
+            shared.push(*x);
+        }
+    }
+
+    shared
+}
+
+/// A sub-plan of any type, s.t. the arrangements of sub-plans of
+/// different types can be kept in the same map. Sub-plans are only
+/// ever equal to sub-plans of the same type. Comparing them, rather
+/// than only their hashes, ensures that colliding sub-plans never
+/// share an arrangement.
+#[derive(Clone)]
+pub struct SharedPlan {
+    hash: u64,
+    plan: Rc<dyn Any>,
+    eq: fn(&dyn Any, &dyn Any) -> bool,
+}
+
+impl SharedPlan {
+    fn new<P: Hash + Eq + 'static>(plan: P) -> Self {
+        let mut hasher = DefaultHasher::new();
+        plan.hash(&mut hasher);
+
+        SharedPlan {
+            hash: hasher.finish(),
+            plan: Rc::new(plan),
+            eq: plan_eq::<P>,
+        }
+    }
+}
+
+fn plan_eq<P: Eq + 'static>(left: &dyn Any, right: &dyn Any) -> bool {
+    match (left.downcast_ref::<P>(), right.downcast_ref::<P>()) {
+        (Some(left), Some(right)) => left == right,
+        _ => false,
+    }
+}
+
+impl PartialEq for SharedPlan {
+    fn eq(&self, other: &Self) -> bool {
+        self.hash == other.hash && (self.eq)(&*self.plan, &*other.plan)
+    }
+}
+
+impl Eq for SharedPlan {}
+
+impl Hash for SharedPlan {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.hash.hash(state);
+    }
+}
+
+/// Identifies a sub-plan arranged by the specified variables. Plans
+/// that can be alpha-renamed are identified by their renamed form,
+/// and the renaming is kept, s.t. the variables recorded for a shared
+/// arrangement can be translated back into their own.
+#[derive(Clone)]
+pub(crate) struct ArrangementKey {
+    id: (SharedPlan, Vec<Var>),
+    renaming: Option<Vec<Var>>,
+}
+
+impl ArrangementKey {
+    pub(crate) fn new<P>(plan: &P, variables: &[Var]) -> Self
+    where
+        P: Implementable + Hash + Eq + Clone + 'static,
+    {
+        if let Some((renamed, order)) = plan.alpha_key() {
+            if variables.iter().all(|x| order.contains(x)) {
+                let mut key = ArrangementKey {
+                    id: (SharedPlan::new(renamed), vec![]),
+                    renaming: Some(order),
+                };
+                key.id.1 = key.to_shared(variables);
+
+                return key;
+            }
+        }
+
+        ArrangementKey {
+            id: (SharedPlan::new(plan.clone()), variables.to_vec()),
+            renaming: None,
+        }
+    }
+
+    /// Translates the sub-plan's own variables into those recorded
+    /// with its arrangement.
+    fn to_shared(&self, variables: &[Var]) -> Vec<Var> {
+        match self.renaming {
+            None => variables.to_vec(),
+            Some(ref order) => variables
+                .iter()
+                .map(|x| order.iter().position(|y| y == x).expect("unknown variable") as Var)
+                .collect(),
+        }
+    }
+
+    /// Translates the variables recorded with an arrangement into the
+    /// sub-plan's own.
+    fn to_own(&self, variables: &[Var]) -> Vec<Var> {
+        match self.renaming {
+            None => variables.to_vec(),
+            Some(ref order) => variables.iter().map(|x| order[*x as usize]).collect(),
+        }
+    }
+
+    /// Returns the arrangement recorded for this key, if any, in terms
+    /// of the sub-plan's own variables.
+    pub(crate) fn cached<'b, S>(
+        &self,
+        arrangements: &ArrangementMap<Iterative<'b, S, u64>>,
+    ) -> Option<(
+        Vec<Var>,
+        Arranged<
+            Iterative<'b, S, u64>,
+            TraceValHandle<Vec<Value>, Vec<Value>, Product<S::Timestamp, u64>, isize>,
+        >,
+    )>
+    where
+        S: Scope,
+        S::Timestamp: Timestamp + Lattice + Rewind,
+    {
+        arrangements
+            .get(&self.id)
+            .map(|(variables, arranged)| (self.to_own(variables), arranged.clone()))
+    }
+}
+
+/// Arranges a relation by the target variables, s.t. other joins on
+/// the same sub-plan and variables can re-use the arrangement. Re-uses
+/// an existing arrangement itself, if there is one.
+pub(crate) fn arrange_relation<'b, A, S, R>(
     nested: &mut Iterative<'b, S, u64>,
     domain: &mut Domain<A, S::Timestamp>,
+    arrangements: &mut ArrangementMap<Iterative<'b, S, u64>>,
+    key: ArrangementKey,
     target_variables: &[Var],
-    left: CollectionRelation<'b, S>,
-    right: CollectionRelation<'b, S>,
-) -> (Implemented<'b, A, S>, ShutdownHandle)
+    relation: R,
+) -> (
+    Vec<Var>,
+    Arranged<
+        Iterative<'b, S, u64>,
+        TraceValHandle<Vec<Value>, Vec<Value>, Product<S::Timestamp, u64>, isize>,
+    >,
+    ShutdownHandle,
+)
 where
     A: AsAid,
     S: Scope,
     S::Timestamp: Timestamp + Lattice + Rewind,
+    R: Relation<'b, A, S>,
 {
-    let mut shutdown_handle = ShutdownHandle::empty();
+    if let Some((variables, arranged)) = key.cached(arrangements) {
+        return (variables, arranged, ShutdownHandle::empty());
+    }
 
+    let variables: Vec<Var> = relation
+        .variables()
+        .drain(..)
+        .filter(|x| !target_variables.contains(x))
+        .collect();
+
+    let (tuples, shutdown) = relation.tuples_by_variables(nested, domain, target_variables);
+    let arranged = tuples.arrange();
+
+    arrangements.insert(
+        key.id.clone(),
+        (key.to_shared(&variables), arranged.clone()),
+    );
+
+    (variables, arranged, shutdown)
+}
+
+fn join_arranged<'b, A, S>(
+    target_variables: &[Var],
+    (left_variables, left_arranged): (
+        Vec<Var>,
+        Arranged<
+            Iterative<'b, S, u64>,
+            TraceValHandle<Vec<Value>, Vec<Value>, Product<S::Timestamp, u64>, isize>,
+        >,
+    ),
+    (right_variables, right_arranged): (
+        Vec<Var>,
+        Arranged<
+            Iterative<'b, S, u64>,
+            TraceValHandle<Vec<Value>, Vec<Value>, Product<S::Timestamp, u64>, isize>,
+        >,
+    ),
+) -> Implemented<'b, A, S>
+where
+    A: AsAid,
+    S: Scope,
+    S::Timestamp: Timestamp + Lattice + Rewind,
+{
     let variables = target_variables
         .iter()
         .cloned()
-        .chain(
-            left.variables()
-                .drain(..)
-                .filter(|x| !target_variables.contains(x)),
-        )
-        .chain(
-            right
-                .variables()
-                .drain(..)
-                .filter(|x| !target_variables.contains(x)),
-        )
+        .chain(left_variables.into_iter())
+        .chain(right_variables.into_iter())
         .collect();
 
-    let left_arranged: Arranged<
-        Iterative<'b, S, u64>,
-        TraceValHandle<Vec<Value>, Vec<Value>, Product<S::Timestamp, u64>, isize>,
-    > = {
-        let (arranged, shutdown) = left.tuples_by_variables(nested, domain, &target_variables);
-        shutdown_handle.merge_with(shutdown);
-        arranged.arrange()
-    };
-
-    let right_arranged: Arranged<
-        Iterative<'b, S, u64>,
-        TraceValHandle<Vec<Value>, Vec<Value>, Product<S::Timestamp, u64>, isize>,
-    > = {
-        let (arranged, shutdown) = right.tuples_by_variables(nested, domain, &target_variables);
-        shutdown_handle.merge_with(shutdown);
-        arranged.arrange()
-    };
-
     let tuples = left_arranged.join_core(&right_arranged, |key: &Vec<Value>, v1, v2| {
         Some(
             key.iter()
@@ -174,52 +340,166 @@ where
         )
     });
 
-    let relation = CollectionRelation { variables, tuples };
-
-    (Implemented::Collection(relation), shutdown_handle)
+    Implemented::Collection(CollectionRelation { variables, tuples })
 }
 
-fn collection_attribute<'b, A, S>(
+fn collection_collection<'b, A, S>(
     nested: &mut Iterative<'b, S, u64>,
     domain: &mut Domain<A, S::Timestamp>,
+    arrangements: &mut ArrangementMap<Iterative<'b, S, u64>>,
     target_variables: &[Var],
-    left: CollectionRelation<'b, S>,
-    right: AttributeBinding<A>,
+    (left_key, left): (ArrangementKey, CollectionRelation<'b, S>),
+    (right_key, right): (ArrangementKey, CollectionRelation<'b, S>),
 ) -> (Implemented<'b, A, S>, ShutdownHandle)
 where
     A: AsAid,
     S: Scope,
     S::Timestamp: Timestamp + Lattice + Rewind,
 {
-    // @TODO specialized implementation
+    let mut shutdown_handle = ShutdownHandle::empty();
 
-    let (tuples, shutdown_propose) = match domain.forward_propose(&right.source_attribute) {
-        None => panic!("attribute {:?} does not exist", &right.source_attribute),
+    let (left_variables, left_arranged, shutdown) = arrange_relation(
+        nested,
+        domain,
+        arrangements,
+        left_key,
+        target_variables,
+        left,
+    );
+    shutdown_handle.merge_with(shutdown);
+
+    let (right_variables, right_arranged, shutdown) = arrange_relation(
+        nested,
+        domain,
+        arrangements,
+        right_key,
+        target_variables,
+        right,
+    );
+    shutdown_handle.merge_with(shutdown);
+
+    let implemented = join_arranged(
+        target_variables,
+        (left_variables, left_arranged),
+        (right_variables, right_arranged),
+    );
+
+    (implemented, shutdown_handle)
+}
+
+fn collect_attribute<'b, A, S>(
+    nested: &mut Iterative<'b, S, u64>,
+    domain: &mut Domain<A, S::Timestamp>,
+    binding: AttributeBinding<A>,
+) -> Result<(CollectionRelation<'b, S>, ShutdownHandle), Error>
+where
+    A: AsAid,
+    S: Scope,
+    S::Timestamp: Timestamp + Lattice + Rewind,
+{
+    match domain.forward_propose(&binding.source_attribute) {
+        None => Err(PlanError::UnknownAttribute(binding.source_attribute.to_string()).into()),
         Some(propose_trace) => {
             let (propose, shutdown_propose) = propose_trace.import_frontier(
                 &nested.parent,
-                &format!("Propose({:?})", right.source_attribute),
+                &format!("Propose({:?})", binding.source_attribute),
             );
 
             let tuples = propose
                 .enter(nested)
                 .as_collection(|e, v| vec![e.clone(), v.clone()]);
 
-            (tuples, shutdown_propose)
+            let relation = CollectionRelation {
+                variables: vec![binding.variables.0, binding.variables.1],
+                tuples,
+            };
+
+            Ok((relation, ShutdownHandle::from_button(shutdown_propose)))
         }
-    };
+    }
+}
 
-    let right_collected = CollectionRelation {
-        variables: vec![right.variables.0, right.variables.1],
-        tuples,
-    };
+fn collection_attribute<'b, A, S>(
+    nested: &mut Iterative<'b, S, u64>,
+    domain: &mut Domain<A, S::Timestamp>,
+    arrangements: &mut ArrangementMap<Iterative<'b, S, u64>>,
+    target_variables: &[Var],
+    left: (ArrangementKey, CollectionRelation<'b, S>),
+    (right_key, right): (ArrangementKey, AttributeBinding<A>),
+) -> Result<(Implemented<'b, A, S>, ShutdownHandle), Error>
+where
+    A: AsAid,
+    S: Scope,
+    S::Timestamp: Timestamp + Lattice + Rewind,
+{
+    // @TODO specialized implementation
 
-    let (implemented, mut shutdown_handle) =
-        collection_collection(nested, domain, target_variables, left, right_collected);
+    let (right_collected, shutdown_propose) = collect_attribute(nested, domain, right)?;
 
-    shutdown_handle.add_button(shutdown_propose);
+    let (implemented, mut shutdown_handle) = collection_collection(
+        nested,
+        domain,
+        arrangements,
+        target_variables,
+        left,
+        (right_key, right_collected),
+    );
 
-    (implemented, shutdown_handle)
+    shutdown_handle.merge_with(shutdown_propose);
+
+    Ok((implemented, shutdown_handle))
+}
+
+/// Joins two arrangements on additional variables, which they both
+/// hold outside of their keys, by re-arranging them on the full set
+/// of shared variables.
+fn rekey_arranged<'b, A, S>(
+    nested: &mut Iterative<'b, S, u64>,
+    domain: &mut Domain<A, S::Timestamp>,
+    arrangements: &mut ArrangementMap<Iterative<'b, S, u64>>,
+    key_variables: &[Var],
+    target_variables: &[Var],
+    (left_key, (left_variables, left_arranged)): (
+        ArrangementKey,
+        (
+            Vec<Var>,
+            Arranged<
+                Iterative<'b, S, u64>,
+                TraceValHandle<Vec<Value>, Vec<Value>, Product<S::Timestamp, u64>, isize>,
+            >,
+        ),
+    ),
+    (right_key, (right_variables, right_arranged)): (
+        ArrangementKey,
+        (
+            Vec<Var>,
+            Arranged<
+                Iterative<'b, S, u64>,
+                TraceValHandle<Vec<Value>, Vec<Value>, Product<S::Timestamp, u64>, isize>,
+            >,
+        ),
+    ),
+) -> (Implemented<'b, A, S>, ShutdownHandle)
+where
+    A: AsAid,
+    S: Scope,
+    S::Timestamp: Timestamp + Lattice + Rewind,
+{
+    let flatten = |variables: Vec<Var>, arranged: Arranged<_, _>| CollectionRelation {
+        variables: key_variables.iter().cloned().chain(variables).collect(),
+        tuples: arranged.as_collection(|key: &Vec<Value>, values: &Vec<Value>| {
+            key.iter().chain(values.iter()).cloned().collect()
+        }),
+    };
+
+    collection_collection(
+        nested,
+        domain,
+        arrangements,
+        target_variables,
+        (left_key, flatten(left_variables, left_arranged)),
+        (right_key, flatten(right_variables, right_arranged)),
+    )
 }
 
 //             Some(var) => {
@@ -258,22 +538,26 @@ where
 //                 (forwarded, ShutdownHandle::from_button(shutdown_button))
 //             }
 
-impl<P1: Implementable, P2: Implementable<A = P1::A>> Implementable for Join<P1, P2> {
+impl<P1, P2> Implementable for Join<P1, P2>
+where
+    P1: Implementable + Hash + Eq + Clone + 'static,
+    P2: Implementable<A = P1::A> + Hash + Eq + Clone + 'static,
+{
     type A = P1::A;
 
     fn dependencies(&self) -> Dependencies<Self::A> {
         self.left_plan.dependencies() + self.right_plan.dependencies()
     }
 
-    fn into_bindings(&self) -> Vec<Binding<Self::A>> {
-        let mut left_bindings = self.left_plan.into_bindings();
-        let mut right_bindings = self.right_plan.into_bindings();
+    fn into_bindings(&self) -> Result<Vec<Binding<Self::A>>, PlanError> {
+        let mut left_bindings = self.left_plan.into_bindings()?;
+        let mut right_bindings = self.right_plan.into_bindings()?;
 
         let mut bindings = Vec::with_capacity(left_bindings.len() + right_bindings.len());
         bindings.append(&mut left_bindings);
         bindings.append(&mut right_bindings);
 
-        bindings
+        Ok(bindings)
     }
 
     fn implement<'b, S>(
@@ -281,51 +565,177 @@ impl<P1: Implementable, P2: Implementable<A = P1::A>> Implementable for Join<P1,
         nested: &mut Iterative<'b, S, u64>,
         domain: &mut Domain<Self::A, S::Timestamp>,
         local_arrangements: &VariableMap<Self::A, Iterative<'b, S, u64>>,
-    ) -> (Implemented<'b, Self::A, S>, ShutdownHandle)
+        arrangements: &mut ArrangementMap<Iterative<'b, S, u64>>,
+    ) -> Result<(Implemented<'b, Self::A, S>, ShutdownHandle), Error>
     where
         S: Scope,
         S::Timestamp: Timestamp + Lattice + Rewind,
     {
-        assert!(!self.variables.is_empty());
+        if self.variables.is_empty() {
+            return Err(
+                PlanError::Unsupported("Joins require at least one variable.".to_string()).into(),
+            );
+        }
+
+        let left_key = ArrangementKey::new(&*self.left_plan, &self.variables);
+        let right_key = ArrangementKey::new(&*self.right_plan, &self.variables);
+
+        // Sub-plans that were arranged by the same variables elsewhere
+        // are joined via their existing arrangement, instead of being
+        // implemented and arranged again.
+        if arrangements.contains_key(&left_key.id) || arrangements.contains_key(&right_key.id) {
+            let mut shutdown_handle = ShutdownHandle::empty();
+
+            let left_arranged = match left_key.cached(arrangements) {
+                Some(cached) => cached,
+                None => {
+                    let (left, shutdown) = self.left_plan.implement(
+                        nested,
+                        domain,
+                        local_arrangements,
+                        arrangements,
+                    )?;
+                    shutdown_handle.merge_with(shutdown);
+                    ensure_bound(&left, &self.variables)?;
+
+                    let (variables, arranged, shutdown) = arrange_relation(
+                        nested,
+                        domain,
+                        arrangements,
+                        left_key,
+                        &self.variables,
+                        left,
+                    );
+                    shutdown_handle.merge_with(shutdown);
+
+                    (variables, arranged)
+                }
+            };
+
+            let right_arranged = match right_key.cached(arrangements) {
+                Some(cached) => cached,
+                None => {
+                    let (right, shutdown) = self.right_plan.implement(
+                        nested,
+                        domain,
+                        local_arrangements,
+                        arrangements,
+                    )?;
+                    shutdown_handle.merge_with(shutdown);
+                    ensure_bound(&right, &self.variables)?;
+
+                    let (variables, arranged, shutdown) = arrange_relation(
+                        nested,
+                        domain,
+                        arrangements,
+                        right_key,
+                        &self.variables,
+                        right,
+                    );
+                    shutdown_handle.merge_with(shutdown);
+
+                    (variables, arranged)
+                }
+            };
+
+            let variables = shared_variables(&self.variables, &left_arranged.0, &right_arranged.0);
+
+            if variables.len() == self.variables.len() {
+                let implemented = join_arranged(&self.variables, left_arranged, right_arranged);
+
+                return Ok((implemented, shutdown_handle));
+            } else {
+                let (implemented, shutdown) = rekey_arranged(
+                    nested,
+                    domain,
+                    arrangements,
+                    &self.variables,
+                    &variables,
+                    (
+                        ArrangementKey::new(&*self.left_plan, &variables),
+                        left_arranged,
+                    ),
+                    (
+                        ArrangementKey::new(&*self.right_plan, &variables),
+                        right_arranged,
+                    ),
+                );
+                shutdown_handle.merge_with(shutdown);
+
+                return Ok((implemented, shutdown_handle));
+            }
+        }
 
-        let (left, shutdown_left) = self.left_plan.implement(nested, domain, local_arrangements);
-        let (right, shutdown_right) = self
-            .right_plan
-            .implement(nested, domain, local_arrangements);
+        let (left, shutdown_left) =
+            self.left_plan
+                .implement(nested, domain, local_arrangements, arrangements)?;
+        let (right, shutdown_right) =
+            self.right_plan
+                .implement(nested, domain, local_arrangements, arrangements)?;
+
+        ensure_bound(&left, &self.variables)?;
+        ensure_bound(&right, &self.variables)?;
+
+        let variables = shared_variables(&self.variables, &left.variables(), &right.variables());
+        let left_key = ArrangementKey::new(&*self.left_plan, &variables);
+        let right_key = ArrangementKey::new(&*self.right_plan, &variables);
 
         let (implemented, mut shutdown_handle) = match left {
-            Implemented::Attribute(left) => {
-                match right {
-                    Implemented::Attribute(right) => {
-                        if self.variables.len() == 1 {
-                            attribute_attribute(nested, domain, self.variables[0], left, right)
-                        } else if self.variables.len() == 2 {
-                            unimplemented!();
-                        // intersect_attributes(domain, self.variables, left, right)
-                        } else {
-                            panic!(
-                                "Attribute<->Attribute joins can't target more than two variables."
-                            );
-                        }
-                    }
-                    Implemented::Collection(right) => {
-                        collection_attribute(nested, domain, &self.variables, right, left)
+            Implemented::Attribute(left) => match right {
+                Implemented::Attribute(right) => {
+                    if variables.len() == 1 {
+                        attribute_attribute(nested, domain, variables[0], left, right)?
+                    } else {
+                        // @TODO intersect attribute indices directly
+                        let (left, shutdown_left) = collect_attribute(nested, domain, left)?;
+                        let (right, shutdown_right) = collect_attribute(nested, domain, right)?;
+
+                        let (implemented, mut shutdown_handle) = collection_collection(
+                            nested,
+                            domain,
+                            arrangements,
+                            &variables,
+                            (left_key, left),
+                            (right_key, right),
+                        );
+                        shutdown_handle.merge_with(shutdown_left);
+                        shutdown_handle.merge_with(shutdown_right);
+
+                        (implemented, shutdown_handle)
                     }
                 }
-            }
+                Implemented::Collection(right) => collection_attribute(
+                    nested,
+                    domain,
+                    arrangements,
+                    &variables,
+                    (right_key, right),
+                    (left_key, left),
+                )?,
+            },
             Implemented::Collection(left) => match right {
-                Implemented::Attribute(right) => {
-                    collection_attribute(nested, domain, &self.variables, left, right)
-                }
-                Implemented::Collection(right) => {
-                    collection_collection(nested, domain, &self.variables, left, right)
-                }
+                Implemented::Attribute(right) => collection_attribute(
+                    nested,
+                    domain,
+                    arrangements,
+                    &variables,
+                    (left_key, left),
+                    (right_key, right),
+                )?,
+                Implemented::Collection(right) => collection_collection(
+                    nested,
+                    domain,
+                    arrangements,
+                    &variables,
+                    (left_key, left),
+                    (right_key, right),
+                ),
             },
         };
 
         shutdown_handle.merge_with(shutdown_left);
         shutdown_handle.merge_with(shutdown_right);
 
-        (implemented, shutdown_handle)
+        Ok((implemented, shutdown_handle))
     }
 }
@@ -4,23 +4,36 @@ use std::collections::HashSet;
 use std::ops::Deref;
 use std::sync::atomic::{self, AtomicUsize};
 
+use timely::dataflow::operators::{Concatenate, Map, ToStream};
 use timely::dataflow::scopes::child::Iterative;
 use timely::dataflow::Scope;
+use timely::order::Product;
 use timely::progress::Timestamp;
 
 use differential_dataflow::lattice::Lattice;
+use differential_dataflow::operators::arrange::{ArrangeBySelf, Arranged};
+use differential_dataflow::operators::{Count, JoinCore, Threshold};
+use differential_dataflow::AsCollection;
 
 use crate::binding::{AsBinding, AttributeBinding, Binding};
+use crate::domain::fulltext::{self, Term};
 use crate::domain::Domain;
 use crate::timestamp::Rewind;
-use crate::{AsAid, Eid, Value, Var};
-use crate::{CollectionRelation, Implemented, Relation, ShutdownHandle, VariableMap};
+use crate::{
+    ArrangementMap, CollectionRelation, Implemented, Relation, ShutdownHandle, VariableMap,
+};
+use crate::{AsAid, Eid, Error, PlanError, TraceKeyHandle, Value, Var};
 
 #[cfg(feature = "set-semantics")]
 pub mod aggregate;
 #[cfg(not(feature = "set-semantics"))]
 pub mod aggregate_neu;
 pub mod antijoin;
+pub mod builder;
+pub mod coalesce;
+pub mod debounce;
+pub mod delay;
+pub mod downsample;
 pub mod filter;
 #[cfg(feature = "graphql")]
 pub mod graphql;
@@ -30,24 +43,37 @@ pub mod hector;
 pub mod join;
 pub mod project;
 pub mod pull;
+pub mod sample;
+pub mod sessionize;
 // pub mod pull_v2;
 pub mod transform;
+pub mod transitions;
 pub mod union;
+pub mod within;
 
 #[cfg(feature = "set-semantics")]
 pub use self::aggregate::{Aggregate, AggregationFn};
 #[cfg(not(feature = "set-semantics"))]
 pub use self::aggregate_neu::{Aggregate, AggregationFn};
 pub use self::antijoin::Antijoin;
+pub use self::builder::PlanBuilder;
+pub use self::coalesce::Coalesce;
+pub use self::debounce::Debounce;
+pub use self::delay::Delay;
+pub use self::downsample::{Downsample, DownsampleFn};
 pub use self::filter::{Filter, Predicate};
 #[cfg(feature = "graphql")]
 pub use self::graphql::GraphQl;
-pub use self::hector::Hector;
+pub use self::hector::{Hector, VariableOrder};
 pub use self::join::Join;
 pub use self::project::Project;
-pub use self::pull::{Pull, PullAll, PullLevel};
+pub use self::pull::{Pull, PullAll, PullLevel, PullPattern};
+pub use self::sample::Sample;
+pub use self::sessionize::Sessionize;
 pub use self::transform::{Function, Transform};
+pub use self::transitions::Transitions;
 pub use self::union::Union;
+pub use self::within::{Region, Within};
 
 static SYM: AtomicUsize = AtomicUsize::new(std::usize::MAX);
 
@@ -133,22 +159,81 @@ pub trait Implementable {
 
     /// Transforms an implementable into an equivalent set of bindings
     /// that can be unified by Hector.
-    fn into_bindings(&self) -> Vec<Binding<Self::A>> {
-        panic!("This plan can't be implemented via Hector.");
+    fn into_bindings(&self) -> Result<Vec<Binding<Self::A>>, PlanError> {
+        Err(PlanError::Unsupported(
+            "This plan can't be implemented via Hector.".to_string(),
+        ))
     }
 
-    /// Implements the type as a simple relation.
+    /// Returns the plan with its variables renamed consistently,
+    /// along with its variables in the order in which they were
+    /// renamed. Sub-plans differing only in the names of their
+    /// variables, such as the two sides of a self-join, can thus
+    /// share arrangements. By default, plans are only identified with
+    /// themselves.
+    fn alpha_key(&self) -> Option<(Self, Vec<Var>)>
+    where
+        Self: Sized,
+    {
+        None
+    }
+
+    /// Returns true iff the relation implemented by the plan can only
+    /// ever grow, given which attributes do. Plans that can't tell
+    /// are not monotonic by default.
+    fn is_monotonic<F>(&self, _is_monotonic_attribute: &F) -> bool
+    where
+        F: Fn(&Self::A) -> bool,
+    {
+        false
+    }
+
+    /// Implements the type as a simple relation, failing if it
+    /// references anything not available in the domain or scope.
     fn implement<'b, S>(
         &self,
         nested: &mut Iterative<'b, S, u64>,
         domain: &mut Domain<Self::A, S::Timestamp>,
         local_arrangements: &VariableMap<Self::A, Iterative<'b, S, u64>>,
-    ) -> (Implemented<'b, Self::A, S>, ShutdownHandle)
+        arrangements: &mut ArrangementMap<Iterative<'b, S, u64>>,
+    ) -> Result<(Implemented<'b, Self::A, S>, ShutdownHandle), Error>
     where
         S: Scope,
         S::Timestamp: Timestamp + Lattice + Rewind;
 }
 
+/// Ensures that a relation binds all of the specified variables,
+/// before any of them are looked up in its tuples.
+pub(crate) fn ensure_bound<B: AsBinding>(relation: &B, variables: &[Var]) -> Result<(), Error> {
+    match variables
+        .iter()
+        .find(|variable| relation.binds(**variable).is_none())
+    {
+        None => Ok(()),
+        Some(unbound) => Err(PlanError::UnboundVariable(*unbound).into()),
+    }
+}
+
+/// Arranges a collection holding only the specified key, for seeking
+/// it in an index via a join, instead of scanning the whole index.
+/// The key is introduced by the first worker only, s.t. it is held
+/// once, rather than once per worker.
+fn seek_key<'b, S>(
+    nested: &mut Iterative<'b, S, u64>,
+    key: Value,
+) -> Arranged<Iterative<'b, S, u64>, TraceKeyHandle<Value, Product<S::Timestamp, u64>, isize>>
+where
+    S: Scope,
+    S::Timestamp: Timestamp + Lattice,
+{
+    let key = if nested.index() == 0 { Some(key) } else { None };
+
+    key.to_stream(nested)
+        .map(|key| (key, Default::default(), 1))
+        .as_collection()
+        .arrange_by_self()
+}
+
 /// Possible query plan types.
 #[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
 pub enum Plan<A: AsAid> {
@@ -156,6 +241,20 @@ pub enum Plan<A: AsAid> {
     Project(Project<Plan<A>>),
     /// Aggregation
     Aggregate(Aggregate<Plan<A>>),
+    /// Per-bucket aggregation of time series
+    Downsample(Downsample<Plan<A>>),
+    /// Groups events into sessions of activity
+    Sessionize(Sessionize<Plan<A>>),
+    /// Emits changes of keyed values as transitions
+    Transitions(Transitions<Plan<A>>),
+    /// Holds back tuples until they are stable
+    Debounce(Debounce<Plan<A>>),
+    /// Shifts tuples forward in time
+    Delay(Delay<Plan<A>>),
+    /// Keeps a deterministic fraction of tuples
+    Sample(Sample<Plan<A>>),
+    /// Values of the first alternative holding any
+    Coalesce(Coalesce<Plan<A>>),
     /// Union
     Union(Union<Plan<A>>),
     /// Equijoin
@@ -170,14 +269,23 @@ pub enum Plan<A: AsAid> {
     Filter(Filter<Plan<A>>),
     /// Transforms a binding by a function expression
     Transform(Transform<Plan<A>>),
+    /// Filters bindings by whether a geo point lies within a region
+    Within(Within<Plan<A>>),
     /// Data pattern of the form [?e a ?v]
     MatchA(Var, A, Var),
     /// Data pattern of the form [e a ?v]
     MatchEA(Eid, A, Var),
     /// Data pattern of the form [?e a v]
     MatchAV(Var, A, Value),
+    /// Data pattern of the form [[a' v'] a ?v], where the entity is
+    /// identified by its value v' for attribute a'
+    MatchLookupA((A, Value), A, Var),
     /// Sources data from another relation.
     NameExpr(Vec<Var>, A),
+    /// Full-text search for entities whose values for a fulltext
+    /// attribute match all terms of the query. Terms ending in `*`
+    /// match by prefix.
+    Search(Var, A, String),
     /// Pull expression
     Pull(Pull<Plan<A>>),
     /// Single-level pull expression
@@ -205,11 +313,39 @@ impl<A: AsAid> Plan<A> {
         Plan::MatchAV(e, a.into(), v.into())
     }
 
+    /// Returns a plan expressing a base data pattern, on the entity
+    /// referred to by the specified lookup ref.
+    pub fn match_lookup_a<RX: Into<A>, RV: Into<Value>, AX: Into<A>>(
+        lookup: (RX, RV),
+        a: AX,
+        v: Var,
+    ) -> Self {
+        Plan::MatchLookupA((lookup.0.into(), lookup.1.into()), a.into(), v)
+    }
+
+    /// Returns a plan searching an attribute's full-text index.
+    pub fn search<AX: Into<A>, QX: Into<String>>(e: Var, a: AX, query: QX) -> Self {
+        Plan::Search(e, a.into(), query.into())
+    }
+
+    /// Returns a plan pulling the specified pattern for every entity
+    /// holding a value for `a`.
+    pub fn pull_all<X: Into<A>>(a: X, pattern: PullPattern<A>) -> Self {
+        Plan::Pull(Pull::all(a, pattern))
+    }
+
     /// Returns the variables bound by this plan.
     pub fn variables(&self) -> Vec<Var> {
         match *self {
             Plan::Project(ref projection) => projection.variables.clone(),
             Plan::Aggregate(ref aggregate) => aggregate.variables.clone(),
+            Plan::Downsample(ref downsample) => downsample.variables(),
+            Plan::Sessionize(ref sessionize) => sessionize.variables(),
+            Plan::Transitions(ref transitions) => transitions.variables(),
+            Plan::Debounce(ref debounce) => debounce.plan.variables(),
+            Plan::Delay(ref delay) => delay.plan.variables(),
+            Plan::Sample(ref sample) => sample.plan.variables(),
+            Plan::Coalesce(ref coalesce) => coalesce.variables(),
             Plan::Union(ref union) => union.variables.clone(),
             Plan::Join(ref join) => join.variables.clone(),
             Plan::Hector(ref hector) => hector.variables.clone(),
@@ -217,10 +353,13 @@ impl<A: AsAid> Plan<A> {
             Plan::Negate(ref plan) => plan.variables(),
             Plan::Filter(ref filter) => filter.variables.clone(),
             Plan::Transform(ref transform) => transform.variables.clone(),
+            Plan::Within(ref within) => within.plan.variables(),
             Plan::MatchA(e, _, v) => vec![e, v],
             Plan::MatchEA(_, _, v) => vec![v],
             Plan::MatchAV(e, _, _) => vec![e],
+            Plan::MatchLookupA(_, _, v) => vec![v],
             Plan::NameExpr(ref variables, ref _name) => variables.clone(),
+            Plan::Search(e, _, _) => vec![e],
             Plan::Pull(ref pull) => pull.variables.clone(),
             Plan::PullLevel(ref path) => path.variables.clone(),
             Plan::PullAll(ref path) => path.variables.clone(),
@@ -241,6 +380,13 @@ where
         match *self {
             Plan::Project(ref projection) => projection.dependencies(),
             Plan::Aggregate(ref aggregate) => aggregate.dependencies(),
+            Plan::Downsample(ref downsample) => downsample.dependencies(),
+            Plan::Sessionize(ref sessionize) => sessionize.dependencies(),
+            Plan::Transitions(ref transitions) => transitions.dependencies(),
+            Plan::Debounce(ref debounce) => debounce.dependencies(),
+            Plan::Delay(ref delay) => delay.dependencies(),
+            Plan::Sample(ref sample) => sample.dependencies(),
+            Plan::Coalesce(ref coalesce) => coalesce.dependencies(),
             Plan::Union(ref union) => union.dependencies(),
             Plan::Join(ref join) => join.dependencies(),
             Plan::Hector(ref hector) => hector.dependencies(),
@@ -248,10 +394,15 @@ where
             Plan::Negate(ref plan) => plan.dependencies(),
             Plan::Filter(ref filter) => filter.dependencies(),
             Plan::Transform(ref transform) => transform.dependencies(),
+            Plan::Within(ref within) => within.dependencies(),
             Plan::MatchA(_, ref a, _) => Dependencies::attribute(a.clone()),
             Plan::MatchEA(_, ref a, _) => Dependencies::attribute(a.clone()),
             Plan::MatchAV(_, ref a, _) => Dependencies::attribute(a.clone()),
+            Plan::MatchLookupA((ref lookup_a, _), ref a, _) => {
+                Dependencies::attribute(lookup_a.clone()) + Dependencies::attribute(a.clone())
+            }
             Plan::NameExpr(_, ref name) => Dependencies::name(name.clone()),
+            Plan::Search(_, ref a, _) => Dependencies::attribute(a.clone()),
             Plan::Pull(ref pull) => pull.dependencies(),
             Plan::PullLevel(ref path) => path.dependencies(),
             Plan::PullAll(ref path) => path.dependencies(),
@@ -260,11 +411,18 @@ where
         }
     }
 
-    fn into_bindings(&self) -> Vec<Binding<Self::A>> {
+    fn into_bindings(&self) -> Result<Vec<Binding<Self::A>>, PlanError> {
         // @TODO provide a general fold for plans
         match *self {
             Plan::Project(ref projection) => projection.into_bindings(),
             Plan::Aggregate(ref aggregate) => aggregate.into_bindings(),
+            Plan::Downsample(ref downsample) => downsample.into_bindings(),
+            Plan::Sessionize(ref sessionize) => sessionize.into_bindings(),
+            Plan::Transitions(ref transitions) => transitions.into_bindings(),
+            Plan::Debounce(ref debounce) => debounce.into_bindings(),
+            Plan::Delay(ref delay) => delay.into_bindings(),
+            Plan::Sample(ref sample) => sample.into_bindings(),
+            Plan::Coalesce(ref coalesce) => coalesce.into_bindings(),
             Plan::Union(ref union) => union.into_bindings(),
             Plan::Join(ref join) => join.into_bindings(),
             Plan::Hector(ref hector) => hector.into_bindings(),
@@ -272,22 +430,42 @@ where
             Plan::Negate(ref plan) => plan.into_bindings(),
             Plan::Filter(ref filter) => filter.into_bindings(),
             Plan::Transform(ref transform) => transform.into_bindings(),
-            Plan::MatchA(e, ref a, v) => vec![Binding::attribute(e, a.clone(), v)],
+            Plan::Within(ref within) => within.into_bindings(),
+            Plan::MatchA(e, ref a, v) => Ok(vec![Binding::attribute(e, a.clone(), v)]),
             Plan::MatchEA(match_e, ref a, v) => {
                 let e = gensym();
-                vec![
+                Ok(vec![
                     Binding::attribute(e, a.clone(), v),
                     Binding::constant(e, Value::Eid(match_e)),
-                ]
+                ])
             }
             Plan::MatchAV(e, ref a, ref match_v) => {
                 let v = gensym();
-                vec![
+                Ok(vec![
                     Binding::attribute(e, a.clone(), v),
                     Binding::constant(v, match_v.clone()),
-                ]
+                ])
+            }
+            Plan::MatchLookupA((ref lookup_a, ref lookup_v), ref a, v) => {
+                let e = gensym();
+                let match_v = gensym();
+                Ok(vec![
+                    Binding::attribute(e, lookup_a.clone(), match_v),
+                    Binding::constant(match_v, lookup_v.clone()),
+                    Binding::attribute(e, a.clone(), v),
+                ])
             }
-            Plan::NameExpr(_, ref _name) => unimplemented!(), // @TODO hmm...
+            // @TODO hmm...
+            Plan::NameExpr(_, ref name) => Err(PlanError::Unsupported(format!(
+                "Rule {} can't be referenced via Hector.",
+                name
+            ))),
+            // Hector has no bindings for looking up tokens in a
+            // fulltext index.
+            Plan::Search(_, ref a, _) => Err(PlanError::Unsupported(format!(
+                "Searching {} can't be implemented via Hector.",
+                a
+            ))),
             Plan::Pull(ref pull) => pull.into_bindings(),
             Plan::PullLevel(ref path) => path.into_bindings(),
             Plan::PullAll(ref path) => path.into_bindings(),
@@ -296,30 +474,140 @@ where
         }
     }
 
+    fn alpha_key(&self) -> Option<(Self, Vec<Var>)> {
+        fn rename(order: &mut Vec<Var>, variable: Var) -> Var {
+            match order.iter().position(|x| *x == variable) {
+                Some(index) => index as Var,
+                None => {
+                    order.push(variable);
+                    (order.len() - 1) as Var
+                }
+            }
+        }
+
+        // @TODO rename compound plans as well
+        let mut order = Vec::new();
+        let renamed = match *self {
+            Plan::MatchA(e, ref a, v) => {
+                let e = rename(&mut order, e);
+                Plan::MatchA(e, a.clone(), rename(&mut order, v))
+            }
+            Plan::MatchEA(e, ref a, v) => Plan::MatchEA(e, a.clone(), rename(&mut order, v)),
+            Plan::MatchAV(e, ref a, ref v) => {
+                Plan::MatchAV(rename(&mut order, e), a.clone(), v.clone())
+            }
+            Plan::MatchLookupA(ref lookup, ref a, v) => {
+                Plan::MatchLookupA(lookup.clone(), a.clone(), rename(&mut order, v))
+            }
+            Plan::NameExpr(ref variables, ref name) => {
+                let variables = variables
+                    .iter()
+                    .map(|variable| rename(&mut order, *variable))
+                    .collect();
+
+                Plan::NameExpr(variables, name.clone())
+            }
+            _ => return None,
+        };
+
+        Some((renamed, order))
+    }
+
+    fn is_monotonic<F>(&self, is_monotonic_attribute: &F) -> bool
+    where
+        F: Fn(&A) -> bool,
+    {
+        match *self {
+            Plan::Project(ref projection) => projection.plan.is_monotonic(is_monotonic_attribute),
+            Plan::Delay(ref delay) => delay.plan.is_monotonic(is_monotonic_attribute),
+            Plan::Sample(ref sample) => sample.plan.is_monotonic(is_monotonic_attribute),
+            Plan::Filter(ref filter) => filter.plan.is_monotonic(is_monotonic_attribute),
+            Plan::Transform(ref transform) => transform.plan.is_monotonic(is_monotonic_attribute),
+            Plan::Within(ref within) => within.plan.is_monotonic(is_monotonic_attribute),
+            // Transitions are never retracted.
+            Plan::Transitions(_) => true,
+            Plan::Union(ref union) => union
+                .plans
+                .iter()
+                .all(|plan| plan.is_monotonic(is_monotonic_attribute)),
+            Plan::Join(ref join) => {
+                join.left_plan.is_monotonic(is_monotonic_attribute)
+                    && join.right_plan.is_monotonic(is_monotonic_attribute)
+            }
+            Plan::Hector(ref hector) => hector.bindings.iter().all(|binding| match *binding {
+                Binding::Attribute(ref binding) => {
+                    is_monotonic_attribute(&binding.source_attribute)
+                }
+                Binding::Constant(_) | Binding::BinaryPredicate(_) => true,
+                Binding::Not(_) => false,
+            }),
+            Plan::MatchA(_, ref a, _) | Plan::MatchEA(_, ref a, _) | Plan::MatchAV(_, ref a, _) => {
+                is_monotonic_attribute(a)
+            }
+            Plan::MatchLookupA((ref lookup_a, _), ref a, _) => {
+                is_monotonic_attribute(lookup_a) && is_monotonic_attribute(a)
+            }
+            // Everything else either retracts tuples on its own, as
+            // negations and aggregations do, or depends on data whose
+            // growth isn't known here, such as other rules.
+            _ => false,
+        }
+    }
+
     fn implement<'b, S>(
         &self,
         nested: &mut Iterative<'b, S, u64>,
         domain: &mut Domain<A, S::Timestamp>,
         local_arrangements: &VariableMap<Self::A, Iterative<'b, S, u64>>,
-    ) -> (Implemented<'b, Self::A, S>, ShutdownHandle)
+        arrangements: &mut ArrangementMap<Iterative<'b, S, u64>>,
+    ) -> Result<(Implemented<'b, Self::A, S>, ShutdownHandle), Error>
     where
         S: Scope,
         S::Timestamp: Timestamp + Lattice + Rewind,
     {
         match *self {
             Plan::Project(ref projection) => {
-                projection.implement(nested, domain, local_arrangements)
+                projection.implement(nested, domain, local_arrangements, arrangements)
             }
             Plan::Aggregate(ref aggregate) => {
-                aggregate.implement(nested, domain, local_arrangements)
+                aggregate.implement(nested, domain, local_arrangements, arrangements)
+            }
+            Plan::Downsample(ref downsample) => {
+                downsample.implement(nested, domain, local_arrangements, arrangements)
+            }
+            Plan::Sessionize(ref sessionize) => {
+                sessionize.implement(nested, domain, local_arrangements, arrangements)
+            }
+            Plan::Transitions(ref transitions) => {
+                transitions.implement(nested, domain, local_arrangements, arrangements)
+            }
+            Plan::Debounce(ref debounce) => {
+                debounce.implement(nested, domain, local_arrangements, arrangements)
+            }
+            Plan::Delay(ref delay) => {
+                delay.implement(nested, domain, local_arrangements, arrangements)
+            }
+            Plan::Sample(ref sample) => {
+                sample.implement(nested, domain, local_arrangements, arrangements)
+            }
+            Plan::Coalesce(ref coalesce) => {
+                coalesce.implement(nested, domain, local_arrangements, arrangements)
+            }
+            Plan::Union(ref union) => {
+                union.implement(nested, domain, local_arrangements, arrangements)
+            }
+            Plan::Join(ref join) => {
+                join.implement(nested, domain, local_arrangements, arrangements)
+            }
+            Plan::Hector(ref hector) => {
+                hector.implement(nested, domain, local_arrangements, arrangements)
+            }
+            Plan::Antijoin(ref antijoin) => {
+                antijoin.implement(nested, domain, local_arrangements, arrangements)
             }
-            Plan::Union(ref union) => union.implement(nested, domain, local_arrangements),
-            Plan::Join(ref join) => join.implement(nested, domain, local_arrangements),
-            Plan::Hector(ref hector) => hector.implement(nested, domain, local_arrangements),
-            Plan::Antijoin(ref antijoin) => antijoin.implement(nested, domain, local_arrangements),
             Plan::Negate(ref plan) => {
                 let (relation, mut shutdown_handle) =
-                    plan.implement(nested, domain, local_arrangements);
+                    plan.implement(nested, domain, local_arrangements, arrangements)?;
                 let variables = relation.variables();
 
                 let tuples = {
@@ -329,34 +617,43 @@ where
                     projected.negate()
                 };
 
-                (
+                Ok((
                     Implemented::Collection(CollectionRelation { variables, tuples }),
                     shutdown_handle,
-                )
+                ))
+            }
+            Plan::Filter(ref filter) => {
+                filter.implement(nested, domain, local_arrangements, arrangements)
             }
-            Plan::Filter(ref filter) => filter.implement(nested, domain, local_arrangements),
             Plan::Transform(ref transform) => {
-                transform.implement(nested, domain, local_arrangements)
+                transform.implement(nested, domain, local_arrangements, arrangements)
+            }
+            Plan::Within(ref within) => {
+                within.implement_indexed(nested, domain, local_arrangements, arrangements)
             }
             Plan::MatchA(e, ref a, v) => {
+                if !domain.has_attribute(a) {
+                    return Err(PlanError::UnknownAttribute(a.to_string()).into());
+                }
+
                 let binding = AttributeBinding {
                     variables: (e, v),
                     source_attribute: a.clone(),
                 };
 
-                (Implemented::Attribute(binding), ShutdownHandle::empty())
+                Ok((Implemented::Attribute(binding), ShutdownHandle::empty()))
             }
             Plan::MatchEA(match_e, ref a, sym1) => {
                 let (tuples, shutdown_propose) = match domain.forward_propose(a) {
-                    None => panic!("attribute {:?} does not exist", a),
+                    None => return Err(PlanError::UnknownAttribute(a.to_string()).into()),
                     Some(propose_trace) => {
                         let (propose, shutdown_propose) = propose_trace
                             .import_frontier(&nested.parent, &format!("Propose({:?})", a));
 
+                        let key = seek_key(nested, Value::Eid(match_e));
                         let tuples = propose
                             .enter(nested)
-                            .filter(move |e, _v| *e == Value::Eid(match_e))
-                            .as_collection(|_e, v| vec![v.clone()]);
+                            .join_core(&key, |_e, v, &()| Some(vec![v.clone()]));
 
                         (tuples, shutdown_propose)
                     }
@@ -367,26 +664,41 @@ where
                     tuples,
                 };
 
-                (
+                Ok((
                     Implemented::Collection(relation),
                     ShutdownHandle::from_button(shutdown_propose),
-                )
+                ))
             }
             Plan::MatchAV(sym1, ref a, ref match_v) => {
-                let (tuples, shutdown_propose) = match domain.forward_propose(a) {
-                    None => panic!("attribute {:?} does not exist", a),
+                // Attributes without a reverse index have to be
+                // scanned for the value.
+                let (tuples, shutdown_propose) = match domain.reverse_propose(a) {
                     Some(propose_trace) => {
-                        let match_v = match_v.clone();
                         let (propose, shutdown_propose) = propose_trace
-                            .import_frontier(&nested.parent, &format!("Propose({:?})", a));
+                            .import_frontier(&nested.parent, &format!("_Propose({:?})", a));
 
+                        let key = seek_key(nested, match_v.clone());
                         let tuples = propose
                             .enter(nested)
-                            .filter(move |_e, v| *v == match_v)
-                            .as_collection(|e, _v| vec![e.clone()]);
+                            .join_core(&key, |_v, e, &()| Some(vec![e.clone()]));
 
                         (tuples, shutdown_propose)
                     }
+                    None => match domain.forward_propose(a) {
+                        None => return Err(PlanError::UnknownAttribute(a.to_string()).into()),
+                        Some(propose_trace) => {
+                            let match_v = match_v.clone();
+                            let (propose, shutdown_propose) = propose_trace
+                                .import_frontier(&nested.parent, &format!("Propose({:?})", a));
+
+                            let tuples = propose
+                                .enter(nested)
+                                .filter(move |_e, v| *v == match_v)
+                                .as_collection(|e, _v| vec![e.clone()]);
+
+                            (tuples, shutdown_propose)
+                        }
+                    },
                 };
 
                 let relation = CollectionRelation {
@@ -394,29 +706,117 @@ where
                     tuples,
                 };
 
-                (
+                Ok((
                     Implemented::Collection(relation),
                     ShutdownHandle::from_button(shutdown_propose),
-                )
+                ))
+            }
+            Plan::MatchLookupA((ref lookup_a, ref lookup_v), ref a, v) => {
+                // Resolving the lookup ref is just another join, s.t.
+                // results follow any changes to the referenced value.
+                let e = gensym();
+                let resolved = Plan::Project(Project {
+                    variables: vec![v],
+                    plan: Box::new(Plan::Join(Join {
+                        variables: vec![e],
+                        left_plan: Box::new(Plan::MatchAV(e, lookup_a.clone(), lookup_v.clone())),
+                        right_plan: Box::new(Plan::MatchA(e, a.clone(), v)),
+                    })),
+                });
+
+                resolved.implement(nested, domain, local_arrangements, arrangements)
             }
             Plan::NameExpr(ref syms, ref name) => {
                 match local_arrangements.get(name) {
-                    None => panic!("{:?} not in relation map", name),
+                    None => Err(PlanError::UnknownRelation(name.to_string()).into()),
                     Some(named) => {
                         let relation = CollectionRelation {
                             variables: syms.clone(),
                             tuples: named.deref().clone(), // @TODO re-use variable directly?
                         };
 
-                        (Implemented::Collection(relation), ShutdownHandle::empty())
+                        Ok((Implemented::Collection(relation), ShutdownHandle::empty()))
                     }
                 }
             }
-            Plan::Pull(ref pull) => pull.implement(nested, domain, local_arrangements),
-            Plan::PullLevel(ref path) => path.implement(nested, domain, local_arrangements),
-            Plan::PullAll(ref path) => path.implement(nested, domain, local_arrangements),
+            Plan::Search(e, ref a, ref query) => {
+                let terms = fulltext::terms(query);
+                if terms.is_empty() {
+                    return Err(PlanError::InvalidArgument(format!(
+                        "Search query {:?} holds no terms.",
+                        query
+                    ))
+                    .into());
+                }
+
+                if !domain.has_attribute(a) {
+                    return Err(PlanError::UnknownAttribute(a.to_string()).into());
+                }
+
+                let (index, shutdown_index) = match domain.fulltext(a) {
+                    None => return Err(PlanError::MissingIndex(a.to_string(), "fulltext").into()),
+                    Some(trace) => {
+                        trace.import_frontier(&nested.parent, &format!("Fulltext({:?})", a))
+                    }
+                };
+
+                let index = index.enter(nested);
+
+                // Each term contributes every matching entity once,
+                // s.t. entities matching all of them are those
+                // counted once per term.
+                let mut streams = Vec::with_capacity(terms.len());
+                for term in terms.iter() {
+                    let matches = match term {
+                        Term::Token(token) => {
+                            let key = seek_key(nested, Value::String(token.clone()));
+                            index.join_core(&key, |_token, e, &()| Some(e.clone()))
+                        }
+                        Term::Prefix(prefix) => {
+                            let prefix = prefix.clone();
+                            index
+                                .filter(move |token, _e| match token {
+                                    Value::String(token) => token.starts_with(&prefix),
+                                    _ => false,
+                                })
+                                .as_collection(|_token, e| e.clone())
+                        }
+                    };
+
+                    streams.push(matches.distinct().inner);
+                }
+
+                let arity = terms.len() as isize;
+                let tuples = nested
+                    .concatenate(streams)
+                    .as_collection()
+                    .count()
+                    .filter(move |(_e, count)| *count == arity)
+                    .map(|(e, _count)| vec![e]);
+
+                let relation = CollectionRelation {
+                    variables: vec![e],
+                    tuples,
+                };
+
+                Ok((
+                    Implemented::Collection(relation),
+                    ShutdownHandle::from_button(shutdown_index),
+                ))
+            }
+            Plan::Pull(ref pull) => {
+                pull.implement(nested, domain, local_arrangements, arrangements)
+            }
+            Plan::PullLevel(ref path) => {
+                path.implement(nested, domain, local_arrangements, arrangements)
+            }
+            Plan::PullAll(ref path) => {
+                path.implement(nested, domain, local_arrangements, arrangements)
+            }
             #[cfg(feature = "graphql")]
-            Plan::GraphQl(ref query) => query.implement(nested, domain, local_arrangements),
+            Plan::GraphQl(ref query) => {
+                query.implement(nested, domain, local_arrangements, arrangements)
+            }
         }
     }
 }
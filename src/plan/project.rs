@@ -1,20 +1,24 @@
 //! Projection expression plan.
 
+use std::collections::HashMap;
+
 use timely::dataflow::scopes::child::Iterative;
 use timely::dataflow::Scope;
 use timely::progress::Timestamp;
 
 use differential_dataflow::lattice::Lattice;
 
-use crate::binding::Binding;
+use crate::binding::{AsBinding, Binding};
 use crate::domain::Domain;
-use crate::plan::{Dependencies, Implementable};
+use crate::plan::{ensure_bound, gensym, Dependencies, Implementable};
 use crate::timestamp::Rewind;
-use crate::Var;
-use crate::{CollectionRelation, Implemented, Relation, ShutdownHandle, VariableMap};
+use crate::{
+    ArrangementMap, CollectionRelation, Implemented, Relation, ShutdownHandle, VariableMap,
+};
+use crate::{Error, PlanError, Var};
 
 /// A plan stage projecting its source to only the specified sequence
-/// of variables. Throws on unbound variables. Frontends are responsible
+/// of variables. Fails on unbound variables. Frontends are responsible
 /// for ensuring that the source binds all requested variables.
 #[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
 pub struct Project<P: Implementable> {
@@ -31,8 +35,31 @@ impl<P: Implementable> Implementable for Project<P> {
         self.plan.dependencies()
     }
 
-    fn into_bindings(&self) -> Vec<Binding<Self::A>> {
-        self.plan.into_bindings()
+    fn into_bindings(&self) -> Result<Vec<Binding<Self::A>>, PlanError> {
+        let bindings = self.plan.into_bindings()?;
+
+        // Variables projected away are local to this plan. They are
+        // renamed to fresh ones, s.t. they can't be confused with
+        // variables of the same name elsewhere, once all bindings of
+        // a query are unified (e.g. in self-joins).
+        let mut hidden = HashMap::new();
+        for binding in bindings.iter() {
+            for variable in binding.variables() {
+                if !self.variables.contains(&variable) {
+                    hidden.entry(variable).or_insert_with(gensym);
+                }
+            }
+        }
+
+        if hidden.is_empty() {
+            Ok(bindings)
+        } else {
+            let rename = |variable: Var| *hidden.get(&variable).unwrap_or(&variable);
+            Ok(bindings
+                .iter()
+                .map(|binding| binding.renamed(&rename))
+                .collect())
+        }
     }
 
     fn implement<'b, S>(
@@ -40,13 +67,18 @@ impl<P: Implementable> Implementable for Project<P> {
         nested: &mut Iterative<'b, S, u64>,
         domain: &mut Domain<Self::A, S::Timestamp>,
         local_arrangements: &VariableMap<Self::A, Iterative<'b, S, u64>>,
-    ) -> (Implemented<'b, Self::A, S>, ShutdownHandle)
+        arrangements: &mut ArrangementMap<Iterative<'b, S, u64>>,
+    ) -> Result<(Implemented<'b, Self::A, S>, ShutdownHandle), Error>
     where
         S: Scope,
         S::Timestamp: Timestamp + Lattice + Rewind,
     {
         let (relation, mut shutdown_handle) =
-            self.plan.implement(nested, domain, local_arrangements);
+            self.plan
+                .implement(nested, domain, local_arrangements, arrangements)?;
+
+        ensure_bound(&relation, &self.variables)?;
+
         let tuples = {
             let (projected, shutdown) = relation.projected(nested, domain, &self.variables);
             shutdown_handle.merge_with(shutdown);
@@ -59,6 +91,6 @@ impl<P: Implementable> Implementable for Project<P> {
             tuples,
         };
 
-        (Implemented::Collection(projected), shutdown_handle)
+        Ok((Implemented::Collection(projected), shutdown_handle))
     }
 }
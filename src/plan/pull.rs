@@ -11,10 +11,12 @@ use differential_dataflow::AsCollection;
 
 use crate::binding::AsBinding;
 use crate::domain::Domain;
-use crate::plan::{Dependencies, Implementable};
+use crate::plan::{gensym, Dependencies, Implementable, Join, Plan, Project};
 use crate::timestamp::Rewind;
-use crate::{AsAid, Value, Var};
-use crate::{CollectionRelation, Implemented, Relation, ShutdownHandle, VariableMap};
+use crate::{
+    ArrangementMap, CollectionRelation, Implemented, Relation, ShutdownHandle, VariableMap,
+};
+use crate::{AsAid, Error, PlanError, Value, Var};
 
 /// A plan stage for extracting all matching [e a v] tuples for a
 /// given set of attributes and an input relation specifying entities.
@@ -28,6 +30,10 @@ pub struct PullLevel<A: AsAid, P: Implementable<A = A>> {
     pub pull_variable: Var,
     /// Attributes to pull for the input entities.
     pub pull_attributes: Vec<A>,
+    /// Registered rules to pull for the input entities, each read
+    /// as a relation of [e v] pairs.
+    #[serde(default)]
+    pub pull_rules: Vec<A>,
     /// Attribute names to distinguish plans of the same
     /// length. Useful to feed into a nested hash-map directly.
     pub path_attributes: Vec<A>,
@@ -49,6 +55,125 @@ pub struct Pull<P: Implementable> {
     pub paths: Vec<P>,
 }
 
+/// A pull pattern, such as `[:name {:friend [:name]}]`, selecting
+/// the attributes to pull for an entity, as well as the reference
+/// attributes along which to pull nested entities.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct PullPattern<A: AsAid> {
+    /// Attributes to pull.
+    pub attributes: Vec<A>,
+    /// Reference attributes, each with the pattern to pull for the
+    /// entities it refers to.
+    pub nested: Vec<(A, PullPattern<A>)>,
+}
+
+impl<A: AsAid> PullPattern<A> {
+    /// Returns a pattern pulling the specified attributes.
+    pub fn new<X: Into<A>>(attributes: Vec<X>) -> Self {
+        PullPattern {
+            attributes: attributes.into_iter().map(Into::into).collect(),
+            nested: vec![],
+        }
+    }
+
+    /// Additionally pulls the specified pattern for the entities
+    /// referred to via `a`.
+    pub fn nest<X: Into<A>>(mut self, a: X, pattern: PullPattern<A>) -> Self {
+        self.nested.push((a.into(), pattern));
+        self
+    }
+}
+
+impl<A: AsAid> Pull<Plan<A>> {
+    /// Returns a plan pulling the specified pattern for every entity
+    /// holding a value for `a`, e.g. "pull [:name :age] for every
+    /// :person/id". Nested entities are keyed by their eid, s.t. the
+    /// values of several ones never get mixed up.
+    pub fn all<X: Into<A>>(a: X, pattern: PullPattern<A>) -> Self {
+        let e = 0;
+        let entities = Plan::Project(Project {
+            variables: vec![e],
+            plan: Box::new(Plan::match_a(e, a, gensym())),
+        });
+
+        let mut pull = Pull {
+            variables: vec![],
+            paths: vec![],
+        };
+
+        pull.push_paths(entities, &[e], &[], pattern);
+        pull
+    }
+
+    fn push_paths(
+        &mut self,
+        plan: Plan<A>,
+        path: &[Var],
+        path_attributes: &[A],
+        pattern: PullPattern<A>,
+    ) {
+        let PullPattern { attributes, nested } = pattern;
+        let this = *path.last().expect("empty path");
+
+        // Levels without attributes of their own only serve to bind
+        // the entities nested below them.
+        if !attributes.is_empty() {
+            self.paths.push(Plan::PullLevel(PullLevel {
+                variables: vec![],
+                plan: Box::new(plan.clone()),
+                pull_variable: this,
+                pull_attributes: attributes,
+                pull_rules: vec![],
+                path_attributes: path_attributes.to_vec(),
+                cardinality_many: true,
+            }));
+        }
+
+        for (a, pattern) in nested.into_iter() {
+            let child = path.len() as Var;
+
+            let mut child_path = path.to_vec();
+            child_path.push(child);
+
+            let mut child_attributes = path_attributes.to_vec();
+            child_attributes.push(a.clone());
+
+            let child_plan = Plan::Project(Project {
+                variables: child_path.clone(),
+                plan: Box::new(Plan::Join(Join {
+                    variables: vec![this],
+                    left_plan: Box::new(plan.clone()),
+                    right_plan: Box::new(Plan::MatchA(this, a, child)),
+                })),
+            });
+
+            self.push_paths(child_plan, &child_path, &child_attributes, pattern);
+        }
+    }
+}
+
+/// Returns those of the specified attributes that are declared to be
+/// of cardinality one via their input semantics. When assembling pull
+/// results, these resolve to their latest value.
+pub fn cardinality_one<A, T, I>(domain: &Domain<A, T>, attributes: I) -> Vec<A>
+where
+    A: AsAid,
+    T: Timestamp + Lattice,
+    I: IntoIterator<Item = A>,
+{
+    let mut one: Vec<A> = attributes
+        .into_iter()
+        .filter(|aid| match domain.attributes.get(aid) {
+            None => false,
+            Some(config) => config.is_cardinality_one(),
+        })
+        .collect();
+
+    one.sort();
+    one.dedup();
+    one
+}
+
 fn interleave<A: AsAid>(values: &[Value], constants: &[A]) -> Vec<Value> {
     if values.is_empty() || constants.is_empty() {
         values.to_owned()
@@ -77,6 +202,31 @@ fn interleave<A: AsAid>(values: &[Value], constants: &[A]) -> Vec<Value> {
     }
 }
 
+/// Returns the join logic for pulling the values of a single
+/// attribute (or rule) along the specified paths.
+fn pull_values<A: AsAid>(
+    attribute: Value,
+    path_attributes: Vec<A>,
+    cardinality_many: bool,
+) -> impl Fn(&Value, &Vec<Value>, &Value) -> Option<Vec<Value>> + 'static {
+    move |_e, path, v| {
+        // Each result tuple must hold the interleaved path, the
+        // attribute, and the value, i.e. [?p "parent/child" ?c ?a ?v]
+        let mut result = interleave(path, &path_attributes);
+
+        if !path_attributes.is_empty() && !cardinality_many {
+            // Cardinality single means we don't need to distinguish
+            // child ids (there can only be one).
+            result.pop().expect("malformed path");
+        }
+
+        result.push(attribute.clone());
+        result.push(v.clone());
+
+        Some(result)
+    }
+}
+
 impl<A: AsAid + 'static, P: Implementable<A = A>> Implementable for PullLevel<A, P> {
     type A = A;
 
@@ -88,7 +238,14 @@ impl<A: AsAid + 'static, P: Implementable<A = A>> Implementable for PullLevel<A,
             .map(Dependencies::attribute)
             .sum();
 
-        self.plan.dependencies() + attribute_dependencies
+        let rule_dependencies = self
+            .pull_rules
+            .iter()
+            .cloned()
+            .map(Dependencies::name)
+            .sum();
+
+        self.plan.dependencies() + attribute_dependencies + rule_dependencies
     }
 
     fn implement<'b, S>(
@@ -96,7 +253,8 @@ impl<A: AsAid + 'static, P: Implementable<A = A>> Implementable for PullLevel<A,
         nested: &mut Iterative<'b, S, u64>,
         domain: &mut Domain<Self::A, S::Timestamp>,
         local_arrangements: &VariableMap<Self::A, Iterative<'b, S, u64>>,
-    ) -> (Implemented<'b, Self::A, S>, ShutdownHandle)
+        arrangements: &mut ArrangementMap<Iterative<'b, S, u64>>,
+    ) -> Result<(Implemented<'b, Self::A, S>, ShutdownHandle), Error>
     where
         S: Scope,
         S::Timestamp: Timestamp + Lattice + Rewind,
@@ -106,12 +264,14 @@ impl<A: AsAid + 'static, P: Implementable<A = A>> Implementable for PullLevel<A,
         use differential_dataflow::trace::implementations::ord::OrdValSpine;
         use differential_dataflow::trace::TraceReader;
 
-        let (input, mut shutdown_handle) = self.plan.implement(nested, domain, local_arrangements);
+        let (input, mut shutdown_handle) =
+            self.plan
+                .implement(nested, domain, local_arrangements, arrangements)?;
 
-        if self.pull_attributes.is_empty() {
+        if self.pull_attributes.is_empty() && self.pull_rules.is_empty() {
             if self.path_attributes.is_empty() {
                 // nothing to pull
-                (input, shutdown_handle)
+                Ok((input, shutdown_handle))
             } else {
                 let path_attributes = self.path_attributes.clone();
                 let tuples = {
@@ -121,19 +281,19 @@ impl<A: AsAid + 'static, P: Implementable<A = A>> Implementable for PullLevel<A,
                     tuples.map(move |tuple| interleave(&tuple, &path_attributes))
                 };
 
-                (
+                Ok((
                     Implemented::Collection(CollectionRelation {
                         variables: self.variables.to_vec(),
                         tuples,
                     }),
                     shutdown_handle,
-                )
+                ))
             }
         } else {
             // Arrange input entities by eid.
             let e_offset = input
                 .binds(self.pull_variable)
-                .expect("input relation doesn't bind pull_variable");
+                .ok_or_else(|| PlanError::UnboundVariable(self.pull_variable))?;
 
             let paths = {
                 let (tuples, shutdown) = input.tuples(nested, domain);
@@ -147,9 +307,11 @@ impl<A: AsAid + 'static, P: Implementable<A = A>> Implementable for PullLevel<A,
             > = paths.map(move |t| (t[e_offset].clone(), t)).arrange();
 
             let mut shutdown_handle = shutdown_handle;
-            let streams = self.pull_attributes.iter().map(|a| {
+            let mut streams =
+                Vec::with_capacity(self.pull_attributes.len() + self.pull_rules.len());
+            for a in self.pull_attributes.iter() {
                 let e_v = match domain.forward_propose(a) {
-                    None => panic!("attribute {:?} does not exist", a),
+                    None => return Err(PlanError::UnknownAttribute(a.to_string()).into()),
                     Some(propose_trace) => {
                         let frontier: Vec<S::Timestamp> = propose_trace.advance_frontier().to_vec();
                         let (arranged, shutdown_propose) = propose_trace
@@ -167,43 +329,46 @@ impl<A: AsAid + 'static, P: Implementable<A = A>> Implementable for PullLevel<A,
                     }
                 };
 
-                let attribute = a.clone().into_value();
-                let path_attributes: Vec<Self::A> = self.path_attributes.clone();
+                let pull = pull_values(
+                    a.clone().into_value(),
+                    self.path_attributes.clone(),
+                    self.cardinality_many,
+                );
 
-                if path_attributes.is_empty() || self.cardinality_many {
-                    e_path
-                        .join_core(&e_v, move |_e, path: &Vec<Value>, v: &Value| {
-                            // Each result tuple must hold the interleaved
-                            // path, the attribute, and the value,
-                            // i.e. [?p "parent/child" ?c ?a ?v]
-                            let mut result = interleave(path, &path_attributes);
-                            result.push(attribute.clone());
-                            result.push(v.clone());
+                streams.push(e_path.join_core(&e_v, pull).inner);
+            }
 
-                            Some(result)
-                        })
-                        .inner
-                } else {
-                    e_path
-                        .join_core(&e_v, move |_e, path: &Vec<Value>, v: &Value| {
-                            // Each result tuple must hold the interleaved
-                            // path, the attribute, and the value,
-                            // i.e. [?p "parent/child" ?c ?a ?v]
-                            let mut result = interleave(path, &path_attributes);
-
-                            // Cardinality single means we don't need
-                            // to distinguish child ids (there can
-                            // only be one).
-                            result.pop().expect("malformed path");
-
-                            result.push(attribute.clone());
-                            result.push(v.clone());
-
-                            Some(result)
-                        })
-                        .inner
-                }
-            });
+            for name in self.pull_rules.iter() {
+                let arity = match domain.rule(name) {
+                    None => None,
+                    Some(rule) => Some(rule.plan.variables().len()),
+                };
+
+                let e_v: Arranged<
+                    Iterative<S, u64>,
+                    TraceAgent<OrdValSpine<Value, Value, Product<S::Timestamp, u64>, isize>>,
+                > = match (local_arrangements.get(name), arity) {
+                    (Some(named), Some(2)) => named
+                        .map(|tuple| (tuple[0].clone(), tuple[1].clone()))
+                        .arrange(),
+                    (Some(_), Some(_)) => {
+                        return Err(PlanError::InvalidArgument(format!(
+                            "Rule {} must bind exactly two variables to be pulled.",
+                            name
+                        ))
+                        .into());
+                    }
+                    _ => return Err(PlanError::UnknownRelation(name.to_string()).into()),
+                };
+
+                let pull = pull_values(
+                    name.clone().into_value(),
+                    self.path_attributes.clone(),
+                    self.cardinality_many,
+                );
+
+                streams.push(e_path.join_core(&e_v, pull).inner);
+            }
 
             let tuples = if self.path_attributes.is_empty() || self.cardinality_many {
                 nested.concatenate(streams)
@@ -231,7 +396,7 @@ impl<A: AsAid + 'static, P: Implementable<A = A>> Implementable for PullLevel<A,
                 tuples: tuples.as_collection(),
             };
 
-            (Implemented::Collection(relation), shutdown_handle)
+            Ok((Implemented::Collection(relation), shutdown_handle))
         }
     }
 }
@@ -248,7 +413,8 @@ impl<P: Implementable> Implementable for Pull<P> {
         nested: &mut Iterative<'b, S, u64>,
         domain: &mut Domain<Self::A, S::Timestamp>,
         local_arrangements: &VariableMap<Self::A, Iterative<'b, S, u64>>,
-    ) -> (Implemented<'b, Self::A, S>, ShutdownHandle)
+        arrangements: &mut ArrangementMap<Iterative<'b, S, u64>>,
+    ) -> Result<(Implemented<'b, Self::A, S>, ShutdownHandle), Error>
     where
         S: Scope,
         S::Timestamp: Timestamp + Lattice + Rewind,
@@ -256,9 +422,11 @@ impl<P: Implementable> Implementable for Pull<P> {
         let mut scope = nested.clone();
         let mut shutdown_handle = ShutdownHandle::empty();
 
-        let streams = self.paths.iter().map(|path| {
+        let mut streams = Vec::with_capacity(self.paths.len());
+        for path in self.paths.iter() {
             let relation = {
-                let (relation, shutdown) = path.implement(&mut scope, domain, local_arrangements);
+                let (relation, shutdown) =
+                    path.implement(&mut scope, domain, local_arrangements, arrangements)?;
                 shutdown_handle.merge_with(shutdown);
                 relation
             };
@@ -269,8 +437,8 @@ impl<P: Implementable> Implementable for Pull<P> {
                 tuples
             };
 
-            tuples.inner
-        });
+            streams.push(tuples.inner);
+        }
 
         let tuples = nested.concatenate(streams).as_collection();
 
@@ -279,7 +447,7 @@ impl<P: Implementable> Implementable for Pull<P> {
             tuples,
         };
 
-        (Implemented::Collection(relation), shutdown_handle)
+        Ok((Implemented::Collection(relation), shutdown_handle))
     }
 }
 
@@ -309,20 +477,24 @@ impl<A: AsAid> Implementable for PullAll<A> {
         nested: &mut Iterative<'b, S, u64>,
         domain: &mut Domain<A, S::Timestamp>,
         _local_arrangements: &VariableMap<Self::A, Iterative<'b, S, u64>>,
-    ) -> (Implemented<'b, Self::A, S>, ShutdownHandle)
+        _arrangements: &mut ArrangementMap<Iterative<'b, S, u64>>,
+    ) -> Result<(Implemented<'b, Self::A, S>, ShutdownHandle), Error>
     where
         S: Scope,
         S::Timestamp: Timestamp + Lattice + Rewind,
     {
         use differential_dataflow::trace::TraceReader;
 
-        assert!(!self.pull_attributes.is_empty());
+        if self.pull_attributes.is_empty() {
+            return Err(PlanError::Unsupported("Nothing to pull.".to_string()).into());
+        }
 
         let mut shutdown_handle = ShutdownHandle::empty();
 
-        let streams = self.pull_attributes.iter().map(|a| {
+        let mut streams = Vec::with_capacity(self.pull_attributes.len());
+        for a in self.pull_attributes.iter() {
             let e_v = match domain.forward_propose(a) {
-                None => panic!("attribute {:?} does not exist", a),
+                None => return Err(PlanError::UnknownAttribute(a.to_string()).into()),
                 Some(propose_trace) => {
                     let frontier: Vec<S::Timestamp> = propose_trace.advance_frontier().to_vec();
                     let (arranged, shutdown_propose) =
@@ -342,9 +514,11 @@ impl<A: AsAid> Implementable for PullAll<A> {
 
             let attribute = a.clone().into_value();
 
-            e_v.as_collection(move |e, v| vec![e.clone(), attribute.clone(), v.clone()])
-                .inner
-        });
+            streams.push(
+                e_v.as_collection(move |e, v| vec![e.clone(), attribute.clone(), v.clone()])
+                    .inner,
+            );
+        }
 
         let tuples = nested.concatenate(streams).as_collection();
 
@@ -353,6 +527,6 @@ impl<A: AsAid> Implementable for PullAll<A> {
             tuples,
         };
 
-        (Implemented::Collection(relation), shutdown_handle)
+        Ok((Implemented::Collection(relation), shutdown_handle))
     }
 }
@@ -13,8 +13,8 @@ use crate::binding::AsBinding;
 use crate::domain::Domain;
 use crate::plan::{Dependencies, Implementable, Plan};
 use crate::timestamp::Rewind;
-use crate::{AsAid, Value, Var};
-use crate::{Relation, ShutdownHandle, VariableMap};
+use crate::{ArrangementMap, Relation, ShutdownHandle, VariableMap};
+use crate::{AsAid, Error, PlanError, Value, Var};
 
 /// A sequence of attributes that uniquely identify a nesting level in
 /// a Pull query.
@@ -58,10 +58,14 @@ impl<P: Implementable> PullLevel<P> {
         nested: &mut Iterative<'b, S, u64>,
         domain: &mut Domain<P::A, S::Timestamp>,
         local_arrangements: &VariableMap<P::A, Iterative<'b, S, u64>>,
-    ) -> (
-        HashMap<PathId<P::A>, Stream<S, (Vec<Value>, S::Timestamp, isize)>>,
-        ShutdownHandle,
-    )
+        arrangements: &mut ArrangementMap<Iterative<'b, S, u64>>,
+    ) -> Result<
+        (
+            HashMap<PathId<P::A>, Stream<S, (Vec<Value>, S::Timestamp, isize)>>,
+            ShutdownHandle,
+        ),
+        Error,
+    >
     where
         S: Scope,
         S::Timestamp: Timestamp + Lattice + Rewind,
@@ -71,14 +75,25 @@ impl<P: Implementable> PullLevel<P> {
         use differential_dataflow::trace::implementations::ord::OrdValSpine;
         use differential_dataflow::trace::TraceReader;
 
-        assert_eq!(self.pull_attributes.is_empty(), false);
+        if self.pull_attributes.is_empty() {
+            return Err(PlanError::Unsupported("Nothing to pull.".to_string()).into());
+        }
+
+        if self.path_attributes.is_empty() {
+            return Err(PlanError::InvalidArgument(
+                "Nested pull levels require a path.".to_string(),
+            )
+            .into());
+        }
 
-        let (input, mut shutdown_handle) = self.plan.implement(nested, domain, local_arrangements);
+        let (input, mut shutdown_handle) =
+            self.plan
+                .implement(nested, domain, local_arrangements, arrangements)?;
 
         // Arrange input entities by eid.
         let e_offset = input
             .binds(self.pull_variable)
-            .expect("input relation doesn't bind pull_variable");
+            .ok_or_else(|| PlanError::UnboundVariable(self.pull_variable))?;
 
         let paths = {
             let (tuples, shutdown) = input.tuples(nested, domain);
@@ -97,7 +112,7 @@ impl<P: Implementable> PullLevel<P> {
             .iter()
             .map(|a| {
                 let e_v = match domain.forward_propose(a) {
-                    None => panic!("attribute {:?} does not exist", a),
+                    None => return Err(PlanError::UnknownAttribute(a.to_string()).into()),
                     Some(propose_trace) => {
                         let frontier: Vec<S::Timestamp> = propose_trace.advance_frontier().to_vec();
                         let (arranged, shutdown_propose) = propose_trace
@@ -116,8 +131,6 @@ impl<P: Implementable> PullLevel<P> {
                 };
 
                 let path_id: Vec<P::A> = {
-                    assert_eq!(self.path_attributes.is_empty(), false);
-
                     let mut path_attributes = self.path_attributes.clone();
                     path_attributes.push(a.clone());
                     path_attributes
@@ -133,11 +146,11 @@ impl<P: Implementable> PullLevel<P> {
                     .leave()
                     .inner;
 
-                (path_id, path_stream)
+                Ok((path_id, path_stream))
             })
-            .collect::<HashMap<_, _>>();
+            .collect::<Result<HashMap<_, _>, Error>>()?;
 
-        (path_streams, shutdown_handle)
+        Ok((path_streams, shutdown_handle))
     }
 }
 
@@ -167,17 +180,23 @@ impl<A: AsAid> PullAll<A> {
         nested: &mut Iterative<'b, S, u64>,
         domain: &mut Domain<A, S::Timestamp>,
         _local_arrangements: &VariableMap<A, Iterative<'b, S, u64>>,
-    ) -> (
-        HashMap<PathId<A>, Stream<S, (Vec<Value>, S::Timestamp, isize)>>,
-        ShutdownHandle,
-    )
+        _arrangements: &mut ArrangementMap<Iterative<'b, S, u64>>,
+    ) -> Result<
+        (
+            HashMap<PathId<A>, Stream<S, (Vec<Value>, S::Timestamp, isize)>>,
+            ShutdownHandle,
+        ),
+        Error,
+    >
     where
         S: Scope,
         S::Timestamp: Timestamp + Lattice + Rewind,
     {
         use differential_dataflow::trace::TraceReader;
 
-        assert!(!self.pull_attributes.is_empty());
+        if self.pull_attributes.is_empty() {
+            return Err(PlanError::Unsupported("Nothing to pull.".to_string()).into());
+        }
 
         let mut shutdown_handle = ShutdownHandle::empty();
 
@@ -186,7 +205,7 @@ impl<A: AsAid> PullAll<A> {
             .iter()
             .map(|a| {
                 let e_v = match domain.forward_propose(a) {
-                    None => panic!("attribute {:?} does not exist", a),
+                    None => return Err(PlanError::UnknownAttribute(a.to_string()).into()),
                     Some(propose_trace) => {
                         let frontier: Vec<S::Timestamp> = propose_trace.advance_frontier().to_vec();
                         let (arranged, shutdown_propose) = propose_trace
@@ -209,11 +228,11 @@ impl<A: AsAid> PullAll<A> {
                     .leave()
                     .inner;
 
-                (vec![a.clone()], path_stream)
+                Ok((vec![a.clone()], path_stream))
             })
-            .collect::<HashMap<_, _>>();
+            .collect::<Result<HashMap<_, _>, Error>>()?;
 
-        (path_streams, shutdown_handle)
+        Ok((path_streams, shutdown_handle))
     }
 }
 
@@ -246,17 +265,23 @@ where
         nested: &mut Iterative<'b, S, u64>,
         domain: &mut Domain<A, S::Timestamp>,
         local_arrangements: &VariableMap<A, Iterative<'b, S, u64>>,
-    ) -> (
-        HashMap<PathId<A>, Stream<S, (Vec<Value>, S::Timestamp, isize)>>,
-        ShutdownHandle,
-    )
+        arrangements: &mut ArrangementMap<Iterative<'b, S, u64>>,
+    ) -> Result<
+        (
+            HashMap<PathId<A>, Stream<S, (Vec<Value>, S::Timestamp, isize)>>,
+            ShutdownHandle,
+        ),
+        Error,
+    >
     where
         S: Scope,
         S::Timestamp: Timestamp + Lattice + Rewind,
     {
         match self {
-            Pull::All(ref pull) => pull.implement(nested, domain, local_arrangements),
-            Pull::Level(ref pull) => pull.implement(nested, domain, local_arrangements),
+            Pull::All(ref pull) => pull.implement(nested, domain, local_arrangements, arrangements),
+            Pull::Level(ref pull) => {
+                pull.implement(nested, domain, local_arrangements, arrangements)
+            }
         }
     }
 }
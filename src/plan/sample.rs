@@ -0,0 +1,109 @@
+//! Sampling plan.
+
+use timely::dataflow::scopes::child::Iterative;
+use timely::dataflow::Scope;
+use timely::progress::Timestamp;
+
+use timely_sort::Unsigned;
+
+use differential_dataflow::hashable::Hashable;
+use differential_dataflow::lattice::Lattice;
+
+use crate::binding::Binding;
+use crate::domain::Domain;
+use crate::plan::{ensure_bound, Dependencies, Implementable};
+use crate::timestamp::Rewind;
+use crate::{
+    ArrangementMap, CollectionRelation, Implemented, Relation, ShutdownHandle, Var, VariableMap,
+};
+use crate::{Error, PlanError};
+
+/// A plan stage keeping roughly `numerator / denominator` of the
+/// source tuples. Whether a tuple is kept depends only on a hash of
+/// the values bound to the sample variables, such that all workers
+/// and all queries agree on the sample and retractions always match
+/// the tuples they retract. Without sample variables, entire tuples
+/// are hashed.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct Sample<P: Implementable> {
+    /// Plan for the data source.
+    pub plan: Box<P>,
+    /// Variables whose values decide whether a tuple is kept.
+    pub variables: Vec<Var>,
+    /// Number of buckets kept out of every `denominator`.
+    pub numerator: u64,
+    /// Number of buckets tuples are hashed into.
+    pub denominator: u64,
+}
+
+impl<P: Implementable> Implementable for Sample<P> {
+    type A = P::A;
+
+    fn dependencies(&self) -> Dependencies<Self::A> {
+        self.plan.dependencies()
+    }
+
+    fn into_bindings(&self) -> Result<Vec<Binding<Self::A>>, PlanError> {
+        self.plan.into_bindings()
+    }
+
+    fn implement<'b, S>(
+        &self,
+        nested: &mut Iterative<'b, S, u64>,
+        domain: &mut Domain<Self::A, S::Timestamp>,
+        local_arrangements: &VariableMap<Self::A, Iterative<'b, S, u64>>,
+        arrangements: &mut ArrangementMap<Iterative<'b, S, u64>>,
+    ) -> Result<(Implemented<'b, Self::A, S>, ShutdownHandle), Error>
+    where
+        S: Scope,
+        S::Timestamp: Timestamp + Lattice + Rewind,
+    {
+        if self.denominator == 0 || self.numerator > self.denominator {
+            return Err(PlanError::InvalidArgument(format!(
+                "Can't sample {} out of {} tuples.",
+                self.numerator, self.denominator
+            ))
+            .into());
+        }
+
+        let (relation, mut shutdown_handle) =
+            self.plan
+                .implement(nested, domain, local_arrangements, arrangements)?;
+
+        ensure_bound(&relation, &self.variables)?;
+
+        let variables = relation.variables();
+        let tuples = {
+            let (projected, shutdown) = relation.projected(nested, domain, &variables);
+            shutdown_handle.merge_with(shutdown);
+            projected
+        };
+
+        let offsets: Vec<usize> = self
+            .variables
+            .iter()
+            .map(|x| variables.iter().position(|y| y == x).unwrap())
+            .collect();
+
+        let numerator = self.numerator;
+        let denominator = self.denominator;
+
+        let tuples = tuples.filter(move |tuple| {
+            let hash = if offsets.is_empty() {
+                tuple.hashed()
+            } else {
+                offsets
+                    .iter()
+                    .map(|offset| tuple[*offset].clone())
+                    .collect::<Vec<_>>()
+                    .hashed()
+            };
+
+            hash.as_u64() % denominator < numerator
+        });
+
+        let relation = CollectionRelation { variables, tuples };
+
+        Ok((Implemented::Collection(relation), shutdown_handle))
+    }
+}
@@ -0,0 +1,146 @@
+//! Session windowing plan.
+
+use timely::dataflow::scopes::child::Iterative;
+use timely::dataflow::Scope;
+use timely::progress::Timestamp;
+
+use differential_dataflow::lattice::Lattice;
+use differential_dataflow::operators::Reduce;
+
+use crate::binding::Binding;
+use crate::domain::Domain;
+use crate::plan::{ensure_bound, Dependencies, Implementable};
+use crate::timestamp::Rewind;
+use crate::{
+    ArrangementMap, CollectionRelation, Implemented, Relation, ShutdownHandle, Value, Var,
+    VariableMap,
+};
+use crate::{Error, PlanError};
+
+/// A plan stage grouping the events of each key into sessions, such
+/// that consecutive events within a session are at most `gap` apart.
+///
+/// The stage binds the key variables, followed by the instants of
+/// the first and the last event of each session. The first instant
+/// doubles as the session's identifier. Whenever events of a key
+/// change, its sessions are re-computed, splitting or merging them as
+/// needed.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct Sessionize<P: Implementable> {
+    /// Plan for the data source.
+    pub plan: Box<P>,
+    /// Variables identifying whose events belong together.
+    pub key_variables: Vec<Var>,
+    /// Variable bound to the instant of each event.
+    pub time_variable: Var,
+    /// Longest period of inactivity within a session, in the units of
+    /// the time variable, i.e. milliseconds for instants.
+    pub gap: u64,
+    /// Variable to bind the start of each session to.
+    pub start_variable: Var,
+    /// Variable to bind the end of each session to.
+    pub end_variable: Var,
+}
+
+impl<P: Implementable> Sessionize<P> {
+    /// Returns the variables bound by this stage.
+    pub fn variables(&self) -> Vec<Var> {
+        let mut variables = self.key_variables.clone();
+        variables.push(self.start_variable);
+        variables.push(self.end_variable);
+        variables
+    }
+}
+
+/// Returns the position of a time value on a common scale, if it is
+/// one.
+fn position(time: &Value) -> Option<i128> {
+    match *time {
+        Value::Instant(t) => Some(i128::from(t)),
+        Value::Number(t) => Some(i128::from(t)),
+        _ => None,
+    }
+}
+
+impl<P: Implementable> Implementable for Sessionize<P> {
+    type A = P::A;
+
+    fn dependencies(&self) -> Dependencies<Self::A> {
+        self.plan.dependencies()
+    }
+
+    fn into_bindings(&self) -> Result<Vec<Binding<Self::A>>, PlanError> {
+        self.plan.into_bindings()
+    }
+
+    fn implement<'b, S>(
+        &self,
+        nested: &mut Iterative<'b, S, u64>,
+        domain: &mut Domain<Self::A, S::Timestamp>,
+        local_arrangements: &VariableMap<Self::A, Iterative<'b, S, u64>>,
+        arrangements: &mut ArrangementMap<Iterative<'b, S, u64>>,
+    ) -> Result<(Implemented<'b, Self::A, S>, ShutdownHandle), Error>
+    where
+        S: Scope,
+        S::Timestamp: Timestamp + Lattice + Rewind,
+    {
+        if self.start_variable == self.end_variable {
+            return Err(PlanError::InvalidArgument(
+                "Session starts and ends must be bound to distinct variables.".into(),
+            )
+            .into());
+        }
+
+        let (relation, mut shutdown_handle) =
+            self.plan
+                .implement(nested, domain, local_arrangements, arrangements)?;
+
+        ensure_bound(&relation, &self.key_variables)?;
+        ensure_bound(&relation, &[self.time_variable])?;
+
+        let mut variables = self.key_variables.clone();
+        variables.push(self.time_variable);
+
+        let tuples = {
+            let (projected, shutdown) = relation.projected(nested, domain, &variables);
+            shutdown_handle.merge_with(shutdown);
+            projected
+        };
+
+        let gap = i128::from(self.gap);
+
+        let tuples = tuples
+            .flat_map(|mut tuple| {
+                let time = tuple.pop().unwrap();
+                position(&time).map(|_| (tuple, time))
+            })
+            .reduce(move |_key, events, output| {
+                // Events arrive ordered by time, s.t. sessions are
+                // delimited by consecutive events too far apart.
+                let mut start = events[0].0;
+                let mut end = events[0].0;
+
+                for &(time, _count) in events[1..].iter() {
+                    if position(time).unwrap() - position(end).unwrap() > gap {
+                        output.push((vec![start.clone(), end.clone()], 1));
+                        start = time;
+                    }
+
+                    end = time;
+                }
+
+                output.push((vec![start.clone(), end.clone()], 1));
+            })
+            .map(|(mut key, mut session): (Vec<Value>, Vec<Value>)| {
+                key.append(&mut session);
+                key
+            });
+
+        let relation = CollectionRelation {
+            variables: self.variables(),
+            tuples,
+        };
+
+        Ok((Implemented::Collection(relation), shutdown_handle))
+    }
+}
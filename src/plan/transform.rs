@@ -8,9 +8,13 @@ use differential_dataflow::lattice::Lattice;
 
 use crate::binding::{AsBinding, Binding};
 use crate::domain::Domain;
-use crate::plan::{Dependencies, Implementable};
+use crate::plan::{ensure_bound, Dependencies, Implementable};
 use crate::timestamp::Rewind;
-use crate::{CollectionRelation, Implemented, Relation, ShutdownHandle, Value, Var, VariableMap};
+use crate::{
+    ArrangementMap, CollectionRelation, Implemented, Relation, ShutdownHandle, Value, Var,
+    VariableMap,
+};
+use crate::{Error, PlanError};
 
 /// Permitted functions.
 #[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
@@ -21,6 +25,54 @@ pub enum Function {
     ADD,
     /// Subtracts one or more numbers from the first provided
     SUBTRACT,
+    /// Multiplies the first provided number by one or more others
+    MULTIPLY,
+}
+
+impl Function {
+    /// Checks the constant inputs to the function, independently of
+    /// any tuples it is applied to.
+    pub fn validate(&self, constants: &[Option<Value>]) -> Result<(), Error> {
+        match *self {
+            Function::TRUNCATE => truncation_interval(constants).map(|_| ()),
+            Function::ADD => numbers(constants, "ADD").map(|_| ()),
+            Function::SUBTRACT => numbers(constants, "SUBTRACT").map(|_| ()),
+            Function::MULTIPLY => numbers(constants, "MULTIPLY").map(|_| ()),
+        }
+    }
+
+    /// Applies the function to a single value, as a stage with a
+    /// single argument variable would. Returns None for values the
+    /// function can't be applied to, as well as for invalid
+    /// constants.
+    pub fn apply(&self, value: &Value, constants: &[Option<Value>]) -> Option<Value> {
+        match (self, value) {
+            (Function::TRUNCATE, Value::Instant(t)) => {
+                let interval = truncation_interval(constants).ok()?;
+                Some(Value::Instant(t - (t % interval)))
+            }
+            (Function::ADD, Value::Number(x)) => {
+                let summands = numbers(constants, "ADD").ok()?;
+                Some(Value::Number(x + summands.iter().sum::<i64>()))
+            }
+            (Function::SUBTRACT, Value::Number(x)) => {
+                let subtrahends = numbers(constants, "SUBTRACT").ok()?;
+                let minuend = match constants.get(0) {
+                    Some(Some(_)) => subtrahends[0],
+                    _ => *x,
+                };
+
+                Some(Value::Number(
+                    minuend + minuend - x - subtrahends.iter().sum::<i64>(),
+                ))
+            }
+            (Function::MULTIPLY, Value::Number(x)) => {
+                let factors = numbers(constants, "MULTIPLY").ok()?;
+                Some(Value::Number(x * factors.iter().product::<i64>()))
+            }
+            _ => None,
+        }
+    }
 }
 
 /// A plan stage applying a built-in function to source tuples.
@@ -48,7 +100,7 @@ impl<P: Implementable> Implementable for Transform<P> {
         self.plan.dependencies()
     }
 
-    fn into_bindings(&self) -> Vec<Binding<Self::A>> {
+    fn into_bindings(&self) -> Result<Vec<Binding<Self::A>>, PlanError> {
         self.plan.into_bindings()
     }
 
@@ -57,141 +109,197 @@ impl<P: Implementable> Implementable for Transform<P> {
         nested: &mut Iterative<'b, S, u64>,
         domain: &mut Domain<Self::A, S::Timestamp>,
         local_arrangements: &VariableMap<Self::A, Iterative<'b, S, u64>>,
-    ) -> (Implemented<'b, Self::A, S>, ShutdownHandle)
+        arrangements: &mut ArrangementMap<Iterative<'b, S, u64>>,
+    ) -> Result<(Implemented<'b, Self::A, S>, ShutdownHandle), Error>
     where
         S: Scope,
         S::Timestamp: Timestamp + Lattice + Rewind,
     {
         let (relation, mut shutdown_handle) =
-            self.plan.implement(nested, domain, local_arrangements);
+            self.plan
+                .implement(nested, domain, local_arrangements, arrangements)?;
+
+        ensure_bound(&relation, &self.variables)?;
 
         let key_offsets: Vec<usize> = self
             .variables
             .iter()
-            .map(|variable| relation.binds(*variable).expect("variable not found"))
+            .map(|variable| relation.binds(*variable).unwrap())
             .collect();
 
         let mut variables = relation.variables();
         variables.push(self.result_variable);
 
-        let constants_local = self.constants.clone();
-
         let tuples = {
             let (tuples, shutdown) = relation.tuples(nested, domain);
             shutdown_handle.merge_with(shutdown);
             tuples
         };
 
+        // Constants are checked up front, tuples of the wrong type
+        // are skipped, rather than taking down the worker.
         let transformed = match self.function {
-            Function::TRUNCATE => CollectionRelation {
-                variables,
-                tuples: tuples.map(move |tuple| {
-                    let mut t = match tuple[key_offsets[0]] {
-                        Value::Instant(inst) => inst as u64,
-                        _ => panic!("TRUNCATE can only be applied to timestamps"),
-                    };
-                    let default_interval = String::from(":hour");
-                    let interval_param = match constants_local[1].clone() {
-                        Some(Value::String(interval)) => interval,
-                        None => default_interval,
-                        _ => panic!("Parameter for TRUNCATE must be a string"),
-                    };
-
-                    let mod_val = match interval_param.as_ref() {
-                        ":minute" => 60000,
-                        ":hour" => 3_600_000,
-                        ":day" => 86_400_000,
-                        ":week" => 604_800_000,
-                        _ => panic!("Unknown interval for TRUNCATE"),
-                    };
-
-                    t = t - (t % mod_val);
-                    let mut v = tuple.clone();
-                    v.push(Value::Instant(t));
-                    v
-                }),
-            },
-            Function::ADD => CollectionRelation {
-                variables,
-                tuples: tuples.map(move |tuple| {
-                    let mut result = 0;
-
-                    // summands (vars)
-                    for offset in &key_offsets {
-                        let summand = match tuple[*offset] {
-                            Value::Number(s) => s as i64,
-                            _ => panic!("ADD can only be applied to numbers"),
+            Function::TRUNCATE => {
+                let mod_val = truncation_interval(&self.constants)?;
+
+                CollectionRelation {
+                    variables,
+                    tuples: tuples.flat_map(move |tuple| {
+                        let t = match tuple[key_offsets[0]] {
+                            Value::Instant(inst) => inst as u64,
+                            ref other => {
+                                error!(
+                                    "TRUNCATE can only be applied to timestamps, not {:?}",
+                                    other
+                                );
+                                return None;
+                            }
                         };
 
-                        result += summand;
-                    }
+                        let mut v = tuple.clone();
+                        v.push(Value::Instant(t - (t % mod_val)));
+                        Some(v)
+                    }),
+                }
+            }
+            Function::ADD => {
+                let constant = numbers(&self.constants, "ADD")?.iter().sum::<i64>();
 
-                    // summands (constants)
-                    for arg in &constants_local {
-                        if let Some(constant) = arg {
-                            let summand = match constant {
-                                Value::Number(s) => *s as i64,
-                                _ => panic!("ADD can only be applied to numbers"),
-                            };
+                CollectionRelation {
+                    variables,
+                    tuples: tuples.flat_map(move |tuple| {
+                        let mut result = constant;
 
-                            result += summand;
+                        // summands (vars)
+                        for offset in &key_offsets {
+                            match tuple[*offset] {
+                                Value::Number(s) => result += s as i64,
+                                ref other => {
+                                    error!("ADD can only be applied to numbers, not {:?}", other);
+                                    return None;
+                                }
+                            }
                         }
-                    }
-
-                    let mut v = tuple.clone();
-                    v.push(Value::Number(result));
-                    v
-                }),
-            },
-            Function::SUBTRACT => CollectionRelation {
-                variables,
-                tuples: tuples.map(move |tuple| {
-                    // minuend is either variable or variable, depending on
-                    // position in transform
-
-                    let mut result = match constants_local[0].clone() {
-                        Some(constant) => match constant {
-                            Value::Number(minuend) => minuend as i64,
-                            _ => panic!("SUBTRACT can only be applied to numbers"),
-                        },
-                        None => match tuple[key_offsets[0]] {
-                            Value::Number(minuend) => minuend as i64,
-                            _ => panic!("SUBTRACT can only be applied to numbers"),
-                        },
-                    };
-
-                    // avoid filtering out the minuend by doubling it
-                    result = result + result;
-
-                    // subtrahends (vars)
-                    for offset in &key_offsets {
-                        let subtrahend = match tuple[*offset] {
-                            Value::Number(s) => s as i64,
-                            _ => panic!("SUBTRACT can only be applied to numbers"),
-                        };
 
-                        result -= subtrahend;
-                    }
+                        let mut v = tuple.clone();
+                        v.push(Value::Number(result));
+                        Some(v)
+                    }),
+                }
+            }
+            Function::MULTIPLY => {
+                let constant = numbers(&self.constants, "MULTIPLY")?
+                    .iter()
+                    .product::<i64>();
 
-                    // subtrahends (constants)
-                    for arg in &constants_local {
-                        if let Some(constant) = arg {
-                            let subtrahend = match constant {
-                                Value::Number(s) => *s as i64,
-                                _ => panic!("SUBTRACT can only be applied to numbers"),
-                            };
+                CollectionRelation {
+                    variables,
+                    tuples: tuples.flat_map(move |tuple| {
+                        let mut result = constant;
 
-                            result -= subtrahend;
+                        for offset in &key_offsets {
+                            match tuple[*offset] {
+                                Value::Number(s) => result *= s,
+                                ref other => {
+                                    error!(
+                                        "MULTIPLY can only be applied to numbers, not {:?}",
+                                        other
+                                    );
+                                    return None;
+                                }
+                            }
                         }
-                    }
 
-                    let mut v = tuple.clone();
-                    v.push(Value::Number(result));
-                    v
-                }),
-            },
+                        let mut v = tuple.clone();
+                        v.push(Value::Number(result));
+                        Some(v)
+                    }),
+                }
+            }
+            Function::SUBTRACT => {
+                let constants = numbers(&self.constants, "SUBTRACT")?;
+                let constant_minuend = match self.constants.get(0) {
+                    Some(Some(_)) => Some(constants[0]),
+                    _ => None,
+                };
+                let subtrahends = constants.iter().sum::<i64>();
+
+                CollectionRelation {
+                    variables,
+                    tuples: tuples.flat_map(move |tuple| {
+                        let mut values = Vec::with_capacity(key_offsets.len());
+                        for offset in &key_offsets {
+                            match tuple[*offset] {
+                                Value::Number(s) => values.push(s as i64),
+                                ref other => {
+                                    error!(
+                                        "SUBTRACT can only be applied to numbers, not {:?}",
+                                        other
+                                    );
+                                    return None;
+                                }
+                            }
+                        }
+
+                        // minuend is either variable or variable, depending on
+                        // position in transform
+                        let minuend = constant_minuend
+                            .or_else(|| values.first().cloned())
+                            .unwrap_or(0);
+
+                        // avoid filtering out the minuend by doubling it
+                        let result = minuend + minuend - values.iter().sum::<i64>() - subtrahends;
+
+                        let mut v = tuple.clone();
+                        v.push(Value::Number(result));
+                        Some(v)
+                    }),
+                }
+            }
         };
 
-        (Implemented::Collection(transformed), shutdown_handle)
+        Ok((Implemented::Collection(transformed), shutdown_handle))
+    }
+}
+
+/// Returns the length in milliseconds of the interval TRUNCATE
+/// rounds down to, as specified by its second constant.
+fn truncation_interval(constants: &[Option<Value>]) -> Result<u64, Error> {
+    let interval = match constants.get(1).cloned().unwrap_or(None) {
+        None => ":hour".to_string(),
+        Some(Value::String(interval)) => interval,
+        Some(_) => {
+            return Err(PlanError::InvalidArgument(
+                "Parameter for TRUNCATE must be a string".to_string(),
+            )
+            .into());
+        }
+    };
+
+    match interval.as_ref() {
+        ":minute" => Ok(60000),
+        ":hour" => Ok(3_600_000),
+        ":day" => Ok(86_400_000),
+        ":week" => Ok(604_800_000),
+        _ => Err(
+            PlanError::InvalidArgument(format!("Unknown interval {} for TRUNCATE", interval))
+                .into(),
+        ),
     }
 }
+
+/// Checks that all constant arguments to a function are numbers.
+fn numbers(constants: &[Option<Value>], function: &str) -> Result<Vec<i64>, Error> {
+    constants
+        .iter()
+        .filter_map(|constant| constant.as_ref())
+        .map(|constant| match constant {
+            Value::Number(x) => Ok(*x as i64),
+            _ => Err(PlanError::InvalidArgument(format!(
+                "{} can only be applied to numbers",
+                function
+            ))
+            .into()),
+        })
+        .collect()
+}
@@ -0,0 +1,156 @@
+//! Change-detection plan.
+
+use timely::dataflow::operators::Map;
+use timely::dataflow::scopes::child::Iterative;
+use timely::dataflow::Scope;
+use timely::progress::Timestamp;
+
+use differential_dataflow::lattice::Lattice;
+use differential_dataflow::AsCollection;
+
+use crate::binding::Binding;
+use crate::domain::Domain;
+use crate::operators::Transitions as _;
+use crate::plan::{ensure_bound, Dependencies, Implementable};
+use crate::timestamp::{Rewind, Time};
+use crate::{
+    ArrangementMap, CollectionRelation, Implemented, Relation, ShutdownHandle, Value, Var,
+    VariableMap,
+};
+use crate::{Error, PlanError};
+
+/// A plan stage emitting a transition tuple whenever the value bound
+/// to `value_variable` changes for some key. The stage binds the key
+/// variables, followed by the previous and the new value, and the
+/// time of the change.
+///
+/// Each transition appears at the time of the change, once for every
+/// value replaced, and is never retracted. Clients and triggers
+/// subscribed to the stage therefore see edges, rather than levels.
+///
+/// Real times of changes are bound as instants on the domain's
+/// clock. These only compare to stored instants if inputs are
+/// introduced at event times since the Unix epoch, rather than at
+/// times measured from the start of the computation.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct Transitions<P: Implementable> {
+    /// Plan for the data source.
+    pub plan: Box<P>,
+    /// Variables identifying whose value is tracked.
+    pub key_variables: Vec<Var>,
+    /// Variable bound to the tracked value. Bound to the new value
+    /// by this stage.
+    pub value_variable: Var,
+    /// Variable to bind the previous value to.
+    pub previous_variable: Var,
+    /// Variable to bind the time of the change to.
+    pub time_variable: Var,
+}
+
+impl<P: Implementable> Transitions<P> {
+    /// Returns the variables bound by this stage.
+    pub fn variables(&self) -> Vec<Var> {
+        let mut variables = self.key_variables.clone();
+        variables.push(self.previous_variable);
+        variables.push(self.value_variable);
+        variables.push(self.time_variable);
+        variables
+    }
+}
+
+/// Returns the value a time is bound as. Transaction ids are bound
+/// as numbers, real and bitemporal times as the instant at which they
+/// happen. Instants are taken as they are, without an offset: they
+/// count from the Unix epoch for event times introduced by sources,
+/// but from the start of the computation for times the server
+/// advances to on its own. Only the former compare to stored
+/// instants.
+fn time_value(time: Time) -> Value {
+    match time {
+        Time::TxId(t) => Value::Number(t as i64),
+        Time::Real(t) | Time::Bi(t, _) => Value::Instant(t.as_millis() as u64),
+    }
+}
+
+impl<P: Implementable> Implementable for Transitions<P> {
+    type A = P::A;
+
+    fn dependencies(&self) -> Dependencies<Self::A> {
+        self.plan.dependencies()
+    }
+
+    fn into_bindings(&self) -> Result<Vec<Binding<Self::A>>, PlanError> {
+        self.plan.into_bindings()
+    }
+
+    fn implement<'b, S>(
+        &self,
+        nested: &mut Iterative<'b, S, u64>,
+        domain: &mut Domain<Self::A, S::Timestamp>,
+        local_arrangements: &VariableMap<Self::A, Iterative<'b, S, u64>>,
+        arrangements: &mut ArrangementMap<Iterative<'b, S, u64>>,
+    ) -> Result<(Implemented<'b, Self::A, S>, ShutdownHandle), Error>
+    where
+        S: Scope,
+        S::Timestamp: Timestamp + Lattice + Rewind,
+    {
+        if self.previous_variable == self.value_variable
+            || self.key_variables.contains(&self.previous_variable)
+        {
+            return Err(PlanError::InvalidArgument(format!(
+                "Variable {} can't hold previous values.",
+                self.previous_variable
+            ))
+            .into());
+        }
+
+        if self.time_variable == self.value_variable
+            || self.time_variable == self.previous_variable
+            || self.key_variables.contains(&self.time_variable)
+        {
+            return Err(PlanError::InvalidArgument(format!(
+                "Variable {} can't hold times of changes.",
+                self.time_variable
+            ))
+            .into());
+        }
+
+        let (relation, mut shutdown_handle) =
+            self.plan
+                .implement(nested, domain, local_arrangements, arrangements)?;
+
+        ensure_bound(&relation, &self.key_variables)?;
+        ensure_bound(&relation, &[self.value_variable])?;
+
+        let mut variables = self.key_variables.clone();
+        variables.push(self.value_variable);
+
+        let tuples = {
+            let (projected, shutdown) = relation.projected(nested, domain, &variables);
+            shutdown_handle.merge_with(shutdown);
+            projected
+        };
+
+        let tuples = tuples
+            .map(|mut tuple| {
+                let value = tuple.pop().unwrap();
+                (tuple, value)
+            })
+            .transitions()
+            .inner
+            .map(|((mut key, (previous, value)), t, diff)| {
+                key.push(previous);
+                key.push(value);
+                key.push(time_value(t.outer.to_time()));
+                (key, t, diff)
+            })
+            .as_collection();
+
+        let relation = CollectionRelation {
+            variables: self.variables(),
+            tuples,
+        };
+
+        Ok((Implemented::Collection(relation), shutdown_handle))
+    }
+}
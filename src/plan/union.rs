@@ -7,21 +7,70 @@ use timely::progress::Timestamp;
 use differential_dataflow::lattice::Lattice;
 use differential_dataflow::operators::Threshold;
 
-use crate::binding::Binding;
+use crate::binding::{AsBinding, Binding};
 use crate::domain::Domain;
 use crate::plan::{Dependencies, Implementable};
 use crate::timestamp::Rewind;
-use crate::{CollectionRelation, Implemented, Relation, ShutdownHandle, Var, VariableMap};
+use crate::{
+    ArrangementMap, CollectionRelation, Implemented, Relation, ShutdownHandle, Var, VariableMap,
+};
+use crate::{Error, PlanError};
 
-/// A plan stage taking the union over its sources. Frontends are
-/// responsible to ensure that the sources are union-compatible
-/// (i.e. bind all of the same variables in the same order).
+/// A plan stage taking the union over any number of sources.
+///
+/// By default, every source must bind all of the union's variables.
+/// Sources binding their columns to other variables can instead be
+/// given an explicit mapping, listing the source variables that make
+/// up each of the union's variables, in order. Sources not matching
+/// up with the union are rejected, rather than having their columns
+/// misaligned.
 #[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
 pub struct Union<P: Implementable> {
-    /// TODO
+    /// Variables bound by the union.
     pub variables: Vec<Var>,
     /// Plan for the data source.
     pub plans: Vec<P>,
+    /// Per-source variables corresponding to the union's variables.
+    /// Either empty, or holding one mapping for each source.
+    #[serde(default)]
+    pub mappings: Vec<Vec<Var>>,
+}
+
+impl<P: Implementable> Union<P> {
+    /// Returns the variables the specified source contributes to the
+    /// union, in the order of the union's variables.
+    pub fn mapping(&self, source: usize) -> &[Var] {
+        match self.mappings.get(source) {
+            None => &self.variables,
+            Some(mapping) => mapping,
+        }
+    }
+
+    /// Checks that the mappings line up with sources and variables.
+    fn validate(&self) -> Result<(), Error> {
+        if !self.mappings.is_empty() && self.mappings.len() != self.plans.len() {
+            return Err(PlanError::InvalidArgument(format!(
+                "Union over {} inputs has {} mappings.",
+                self.plans.len(),
+                self.mappings.len()
+            ))
+            .into());
+        }
+
+        for (source, mapping) in self.mappings.iter().enumerate() {
+            if mapping.len() != self.variables.len() {
+                return Err(PlanError::InvalidArgument(format!(
+                    "Union input {} maps {} variables onto the union's {}.",
+                    source,
+                    mapping.len(),
+                    self.variables.len()
+                ))
+                .into());
+            }
+        }
+
+        Ok(())
+    }
 }
 
 impl<P: Implementable> Implementable for Union<P> {
@@ -31,11 +80,14 @@ impl<P: Implementable> Implementable for Union<P> {
         self.plans.iter().map(|plan| plan.dependencies()).sum()
     }
 
-    fn into_bindings(&self) -> Vec<Binding<Self::A>> {
-        self.plans
-            .iter()
-            .flat_map(Implementable::into_bindings)
-            .collect()
+    fn into_bindings(&self) -> Result<Vec<Binding<Self::A>>, PlanError> {
+        let mut bindings = Vec::new();
+
+        for plan in self.plans.iter() {
+            bindings.append(&mut plan.into_bindings()?);
+        }
+
+        Ok(bindings)
     }
 
     fn implement<'b, S>(
@@ -43,7 +95,8 @@ impl<P: Implementable> Implementable for Union<P> {
         nested: &mut Iterative<'b, S, u64>,
         domain: &mut Domain<Self::A, S::Timestamp>,
         local_arrangements: &VariableMap<Self::A, Iterative<'b, S, u64>>,
-    ) -> (Implemented<'b, Self::A, S>, ShutdownHandle)
+        arrangements: &mut ArrangementMap<Iterative<'b, S, u64>>,
+    ) -> Result<(Implemented<'b, Self::A, S>, ShutdownHandle), Error>
     where
         S: Scope,
         S::Timestamp: Timestamp + Lattice + Rewind,
@@ -51,24 +104,41 @@ impl<P: Implementable> Implementable for Union<P> {
         use differential_dataflow::AsCollection;
         use timely::dataflow::operators::Concatenate;
 
+        self.validate()?;
+
         let mut scope = nested.clone();
         let mut shutdown_handle = ShutdownHandle::empty();
 
-        let streams = self.plans.iter().map(|plan| {
+        let mut streams = Vec::with_capacity(self.plans.len());
+        for (source, plan) in self.plans.iter().enumerate() {
             let relation = {
-                let (relation, shutdown) = plan.implement(&mut scope, domain, local_arrangements);
+                let (relation, shutdown) =
+                    plan.implement(&mut scope, domain, local_arrangements, arrangements)?;
                 shutdown_handle.merge_with(shutdown);
                 relation
             };
 
+            let mapping = self.mapping(source);
+
+            if let Some(unbound) = mapping
+                .iter()
+                .find(|variable| relation.binds(**variable).is_none())
+            {
+                return Err(PlanError::InvalidArgument(format!(
+                    "Union input {} doesn't bind variable {}.",
+                    source, unbound
+                ))
+                .into());
+            }
+
             let projected = {
-                let (projected, shutdown) = relation.projected(&mut scope, domain, &self.variables);
+                let (projected, shutdown) = relation.projected(&mut scope, domain, mapping);
                 shutdown_handle.merge_with(shutdown);
                 projected
             };
 
-            projected.inner
-        });
+            streams.push(projected.inner);
+        }
 
         let concat = nested.concatenate(streams).as_collection();
 
@@ -77,6 +147,6 @@ impl<P: Implementable> Implementable for Union<P> {
             tuples: concat.distinct(),
         };
 
-        (Implemented::Collection(concatenated), shutdown_handle)
+        Ok((Implemented::Collection(concatenated), shutdown_handle))
     }
 }
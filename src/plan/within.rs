@@ -0,0 +1,292 @@
+//! Spatial predicate plan.
+
+use timely::dataflow::operators::{Map, ToStream};
+use timely::dataflow::scopes::child::Iterative;
+use timely::dataflow::Scope;
+use timely::progress::Timestamp;
+
+use differential_dataflow::lattice::Lattice;
+use differential_dataflow::operators::arrange::ArrangeBySelf;
+use differential_dataflow::operators::JoinCore;
+use differential_dataflow::AsCollection;
+
+use crate::binding::{AsBinding, Binding};
+use crate::domain::spatial::{self, UNITS_PER_DEGREE};
+use crate::domain::Domain;
+use crate::plan::{ensure_bound, Dependencies, Implementable, Plan};
+use crate::timestamp::Rewind;
+use crate::{
+    ArrangementMap, CollectionRelation, Implemented, Relation, ShutdownHandle, Value, Var,
+    VariableMap,
+};
+use crate::{AsAid, Error, PlanError};
+
+/// Mean radius of the earth, in meters.
+const EARTH_RADIUS: f64 = 6_371_008.8;
+
+/// Regions covering more cells than this are scanned for, rather
+/// than looked up in a spatial index.
+const MAX_CELLS: usize = 4096;
+
+/// Regions that geo points can be tested against. All corners and
+/// centers must be geo points.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub enum Region {
+    /// Points at most the specified number of meters away from the
+    /// center, along the surface of the earth.
+    WithinRadius(Value, u64),
+    /// Points inside the box between its south-west and north-east
+    /// corners. Boxes whose western edge lies east of their eastern
+    /// one wrap around the antimeridian.
+    WithinBbox(Value, Value),
+}
+
+fn coordinates(point: &Value) -> Result<(i64, i64), Error> {
+    match *point {
+        Value::GeoPoint(lat, lon) => Ok((i64::from(lat), i64::from(lon))),
+        _ => Err(PlanError::InvalidArgument(format!("{:?} is not a geo point.", point)).into()),
+    }
+}
+
+fn radians(units: i64) -> f64 {
+    (units as f64 / UNITS_PER_DEGREE as f64).to_radians()
+}
+
+/// Great-circle distance between two points, in meters.
+fn distance(a: (i64, i64), b: (i64, i64)) -> f64 {
+    let (lat_a, lat_b) = (radians(a.0), radians(b.0));
+    let d_lat = radians(b.0 - a.0);
+    let d_lon = radians(b.1 - a.1);
+
+    let h = (d_lat / 2.0).sin().powi(2) + lat_a.cos() * lat_b.cos() * (d_lon / 2.0).sin().powi(2);
+
+    2.0 * EARTH_RADIUS * h.sqrt().min(1.0).asin()
+}
+
+/// Normalizes a longitude into [-180, 180) degrees.
+fn wrap(lon: i64) -> i64 {
+    let circle = 360 * UNITS_PER_DEGREE;
+    ((lon + 180 * UNITS_PER_DEGREE) % circle + circle) % circle - 180 * UNITS_PER_DEGREE
+}
+
+impl Region {
+    /// Returns the (south, west, north, east) bounds of the region.
+    fn bounds(&self) -> Result<(i64, i64, i64, i64), Error> {
+        match *self {
+            Region::WithinRadius(ref center, meters) => {
+                let (lat, lon) = coordinates(center)?;
+                let degrees = (meters as f64 / EARTH_RADIUS).to_degrees();
+                let d_lat = (degrees * UNITS_PER_DEGREE as f64).ceil() as i64;
+
+                let south = lat - d_lat;
+                let north = lat + d_lat;
+                let pole = 90 * UNITS_PER_DEGREE;
+
+                // Circles around a pole, or wider than the meridians
+                // allow, span all longitudes.
+                let d_lon = if south <= -pole || north >= pole {
+                    None
+                } else {
+                    let widest = radians(south.abs().max(north.abs())).cos();
+                    let d_lon = (d_lat as f64 / widest).ceil() as i64;
+
+                    if d_lon >= 180 * UNITS_PER_DEGREE {
+                        None
+                    } else {
+                        Some(d_lon)
+                    }
+                };
+
+                let south = south.max(-pole);
+                let north = north.min(pole);
+
+                match d_lon {
+                    None => Ok((
+                        south,
+                        -180 * UNITS_PER_DEGREE,
+                        north,
+                        180 * UNITS_PER_DEGREE,
+                    )),
+                    Some(d_lon) => Ok((south, wrap(lon - d_lon), north, wrap(lon + d_lon))),
+                }
+            }
+            Region::WithinBbox(ref south_west, ref north_east) => {
+                let (south, west) = coordinates(south_west)?;
+                let (north, east) = coordinates(north_east)?;
+
+                if south > north {
+                    return Err(PlanError::InvalidArgument(format!(
+                        "Bounding box corners {:?} and {:?} are not south-west and north-east.",
+                        south_west, north_east
+                    ))
+                    .into());
+                }
+
+                Ok((south, west, north, east))
+            }
+        }
+    }
+
+    /// Checks whether the specified value is a geo point inside the
+    /// region. Assumes the region's bounds are valid.
+    pub fn contains(&self, point: &Value) -> bool {
+        let point = match coordinates(point) {
+            Ok(point) => point,
+            Err(_) => return false,
+        };
+
+        match *self {
+            Region::WithinRadius(ref center, meters) => match coordinates(center) {
+                Ok(center) => distance(center, point) <= meters as f64,
+                Err(_) => false,
+            },
+            Region::WithinBbox(ref south_west, ref north_east) => {
+                match (coordinates(south_west), coordinates(north_east)) {
+                    (Ok((south, west)), Ok((north, east))) => {
+                        let (lat, lon) = point;
+                        let within_lon = if west <= east {
+                            west <= lon && lon <= east
+                        } else {
+                            lon >= west || lon <= east
+                        };
+
+                        south <= lat && lat <= north && within_lon
+                    }
+                    _ => false,
+                }
+            }
+        }
+    }
+}
+
+/// A plan stage retaining those source tuples whose geo point bound
+/// to `variable` lies inside a region. Stages directly on top of a
+/// `MatchA` on an attribute with a spatial index look up the cells
+/// covering the region, instead of scanning all points.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct Within<P: Implementable> {
+    /// Variable bound to the geo points to test.
+    pub variable: Var,
+    /// Region points have to lie within.
+    pub region: Region,
+    /// Plan for the data source.
+    pub plan: Box<P>,
+}
+
+impl<P: Implementable> Implementable for Within<P> {
+    type A = P::A;
+
+    fn dependencies(&self) -> Dependencies<Self::A> {
+        self.plan.dependencies()
+    }
+
+    fn into_bindings(&self) -> Result<Vec<Binding<Self::A>>, PlanError> {
+        Err(PlanError::Unsupported(
+            "Spatial predicates can't be implemented via Hector yet.".to_string(),
+        ))
+    }
+
+    fn implement<'b, S>(
+        &self,
+        nested: &mut Iterative<'b, S, u64>,
+        domain: &mut Domain<Self::A, S::Timestamp>,
+        local_arrangements: &VariableMap<Self::A, Iterative<'b, S, u64>>,
+        arrangements: &mut ArrangementMap<Iterative<'b, S, u64>>,
+    ) -> Result<(Implemented<'b, Self::A, S>, ShutdownHandle), Error>
+    where
+        S: Scope,
+        S::Timestamp: Timestamp + Lattice + Rewind,
+    {
+        self.region.bounds()?;
+
+        let (relation, mut shutdown_handle) =
+            self.plan
+                .implement(nested, domain, local_arrangements, arrangements)?;
+
+        ensure_bound(&relation, &[self.variable])?;
+
+        let offset = relation.binds(self.variable).unwrap();
+        let variables = relation.variables();
+        let projected = {
+            let (projected, shutdown) = relation.projected(nested, domain, &variables);
+            shutdown_handle.merge_with(shutdown);
+            projected
+        };
+
+        let region = self.region.clone();
+        let tuples = projected.filter(move |tuple| region.contains(&tuple[offset]));
+
+        let filtered = CollectionRelation { variables, tuples };
+
+        Ok((Implemented::Collection(filtered), shutdown_handle))
+    }
+}
+
+impl<A: AsAid> Within<Plan<A>> {
+    /// Implements the stage via the source attribute's spatial index,
+    /// falling back to scanning if it has none or the region covers
+    /// too many cells.
+    pub(crate) fn implement_indexed<'b, S>(
+        &self,
+        nested: &mut Iterative<'b, S, u64>,
+        domain: &mut Domain<A, S::Timestamp>,
+        local_arrangements: &VariableMap<A, Iterative<'b, S, u64>>,
+        arrangements: &mut ArrangementMap<Iterative<'b, S, u64>>,
+    ) -> Result<(Implemented<'b, A, S>, ShutdownHandle), Error>
+    where
+        S: Scope,
+        S::Timestamp: Timestamp + Lattice + Rewind,
+    {
+        let (e, a, v) = match *self.plan {
+            Plan::MatchA(e, ref a, v) if v == self.variable && e != v => (e, a, v),
+            _ => return self.implement(nested, domain, local_arrangements, arrangements),
+        };
+
+        let (south, west, north, east) = self.region.bounds()?;
+
+        let cells = match spatial::cover(south, west, north, east, MAX_CELLS) {
+            None => return self.implement(nested, domain, local_arrangements, arrangements),
+            Some(cells) => cells,
+        };
+
+        let (index, shutdown_index) = match domain.spatial(a) {
+            None => return self.implement(nested, domain, local_arrangements, arrangements),
+            Some(trace) => trace.import_frontier(&nested.parent, &format!("Spatial({:?})", a)),
+        };
+
+        // Cells are introduced on a single worker, s.t. every one of
+        // them is looked up once, rather than once per worker.
+        let cells = if nested.index() == 0 {
+            cells
+        } else {
+            Vec::new()
+        };
+
+        let keys = cells
+            .to_stream(nested)
+            .map(|cell| (cell, Default::default(), 1))
+            .as_collection()
+            .arrange_by_self();
+
+        let region = self.region.clone();
+        let tuples = index
+            .enter(nested)
+            .join_core(&keys, move |_cell, (e, v), &()| {
+                if region.contains(v) {
+                    Some(vec![e.clone(), v.clone()])
+                } else {
+                    None
+                }
+            });
+
+        let relation = CollectionRelation {
+            variables: vec![e, v],
+            tuples,
+        };
+
+        Ok((
+            Implemented::Collection(relation),
+            ShutdownHandle::from_button(shutdown_index),
+        ))
+    }
+}
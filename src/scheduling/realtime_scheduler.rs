@@ -3,10 +3,11 @@
 use std::cmp::Ordering;
 use std::collections::BinaryHeap;
 use std::rc::Weak;
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
 use timely::scheduling::Activator;
 
+use crate::clock::Instant;
 use crate::scheduling::AsScheduler;
 
 /// A scheduler allows polling sources to defer triggering their
@@ -0,0 +1,198 @@
+//! Bounds on the outputs buffered for clients that can't keep up.
+
+use std::collections::{HashMap, VecDeque};
+
+use crate::server::TxId;
+use crate::timestamp::Time;
+use crate::{Client, Error, Output, ResultDiff};
+
+/// What to do about clients that can't keep up with their outputs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Disconnect the client.
+    Disconnect,
+    /// Merge pending diffs on the same query into fewer, consolidated
+    /// batches. Clients are disconnected if that is not enough.
+    Coalesce,
+    /// Drop pending outputs of the subscriptions responsible for most
+    /// of them, and stop sending further results on those, until the
+    /// client expresses interest again.
+    Pause,
+}
+
+impl std::str::FromStr for OverflowPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "disconnect" => Ok(OverflowPolicy::Disconnect),
+            "coalesce" => Ok(OverflowPolicy::Coalesce),
+            "pause" => Ok(OverflowPolicy::Pause),
+            _ => Err(format!("unknown overflow policy {}", s)),
+        }
+    }
+}
+
+/// What has to happen to a client after queueing an output for it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Overflow {
+    /// The queue is within bounds. The listed subscriptions have been
+    /// paused to get there, and should be dropped on the server.
+    Within(Vec<String>),
+    /// The queue can't be brought back within bounds, and the client
+    /// has to be disconnected.
+    Exceeded,
+}
+
+/// Bounds on the outputs buffered for each client connection.
+#[derive(Debug, Clone, Copy)]
+pub struct Backpressure {
+    /// Maximum number of outputs waiting to be handed to a connection.
+    pub max_pending: usize,
+    /// What to do once a client exceeds that bound.
+    pub policy: OverflowPolicy,
+}
+
+impl Backpressure {
+    /// Queues an output for the specified client, applying the
+    /// overflow policy if that brings the queue over its bound.
+    pub fn enqueue(
+        &self,
+        queue: &mut VecDeque<Output>,
+        out: Output,
+        client: Client,
+        t: TxId,
+    ) -> Overflow {
+        queue.push_back(out);
+
+        let mut paused = Vec::new();
+
+        if queue.len() > self.max_pending {
+            match self.policy {
+                OverflowPolicy::Disconnect => {}
+                OverflowPolicy::Coalesce => coalesce(queue),
+                OverflowPolicy::Pause => {
+                    while queue.len() > self.max_pending {
+                        let name = match busiest(queue) {
+                            None => break,
+                            Some(name) => name,
+                        };
+
+                        queue.retain(|out| subscription(out) != Some(&name));
+                        queue.push_back(Output::Error(
+                            client,
+                            Error::interrupted(format!(
+                                "Paused {}, because the client can't keep up.",
+                                name
+                            )),
+                            t,
+                            None,
+                        ));
+
+                        paused.push(name);
+                    }
+                }
+            }
+
+            if queue.len() > self.max_pending {
+                return Overflow::Exceeded;
+            }
+        }
+
+        Overflow::Within(paused)
+    }
+}
+
+/// Returns the name of the subscription an output belongs to, if any.
+pub fn subscription(out: &Output) -> Option<&String> {
+    match out {
+        Output::QueryDiff(name, _) => Some(name),
+        Output::KeyedDiff(name, _) => Some(name),
+        Output::Json(name, _, _, _) => Some(name),
+        Output::InitialComplete(name, _) => Some(name),
+        Output::Progress(name, _) => Some(name),
+        Output::GroupDiff(name, _, _) => Some(name),
+        Output::Commit(name, _) => Some(name),
+        Output::Message(_, _) | Output::Ack(_, _, _) | Output::Error(_, _, _, _) => None,
+    }
+}
+
+/// Returns the subscription with the most pending outputs.
+fn busiest(queue: &VecDeque<Output>) -> Option<String> {
+    let mut counts: HashMap<&String, usize> = HashMap::new();
+
+    for name in queue.iter().filter_map(subscription) {
+        *counts.entry(name).or_insert(0) += 1;
+    }
+
+    counts
+        .into_iter()
+        .max_by_key(|(_, count)| *count)
+        .map(|(name, _)| name.clone())
+}
+
+/// Merges pending batches of diffs on the same query, unless they are
+/// separated by other outputs concerning that query. Progress
+/// notifications may be delayed, so only the latest one on each query
+/// is kept.
+pub fn coalesce(queue: &mut VecDeque<Output>) {
+    let mut coalesced: Vec<Option<Output>> = Vec::with_capacity(queue.len());
+    let mut open_diffs: HashMap<String, usize> = HashMap::new();
+    let mut last_progress: HashMap<String, usize> = HashMap::new();
+
+    for out in queue.drain(..) {
+        match out {
+            Output::QueryDiff(name, mut results) => match open_diffs.get(&name) {
+                Some(&index) => {
+                    if let Some(Output::QueryDiff(_, ref mut merged)) = coalesced[index] {
+                        merged.append(&mut results);
+                    }
+                }
+                None => {
+                    open_diffs.insert(name.clone(), coalesced.len());
+                    coalesced.push(Some(Output::QueryDiff(name, results)));
+                }
+            },
+            Output::Progress(name, frontier) => {
+                if let Some(index) = last_progress.insert(name.clone(), coalesced.len()) {
+                    coalesced[index] = None;
+                }
+
+                coalesced.push(Some(Output::Progress(name, frontier)));
+            }
+            other => {
+                if let Some(name) = subscription(&other) {
+                    open_diffs.remove(name);
+                }
+
+                coalesced.push(Some(other));
+            }
+        }
+    }
+
+    for out in coalesced.into_iter().flatten() {
+        match out {
+            Output::QueryDiff(name, mut results) => {
+                results.sort();
+
+                let mut consolidated: Vec<ResultDiff<Time>> = Vec::with_capacity(results.len());
+                for (tuple, time, diff) in results.drain(..) {
+                    let is_same = consolidated
+                        .last()
+                        .map(|last| last.0 == tuple && last.1 == time)
+                        .unwrap_or(false);
+
+                    if is_same {
+                        consolidated.last_mut().unwrap().2 += diff;
+                    } else {
+                        consolidated.push((tuple, time, diff));
+                    }
+                }
+
+                consolidated.retain(|(_, _, diff)| *diff != 0);
+                queue.push_back(Output::QueryDiff(name, consolidated));
+            }
+            other => queue.push_back(other),
+        }
+    }
+}
@@ -0,0 +1,862 @@
+//! EDN as a wire format for requests and outputs, next to JSON.
+//!
+//! Clojure(Script) clients would otherwise lose keywords, ratios and
+//! the like on the way through JSON. Values are therefore written in
+//! the form matching their type, e.g. `:person/name`, `1/3`, `#inst`
+//! or `#uuid` literals, while everything else follows serde's data
+//! model: structs become maps keyed by keywords, sequences become
+//! vectors, and enum variants are either a keyword or a single-entry
+//! map from the variant's keyword to its contents.
+
+use std::fmt;
+
+use serde::de::{self, DeserializeOwned, DeserializeSeed, Deserializer as _, Visitor};
+use serde::ser::{self, Serialize, Serializer as _};
+
+use crate::frontends::edn::{self, Edn};
+#[cfg(feature = "serde_json")]
+use crate::server::versioning::CURRENT_VERSION;
+#[cfg(feature = "serde_json")]
+use crate::server::Envelope;
+#[cfg(feature = "serde_json")]
+use crate::AsAid;
+use crate::{Error, ProtocolError};
+
+/// Tag of attribute identifiers that don't make for a keyword.
+pub const AID_TAG: &str = "df/aid";
+
+/// Tag of entity identifiers, telling them apart from numbers.
+pub const EID_TAG: &str = "df/eid";
+
+/// Serializes a value, e.g. an output, into EDN.
+pub fn to_string<T: Serialize + ?Sized>(value: &T) -> Result<String, Error> {
+    to_edn(value).map(|form| form.to_string())
+}
+
+/// Serializes a value into a single EDN form.
+pub fn to_edn<T: Serialize + ?Sized>(value: &T) -> Result<Edn, Error> {
+    value
+        .serialize(Serializer)
+        .map_err(|failure| Error::unsupported(failure.0))
+}
+
+/// Deserializes a value, e.g. a batch of requests, from EDN.
+pub fn from_str<T: DeserializeOwned>(string: &str) -> Result<T, ProtocolError> {
+    let form = edn::read_one(string).map_err(|error| ProtocolError::Malformed(error.message))?;
+
+    from_edn(form)
+}
+
+/// Deserializes a value from a single EDN form.
+pub fn from_edn<T: DeserializeOwned>(form: Edn) -> Result<T, ProtocolError> {
+    T::deserialize(form).map_err(|failure| ProtocolError::Malformed(failure.0))
+}
+
+/// Deserializes an identified batch of requests. EDN was introduced
+/// with the current request format, which is the only one accepted.
+#[cfg(feature = "serde_json")]
+pub fn parse_envelope<A>(form: Edn) -> Result<Envelope<A>, ProtocolError>
+where
+    A: AsAid + From<&'static str> + DeserializeOwned,
+{
+    let envelope: Envelope<A> = from_edn(form)?;
+
+    match envelope.version {
+        Some(version) if version != CURRENT_VERSION => {
+            Err(ProtocolError::UnsupportedVersion(version))
+        }
+        _ => Ok(envelope),
+    }
+}
+
+#[derive(Debug)]
+struct Failure(String);
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for Failure {}
+
+impl ser::Error for Failure {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Failure(msg.to_string())
+    }
+}
+
+impl de::Error for Failure {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Failure(msg.to_string())
+    }
+}
+
+fn is_keyword(name: &str) -> bool {
+    !name.is_empty() && !name.starts_with(':') && !name.chars().any(edn::is_delimiter)
+}
+
+fn variant_form(variant: &str, form: Edn) -> Edn {
+    Edn::Map(vec![(Edn::Keyword(variant.to_string()), form)])
+}
+
+fn aid_form(aid: String) -> Edn {
+    if aid.starts_with(':') && is_keyword(&aid[1..]) {
+        Edn::Keyword(aid[1..].to_string())
+    } else {
+        Edn::Tagged(AID_TAG.to_string(), Box::new(Edn::String(aid)))
+    }
+}
+
+fn ratio_form(parts: Vec<Edn>) -> Edn {
+    if let [Edn::Integer(numerator), Edn::Integer(denominator)] = parts.as_slice() {
+        return Edn::Ratio(*numerator, *denominator);
+    }
+
+    variant_form("Rational32", Edn::Vector(parts))
+}
+
+// Values are identified by the name of their type, which serde hands
+// to us along with each variant.
+fn is_value(name: &str, variants: &[&str]) -> bool {
+    name == "Value" && variants.contains(&"Aid") && variants.contains(&"Eid")
+}
+
+fn value_form(variant: &'static str, form: Edn) -> Edn {
+    match (variant, form) {
+        ("Aid", Edn::String(aid)) => aid_form(aid),
+        ("String", form) | ("Bool", form) | ("Number", form) => form,
+        ("Rational32", Edn::Vector(parts)) => ratio_form(parts),
+        ("Eid", form) => Edn::Tagged(EID_TAG.to_string(), Box::new(form)),
+        ("Instant", Edn::Integer(millis)) if millis >= 0 => Edn::Tagged(
+            "inst".to_string(),
+            Box::new(Edn::String(format_instant(millis as u64))),
+        ),
+        ("Uuid", Edn::String(uuid)) => Edn::Tagged("uuid".to_string(), Box::new(Edn::String(uuid))),
+        (variant, form) => variant_form(variant, form),
+    }
+}
+
+struct Serializer;
+
+impl ser::Serializer for Serializer {
+    type Ok = Edn;
+    type Error = Failure;
+
+    type SerializeSeq = SerializeVector;
+    type SerializeTuple = SerializeVector;
+    type SerializeTupleStruct = SerializeVector;
+    type SerializeTupleVariant = SerializeVariant;
+    type SerializeMap = SerializeMap;
+    type SerializeStruct = SerializeMap;
+    type SerializeStructVariant = SerializeVariant;
+
+    fn serialize_bool(self, v: bool) -> Result<Edn, Failure> {
+        Ok(Edn::Bool(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Edn, Failure> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Edn, Failure> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Edn, Failure> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Edn, Failure> {
+        Ok(Edn::Integer(v))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Edn, Failure> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Edn, Failure> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Edn, Failure> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Edn, Failure> {
+        if v > std::i64::MAX as u64 {
+            Ok(Edn::BigInt(v))
+        } else {
+            self.serialize_i64(v as i64)
+        }
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Edn, Failure> {
+        self.serialize_f64(f64::from(v))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Edn, Failure> {
+        Ok(Edn::Float(v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Edn, Failure> {
+        Ok(Edn::String(v.to_string()))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Edn, Failure> {
+        Ok(Edn::String(v.to_string()))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Edn, Failure> {
+        Ok(Edn::Vector(
+            v.iter()
+                .map(|byte| Edn::Integer(i64::from(*byte)))
+                .collect(),
+        ))
+    }
+
+    fn serialize_none(self) -> Result<Edn, Failure> {
+        Ok(Edn::Nil)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Edn, Failure> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Edn, Failure> {
+        Ok(Edn::Nil)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Edn, Failure> {
+        Ok(Edn::Nil)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<Edn, Failure> {
+        Ok(Edn::Keyword(variant.to_string()))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Edn, Failure> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Edn, Failure> {
+        let form = value.serialize(Serializer)?;
+
+        if name == "Value" {
+            Ok(value_form(variant, form))
+        } else {
+            Ok(variant_form(variant, form))
+        }
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SerializeVector, Failure> {
+        Ok(SerializeVector(Vec::with_capacity(len.unwrap_or(0))))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SerializeVector, Failure> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SerializeVector, Failure> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SerializeVariant, Failure> {
+        Ok(SerializeVariant {
+            variant,
+            form: Edn::Vector(Vec::with_capacity(len)),
+        })
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<SerializeMap, Failure> {
+        Ok(SerializeMap {
+            entries: Vec::with_capacity(len.unwrap_or(0)),
+            key: None,
+        })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<SerializeMap, Failure> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SerializeVariant, Failure> {
+        Ok(SerializeVariant {
+            variant,
+            form: Edn::Map(Vec::with_capacity(len)),
+        })
+    }
+}
+
+struct SerializeVector(Vec<Edn>);
+
+impl ser::SerializeSeq for SerializeVector {
+    type Ok = Edn;
+    type Error = Failure;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Failure> {
+        self.0.push(value.serialize(Serializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Edn, Failure> {
+        Ok(Edn::Vector(self.0))
+    }
+}
+
+impl ser::SerializeTuple for SerializeVector {
+    type Ok = Edn;
+    type Error = Failure;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Failure> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Edn, Failure> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for SerializeVector {
+    type Ok = Edn;
+    type Error = Failure;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Failure> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Edn, Failure> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+struct SerializeMap {
+    entries: Vec<(Edn, Edn)>,
+    key: Option<Edn>,
+}
+
+impl ser::SerializeMap for SerializeMap {
+    type Ok = Edn;
+    type Error = Failure;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Failure> {
+        self.key = Some(key.serialize(Serializer)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Failure> {
+        match self.key.take() {
+            None => Err(Failure("Map value serialized before its key.".to_string())),
+            Some(key) => {
+                self.entries.push((key, value.serialize(Serializer)?));
+                Ok(())
+            }
+        }
+    }
+
+    fn end(self) -> Result<Edn, Failure> {
+        Ok(Edn::Map(self.entries))
+    }
+}
+
+impl ser::SerializeStruct for SerializeMap {
+    type Ok = Edn;
+    type Error = Failure;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Failure> {
+        self.entries
+            .push((Edn::Keyword(key.to_string()), value.serialize(Serializer)?));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Edn, Failure> {
+        Ok(Edn::Map(self.entries))
+    }
+}
+
+// Tuple and struct variants, whose contents are collected into a
+// vector or a map, respectively.
+struct SerializeVariant {
+    variant: &'static str,
+    form: Edn,
+}
+
+impl ser::SerializeTupleVariant for SerializeVariant {
+    type Ok = Edn;
+    type Error = Failure;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Failure> {
+        if let Edn::Vector(ref mut forms) = self.form {
+            forms.push(value.serialize(Serializer)?);
+        }
+
+        Ok(())
+    }
+
+    fn end(self) -> Result<Edn, Failure> {
+        Ok(variant_form(self.variant, self.form))
+    }
+}
+
+impl ser::SerializeStructVariant for SerializeVariant {
+    type Ok = Edn;
+    type Error = Failure;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Failure> {
+        if let Edn::Map(ref mut entries) = self.form {
+            entries.push((Edn::Keyword(key.to_string()), value.serialize(Serializer)?));
+        }
+
+        Ok(())
+    }
+
+    fn end(self) -> Result<Edn, Failure> {
+        Ok(variant_form(self.variant, self.form))
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Edn {
+    type Error = Failure;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Failure> {
+        match self {
+            Edn::Nil => visitor.visit_unit(),
+            Edn::Bool(boolean) => visitor.visit_bool(boolean),
+            Edn::Integer(number) => visitor.visit_i64(number),
+            Edn::BigInt(number) => visitor.visit_u64(number),
+            Edn::Ratio(numerator, denominator) => visit_seq(
+                vec![Edn::Integer(numerator), Edn::Integer(denominator)],
+                visitor,
+            ),
+            Edn::Float(number) => visitor.visit_f64(number),
+            Edn::String(string) | Edn::Symbol(string) => visitor.visit_string(string),
+            // Keywords are read as the strings naming attributes.
+            Edn::Keyword(keyword) => visitor.visit_string(format!(":{}", keyword)),
+            Edn::Tagged(_tag, form) => (*form).deserialize_any(visitor),
+            Edn::List(forms) | Edn::Vector(forms) => visit_seq(forms, visitor),
+            Edn::Map(entries) => visit_map(entries, visitor),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Failure> {
+        match self {
+            Edn::Nil => visitor.visit_none(),
+            form => visitor.visit_some(form),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Failure> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Failure> {
+        if is_value(name, variants) {
+            visitor.visit_enum(value_variant(self)?)
+        } else {
+            visitor.visit_enum(variant(self)?)
+        }
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Failure> {
+        match self {
+            Edn::Keyword(name) | Edn::String(name) | Edn::Symbol(name) => {
+                visitor.visit_string(name)
+            }
+            form => form.deserialize_any(visitor),
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 u8 u16 u32 u64 f32 f64 char str string bytes
+        byte_buf unit unit_struct seq tuple tuple_struct map struct ignored_any
+    }
+}
+
+fn visit_seq<'de, V: Visitor<'de>>(forms: Vec<Edn>, visitor: V) -> Result<V::Value, Failure> {
+    let len = forms.len();
+    let mut seq = Seq(forms.into_iter());
+    let value = visitor.visit_seq(&mut seq)?;
+
+    if seq.0.len() == 0 {
+        Ok(value)
+    } else {
+        Err(de::Error::invalid_length(len, &"fewer elements"))
+    }
+}
+
+fn visit_map<'de, V: Visitor<'de>>(
+    entries: Vec<(Edn, Edn)>,
+    visitor: V,
+) -> Result<V::Value, Failure> {
+    let len = entries.len();
+    let mut map = Entries {
+        entries: entries.into_iter(),
+        value: None,
+    };
+    let value = visitor.visit_map(&mut map)?;
+
+    if map.entries.len() == 0 {
+        Ok(value)
+    } else {
+        Err(de::Error::invalid_length(len, &"fewer entries"))
+    }
+}
+
+struct Seq(std::vec::IntoIter<Edn>);
+
+impl<'de> de::SeqAccess<'de> for Seq {
+    type Error = Failure;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Failure> {
+        match self.0.next() {
+            None => Ok(None),
+            Some(form) => seed.deserialize(form).map(Some),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.0.len())
+    }
+}
+
+struct Entries {
+    entries: std::vec::IntoIter<(Edn, Edn)>,
+    value: Option<Edn>,
+}
+
+impl<'de> de::MapAccess<'de> for Entries {
+    type Error = Failure;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Failure> {
+        match self.entries.next() {
+            None => Ok(None),
+            Some((key, value)) => {
+                self.value = Some(value);
+                seed.deserialize(key).map(Some)
+            }
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Failure> {
+        match self.value.take() {
+            None => Err(Failure(
+                "Map key deserialized without its value.".to_string(),
+            )),
+            Some(value) => seed.deserialize(value),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.entries.len())
+    }
+}
+
+// An enum variant, along with its contents unless it is a unit
+// variant.
+struct Variant {
+    name: String,
+    form: Option<Edn>,
+}
+
+fn variant(form: Edn) -> Result<Variant, Failure> {
+    match form {
+        Edn::Keyword(name) | Edn::String(name) | Edn::Symbol(name) => {
+            Ok(Variant { name, form: None })
+        }
+        Edn::Map(mut entries) => {
+            if entries.len() != 1 {
+                return Err(Failure(format!(
+                    "Variants must be maps with a single entry, not {}.",
+                    entries.len()
+                )));
+            }
+
+            match entries.remove(0) {
+                (Edn::Keyword(name), form)
+                | (Edn::String(name), form)
+                | (Edn::Symbol(name), form) => Ok(Variant {
+                    name,
+                    form: Some(form),
+                }),
+                (key, _) => Err(Failure(format!("{} does not name a variant.", key))),
+            }
+        }
+        other => Err(Failure(format!("Expected a variant, found {}.", other))),
+    }
+}
+
+fn value_variant(form: Edn) -> Result<Variant, Failure> {
+    let (name, form) = match form {
+        Edn::Keyword(keyword) => ("Aid", Edn::String(format!(":{}", keyword))),
+        string @ Edn::String(_) => ("String", string),
+        boolean @ Edn::Bool(_) => ("Bool", boolean),
+        number @ Edn::Integer(_) => ("Number", number),
+        Edn::Ratio(numerator, denominator) => (
+            "Rational32",
+            Edn::Vector(vec![Edn::Integer(numerator), Edn::Integer(denominator)]),
+        ),
+        Edn::Tagged(tag, form) => match (tag.as_str(), *form) {
+            (AID_TAG, form) => ("Aid", form),
+            (EID_TAG, form) => ("Eid", form),
+            ("uuid", form) => ("Uuid", form),
+            ("inst", Edn::String(instant)) => match parse_instant(&instant) {
+                None => return Err(Failure(format!("Invalid instant {}.", instant))),
+                Some(millis) => ("Instant", Edn::Integer(millis as i64)),
+            },
+            (tag, form) => return Err(Failure(format!("Unknown tagged value #{} {}.", tag, form))),
+        },
+        map @ Edn::Map(_) => return variant(map),
+        other => return Err(Failure(format!("{} is not a valid value.", other))),
+    };
+
+    Ok(Variant {
+        name: name.to_string(),
+        form: Some(form),
+    })
+}
+
+impl<'de> de::EnumAccess<'de> for Variant {
+    type Error = Failure;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self), Failure> {
+        let name = seed.deserialize(Edn::String(self.name.clone()))?;
+        Ok((name, self))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for Variant {
+    type Error = Failure;
+
+    fn unit_variant(self) -> Result<(), Failure> {
+        match self.form {
+            None | Some(Edn::Nil) => Ok(()),
+            Some(form) => Err(Failure(format!(
+                "Unit variant {} can't hold {}.",
+                self.name, form
+            ))),
+        }
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, Failure> {
+        seed.deserialize(self.form.unwrap_or(Edn::Nil))
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, Failure> {
+        match self.form {
+            Some(Edn::Vector(forms)) | Some(Edn::List(forms)) => visit_seq(forms, visitor),
+            _ => Err(Failure(format!(
+                "Tuple variant {} must hold a vector.",
+                self.name
+            ))),
+        }
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Failure> {
+        match self.form {
+            Some(Edn::Map(entries)) => visit_map(entries, visitor),
+            _ => Err(Failure(format!(
+                "Struct variant {} must hold a map.",
+                self.name
+            ))),
+        }
+    }
+}
+
+const MILLIS_PER_DAY: i64 = 86_400_000;
+
+// Days since the epoch of a date in the proleptic Gregorian calendar,
+// see http://howardhinnant.github.io/date_algorithms.html.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = if year >= 0 { year } else { year - 399 } / 400;
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    era * 146_097 + day_of_era - 719_468
+}
+
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = if days >= 0 { days } else { days - 146_096 } / 146_097;
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+
+    (
+        year_of_era + era * 400 + if month <= 2 { 1 } else { 0 },
+        month,
+        day,
+    )
+}
+
+/// Formats milliseconds since the epoch as an RFC 3339 timestamp in
+/// UTC, as expected by `#inst` literals.
+pub fn format_instant(millis: u64) -> String {
+    let millis = millis as i64;
+    let (year, month, day) = civil_from_days(millis / MILLIS_PER_DAY);
+    let of_day = millis % MILLIS_PER_DAY;
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        of_day / 3_600_000,
+        of_day / 60_000 % 60,
+        of_day / 1000 % 60,
+        of_day % 1000
+    )
+}
+
+/// Parses an RFC 3339 timestamp into milliseconds since the epoch.
+/// Everything after the date is optional, and precision beyond
+/// milliseconds is dropped. Instants before the epoch are rejected.
+pub fn parse_instant(string: &str) -> Option<u64> {
+    let at = |index: usize| string.as_bytes().get(index).cloned();
+    let field = |from: usize, len: usize| -> Option<i64> {
+        let digits = string.get(from..from + len)?;
+
+        if digits.bytes().all(|byte| byte.is_ascii_digit()) {
+            digits.parse().ok()
+        } else {
+            None
+        }
+    };
+
+    let (year, month, day) = (field(0, 4)?, field(5, 2)?, field(8, 2)?);
+    if at(4) != Some(b'-') || at(7) != Some(b'-') || month < 1 || month > 12 || day < 1 || day > 31
+    {
+        return None;
+    }
+
+    let mut millis = days_from_civil(year, month, day) * MILLIS_PER_DAY;
+    let mut index = 10;
+
+    if at(index) == Some(b'T') {
+        let (hours, minutes) = (field(11, 2)?, field(14, 2)?);
+        if at(13) != Some(b':') || hours > 23 || minutes > 59 {
+            return None;
+        }
+
+        millis += (hours * 60 + minutes) * 60_000;
+        index = 16;
+
+        if at(index) == Some(b':') {
+            let seconds = field(17, 2)?;
+            if seconds > 59 {
+                return None;
+            }
+
+            millis += seconds * 1000;
+            index = 19;
+
+            if at(index) == Some(b'.') {
+                index += 1;
+
+                let start = index;
+                while at(index).map_or(false, |byte| byte.is_ascii_digit()) {
+                    index += 1;
+                }
+
+                let fraction = &string[start..index];
+                if fraction.is_empty() {
+                    return None;
+                }
+
+                let fraction = format!("{:0<3}", &fraction[..fraction.len().min(3)]);
+                millis += fraction.parse::<i64>().ok()?;
+            }
+        }
+    }
+
+    match &string[index..] {
+        "" | "Z" => {}
+        offset => {
+            let sign = match at(index) {
+                Some(b'+') => 1,
+                Some(b'-') => -1,
+                _ => return None,
+            };
+
+            let (hours, minutes) = (field(index + 1, 2)?, field(index + 4, 2)?);
+            if offset.len() != 6 || at(index + 3) != Some(b':') {
+                return None;
+            }
+
+            millis -= sign * (hours * 60 + minutes) * 60_000;
+        }
+    }
+
+    if millis >= 0 {
+        Some(millis as u64)
+    } else {
+        None
+    }
+}
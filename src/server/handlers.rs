@@ -0,0 +1,617 @@
+//! Handlers for requests concerning the state of a server, shared by
+//! everything driving one, such as the server binary or simulations.
+//! Requests concerning the process a server runs in, e.g. its
+//! connections, its journal, or its lifecycle, are left to whoever
+//! drives it.
+
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::hash::Hash;
+use std::rc::Rc;
+
+use timely::communication::Allocate;
+use timely::dataflow::channels::pact::{Exchange, Pipeline};
+use timely::dataflow::operators::generic::{Operator, OutputHandle};
+use timely::dataflow::operators::Probe;
+use timely::logging::{Logger, TimelyEvent};
+use timely::progress::Timestamp;
+use timely::worker::Worker;
+
+use differential_dataflow::lattice::Lattice;
+use differential_dataflow::logging::DifferentialEvent;
+use differential_dataflow::operators::Consolidate;
+use differential_dataflow::{AsCollection, ExchangeData};
+
+use crate::operators::{Coalesce, CountBy, Snapshot, Validated};
+use crate::server::{CreateAttribute, Interest, Request, RequestId, Server, TransactSync};
+use crate::server::{TxId, Visibility};
+#[cfg(feature = "serde_json")]
+use crate::sinks::format_fields;
+use crate::sinks::{format_tuple, keyed_changes, sort_by_fields};
+use crate::sinks::{
+    Limit, Limiter, ProgressTracker, Sink, Sinkable, SinkingContext, Transactional,
+};
+use crate::timestamp::{Coarsen, Rewind};
+use crate::{Aid, Client, Error, Output, ResultDiff, Time};
+
+/// The command a request is handled as part of, and what is left to
+/// do once all of its requests have been handled.
+pub struct Handling<Token, T, F> {
+    /// Worker holding the connection of the issuing client.
+    pub owner: usize,
+    /// The issuing client.
+    pub client: Token,
+    /// The transaction the command is handled as.
+    pub last_tx: TxId,
+    /// Id of the command, if the client wants it acknowledged.
+    pub id: Option<RequestId>,
+    /// Hands outputs to the clients they are addressed to.
+    pub send: F,
+    /// Relations whose dataflows have to be torn down on all
+    /// workers, e.g. because they exceeded their limits.
+    pub cancellations: Rc<RefCell<Vec<String>>>,
+    /// Time at which the command's transactions will have been
+    /// checked against all constraints, if they have to be.
+    pub deferred: Option<T>,
+    /// Time of the command's synchronous transactions, and when they
+    /// count as visible.
+    pub synced: Option<(T, Visibility)>,
+    /// Whether an interest of the command is held back until its
+    /// causality token has passed. The command is then acknowledged
+    /// once the interest is issued again, under the same id.
+    pub held_back: bool,
+    /// Interests to issue again, along with the worker that has to
+    /// do so.
+    pub reissued: Vec<(usize, Interest)>,
+}
+
+impl<Token, T, F> Handling<Token, T, F> {
+    /// Starts handling a command.
+    pub fn new(
+        owner: usize,
+        client: Token,
+        last_tx: TxId,
+        id: Option<RequestId>,
+        send: F,
+        cancellations: Rc<RefCell<Vec<String>>>,
+    ) -> Self {
+        Handling {
+            owner,
+            client,
+            last_tx,
+            id,
+            send,
+            cancellations,
+            deferred: None,
+            synced: None,
+            held_back: false,
+            reissued: Vec::new(),
+        }
+    }
+}
+
+impl<T, Token> Server<Aid, T, Token>
+where
+    T: Timestamp + Lattice + Default + Rewind + Coarsen + ExchangeData,
+    T: std::convert::Into<Time> + std::convert::From<Time>,
+    Token: Hash + Eq + Copy + Into<Client> + 'static,
+{
+    /// Handles a request on the specified worker. All workers have to
+    /// handle the same requests in the same order. Outputs are only
+    /// sent by the worker holding the issuing client's connection, or
+    /// by the one holding those of the clients they are addressed to.
+    pub fn handle<Al, F>(
+        &mut self,
+        worker: &mut Worker<Al>,
+        handling: &mut Handling<Token, T, F>,
+        req: Request<Aid>,
+    ) -> Result<(), Error>
+    where
+        Al: Allocate,
+        F: Fn(Output) + Clone + 'static,
+    {
+        let owner = handling.owner;
+        let client = handling.client;
+        let is_owner = owner == worker.index();
+
+        match req {
+            Request::Transact(tx_data) => {
+                let checked = if (is_owner || worker.index() == 0) && self.has_constraints() {
+                    Some(tx_data.clone())
+                } else {
+                    None
+                };
+
+                self.transact(tx_data, owner, worker.index()).map(|()| {
+                    if let Some(tx_data) = checked {
+                        handling.deferred =
+                            self.check_constraints(tx_data).or(handling.deferred.take());
+                    }
+                })
+            }
+            Request::TransactSync(TransactSync {
+                tx_data,
+                visibility,
+            }) => {
+                let checked = if (is_owner || worker.index() == 0) && self.has_constraints() {
+                    Some(tx_data.clone())
+                } else {
+                    None
+                };
+
+                let t = self.internal.epoch().clone();
+
+                self.transact(tx_data, owner, worker.index()).map(|()| {
+                    if let Some(tx_data) = checked {
+                        handling.deferred =
+                            self.check_constraints(tx_data).or(handling.deferred.take());
+                    }
+
+                    handling.synced = Some((t, visibility));
+                })
+            }
+            // Interests in relations on attributes that don't exist
+            // yet are held back, if late binding is enabled.
+            Request::Interest(req) => {
+                let awaits_token = req.at_least.is_some();
+
+                match self.defer_interest(owner, client, handling.id, req) {
+                    None => {
+                        handling.held_back |= awaits_token;
+                        Ok(())
+                    }
+                    Some(req) => self.handle_interest(worker, handling, req),
+                }
+            }
+            Request::InterestGroup(req) => {
+                let interests = self
+                    .interests
+                    .entry(req.name.clone())
+                    .or_insert_with(HashSet::new);
+
+                // As for single interests, we only want to setup the
+                // dataflow on the first interest.
+                let was_first = interests.is_empty();
+
+                interests.insert(client);
+
+                if !was_first {
+                    return Ok(());
+                }
+
+                let send_results = handling.send.clone();
+
+                worker.dataflow::<T, _, _>(|scope| {
+                    let grouped = self.interest_group(req.name.clone(), &req.members, scope)?;
+
+                    let pact = Exchange::new(move |_| owner as u64);
+                    let mut vector = Vec::new();
+
+                    grouped
+                        .transactional(pact, req.name)
+                        .unary(Pipeline, "GroupResults", move |_cap, _info| {
+                            move |input, _output: &mut OutputHandle<_, ResultDiff<T>, _>| {
+                                input.for_each(|_time, data| {
+                                    data.swap(&mut vector);
+
+                                    for out in vector.drain(..) {
+                                        send_results(out);
+                                    }
+                                });
+                            }
+                        })
+                        .probe_with(&mut self.probe);
+
+                    Ok(())
+                })
+            }
+            Request::Uninterest(name) => self.uninterest(client, &name),
+            Request::Cancel(name) => self.cancel(&name),
+            Request::Register(req) => {
+                let names = req.rules.iter().map(|rule| rule.name.clone()).collect();
+                self.register(req).map(|()| self.claim_rules(client, names))
+            }
+            Request::RegisterQuery(req) => {
+                let names = vec![req.name.clone()];
+                self.register_query(req)
+                    .map(|()| self.claim_rules(client, names))
+            }
+            #[cfg(feature = "serde_json")]
+            Request::Validate(req) => {
+                let diagnostics = worker.dataflow::<T, _, _>(|scope| self.validate(scope, &req));
+
+                if is_owner {
+                    let message = serde_json::json!({
+                        "category": "df/validate",
+                        "diagnostics": diagnostics,
+                    });
+
+                    (handling.send)(Output::Message(client.into(), message));
+                }
+
+                Ok(())
+            }
+            Request::SwapRule(rule) => {
+                worker.dataflow::<T, _, _>(|scope| self.swap_rule(rule, scope))
+            }
+            // Subscribers are switched over by issuing their interests
+            // again, on their behalf.
+            Request::CompleteSwap(name) => self.complete_swap(&name).map(|reissued| {
+                handling.reissued.extend(reissued);
+            }),
+            Request::CreateAttribute(CreateAttribute {
+                name,
+                config,
+                backfill: None,
+            }) => worker.dataflow::<T, _, _>(|scope| self.create_attribute(scope, name, config)),
+            // Sources only introduce data at real times.
+            Request::CreateAttribute(CreateAttribute { name, .. }) => Err(Error::unsupported(
+                format!("Attribute {} can't be backfilled on logical time.", name),
+            )),
+            Request::AliasAttribute(req) => self.alias_attribute(req),
+            Request::MigrateAttribute(req) => {
+                worker.dataflow::<T, _, _>(|scope| self.migrate_attribute(scope, req))
+            }
+            Request::CopyAttribute(req) => {
+                worker.dataflow::<T, _, _>(|scope| self.copy_attribute(scope, req))
+            }
+            Request::DeriveAttribute(req) => {
+                worker.dataflow::<T, _, _>(|scope| self.derive_attribute(scope, req))
+            }
+            Request::RegisterTrigger(req) => {
+                worker.dataflow::<T, _, _>(|scope| self.register_trigger(scope, req))
+            }
+            Request::InsertInto(req) => {
+                worker.dataflow::<T, _, _>(|scope| self.insert_into(scope, req))
+            }
+            Request::RegisterConstraint(req) => {
+                worker.dataflow::<T, _, _>(|scope| self.register_constraint(scope, req))
+            }
+            Request::Excise(req) => self.excise(req),
+            Request::AdvanceDomain(name, next) => self.advance_domain(name, next.into()),
+            Request::CloseInput(name) => self.internal.close_input(name),
+            Request::Disconnect => self.disconnect_client(client),
+            Request::Heartbeat => Ok(()),
+            Request::EndSession => self.end_session(client),
+            Request::Hibernate(name) => self.hibernate(&name),
+            Request::Evict(name) => self.evict(&name).map(|eviction| {
+                // Every worker informs the clients it holds
+                // connections to. Hibernation is announced via a
+                // message, which requires JSON support.
+                for (subscriber, token) in eviction.clients {
+                    if subscriber != worker.index() {
+                        continue;
+                    }
+
+                    let output = match eviction.error {
+                        Some(ref error) => {
+                            Output::Error(token.into(), error.clone(), handling.last_tx, None)
+                        }
+                        #[cfg(feature = "serde_json")]
+                        None => Output::Message(
+                            token.into(),
+                            serde_json::json!({"category": "df/hibernated", "name": name}),
+                        ),
+                        #[cfg(not(feature = "serde_json"))]
+                        None => continue,
+                    };
+
+                    (handling.send)(output);
+                }
+            }),
+            Request::MemoryUsage(usage) => {
+                self.note_memory_usage(usage);
+                Ok(())
+            }
+            Request::Setup => Err(Error::unsupported("Setup requests.")),
+            #[cfg(feature = "serde_json")]
+            Request::AllocateIds(count) => {
+                // Only the owner draws from its range, all others must
+                // leave theirs untouched.
+                if !is_owner {
+                    return Ok(());
+                }
+
+                self.ids.allocate(count).map(|ids| {
+                    let message = serde_json::json!({
+                        "category": "df/ids",
+                        "ids": ids,
+                    });
+
+                    (handling.send)(Output::Message(client.into(), message));
+                })
+            }
+            #[cfg(feature = "serde_json")]
+            Request::Status => {
+                let status = serde_json::json!({
+                    "category": "df/status",
+                    "message": "running",
+                });
+
+                (handling.send)(Output::Message(client.into(), status));
+
+                Ok(())
+            }
+            #[cfg(feature = "serde_json")]
+            Request::Inspect(what) => self.inspect(&what).map(|report| {
+                if is_owner {
+                    let message = serde_json::json!({
+                        "category": "df/inspect",
+                        "worker": worker.index(),
+                        "report": serde_json::to_value(report).expect("failed to serialize report"),
+                    });
+
+                    (handling.send)(Output::Message(client.into(), message));
+                }
+            }),
+            Request::Configure(req) => self.configure(req),
+            other => Err(Error::unsupported(format!(
+                "{:?} has to be handled by the process running the server.",
+                other
+            ))),
+        }
+    }
+
+    /// Handles an Interest request that is not held back any longer,
+    /// setting up a dataflow delivering results to clients, unless
+    /// one is running already.
+    fn handle_interest<Al, F>(
+        &mut self,
+        worker: &mut Worker<Al>,
+        handling: &mut Handling<Token, T, F>,
+        req: Interest,
+    ) -> Result<(), Error>
+    where
+        Al: Allocate,
+        F: Fn(Output) + Clone + 'static,
+    {
+        let owner = handling.owner;
+        let is_owner = owner == worker.index();
+        let client: Client = handling.client.into();
+        let last_tx = handling.last_tx;
+        let id = handling.id;
+
+        // Identical rules share a single dataflow.
+        let req = self.share_interest(handling.client, req);
+
+        let interests = self
+            .interests
+            .entry(req.name.clone())
+            .or_insert_with(HashSet::new);
+
+        // We need to check this, because we only want to setup the
+        // dataflow on the first interest.
+        let was_first = interests.is_empty();
+
+        // All workers keep track of every client's interests, s.t.
+        // they know when to clean up unused dataflows.
+        interests.insert(handling.client);
+
+        if !was_first {
+            return Ok(());
+        }
+
+        let send_results = handling.send.clone();
+
+        let disable_logging = req.disable_logging.unwrap_or(false);
+        let mut timely_logger = None;
+        let mut differential_logger = None;
+
+        if disable_logging {
+            info!("Disabling logging");
+            timely_logger = worker.log_register().remove("timely");
+            differential_logger = worker.log_register().remove("differential/arrange");
+        }
+
+        // Snapshots comprise everything before the current epoch.
+        let as_of = self.internal.epoch().clone();
+        let snapshot = req.snapshot.unwrap_or(false);
+        let progress = req.progress.unwrap_or(false);
+        let limits = req.limits.clone();
+        let order_by = req.order_by.clone();
+        let key_by = req.key_by.clone();
+        let formats = req.format.clone().unwrap_or_default();
+        let cancellations = handling.cancellations.clone();
+
+        let result = worker.dataflow::<T, _, _>(|scope| {
+            let sink_context: SinkingContext = (&req).into();
+
+            if let Some(ref fields) = req.count_by {
+                self.check_fields(&req.name, fields)?;
+            }
+
+            let relation = self.interest(req.name, scope)?;
+
+            let relation = match req.count_by {
+                None => relation,
+                Some(ref fields) => relation.inner.count_by(fields).as_collection(),
+            };
+
+            let delayed = match req.granularity {
+                None => relation.consolidate(),
+                Some(granularity) => {
+                    let granularity: T = granularity.into();
+                    relation
+                        .delay(move |t| t.coarsen(&granularity))
+                        .consolidate()
+                }
+            };
+
+            // Results of transactions violating constraints are never
+            // revealed.
+            let delayed = if self.has_constraints() {
+                delayed.inner.validated(self.validation()).as_collection()
+            } else {
+                delayed
+            };
+
+            let delayed = if snapshot {
+                delayed.inner.snapshot(as_of.clone()).as_collection()
+            } else {
+                delayed
+            };
+
+            let delayed = match req.coalesce {
+                None => delayed,
+                Some(ref policy) => {
+                    let scheduler = Rc::downgrade(&self.scheduler);
+                    delayed.inner.coalesce(policy, scheduler).as_collection()
+                }
+            };
+
+            let delayed = if formats.is_empty() {
+                delayed
+            } else {
+                let formats = formats.clone();
+                delayed.map(move |tuple| format_tuple(&formats, tuple))
+            };
+
+            // Only the owning worker informs the client about progress.
+            let mut tracker = ProgressTracker::new(
+                sink_context.name.clone(),
+                if snapshot && is_owner {
+                    Some(as_of.clone())
+                } else {
+                    None
+                },
+                progress && is_owner,
+            );
+
+            let name = sink_context.name.clone();
+
+            // Limits are enforced on the owning worker, before
+            // anything is handed to a sink, s.t. results exceeding
+            // them are never sent.
+            let delayed = match limits {
+                None => delayed,
+                Some(limits) => {
+                    let send_errors = send_results.clone();
+                    let name = name.clone();
+                    let limiter = if is_owner {
+                        Some(Limiter::new(limits, as_of.clone()))
+                    } else {
+                        None
+                    };
+
+                    delayed
+                        .inner
+                        .limit(
+                            Exchange::new(move |_| owner as u64),
+                            limiter,
+                            move |error| {
+                                send_errors(Output::Error(client, error, last_tx, id));
+                                cancellations.borrow_mut().push(name.clone());
+                            },
+                        )
+                        .as_collection()
+                }
+            };
+
+            let pact = Exchange::new(move |_| owner as u64);
+
+            let sink = match req.sink {
+                #[cfg(feature = "serde_json")]
+                Some(Sink::Named(mut named)) => {
+                    format_fields(&formats, &mut named.fields);
+                    Some(Sink::Named(named))
+                }
+                #[cfg(feature = "serde_json")]
+                Some(Sink::AssocIn(mut assoc_in)) => {
+                    // Cardinality one attributes resolve to their
+                    // latest value, as per the schema.
+                    assoc_in.cardinality_one = self.cardinality_one(&name);
+                    Some(Sink::AssocIn(assoc_in))
+                }
+                other => other,
+            };
+
+            match sink {
+                Some(sink) => {
+                    let sunk = sink.sink(&delayed.inner, pact, &mut self.probe, sink_context)?;
+
+                    if let Some(sunk) = sunk {
+                        let mut vector = Vec::new();
+                        sunk.unary_frontier(Pipeline, "SinkResults", move |_cap, _info| {
+                            move |input, _output: &mut OutputHandle<_, ResultDiff<T>, _>| {
+                                input.for_each(|_time, data| {
+                                    data.swap(&mut vector);
+
+                                    for out in vector.drain(..) {
+                                        send_results(out);
+                                    }
+                                });
+
+                                for out in tracker.observe(input.frontier.frontier()) {
+                                    send_results(out);
+                                }
+                            }
+                        })
+                        .probe_with(&mut self.probe);
+                    }
+
+                    Ok(())
+                }
+                None => {
+                    delayed
+                        .inner
+                        .unary_frontier(pact, "ResultsRecv", move |_cap, _info| {
+                            move |input, _output: &mut OutputHandle<_, ResultDiff<T>, _>| {
+                                // Due to the exchange pact, this closure
+                                // is only executed by the owning worker.
+
+                                input.for_each(|_time, data| {
+                                    let mut data = data
+                                        .iter()
+                                        .map(|(tuple, t, diff)| {
+                                            (tuple.clone(), t.clone().into(), *diff)
+                                        })
+                                        .collect::<Vec<ResultDiff<Time>>>();
+
+                                    if let Some(ref fields) = order_by {
+                                        sort_by_fields(&mut data, fields);
+                                    }
+
+                                    let out = match key_by {
+                                        None => Output::QueryDiff(name.clone(), data),
+                                        Some(ref fields) => Output::KeyedDiff(
+                                            name.clone(),
+                                            keyed_changes(data, fields),
+                                        ),
+                                    };
+
+                                    send_results(out);
+                                });
+
+                                for out in tracker.observe(input.frontier.frontier()) {
+                                    send_results(out);
+                                }
+                            }
+                        })
+                        .probe_with(&mut self.probe);
+
+                    Ok(())
+                }
+            }
+        });
+
+        if disable_logging {
+            if let Some(logger) = timely_logger {
+                if let Ok(logger) = logger.downcast::<Logger<TimelyEvent>>() {
+                    worker
+                        .log_register()
+                        .insert_logger::<TimelyEvent>("timely", *logger);
+                }
+            }
+
+            if let Some(logger) = differential_logger {
+                if let Ok(logger) = logger.downcast::<Logger<DifferentialEvent>>() {
+                    worker
+                        .log_register()
+                        .insert_logger::<DifferentialEvent>("differential/arrange", *logger);
+                }
+            }
+        }
+
+        result
+    }
+}
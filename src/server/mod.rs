@@ -1,39 +1,65 @@
 //! Server logic for driving the library via commands.
 
 use std::cell::RefCell;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use std::hash::Hash;
+use std::path::{Component, Path, PathBuf};
 use std::rc::Rc;
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
 use timely::communication::Allocate;
+use timely::dataflow::channels::pact::Exchange;
 use timely::dataflow::operators::capture::event::link::EventLink;
-use timely::dataflow::operators::UnorderedInput;
-use timely::dataflow::{ProbeHandle, Scope};
+use timely::dataflow::operators::generic::Operator;
+use timely::dataflow::operators::Inspect as _;
+use timely::dataflow::operators::{Broadcast, Concatenate, Map, Probe, UnorderedInput};
+use timely::dataflow::{ProbeHandle, Scope, Stream};
 use timely::logging::{BatchLogger, TimelyEvent};
+use timely::order::PartialOrder;
 use timely::progress::Timestamp;
 use timely::worker::Worker;
 
 use differential_dataflow::collection::{AsCollection, Collection};
+use differential_dataflow::consolidation::consolidate;
 use differential_dataflow::lattice::Lattice;
 use differential_dataflow::logging::DifferentialEvent;
-use differential_dataflow::operators::Threshold;
+use differential_dataflow::operators::arrange::ArrangeBySelf;
+use differential_dataflow::operators::{Consolidate, Threshold};
+use differential_dataflow::trace::TraceReader;
 use differential_dataflow::ExchangeData;
 
-use crate::domain::{AsSingletonDomain, Domain};
+use crate::clock::{self, Instant};
+use crate::domain::{trace_stats, AsSingletonDomain, Domain, TraceStats};
+use crate::frontends::{self, Language};
+use crate::ids::{IdAllocator, IdStrategy};
 use crate::logging::DeclarativeEvent;
-use crate::operators::LastWriteWins;
+use crate::operators::{CoalescePolicy, Expire, LastWriteWins, Summarize, Validation};
+use crate::plan::{Function, Implementable, Predicate};
 use crate::scheduling::Scheduler;
-use crate::sinks::Sink;
-use crate::sources::{Source, Sourceable, SourcingContext};
+use crate::sinks::{Format, Sink};
+use crate::sources::{LateData, Partitioning, Source, Sourceable, SourcingContext};
+#[cfg(feature = "store")]
+use crate::store::Store;
 use crate::Rule;
 use crate::{
-    implement, implement_neu, AttributeConfig, IndexDirection, InputSemantics, ShutdownHandle,
+    collect_dependencies, collect_dependencies_with, implement_neu_with, implement_with,
+    AttributeConfig, IndexDirection, InputSemantics, ShutdownHandle, TimeToLive, TraceKeyHandle,
 };
-use crate::{AsAid, Datom, Error, Rewind, Time, Value};
+use crate::{Aid, AsAid, Datom, Error, Plan, PlanError, ResultDiff, Rewind, SourceError};
+use crate::{Time, TxError, Value};
 
-/// Server configuration.
+#[cfg(feature = "serde_json")]
+pub mod backpressure;
+pub mod edn;
+pub mod handlers;
+pub mod simulation;
+#[cfg(feature = "serde_json")]
+pub mod versioning;
+
+/// Server configuration. Fields missing from a configuration file
+/// take on their default values.
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Configuration {
     /// Automatic domain tick interval.
     pub tick: Option<Duration>,
@@ -43,6 +69,46 @@ pub struct Configuration {
     pub enable_logging: bool,
     /// Should queries use the optimizer during implementation?
     pub enable_optimizer: bool,
+    /// How entity ids come about.
+    #[serde(default)]
+    pub id_strategy: IdStrategy,
+    /// How long clients may stay silent, before everything they own
+    /// is cleaned up. If set, disconnected clients' state outlives
+    /// them for this long as well. Otherwise, state is cleaned up
+    /// upon disconnecting.
+    #[serde(default)]
+    pub session_timeout: Option<Duration>,
+    /// How transacted datoms are split up between workers. Under
+    /// entity partitioning, every worker introduces the datoms about
+    /// the entities it holds in forward indices, avoiding an exchange.
+    #[serde(default)]
+    pub tx_partitioning: Partitioning,
+    /// Should interests in relations depending on attributes that
+    /// don't exist yet be held back until they are created, rather
+    /// than rejected? This allows schema and queries to be
+    /// registered in any order.
+    #[serde(default)]
+    pub late_binding: bool,
+    /// How long dataflows may go without connected subscribers before
+    /// they are torn down. Their rules remain registered for as long
+    /// as the sessions owning them last, and are implemented again
+    /// on the next interest. Only takes effect if sessions outlive
+    /// their connections.
+    #[serde(default)]
+    pub hibernate_after: Option<Duration>,
+    /// Directory holding stored attributes, in a separate store per
+    /// worker. Requires the `store` feature.
+    #[serde(default)]
+    pub store: Option<String>,
+    /// Limits on transactions introduced together, at a single
+    /// timestamp. Without these, every transaction gets its own.
+    #[serde(default)]
+    pub group_commit: Option<GroupCommit>,
+    /// Directory that clients may export state to. Paths named in
+    /// ExportState requests are resolved within it. Without it,
+    /// exports are rejected.
+    #[serde(default)]
+    pub export_dir: Option<String>,
 }
 
 impl Default for Configuration {
@@ -52,6 +118,14 @@ impl Default for Configuration {
             manual_advance: false,
             enable_logging: false,
             enable_optimizer: false,
+            id_strategy: IdStrategy::ClientSupplied,
+            session_timeout: None,
+            tx_partitioning: Partitioning::Single,
+            late_binding: false,
+            hibernate_after: None,
+            store: None,
+            group_commit: None,
+            export_dir: None,
         }
     }
 }
@@ -77,6 +151,65 @@ impl Configuration {
         opts.optflag("", "enable-logging", "enable log event sources");
         opts.optflag("", "enable-optimizer", "enable WCO queries");
         opts.optflag("", "enable-meta", "enable queries on the query graph");
+        opts.optopt(
+            "",
+            "id-strategy",
+            "how entity ids come about: client, partitioned, or squuid",
+            "STRATEGY",
+        );
+        opts.optopt(
+            "",
+            "session-timeout",
+            "clean up state of clients silent for this long",
+            "SECONDS",
+        );
+        opts.optopt(
+            "",
+            "tx-partitioning",
+            "how transactions are split up between workers: single, record, or entity",
+            "PARTITIONING",
+        );
+        opts.optflag(
+            "",
+            "late-binding",
+            "defer interests until the attributes they depend on exist",
+        );
+        opts.optopt(
+            "",
+            "hibernate-after",
+            "tear down dataflows without connected subscribers after this long",
+            "SECONDS",
+        );
+        opts.optopt(
+            "",
+            "store",
+            "keep stored attributes in this directory",
+            "DIR",
+        );
+        opts.optopt(
+            "",
+            "group-commit",
+            "introduce transactions arriving within this long together",
+            "MILLIS",
+        );
+        opts.optopt(
+            "",
+            "group-commit-datoms",
+            "commit transaction groups early once they hold this many datoms",
+            "NUM",
+        );
+        opts.optopt(
+            "",
+            "group-commit-min",
+            "adapt the group commit delay to load, down to this many milliseconds",
+            "MILLIS",
+        );
+        opts.optopt(
+            "",
+            "export-dir",
+            "allow clients to export state to this directory",
+            "DIR",
+        );
 
         opts
     }
@@ -86,27 +219,210 @@ impl Configuration {
         let default: Self = Default::default();
         let opts = Self::options();
 
-        let matches = opts.parse(args)?;
+        let matches = opts.parse(args).map_err(|fail| fail.to_string())?;
+
+        let group_commit = match parse_opt(&matches, "group-commit")? {
+            None => None,
+            Some(max_delay) => Some(GroupCommit {
+                max_delay: Duration::from_millis(max_delay),
+                max_datoms: parse_opt(&matches, "group-commit-datoms")?
+                    .unwrap_or(GroupCommit::DEFAULT_MAX_DATOMS),
+                min_delay: parse_opt(&matches, "group-commit-min")?.map(Duration::from_millis),
+            }),
+        };
 
-        let tick: Option<Duration> = matches
-            .opt_str("tick")
-            .map(|x| Duration::from_secs(x.parse().expect("failed to parse tick duration")));
+        let tick: Option<Duration> = parse_opt(&matches, "tick")?.map(Duration::from_secs);
 
-        Self {
+        Ok(Self {
             tick,
             manual_advance: matches.opt_present("manual-advance"),
             enable_logging: matches.opt_present("enable-logging"),
             enable_optimizer: matches.opt_present("enable-optimizer"),
+            id_strategy: parse_opt(&matches, "id-strategy")?.unwrap_or(default.id_strategy),
+            session_timeout: parse_opt(&matches, "session-timeout")?.map(Duration::from_secs),
+            tx_partitioning: parse_opt(&matches, "tx-partitioning")?
+                .unwrap_or(default.tx_partitioning),
+            late_binding: matches.opt_present("late-binding"),
+            hibernate_after: parse_opt(&matches, "hibernate-after")?.map(Duration::from_secs),
+            store: matches.opt_str("store"),
+            group_commit,
+            export_dir: matches.opt_str("export-dir"),
+        })
+    }
+}
+
+/// Parses the value of an option, if it was provided, naming the
+/// option in case the value is malformed.
+#[cfg(feature = "getopts")]
+fn parse_opt<X>(matches: &getopts::Matches, name: &str) -> Result<Option<X>, String>
+where
+    X: std::str::FromStr,
+    X::Err: std::fmt::Display,
+{
+    matches
+        .opt_str(name)
+        .map(|x| {
+            x.parse()
+                .map_err(|err| format!("failed to parse {}: {}", name, err))
+        })
+        .transpose()
+}
+
+/// Limits on groups of transactions sharing a timestamp. Grouping
+/// many small transactions saves advancing inputs and traces for each
+/// of them, at the cost of holding them back for a little while.
+/// Transactions are still acknowledged individually, once their group
+/// is committed.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct GroupCommit {
+    /// How long the first transaction of a group may be held back.
+    pub max_delay: Duration,
+    /// How many datoms a group may hold, before it is committed
+    /// right away.
+    #[serde(default = "GroupCommit::default_max_datoms")]
+    pub max_datoms: usize,
+    /// If set, groups are held back for an adaptive delay between
+    /// this and `max_delay`, depending on the rate of transactions.
+    #[serde(default)]
+    pub min_delay: Option<Duration>,
+}
+
+impl GroupCommit {
+    /// Number of datoms committing a group, if not configured
+    /// otherwise.
+    pub const DEFAULT_MAX_DATOMS: usize = 10_000;
+
+    fn default_max_datoms() -> usize {
+        Self::DEFAULT_MAX_DATOMS
+    }
+
+    /// Returns true if a group open for `age`, holding the specified
+    /// number of datoms, is due to be committed.
+    pub fn is_due(&self, age: Duration, datoms: usize) -> bool {
+        self.is_due_after(self.max_delay, age, datoms)
+    }
+
+    /// Returns true if a group open for `age`, holding the specified
+    /// number of datoms, is due to be committed, when groups are
+    /// held back for `delay`.
+    pub fn is_due_after(&self, delay: Duration, age: Duration, datoms: usize) -> bool {
+        datoms >= self.max_datoms || age >= delay
+    }
+
+    /// Returns how long to hold back the first group.
+    pub fn initial_delay(&self) -> Duration {
+        self.min_delay.unwrap_or(self.max_delay)
+    }
+
+    /// Returns how long to hold back the next group, after one of the
+    /// specified number of transactions was committed, having been
+    /// held back for `delay`. Adaptive delays double while
+    /// transactions keep arriving together, trading latency for
+    /// throughput, and halve again as they come in one at a time.
+    pub fn next_delay(&self, delay: Duration, transactions: usize) -> Duration {
+        match self.min_delay {
+            None => self.max_delay,
+            Some(min_delay) => {
+                let next = if transactions > 1 {
+                    delay * 2
+                } else {
+                    delay / 2
+                };
+                std::cmp::min(std::cmp::max(next, min_delay), self.max_delay)
+            }
         }
     }
 }
 
+/// A partial update of the server configuration, applied at runtime
+/// without affecting existing dataflows. Fields left out remain
+/// unchanged.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Configure {
+    /// New automatic domain tick interval. A zero interval disables
+    /// ticking.
+    pub tick: Option<Duration>,
+    /// Whether clients have to call AdvanceDomain explicitely.
+    pub manual_advance: Option<bool>,
+    /// Whether queries registered from now on should use the
+    /// optimizer.
+    pub enable_optimizer: Option<bool>,
+    /// Maximum level of log messages to emit, e.g. `"debug"`. Can't
+    /// exceed the level the logger was initialized with.
+    pub log_level: Option<String>,
+    /// New trace slack for individual attributes. Compaction that
+    /// has already happened can't be undone by increasing it.
+    #[serde(default)]
+    pub trace_slack: BTreeMap<String, Option<Time>>,
+}
+
 /// Transaction ids.
 pub type TxId = u64;
 
+/// Client-chosen ids, correlating replies with the requests that
+/// caused them.
+pub type RequestId = u64;
+
+/// A batch of requests tagged with a client-chosen id. Clients may
+/// also send bare batches of requests, if they don't require replies
+/// to be correlated.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct Envelope<A: AsAid + From<&'static str>> {
+    /// An id referenced by all replies to these requests.
+    pub id: RequestId,
+    /// The version of the request format the requests are in. None
+    /// indicates the current one.
+    #[serde(default)]
+    pub version: Option<u32>,
+    /// The requests to handle, in order.
+    pub requests: Vec<Request<A>>,
+}
+
+/// How far a synchronously acknowledged transaction must have made
+/// it through the system, before it is acknowledged.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum Visibility {
+    /// The transaction's timestamp is closed on all workers. Queries
+    /// registered afterwards will see it.
+    Closed,
+    /// Every dataflow running on the server has incorporated the
+    /// transaction into its results.
+    Incorporated,
+}
+
+impl Default for Visibility {
+    fn default() -> Self {
+        Visibility::Closed
+    }
+}
+
+/// Transacts datoms, holding back the acknowledgement until the
+/// transaction has become visible. Writers can then rely on reading
+/// their own writes, no matter which worker they read from.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct TransactSync<A: AsAid> {
+    /// The datoms to transact.
+    pub tx_data: Vec<Datom<A>>,
+    /// What to wait for, before acknowledging.
+    #[serde(default)]
+    pub visibility: Visibility,
+}
+
+/// A chunk of datoms in a bulk load. Chunks are sent as binary
+/// messages in bincode encoding, which is much cheaper to decode than
+/// JSON. Each chunk is acknowledged separately, once its datoms have
+/// been transacted.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct BulkTransact<A: AsAid> {
+    /// An id referenced by all replies to this chunk.
+    pub id: RequestId,
+    /// The datoms to transact.
+    pub tx_data: Vec<Datom<A>>,
+}
+
 /// A request expressing interest in receiving results published under
 /// the specified name.
-#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Default, Serialize, Deserialize)]
 pub struct Interest {
     /// The name of a previously registered dataflow.
     pub name: String,
@@ -116,6 +432,77 @@ pub struct Interest {
     pub sink: Option<Sink>,
     /// Whether or not to log events from this dataflow.
     pub disable_logging: Option<bool>,
+    /// Whether to first deliver the current result set as a single,
+    /// consolidated snapshot, followed by an `InitialComplete`
+    /// marker, before any incremental diffs.
+    pub snapshot: Option<bool>,
+    /// Whether to send `Progress` notifications whenever results
+    /// become complete through a new frontier.
+    pub progress: Option<bool>,
+    /// Resource limits, upon violation of which the dataflow is torn
+    /// down again.
+    pub limits: Option<Limits>,
+    /// An optional policy for consolidating diffs into fewer
+    /// batches. None indicates that every batch is sent as is.
+    pub coalesce: Option<CoalescePolicy>,
+    /// Tuple fields by which to sort each batch of results before it
+    /// is sent. None indicates that batches are sent in whatever order
+    /// they were produced in.
+    pub order_by: Option<Vec<usize>>,
+    /// Formatting steps applied to each result before it is sent.
+    pub format: Option<Vec<Format>>,
+    /// Whether to only send the number of results, grouped by the
+    /// specified tuple fields. An empty list counts all results.
+    pub count_by: Option<Vec<usize>>,
+    /// Tuple fields identifying a result, by which to send each batch
+    /// as `KeyedDiff` upserts and removals, rather than as raw diffs.
+    pub key_by: Option<Vec<usize>>,
+    /// A causality token, i.e. the transaction id of an earlier
+    /// acknowledgement. The interest is held back until the
+    /// timestamp of that transaction is closed on all workers, s.t.
+    /// its results reflect the acknowledged writes.
+    #[serde(default)]
+    pub at_least: Option<TxId>,
+}
+
+/// Per-query resource limits, protecting a shared server from
+/// runaway computations.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Limits {
+    /// Maximum wall-clock time to spend on computing the initial
+    /// results.
+    pub max_runtime: Option<Duration>,
+    /// Maximum number of results to send.
+    pub max_output: Option<usize>,
+    /// Maximum estimated memory, in bytes, to hold the arranged
+    /// results in. Checked by the server, rather than while sending.
+    #[serde(default)]
+    pub max_memory: Option<usize>,
+    /// What to do once the memory budget is exceeded. Defaults to
+    /// failing.
+    #[serde(default)]
+    pub budget_policy: Option<BudgetPolicy>,
+}
+
+/// What happens to dataflows exceeding their memory budget. Either
+/// way, they are torn down for all subscribers.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum BudgetPolicy {
+    /// Subscribers receive an error.
+    Fail,
+    /// Subscribers are informed, but may subscribe again to have the
+    /// dataflow implemented anew.
+    Hibernate,
+}
+
+/// A dataflow torn down for exceeding its memory budget.
+#[derive(Clone, Debug)]
+pub struct Eviction<Token> {
+    /// The clients that were subscribed, along with the worker
+    /// holding each one's connection.
+    pub clients: Vec<(usize, Token)>,
+    /// The error to send subscribers, unless the dataflow hibernated.
+    pub error: Option<Error>,
 }
 
 impl std::convert::From<&Interest> for crate::sinks::SinkingContext {
@@ -127,6 +514,16 @@ impl std::convert::From<&Interest> for crate::sinks::SinkingContext {
     }
 }
 
+/// A request expressing interest in receiving the results of several
+/// relations together, batched per timestamp.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct InterestGroup {
+    /// A name identifying the group, under which results are sent.
+    pub name: String,
+    /// The names of previously registered dataflows.
+    pub members: Vec<String>,
+}
+
 /// A request with the intent of synthesising one or more new rules
 /// and optionally publishing one or more of them.
 #[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
@@ -137,6 +534,32 @@ pub struct Register<A: AsAid> {
     pub publish: Vec<A>,
 }
 
+/// A request with the intent of compiling a textual query into a new
+/// rule, to be registered under the specified name.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct RegisterQuery {
+    /// The name under which to register the compiled rule.
+    pub name: String,
+    /// The query text, e.g. `[:find ?e :where [?e :name "Alice"]]`.
+    pub query: String,
+    /// The language the query is written in, Datalog by default.
+    #[serde(default)]
+    pub language: Language,
+}
+
+/// A request with the intent of subscribing to the results of a
+/// GraphQL query or subscription document, delivered as nested JSON.
+#[cfg(feature = "graphql")]
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct SubscribeGraphQl {
+    /// The name under which to register and publish the query.
+    pub name: String,
+    /// The GraphQL document, e.g. `subscription { name age }`.
+    pub query: String,
+    /// Forwarded to the underlying `AssocIn` sink.
+    pub stateful: Option<usize>,
+}
+
 /// A request with the intent of creating a new named, globally
 /// available input that can be transacted upon.
 #[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
@@ -146,6 +569,301 @@ pub struct CreateAttribute {
     pub name: String,
     /// Semantics enforced on this attribute by 3DF.
     pub config: AttributeConfig,
+    /// An optional source providing the attribute's initial data,
+    /// introduced alongside anything transacted against it. Only the
+    /// source's stream named like the attribute is used.
+    #[serde(default)]
+    pub backfill: Option<Source<Aid>>,
+}
+
+/// A request to make an existing attribute available under another
+/// name as well.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct AliasAttribute {
+    /// The additional name.
+    pub alias: String,
+    /// The name of the existing attribute.
+    pub name: String,
+}
+
+/// A request to move all data of an existing attribute under a new
+/// name. The old name remains available as an alias, s.t. clients
+/// can switch over at their own pace.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct MigrateAttribute {
+    /// The name of the existing attribute.
+    pub from: String,
+    /// The new name, which must not be taken yet.
+    pub to: String,
+}
+
+/// A request to copy the current contents of an existing attribute
+/// into a new one, all at a single timestamp. Values may be passed
+/// through a function on the way, e.g. to convert units. Values the
+/// function can't be applied to are left out.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct CopyAttribute {
+    /// The name of the existing attribute.
+    pub from: String,
+    /// The name of the new attribute, which must not be taken yet.
+    pub to: String,
+    /// Semantics of the new attribute. Defaults to those of the
+    /// existing one.
+    #[serde(default)]
+    pub config: Option<AttributeConfig>,
+    /// A function to apply to each value.
+    #[serde(default)]
+    pub function: Option<Function>,
+    /// Constant inputs to the function.
+    #[serde(default)]
+    pub constants: Vec<Option<Value>>,
+}
+
+/// A request to materialize the results of a registered rule as a
+/// new attribute, s.t. other queries can use it like any other. The
+/// rule must bind exactly two variables, entity and value.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct DeriveAttribute {
+    /// The name of the registered rule.
+    pub rule: String,
+    /// The name of the new attribute.
+    pub name: String,
+    /// Semantics of the new attribute. Derived attributes are
+    /// indexed in both directions by default.
+    #[serde(default)]
+    pub config: Option<AttributeConfig>,
+}
+
+/// Changes to the results of a rule that a trigger responds to.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum TriggerEvent {
+    /// A tuple was added to the results.
+    Appear,
+    /// A tuple was removed from the results.
+    Disappear,
+}
+
+/// The entity or value position of a datom template.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub enum TemplateValue {
+    /// The specified field of the triggering tuple.
+    Field(usize),
+    /// A fixed value.
+    Constant(Value),
+}
+
+impl TemplateValue {
+    fn instantiate(&self, tuple: &[Value]) -> Value {
+        match *self {
+            TemplateValue::Field(index) => tuple[index].clone(),
+            TemplateValue::Constant(ref value) => value.clone(),
+        }
+    }
+}
+
+/// A datom to be transacted whenever a trigger fires.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct DatomTemplate {
+    /// The entity the datom is about.
+    pub e: TemplateValue,
+    /// The attribute to transact on.
+    pub a: String,
+    /// The value to assert or retract.
+    pub v: TemplateValue,
+    /// Retracts the datom, rather than asserting it.
+    #[serde(default)]
+    pub retract: bool,
+}
+
+/// A request to transact datoms whenever tuples appear in, or
+/// disappear from, the results of a registered rule. Triggers may
+/// not feed back into their own rule, neither directly nor via other
+/// triggers.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct RegisterTrigger {
+    /// The name under which the trigger can be cancelled.
+    pub name: String,
+    /// The name of the rule to watch.
+    pub rule: String,
+    /// The kind of change that fires the trigger.
+    pub on: TriggerEvent,
+    /// Datoms to transact for each triggering tuple.
+    pub template: Vec<DatomTemplate>,
+}
+
+/// A request to continuously transact the results of a registered
+/// rule into an existing attribute. Tuples appearing in the results
+/// are asserted, tuples disappearing from them are retracted. The
+/// rule must bind exactly two variables, entity and value.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct InsertInto {
+    /// The name under which the insertion can be cancelled.
+    pub name: String,
+    /// The name of the registered rule.
+    pub rule: String,
+    /// The attribute to transact into.
+    pub attribute: String,
+}
+
+/// A request to reject all transactions that would produce tuples in
+/// the results of a registered rule, e.g. orders without a customer.
+/// Rejected transactions are rolled back by retracting their datoms.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct RegisterConstraint {
+    /// The name under which the constraint can be cancelled.
+    pub name: String,
+    /// The name of the rule describing violations.
+    pub rule: String,
+}
+
+/// A request to permanently remove datoms, e.g. to comply with
+/// deletion requests. Matching datoms are retracted and all indices
+/// are compacted past the retraction, s.t. no trace of them remains.
+/// At least one of entity and attribute must be specified.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Excise {
+    /// Excise only datoms about this entity, given as an entity id
+    /// or a lookup ref.
+    #[serde(default)]
+    pub entity: Option<Value>,
+    /// Excise only datoms of this attribute.
+    #[serde(default)]
+    pub attribute: Option<String>,
+    /// Excise only datoms whose value satisfies the predicate with
+    /// respect to the constant.
+    #[serde(default)]
+    pub value: Option<(Predicate, Value)>,
+}
+
+/// Name of the built-in relation holding a tuple of statistics per
+/// attribute: its name, the number of datoms, the number of distinct
+/// entities, and the instant it was last seen changing.
+pub const ATTRIBUTE_STATS: &str = "df.stats/attributes";
+
+/// Name of the built-in relation holding a tuple of statistics per
+/// materialized rule: its name, the number of results, the number of
+/// distinct values in their first field, and the instant they were
+/// last seen changing.
+pub const RULE_STATS: &str = "df.stats/rules";
+
+fn is_stats<A: AsAid>(name: &A) -> bool {
+    let name = name.to_string();
+    name == ATTRIBUTE_STATS || name == RULE_STATS
+}
+
+/// Drops retractions from the pairs of a monotonic attribute that
+/// isn't transacted into, where they can't be rejected.
+fn without_retractions<S, A>(
+    name: &A,
+    pairs: Collection<S, (Value, Value), isize>,
+) -> Collection<S, (Value, Value), isize>
+where
+    S: Scope,
+    A: AsAid,
+{
+    let name = name.to_string();
+
+    pairs
+        .inner
+        .flat_map(move |(pair, t, diff)| {
+            if diff < 0 {
+                error!(
+                    "Dropping a retraction of {:?} from monotonic {}.",
+                    pair, name
+                );
+                None
+            } else {
+                Some((pair, t, diff))
+            }
+        })
+        .as_collection()
+}
+
+/// Administrative queries on the state of a running server.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub enum Inspect {
+    /// Lists all registered rules.
+    Rules,
+    /// Reports the configuration and index sizes of all attributes.
+    Attributes,
+    /// Lists all active subscriptions.
+    Interests,
+    /// Reports throughput per client connection. Only answered by
+    /// servers that manage connections themselves.
+    Clients,
+    /// Reports the estimated memory held by the results of all
+    /// running dataflows.
+    Memory,
+}
+
+/// Answer to an `Inspect` request.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum Report<A: AsAid> {
+    /// All registered rules.
+    Rules(Vec<Rule<A>>),
+    /// Statistics on all attributes.
+    Attributes(Vec<AttributeStats>),
+    /// All active subscriptions.
+    Interests(Vec<InterestStats>),
+    /// Memory held by all running dataflows.
+    Memory(Vec<MemoryStats>),
+}
+
+/// Statistics on a single attribute.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AttributeStats {
+    /// The attribute's name.
+    pub name: String,
+    /// Semantics enforced on the attribute.
+    pub config: AttributeConfig,
+    /// Sizes of the attribute's indices, keyed by index name.
+    pub indices: BTreeMap<String, TraceStats>,
+    /// Late datoms its sources have handled.
+    #[serde(default)]
+    pub late_data: LateData,
+}
+
+/// Statistics on a single subscription.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct InterestStats {
+    /// The name of the subscribed relation.
+    pub name: String,
+    /// Number of clients interested in it.
+    pub clients: usize,
+    /// Whether a dataflow is currently maintained for it.
+    pub running: bool,
+}
+
+/// Memory held by a single dataflow.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MemoryStats {
+    /// The name of the relation computed by the dataflow.
+    pub name: String,
+    /// Size of the arranged results.
+    pub results: TraceStats,
+    /// Size of the indices of all attributes the dataflow reads.
+    #[serde(default)]
+    pub attributes: TraceStats,
+    /// The memory budget of the dataflow, if any.
+    pub budget: Option<usize>,
+}
+
+/// Memory held by the dataflows with a budget on a single worker.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct MemoryUsage {
+    /// The worker reporting its usage.
+    pub worker: usize,
+    /// Estimated bytes held on that worker, by dataflow.
+    pub bytes: Vec<(String, usize)>,
+}
+
+/// A problem found with a rule while validating it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Diagnostic {
+    /// The name of the offending rule.
+    pub rule: String,
+    /// What implementing the rule would fail with.
+    pub error: Error,
 }
 
 /// Possible request types.
@@ -153,6 +871,9 @@ pub struct CreateAttribute {
 pub enum Request<A: AsAid + From<&'static str>> {
     /// Sends inputs via one or more registered handles.
     Transact(Vec<Datom<A>>),
+    /// Sends inputs like `Transact`, but acknowledges them only once
+    /// they are visible to subsequent reads.
+    TransactSync(TransactSync<A>),
     /// Expresses interest in an entire attribute.
     Subscribe(String),
     /// Derives new attributes under a new namespace.
@@ -160,17 +881,65 @@ pub enum Request<A: AsAid + From<&'static str>> {
     Derive(String, String),
     /// Expresses interest in a named relation.
     Interest(Interest),
+    /// Expresses interest in several named relations, whose results
+    /// should be delivered together. May be undone via Uninterest on
+    /// the group's name.
+    InterestGroup(InterestGroup),
     /// Expresses that the interest in a named relation has
     /// stopped. Once all interested clients have sent this, the
     /// dataflow can be cleaned up.
     Uninterest(String),
+    /// Tears down the dataflow of a named relation, regardless of
+    /// any remaining interest in it.
+    Cancel(String),
     /// Registers one or more named relations.
     Register(Register<A>),
+    /// Registers a named relation described by a query string.
+    RegisterQuery(RegisterQuery),
+    /// Checks rules against the current catalog of attributes and
+    /// rules, reporting any problems without registering them.
+    Validate(Register<A>),
+    /// Registers a new version of a rule. Its subscribers are switched
+    /// over to the new version once it has caught up.
+    SwapRule(Rule<A>),
+    /// Switches the subscribers of a rule over to its new version.
+    /// Issued once the new version has caught up.
+    CompleteSwap(String),
+    /// Registers a GraphQL document and expresses interest in its
+    /// results.
+    #[cfg(feature = "graphql")]
+    SubscribeGraphQl(SubscribeGraphQl),
     /// A request with the intent of attaching to an external data
     /// source that publishes one or more attributes and relations.
     RegisterSource(Source<A>),
     /// Creates a named input handle that can be `Transact`ed upon.
     CreateAttribute(CreateAttribute),
+    /// Makes an existing attribute available under another name.
+    AliasAttribute(AliasAttribute),
+    /// Moves an existing attribute to a new name.
+    MigrateAttribute(MigrateAttribute),
+    /// Copies the contents of an existing attribute into a new one.
+    CopyAttribute(CopyAttribute),
+    /// Materializes a rule as a new attribute.
+    DeriveAttribute(DeriveAttribute),
+    /// Transacts datoms in response to changes in a rule's results.
+    RegisterTrigger(RegisterTrigger),
+    /// Transacts a rule's changing results into an attribute.
+    InsertInto(InsertInto),
+    /// Rejects transactions producing results for a rule.
+    RegisterConstraint(RegisterConstraint),
+    /// Permanently removes datoms.
+    Excise(Excise),
+    /// Writes the state of this server to a file per worker, at the
+    /// specified path suffixed by the worker index. The path is
+    /// relative to the configured export directory. A server with a
+    /// different number of workers can be started from these.
+    ExportState(String),
+    /// Moves the state of this server onto the specified number of
+    /// worker threads, by draining the running computation and
+    /// restoring its exported state into a new one. Clients are
+    /// disconnected in the process.
+    Rescale(usize),
     /// Advances the specified domain to the specified time.
     AdvanceDomain(Option<String>, Time),
     /// Requests a domain advance to whatever epoch the server
@@ -181,13 +950,108 @@ pub enum Request<A: AsAid + From<&'static str>> {
     CloseInput(String),
     /// Client has disconnected.
     Disconnect,
+    /// Signals that a client is still around.
+    Heartbeat,
+    /// Ends a client's session, cleaning up everything it owned.
+    /// Issued on behalf of clients that went silent.
+    EndSession,
+    /// Tears down a dataflow without connected subscribers. Issued
+    /// once it has been idle for the configured period.
+    Hibernate(String),
+    /// Tears down a dataflow that exceeded its memory budget.
+    Evict(String),
+    /// Shares the memory a worker holds for dataflows with a budget,
+    /// s.t. budgets apply to their total across all workers.
+    MemoryUsage(MemoryUsage),
     /// Requests any setup logic that needs to be executed
     /// deterministically across all workers.
     Setup,
+    /// Requests the specified number of fresh entity ids.
+    AllocateIds(usize),
     /// Requests a heartbeat containing status information.
     Status,
+    /// Requests administrative information about the server.
+    Inspect(Inspect),
+    /// Updates the server configuration.
+    Configure(Configure),
     /// Requests orderly shutdown of the system.
     Shutdown,
+    /// Promotes a standby to take over from its primary.
+    Promote,
+}
+
+impl<A: AsAid> Request<A> {
+    /// Returns true iff the request changes state that standbys
+    /// have to replicate, i.e. anything but reads and requests
+    /// concerning individual clients.
+    pub fn is_replicated(&self) -> bool {
+        match *self {
+            #[cfg(feature = "graphql")]
+            Request::Derive(_, _) => true,
+            Request::Transact(_)
+            | Request::TransactSync(_)
+            | Request::Register(_)
+            | Request::RegisterQuery(_)
+            | Request::SwapRule(_)
+            | Request::CompleteSwap(_)
+            | Request::RegisterSource(_)
+            | Request::CreateAttribute(_)
+            | Request::AliasAttribute(_)
+            | Request::MigrateAttribute(_)
+            | Request::CopyAttribute(_)
+            | Request::DeriveAttribute(_)
+            | Request::RegisterTrigger(_)
+            | Request::InsertInto(_)
+            | Request::RegisterConstraint(_)
+            | Request::Excise(_)
+            | Request::AdvanceDomain(_, _)
+            | Request::CloseInput(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Returns true iff the request changes data, rather than only
+    /// how it is queried. Read replicas accept anything else.
+    pub fn is_write(&self) -> bool {
+        match *self {
+            #[cfg(feature = "graphql")]
+            Request::Derive(_, _) => false,
+            Request::Register(_)
+            | Request::RegisterQuery(_)
+            | Request::SwapRule(_)
+            | Request::CompleteSwap(_) => false,
+            ref req => req.is_replicated(),
+        }
+    }
+}
+
+/// Everything a server knows about a single client.
+struct Session<A> {
+    /// The worker holding the client's connection.
+    owner: usize,
+    /// The last time the client was heard from.
+    last_seen: Instant,
+    /// Rules registered by the client.
+    rules: HashSet<A>,
+    /// Whether the client is still connected.
+    is_connected: bool,
+    /// Whether the session is about to be ended.
+    is_ending: bool,
+}
+
+/// A new version of a rule, catching up before the subscribers of
+/// the current one are switched over.
+struct Swap<A: AsAid, T: Timestamp> {
+    /// The name under which the new version is implemented.
+    version: A,
+    /// The new version's plan.
+    plan: Plan<A>,
+    /// The time up to which the new version has to catch up.
+    target: T,
+    /// Probe keeping track of the new version's progress.
+    probe: ProbeHandle<T>,
+    /// Whether the swap has been reported as caught up.
+    is_reported: bool,
 }
 
 /// Server context maintaining globally registered arrangements and
@@ -205,13 +1069,88 @@ where
     pub t0: Instant,
     /// Internal domain in server time.
     pub internal: Domain<A, T>,
+    /// Entity ids handed out by this worker.
+    pub ids: IdAllocator,
+    /// Number of workers in the computation (copied from worker).
+    pub peers: usize,
+    /// Index of this worker (copied from worker).
+    pub index: usize,
     /// Mapping from query names to interested client tokens.
     pub interests: HashMap<A, HashSet<Token>>,
+    /// Names by which individual clients know shared relations, if
+    /// they differ from the name the relation is shared under.
+    pub output_names: HashMap<A, HashMap<Token, A>>,
+    // Registered plans, by the first name they were registered under.
+    plans: HashMap<Plan<A>, A>,
+    // Rules sharing the dataflow of an identical rule.
+    shared: HashMap<A, A>,
+    // Arranged results of relations of interest.
+    result_traces: HashMap<A, TraceKeyHandle<Vec<Value>, T, isize>>,
+    // Dataflows catching up late joiners, by the relation they are
+    // caught up on.
+    catch_ups: HashMap<A, A>,
+    next_catch_up: usize,
     // Mapping from query names to their shutdown handles. This is
     // separate from internal shutdown handles on domains, because
     // user queries might be one-off and not result in a new domain
     // being created.
     shutdown_handles: HashMap<A, ShutdownHandle>,
+    // Attributes read and written by each registered trigger.
+    triggers: HashMap<A, (HashSet<A>, HashSet<A>)>,
+    // Transactions enqueued by triggers, waiting to be sequenced.
+    fired: Rc<RefCell<VecDeque<Vec<Datom<A>>>>>,
+    // Names of all registered constraints.
+    constraints: HashSet<A>,
+    // Violations reported by constraints, on all workers.
+    violations: Rc<RefCell<Vec<(A, Vec<Value>, T)>>>,
+    // Probe keeping track of the progress of constraint checks.
+    constraint_probe: ProbeHandle<T>,
+    // Transactions awaiting their constraints checks, by time.
+    unchecked: VecDeque<(T, Vec<Datom<A>>)>,
+    // Outcome of constraint checks, for holding back results.
+    validation: Rc<RefCell<Validation<T>>>,
+    // Clients that state is held for.
+    sessions: HashMap<Token, Session<A>>,
+    // Rules whose owners are gone, but which were still in use.
+    orphaned: HashSet<A>,
+    // Interests waiting for the attributes they depend on, along
+    // with the worker holding the client's connection.
+    deferred: Vec<(usize, Token, Interest)>,
+    // Interests waiting for their causality tokens to pass, along
+    // with the worker holding the client's connection.
+    awaiting_tokens: Vec<(usize, Token, Option<RequestId>, Interest)>,
+    // Transactions whose timestamps might not be closed yet, in order.
+    unclosed: VecDeque<(TxId, T)>,
+    // The latest transaction whose timestamp is known to be closed.
+    closed_tx: Option<TxId>,
+    // The worker and request that set up each dataflow delivering
+    // results to clients.
+    subscriptions: HashMap<A, (usize, Interest)>,
+    // New versions of rules catching up, by the rule they replace.
+    swaps: HashMap<A, Swap<A, T>>,
+    next_version: usize,
+    // Names under which new versions of rules are implemented.
+    versions: HashSet<A>,
+    // Results of replaced dataflows, to be retracted from their
+    // subscribers once they are switched over.
+    retired: HashMap<A, TraceKeyHandle<Vec<Value>, T, isize>>,
+    // Interests in relations being switched over, restored once the
+    // new version delivers to them.
+    switching: HashMap<A, HashSet<Token>>,
+    // When dataflows were first found without connected subscribers.
+    idle_since: HashMap<A, Instant>,
+    // Idle dataflows already reported for hibernation.
+    hibernating: HashSet<A>,
+    // Dataflows already reported for exceeding their budget.
+    evicting: HashSet<A>,
+    // Memory held by dataflows with a budget, by reporting worker.
+    memory_reports: HashMap<A, HashMap<usize, usize>>,
+    // Memory last reported by this worker, by dataflow.
+    reported_usage: HashMap<A, usize>,
+    #[cfg(feature = "store")]
+    store: Option<Store>,
+    // Stored attributes that are not indexed in memory yet.
+    cold: HashMap<A, AttributeConfig>,
     /// Probe keeping track of overall dataflow progress.
     pub probe: ProbeHandle<T>,
     /// Scheduler managing deferred operator activations.
@@ -220,6 +1159,8 @@ where
     timely_events: Option<Rc<EventLink<Duration, (Duration, usize, TimelyEvent)>>>,
     // Link to replayable Differential logging events.
     differential_events: Option<Rc<EventLink<Duration, (Duration, usize, DifferentialEvent)>>>,
+    // Late datoms handled by sources, per attribute.
+    late_data: Rc<RefCell<HashMap<String, LateData>>>,
 }
 
 impl<A, T, Token> Server<A, T, Token>
@@ -241,17 +1182,55 @@ where
         let differential_events = Some(Rc::new(EventLink::new()));
 
         let probe = ProbeHandle::new();
+        let ids = IdAllocator::new(config.id_strategy, 0, 1);
 
         Server {
             config,
             t0,
+            ids,
+            peers: 1,
+            index: 0,
             internal: Domain::new(Default::default()),
             interests: HashMap::new(),
+            output_names: HashMap::new(),
+            plans: HashMap::new(),
+            shared: HashMap::new(),
+            result_traces: HashMap::new(),
+            catch_ups: HashMap::new(),
+            next_catch_up: 0,
             shutdown_handles: HashMap::new(),
+            triggers: HashMap::new(),
+            fired: Rc::new(RefCell::new(VecDeque::new())),
+            constraints: HashSet::new(),
+            violations: Rc::new(RefCell::new(Vec::new())),
+            constraint_probe: ProbeHandle::new(),
+            unchecked: VecDeque::new(),
+            validation: Rc::new(RefCell::new(Validation::new())),
+            sessions: HashMap::new(),
+            orphaned: HashSet::new(),
+            deferred: Vec::new(),
+            awaiting_tokens: Vec::new(),
+            unclosed: VecDeque::new(),
+            closed_tx: None,
+            subscriptions: HashMap::new(),
+            swaps: HashMap::new(),
+            next_version: 0,
+            versions: HashSet::new(),
+            retired: HashMap::new(),
+            switching: HashMap::new(),
+            idle_since: HashMap::new(),
+            hibernating: HashSet::new(),
+            evicting: HashSet::new(),
+            memory_reports: HashMap::new(),
+            reported_usage: HashMap::new(),
+            #[cfg(feature = "store")]
+            store: None,
+            cold: HashMap::new(),
             scheduler: Rc::new(RefCell::new(Scheduler::from(probe.clone()))),
             probe,
             timely_events,
             differential_events,
+            late_data: Rc::new(RefCell::new(HashMap::new())),
         }
     }
 
@@ -278,107 +1257,1991 @@ where
     fn shutdown_query(&mut self, name: &A) {
         info!("Shutting down {}", name);
         self.shutdown_handles.remove(name);
+        self.result_traces.remove(name);
+        self.subscriptions.remove(name);
+        self.idle_since.remove(name);
+        self.hibernating.remove(name);
+        self.evicting.remove(name);
+        self.memory_reports.remove(name);
+        self.reported_usage.remove(name);
+        self.triggers.remove(name);
+        self.constraints.remove(name);
+
+        if self.versions.remove(name) {
+            self.internal.rules.remove(name);
+        }
     }
 
-    /// Handles a Transact request.
+    /// Handles a Transact request. Every worker sees every
+    /// transaction, but introduces only its share of it, as
+    /// determined by the configured partitioning.
     pub fn transact(
         &mut self,
         tx_data: Vec<Datom<A>>,
         owner: usize,
         worker_index: usize,
     ) -> Result<(), Error> {
-        // only the owner should actually introduce new inputs
-        if owner == worker_index {
-            self.internal.transact(tx_data)
-        } else {
-            Ok(())
-        }
-    }
+        let partitioning = self.config.tx_partitioning;
 
-    /// Handles an Interest request.
-    pub fn interest<S: Scope<Timestamp = T>>(
-        &mut self,
-        name: A,
-        scope: &mut S,
-    ) -> Result<Collection<S, Vec<Value>, isize>, Error> {
-        let (mut rel_map, shutdown_handle) = if self.config.enable_optimizer {
-            implement_neu(scope, &mut self.internal, name.clone())?
-        } else {
-            implement(scope, &mut self.internal, name.clone())?
-        };
+        // Transactions are checked in their entirety by every worker,
+        // s.t. all of them agree on whether to apply their share.
+        for Datom(_, a, _, _, diff) in tx_data.iter() {
+            if !self.internal.is_transactable(a) && !self.cold.contains_key(a) {
+                return Err(TxError::UnknownAttribute(a.to_string()).into());
+            }
 
-        match rel_map.remove(&name) {
-            None => Err(Error::fault(format!(
-                "Relation of interest ({}) wasn't actually implemented.",
-                name
-            ))),
-            Some(relation) => {
-                self.shutdown_handles.insert(name, shutdown_handle);
+            let monotonic = self
+                .internal
+                .attribute_config(a)
+                .map(|config| config.is_monotonic())
+                .unwrap_or(false);
 
-                Ok(relation)
+            if *diff < 0 && monotonic {
+                return Err(TxError::AppendOnly(a.to_string()).into());
             }
         }
-    }
-
-    /// Handles a Register request.
-    pub fn register(&mut self, req: Register<A>) -> Result<(), Error> {
-        let Register { rules, .. } = req;
 
-        for rule in rules.into_iter() {
-            if self.internal.rules.contains_key(&rule.name) {
-                // @TODO panic if hashes don't match
-                // panic!("Attempted to re-register a named relation");
-                continue;
-            } else {
-                self.internal.rules.insert(rule.name.clone(), rule);
+        for Datom(e, _, _, _, _) in tx_data.iter() {
+            match e {
+                Value::Eid(eid) => self.ids.validate(*eid)?,
+                Value::LookupRef(_, _) if self.peers > 1 => {
+                    return Err(Error::unsupported(
+                        "Lookup refs can only be resolved with a single worker.",
+                    ));
+                }
+                _ => {}
             }
         }
 
-        Ok(())
-    }
+        // Every worker sees the whole transaction here, which makes
+        // this the place to maintain statistics they all share, and
+        // to learn about ids allocated before a restart.
+        self.internal.count_datoms(&tx_data);
+        self.observe_ids(tx_data.iter().map(|Datom(e, _, v, _, _)| (e, v)));
 
-    /// Handles a CreateAttribute request.
-    pub fn create_attribute<X, S>(
-        &mut self,
-        scope: &mut S,
-        name: X,
-        config: AttributeConfig,
-    ) -> Result<(), Error>
-    where
-        X: Into<A>,
-        S: Scope<Timestamp = T>,
-        S::Timestamp: std::convert::Into<crate::timestamp::Time>,
-    {
-        let ((handle, cap), pairs) =
-            scope.new_unordered_input::<((Value, Value), S::Timestamp, isize)>();
+        if partitioning == Partitioning::Single && owner != worker_index {
+            // only the owner should actually introduce new inputs
+            return Ok(());
+        }
 
-        let tuples = match config.input_semantics {
-            InputSemantics::Raw => pairs.as_collection(),
-            InputSemantics::LastWriteWins => pairs.as_collection().last_write_wins(),
-            // Ensure that redundant (e,v) pairs don't cause
-            // misleading proposals during joining.
-            InputSemantics::Distinct => pairs.as_collection().distinct(),
+        let peers = self.peers;
+        let mut share: Vec<Datom<A>> = if partitioning == Partitioning::Single {
+            tx_data
+        } else {
+            tx_data
+                .into_iter()
+                .enumerate()
+                .filter(|(position, Datom(e, _, _, _, _))| {
+                    partitioning.is_responsible(owner, *position, e, worker_index, peers)
+                })
+                .map(|(_, datom)| datom)
+                .collect()
         };
 
-        let mut scoped_domain = ((handle, cap), tuples).as_singleton_domain(name.into());
-
-        if let Some(slack) = config.trace_slack {
-            scoped_domain = scoped_domain.with_slack(slack.into());
-        }
+        // Stored attributes that aren't indexed yet only live in the
+        // store, all others are written through to it. Transactions
+        // are only split up if there are any such attributes.
+        let cold: Vec<Datom<A>> = if self.cold.is_empty() {
+            Vec::new()
+        } else {
+            let (cold, indexed) = share
+                .into_iter()
+                .partition(|Datom(_, a, _, _, _)| self.cold.contains_key(a));
+            share = indexed;
+            cold
+        };
 
-        // LastWriteWins is a special case, because count, propose,
-        // and validate are all essentially the same.
-        if config.input_semantics != InputSemantics::LastWriteWins {
-            scoped_domain = scoped_domain.with_query_support(config.query_support);
-        }
+        let stored: Vec<Datom<A>> = share
+            .iter()
+            .filter(|Datom(_, a, _, _, _)| self.is_stored(a))
+            .cloned()
+            .chain(cold.into_iter())
+            .collect();
 
-        if config.index_direction == IndexDirection::Both {
-            scoped_domain = scoped_domain.with_reverse_indices();
+        if stored.iter().any(|Datom(e, _, _, _, _)| match e {
+            Value::LookupRef(_, _) => true,
+            _ => false,
+        }) {
+            return Err(Error::unsupported(
+                "Lookup refs can't be used on stored attributes.",
+            ));
         }
 
-        self.internal += scoped_domain.into();
-
-        Ok(())
+        self.internal.transact(share)?;
+        self.write_through(stored)
+    }
+
+    /// Seeds the id allocator with the ids found in the specified
+    /// entity and value positions.
+    fn observe_ids<'a, I>(&mut self, pairs: I)
+    where
+        I: Iterator<Item = (&'a Value, &'a Value)>,
+    {
+        for (e, v) in pairs {
+            for value in [e, v].iter() {
+                if let Value::Eid(eid) = value {
+                    self.ids.observe(*eid);
+                }
+            }
+        }
+    }
+
+    fn is_stored(&self, name: &A) -> bool {
+        self.cold.contains_key(name)
+            || self
+                .internal
+                .attributes
+                .get(name)
+                .map_or(false, |config| config.stored)
+    }
+
+    #[cfg(feature = "store")]
+    fn write_through(&mut self, tx_data: Vec<Datom<A>>) -> Result<(), Error> {
+        match self.store {
+            None => Ok(()),
+            Some(ref mut store) => store.write(
+                tx_data
+                    .into_iter()
+                    .map(|Datom(e, a, v, _, diff)| (a.to_string(), e, v, diff))
+                    .collect(),
+            ),
+        }
+    }
+
+    #[cfg(not(feature = "store"))]
+    fn write_through(&mut self, _tx_data: Vec<Datom<A>>) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Opens the store of the specified worker, if one is configured,
+    /// and learns about all attributes kept in it, see `use_store`.
+    #[cfg(feature = "store")]
+    pub fn open_store(&mut self, worker_index: usize) -> Result<(), Error> {
+        if let Some(ref path) = self.config.store {
+            let store = Store::open_worker(path, worker_index)?;
+            self.use_store(store);
+        }
+
+        Ok(())
+    }
+
+    /// Keeps stored attributes in the specified store, and learns
+    /// about all attributes kept in it already. These are indexed
+    /// once queried, just like newly created stored attributes.
+    #[cfg(feature = "store")]
+    pub fn use_store(&mut self, store: Store) {
+        for (name, config) in store.attributes().iter() {
+            self.cold.insert(A::from(name.clone()), config.clone());
+        }
+
+        self.store = Some(store);
+    }
+
+    #[cfg(feature = "store")]
+    fn persist_attribute(&mut self, name: &A, config: &AttributeConfig) -> Result<(), Error> {
+        match self.store {
+            None => Err(Error::unsupported("No store is configured.")),
+            Some(ref mut store) => store.create_attribute(&name.to_string(), config),
+        }
+    }
+
+    #[cfg(not(feature = "store"))]
+    fn persist_attribute(&mut self, _name: &A, _config: &AttributeConfig) -> Result<(), Error> {
+        Err(Error::unsupported(
+            "Stored attributes are not supported in this build.",
+        ))
+    }
+
+    #[cfg(feature = "store")]
+    fn stored_contents(&self, name: &A) -> Result<Vec<((Value, Value), isize)>, Error> {
+        match self.store {
+            None => Ok(Vec::new()),
+            Some(ref store) => store.contents(&name.to_string()),
+        }
+    }
+
+    #[cfg(not(feature = "store"))]
+    fn stored_contents(&self, _name: &A) -> Result<Vec<((Value, Value), isize)>, Error> {
+        Ok(Vec::new())
+    }
+
+    /// Deletes the stored updates an excision covers, s.t. they don't
+    /// come back when their attribute is hydrated again.
+    #[cfg(feature = "store")]
+    fn excise_stored(
+        &mut self,
+        entity: Option<&Value>,
+        attribute: Option<&A>,
+        value: Option<&(Predicate, Value)>,
+    ) -> Result<usize, Error> {
+        let store = match self.store {
+            None => return Ok(0),
+            Some(ref mut store) => store,
+        };
+
+        let internal = &self.internal;
+        let attribute = attribute.map(|a| internal.canonical_name(a).to_string());
+
+        store.excise(attribute.as_ref().map(|a| a.as_str()), |_a, e, v| {
+            let is_entity = entity.map_or(true, |entity| e == entity);
+            let is_value = value.map_or(true, |(predicate, constant)| predicate.holds(v, constant));
+
+            is_entity && is_value
+        })
+    }
+
+    #[cfg(not(feature = "store"))]
+    fn excise_stored(
+        &mut self,
+        _entity: Option<&Value>,
+        _attribute: Option<&A>,
+        _value: Option<&(Predicate, Value)>,
+    ) -> Result<usize, Error> {
+        Ok(0)
+    }
+
+    /// Feeds pre-timestamped updates into an attribute directly,
+    /// bypassing transactions, s.t. original times are preserved. As
+    /// with transactions, only the owning worker should do so.
+    pub fn ingest(
+        &mut self,
+        name: &A,
+        updates: Vec<((Value, Value), T, isize)>,
+    ) -> Result<(), Error> {
+        self.internal.ingest(name, updates)
+    }
+
+    /// Handles an Interest request.
+    pub fn interest<S>(
+        &mut self,
+        name: A,
+        scope: &mut S,
+    ) -> Result<Collection<S, Vec<Value>, isize>, Error>
+    where
+        S: Scope<Timestamp = T>,
+        S::Timestamp: std::convert::Into<crate::timestamp::Time>,
+    {
+        let (relation, shutdown_handle) = self.implement_relation(name.clone(), scope)?;
+        self.shutdown_handles.insert(name.clone(), shutdown_handle);
+
+        if let Some(clients) = self.switching.remove(&name) {
+            self.interests.insert(name.clone(), clients);
+        }
+
+        // Rules switched over to a new version keep their results
+        // around, just as the version they replaced did.
+        let is_catch_up = match self.catch_ups.get(&name) {
+            None => false,
+            Some(source) => !self.versions.contains(source),
+        };
+
+        if is_catch_up {
+            Ok(relation)
+        } else {
+            // Results are kept around for late joiners.
+            let arranged = relation.arrange_by_self();
+            self.result_traces.insert(name, arranged.trace.clone());
+
+            Ok(arranged.as_collection(|tuple, &()| tuple.clone()))
+        }
+    }
+
+    /// Returns the name under which dataflows for the specified rule
+    /// are shared.
+    pub fn shared_name(&self, name: &A) -> A {
+        self.shared.get(name).unwrap_or(name).clone()
+    }
+
+    /// Redirects an interest on a rule to the dataflow it shares with
+    /// identical rules, if any. Clients joining a running dataflow
+    /// late are redirected to a dataflow of their own, which catches
+    /// them up from the arranged results. Outputs are delivered to
+    /// the client under the name it asked for.
+    pub fn share_interest(&mut self, client: Token, mut interest: Interest) -> Interest {
+        let requested = A::from(interest.name.clone());
+        let mut name = self.shared_name(&requested);
+
+        let is_late = match self.interests.get(&name) {
+            None => false,
+            Some(clients) => !clients.is_empty() && !clients.contains(&client),
+        };
+
+        if is_late && self.result_traces.contains_key(&name) {
+            let catch_up = A::from(format!("{}#{}", name, self.next_catch_up));
+            self.next_catch_up += 1;
+            self.catch_ups.insert(catch_up.clone(), name);
+            name = catch_up;
+        }
+
+        if name != requested {
+            self.output_names
+                .entry(name.clone())
+                .or_insert_with(HashMap::new)
+                .insert(client, requested);
+
+            interest.name = name.to_string();
+        }
+
+        let is_first = self
+            .interests
+            .get(&name)
+            .map_or(true, |clients| clients.is_empty());
+
+        // Interests issued again after a swap keep the original owner.
+        if is_first {
+            let owner = self
+                .sessions
+                .get(&client)
+                .map_or(0, |session| session.owner);
+            self.subscriptions
+                .entry(name)
+                .or_insert_with(|| (owner, interest.clone()));
+        }
+
+        interest
+    }
+
+    /// Returns the attributes a relation pulls, directly or via other
+    /// rules, that are declared to be of cardinality one.
+    pub fn cardinality_one(&self, name: &A) -> Vec<String> {
+        let attributes = collect_dependencies(&self.internal, &[name.clone()])
+            .unwrap_or_default()
+            .into_iter()
+            .flat_map(|rule| rule.plan.dependencies().attributes.into_iter());
+
+        crate::plan::pull::cardinality_one(&self.internal, attributes)
+            .iter()
+            .map(|aid| aid.to_string())
+            .collect()
+    }
+
+    /// Checks that the specified tuple fields exist in the results of
+    /// the named relation, as far as its definition tells. Pulled
+    /// results vary in length and are not checked.
+    pub fn check_fields(&self, name: &A, fields: &[usize]) -> Result<(), Error> {
+        let source = self.catch_ups.get(name).unwrap_or(name);
+
+        let plan = match self.internal.rule(source) {
+            None => return Ok(()),
+            Some(rule) => &rule.plan,
+        };
+
+        let arity = match plan {
+            Plan::Pull(_) | Plan::PullLevel(_) | Plan::PullAll(_) => return Ok(()),
+            #[cfg(feature = "graphql")]
+            Plan::GraphQl(_) => return Ok(()),
+            _ => plan.variables().len(),
+        };
+
+        match fields.iter().find(|field| **field >= arity) {
+            None => Ok(()),
+            Some(field) => Err(PlanError::InvalidArgument(format!(
+                "Field {} is out of range for {}, which binds {} variables.",
+                field, name, arity
+            ))
+            .into()),
+        }
+    }
+
+    /// Returns the attributes a relation depends on, directly or via
+    /// other rules, that don't exist yet. Unknown rules are left for
+    /// implementation to complain about.
+    pub fn missing_attributes(&self, name: &A) -> Vec<A> {
+        self.unindexed_attributes(name)
+            .into_iter()
+            .filter(|aid| !self.cold.contains_key(aid))
+            .collect()
+    }
+
+    /// Returns the attributes a relation depends on, directly or via
+    /// other rules, that aren't indexed in memory.
+    fn unindexed_attributes(&self, name: &A) -> Vec<A> {
+        let mut seen = HashSet::new();
+        let mut queue = vec![name.clone()];
+        let mut missing = Vec::new();
+
+        while let Some(next) = queue.pop() {
+            if !seen.insert(next.clone()) {
+                continue;
+            }
+
+            if let Some(rule) = self.internal.rule(&next) {
+                let dependencies = rule.plan.dependencies();
+                queue.extend(dependencies.names);
+
+                for aid in dependencies.attributes.into_iter() {
+                    if !self.internal.has_attribute(&aid) && !missing.contains(&aid) {
+                        missing.push(aid);
+                    }
+                }
+            }
+        }
+
+        missing
+    }
+
+    /// Indexes a stored attribute in memory, from its contents in the
+    /// store, unless it is indexed already. Hydrated attributes remain
+    /// indexed from then on. Their history starts out compacted to
+    /// the current epoch.
+    fn hydrate<S>(&mut self, scope: &mut S, aid: &A) -> Result<(), Error>
+    where
+        S: Scope<Timestamp = T>,
+        S::Timestamp: std::convert::Into<crate::timestamp::Time>,
+    {
+        let config = match self.cold.get(aid) {
+            None => return Ok(()),
+            Some(config) => config.clone(),
+        };
+
+        self.create_attribute_from(scope, aid.clone(), config, None)?;
+        self.cold.remove(aid);
+
+        let t = self.internal.epoch().clone();
+        let contents = self.stored_contents(aid)?;
+        self.observe_ids(contents.iter().map(|((e, v), _)| (e, v)));
+
+        let updates = contents
+            .into_iter()
+            .map(|(pair, diff)| (pair, t.clone(), diff))
+            .collect();
+
+        self.internal.ingest(aid, updates)?;
+
+        info!("Hydrated {}", aid);
+
+        Ok(())
+    }
+
+    /// Holds back an interest in a relation depending on attributes
+    /// that don't exist yet, if late binding is enabled. Returns the
+    /// interest if it should be handled right away instead.
+    pub fn defer_interest(
+        &mut self,
+        owner: usize,
+        client: Token,
+        id: Option<RequestId>,
+        interest: Interest,
+    ) -> Option<Interest> {
+        // Whether a token has passed differs between workers, so the
+        // owner has to issue the interest again once it has.
+        if interest.at_least.is_some() {
+            self.awaiting_tokens.push((owner, client, id, interest));
+            return None;
+        }
+
+        if !self.config.late_binding {
+            return Some(interest);
+        }
+
+        if self.is_bindable(&interest.name) {
+            Some(interest)
+        } else {
+            info!("Deferring interest in {}", interest.name);
+            self.deferred.push((owner, client, interest));
+            None
+        }
+    }
+
+    /// Removes and returns all deferred interests whose attributes
+    /// have been created by now, along with the worker and client
+    /// they were deferred for. Each should be handled as a regular
+    /// Interest request on behalf of its client.
+    pub fn bound_interests(&mut self) -> Vec<(usize, Token, Interest)> {
+        let deferred = std::mem::replace(&mut self.deferred, Vec::new());
+        let (bound, deferred): (Vec<_>, Vec<_>) = deferred
+            .into_iter()
+            .partition(|(_, _, interest)| self.is_bindable(&interest.name));
+
+        self.deferred = deferred;
+
+        bound
+    }
+
+    /// Returns true iff all attributes the named relation depends on
+    /// exist.
+    fn is_bindable(&self, name: &str) -> bool {
+        self.missing_attributes(&A::from(name.to_string()))
+            .is_empty()
+    }
+
+    /// Notes that the transaction of the specified id is being
+    /// introduced at the current epoch, s.t. causality tokens
+    /// referencing it can be resolved. Only the last transaction at
+    /// each epoch that hasn't been closed yet is kept track of.
+    pub fn note_tx(&mut self, tx: TxId) {
+        self.close_txs();
+
+        let t = self.internal.epoch().clone();
+
+        match self.unclosed.back_mut() {
+            Some((last_tx, last_t)) if *last_t == t => *last_tx = tx,
+            _ => self.unclosed.push_back((tx, t)),
+        }
+    }
+
+    /// Forgets about all transactions whose timestamps have been
+    /// closed on all workers, remembering only the last of them.
+    fn close_txs(&mut self) {
+        while let Some((_tx, t)) = self.unclosed.front() {
+            if !self.is_visible(t, Visibility::Closed) {
+                break;
+            }
+
+            let (closed, _t) = self.unclosed.pop_front().unwrap();
+            self.closed_tx = Some(closed);
+        }
+    }
+
+    /// Returns true iff the timestamps of all transactions up to the
+    /// specified one are closed on all workers.
+    pub fn has_passed(&mut self, tx: TxId) -> bool {
+        self.close_txs();
+
+        self.closed_tx.map_or(false, |closed| closed >= tx)
+    }
+
+    /// Removes and returns all interests whose causality tokens have
+    /// passed, along with the worker, client, and request id they
+    /// were held back for. Only the worker holding the client's
+    /// connection should issue each of them again, as a regular
+    /// Interest request under the same id.
+    pub fn caught_up_interests(&mut self) -> Vec<(usize, Token, Option<RequestId>, Interest)> {
+        if self.awaiting_tokens.is_empty() {
+            return Vec::new();
+        }
+
+        let awaiting = std::mem::replace(&mut self.awaiting_tokens, Vec::new());
+        let (mut caught_up, awaiting): (Vec<_>, Vec<_>) = awaiting
+            .into_iter()
+            .partition(|(_, _, _, interest)| self.has_passed(interest.at_least.unwrap()));
+
+        self.awaiting_tokens = awaiting;
+
+        for (_owner, _client, _id, interest) in caught_up.iter_mut() {
+            interest.at_least = None;
+        }
+
+        caught_up
+    }
+
+    /// Handles an InterestGroup request, by implementing all member
+    /// relations within the same dataflow. Results are tagged with
+    /// the name of the member they belong to.
+    pub fn interest_group<S>(
+        &mut self,
+        name: A,
+        members: &[A],
+        scope: &mut S,
+    ) -> Result<Stream<S, (String, ResultDiff<T>)>, Error>
+    where
+        S: Scope<Timestamp = T>,
+        S::Timestamp: std::convert::Into<crate::timestamp::Time>,
+    {
+        let mut shutdown_handle = ShutdownHandle::empty();
+        let mut streams = Vec::with_capacity(members.len());
+
+        for member in members.iter() {
+            let (relation, member_handle) = self.implement_relation(member.clone(), scope)?;
+            shutdown_handle.merge_with(member_handle);
+
+            let member = member.to_string();
+            streams.push(
+                relation
+                    .consolidate()
+                    .inner
+                    .map(move |result| (member.clone(), result)),
+            );
+        }
+
+        self.shutdown_handles.insert(name, shutdown_handle);
+
+        Ok(scope.concatenate(streams))
+    }
+
+    /// Implements the named relation, as if the specified candidate
+    /// rules had been registered. Worst-case optimal joins draw on
+    /// all indices of the attributes they touch. These are built
+    /// here on first use and outlive the query, s.t. later ones can
+    /// share them.
+    fn implement_named<S>(
+        &mut self,
+        scope: &mut S,
+        name: A,
+        candidates: &[Rule<A>],
+    ) -> Result<(HashMap<A, Collection<S, Vec<Value>, isize>>, ShutdownHandle), Error>
+    where
+        S: Scope<Timestamp = T>,
+        S::Timestamp: std::convert::Into<crate::timestamp::Time>,
+    {
+        let rules = collect_dependencies_with(&self.internal, &[name.clone()], candidates)?;
+
+        // Stored attributes are only indexed once something reads
+        // them, whatever that is.
+        for rule in rules.iter() {
+            for aid in rule.plan.dependencies().attributes.iter() {
+                self.hydrate(scope, aid)?;
+            }
+        }
+
+        let wco = self.config.enable_optimizer
+            || rules.iter().any(|rule| match rule.plan {
+                Plan::Hector(_) => true,
+                _ => false,
+            });
+
+        if wco {
+            for rule in rules.iter() {
+                for aid in rule.plan.dependencies().attributes.iter() {
+                    self.internal.complete_indices(scope, aid)?;
+                }
+            }
+        }
+
+        if self.config.enable_optimizer {
+            implement_neu_with(scope, &mut self.internal, name, candidates)
+        } else {
+            implement_with(scope, &mut self.internal, name, candidates)
+        }
+    }
+
+    fn implement_relation<S>(
+        &mut self,
+        name: A,
+        scope: &mut S,
+    ) -> Result<(Collection<S, Vec<Value>, isize>, ShutdownHandle), Error>
+    where
+        S: Scope<Timestamp = T>,
+        S::Timestamp: std::convert::Into<crate::timestamp::Time>,
+    {
+        if let Some(source) = self.catch_ups.get(&name) {
+            let trace = match self.result_traces.get_mut(source) {
+                None => return Err(Error::not_found(format!("Results of {} are gone.", source))),
+                Some(trace) => trace,
+            };
+
+            // Imported results are consolidated up to the point to
+            // which the trace has been compacted.
+            let (arranged, shutdown_button) = trace.import_frontier(scope, &name.to_string());
+            let mut relation = arranged.as_collection(|tuple, &()| tuple.clone());
+            let mut shutdown_handle = ShutdownHandle::from_button(shutdown_button);
+
+            // Subscribers switched over from a replaced dataflow
+            // already hold its results, which are retracted again.
+            if let Some(mut retired) = self.retired.remove(&name) {
+                let (arranged, shutdown_button) =
+                    retired.import_frontier(scope, &format!("Retired({})", name));
+                shutdown_handle.add_button(shutdown_button);
+
+                relation =
+                    relation.concat(&arranged.as_collection(|tuple, &()| tuple.clone()).negate());
+            }
+
+            return Ok((relation, shutdown_handle));
+        }
+
+        if is_stats(&name) {
+            return self.implement_stats(&name, scope);
+        }
+
+        let (mut rel_map, shutdown_handle) = self.implement_named(scope, name.clone(), &[])?;
+
+        match rel_map.remove(&name) {
+            None => Err(Error::fault(format!(
+                "Relation of interest ({}) wasn't actually implemented.",
+                name
+            ))),
+            Some(relation) => Ok((relation, shutdown_handle)),
+        }
+    }
+
+    /// Implements one of the built-in statistics relations. These
+    /// cover the attributes indexed in memory, or the rules whose
+    /// results are materialized, at the time of the interest.
+    fn implement_stats<S: Scope<Timestamp = T>>(
+        &mut self,
+        name: &A,
+        scope: &mut S,
+    ) -> Result<(Collection<S, Vec<Value>, isize>, ShutdownHandle), Error> {
+        let mut shutdown_handle = ShutdownHandle::empty();
+        let mut summaries = Vec::new();
+
+        if name.to_string() == ATTRIBUTE_STATS {
+            let mut names: Vec<A> = self.internal.forward_propose.keys().cloned().collect();
+            names.sort();
+
+            for aid in names.into_iter() {
+                let trace = self.internal.forward_propose.get_mut(&aid).unwrap();
+                let (arranged, shutdown_button) =
+                    trace.import_frontier(scope, &format!("Stats({})", aid));
+                shutdown_handle.add_button(shutdown_button);
+
+                let entities = arranged.as_collection(|e, _v| e.clone());
+                summaries.push(entities.summarize(aid.into_value()).inner);
+            }
+        } else {
+            let mut names: Vec<A> = self
+                .result_traces
+                .keys()
+                .filter(|name| !is_stats(*name))
+                .cloned()
+                .collect();
+            names.sort();
+
+            for rule in names.into_iter() {
+                let trace = self.result_traces.get_mut(&rule).unwrap();
+                let (arranged, shutdown_button) =
+                    trace.import_frontier(scope, &format!("Stats({})", rule));
+                shutdown_handle.add_button(shutdown_button);
+
+                let leading = arranged
+                    .as_collection(|tuple, &()| tuple.clone())
+                    .flat_map(|tuple| tuple.into_iter().next());
+                summaries.push(leading.summarize(rule.into_value()).inner);
+            }
+        }
+
+        Ok((
+            scope.concatenate(summaries).as_collection(),
+            shutdown_handle,
+        ))
+    }
+
+    /// Handles a Register request.
+    pub fn register(&mut self, req: Register<A>) -> Result<(), Error> {
+        let Register { rules, .. } = req;
+
+        for rule in rules.into_iter() {
+            if self.internal.rules.contains_key(&rule.name) {
+                // @TODO panic if hashes don't match
+                // panic!("Attempted to re-register a named relation");
+                continue;
+            } else {
+                // Identical plans can share a single dataflow.
+                match self.plans.get(&rule.plan) {
+                    None => {
+                        self.plans.insert(rule.plan.clone(), rule.name.clone());
+                    }
+                    Some(existing) => {
+                        self.shared.insert(rule.name.clone(), existing.clone());
+                    }
+                }
+
+                self.internal.rules.insert(rule.name.clone(), rule);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Handles a RegisterQuery request.
+    pub fn register_query(&mut self, req: RegisterQuery) -> Result<(), Error> {
+        let RegisterQuery {
+            name,
+            query,
+            language,
+        } = req;
+        let plan = frontends::parse(language, &query)?;
+
+        self.register(Register {
+            rules: vec![Rule::named(name.clone(), plan)],
+            publish: vec![name.into()],
+        })
+    }
+
+    /// Handles a SwapRule request, by implementing a new version of a
+    /// rule alongside its current dataflow. Subscribers are switched
+    /// over via `complete_swap`, once the new version has caught up
+    /// with the epoch at which it was requested. Rules no one is
+    /// subscribed to are replaced right away. Either way, dataflows of
+    /// other rules depending on the rule keep the plan they were
+    /// implemented with.
+    pub fn swap_rule<S>(&mut self, rule: Rule<A>, scope: &mut S) -> Result<(), Error>
+    where
+        S: Scope<Timestamp = T>,
+        S::Timestamp: std::convert::Into<crate::timestamp::Time>,
+    {
+        let Rule { name, plan } = rule;
+
+        match self.internal.rules.get(&name) {
+            None => {
+                return Err(Error::not_found(format!(
+                    "Rule {} is not registered.",
+                    name
+                )))
+            }
+            Some(existing) if existing.plan == plan => return Ok(()),
+            Some(_) => {}
+        }
+
+        if self.shared.contains_key(&name) || self.shared.values().any(|shared| *shared == name) {
+            return Err(Error::unsupported(format!(
+                "Rule {} shares its dataflow with identical rules.",
+                name
+            )));
+        }
+
+        if self.swaps.contains_key(&name) {
+            return Err(Error::conflict(format!(
+                "A new version of {} is already catching up.",
+                name
+            )));
+        }
+
+        if !self.subscriptions.contains_key(&name) {
+            self.replace_rule(name, plan);
+            return Ok(());
+        }
+
+        let version = A::from(format!("{}@{}", name, self.next_version));
+        self.next_version += 1;
+
+        self.versions.insert(version.clone());
+        self.internal
+            .rules
+            .insert(version.clone(), Rule::named(version.clone(), plan.clone()));
+
+        let mut probe = ProbeHandle::new();
+
+        match self.interest(version.clone(), scope) {
+            Err(error) => {
+                self.shutdown_query(&version);
+                return Err(error);
+            }
+            Ok(relation) => {
+                relation
+                    .inner
+                    .probe_with(&mut probe)
+                    .probe_with(&mut self.probe);
+            }
+        }
+
+        let swap = Swap {
+            version,
+            plan,
+            target: self.internal.epoch().clone(),
+            probe,
+            is_reported: false,
+        };
+
+        self.swaps.insert(name, swap);
+
+        Ok(())
+    }
+
+    /// Returns the rules whose new versions have caught up, s.t. their
+    /// subscribers can be switched over. Each rule is reported once,
+    /// and only to the first worker, which is expected to issue the
+    /// corresponding CompleteSwap request.
+    pub fn caught_up_swaps(&mut self, worker_index: usize) -> Vec<A> {
+        if worker_index != 0 {
+            return Vec::new();
+        }
+
+        let mut caught_up = Vec::new();
+
+        for (name, swap) in self.swaps.iter_mut() {
+            if !swap.is_reported && !swap.probe.less_than(&swap.target) {
+                swap.is_reported = true;
+                caught_up.push(name.clone());
+            }
+        }
+
+        caught_up.sort();
+        caught_up
+    }
+
+    /// Handles a CompleteSwap request, by replacing a rule with its
+    /// new version and tearing down the current dataflow, along with
+    /// those catching up late joiners. Returns the interests through
+    /// which these dataflows were set up, together with the workers
+    /// that issued them. Issuing them again, in order, delivers the
+    /// results of the new version to the same clients, offset by the
+    /// results they have received so far.
+    pub fn complete_swap(&mut self, name: &A) -> Result<Vec<(usize, Interest)>, Error> {
+        let Swap { version, plan, .. } = match self.swaps.remove(name) {
+            None => {
+                return Err(Error::not_found(format!(
+                    "No new version of {} is catching up.",
+                    name
+                )));
+            }
+            Some(swap) => swap,
+        };
+
+        self.replace_rule(name.clone(), plan);
+
+        // Subscribers might have left in the meantime.
+        let retired = match self.result_traces.get(name) {
+            Some(trace) if self.subscriptions.contains_key(name) => trace.clone(),
+            _ => {
+                self.shutdown_query(&version);
+                return Ok(Vec::new());
+            }
+        };
+
+        let mut catch_ups: Vec<A> = self
+            .catch_ups
+            .iter()
+            .filter(|(_catch_up, source)| *source == name)
+            .map(|(catch_up, _source)| catch_up.clone())
+            .collect();
+        catch_ups.sort();
+
+        let mut reissued = Vec::new();
+
+        for switched in std::iter::once(name.clone()).chain(catch_ups.into_iter()) {
+            if let Some(clients) = self.interests.remove(&switched) {
+                self.switching.insert(switched.clone(), clients);
+            }
+
+            let subscription = self.subscriptions.get(&switched).cloned();
+
+            self.retired.insert(switched.clone(), retired.clone());
+            self.shutdown_query(&switched);
+
+            if let Some(subscription) = subscription {
+                self.subscriptions.insert(switched, subscription.clone());
+                reissued.push(subscription);
+            }
+        }
+
+        // From now on, the rule catches up from the new version, like
+        // a late joiner would.
+        if let Some(previous) = self.catch_ups.insert(name.clone(), version) {
+            if !self.interests.contains_key(&previous) {
+                self.release(&previous);
+            }
+        }
+
+        Ok(reissued)
+    }
+
+    fn replace_rule(&mut self, name: A, plan: Plan<A>) {
+        if let Some(rule) = self.internal.rules.get(&name) {
+            if self.plans.get(&rule.plan) == Some(&name) {
+                self.plans.remove(&rule.plan);
+            }
+        }
+
+        self.plans
+            .entry(plan.clone())
+            .or_insert_with(|| name.clone());
+        self.internal
+            .rules
+            .insert(name.clone(), Rule::named(name, plan));
+    }
+
+    /// Handles a SubscribeGraphQl request, by registering the query
+    /// and returning the interest through which results should be
+    /// delivered.
+    #[cfg(feature = "graphql")]
+    pub fn subscribe_graphql(&mut self, req: SubscribeGraphQl) -> Result<Interest, Error> {
+        use crate::plan::GraphQl;
+        use crate::sinks::AssocIn;
+
+        let SubscribeGraphQl {
+            name,
+            query,
+            stateful,
+        } = req;
+        let plan = Plan::GraphQl(GraphQl::parse(query)?);
+
+        self.register(Register {
+            rules: vec![Rule::named(name.clone(), plan)],
+            publish: vec![name.clone().into()],
+        })?;
+
+        Ok(Interest {
+            name,
+            granularity: None,
+            sink: Some(Sink::AssocIn(AssocIn {
+                stateful,
+                cardinality_one: vec![],
+            })),
+            ..Default::default()
+        })
+    }
+
+    /// Handles a CreateAttribute request.
+    pub fn create_attribute<X, S>(
+        &mut self,
+        scope: &mut S,
+        name: X,
+        config: AttributeConfig,
+    ) -> Result<(), Error>
+    where
+        X: Into<A>,
+        S: Scope<Timestamp = T>,
+        S::Timestamp: std::convert::Into<crate::timestamp::Time>,
+    {
+        self.create_attribute_from(scope, name, config, None)
+    }
+
+    /// Creates an attribute, like `create_attribute`, whose indices
+    /// are backfilled from the stream of the specified source that
+    /// is named like the attribute.
+    pub fn create_attribute_from<X, S>(
+        &mut self,
+        scope: &mut S,
+        name: X,
+        config: AttributeConfig,
+        backfill: Option<Box<dyn Sourceable<A, S>>>,
+    ) -> Result<(), Error>
+    where
+        X: Into<A>,
+        S: Scope<Timestamp = T>,
+        S::Timestamp: std::convert::Into<crate::timestamp::Time>,
+    {
+        let name = name.into();
+
+        // Stored attributes are indexed once queried, see `hydrate`.
+        if config.stored && !self.cold.contains_key(&name) {
+            if backfill.is_some() {
+                return Err(Error::unsupported(format!(
+                    "Stored attribute {} can't be backfilled.",
+                    name
+                )));
+            }
+
+            return self.store_attribute(name, config);
+        }
+
+        self.check_attribute(&name, &config)?;
+
+        let backfill = match backfill {
+            None => None,
+            Some(source) => {
+                let context = self.make_sourcing_context();
+                let backfill = source
+                    .source(scope, context)?
+                    .into_iter()
+                    .find(|(aid, _config, _stream)| *aid == name)
+                    .map(|(_aid, _config, stream)| stream)
+                    .ok_or_else(|| {
+                        SourceError::Misconfigured(format!(
+                            "Source doesn't provide data for {}.",
+                            name
+                        ))
+                    })?;
+
+                Some(backfill)
+            }
+        };
+
+        self.index_attribute(scope, name, config, backfill)
+    }
+
+    /// Checks whether an attribute of the specified name and
+    /// configuration can be created.
+    fn check_attribute(&self, name: &A, config: &AttributeConfig) -> Result<(), Error> {
+        // Both would retract datoms on their own.
+        if (config.monotonic || config.append_only)
+            && (config.is_cardinality_one() || config.time_to_live.is_some())
+        {
+            return Err(Error::incorrect(
+                "Monotonic and append-only attributes can't be last-write-wins or expire.",
+            ));
+        }
+
+        if self.internal.has_attribute(name) {
+            return Err(Error::conflict(format!(
+                "An attribute of name {} already exists.",
+                name
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Builds the input and indices of a new attribute, merging in
+    /// the specified updates, if any.
+    fn index_attribute<S>(
+        &mut self,
+        scope: &mut S,
+        name: A,
+        config: AttributeConfig,
+        backfill: Option<Stream<S, ((Value, Value), T, isize)>>,
+    ) -> Result<(), Error>
+    where
+        S: Scope<Timestamp = T>,
+        S::Timestamp: std::convert::Into<crate::timestamp::Time>,
+    {
+        let ((handle, cap), pairs) =
+            scope.new_unordered_input::<((Value, Value), S::Timestamp, isize)>();
+
+        let pairs = match backfill {
+            None => pairs,
+            Some(backfill) => scope.concatenate(vec![pairs, backfill]),
+        };
+
+        let tuples = match config.input_semantics {
+            InputSemantics::Raw => pairs.as_collection(),
+            InputSemantics::LastWriteWins => pairs.as_collection().last_write_wins(),
+            // Ensure that redundant (e,v) pairs don't cause
+            // misleading proposals during joining.
+            InputSemantics::Distinct => pairs.as_collection().distinct(),
+        };
+
+        let tuples = self.with_time_to_live(tuples, &config)?;
+
+        let mut scoped_domain = ((handle, cap), tuples).as_singleton_domain(name.clone());
+
+        if let Some(slack) = config.trace_slack {
+            scoped_domain = scoped_domain.with_slack(slack.into());
+        }
+
+        // LastWriteWins is a special case, because count, propose,
+        // and validate are all essentially the same.
+        if config.input_semantics != InputSemantics::LastWriteWins {
+            scoped_domain = scoped_domain.with_query_support(config.query_support);
+        }
+
+        if config.index_direction == IndexDirection::Both {
+            scoped_domain = scoped_domain.with_reverse_indices();
+        }
+
+        if config.fulltext {
+            scoped_domain = scoped_domain.with_fulltext_indices();
+        }
+
+        if config.spatial {
+            scoped_domain = scoped_domain.with_spatial_indices();
+        }
+
+        self.internal += scoped_domain.into();
+
+        // Singleton domains only know the default configuration, but
+        // readers of the attribute's config expect the real one.
+        self.internal.attributes.insert(name, config);
+
+        Ok(())
+    }
+
+    fn store_attribute(&mut self, name: A, config: AttributeConfig) -> Result<(), Error> {
+        if self.internal.has_attribute(&name) || self.cold.contains_key(&name) {
+            return Err(Error::conflict(format!(
+                "An attribute of name {} already exists.",
+                name
+            )));
+        }
+
+        // Stored updates are replayed as a single batch, in which
+        // only raw semantics are well-defined.
+        if config.input_semantics != InputSemantics::Raw {
+            return Err(Error::unsupported(
+                "Stored attributes only support raw input semantics.",
+            ));
+        }
+
+        self.persist_attribute(&name, &config)?;
+        self.cold.insert(name, config);
+
+        Ok(())
+    }
+
+    /// Handles an AliasAttribute request.
+    pub fn alias_attribute(&mut self, req: AliasAttribute) -> Result<(), Error> {
+        self.internal.alias(A::from(req.alias), A::from(req.name))
+    }
+
+    /// Handles a MigrateAttribute request, by creating the new
+    /// attribute under the old one's configuration and moving all
+    /// data over. Data is read from the old attribute's forward index
+    /// as a stream, rather than from its current contents, s.t. every
+    /// worker carries over everything that was transacted before the
+    /// migration, whether it has already been indexed or not.
+    pub fn migrate_attribute<S>(
+        &mut self,
+        scope: &mut S,
+        req: MigrateAttribute,
+    ) -> Result<(), Error>
+    where
+        S: Scope<Timestamp = T>,
+        S::Timestamp: std::convert::Into<crate::timestamp::Time>,
+    {
+        let from = A::from(req.from);
+        let to = A::from(req.to);
+
+        self.hydrate(scope, &from)?;
+
+        let config = match self.internal.attribute_config(&from) {
+            None => {
+                return Err(Error::not_found(format!(
+                    "Attribute {} does not exist.",
+                    from
+                )));
+            }
+            Some(config) => config.clone(),
+        };
+
+        if self.internal.has_attribute(&to) {
+            return Err(Error::conflict(format!("Attribute {} already exists.", to)));
+        }
+
+        self.check_attribute(&to, &config)?;
+
+        let backfill = match self.internal.forward_propose(&from) {
+            None => {
+                return Err(Error::unsupported(format!(
+                    "Attribute {} has no forward index to migrate from.",
+                    from
+                )));
+            }
+            Some(trace) => {
+                // The import keeps on delivering what reaches the old
+                // index until its input is closed below.
+                let (arranged, _shutdown_button) =
+                    trace.import_frontier(scope, &format!("Migrate({})", from));
+
+                arranged.as_collection(|e, v| (e.clone(), v.clone())).inner
+            }
+        };
+
+        self.index_attribute(scope, to.clone(), config, Some(backfill))?;
+        self.internal.migrate(&from, &to)
+    }
+
+    /// Handles a CopyAttribute request. The copy comprises everything
+    /// transacted up to the current epoch, read from the existing
+    /// attribute's forward index as a stream once that epoch has been
+    /// sealed on all workers. It is gathered on the first worker and
+    /// picked up via `fired_transactions`, s.t. it is introduced like
+    /// any other transaction, at a single timestamp.
+    pub fn copy_attribute<S>(&mut self, scope: &mut S, req: CopyAttribute) -> Result<(), Error>
+    where
+        S: Scope<Timestamp = T>,
+        S::Timestamp: std::convert::Into<crate::timestamp::Time>,
+    {
+        let from = A::from(req.from);
+        let to = A::from(req.to);
+
+        self.hydrate(scope, &from)?;
+
+        let config = match self.internal.attribute_config(&from) {
+            None => {
+                return Err(Error::not_found(format!(
+                    "Attribute {} does not exist.",
+                    from
+                )));
+            }
+            Some(config) => req.config.unwrap_or_else(|| config.clone()),
+        };
+
+        if self.internal.has_attribute(&to) {
+            return Err(Error::conflict(format!("Attribute {} already exists.", to)));
+        }
+
+        if let Some(ref function) = req.function {
+            function.validate(&req.constants)?;
+        }
+
+        let copied = match self.internal.forward_propose(&from) {
+            None => {
+                return Err(Error::unsupported(format!(
+                    "Attribute {} has no forward index to copy from.",
+                    from
+                )));
+            }
+            Some(trace) => {
+                let (arranged, shutdown_button) =
+                    trace.import_frontier(scope, &format!("Copy({})", from));
+
+                (
+                    arranged.as_collection(|e, v| (e.clone(), v.clone())).inner,
+                    shutdown_button,
+                )
+            }
+        };
+
+        self.create_attribute(scope, to.clone(), config)?;
+
+        let (stream, shutdown_button) = copied;
+        let mut shutdown_button = Some(shutdown_button);
+        let epoch = self.internal.epoch().clone();
+        let fired = self.fired.clone();
+        let function = req.function;
+        let constants = req.constants;
+
+        stream
+            .unary_frontier::<(), _, _, _>(Exchange::new(|_| 0), "Copy", move |cap, _info| {
+                // Held until the copy is complete, s.t. the server
+                // keeps stepping until it has been fired.
+                let mut cap = Some(cap.delayed(&epoch));
+                let mut buffer = Vec::new();
+                let mut contents = Vec::new();
+
+                move |input, _output| {
+                    input.for_each(|_time, data| {
+                        data.swap(&mut buffer);
+
+                        for (datum, t, diff) in buffer.drain(..) {
+                            if t.less_equal(&epoch) {
+                                contents.push((datum, diff));
+                            }
+                        }
+                    });
+
+                    if cap.is_none() || input.frontier.frontier().less_equal(&epoch) {
+                        return;
+                    }
+
+                    consolidate(&mut contents);
+
+                    let tx_data: Vec<Datom<A>> = contents
+                        .drain(..)
+                        .filter_map(|((e, v), diff)| {
+                            let v = match function {
+                                None => v,
+                                Some(ref function) => function.apply(&v, &constants)?,
+                            };
+
+                            Some(Datom(e, to.clone(), v, None, diff))
+                        })
+                        .collect();
+
+                    if !tx_data.is_empty() {
+                        fired.borrow_mut().push_back(tx_data);
+                    }
+
+                    if let Some(mut button) = shutdown_button.take() {
+                        button.press();
+                    }
+
+                    cap = None;
+                }
+            })
+            .probe_with(&mut self.probe);
+
+        Ok(())
+    }
+
+    /// Handles a DeriveAttribute request, by implementing the rule
+    /// and registering its results as a new attribute. The
+    /// underlying dataflow stays around until the attribute name is
+    /// cancelled, which leaves the attribute at its last state.
+    pub fn derive_attribute<S>(&mut self, scope: &mut S, req: DeriveAttribute) -> Result<(), Error>
+    where
+        S: Scope<Timestamp = T>,
+        S::Timestamp: std::convert::Into<crate::timestamp::Time>,
+    {
+        let rule = A::from(req.rule);
+        let name = A::from(req.name);
+
+        match self.internal.rules.get(&rule) {
+            None => {
+                return Err(Error::not_found(format!("Rule {} does not exist.", rule)));
+            }
+            Some(registered) => {
+                if registered.plan.variables().len() != 2 {
+                    return Err(Error::incorrect(format!(
+                        "Rule {} must bind exactly two variables to be derived as an attribute.",
+                        rule
+                    )));
+                }
+            }
+        }
+
+        if self.internal.has_attribute(&name) {
+            return Err(Error::conflict(format!(
+                "An attribute of name {} already exists.",
+                name
+            )));
+        }
+
+        let config = req.config.unwrap_or_else(|| AttributeConfig {
+            index_direction: IndexDirection::Both,
+            ..Default::default()
+        });
+
+        let (relation, shutdown_handle) = self.implement_relation(rule, scope)?;
+        let pairs = relation.map(|mut tuple| {
+            let v = tuple.pop().expect("derived tuples have two fields");
+            let e = tuple.pop().expect("derived tuples have two fields");
+            (e, v)
+        });
+
+        self.register_collection(name.clone(), config, &pairs)?;
+        self.shutdown_handles.insert(name, shutdown_handle);
+
+        Ok(())
+    }
+
+    /// Handles a RegisterTrigger request. Fired transactions are
+    /// collected by each worker for the results it holds, and must be
+    /// picked up via `fired_transactions`.
+    pub fn register_trigger<S>(&mut self, scope: &mut S, req: RegisterTrigger) -> Result<(), Error>
+    where
+        S: Scope<Timestamp = T>,
+        S::Timestamp: std::convert::Into<crate::timestamp::Time>,
+    {
+        let name = A::from(req.name);
+        let rule = A::from(req.rule);
+
+        if self.triggers.contains_key(&name) || self.shutdown_handles.contains_key(&name) {
+            return Err(Error::conflict(format!(
+                "A dataflow of name {} already exists.",
+                name
+            )));
+        }
+
+        let reads: HashSet<A> = collect_dependencies(&self.internal, &[rule.clone()])?
+            .into_iter()
+            .flat_map(|rule| rule.plan.dependencies().attributes.into_iter())
+            .collect();
+
+        let writes: HashSet<A> = req
+            .template
+            .iter()
+            .map(|datom| A::from(datom.a.clone()))
+            .collect();
+
+        if self.would_loop(&name, &reads, &writes) {
+            return Err(Error::incorrect(format!(
+                "Trigger {} would fire itself.",
+                name
+            )));
+        }
+
+        let arity = match self.internal.rules.get(&rule) {
+            None => return Err(Error::not_found(format!("Rule {} does not exist.", rule))),
+            Some(registered) => registered.plan.variables().len(),
+        };
+
+        for datom in req.template.iter() {
+            for template_value in [&datom.e, &datom.v].iter() {
+                if let TemplateValue::Field(index) = template_value {
+                    if *index >= arity {
+                        return Err(Error::incorrect(format!(
+                            "Rule {} has no field {}.",
+                            rule, index
+                        )));
+                    }
+                }
+            }
+        }
+
+        let template: Vec<(TemplateValue, A, TemplateValue, isize)> = req
+            .template
+            .into_iter()
+            .map(|datom| {
+                let diff = if datom.retract { -1 } else { 1 };
+                (datom.e, A::from(datom.a), datom.v, diff)
+            })
+            .collect();
+
+        let on = req.on;
+        let fired = self.fired.clone();
+
+        let (relation, shutdown_handle) = self.implement_relation(rule, scope)?;
+
+        relation
+            .consolidate()
+            .inspect(move |(tuple, _t, diff)| {
+                let is_match = match on {
+                    TriggerEvent::Appear => *diff > 0,
+                    TriggerEvent::Disappear => *diff < 0,
+                };
+
+                if is_match {
+                    let tx_data = template
+                        .iter()
+                        .map(|(e, a, v, diff)| {
+                            Datom(
+                                e.instantiate(tuple),
+                                a.clone(),
+                                v.instantiate(tuple),
+                                None,
+                                *diff,
+                            )
+                        })
+                        .collect();
+
+                    fired.borrow_mut().push_back(tx_data);
+                }
+            })
+            .probe_with(&mut self.probe);
+
+        self.triggers.insert(name.clone(), (reads, writes));
+        self.shutdown_handles.insert(name, shutdown_handle);
+
+        Ok(())
+    }
+
+    /// Handles an InsertInto request. Like triggers, insertions are
+    /// picked up via `fired_transactions`, with all changes that
+    /// arrive together transacted together.
+    pub fn insert_into<S>(&mut self, scope: &mut S, req: InsertInto) -> Result<(), Error>
+    where
+        S: Scope<Timestamp = T>,
+        S::Timestamp: std::convert::Into<crate::timestamp::Time>,
+    {
+        let name = A::from(req.name);
+        let rule = A::from(req.rule);
+        let attribute = A::from(req.attribute);
+
+        if self.triggers.contains_key(&name) || self.shutdown_handles.contains_key(&name) {
+            return Err(Error::conflict(format!(
+                "A dataflow of name {} already exists.",
+                name
+            )));
+        }
+
+        match self.internal.rules.get(&rule) {
+            None => {
+                return Err(Error::not_found(format!("Rule {} does not exist.", rule)));
+            }
+            Some(registered) => {
+                if registered.plan.variables().len() != 2 {
+                    return Err(Error::incorrect(format!(
+                        "Rule {} must bind exactly two variables to be inserted into an attribute.",
+                        rule
+                    )));
+                }
+            }
+        }
+
+        if !self.internal.has_attribute(&attribute) {
+            return Err(Error::not_found(format!(
+                "Attribute {} does not exist.",
+                attribute
+            )));
+        }
+
+        let reads: HashSet<A> = collect_dependencies(&self.internal, &[rule.clone()])?
+            .into_iter()
+            .flat_map(|rule| rule.plan.dependencies().attributes.into_iter())
+            .collect();
+
+        let mut writes = HashSet::new();
+        writes.insert(attribute.clone());
+
+        if self.would_loop(&name, &reads, &writes) {
+            return Err(Error::incorrect(format!(
+                "Insertion {} would feed into its own rule.",
+                name
+            )));
+        }
+
+        let fired = self.fired.clone();
+
+        let (relation, shutdown_handle) = self.implement_relation(rule, scope)?;
+
+        relation
+            .consolidate()
+            .inner
+            .inspect_batch(move |_t, updates| {
+                let tx_data: Vec<Datom<A>> = updates
+                    .iter()
+                    .map(|(tuple, _t, diff)| {
+                        Datom(
+                            tuple[0].clone(),
+                            attribute.clone(),
+                            tuple[1].clone(),
+                            None,
+                            *diff,
+                        )
+                    })
+                    .collect();
+
+                if !tx_data.is_empty() {
+                    fired.borrow_mut().push_back(tx_data);
+                }
+            })
+            .probe_with(&mut self.probe);
+
+        self.triggers.insert(name.clone(), (reads, writes));
+        self.shutdown_handles.insert(name, shutdown_handle);
+
+        Ok(())
+    }
+
+    /// Checks whether a trigger reading and writing the specified
+    /// attributes would, possibly via other triggers, cause itself
+    /// to fire again.
+    fn would_loop(&self, name: &A, reads: &HashSet<A>, writes: &HashSet<A>) -> bool {
+        let mut seen = HashSet::new();
+        let mut queue: VecDeque<&HashSet<A>> = VecDeque::new();
+        queue.push_back(writes);
+
+        while let Some(written) = queue.pop_front() {
+            if !written.is_disjoint(reads) {
+                return true;
+            }
+
+            for (other, (other_reads, other_writes)) in self.triggers.iter() {
+                if other != name && !seen.contains(other) && !written.is_disjoint(other_reads) {
+                    seen.insert(other.clone());
+                    queue.push_back(other_writes);
+                }
+            }
+        }
+
+        false
+    }
+
+    /// Returns all transactions enqueued by triggers since the last
+    /// call. These should be sequenced like any other transaction.
+    pub fn fired_transactions(&mut self) -> Vec<Vec<Datom<A>>> {
+        self.fired.borrow_mut().drain(..).collect()
+    }
+
+    /// Handles a RegisterConstraint request. Violations are shared
+    /// with all workers, s.t. each can check the transactions it
+    /// introduced.
+    pub fn register_constraint<S>(
+        &mut self,
+        scope: &mut S,
+        req: RegisterConstraint,
+    ) -> Result<(), Error>
+    where
+        S: Scope<Timestamp = T>,
+        S::Timestamp: std::convert::Into<crate::timestamp::Time>,
+    {
+        let name = A::from(req.name);
+
+        if self.constraints.contains(&name) || self.shutdown_handles.contains_key(&name) {
+            return Err(Error::conflict(format!(
+                "A dataflow of name {} already exists.",
+                name
+            )));
+        }
+
+        let (relation, shutdown_handle) = self.implement_relation(A::from(req.rule), scope)?;
+
+        let constraint = name.clone();
+        let violations = self.violations.clone();
+
+        relation
+            .consolidate()
+            .inner
+            .flat_map(|(tuple, t, diff)| if diff > 0 { Some((tuple, t)) } else { None })
+            .broadcast()
+            .inspect(move |(tuple, t)| {
+                violations
+                    .borrow_mut()
+                    .push((constraint.clone(), tuple.clone(), t.clone()))
+            })
+            .probe_with(&mut self.constraint_probe)
+            .probe_with(&mut self.probe);
+
+        self.constraints.insert(name.clone());
+        self.shutdown_handles.insert(name, shutdown_handle);
+
+        Ok(())
+    }
+
+    /// Returns true iff transactions have to be checked against
+    /// constraints.
+    pub fn has_constraints(&self) -> bool {
+        !self.constraints.is_empty()
+    }
+
+    /// Returns the outcome of constraint checks, for results to be
+    /// held back by, see `Validated`.
+    pub fn validation(&self) -> Rc<RefCell<Validation<T>>> {
+        self.validation.clone()
+    }
+
+    /// Schedules a successful transaction for being checked against
+    /// all constraints, returning the time at which it will have
+    /// been checked. The worker that introduced the transaction has
+    /// to do so, in order to learn about its outcome, and the first
+    /// worker, which rolls back violating transactions.
+    pub fn check_constraints(&mut self, tx_data: Vec<Datom<A>>) -> Option<T> {
+        if !self.has_constraints() {
+            return None;
+        }
+
+        let t = self.internal.epoch().clone();
+
+        match self.unchecked.back_mut() {
+            Some((last, unchecked)) if *last == t => unchecked.extend(tx_data),
+            _ => self.unchecked.push_back((t.clone(), tx_data)),
+        }
+
+        Some(t)
+    }
+
+    /// Returns the outcome of all constraint checks that have
+    /// completed since the last call. Transactions that violated a
+    /// constraint were rolled back already, by the first worker. All
+    /// transactions at the same time share their outcome.
+    pub fn checked_transactions(&mut self) -> Vec<(T, Result<(), Error>)> {
+        let mut checked = Vec::new();
+
+        while let Some((t, tx_data)) = self.unchecked.pop_front() {
+            if self.constraint_probe.less_equal(&t) {
+                self.unchecked.push_front((t, tx_data));
+                break;
+            }
+
+            let violated: Vec<String> = self
+                .violations
+                .borrow()
+                .iter()
+                .filter(|(_name, _tuple, violated_at)| *violated_at == t)
+                .map(|(name, tuple, _violated_at)| format!("{} {:?}", name, tuple))
+                .collect();
+
+            if violated.is_empty() {
+                checked.push((t, Ok(())));
+            } else {
+                let rolled_back = if self.index == 0 {
+                    let rolled_back_at = self.internal.epoch().clone();
+                    self.internal.roll_back(tx_data).map(|()| {
+                        self.validation
+                            .borrow_mut()
+                            .rollbacks
+                            .push((t.clone(), rolled_back_at));
+                    })
+                } else {
+                    Ok(())
+                };
+
+                let result = rolled_back.and_then(|()| {
+                    Err(Error::conflict(format!(
+                        "Transaction violates constraints: {}.",
+                        violated.join(", ")
+                    )))
+                });
+
+                checked.push((t, result));
+            }
+        }
+
+        // Results are held back up to here, and rollbacks only matter
+        // to results that haven't been released yet.
+        {
+            let mut validation = self.validation.borrow_mut();
+            let probe = &self.probe;
+
+            validation.decided = self
+                .constraint_probe
+                .with_frontier(|frontier| frontier.to_vec());
+            validation
+                .rollbacks
+                .retain(|(_violated, rolled_back)| probe.less_equal(rolled_back));
+            validation.notify();
+        }
+
+        // Violations that no pending transaction might be blamed for
+        // anymore can be dropped.
+        let probe = &self.constraint_probe;
+        self.violations
+            .borrow_mut()
+            .retain(|(_name, _tuple, violated_at)| probe.less_equal(violated_at));
+
+        checked
+    }
+
+    /// Resolves the path of an ExportState request within the export
+    /// directory. Paths escaping it, e.g. via `..`, are rejected.
+    pub fn export_path(&self, path: &str) -> Result<PathBuf, Error> {
+        let dir = match self.config.export_dir {
+            None => return Err(Error::unsupported("Exports without an export directory.")),
+            Some(ref dir) => dir,
+        };
+
+        let requested = Path::new(path);
+        let is_contained = requested.components().all(|component| match component {
+            Component::Normal(_) => true,
+            _ => false,
+        });
+
+        if path.is_empty() || !is_contained {
+            return Err(Error::incorrect(format!(
+                "Export path {} must be relative to the export directory.",
+                path
+            )));
+        }
+
+        Ok(Path::new(dir).join(requested))
+    }
+
+    /// Handles an ExportState request, returning the requests that
+    /// recreate the state held by this worker in another
+    /// computation. Attributes fed by transactions and rules are
+    /// recreated by the first worker. Sourced and derived attributes,
+    /// aliases, and interests have to be set up again.
+    pub fn export_state(&mut self, worker_index: usize) -> Vec<Request<A>> {
+        let mut requests = Vec::new();
+
+        if worker_index == 0 {
+            let mut names: Vec<A> = self
+                .internal
+                .attributes
+                .keys()
+                .filter(|name| {
+                    self.internal.is_transactable(name) || self.internal.is_drained(name)
+                })
+                .cloned()
+                .collect();
+            names.sort();
+
+            for name in names {
+                requests.push(Request::CreateAttribute(CreateAttribute {
+                    name: name.to_string(),
+                    config: self.internal.attributes[&name].clone(),
+                    backfill: None,
+                }));
+            }
+
+            let mut rules: Vec<Rule<A>> = self.internal.rules.values().cloned().collect();
+            rules.sort_by(|x, y| x.name.cmp(&y.name));
+
+            if !rules.is_empty() {
+                requests.push(Request::Register(Register {
+                    rules,
+                    publish: Vec::new(),
+                }));
+            }
+        }
+
+        let contents = self.internal.contents();
+        if !contents.is_empty() {
+            requests.push(Request::Transact(contents));
+        }
+
+        requests
+    }
+
+    /// Handles an Excise request. Every worker has to handle it, in
+    /// order to excise the data it holds. Lookup refs are resolved
+    /// once, before anything is retracted, which requires all of the
+    /// data to be held by a single worker.
+    pub fn excise(&mut self, req: Excise) -> Result<(), Error> {
+        let entity = match req.entity {
+            None => None,
+            Some(Value::LookupRef(_, _)) if self.peers > 1 => {
+                return Err(Error::unsupported(
+                    "Lookup refs can only be resolved with a single worker.",
+                ));
+            }
+            Some(e) => Some(self.internal.resolve(e)?),
+        };
+
+        let attribute = req.attribute.map(A::from);
+
+        // Attributes not indexed yet only live in the store.
+        let is_cold = attribute
+            .as_ref()
+            .map_or(false, |a| self.cold.contains_key(a));
+
+        let excised = if is_cold {
+            0
+        } else {
+            self.internal
+                .excise(entity.as_ref(), attribute.as_ref(), req.value.as_ref())?
+        };
+
+        let purged = self.excise_stored(entity.as_ref(), attribute.as_ref(), req.value.as_ref())?;
+
+        info!(
+            "Excised {} datoms, {} of them stored",
+            excised + purged,
+            purged
+        );
+
+        Ok(())
+    }
+
+    /// Removes the datoms an excision covers from requests handled
+    /// before, e.g. from a journal of them, returning how many were
+    /// removed. Lookup refs are resolved against the data indexed so
+    /// far, which still includes everything about to be excised.
+    pub fn excise_requests(
+        &mut self,
+        req: &Excise,
+        requests: &mut [Request<A>],
+    ) -> Result<usize, Error> {
+        let entity = match req.entity {
+            None => None,
+            Some(ref e) => Some(self.internal.resolve(e.clone())?),
+        };
+        let attribute = req.attribute.clone().map(A::from);
+
+        let internal = &mut self.internal;
+        let mut removed = 0;
+
+        for request in requests.iter_mut() {
+            let tx_data = match request {
+                Request::Transact(tx_data) => tx_data,
+                Request::TransactSync(sync) => &mut sync.tx_data,
+                _ => continue,
+            };
+
+            let before = tx_data.len();
+
+            tx_data.retain(|Datom(e, a, v, _, _)| {
+                let is_entity = match entity {
+                    None => true,
+                    Some(ref entity) => internal
+                        .resolve(e.clone())
+                        .map(|e| e == *entity)
+                        .unwrap_or(false),
+                };
+                let is_attribute = match attribute {
+                    None => true,
+                    Some(ref attribute) => {
+                        internal.canonical_name(a) == internal.canonical_name(attribute)
+                    }
+                };
+                let is_value = req
+                    .value
+                    .as_ref()
+                    .map(|(predicate, constant)| predicate.holds(v, constant))
+                    .unwrap_or(true);
+
+                !(is_entity && is_attribute && is_value)
+            });
+
+            removed += before - tx_data.len();
+        }
+
+        Ok(removed)
+    }
+
+    /// Schedules retractions according to the attribute's time to
+    /// live, if it has one. Fails for times to live the domain's
+    /// timestamps can't express.
+    fn with_time_to_live<S>(
+        &self,
+        pairs: Collection<S, (Value, Value), isize>,
+        config: &AttributeConfig,
+    ) -> Result<Collection<S, (Value, Value), isize>, Error>
+    where
+        S: Scope<Timestamp = T>,
+    {
+        match config.time_to_live {
+            None => Ok(pairs),
+            Some(TimeToLive::TxTime(ref ttl)) if !T::is_convertible(ttl) => {
+                Err(Error::incorrect(format!(
+                    "Time to live {:?} doesn't match the domain's timestamps.",
+                    ttl
+                )))
+            }
+            Some(TimeToLive::Instant(_))
+                if !T::is_convertible(&Time::Real(Duration::from_secs(0))) =>
+            {
+                Err(Error::unsupported(
+                    "Times to live relative to instants require a real time domain.",
+                ))
+            }
+            Some(ref ttl) => {
+                // Real time domains count from the start of the
+                // computation, whereas instants count from the Unix
+                // epoch.
+                let epoch = match *ttl {
+                    TimeToLive::TxTime(_) => Duration::from_secs(0),
+                    TimeToLive::Instant(_) => clock::since_unix_epoch()
+                        .checked_sub(self.t0.elapsed())
+                        .unwrap_or_default(),
+                };
+
+                Ok(pairs.expire(ttl, epoch))
+            }
+        }
     }
 
     /// Returns a fresh sourcing context, useful for installing 3DF
@@ -390,7 +3253,75 @@ where
             domain_probe: self.internal.domain_probe().clone(),
             timely_events: self.timely_events.clone().unwrap(),
             differential_events: self.differential_events.clone().unwrap(),
+            late_data: self.late_data.clone(),
+        }
+    }
+
+    /// Registers an attribute backed by an arbitrary collection of
+    /// (e, v) pairs, e.g. one computed by a surrounding timely
+    /// computation. This allows the library to be embedded without
+    /// any of the request handling.
+    pub fn register_collection<S, X>(
+        &mut self,
+        name: X,
+        config: AttributeConfig,
+        pairs: &Collection<S, (Value, Value), isize>,
+    ) -> Result<(), Error>
+    where
+        S: Scope<Timestamp = T>,
+        X: Into<A>,
+    {
+        let name = name.into();
+
+        self.check_attribute(&name, &config)?;
+
+        let pairs = match config.input_semantics {
+            InputSemantics::Raw => pairs.clone(),
+            InputSemantics::LastWriteWins => pairs.last_write_wins(),
+            // Ensure that redundant (e,v) pairs don't cause
+            // misleading proposals during joining.
+            InputSemantics::Distinct => pairs.distinct(),
+        };
+
+        let pairs = if config.is_monotonic() {
+            without_retractions(&name, pairs)
+        } else {
+            pairs
+        };
+
+        let pairs = self.with_time_to_live(pairs, &config)?;
+
+        let mut scoped_domain = pairs.as_singleton_domain(name.clone());
+
+        if let Some(slack) = config.trace_slack {
+            scoped_domain = scoped_domain.with_slack(slack.into());
+        }
+
+        // LastWriteWins is a special case, because count, propose,
+        // and validate are all essentially the same.
+        if config.input_semantics != InputSemantics::LastWriteWins {
+            scoped_domain = scoped_domain.with_query_support(config.query_support);
+        }
+
+        if config.index_direction == IndexDirection::Both {
+            scoped_domain = scoped_domain.with_reverse_indices();
+        }
+
+        if config.fulltext {
+            scoped_domain = scoped_domain.with_fulltext_indices();
         }
+
+        if config.spatial {
+            scoped_domain = scoped_domain.with_spatial_indices();
+        }
+
+        self.internal += scoped_domain.into();
+
+        // Singleton domains only know the default configuration, but
+        // readers of the attribute's config expect the real one.
+        self.internal.attributes.insert(name, config);
+
+        Ok(())
     }
 
     /// Handles a RegisterSource request.
@@ -413,34 +3344,10 @@ where
         // self.timely_events = None;
         // self.differential_events = None;
 
-        let mut attribute_streams = source.source(scope, context);
+        let mut attribute_streams = source.source(scope, context)?;
 
         for (aid, config, pairs) in attribute_streams.drain(..) {
-            let pairs = match config.input_semantics {
-                InputSemantics::Raw => pairs.as_collection(),
-                InputSemantics::LastWriteWins => pairs.as_collection().last_write_wins(),
-                // Ensure that redundant (e,v) pairs don't cause
-                // misleading proposals during joining.
-                InputSemantics::Distinct => pairs.as_collection().distinct(),
-            };
-
-            let mut scoped_domain = pairs.as_singleton_domain(aid);
-
-            if let Some(slack) = config.trace_slack {
-                scoped_domain = scoped_domain.with_slack(slack.into());
-            }
-
-            // LastWriteWins is a special case, because count, propose,
-            // and validate are all essentially the same.
-            if config.input_semantics != InputSemantics::LastWriteWins {
-                scoped_domain = scoped_domain.with_query_support(config.query_support);
-            }
-
-            if config.index_direction == IndexDirection::Both {
-                scoped_domain = scoped_domain.with_reverse_indices();
-            }
-
-            self.internal += scoped_domain.into();
+            self.register_collection(aid, config, &pairs.as_collection())?;
         }
 
         // if let Some(logger) = timely_logger {
@@ -473,31 +3380,718 @@ where
     /// Handles an Uninterest request, possibly cleaning up dataflows
     /// that are no longer interesting to any client.
     pub fn uninterest(&mut self, client: Token, name: &A) -> Result<(), Error> {
+        self.deferred.retain(|(_, deferred_client, interest)| {
+            *deferred_client != client || interest.name != name.to_string()
+        });
+
+        let name = self.shared_name(name);
+
+        // Late joiners are interested in a dataflow of their own.
+        let interests = &self.interests;
+        let name = &self
+            .catch_ups
+            .iter()
+            .find(|(catch_up, source)| {
+                **source == name
+                    && interests
+                        .get(*catch_up)
+                        .map_or(false, |clients| clients.contains(&client))
+            })
+            .map(|(catch_up, _source)| catch_up.clone())
+            .unwrap_or(name);
+
+        if let Some(names) = self.output_names.get_mut(name) {
+            names.remove(&client);
+            if names.is_empty() {
+                self.output_names.remove(name);
+            }
+        }
+
         // All workers keep track of every client's interests, s.t. they
         // know when to clean up unused dataflows.
         if let Some(entry) = self.interests.get_mut(name) {
             entry.remove(&client);
 
             if entry.is_empty() {
-                self.shutdown_query(name);
                 self.interests.remove(name);
+                self.release(name);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Shuts down the dataflow of a relation no one is interested in
+    /// anymore, unless late joiners are still being caught up from
+    /// its results.
+    fn release(&mut self, name: &A) {
+        if self.catch_ups.values().any(|source| source == name) {
+            return;
+        }
+
+        self.shutdown_query(name);
+
+        if let Some(source) = self.catch_ups.remove(name) {
+            if !self.interests.contains_key(&source) {
+                self.release(&source);
+            }
+        }
+    }
+
+    /// Handles a Cancel request, tearing down the specified dataflow
+    /// for all interested clients.
+    pub fn cancel(&mut self, name: &A) -> Result<(), Error> {
+        let name = &self.shared_name(name);
+        self.output_names.remove(name);
+
+        let was_interesting = self.interests.remove(name).is_some();
+        let was_running = self.shutdown_handles.contains_key(name);
+
+        if was_interesting || was_running {
+            self.shutdown_query(name);
+
+            let catch_ups: Vec<A> = self
+                .catch_ups
+                .iter()
+                .filter(|(_catch_up, source)| *source == name)
+                .map(|(catch_up, _source)| catch_up.clone())
+                .collect();
+
+            for catch_up in catch_ups.iter() {
+                self.interests.remove(catch_up);
+                self.output_names.remove(catch_up);
+                self.catch_ups.remove(catch_up);
+                self.shutdown_query(catch_up);
+            }
+
+            // Rules that were switched over take their version down
+            // with them, pending versions are taken over directly.
+            if let Some(version) = self.catch_ups.remove(name) {
+                self.shutdown_query(&version);
+            }
+
+            if let Some(swap) = self.swaps.remove(name) {
+                self.replace_rule(name.clone(), swap.plan);
+                self.shutdown_query(&swap.version);
+            }
+
+            Ok(())
+        } else {
+            Err(Error::not_found(format!("No dataflow for {}.", name)))
+        }
+    }
+
+    /// Allows the arranged results kept for late joiners to be
+    /// compacted up to the current epoch.
+    pub fn compact_results(&mut self) {
+        let frontier = [self.internal.epoch().clone()];
+
+        for trace in self.result_traces.values_mut() {
+            trace.advance_by(&frontier);
+            trace.distinguish_since(&frontier);
+        }
+    }
+
+    /// Handles a Validate request, by checking the rules against the
+    /// attributes and rules known at this point, as registering and
+    /// implementing them would. Each rule is implemented in the
+    /// specified scope, and shut down again right away. Nothing is
+    /// registered, but indices worst-case optimal joins require are
+    /// built, as they would be for an interest. Rules passing
+    /// validation might still be rejected, e.g. if required
+    /// attributes are removed in the meantime.
+    pub fn validate<S>(&mut self, scope: &mut S, req: &Register<A>) -> Vec<Diagnostic>
+    where
+        S: Scope<Timestamp = T>,
+        S::Timestamp: std::convert::Into<crate::timestamp::Time>,
+    {
+        let mut diagnostics = Vec::new();
+
+        let mut report = |rule: &A, error: Error| {
+            diagnostics.push(Diagnostic {
+                rule: rule.to_string(),
+                error,
+            });
+        };
+
+        for rule in req.rules.iter() {
+            if let Some(existing) = self.internal.rules.get(&rule.name) {
+                if existing.plan != rule.plan {
+                    report(
+                        &rule.name,
+                        Error::conflict(format!(
+                            "Rule {} is already registered with a different plan.",
+                            rule.name
+                        )),
+                    );
+                }
+            }
+
+            if let Err(error) = self.implement_named(scope, rule.name.clone(), &req.rules) {
+                report(&rule.name, error);
+            }
+        }
+
+        diagnostics
+    }
+
+    /// Handles an Inspect request. Index sizes only cover the shards
+    /// maintained by this worker.
+    pub fn inspect(&mut self, what: &Inspect) -> Result<Report<A>, Error> {
+        match what {
+            Inspect::Rules => {
+                let mut rules: Vec<Rule<A>> = self.internal.rules.values().cloned().collect();
+                rules.sort_by(|x, y| x.name.cmp(&y.name));
+
+                Ok(Report::Rules(rules))
+            }
+            Inspect::Attributes => {
+                let mut names: Vec<A> = self.internal.attributes.keys().cloned().collect();
+                names.sort();
+
+                let stats = names
+                    .into_iter()
+                    .map(|name| AttributeStats {
+                        name: name.to_string(),
+                        config: self.internal.attributes[&name].clone(),
+                        indices: self.internal.index_stats(&name),
+                        late_data: self
+                            .late_data
+                            .borrow()
+                            .get(&name.to_string())
+                            .cloned()
+                            .unwrap_or_default(),
+                    })
+                    .collect();
+
+                Ok(Report::Attributes(stats))
+            }
+            Inspect::Interests => {
+                let mut stats: Vec<InterestStats> = self
+                    .interests
+                    .iter()
+                    .map(|(name, clients)| InterestStats {
+                        name: name.to_string(),
+                        clients: clients.len(),
+                        running: self.shutdown_handles.contains_key(name),
+                    })
+                    .collect();
+                stats.sort_by(|x, y| x.name.cmp(&y.name));
+
+                Ok(Report::Interests(stats))
+            }
+            Inspect::Memory => {
+                let mut names: Vec<A> = self.result_traces.keys().cloned().collect();
+                names.sort();
+
+                let stats = names
+                    .into_iter()
+                    .filter_map(|name| {
+                        let budget = self.budget(&name).map(|(budget, _policy)| budget);
+
+                        let attributes = self.attribute_stats(&name);
+
+                        self.result_stats(&name).map(|results| MemoryStats {
+                            name: name.to_string(),
+                            results,
+                            attributes,
+                            budget,
+                        })
+                    })
+                    .collect();
+
+                Ok(Report::Memory(stats))
+            }
+            Inspect::Clients => Err(Error::unsupported(
+                "Client statistics are not tracked by the library.",
+            )),
+        }
+    }
+
+    /// Handles a Configure request. Nothing is applied, if any part of
+    /// the update is invalid.
+    pub fn configure(&mut self, req: Configure) -> Result<(), Error> {
+        let log_level = match req.log_level {
+            None => None,
+            Some(level) => match level.parse::<log::LevelFilter>() {
+                Err(_) => return Err(Error::incorrect(format!("Unknown log level {}.", level))),
+                Ok(level) => Some(level),
+            },
+        };
+
+        for name in req.trace_slack.keys() {
+            if !self.internal.has_attribute(&A::from(name.clone())) {
+                return Err(Error::not_found(format!("Unknown attribute {}.", name)));
+            }
+        }
+
+        if let Some(tick) = req.tick {
+            if tick == Duration::from_secs(0) {
+                self.config.tick = None;
+            } else {
+                self.config.tick = Some(tick);
+            }
+        }
+
+        if let Some(manual_advance) = req.manual_advance {
+            self.config.manual_advance = manual_advance;
+        }
+
+        if let Some(enable_optimizer) = req.enable_optimizer {
+            self.config.enable_optimizer = enable_optimizer;
+        }
+
+        if let Some(level) = log_level {
+            log::set_max_level(level);
+        }
+
+        for (name, slack) in req.trace_slack.into_iter() {
+            if let Some(config) = self.internal.attributes.get_mut(&A::from(name)) {
+                config.trace_slack = slack;
             }
         }
 
         Ok(())
     }
 
+    /// Handles a Shutdown request. Closes all inputs and returns the
+    /// last epoch sealed by doing so, up to and including which
+    /// results must be delivered, before the server can shut down
+    /// without losing any accepted inputs.
+    pub fn drain(&mut self) -> Vec<T> {
+        // Closing the inputs seals the current epoch, including
+        // whatever has been transacted into it.
+        self.internal.close_inputs();
+        self.internal.frontier()
+    }
+
+    /// Returns true iff everything transacted at the specified time
+    /// is visible as requested.
+    pub fn is_visible(&self, t: &T, visibility: Visibility) -> bool {
+        match visibility {
+            Visibility::Closed => !self.internal.domain_probe().less_equal(t),
+            Visibility::Incorporated => !self.probe.less_equal(t),
+        }
+    }
+
+    /// Returns true iff all results at times up to and including the
+    /// specified epochs have been computed.
+    pub fn is_drained(&self, sealed: &[T]) -> bool {
+        self.probe
+            .with_frontier(|frontier| sealed.iter().all(|t| !frontier.less_equal(t)))
+    }
+
     /// Cleans up all bookkeeping state for the specified client.
     pub fn disconnect_client(&mut self, client: Token) -> Result<(), Error> {
+        if self.config.session_timeout.is_some() {
+            // The session ends once it times out.
+            if let Some(session) = self.sessions.get_mut(&client) {
+                session.is_connected = false;
+            }
+
+            Ok(())
+        } else {
+            self.end_session(client)
+        }
+    }
+
+    /// Records that a client was heard from, via the specified
+    /// worker.
+    pub fn touch_session(&mut self, client: Token, owner: usize) {
+        let session = self.sessions.entry(client).or_insert_with(|| Session {
+            owner,
+            last_seen: Instant::now(),
+            rules: HashSet::new(),
+            is_connected: true,
+            is_ending: false,
+        });
+
+        session.last_seen = Instant::now();
+    }
+
+    /// Records that the specified rules were registered by a client,
+    /// s.t. they can be unregistered once its session ends.
+    pub fn claim_rules(&mut self, client: Token, names: Vec<A>) {
+        if let Some(session) = self.sessions.get_mut(&client) {
+            session.rules.extend(names);
+        }
+    }
+
+    /// Returns all clients connected via the specified worker that
+    /// have been silent for longer than the session timeout. Each is
+    /// reported only once, because its session should be ended on
+    /// all workers via an EndSession request.
+    pub fn expired_sessions(&mut self, worker_index: usize) -> Vec<Token> {
+        let timeout = match self.config.session_timeout {
+            None => return Vec::new(),
+            Some(timeout) => timeout,
+        };
+
+        let mut expired = Vec::new();
+
+        for (client, session) in self.sessions.iter_mut() {
+            let is_expired = session.last_seen.elapsed() >= timeout;
+
+            if session.owner == worker_index && !session.is_ending && is_expired {
+                session.is_ending = true;
+                expired.push(*client);
+            }
+        }
+
+        expired
+    }
+
+    /// Returns all dataflows set up via the specified worker that have
+    /// gone without connected subscribers for longer than the
+    /// hibernation period. Dataflows feeding sinks never hibernate.
+    /// Each is reported only once, because it should be torn down on
+    /// all workers via a Hibernate request.
+    pub fn idle_dataflows(&mut self, worker_index: usize) -> Vec<A> {
+        let period = match self.config.hibernate_after {
+            None => return Vec::new(),
+            Some(period) => period,
+        };
+
+        // Clients without a session, such as the server itself, never
+        // disconnect.
+        let sessions = &self.sessions;
+        let is_disconnected = |client: &Token| {
+            sessions
+                .get(client)
+                .map_or(false, |session| !session.is_connected)
+        };
+
+        let mut idle = Vec::new();
+
+        for (name, (owner, interest)) in self.subscriptions.iter() {
+            if *owner != worker_index || self.hibernating.contains(name) {
+                continue;
+            }
+
+            let is_idle = interest.sink.is_none()
+                && !self.swaps.contains_key(name)
+                && self.interests.get(name).map_or(false, |clients| {
+                    clients.iter().all(|client| is_disconnected(client))
+                });
+
+            if !is_idle {
+                self.idle_since.remove(name);
+            } else {
+                let since = self
+                    .idle_since
+                    .entry(name.clone())
+                    .or_insert_with(Instant::now);
+
+                if since.elapsed() >= period {
+                    self.hibernating.insert(name.clone());
+                    idle.push(name.clone());
+                }
+            }
+        }
+
+        idle
+    }
+
+    /// Handles a Hibernate request, by tearing down the dataflow of a
+    /// relation, as if all of its subscribers had lost interest.
+    /// Nothing happens if someone connected took an interest in the
+    /// meantime.
+    pub fn hibernate(&mut self, name: &A) -> Result<(), Error> {
+        self.idle_since.remove(name);
+        self.hibernating.remove(name);
+
+        let clients: Vec<Token> = match self.interests.get(name) {
+            None => return Ok(()),
+            Some(clients) => clients.iter().cloned().collect(),
+        };
+
+        let sessions = &self.sessions;
+        let is_connected = |client: &Token| {
+            sessions
+                .get(client)
+                .map_or(true, |session| session.is_connected)
+        };
+
+        if clients.iter().any(is_connected) {
+            return Ok(());
+        }
+
+        info!("Hibernating {}", name);
+
+        for client in clients.into_iter() {
+            self.uninterest(client, name)?;
+        }
+
+        Ok(())
+    }
+
+    /// Estimates the memory held by the arranged results of a relation.
+    fn result_stats(&mut self, name: &A) -> Option<TraceStats> {
+        let arity = self
+            .internal
+            .rules
+            .get(name)
+            .map_or(0, |rule| rule.plan.variables().len());
+
+        self.result_traces.get_mut(name).map(|trace| {
+            let mut stats = trace_stats(trace);
+            // Tuples keep their values on the heap.
+            stats.bytes += stats.updates * arity * std::mem::size_of::<Value>();
+            stats
+        })
+    }
+
+    /// Returns the memory budget of a dataflow, as requested by its
+    /// first subscriber.
+    fn budget(&self, name: &A) -> Option<(usize, BudgetPolicy)> {
+        let (_owner, interest) = self.subscriptions.get(name)?;
+        let limits = interest.limits.as_ref()?;
+        let policy = limits.budget_policy.unwrap_or(BudgetPolicy::Fail);
+
+        limits.max_memory.map(|budget| (budget, policy))
+    }
+
+    /// Estimates the memory held by the indices of all attributes a
+    /// relation reads, directly or via other rules. Indices are
+    /// shared between dataflows, and count against the budget of
+    /// each of them.
+    fn attribute_stats(&mut self, name: &A) -> TraceStats {
+        let attributes: HashSet<A> = collect_dependencies(&self.internal, &[name.clone()])
+            .unwrap_or_default()
+            .into_iter()
+            .flat_map(|rule| rule.plan.dependencies().attributes.into_iter())
+            .collect();
+
+        let mut stats = TraceStats::default();
+
+        for aid in attributes.iter() {
+            for index in self.internal.index_stats(aid).values() {
+                stats.batches += index.batches;
+                stats.updates += index.updates;
+                stats.bytes += index.bytes;
+            }
+        }
+
+        stats
+    }
+
+    /// Returns the memory this worker holds for dataflows with a
+    /// budget, if it changed since it was last reported. It has to
+    /// be shared with all workers via a MemoryUsage request.
+    pub fn memory_usage(&mut self, worker_index: usize) -> Option<MemoryUsage> {
+        let mut names: Vec<A> = self
+            .subscriptions
+            .keys()
+            .filter(|name| self.budget(name).is_some())
+            .cloned()
+            .collect();
+        names.sort();
+
+        let mut usage = HashMap::new();
+
+        for name in names.into_iter() {
+            let results = self.result_stats(&name).map_or(0, |stats| stats.bytes);
+            let attributes = self.attribute_stats(&name).bytes;
+
+            usage.insert(name, results + attributes);
+        }
+
+        if usage == self.reported_usage {
+            return None;
+        }
+
+        let mut bytes: Vec<(String, usize)> = usage
+            .iter()
+            .map(|(name, bytes)| (name.to_string(), *bytes))
+            .collect();
+        bytes.sort();
+
+        self.reported_usage = usage;
+
+        Some(MemoryUsage {
+            worker: worker_index,
+            bytes,
+        })
+    }
+
+    /// Handles a MemoryUsage request, by recording the memory the
+    /// reporting worker holds for each dataflow. Dataflows it no
+    /// longer reports don't hold any memory there anymore.
+    pub fn note_memory_usage(&mut self, usage: MemoryUsage) {
+        let reported: HashMap<A, usize> = usage
+            .bytes
+            .into_iter()
+            .map(|(name, bytes)| (A::from(name), bytes))
+            .collect();
+
+        for (name, reports) in self.memory_reports.iter_mut() {
+            if !reported.contains_key(name) {
+                reports.remove(&usage.worker);
+            }
+        }
+
+        for (name, bytes) in reported.into_iter() {
+            // Reports may still arrive for dataflows that were torn
+            // down in the meantime.
+            if self.subscriptions.contains_key(&name) {
+                self.memory_reports
+                    .entry(name)
+                    .or_insert_with(HashMap::new)
+                    .insert(usage.worker, bytes);
+            }
+        }
+    }
+
+    /// Returns all dataflows set up via the specified worker whose
+    /// results and attribute indices, summed across all workers,
+    /// exceed their memory budget. Each is reported only once,
+    /// because it should be torn down on all workers via an Evict
+    /// request.
+    pub fn exceeded_budgets(&mut self, worker_index: usize) -> Vec<A> {
+        let mut names: Vec<A> = self
+            .subscriptions
+            .iter()
+            .filter(|(name, (owner, _interest))| {
+                *owner == worker_index && !self.evicting.contains(*name)
+            })
+            .map(|(name, _)| name.clone())
+            .collect();
+        names.sort();
+
+        let mut exceeded = Vec::new();
+
+        for name in names.into_iter() {
+            if let Some((budget, _policy)) = self.budget(&name) {
+                let bytes: usize = self
+                    .memory_reports
+                    .get(&name)
+                    .map_or(0, |reports| reports.values().sum());
+
+                if bytes > budget {
+                    self.evicting.insert(name.clone());
+                    exceeded.push(name);
+                }
+            }
+        }
+
+        exceeded
+    }
+
+    /// Handles an Evict request, by tearing down a dataflow and those
+    /// catching up from it, for all subscribers. Returns who to
+    /// inform about it.
+    pub fn evict(&mut self, name: &A) -> Result<Eviction<Token>, Error> {
+        let (budget, policy) = match self.budget(name) {
+            None => return Err(Error::not_found(format!("{} has no memory budget.", name))),
+            Some(budget) => budget,
+        };
+
+        let catch_ups = &self.catch_ups;
+        let clients: Vec<Token> = self
+            .interests
+            .iter()
+            .filter(|(relation, _clients)| {
+                *relation == name || catch_ups.get(*relation) == Some(name)
+            })
+            .flat_map(|(_relation, clients)| clients.iter().cloned())
+            .collect();
+
+        info!("Evicting {}", name);
+        self.cancel(name)?;
+
+        let clients = clients
+            .into_iter()
+            .map(|client| {
+                let owner = self
+                    .sessions
+                    .get(&client)
+                    .map_or(0, |session| session.owner);
+                (owner, client)
+            })
+            .collect();
+
+        let error = match policy {
+            BudgetPolicy::Fail => Some(Error::interrupted(format!(
+                "Query exceeded its memory budget of {} bytes.",
+                budget
+            ))),
+            BudgetPolicy::Hibernate => None,
+        };
+
+        Ok(Eviction { clients, error })
+    }
+
+    /// Handles an EndSession request, by removing all interests of
+    /// the client and unregistering its rules, unless they are still
+    /// in use. Rules still in use are unregistered once another
+    /// session ends and they have fallen out of use.
+    pub fn end_session(&mut self, client: Token) -> Result<(), Error> {
+        self.deferred
+            .retain(|(_, deferred_client, _)| *deferred_client != client);
+
         let names: Vec<A> = self.interests.keys().cloned().collect();
 
         for query_name in names.iter() {
             self.uninterest(client, query_name)?
         }
 
+        let mut owned = std::mem::replace(&mut self.orphaned, HashSet::new());
+        if let Some(session) = self.sessions.remove(&client) {
+            owned.extend(session.rules);
+        }
+
+        // Rules might depend on one another, which is why we keep
+        // going until nothing else can be unregistered.
+        loop {
+            let unused: Vec<A> = owned
+                .iter()
+                .filter(|name| self.is_unused_rule(name))
+                .cloned()
+                .collect();
+
+            if unused.is_empty() {
+                break;
+            }
+
+            for name in unused.iter() {
+                info!("Unregistering {}", name);
+                owned.remove(name);
+                self.unregister(name);
+            }
+        }
+
+        self.orphaned = owned;
+
         Ok(())
     }
 
+    fn unregister(&mut self, name: &A) {
+        if let Some(rule) = self.internal.rules.remove(name) {
+            if self.plans.get(&rule.plan) == Some(name) {
+                self.plans.remove(&rule.plan);
+            }
+        }
+
+        self.shared.remove(name);
+    }
+
+    fn is_unused_rule(&self, name: &A) -> bool {
+        let is_running =
+            self.interests.contains_key(name) || self.shutdown_handles.contains_key(name);
+        let is_claimed = self
+            .sessions
+            .values()
+            .any(|session| session.rules.contains(name));
+        let is_depended_upon = self
+            .internal
+            .rules
+            .values()
+            .any(|rule| rule.name != *name && rule.plan.dependencies().names.contains(name))
+            || self.shared.values().any(|shared| shared == name);
+
+        !is_running && !is_claimed && !is_depended_upon
+    }
+
     /// Returns true iff the probe is behind any input handle. Mostly
     /// used as a convenience method during testing. Using this within
     /// `step_while` is not safe in general and might lead to stalls.
@@ -508,11 +4102,15 @@ where
 
     /// Helper for registering, publishing, and indicating interest in
     /// a single, named query. Used for testing.
-    pub fn test_single<S: Scope<Timestamp = T>>(
+    pub fn test_single<S>(
         &mut self,
         scope: &mut S,
         rule: Rule<A>,
-    ) -> Collection<S, Vec<Value>, isize> {
+    ) -> Collection<S, Vec<Value>, isize>
+    where
+        S: Scope<Timestamp = T>,
+        S::Timestamp: std::convert::Into<crate::timestamp::Time>,
+    {
         let interest_name = rule.name.clone();
         let publish_name = rule.name.clone();
 
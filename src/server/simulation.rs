@@ -0,0 +1,315 @@
+//! A deterministic harness driving a server through a script of
+//! commands, on a single worker and in virtual time. Every command is
+//! handled as its own transaction, after which the computation runs
+//! until it has caught up, s.t. the outputs observed after each
+//! command do not depend on scheduling or the wall clock. Requests
+//! are handled by the same handlers as in the server binary, which
+//! allows protocol-level behaviour, such as snapshot markers or
+//! acknowledgements, to be regression tested without any networking.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+use timely::communication::Allocate;
+use timely::worker::Worker;
+
+use crate::server::handlers::Handling;
+use crate::server::{Configuration, Interest, Request, RequestId, Server, TxId, Visibility};
+use crate::{Aid, Client, Output};
+
+/// The client issuing commands on behalf of the server itself, such
+/// as transactions fired by triggers.
+pub const SYSTEM: Client = std::usize::MAX - 3;
+
+/// A batch of requests, as sent by a client.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct Command {
+    /// The client sending the requests.
+    pub client: Client,
+    /// An id to be acknowledged once all requests were handled.
+    pub id: Option<RequestId>,
+    /// The requests to handle.
+    pub requests: Vec<Request<Aid>>,
+}
+
+/// A server driven by a script, rather than by clients.
+pub struct Simulation<'w, Al: Allocate> {
+    worker: &'w mut Worker<Al>,
+    /// The simulated server.
+    pub server: Server<Aid, u64, Client>,
+    next_tx: u64,
+    // Interests to be issued again, once the current command is done.
+    reissued: Vec<Interest>,
+    // Transactions waiting on constraint checks, by the time they
+    // will have been checked at, and whether they have to become
+    // visible afterwards.
+    awaiting: Vec<(u64, Option<Visibility>, Client, Option<RequestId>, TxId)>,
+    // Acknowledgements held back until the computation has caught up.
+    synced: Vec<(Client, RequestId, TxId)>,
+    // Dataflows to tear down, as reported by their sinks.
+    cancellations: Rc<RefCell<Vec<String>>>,
+    send: Sender<Output>,
+    recv: Receiver<Output>,
+}
+
+impl<'w, Al: Allocate> Simulation<'w, Al> {
+    /// Creates a simulation on the specified worker, which must be
+    /// the only one in its computation.
+    pub fn new(worker: &'w mut Worker<Al>, config: Configuration) -> Self {
+        assert_eq!(worker.peers(), 1, "Simulations run on a single worker.");
+
+        let (send, recv) = channel();
+
+        Simulation {
+            worker,
+            server: Server::new(config),
+            next_tx: 0,
+            reissued: Vec::new(),
+            awaiting: Vec::new(),
+            synced: Vec::new(),
+            cancellations: Rc::new(RefCell::new(Vec::new())),
+            send,
+            recv,
+        }
+    }
+
+    /// Runs the entire script, returning the outputs produced by each
+    /// command.
+    pub fn run(&mut self, script: Vec<Command>) -> Vec<Vec<Output>> {
+        script
+            .into_iter()
+            .map(|command| self.handle(command))
+            .collect()
+    }
+
+    /// Handles a single command as the next transaction, runs the
+    /// computation until it has caught up, and returns all outputs
+    /// produced in the meantime, including those of any
+    /// transactions fired by triggers in response.
+    pub fn handle(&mut self, command: Command) -> Vec<Output> {
+        self.execute(command);
+
+        loop {
+            let server = &self.server;
+            self.worker.step_while(|| server.is_any_outdated());
+
+            let checked = self.server.checked_transactions();
+            let cancelled: Vec<String> = self.cancellations.borrow_mut().drain(..).collect();
+            let fired = self.server.fired_transactions();
+            let swapped = self.server.caught_up_swaps(0);
+            let idle = self.server.idle_dataflows(0);
+            if let Some(usage) = self.server.memory_usage(0) {
+                self.server.note_memory_usage(usage);
+            }
+            let exceeded = self.server.exceeded_budgets(0);
+            let caught_up = self.server.caught_up_interests();
+            if checked.is_empty()
+                && cancelled.is_empty()
+                && fired.is_empty()
+                && swapped.is_empty()
+                && idle.is_empty()
+                && exceeded.is_empty()
+                && caught_up.is_empty()
+            {
+                break;
+            }
+
+            // Transactions are acknowledged once they passed all
+            // constraints, or rejected.
+            for (t, result) in checked.into_iter() {
+                let (done, awaiting) = self
+                    .awaiting
+                    .drain(..)
+                    .partition(|(awaited, _, _, _, _)| *awaited == t);
+                self.awaiting = awaiting;
+
+                for (_t, visibility, client, id, tx) in done.into_iter() {
+                    let output = match (result.clone(), id) {
+                        (Err(error), id) => Output::Error(client, error, tx, id),
+                        (Ok(()), None) => continue,
+                        (Ok(()), Some(id)) if visibility.is_some() => {
+                            self.synced.push((client, id, tx));
+                            continue;
+                        }
+                        (Ok(()), Some(id)) => Output::Ack(client, id, tx),
+                    };
+
+                    self.send
+                        .send(output)
+                        .expect("internal channel send failed");
+                }
+            }
+
+            for name in cancelled.into_iter() {
+                self.execute(Command {
+                    client: SYSTEM,
+                    id: None,
+                    requests: vec![Request::Cancel(name)],
+                });
+            }
+
+            for tx_data in fired.into_iter() {
+                self.execute(Command {
+                    client: SYSTEM,
+                    id: None,
+                    requests: vec![Request::Transact(tx_data)],
+                });
+            }
+
+            for name in swapped.into_iter() {
+                self.execute(Command {
+                    client: SYSTEM,
+                    id: None,
+                    requests: vec![Request::CompleteSwap(name)],
+                });
+            }
+
+            for name in idle.into_iter() {
+                self.execute(Command {
+                    client: SYSTEM,
+                    id: None,
+                    requests: vec![Request::Hibernate(name)],
+                });
+            }
+
+            for name in exceeded.into_iter() {
+                self.execute(Command {
+                    client: SYSTEM,
+                    id: None,
+                    requests: vec![Request::Evict(name)],
+                });
+            }
+
+            for (_owner, client, id, interest) in caught_up.into_iter() {
+                self.execute(Command {
+                    client,
+                    id,
+                    requests: vec![Request::Interest(interest)],
+                });
+            }
+        }
+
+        // All transactions are visible now.
+        for (client, id, tx) in self.synced.drain(..) {
+            self.send
+                .send(Output::Ack(client, id, tx))
+                .expect("internal channel send failed");
+        }
+
+        self.server
+            .internal
+            .advance()
+            .expect("failed to advance domain");
+        self.server.compact_results();
+
+        self.recv.try_iter().collect()
+    }
+
+    fn execute(&mut self, command: Command) {
+        self.next_tx += 1;
+
+        let Command {
+            client,
+            id,
+            requests,
+        } = command;
+        let last_tx = self.next_tx - 1;
+
+        self.server.note_tx(last_tx);
+
+        if client != SYSTEM {
+            self.server.touch_session(client, 0);
+        }
+
+        let mut handling = Handling::new(
+            0,
+            client,
+            last_tx,
+            id,
+            deliver(&self.send),
+            self.cancellations.clone(),
+        );
+
+        let mut failed = false;
+
+        for req in requests.into_iter() {
+            let result = match req {
+                // Virtual time advances with every command, so ticks
+                // have nothing left to do.
+                Request::Tick => Ok(()),
+                req => self.server.handle(self.worker, &mut handling, req),
+            };
+
+            if let Err(error) = result {
+                failed = true;
+                (handling.send)(Output::Error(client, error, last_tx, id));
+            }
+        }
+
+        if !failed {
+            if let Some(t) = handling.deferred.take() {
+                let visibility = handling.synced.map(|(_t, visibility)| visibility);
+                self.awaiting.push((t, visibility, client, id, last_tx));
+            } else if handling.held_back {
+                // Acknowledged once the interest is issued again.
+            } else if let (Some(_), Some(id)) = (handling.synced.as_ref(), id) {
+                self.synced.push((client, id, last_tx));
+            } else if let Some(id) = id {
+                self.send
+                    .send(Output::Ack(client, id, last_tx))
+                    .expect("internal channel send failed");
+            }
+        }
+
+        self.reissued.extend(
+            handling
+                .reissued
+                .drain(..)
+                .map(|(_subscriber, interest)| interest),
+        );
+
+        self.server
+            .internal
+            .advance_epoch(self.next_tx)
+            .expect("failed to advance epoch");
+
+        // Deferred interests that can now be bound are handled as if
+        // their clients had just sent them.
+        for (_owner, client, interest) in self.server.bound_interests() {
+            self.execute(Command {
+                client,
+                id: None,
+                requests: vec![Request::Interest(interest)],
+            });
+        }
+
+        let reissued: Vec<Interest> = self.reissued.drain(..).collect();
+        for interest in reissued.into_iter() {
+            self.execute(Command {
+                client: SYSTEM,
+                id: None,
+                requests: vec![Request::Interest(interest)],
+            });
+        }
+    }
+}
+
+/// Returns a function handing outputs to the simulation. Batches of
+/// results are delivered in a canonical order, s.t. scripts can
+/// expect them.
+fn deliver(send: &Sender<Output>) -> impl Fn(Output) + Clone + 'static {
+    let send = send.clone();
+
+    move |out| {
+        let out = match out {
+            Output::QueryDiff(name, mut results) => {
+                results.sort();
+                Output::QueryDiff(name, results)
+            }
+            other => other,
+        };
+
+        send.send(out).expect("internal channel send failed");
+    }
+}
@@ -0,0 +1,122 @@
+//! Versions of the request format. Clients may state the version
+//! they speak in the envelope around their requests, which are then
+//! upgraded to the current shapes before being handled. Fields added
+//! with a default don't warrant a new version, only changes breaking
+//! the requests of existing clients do.
+
+use serde::de::DeserializeOwned;
+use serde_json::Value as Json;
+
+use crate::server::Envelope;
+use crate::{AsAid, AttributeConfig, InputSemantics, ProtocolError};
+
+/// The current request format.
+pub const CURRENT_VERSION: u32 = 2;
+
+/// The oldest request format that can still be upgraded.
+pub const OLDEST_VERSION: u32 = 1;
+
+// Everything that is needed to tell how to parse an envelope.
+#[derive(Deserialize)]
+struct Header {
+    #[serde(default)]
+    version: Option<u32>,
+}
+
+fn malformed<E: std::string::ToString>(error: E) -> ProtocolError {
+    ProtocolError::Malformed(error.to_string())
+}
+
+/// Parses an identified batch of requests, upgrading them from the
+/// version stated in the envelope. Envelopes without a version are
+/// expected to be in the current format.
+pub fn parse_envelope<A>(string: &str) -> Result<Envelope<A>, ProtocolError>
+where
+    A: AsAid + From<&'static str> + DeserializeOwned,
+{
+    let header: Header = serde_json::from_str(string).map_err(malformed)?;
+
+    match header.version.unwrap_or(CURRENT_VERSION) {
+        CURRENT_VERSION => serde_json::from_str(string).map_err(malformed),
+        version => {
+            let mut envelope: Json = serde_json::from_str(string).map_err(malformed)?;
+
+            match envelope.as_object_mut() {
+                None => return Err(malformed("Envelopes must be objects.")),
+                Some(fields) => {
+                    let requests = fields
+                        .get_mut("requests")
+                        .ok_or_else(|| malformed("Envelopes must hold requests."))?;
+
+                    upgrade(requests, version)?;
+                    fields.insert("version".to_string(), Json::from(CURRENT_VERSION));
+                }
+            }
+
+            serde_json::from_value(envelope).map_err(malformed)
+        }
+    }
+}
+
+/// Upgrades a list of requests in the specified version to the
+/// current one, one version at a time.
+pub fn upgrade(requests: &mut Json, version: u32) -> Result<(), ProtocolError> {
+    if version < OLDEST_VERSION || version > CURRENT_VERSION {
+        return Err(ProtocolError::UnsupportedVersion(version));
+    }
+
+    let requests = requests
+        .as_array_mut()
+        .ok_or_else(|| malformed("Requests must be a list."))?;
+
+    for from in version..CURRENT_VERSION {
+        for request in requests.iter_mut() {
+            match from {
+                1 => upgrade_v1(request)?,
+                _ => unreachable!(),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Version 1 transacted datoms as [diff, e, a, v], optionally followed
+// by a time, and created attributes from their input semantics alone.
+fn upgrade_v1(request: &mut Json) -> Result<(), ProtocolError> {
+    if let Some(tx_data) = request.get_mut("Transact").and_then(Json::as_array_mut) {
+        for datom in tx_data.iter_mut() {
+            let fields = datom
+                .as_array_mut()
+                .ok_or_else(|| malformed("Datoms must be lists."))?;
+
+            if fields.len() != 4 && fields.len() != 5 {
+                return Err(malformed(format!(
+                    "Datoms must have 4 or 5 fields, not {}.",
+                    fields.len()
+                )));
+            }
+
+            let diff = fields.remove(0);
+            if fields.len() == 3 {
+                fields.push(Json::Null);
+            }
+            fields.push(diff);
+        }
+    }
+
+    if let Some(req) = request
+        .get_mut("CreateAttribute")
+        .and_then(Json::as_object_mut)
+    {
+        if let Some(semantics) = req.remove("semantics") {
+            let semantics: InputSemantics = serde_json::from_value(semantics).map_err(malformed)?;
+            let config =
+                serde_json::to_value(AttributeConfig::tx_time(semantics)).map_err(malformed)?;
+
+            req.insert("config".to_string(), config);
+        }
+    }
+
+    Ok(())
+}
@@ -29,6 +29,12 @@ pub struct AssocIn {
     /// each change. A granularity of n means that only the
     /// sub-structure at level n will be forwarded on a change.
     pub stateful: Option<usize>,
+    /// Attributes declared to hold at most one value per entity.
+    /// These resolve to their latest value, which retracting values
+    /// it replaced doesn't affect. Filled in from the schema when
+    /// subscribing, see `plan::pull::cardinality_one`.
+    #[serde(default)]
+    pub cardinality_one: Vec<String>,
 }
 
 impl<T> Sinkable<T> for AssocIn
@@ -57,6 +63,7 @@ where
         };
 
         let granularity = self.stateful.unwrap_or(1);
+        let cardinality_one = self.cardinality_one.clone();
 
         let mut vector = Vec::new();
 
@@ -86,7 +93,7 @@ where
                                 let t = cap.time();
 
                                 let mut map = Map::new();
-                                merge_paths(&mut map, paths_at_time, granularity);
+                                merge_paths(&mut map, paths_at_time, granularity, &cardinality_one);
 
                                 let keys: Vec<String> = map.keys().cloned().collect();
 
@@ -106,7 +113,12 @@ where
                                 // Differential time, apply each group
                                 // and produce outputs
 
-                                let changes = merge_paths(states, paths_at_time, granularity);
+                                let changes = merge_paths(
+                                    states,
+                                    paths_at_time,
+                                    granularity,
+                                    &cardinality_one,
+                                );
 
                                 output.session(&cap).give_iterator(changes.iter().map(
                                     |change_key| {
@@ -141,6 +153,7 @@ fn merge_paths<T>(
     acc: &mut Map<String, JValue>,
     mut paths: Vec<(Vec<crate::Value>, T, isize)>,
     granularity: usize,
+    cardinality_one: &[String],
 ) -> Vec<Vec<String>>
 where
     T: Timestamp + Lattice + std::convert::Into<Time>,
@@ -183,7 +196,8 @@ where
             if change_key.len() < granularity {
                 change_key.push(leaf_key.clone());
             }
-            acc.insert(leaf_key, serde_json::Value::from(leaf_val));
+            let is_one = cardinality_one.contains(&leaf_key);
+            assoc_leaf(acc, leaf_key, JValue::from(leaf_val), diff, is_one);
         } else {
             let first_key = parse_key(path[0].clone());
 
@@ -214,11 +228,8 @@ where
 
             // At the lowest level, we finally insert the leaf value.
             if let Object(map) = entry {
-                if diff > 0 {
-                    map.insert(leaf_key, serde_json::Value::from(leaf_val));
-                } else {
-                    map.remove(&leaf_key);
-                }
+                let is_one = cardinality_one.contains(&leaf_key);
+                assoc_leaf(map, leaf_key, JValue::from(leaf_val), diff, is_one);
             }
         }
 
@@ -229,3 +240,21 @@ where
     changes.dedup();
     changes
 }
+
+/// Applies a single change to the value of an attribute.
+fn assoc_leaf(
+    map: &mut Map<String, JValue>,
+    key: String,
+    value: JValue,
+    diff: isize,
+    is_one: bool,
+) {
+    if diff > 0 {
+        map.insert(key, value);
+    } else if !is_one || map.get(&key) == Some(&value) {
+        // Cardinality one attributes hold a single value, so
+        // retracting a value that has been replaced already is a
+        // no-op.
+        map.remove(&key);
+    }
+}
@@ -0,0 +1,90 @@
+//! Display formatting applied to results on their way out, s.t.
+//! presentation concerns don't have to be addressed in plans.
+
+use crate::Value;
+
+/// A single formatting step. Steps are applied in order.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub enum Format {
+    /// Renders instants as ISO 8601 strings in UTC, such as
+    /// `2000-02-29T12:34:56.789Z`.
+    IsoInstants,
+    /// Renders entity ids as strings, for clients that can't
+    /// represent all 64 bit integers.
+    StringEids,
+    /// Renames a field. Only affects results delivered via a `Named`
+    /// sink.
+    Rename(String, String),
+}
+
+/// Applies all value formatting steps to the provided tuple.
+pub fn format_tuple(formats: &[Format], mut tuple: Vec<Value>) -> Vec<Value> {
+    for format in formats.iter() {
+        match *format {
+            Format::IsoInstants => {
+                for value in tuple.iter_mut() {
+                    if let Value::Instant(millis) = *value {
+                        *value = Value::String(iso_8601(millis));
+                    }
+                }
+            }
+            Format::StringEids => {
+                for value in tuple.iter_mut() {
+                    if let Value::Eid(eid) = *value {
+                        *value = Value::String(eid.to_string());
+                    }
+                }
+            }
+            Format::Rename(_, _) => {}
+        }
+    }
+
+    tuple
+}
+
+/// Applies all renaming steps to the provided field names.
+pub fn format_fields(formats: &[Format], fields: &mut [String]) {
+    for format in formats.iter() {
+        if let Format::Rename(ref from, ref to) = *format {
+            for field in fields.iter_mut() {
+                if field == from {
+                    *field = to.clone();
+                }
+            }
+        }
+    }
+}
+
+/// Formats milliseconds since the epoch as a UTC timestamp.
+fn iso_8601(millis: u64) -> String {
+    let days = millis / 86_400_000;
+    let millis_of_day = millis % 86_400_000;
+
+    // Converts days since the epoch into a civil date, cf.
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let day_of_era = z - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        millis_of_day / 3_600_000,
+        millis_of_day / 60_000 % 60,
+        millis_of_day / 1000 % 60,
+        millis_of_day % 1000
+    )
+}
@@ -0,0 +1,74 @@
+//! Results as changes to the tuples identified by some of their
+//! fields, the way UI stores (e.g. Redux or Elm) apply them.
+
+use crate::{ResultDiff, Value};
+
+/// A change to the result tuple identified by a key.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub enum KeyedChange {
+    /// The key now identifies the tuple, replacing whatever it
+    /// identified before.
+    Upsert(Vec<Value>, Vec<Value>),
+    /// The key doesn't identify any tuple anymore.
+    Remove(Vec<Value>),
+}
+
+impl KeyedChange {
+    /// The key this change applies to.
+    pub fn key(&self) -> &[Value] {
+        match *self {
+            KeyedChange::Upsert(ref key, _) => key,
+            KeyedChange::Remove(ref key) => key,
+        }
+    }
+}
+
+/// Turns a batch of results into a single change per key, made up of
+/// the specified tuple fields. Keys are expected to identify at most
+/// one tuple at any time. A key's changes are applied in order of
+/// time, with retractions before additions at the same time, s.t.
+/// replacing one tuple by another results in an upsert. Changes are
+/// ordered by key.
+pub fn keyed_changes<T: Ord>(results: Vec<ResultDiff<T>>, fields: &[usize]) -> Vec<KeyedChange> {
+    let mut keyed: Vec<(Vec<Value>, T, isize, Vec<Value>)> = results
+        .into_iter()
+        .map(|(tuple, t, diff)| {
+            let key = fields
+                .iter()
+                .filter_map(|field| tuple.get(*field).cloned())
+                .collect();
+
+            (key, t, diff, tuple)
+        })
+        .collect();
+
+    keyed.sort();
+
+    let mut changes: Vec<KeyedChange> = Vec::new();
+
+    for (key, _t, diff, tuple) in keyed.into_iter() {
+        let is_same_key = changes.last().map_or(false, |last| last.key() == &key[..]);
+
+        let change = if diff > 0 {
+            KeyedChange::Upsert(key, tuple)
+        } else {
+            // Retracting a tuple other than the one just upserted
+            // leaves the upsert in place.
+            if let Some(KeyedChange::Upsert(_, ref upserted)) = changes.last() {
+                if is_same_key && *upserted != tuple {
+                    continue;
+                }
+            }
+
+            KeyedChange::Remove(key)
+        };
+
+        if is_same_key {
+            *changes.last_mut().unwrap() = change;
+        } else {
+            changes.push(change);
+        }
+    }
+
+    changes
+}
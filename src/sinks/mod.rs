@@ -1,17 +1,20 @@
 //! Types and operators to feed outputs into external systems.
 
+use std::cmp::Ordering;
 use std::fs::File;
 use std::io::{LineWriter, Write};
-use std::time::Instant;
 
 use timely::dataflow::channels::pact::ParallelizationContract;
 use timely::dataflow::operators::generic::{Operator, OutputHandle};
 use timely::dataflow::operators::probe::Probe;
 use timely::dataflow::{ProbeHandle, Scope, Stream};
+use timely::progress::frontier::AntichainRef;
 use timely::progress::Timestamp;
 
 use differential_dataflow::lattice::Lattice;
 
+use crate::clock::Instant;
+use crate::server::Limits;
 use crate::{Error, Output, ResultDiff, Time};
 
 // #[cfg(feature = "csv-source")]
@@ -23,6 +26,19 @@ use crate::{Error, Output, ResultDiff, Time};
 pub mod assoc_in;
 #[cfg(feature = "serde_json")]
 pub use self::assoc_in::AssocIn;
+#[cfg(feature = "serde_json")]
+pub mod named;
+#[cfg(feature = "serde_json")]
+pub use self::named::Named;
+
+pub mod format;
+pub use self::format::{format_fields, format_tuple, Format};
+
+pub mod keyed;
+pub use self::keyed::{keyed_changes, KeyedChange};
+
+pub mod transactional;
+pub use self::transactional::Transactional;
 
 /// A struct encapsulating any state required to create sinks.
 pub struct SinkingContext {
@@ -32,6 +48,212 @@ pub struct SinkingContext {
     pub granularity: Option<Time>,
 }
 
+/// Sorts a batch of results lexicographically by the specified tuple
+/// fields. Ties are broken by the full tuple, then time, then diff,
+/// s.t. the resulting order does not depend on the order of inputs.
+pub fn sort_by_fields<T: Ord>(results: &mut [ResultDiff<T>], fields: &[usize]) {
+    results.sort_by(|(x, x_time, x_diff), (y, y_time, y_diff)| {
+        fields
+            .iter()
+            .map(|field| x.get(*field).cmp(&y.get(*field)))
+            .find(|ordering| *ordering != Ordering::Equal)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| x.cmp(y))
+            .then_with(|| x_time.cmp(y_time))
+            .then_with(|| x_diff.cmp(y_diff))
+    });
+}
+
+/// Tracks the output frontier of a subscription, in order to inform
+/// clients once their initial snapshot is complete and whenever
+/// results become complete through a new frontier.
+pub struct ProgressTracker<T> {
+    name: String,
+    initial_complete: Option<T>,
+    progress: bool,
+    frontier: Vec<T>,
+}
+
+impl<T> ProgressTracker<T>
+where
+    T: Timestamp + std::convert::Into<Time>,
+{
+    /// Creates a new tracker for the named subscription. A marker
+    /// will be produced once the frontier passes `initial_complete`,
+    /// progress notifications only if `progress` is set.
+    pub fn new(name: String, initial_complete: Option<T>, progress: bool) -> Self {
+        ProgressTracker {
+            name,
+            initial_complete,
+            progress,
+            frontier: vec![T::minimum()],
+        }
+    }
+
+    /// Reports any outputs implied by the provided frontier, which
+    /// must be that of the subscription's final operator, after
+    /// all available inputs have been forwarded.
+    pub fn observe(&mut self, frontier: AntichainRef<T>) -> Vec<Output> {
+        let mut outputs = Vec::new();
+
+        if let Some(as_of) = self.initial_complete.clone() {
+            if !frontier.less_equal(&as_of) {
+                outputs.push(Output::InitialComplete(self.name.clone(), as_of.into()));
+                self.initial_complete = None;
+            }
+        }
+
+        if self.progress && self.frontier[..] != frontier[..] {
+            self.frontier = frontier.to_vec();
+            outputs.push(Output::Progress(
+                self.name.clone(),
+                self.frontier.iter().cloned().map(Into::into).collect(),
+            ));
+        }
+
+        outputs
+    }
+}
+
+/// Enforces resource limits on the results of a subscription. Limits
+/// are checked whenever results are sent or the subscription's
+/// frontier advances.
+pub struct Limiter<T> {
+    limits: Limits,
+    as_of: T,
+    started_at: Instant,
+    sent: usize,
+}
+
+impl<T: Timestamp> Limiter<T> {
+    /// Creates a new limiter, starting the clock on the initial
+    /// computation, which is complete once the frontier passes
+    /// `as_of`.
+    pub fn new(limits: Limits, as_of: T) -> Self {
+        Limiter {
+            limits,
+            as_of,
+            started_at: Instant::now(),
+            sent: 0,
+        }
+    }
+
+    /// Accounts for the specified number of additional results
+    /// having been sent, reporting an error if any limit exceeded.
+    pub fn observe(&mut self, sent: usize, frontier: AntichainRef<T>) -> Result<(), Error> {
+        self.sent += sent;
+
+        if let Some(max_output) = self.limits.max_output {
+            if self.sent > max_output {
+                return Err(max_output_exceeded(max_output));
+            }
+        }
+
+        self.check_runtime(frontier)
+    }
+
+    /// Accounts for the specified number of results about to be
+    /// sent, reporting an error instead if sending them would exceed
+    /// the maximum output size.
+    pub fn admit(&mut self, count: usize) -> Result<(), Error> {
+        if let Some(max_output) = self.limits.max_output {
+            if self.sent + count > max_output {
+                return Err(max_output_exceeded(max_output));
+            }
+        }
+
+        self.sent += count;
+
+        Ok(())
+    }
+
+    /// Reports an error if the initial computation, which is in
+    /// progress for as long as `frontier` hasn't passed `as_of`,
+    /// exceeded the maximum runtime.
+    pub fn check_runtime(&self, frontier: AntichainRef<T>) -> Result<(), Error> {
+        if let Some(max_runtime) = self.limits.max_runtime {
+            if frontier.less_equal(&self.as_of) && self.started_at.elapsed() > max_runtime {
+                return Err(Error::interrupted(format!(
+                    "Initial computation exceeded the maximum runtime of {:?}.",
+                    max_runtime
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn max_output_exceeded(max_output: usize) -> Error {
+    Error::interrupted(format!(
+        "Query exceeded the maximum output size of {} results.",
+        max_output
+    ))
+}
+
+/// Provides the `limit` method.
+pub trait Limit<S: Scope> {
+    /// Forwards results for as long as they stay within the limits
+    /// enforced by `limiter`. Batches that would exceed them are
+    /// never forwarded. Instead, the first violation is reported via
+    /// `on_error`, and all later results are dropped. Workers passing
+    /// no limiter forward everything.
+    fn limit<P, F>(
+        &self,
+        pact: P,
+        limiter: Option<Limiter<S::Timestamp>>,
+        on_error: F,
+    ) -> Stream<S, ResultDiff<S::Timestamp>>
+    where
+        P: ParallelizationContract<S::Timestamp, ResultDiff<S::Timestamp>>,
+        F: FnMut(Error) + 'static;
+}
+
+impl<S: Scope> Limit<S> for Stream<S, ResultDiff<S::Timestamp>> {
+    fn limit<P, F>(
+        &self,
+        pact: P,
+        mut limiter: Option<Limiter<S::Timestamp>>,
+        mut on_error: F,
+    ) -> Stream<S, ResultDiff<S::Timestamp>>
+    where
+        P: ParallelizationContract<S::Timestamp, ResultDiff<S::Timestamp>>,
+        F: FnMut(Error) + 'static,
+    {
+        let mut violated = false;
+        let mut buffer = Vec::new();
+
+        self.unary_frontier(pact, "Limit", move |_cap, _info| {
+            move |input, output| {
+                input.for_each(|time, data| {
+                    data.swap(&mut buffer);
+
+                    if violated {
+                        buffer.clear();
+                    } else if let Some(Err(error)) = limiter.as_mut().map(|l| l.admit(buffer.len()))
+                    {
+                        buffer.clear();
+                        violated = true;
+                        on_error(error);
+                    } else {
+                        output.session(&time).give_vec(&mut buffer);
+                    }
+                });
+
+                if !violated {
+                    if let Some(Err(error)) = limiter
+                        .as_ref()
+                        .map(|l| l.check_runtime(input.frontier.frontier()))
+                    {
+                        violated = true;
+                        on_error(error);
+                    }
+                }
+            }
+        })
+    }
+}
+
 /// An external system that wants to receive result diffs.
 pub trait Sinkable<T>
 where
@@ -62,6 +284,9 @@ pub enum Sink {
     /// Nested Hash-Maps
     #[cfg(feature = "serde_json")]
     AssocIn(AssocIn),
+    /// Maps from field names to values
+    #[cfg(feature = "serde_json")]
+    Named(Named),
 }
 
 impl<T> Sinkable<T> for Sink
@@ -128,6 +353,8 @@ where
             }
             #[cfg(feature = "serde_json")]
             Sink::AssocIn(ref sink) => sink.sink(stream, pact, probe, context),
+            #[cfg(feature = "serde_json")]
+            Sink::Named(ref sink) => sink.sink(stream, pact, probe, context),
             _ => unimplemented!(),
         }
     }
@@ -0,0 +1,74 @@
+//! Operator and utilities to deliver results as maps from field
+//! names to values, rather than as positional tuples.
+
+use timely::dataflow::channels::pact::ParallelizationContract;
+use timely::dataflow::operators::generic::Operator;
+use timely::dataflow::{ProbeHandle, Scope, Stream};
+use timely::progress::Timestamp;
+
+use differential_dataflow::lattice::Lattice;
+
+use serde_json::map::Map;
+use serde_json::Value as JValue;
+
+use crate::{Error, Output, ResultDiff, Time};
+
+use super::{Sinkable, SinkingContext};
+
+/// A sink attaching names to tuple positions. Each result is sent as
+/// a separate JSON object, such as `{"name": "Mabel", "age": 12}`.
+/// Positions without a name are omitted.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct Named {
+    /// Field names, in the order of the tuple positions they refer
+    /// to.
+    pub fields: Vec<String>,
+}
+
+impl<T> Sinkable<T> for Named
+where
+    T: Timestamp + Lattice + std::convert::Into<Time>,
+{
+    fn sink<S, P>(
+        &self,
+        stream: &Stream<S, ResultDiff<T>>,
+        pact: P,
+        _probe: &mut ProbeHandle<T>,
+        context: SinkingContext,
+    ) -> Result<Option<Stream<S, Output>>, Error>
+    where
+        S: Scope<Timestamp = T>,
+        P: ParallelizationContract<S::Timestamp, ResultDiff<T>>,
+    {
+        let fields = self.fields.clone();
+        let name = context.name;
+        let mut vector = Vec::new();
+
+        let named = stream.unary(pact, "Named", move |_cap, _info| {
+            move |input, output| {
+                input.for_each(|cap, data| {
+                    data.swap(&mut vector);
+
+                    let mut session = output.session(&cap);
+
+                    for (tuple, time, diff) in vector.drain(..) {
+                        let object: Map<String, JValue> = fields
+                            .iter()
+                            .cloned()
+                            .zip(tuple.into_iter().map(JValue::from))
+                            .collect();
+
+                        session.give(Output::Json(
+                            name.clone(),
+                            JValue::Object(object),
+                            time.into(),
+                            diff,
+                        ));
+                    }
+                });
+            }
+        });
+
+        Ok(Some(named))
+    }
+}
@@ -0,0 +1,90 @@
+//! Operator batching the results of several relations per timestamp.
+
+use std::collections::HashMap;
+
+use timely::dataflow::channels::pact::ParallelizationContract;
+use timely::dataflow::operators::generic::Operator;
+use timely::dataflow::{Scope, Stream};
+
+use differential_dataflow::lattice::Lattice;
+
+use crate::{Output, ResultDiff, Time};
+
+/// Provides the `transactional` method.
+pub trait Transactional<S: Scope> {
+    /// Delivers the results of a group of relations, tagged with the
+    /// name of the relation they belong to, one timestamp at a
+    /// time. All results for a timestamp are followed by a `Commit`
+    /// marker, upon which clients may apply them atomically.
+    fn transactional<P>(&self, pact: P, name: String) -> Stream<S, Output>
+    where
+        P: ParallelizationContract<S::Timestamp, (String, ResultDiff<S::Timestamp>)>;
+}
+
+impl<S> Transactional<S> for Stream<S, (String, ResultDiff<S::Timestamp>)>
+where
+    S: Scope,
+    S::Timestamp: Lattice + std::convert::Into<Time>,
+{
+    fn transactional<P>(&self, pact: P, name: String) -> Stream<S, Output>
+    where
+        P: ParallelizationContract<S::Timestamp, (String, ResultDiff<S::Timestamp>)>,
+    {
+        let mut stash = HashMap::new();
+        let mut buffer = Vec::new();
+
+        self.unary_notify(
+            pact,
+            "Transactional",
+            vec![],
+            move |input, output, notificator| {
+                input.for_each(|time, data| {
+                    data.swap(&mut buffer);
+
+                    // Updates are stashed by their own time, rather
+                    // than by that of the batch they arrived in, as the
+                    // two needn't agree.
+                    for (member, (tuple, t, diff)) in buffer.drain(..) {
+                        stash
+                            .entry(t.clone())
+                            .or_insert_with(|| {
+                                notificator.notify_at(time.delayed(&t));
+                                Vec::new()
+                            })
+                            .push((member, (tuple, t, diff)));
+                    }
+                });
+
+                notificator.for_each(|time, _count, _notificator| {
+                    if let Some(mut updates) = stash.remove(time.time()) {
+                        updates.sort();
+
+                        let mut batches: Vec<(String, Vec<ResultDiff<Time>>)> = Vec::new();
+
+                        for (member, (tuple, t, diff)) in updates.drain(..) {
+                            let diff: ResultDiff<Time> = (tuple, t.into(), diff);
+
+                            if batches
+                                .last()
+                                .map(|(last, _)| last == &member)
+                                .unwrap_or(false)
+                            {
+                                batches.last_mut().unwrap().1.push(diff);
+                            } else {
+                                batches.push((member, vec![diff]));
+                            }
+                        }
+
+                        let mut session = output.session(&time);
+
+                        for (member, diffs) in batches.drain(..) {
+                            session.give(Output::GroupDiff(name.clone(), member, diffs));
+                        }
+
+                        session.give(Output::Commit(name.clone(), time.time().clone().into()));
+                    }
+                });
+            },
+        )
+    }
+}
@@ -1,17 +1,34 @@
 //! Operator and utilities to source data from csv files.
 
 use std::rc::Rc;
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
 use timely::dataflow::operators::generic::builder_rc::OperatorBuilder;
 use timely::dataflow::{Scope, Stream};
 
-// use chrono::DateTime;
+use chrono::DateTime;
 
-use crate::sources::{Sourceable, SourcingContext};
-use crate::{AsAid, Eid, Value};
+use crate::clock::{self, Instant};
+use crate::sources::{LatePolicy, Partitioning, Sourceable, SourcingContext, Watermark};
+use crate::{AsAid, Eid, Error, SourceError, Value};
 use crate::{AttributeConfig, InputSemantics};
 
+/// Parses an event time, as a duration since the Unix epoch.
+fn parse_event_time(field: &str) -> Option<Duration> {
+    match field.parse::<u64>() {
+        Ok(millis) => Some(Duration::from_millis(millis)),
+        Err(_) => {
+            let millis = DateTime::parse_from_rfc3339(field).ok()?.timestamp_millis();
+
+            if millis >= 0 {
+                Some(Duration::from_millis(millis as u64))
+            } else {
+                None
+            }
+        }
+    }
+}
+
 /// A local filesystem data source.
 #[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
 pub struct CsvFile<A: AsAid> {
@@ -27,8 +44,22 @@ pub struct CsvFile<A: AsAid> {
     pub flexible: bool,
     /// Special column offset for the entity id.
     pub eid_offset: usize,
-    /// Special column offset for the timestamp.
+    /// Special column offset for the event time of each record,
+    /// either in milliseconds since the Unix epoch, or as an RFC 3339
+    /// datetime. Records are introduced at their event time, rather
+    /// than when they are read, given an offset. Real time domains
+    /// count from the start of the computation, so records from
+    /// before then are late.
     pub timestamp_offset: Option<usize>,
+    /// How far event times may trail the latest one read, before
+    /// records are considered late.
+    #[serde(default)]
+    pub max_lateness: Option<Duration>,
+    /// What to do with the datoms of late records, per attribute.
+    /// Attributes without a policy have late datoms introduced at the
+    /// watermark.
+    #[serde(default)]
+    pub late_policies: Vec<(A, LatePolicy<A>)>,
     /// Specifies the column offsets and their value types, that
     /// should be introduced.
     pub schema: Vec<(A, (usize, Value))>,
@@ -36,6 +67,10 @@ pub struct CsvFile<A: AsAid> {
     pub fuel: Option<usize>,
     /// Scheduling interval.
     pub interval: Option<Duration>,
+    /// How records are split up between workers, all of which read
+    /// the file.
+    #[serde(default)]
+    pub partitioning: Partitioning,
 }
 
 impl<A: AsAid, S: Scope<Timestamp = Duration>> Sourceable<A, S> for CsvFile<A> {
@@ -43,13 +78,76 @@ impl<A: AsAid, S: Scope<Timestamp = Duration>> Sourceable<A, S> for CsvFile<A> {
         &self,
         scope: &mut S,
         context: SourcingContext<S::Timestamp>,
-    ) -> Vec<(
-        A,
-        AttributeConfig,
-        Stream<S, ((Value, Value), Duration, isize)>,
-    )> {
+    ) -> Result<
+        Vec<(
+            A,
+            AttributeConfig,
+            Stream<S, ((Value, Value), Duration, isize)>,
+        )>,
+        Error,
+    > {
         let filename = self.path.clone();
 
+        for (aid, (_offset, type_hint)) in self.schema.iter() {
+            match type_hint {
+                Value::String(_) | Value::Number(_) | Value::Eid(_) => {}
+                _ => {
+                    return Err(SourceError::Misconfigured(format!(
+                        "Column for {} must be a String, Number, or Eid.",
+                        aid
+                    ))
+                    .into());
+                }
+            }
+        }
+
+        // Late datoms are routed via the index of the output they are
+        // introduced into, with dead-letter attributes following the
+        // ones in the schema.
+        let mut dead_letters: Vec<A> = Vec::new();
+        let mut policies = vec![LatePolicy::Correct; self.schema.len()];
+
+        for (aid, policy) in self.late_policies.iter() {
+            let idx = self
+                .schema
+                .iter()
+                .position(|(other, _)| other == aid)
+                .ok_or_else(|| {
+                    SourceError::Misconfigured(format!("Attribute {} is not in the schema.", aid))
+                })?;
+
+            policies[idx] = match policy {
+                LatePolicy::Correct => LatePolicy::Correct,
+                LatePolicy::Drop => LatePolicy::Drop,
+                LatePolicy::DeadLetter(dead_letter) => {
+                    if self.schema.iter().any(|(other, _)| other == dead_letter) {
+                        return Err(SourceError::Misconfigured(format!(
+                            "Dead-letter attribute {} is also in the schema.",
+                            dead_letter
+                        ))
+                        .into());
+                    }
+
+                    let output = match dead_letters.iter().position(|other| other == dead_letter) {
+                        Some(position) => position,
+                        None => {
+                            dead_letters.push(dead_letter.clone());
+                            dead_letters.len() - 1
+                        }
+                    };
+
+                    LatePolicy::DeadLetter(self.schema.len() + output)
+                }
+            };
+        }
+
+        let reader = csv::ReaderBuilder::new()
+            .has_headers(self.has_headers)
+            .delimiter(self.delimiter)
+            .comment(self.comment)
+            .from_path(&filename)
+            .map_err(|error| SourceError::Io(format!("{}: {}", filename, error)))?;
+
         // The following is mostly the innards of
         // `generic::source`. We use a builder directly, because we
         // need multiple outputs (one for each attribute the user has
@@ -61,11 +159,12 @@ impl<A: AsAid, S: Scope<Timestamp = Duration>> Sourceable<A, S> for CsvFile<A> {
         // Order is very important here, because otherwise the
         // capabilities won't match up with the output streams later
         // on (when creating sessions). We stick to the order dictated
-        // by the schema.
-        let mut wrappers = Vec::with_capacity(self.schema.len());
-        let mut streams = Vec::with_capacity(self.schema.len());
+        // by the schema, followed by dead-letter attributes.
+        let num_outputs = self.schema.len() + dead_letters.len();
+        let mut wrappers = Vec::with_capacity(num_outputs);
+        let mut streams = Vec::with_capacity(num_outputs);
 
-        for _ in self.schema.iter() {
+        for _ in 0..num_outputs {
             let (wrapper, stream) = demux.new_output();
             wrappers.push(wrapper);
             streams.push(stream);
@@ -75,14 +174,8 @@ impl<A: AsAid, S: Scope<Timestamp = Duration>> Sourceable<A, S> for CsvFile<A> {
             let activator = Rc::new(scope.activator_for(&operator_info.address[..]));
 
             let worker_index = scope.index();
-            // let num_workers = scope.peers();
-
-            let reader = csv::ReaderBuilder::new()
-                .has_headers(self.has_headers)
-                .delimiter(self.delimiter)
-                .comment(self.comment)
-                .from_path(&filename)
-                .expect("failed to create reader");
+            let num_workers = scope.peers();
+            let partitioning = self.partitioning;
 
             let mut iterator = reader.into_records();
 
@@ -91,12 +184,18 @@ impl<A: AsAid, S: Scope<Timestamp = Duration>> Sourceable<A, S> for CsvFile<A> {
 
             let schema = self.schema.clone();
             let eid_offset = self.eid_offset;
-            // let timestamp_offset = self.timestamp_offset;
+            let timestamp_offset = self.timestamp_offset;
+            let mut watermark = timestamp_offset
+                .map(|_| Watermark::new(self.max_lateness.unwrap_or_default(), Default::default()));
             let total_fuel: i64 = self.fuel.unwrap_or(256) as i64;
 
             // Grab scheduler handle for deferred re-activation.
             let scheduler = context.scheduler;
+            let late_data = context.late_data;
             let t0 = context.t0;
+            let epoch = clock::since_unix_epoch()
+                .checked_sub(t0.elapsed())
+                .unwrap_or_default();
             let interval = self.interval.unwrap_or(Duration::from_secs(1));
 
             move |_frontiers| {
@@ -109,61 +208,130 @@ impl<A: AsAid, S: Scope<Timestamp = Duration>> Sourceable<A, S> for CsvFile<A> {
                 } else {
                     let mut fuel = total_fuel;
 
-                    let mut handles = Vec::with_capacity(schema.len());
+                    let mut handles = Vec::with_capacity(wrappers.len());
                     for wrapper in wrappers.iter_mut() {
                         handles.push(wrapper.activate());
                     }
 
-                    let mut sessions = Vec::with_capacity(schema.len());
+                    let mut sessions = Vec::with_capacity(handles.len());
                     for (idx, handle) in handles.iter_mut().enumerate() {
                         sessions.push(handle.session(&capabilities[idx]));
                     }
 
-                    let time = Instant::now().duration_since(t0);
+                    let arrival = Instant::now().duration_since(t0);
 
-                    info!("Ingesting at {:?}", time);
+                    info!("Ingesting at {:?}", arrival);
 
                     while let Some(result) = iterator.next() {
-                        let record = result.expect("read error");
-
-                        // if datum_index % num_workers == worker_index {
-                        let eid = Value::Eid(record[eid_offset].parse::<Eid>().expect("not a eid"));
-                        // let time = match timestamp_offset {
-                        //     None => Default::default(),
-                        //     Some(timestamp_offset) => {
-                        //         let epoch =
-                        //             DateTime::parse_from_rfc3339(&record[timestamp_offset])
-                        //                 .expect("not a valid rfc3339 datetime")
-                        //                 .timestamp();
-
-                        //         if epoch >= 0 {
-                        //             epoch as u64
-                        //         } else {
-                        //             panic!("invalid epoch");
-                        //         }
-                        //     }
-                        // };
-
-                        for (idx, (_aid, (offset, type_hint))) in schema.iter().enumerate() {
+                        let record = match result {
+                            Ok(record) => record,
+                            Err(error) => {
+                                error!("[W{}] skipping unreadable record: {}", worker_index, error);
+                                datum_index += 1;
+                                continue;
+                            }
+                        };
+
+                        let eid = match record[eid_offset].parse::<Eid>() {
+                            Ok(eid) => Value::Eid(eid),
+                            Err(_) => {
+                                error!(
+                                    "[W{}] skipping record {} without a valid eid",
+                                    worker_index, datum_index
+                                );
+                                datum_index += 1;
+                                continue;
+                            }
+                        };
+
+                        if !partitioning.is_responsible(
+                            0,
+                            datum_index,
+                            &eid,
+                            worker_index,
+                            num_workers,
+                        ) {
+                            datum_index += 1;
+                            continue;
+                        }
+
+                        let (time, late) = match (timestamp_offset, watermark.as_mut()) {
+                            (Some(timestamp_offset), Some(watermark)) => {
+                                let event_time = parse_event_time(&record[timestamp_offset]);
+
+                                match event_time.map(|event_time| event_time.checked_sub(epoch)) {
+                                    Some(Some(event_time)) => {
+                                        let late = watermark.is_late(event_time);
+                                        (watermark.observe(event_time), late)
+                                    }
+                                    // Event times before the start of
+                                    // the computation can't be
+                                    // introduced at all.
+                                    Some(None) => (watermark.current(), true),
+                                    None => {
+                                        error!(
+                                            "[W{}] skipping record {} without a valid event time",
+                                            worker_index, datum_index
+                                        );
+                                        datum_index += 1;
+                                        continue;
+                                    }
+                                }
+                            }
+                            _ => (arrival, false),
+                        };
+
+                        for (idx, (aid, (offset, type_hint))) in schema.iter().enumerate() {
                             let v = match type_hint {
-                                Value::String(_) => Value::String(record[*offset].to_string()),
-                                Value::Number(_) => Value::Number(
-                                    record[*offset].parse::<i64>().expect("not a number"),
-                                ),
+                                Value::String(_) => {
+                                    Some(Value::String(record[*offset].to_string()))
+                                }
+                                Value::Number(_) => {
+                                    record[*offset].parse::<i64>().ok().map(Value::Number)
+                                }
                                 Value::Eid(_) => {
-                                    Value::Eid(record[*offset].parse::<Eid>().expect("not a eid"))
+                                    record[*offset].parse::<Eid>().ok().map(Value::Eid)
+                                }
+                                _ => unreachable!(),
+                            };
+
+                            let v = match v {
+                                Some(v) => v,
+                                None => {
+                                    error!(
+                                        "[W{}] skipping ill-typed {} in record {}",
+                                        worker_index, aid, datum_index
+                                    );
+                                    continue;
                                 }
-                                _ => panic!(
-                                    "Only String, Number, and Eid are supported at the moment."
-                                ),
                             };
 
                             let tuple = (eid.clone(), v);
-                            sessions[idx].give((tuple, time, 1));
+
+                            if !late {
+                                sessions[idx].give((tuple, time, 1));
+                                continue;
+                            }
+
+                            let mut late_data = late_data.borrow_mut();
+                            let counts = late_data.entry(aid.to_string()).or_default();
+
+                            match policies[idx] {
+                                LatePolicy::Correct => {
+                                    counts.corrected += 1;
+                                    sessions[idx].give((tuple, time, 1));
+                                }
+                                LatePolicy::Drop => {
+                                    counts.dropped += 1;
+                                }
+                                LatePolicy::DeadLetter(output) => {
+                                    counts.dead_lettered += 1;
+                                    sessions[output].give((tuple, time, 1));
+                                }
+                            }
                         }
 
                         num_datums_read += 1;
-                        // }
 
                         datum_index += 1;
 
@@ -180,8 +348,12 @@ impl<A: AsAid, S: Scope<Timestamp = Duration>> Sourceable<A, S> for CsvFile<A> {
                         );
                         capabilities.drain(..);
                     } else {
-                        // Incorporate processing time in downgrade
-                        let time = Instant::now().duration_since(t0);
+                        // Incorporate processing time in downgrade,
+                        // unless records carry their own.
+                        let time = match watermark.as_mut() {
+                            None => Instant::now().duration_since(t0),
+                            Some(watermark) => watermark.advance(),
+                        };
 
                         for cap in capabilities.iter_mut() {
                             cap.downgrade(&time);
@@ -202,8 +374,14 @@ impl<A: AsAid, S: Scope<Timestamp = Duration>> Sourceable<A, S> for CsvFile<A> {
         });
 
         let mut out = Vec::with_capacity(streams.len());
-        for (idx, stream) in streams.drain(..).enumerate() {
-            let aid = self.schema[idx].0.clone();
+        let names = self
+            .schema
+            .iter()
+            .map(|(aid, _)| aid)
+            .chain(dead_letters.iter());
+
+        for (aid, stream) in names.zip(streams.drain(..)) {
+            let aid = aid.clone();
             out.push((
                 aid,
                 AttributeConfig::real_time(InputSemantics::Distinct),
@@ -211,6 +389,6 @@ impl<A: AsAid, S: Scope<Timestamp = Duration>> Sourceable<A, S> for CsvFile<A> {
             ));
         }
 
-        out
+        Ok(out)
     }
 }
@@ -4,7 +4,7 @@
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::rc::{Rc, Weak};
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
 use timely::dataflow::channels::pact::Pipeline;
 use timely::dataflow::operators::capture::event::link::EventLink;
@@ -13,6 +13,7 @@ use timely::dataflow::operators::generic::builder_rc::OperatorBuilder;
 use timely::dataflow::{Scope, Stream};
 use timely::logging::BatchLogger;
 
+use crate::clock::Instant;
 use crate::logging::DeclarativeEvent;
 use crate::scheduling::Scheduler;
 use crate::sources::Sourceable;
@@ -13,7 +13,7 @@ use timely::dataflow::{Scope, Stream};
 use differential_dataflow::logging::DifferentialEvent;
 
 use crate::sources::{Sourceable, SourcingContext};
-use crate::{AsAid, Value};
+use crate::{AsAid, Error, Value};
 use crate::{AttributeConfig, InputSemantics};
 use Value::{Eid, Number};
 
@@ -33,11 +33,14 @@ where
         &self,
         scope: &mut S,
         context: SourcingContext<S::Timestamp>,
-    ) -> Vec<(
-        A,
-        AttributeConfig,
-        Stream<S, ((Value, Value), Duration, isize)>,
-    )> {
+    ) -> Result<
+        Vec<(
+            A,
+            AttributeConfig,
+            Stream<S, ((Value, Value), Duration, isize)>,
+        )>,
+        Error,
+    > {
         let input = Some(context.differential_events).replay_into(scope);
 
         let mut demux =
@@ -104,7 +107,8 @@ where
             }
         });
 
-        self.attributes
+        Ok(self
+            .attributes
             .iter()
             .map(|aid| {
                 (
@@ -113,6 +117,6 @@ where
                     streams.remove(aid).unwrap(),
                 )
             })
-            .collect()
+            .collect())
     }
 }
@@ -0,0 +1,109 @@
+//! Operator to source synthetic data from a generator.
+
+use std::rc::Rc;
+use std::time::Duration;
+
+use timely::dataflow::operators::generic::operator::source;
+use timely::dataflow::{Scope, Stream};
+
+use crate::clock::Instant;
+use crate::sources::{Sourceable, SourcingContext};
+use crate::testing::{Dataset, Generator};
+use crate::{AsAid, Error, Value};
+use crate::{AttributeConfig, InputSemantics};
+
+/// A source of synthetic data for a single attribute, for load
+/// tests. An initial batch is introduced right away, followed by
+/// rounds of updates to it.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct Generated<A: AsAid> {
+    /// Attribute to feed.
+    pub name: A,
+    /// Shape of the generated data.
+    pub generator: Generator,
+    /// Seed for the generator.
+    pub seed: u64,
+    /// Number of datoms introduced initially.
+    pub initial: usize,
+    /// Number of datoms changed per round.
+    pub batch: usize,
+    /// Time between rounds.
+    pub interval: Option<Duration>,
+    /// Number of rounds, or unbounded if none is given.
+    pub rounds: Option<usize>,
+}
+
+impl<A: AsAid, S: Scope<Timestamp = Duration>> Sourceable<A, S> for Generated<A> {
+    fn source(
+        &self,
+        scope: &mut S,
+        context: SourcingContext<S::Timestamp>,
+    ) -> Result<
+        Vec<(
+            A,
+            AttributeConfig,
+            Stream<S, ((Value, Value), Duration, isize)>,
+        )>,
+        Error,
+    > {
+        let mut dataset = Dataset::new(self.generator.clone(), self.seed)?;
+        let initial = self.initial;
+        let batch = self.batch;
+        let interval = self.interval.unwrap_or(Duration::from_secs(1));
+        let rounds = self.rounds;
+
+        let stream = source(scope, &format!("Generated({})", self.name), |cap, info| {
+            let activator = Rc::new(scope.activator_for(&info.address[..]));
+
+            // Only a single worker generates data, s.t. the dataset
+            // is the same for any number of workers.
+            let mut cap = if scope.index() == 0 { Some(cap) } else { None };
+            let mut round = 0;
+
+            let scheduler = context.scheduler;
+            let t0 = context.t0;
+
+            move |output| {
+                if let Some(c) = cap.as_mut() {
+                    let time = Instant::now().duration_since(t0);
+                    c.downgrade(&time);
+
+                    let updates = if round == 0 {
+                        dataset.load(initial)
+                    } else {
+                        dataset.update(batch)
+                    };
+
+                    let mut session = output.session(c);
+                    for (tuple, diff) in updates.into_iter() {
+                        session.give((tuple, time, diff));
+                    }
+
+                    round += 1;
+                }
+
+                let done = match rounds {
+                    None => false,
+                    Some(rounds) => round > rounds,
+                };
+
+                if done {
+                    cap = None;
+                } else if cap.is_some() {
+                    scheduler
+                        .upgrade()
+                        .unwrap()
+                        .borrow_mut()
+                        .realtime
+                        .schedule_after(interval, Rc::downgrade(&activator));
+                }
+            }
+        });
+
+        Ok(vec![(
+            self.name.clone(),
+            AttributeConfig::real_time(InputSemantics::Raw),
+            stream,
+        )])
+    }
+}
@@ -6,13 +6,14 @@ use std::fs::File;
 use std::io::{BufRead, BufReader};
 use std::path::Path;
 use std::rc::Weak;
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
 use timely::dataflow::operators::generic::builder_rc::OperatorBuilder;
 use timely::dataflow::{Scope, Stream};
 
 // use sources::json_file::flate2::read::GzDecoder;
 
+use crate::clock::Instant;
 use crate::scheduling::Scheduler;
 use crate::sources::Sourceable;
 use crate::{AttributeConfig, InputSemantics};
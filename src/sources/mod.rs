@@ -1,32 +1,97 @@
 //! Types and operators to work with external data sources.
 
 use std::cell::RefCell;
+use std::collections::HashMap;
 use std::rc::{Rc, Weak};
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
 use timely::dataflow::operators::capture::event::link::EventLink;
 use timely::dataflow::{ProbeHandle, Scope, Stream};
 use timely::logging::TimelyEvent;
 use timely::progress::Timestamp;
 
+use differential_dataflow::hashable::Hashable;
 use differential_dataflow::lattice::Lattice;
 use differential_dataflow::logging::DifferentialEvent;
 
+use crate::clock::Instant;
 use crate::scheduling::Scheduler;
 use crate::AttributeConfig;
-use crate::{AsAid, Value};
+use crate::{AsAid, Error, SourceError, Value};
 
 #[cfg(feature = "csv-source")]
 pub mod csv_file;
 // pub mod declarative_logging;
 pub mod differential_logging;
+pub mod generated;
 // pub mod json_file;
 pub mod timely_logging;
+pub mod watermark;
 
 #[cfg(feature = "csv-source")]
 pub use self::csv_file::CsvFile;
+pub use self::generated::Generated;
+pub use self::watermark::{LateData, LatePolicy, Watermark};
 // pub use self::json_file::JsonFile;
 
+/// How inputs are split up between workers.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum Partitioning {
+    /// A single worker introduces all inputs.
+    Single,
+    /// Inputs are dealt out to workers in turn.
+    ByRecord,
+    /// All inputs about an entity are introduced by the same worker,
+    /// the one that also holds the entity in forward indices.
+    ByEntity,
+}
+
+impl Default for Partitioning {
+    fn default() -> Self {
+        Partitioning::Single
+    }
+}
+
+impl std::str::FromStr for Partitioning {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "single" => Ok(Partitioning::Single),
+            "record" => Ok(Partitioning::ByRecord),
+            "entity" => Ok(Partitioning::ByEntity),
+            _ => Err(format!("Unknown partitioning {}.", s)),
+        }
+    }
+}
+
+impl Partitioning {
+    /// Returns true iff the specified worker is the one to introduce
+    /// the input at the specified position, about the specified
+    /// entity. Under `Single` partitioning, that is the designated
+    /// worker.
+    pub fn is_responsible(
+        &self,
+        designated: usize,
+        position: usize,
+        e: &Value,
+        worker_index: usize,
+        peers: usize,
+    ) -> bool {
+        match *self {
+            Partitioning::Single => worker_index == designated,
+            Partitioning::ByRecord => position % peers == worker_index,
+            Partitioning::ByEntity => worker_for(e, peers) == worker_index,
+        }
+    }
+}
+
+/// Returns the worker that data keyed by the specified value is
+/// exchanged to by arrangements.
+pub fn worker_for(key: &Value, peers: usize) -> usize {
+    (key.hashed() % peers as u64) as usize
+}
+
 /// A struct encapsulating any state required to create sources.
 pub struct SourcingContext<T: Timestamp> {
     /// The logical start of the computation, used by sources to
@@ -41,6 +106,9 @@ pub struct SourcingContext<T: Timestamp> {
     pub timely_events: Rc<EventLink<Duration, (Duration, usize, TimelyEvent)>>,
     /// A weak handle to Differential event link.
     pub differential_events: Rc<EventLink<Duration, (Duration, usize, DifferentialEvent)>>,
+    /// Tallies of late datoms per attribute, reported alongside the
+    /// server's attribute statistics.
+    pub late_data: Rc<RefCell<HashMap<String, LateData>>>,
 }
 
 /// An external data source that can provide Datoms.
@@ -56,11 +124,14 @@ where
         &self,
         scope: &mut S,
         context: SourcingContext<S::Timestamp>,
-    ) -> Vec<(
-        A,
-        AttributeConfig,
-        Stream<S, ((Value, Value), S::Timestamp, isize)>,
-    )>;
+    ) -> Result<
+        Vec<(
+            A,
+            AttributeConfig,
+            Stream<S, ((Value, Value), S::Timestamp, isize)>,
+        )>,
+        Error,
+    >;
 }
 
 /// Supported external data sources.
@@ -75,6 +146,8 @@ pub enum Source<A: AsAid + From<&'static str>> {
     /// CSV files
     #[cfg(feature = "csv-source")]
     CsvFile(CsvFile<A>),
+    /// Synthetic data
+    Generated(Generated<A>),
     // /// Files containing json objects
     // JsonFile(JsonFile<A>),
 }
@@ -89,18 +162,25 @@ where
         &self,
         scope: &mut S,
         context: SourcingContext<S::Timestamp>,
-    ) -> Vec<(
-        A,
-        AttributeConfig,
-        Stream<S, ((Value, Value), Duration, isize)>,
-    )> {
+    ) -> Result<
+        Vec<(
+            A,
+            AttributeConfig,
+            Stream<S, ((Value, Value), Duration, isize)>,
+        )>,
+        Error,
+    > {
         match *self {
             Source::TimelyLogging(ref source) => source.source(scope, context),
             Source::DifferentialLogging(ref source) => source.source(scope, context),
             // Source::DeclarativeLogging(ref source) => source.source(scope, context),
             #[cfg(feature = "csv-source")]
             Source::CsvFile(ref source) => source.source(scope, context),
-            _ => unimplemented!(),
+            Source::Generated(ref source) => source.source(scope, context),
+            _ => Err(SourceError::Unsupported(
+                "This source is not available in this build.".to_string(),
+            )
+            .into()),
         }
     }
 }
@@ -116,11 +196,14 @@ where
         &self,
         _scope: &mut S,
         _context: SourcingContext<S::Timestamp>,
-    ) -> Vec<(
-        A,
-        AttributeConfig,
-        Stream<S, ((Value, Value), S::Timestamp, isize)>,
-    )> {
-        unimplemented!();
+    ) -> Result<
+        Vec<(
+            A,
+            AttributeConfig,
+            Stream<S, ((Value, Value), S::Timestamp, isize)>,
+        )>,
+        Error,
+    > {
+        Err(SourceError::Unsupported("Sources require the real-time feature.".to_string()).into())
     }
 }
@@ -12,7 +12,7 @@ use timely::dataflow::{Scope, Stream};
 use timely::logging::{TimelyEvent, WorkerIdentifier};
 
 use crate::sources::{Sourceable, SourcingContext};
-use crate::{AsAid, Value};
+use crate::{AsAid, Error, Value};
 use crate::{AttributeConfig, InputSemantics};
 use Value::{Bool, Eid};
 
@@ -35,11 +35,14 @@ where
         &self,
         scope: &mut S,
         context: SourcingContext<S::Timestamp>,
-    ) -> Vec<(
-        A,
-        AttributeConfig,
-        Stream<S, ((Value, Value), Duration, isize)>,
-    )> {
+    ) -> Result<
+        Vec<(
+            A,
+            AttributeConfig,
+            Stream<S, ((Value, Value), Duration, isize)>,
+        )>,
+        Error,
+    > {
         let input = match self.remote_peers {
             None => {
                 // Read events introspectively.
@@ -189,7 +192,8 @@ where
             }
         });
 
-        self.attributes
+        Ok(self
+            .attributes
             .iter()
             .map(|aid| {
                 (
@@ -198,7 +202,7 @@ where
                     streams.remove(aid).unwrap(),
                 )
             })
-            .collect()
+            .collect())
     }
 }
 
@@ -0,0 +1,92 @@
+//! Bounded-lateness watermarks for sources assigning event times.
+
+use std::cmp;
+use std::time::Duration;
+
+/// Tracks how far a source assigning event times has progressed. The
+/// watermark trails the latest event time seen by a fixed lateness,
+/// and is the time before which the source promises not to produce
+/// any more records. It never moves backwards.
+///
+/// Sources hold their capabilities at the current watermark, which
+/// only moves once they `advance` it, e.g. after each batch of
+/// records.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Watermark {
+    lateness: Duration,
+    current: Duration,
+    next: Duration,
+}
+
+impl Watermark {
+    /// Creates a watermark trailing event times by the specified
+    /// lateness, starting out at the specified time.
+    pub fn new(lateness: Duration, start_at: Duration) -> Self {
+        Watermark {
+            lateness,
+            current: start_at,
+            next: start_at,
+        }
+    }
+
+    /// Returns the current watermark.
+    pub fn current(&self) -> Duration {
+        self.current
+    }
+
+    /// Returns true iff a record of the specified event time arrived
+    /// too late to be introduced at that time.
+    pub fn is_late(&self, event_time: Duration) -> bool {
+        event_time < self.current
+    }
+
+    /// Accounts for a record of the specified event time, returning
+    /// the time to introduce it at. Records within the watermark keep
+    /// their event time, late ones are moved up to the watermark.
+    pub fn observe(&mut self, event_time: Duration) -> Duration {
+        if let Some(trailing) = event_time.checked_sub(self.lateness) {
+            self.next = cmp::max(self.next, trailing);
+        }
+
+        cmp::max(event_time, self.current)
+    }
+
+    /// Moves the watermark up to trail the latest event time observed
+    /// so far, returning its new position.
+    pub fn advance(&mut self) -> Duration {
+        self.current = self.next;
+        self.current
+    }
+}
+
+/// What sources do with datoms of records arriving after the
+/// watermark.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub enum LatePolicy<A> {
+    /// Introduces them at the watermark, rather than at their event
+    /// time.
+    Correct,
+    /// Discards them.
+    Drop,
+    /// Introduces them into the specified attribute instead, at the
+    /// watermark.
+    DeadLetter(A),
+}
+
+impl<A> Default for LatePolicy<A> {
+    fn default() -> Self {
+        LatePolicy::Correct
+    }
+}
+
+/// Number of late datoms a source has handled for an attribute, by
+/// the policy applied to them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LateData {
+    /// Datoms introduced at the watermark.
+    pub corrected: usize,
+    /// Datoms discarded.
+    pub dropped: usize,
+    /// Datoms introduced into a dead-letter attribute.
+    pub dead_lettered: usize,
+}
@@ -0,0 +1,232 @@
+//! Attribute storage backed by RocksDB. Stored attributes keep their
+//! configuration and every update transacted on them on disk, s.t.
+//! they only have to be indexed in memory once a query depends on
+//! them. Each worker keeps a store of its own, holding the updates it
+//! introduced.
+//!
+//! Attribute configurations are kept as JSON, tagged with the version
+//! of the format they were written in, s.t. they remain readable as
+//! the configuration grows new fields.
+
+use std::path::Path;
+
+use rocksdb::{Direction, IteratorMode, WriteBatch, DB};
+
+use differential_dataflow::consolidation::consolidate;
+
+use crate::{AttributeConfig, Error, Value};
+
+/// Version of the format configurations are written in.
+const FORMAT: u32 = 1;
+
+/// Prefix of keys holding attribute configurations.
+const CONFIGS: &[u8] = b"c/";
+/// Prefix of keys holding updates, by attribute.
+const UPDATES: &[u8] = b"u/";
+/// Key holding the next sequence number.
+const NEXT_SEQ: &[u8] = b"n";
+
+/// An attribute configuration, as written to the store.
+#[derive(Serialize, Deserialize)]
+struct StoredConfig {
+    version: u32,
+    config: AttributeConfig,
+}
+
+/// A single worker's store.
+pub struct Store {
+    db: DB,
+    // Updates are keyed by sequence number within their attribute,
+    // s.t. they are read back in the order they were written.
+    next_seq: u64,
+    // Configurations of all stored attributes, read when opening.
+    attributes: Vec<(String, AttributeConfig)>,
+}
+
+impl Store {
+    /// Opens the store at the specified path, creating it if need be.
+    /// Fails if the store can't be opened, or holds configurations
+    /// that can't be read.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let db = DB::open_default(path).map_err(Error::fault)?;
+
+        let next_seq = match db.get(NEXT_SEQ).map_err(Error::fault)? {
+            None => 0,
+            Some(bytes) => decode_seq(&bytes)?,
+        };
+
+        let mut store = Store {
+            db,
+            next_seq,
+            attributes: Vec::new(),
+        };
+
+        let mut attributes = Vec::new();
+
+        for (key, value) in store.scan(CONFIGS.to_vec()) {
+            let name = String::from_utf8_lossy(&key[CONFIGS.len()..]).into_owned();
+            let config = decode_config(&name, &value)?;
+
+            attributes.push((name, config));
+        }
+
+        store.attributes = attributes;
+
+        Ok(store)
+    }
+
+    /// Opens the store of the specified worker, in a directory of its
+    /// own below the specified one.
+    pub fn open_worker<P: AsRef<Path>>(path: P, worker_index: usize) -> Result<Self, Error> {
+        Store::open(path.as_ref().join(format!("worker-{}", worker_index)))
+    }
+
+    /// Returns the configurations of all stored attributes, as found
+    /// when opening the store.
+    pub fn attributes(&self) -> &[(String, AttributeConfig)] {
+        &self.attributes
+    }
+
+    /// Records a new stored attribute.
+    pub fn create_attribute(&mut self, name: &str, config: &AttributeConfig) -> Result<(), Error> {
+        let mut key = CONFIGS.to_vec();
+        key.extend_from_slice(name.as_bytes());
+
+        let value = serde_json::to_vec(&StoredConfig {
+            version: FORMAT,
+            config: config.clone(),
+        })
+        .map_err(Error::fault)?;
+
+        self.db.put(&key, &value).map_err(Error::fault)?;
+        self.attributes.push((name.to_string(), config.clone()));
+
+        Ok(())
+    }
+
+    /// Writes a batch of updates to stored attributes atomically.
+    pub fn write(&mut self, updates: Vec<(String, Value, Value, isize)>) -> Result<(), Error> {
+        if updates.is_empty() {
+            return Ok(());
+        }
+
+        let mut batch = WriteBatch::default();
+        let mut seq = self.next_seq;
+
+        for (name, e, v, diff) in updates.into_iter() {
+            let mut key = updates_prefix(&name);
+            key.extend_from_slice(&seq.to_be_bytes());
+            seq += 1;
+
+            let value = bincode::serialize(&(e, v, diff)).map_err(Error::fault)?;
+            batch.put(&key, &value).map_err(Error::fault)?;
+        }
+
+        batch
+            .put(NEXT_SEQ, &seq.to_be_bytes())
+            .map_err(Error::fault)?;
+        self.db.write(batch).map_err(Error::fault)?;
+        self.next_seq = seq;
+
+        Ok(())
+    }
+
+    /// Reads back all updates to an attribute, consolidated.
+    pub fn contents(&self, name: &str) -> Result<Vec<((Value, Value), isize)>, Error> {
+        let mut contents = Vec::new();
+
+        for (_key, value) in self.scan(updates_prefix(name)) {
+            let (e, v, diff) = bincode::deserialize(&value).map_err(Error::fault)?;
+            contents.push(((e, v), diff));
+        }
+
+        consolidate(&mut contents);
+
+        Ok(contents)
+    }
+
+    /// Deletes all updates matching the specified predicate on the
+    /// attribute name, entity, and value, from the specified
+    /// attributes, or from all of them. Returns the number of updates
+    /// deleted.
+    pub fn excise<F>(&mut self, attribute: Option<&str>, mut matches: F) -> Result<usize, Error>
+    where
+        F: FnMut(&str, &Value, &Value) -> bool,
+    {
+        let prefix = match attribute {
+            None => UPDATES.to_vec(),
+            Some(name) => updates_prefix(name),
+        };
+
+        let mut batch = WriteBatch::default();
+        let mut excised = 0;
+
+        for (key, value) in self.scan(prefix) {
+            let name = decode_name(&key)?;
+            let (e, v, _diff): (Value, Value, isize) =
+                bincode::deserialize(&value).map_err(Error::fault)?;
+
+            if matches(&name, &e, &v) {
+                batch.delete(&key).map_err(Error::fault)?;
+                excised += 1;
+            }
+        }
+
+        self.db.write(batch).map_err(Error::fault)?;
+
+        Ok(excised)
+    }
+
+    fn scan<'a>(&'a self, prefix: Vec<u8>) -> impl Iterator<Item = (Box<[u8]>, Box<[u8]>)> + 'a {
+        let updates = self
+            .db
+            .iterator(IteratorMode::From(&prefix, Direction::Forward));
+
+        updates.take_while(move |(key, _value)| key.starts_with(&prefix))
+    }
+}
+
+fn updates_prefix(name: &str) -> Vec<u8> {
+    let mut prefix = UPDATES.to_vec();
+    prefix.extend_from_slice(name.as_bytes());
+    prefix.push(0);
+    prefix
+}
+
+fn decode_name(key: &[u8]) -> Result<String, Error> {
+    let name = &key[UPDATES.len()..];
+
+    match name.iter().position(|byte| *byte == 0) {
+        None => Err(Error::fault("Corrupted update key in store.")),
+        Some(end) => Ok(String::from_utf8_lossy(&name[..end]).into_owned()),
+    }
+}
+
+fn decode_config(name: &str, bytes: &[u8]) -> Result<AttributeConfig, Error> {
+    let stored: StoredConfig = serde_json::from_slice(bytes).map_err(|error| {
+        Error::fault(format!(
+            "Corrupted configuration of {} in store: {}",
+            name, error
+        ))
+    })?;
+
+    if stored.version != FORMAT {
+        return Err(Error::unsupported(format!(
+            "Configuration of {} is stored in format version {}, but only {} is supported.",
+            name, stored.version, FORMAT
+        )));
+    }
+
+    Ok(stored.config)
+}
+
+fn decode_seq(bytes: &[u8]) -> Result<u64, Error> {
+    if bytes.len() != 8 {
+        return Err(Error::fault("Corrupted sequence number in store."));
+    }
+
+    let mut seq = [0; 8];
+    seq.copy_from_slice(bytes);
+
+    Ok(u64::from_be_bytes(seq))
+}
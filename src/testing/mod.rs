@@ -0,0 +1,263 @@
+//! Generators for synthetic attribute data, such as random graphs or
+//! skewed value distributions, for reproducible tests and load tests
+//! without external files. Generated data only depends on the seed it
+//! was generated from. Plans can be tested end-to-end against
+//! scripted transactions via `Scenario`.
+
+use crate::{Datom, Error, Rational32, Value};
+
+pub mod scenario;
+
+pub use self::scenario::Scenario;
+
+/// A small and deterministic pseudo-random number generator
+/// (xorshift64*), good enough for test data and nothing else.
+#[derive(Clone, Debug)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    /// Creates a generator from the specified seed.
+    pub fn seeded(seed: u64) -> Self {
+        Rng {
+            state: seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1,
+        }
+    }
+
+    /// Returns the next number in the sequence.
+    pub fn next_u64(&mut self) -> u64 {
+        let mut x = self.state;
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.state = x;
+
+        x.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// Returns a number in `[0, n)`.
+    pub fn below(&mut self, n: u64) -> u64 {
+        self.next_u64() % n
+    }
+
+    /// Returns a number in `[0, 1)`.
+    pub fn unit(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Distributions to draw values from.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub enum Distribution {
+    /// All values are equally likely.
+    Uniform,
+    /// The k-th most frequent value occurs with a probability
+    /// proportional to `1 / k^s`, for the specified exponent `s`.
+    Zipf(Rational32),
+}
+
+/// Largest number of values skewed distributions can be sampled
+/// over, as these keep the cumulative probability of each around.
+pub const MAX_SKEWED_VALUES: u64 = 1 << 24;
+
+/// Draws values in `[0, count)` from a distribution.
+pub struct Sampler {
+    count: u64,
+    /// Cumulative probabilities of all values, for skewed
+    /// distributions.
+    cdf: Option<Vec<f64>>,
+}
+
+impl Sampler {
+    /// Creates a sampler over the specified number of values.
+    pub fn new(distribution: &Distribution, count: u64) -> Result<Self, Error> {
+        if count == 0 {
+            return Err(Error::incorrect("Can't sample from an empty range."));
+        }
+
+        match *distribution {
+            Distribution::Uniform => Ok(Sampler { count, cdf: None }),
+            Distribution::Zipf(exponent) => {
+                if count > MAX_SKEWED_VALUES {
+                    return Err(Error::unsupported(format!(
+                        "Skewed distributions are limited to {} values.",
+                        MAX_SKEWED_VALUES
+                    )));
+                }
+
+                let s = f64::from(*exponent.numer()) / f64::from(*exponent.denom());
+
+                let mut total = 0.0;
+                let mut cdf = Vec::with_capacity(count as usize);
+                for k in 1..=count {
+                    total += 1.0 / (k as f64).powf(s);
+                    cdf.push(total);
+                }
+
+                for p in cdf.iter_mut() {
+                    *p /= total;
+                }
+
+                Ok(Sampler {
+                    count,
+                    cdf: Some(cdf),
+                })
+            }
+        }
+    }
+
+    /// Draws the next value.
+    pub fn sample(&self, rng: &mut Rng) -> u64 {
+        match self.cdf {
+            None => rng.below(self.count),
+            Some(ref cdf) => {
+                let u = rng.unit();
+                let rank = match cdf.binary_search_by(|p| p.partial_cmp(&u).unwrap()) {
+                    Ok(rank) => rank,
+                    Err(rank) => rank,
+                };
+
+                (rank as u64).min(self.count - 1)
+            }
+        }
+    }
+}
+
+/// Shapes of synthetic attribute data.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub enum Generator {
+    /// Edges between the specified number of nodes, both endpoints
+    /// drawn from the specified distribution. Skewed distributions
+    /// produce graphs with a few highly connected nodes.
+    Graph {
+        /// Number of nodes.
+        nodes: u64,
+        /// Distribution of edge endpoints.
+        distribution: Distribution,
+    },
+    /// A number per entity, for the specified number of entities,
+    /// drawn from the specified number of distinct values.
+    Numbers {
+        /// Number of entities.
+        entities: u64,
+        /// Number of distinct values.
+        values: u64,
+        /// Distribution of values.
+        distribution: Distribution,
+    },
+}
+
+/// A stream of updates to a synthetic attribute. Datoms are
+/// introduced via `load`, and subsequently changed via `update`,
+/// which replaces randomly chosen existing datoms with fresh ones.
+pub struct Dataset {
+    generator: Generator,
+    rng: Rng,
+    entities: Sampler,
+    values: Sampler,
+    /// Position of the next datom to be loaded.
+    position: u64,
+    /// Pairs currently present.
+    live: Vec<(Value, Value)>,
+}
+
+impl Dataset {
+    /// Creates a dataset from the specified generator and seed.
+    pub fn new(generator: Generator, seed: u64) -> Result<Self, Error> {
+        let (entities, values) = match generator {
+            Generator::Graph {
+                nodes,
+                ref distribution,
+            } => (
+                Sampler::new(distribution, nodes)?,
+                Sampler::new(distribution, nodes)?,
+            ),
+            Generator::Numbers {
+                entities,
+                values,
+                ref distribution,
+            } => (
+                Sampler::new(&Distribution::Uniform, entities)?,
+                Sampler::new(distribution, values)?,
+            ),
+        };
+
+        Ok(Dataset {
+            generator,
+            rng: Rng::seeded(seed),
+            entities,
+            values,
+            position: 0,
+            live: Vec::new(),
+        })
+    }
+
+    /// Returns the pairs currently present.
+    pub fn live(&self) -> &[(Value, Value)] {
+        &self.live
+    }
+
+    fn value(&mut self) -> Value {
+        match self.generator {
+            Generator::Graph { .. } => Value::Eid(self.values.sample(&mut self.rng)),
+            Generator::Numbers { .. } => Value::Number(self.values.sample(&mut self.rng) as i64),
+        }
+    }
+
+    /// Introduces the specified number of fresh pairs. Numbers are
+    /// assigned to entities in turn.
+    pub fn load(&mut self, count: usize) -> Vec<((Value, Value), isize)> {
+        let mut updates = Vec::with_capacity(count);
+
+        for _ in 0..count {
+            let e = match self.generator {
+                Generator::Graph { .. } => Value::Eid(self.entities.sample(&mut self.rng)),
+                Generator::Numbers { entities, .. } => Value::Eid(self.position % entities),
+            };
+            let v = self.value();
+
+            self.position += 1;
+            self.live.push((e.clone(), v.clone()));
+            updates.push(((e, v), 1));
+        }
+
+        updates
+    }
+
+    /// Replaces the specified number of randomly chosen pairs. Edges
+    /// are replaced by random other edges, numbers by another number
+    /// for the same entity.
+    pub fn update(&mut self, count: usize) -> Vec<((Value, Value), isize)> {
+        let mut updates = Vec::with_capacity(2 * count);
+
+        if self.live.is_empty() {
+            return updates;
+        }
+
+        for _ in 0..count {
+            let idx = self.rng.below(self.live.len() as u64) as usize;
+
+            let e = match self.generator {
+                Generator::Graph { .. } => Value::Eid(self.entities.sample(&mut self.rng)),
+                Generator::Numbers { .. } => self.live[idx].0.clone(),
+            };
+            let v = self.value();
+
+            let old = std::mem::replace(&mut self.live[idx], (e.clone(), v.clone()));
+            updates.push((old, -1));
+            updates.push(((e, v), 1));
+        }
+
+        updates
+    }
+}
+
+/// Turns generated updates into datoms about the specified attribute,
+/// ready to be transacted.
+pub fn into_datoms<A: Clone>(updates: Vec<((Value, Value), isize)>, a: &A) -> Vec<Datom<A>> {
+    updates
+        .into_iter()
+        .map(|((e, v), diff)| Datom(e, a.clone(), v, None, diff))
+        .collect()
+}
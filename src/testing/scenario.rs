@@ -0,0 +1,155 @@
+//! Scripted end-to-end tests of query plans.
+
+use std::collections::HashMap;
+use std::sync::mpsc::channel;
+
+use timely::dataflow::operators::Inspect;
+
+use differential_dataflow::consolidation::consolidate;
+
+use crate::plan::Implementable;
+use crate::server::{Register, Server};
+use crate::{Aid, AttributeConfig, Datom, Plan, ResultDiff, Rule};
+use crate::{IndexDirection, InputSemantics, QuerySupport};
+
+/// A plan together with a script of transactions, each followed by
+/// the exact output diffs it is expected to produce. Outputs are
+/// compared per transaction, after consolidation and in a canonical
+/// order, s.t. expectations don't depend on batching.
+///
+/// ```ignore
+/// Scenario::new("names", Plan::match_a(0, ":name", 1))
+///     .transact(
+///         vec![Datom::add(1, ":name", String("Dipper".to_string()))],
+///         vec![(vec![Eid(1), String("Dipper".to_string())], 0, 1)],
+///     )
+///     .run();
+/// ```
+pub struct Scenario {
+    description: String,
+    plan: Plan<Aid>,
+    rules: Vec<Rule<Aid>>,
+    configs: HashMap<Aid, AttributeConfig>,
+    steps: Vec<(Vec<Datom<Aid>>, Vec<ResultDiff<u64>>)>,
+}
+
+impl Scenario {
+    /// Creates an empty script testing the specified plan.
+    pub fn new<D: Into<String>>(description: D, plan: Plan<Aid>) -> Self {
+        Scenario {
+            description: description.into(),
+            plan,
+            rules: Vec::new(),
+            configs: HashMap::new(),
+            steps: Vec::new(),
+        }
+    }
+
+    /// Registers an auxiliary rule the plan depends on.
+    pub fn rule<X: Into<Aid>>(mut self, name: X, plan: Plan<Aid>) -> Self {
+        self.rules.push(Rule::named(name.into(), plan));
+        self
+    }
+
+    /// Overrides the configuration of an attribute. All other
+    /// attributes support worst-case optimal joins in both
+    /// directions.
+    pub fn attribute<X: Into<Aid>>(mut self, name: X, config: AttributeConfig) -> Self {
+        self.configs.insert(name.into(), config);
+        self
+    }
+
+    /// Appends a transaction and the diffs it is expected to produce,
+    /// timestamped by the position of the transaction in the script.
+    pub fn transact(mut self, tx_data: Vec<Datom<Aid>>, expected: Vec<ResultDiff<u64>>) -> Self {
+        self.steps.push((tx_data, expected));
+        self
+    }
+
+    /// Runs the script on a single worker, panicking at the first
+    /// transaction whose outputs deviate from expectations.
+    pub fn run(self) {
+        let Scenario {
+            description,
+            plan,
+            rules,
+            mut configs,
+            steps,
+        } = self;
+
+        let mut attributes = plan.dependencies().attributes;
+        for rule in rules.iter() {
+            attributes.extend(rule.plan.dependencies().attributes);
+        }
+        for (tx_data, _) in steps.iter() {
+            attributes.extend(tx_data.iter().map(|datom| datom.1.clone()));
+        }
+
+        timely::execute_directly(move |worker| {
+            let mut server = Server::<Aid, u64, u64>::new(Default::default());
+            let (send_results, results) = channel();
+
+            worker.dataflow::<u64, _, _>(|scope| {
+                for name in attributes.into_iter() {
+                    let config = configs.remove(&name).unwrap_or_else(|| AttributeConfig {
+                        query_support: QuerySupport::AdaptiveWCO,
+                        index_direction: IndexDirection::Both,
+                        ..AttributeConfig::tx_time(InputSemantics::Raw)
+                    });
+
+                    server
+                        .create_attribute(scope, name, config)
+                        .expect("failed to create attribute");
+                }
+
+                if !rules.is_empty() {
+                    server
+                        .register(Register {
+                            rules,
+                            publish: vec![],
+                        })
+                        .expect("failed to register rules");
+                }
+
+                server
+                    .test_single(scope, Rule::named("scenario", plan))
+                    .inner
+                    .inspect(move |x| send_results.send(x.clone()).unwrap());
+            });
+
+            for (tx, (tx_data, expected)) in steps.into_iter().enumerate() {
+                server.transact(tx_data, 0, 0).expect("failed to transact");
+                server
+                    .advance_domain(None, tx as u64 + 1)
+                    .expect("failed to advance domain");
+
+                worker.step_while(|| server.is_any_outdated());
+
+                let outputs = canonical(results.try_iter().collect());
+                let expected = canonical(expected);
+
+                if outputs != expected {
+                    panic!(
+                        "{}: unexpected outputs after transaction {}\n  expected: {:?}\n  produced: {:?}",
+                        description, tx, expected, outputs
+                    );
+                }
+            }
+        });
+    }
+}
+
+/// Consolidates diffs and brings them into a canonical order.
+fn canonical(diffs: Vec<ResultDiff<u64>>) -> Vec<ResultDiff<u64>> {
+    let mut updates = diffs
+        .into_iter()
+        .map(|(tuple, t, diff)| ((tuple, t), diff))
+        .collect();
+
+    consolidate(&mut updates);
+
+    updates
+        .into_iter()
+        .map(|((tuple, t), diff)| (tuple, t, diff))
+        .collect()
+}
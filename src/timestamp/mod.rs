@@ -85,6 +85,21 @@ mod rewind {
         /// e.g. 0.rewind(10) -> 0
         /// and Duration(0).rewind(Duration(1)) -> Duration(0)
         fn rewind(&self, slack: Self) -> Self;
+
+        /// Returns a new timestamp corresponding to self moved ahead
+        /// by the specified amount, saturating at the largest
+        /// representable time.
+        ///
+        /// e.g. 10.fast_forward(5) -> 15
+        fn fast_forward(&self, amount: Self) -> Self;
+
+        /// Returns true iff the specified time can be converted into
+        /// this type, which panics otherwise.
+        fn is_convertible(time: &Time) -> bool;
+
+        /// Returns the timestamp as the corresponding time. Nested
+        /// timestamps correspond to their outer part.
+        fn to_time(&self) -> Time;
     }
 
     impl Rewind for u64 {
@@ -94,6 +109,22 @@ mod rewind {
                 Some(rewound) => rewound,
             }
         }
+
+        fn fast_forward(&self, amount: Self) -> Self {
+            self.saturating_add(amount)
+        }
+
+        fn is_convertible(time: &Time) -> bool {
+            if let Time::TxId(_) = time {
+                true
+            } else {
+                false
+            }
+        }
+
+        fn to_time(&self) -> Time {
+            Time::TxId(*self)
+        }
     }
 
     impl Rewind for Duration {
@@ -103,6 +134,25 @@ mod rewind {
                 Some(rewound) => rewound,
             }
         }
+
+        fn fast_forward(&self, amount: Self) -> Self {
+            match self.checked_add(amount) {
+                None => Duration::new(std::u64::MAX, 999_999_999),
+                Some(forwarded) => forwarded,
+            }
+        }
+
+        fn is_convertible(time: &Time) -> bool {
+            if let Time::Real(_) = time {
+                true
+            } else {
+                false
+            }
+        }
+
+        fn to_time(&self) -> Time {
+            Time::Real(*self)
+        }
     }
 
     impl Rewind for crate::timestamp::pair::Pair<Duration, u64> {
@@ -112,6 +162,25 @@ mod rewind {
 
             Self::new(first_rewound, second_rewound)
         }
+
+        fn fast_forward(&self, amount: Self) -> Self {
+            let first_forwarded = self.first.fast_forward(amount.first);
+            let second_forwarded = self.second.fast_forward(amount.second);
+
+            Self::new(first_forwarded, second_forwarded)
+        }
+
+        fn is_convertible(time: &Time) -> bool {
+            if let Time::Bi(_, _) = time {
+                true
+            } else {
+                false
+            }
+        }
+
+        fn to_time(&self) -> Time {
+            Time::Bi(self.first, self.second)
+        }
     }
 
     impl<TOuter> std::convert::Into<Product<TOuter, u64>> for Time
@@ -135,6 +204,21 @@ mod rewind {
 
             Self::new(outer_rewound, inner_rewound)
         }
+
+        fn fast_forward(&self, amount: Self) -> Self {
+            let outer_forwarded = self.outer.fast_forward(amount.outer);
+            let inner_forwarded = self.inner.fast_forward(amount.inner);
+
+            Self::new(outer_forwarded, inner_forwarded)
+        }
+
+        fn is_convertible(time: &Time) -> bool {
+            TOuter::is_convertible(time)
+        }
+
+        fn to_time(&self) -> Time {
+            self.outer.to_time()
+        }
     }
 }
 
@@ -203,6 +287,17 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_fast_forward() {
+        assert_eq!((10 as u64).fast_forward(5), 15);
+        assert_eq!(std::u64::MAX.fast_forward(1), std::u64::MAX);
+
+        assert_eq!(
+            Duration::from_millis(12300).fast_forward(Duration::from_millis(45)),
+            Duration::from_millis(12345)
+        );
+    }
+
     #[test]
     fn test_coarsen() {
         assert_eq!((0 as u64).coarsen(&10), 10);
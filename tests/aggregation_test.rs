@@ -7,10 +7,12 @@ use timely::dataflow::channels::pact::Pipeline;
 use timely::dataflow::operators::Operator;
 
 use declarative_dataflow::binding::Binding;
-use declarative_dataflow::plan::{Aggregate, AggregationFn, Implementable, Join, Project};
+use declarative_dataflow::plan::{
+    Aggregate, AggregationFn, Downsample, DownsampleFn, Implementable, Join, Project, Sessionize,
+};
 use declarative_dataflow::server::Server;
 use declarative_dataflow::{Aid, AttributeConfig, Datom, InputSemantics, Plan, Rule, Value};
-use Value::{Eid, Number, Rational32, String};
+use Value::{Eid, Instant, Number, Rational32, String};
 
 use num_rational::Ratio;
 
@@ -24,7 +26,7 @@ struct Case {
 fn dependencies(case: &Case) -> HashSet<Aid> {
     let mut deps = HashSet::new();
 
-    for binding in case.plan.into_bindings().iter() {
+    for binding in case.plan.into_bindings().unwrap().iter() {
         if let Binding::Attribute(binding) = binding {
             deps.insert(binding.source_attribute.clone());
         }
@@ -33,8 +35,13 @@ fn dependencies(case: &Case) -> HashSet<Aid> {
     deps
 }
 
-fn run_cases(mut cases: Vec<Case>) {
+fn run_cases(cases: Vec<Case>) {
+    run_cases_with(cases, AttributeConfig::tx_time(InputSemantics::Raw));
+}
+
+fn run_cases_with(mut cases: Vec<Case>, config: AttributeConfig) {
     for case in cases.drain(..) {
+        let config = config.clone();
         timely::execute_directly(move |worker| {
             let mut server = Server::<Aid, u64, u64>::new(Default::default());
             let (send_results, results) = channel();
@@ -46,9 +53,7 @@ fn run_cases(mut cases: Vec<Case>) {
 
             worker.dataflow::<u64, _, _>(|scope| {
                 for dep in deps.iter() {
-                    server
-                        .create_attribute(scope, dep, AttributeConfig::tx_time(InputSemantics::Raw))
-                        .unwrap();
+                    server.create_attribute(scope, dep, config.clone()).unwrap();
                 }
 
                 server
@@ -126,6 +131,8 @@ fn count() {
                 key_variables: vec![],
                 aggregation_variables: vec![amount],
                 with_variables: vec![],
+                key_universe: None,
+                defaults: vec![],
             }),
             transactions: vec![data.clone()],
             expectations: vec![vec![(vec![Number(6)], 0, 1)]],
@@ -143,6 +150,8 @@ fn count() {
                 key_variables: vec![e],
                 aggregation_variables: vec![amount],
                 with_variables: vec![],
+                key_universe: None,
+                defaults: vec![],
             }),
             transactions: vec![data.clone()],
             expectations: vec![vec![
@@ -185,6 +194,8 @@ fn max() {
                 key_variables: vec![],
                 aggregation_variables: vec![amount],
                 with_variables: vec![],
+                key_universe: None,
+                defaults: vec![],
             }),
             transactions: vec![data.clone()],
             expectations: vec![vec![(vec![Number(10)], 0, 1)]],
@@ -198,6 +209,8 @@ fn max() {
                 key_variables: vec![e],
                 aggregation_variables: vec![amount],
                 with_variables: vec![],
+                key_universe: None,
+                defaults: vec![],
             }),
             transactions: vec![data.clone()],
             expectations: vec![vec![
@@ -233,6 +246,8 @@ fn min() {
                 key_variables: vec![],
                 aggregation_variables: vec![amount],
                 with_variables: vec![],
+                key_universe: None,
+                defaults: vec![],
             }),
             transactions: vec![data.clone()],
             expectations: vec![vec![(vec![Number(2)], 0, 1)]],
@@ -246,6 +261,8 @@ fn min() {
                 key_variables: vec![e],
                 aggregation_variables: vec![amount],
                 with_variables: vec![],
+                key_universe: None,
+                defaults: vec![],
             }),
             transactions: vec![data.clone()],
             expectations: vec![vec![
@@ -281,6 +298,8 @@ fn sum() {
                 key_variables: vec![],
                 aggregation_variables: vec![amount],
                 with_variables: vec![],
+                key_universe: None,
+                defaults: vec![],
             }),
             transactions: vec![data.clone()],
             expectations: vec![vec![(vec![Number(37)], 0, 1)]],
@@ -298,6 +317,8 @@ fn sum() {
                 key_variables: vec![e],
                 aggregation_variables: vec![amount],
                 with_variables: vec![],
+                key_universe: None,
+                defaults: vec![],
             }),
             transactions: vec![data.clone()],
             expectations: vec![vec![
@@ -340,6 +361,8 @@ fn avg() {
                 key_variables: vec![],
                 aggregation_variables: vec![amount],
                 with_variables: vec![],
+                key_universe: None,
+                defaults: vec![],
             }),
             transactions: vec![data.clone()],
             expectations: vec![vec![(vec![Rational32(Ratio::new(37, 6))], 0, 1)]],
@@ -357,6 +380,8 @@ fn avg() {
                 key_variables: vec![e],
                 aggregation_variables: vec![amount],
                 with_variables: vec![],
+                key_universe: None,
+                defaults: vec![],
             }),
             transactions: vec![data.clone()],
             expectations: vec![vec![
@@ -399,6 +424,8 @@ fn variance() {
                 key_variables: vec![],
                 aggregation_variables: vec![amount],
                 with_variables: vec![],
+                key_universe: None,
+                defaults: vec![],
             }),
             transactions: vec![data.clone()],
             expectations: vec![vec![(vec![Rational32(Ratio::new(317, 36))], 0, 1)]],
@@ -416,6 +443,8 @@ fn variance() {
                 key_variables: vec![e],
                 aggregation_variables: vec![amount],
                 with_variables: vec![],
+                key_universe: None,
+                defaults: vec![],
             }),
             transactions: vec![data.clone()],
             expectations: vec![vec![
@@ -451,6 +480,8 @@ fn median() {
                 key_variables: vec![],
                 aggregation_variables: vec![amount],
                 with_variables: vec![],
+                key_universe: None,
+                defaults: vec![],
             }),
             transactions: vec![data.clone()],
             expectations: vec![vec![(vec![Number(5)], 0, 1)]],
@@ -464,6 +495,8 @@ fn median() {
                 key_variables: vec![e],
                 aggregation_variables: vec![amount],
                 with_variables: vec![],
+                key_universe: None,
+                defaults: vec![],
             }),
             transactions: vec![data.clone()],
             expectations: vec![vec![
@@ -502,6 +535,8 @@ fn multiple_aggregations() {
                     key_variables: vec![],
                     aggregation_variables: vec![amount, debt, amount, debt],
                     with_variables: vec![],
+                    key_universe: None,
+                    defaults: vec![],
                 })
             },
             transactions: vec![
@@ -563,6 +598,8 @@ fn multiple_aggregations() {
                     key_variables: vec![e],
                     aggregation_variables: vec![amount, amount, amount, amount, debt, debt, debt, debt],
                     with_variables: vec![],
+                    key_universe: None,
+                    defaults: vec![],
                 })
             },
             transactions: vec![
@@ -618,6 +655,8 @@ fn multiple_aggregations() {
                     key_variables: vec![],
                     aggregation_variables: vec![heads],
                     with_variables: vec![monster],
+                    key_universe: None,
+                    defaults: vec![],
                 })
             },
             transactions: vec![
@@ -638,3 +677,321 @@ fn multiple_aggregations() {
         },
     ]);
 }
+
+#[test]
+fn empty_groups() {
+    let (customer, order, name) = (1, 2, 3);
+
+    run_cases(vec![Case {
+        description: "counting orders for every customer, including those without any",
+        plan: Plan::Aggregate(Aggregate {
+            variables: vec![customer, order],
+            plan: Box::new(Plan::Project(Project {
+                variables: vec![customer, order],
+                plan: Box::new(Plan::match_a(customer, ":order", order)),
+            })),
+            aggregation_fns: vec![AggregationFn::COUNT],
+            key_variables: vec![customer],
+            aggregation_variables: vec![order],
+            with_variables: vec![],
+            key_universe: Some(Box::new(Plan::match_a(customer, ":name", name))),
+            defaults: vec![Number(0)],
+        }),
+        transactions: vec![
+            vec![
+                Datom::add(1, ":name", String("Ivan".to_string())),
+                Datom::add(2, ":name", String("Oleg".to_string())),
+                Datom::add(1, ":order", Number(100)),
+                Datom::add(1, ":order", Number(101)),
+            ],
+            vec![
+                Datom::retract(1, ":order", Number(100)),
+                Datom::retract(1, ":order", Number(101)),
+                Datom::add(2, ":order", Number(102)),
+            ],
+        ],
+        expectations: vec![
+            vec![
+                (vec![Eid(1), Number(2)], 0, 1),
+                (vec![Eid(2), Number(0)], 0, 1),
+            ],
+            vec![
+                (vec![Eid(1), Number(2)], 1, -1),
+                (vec![Eid(1), Number(0)], 1, 1),
+                (vec![Eid(2), Number(0)], 1, -1),
+                (vec![Eid(2), Number(1)], 1, 1),
+            ],
+        ],
+    }]);
+}
+
+#[test]
+fn monotonic() {
+    let (e, amount) = (1, 2);
+    let data = vec![
+        vec![
+            Datom::add(1, ":amount", Number(5)),
+            Datom::add(2, ":amount", Number(10)),
+        ],
+        vec![
+            Datom::add(1, ":amount", Number(2)),
+            Datom::add(1, ":amount", Number(7)),
+        ],
+    ];
+
+    let aggregate = |aggregation_fn| {
+        Plan::Aggregate(Aggregate {
+            variables: vec![e, amount],
+            plan: Box::new(Plan::Project(Project {
+                variables: vec![e, amount],
+                plan: Box::new(Plan::match_a(e, ":amount", amount)),
+            })),
+            aggregation_fns: vec![aggregation_fn],
+            key_variables: vec![e],
+            aggregation_variables: vec![amount],
+            with_variables: vec![],
+            key_universe: None,
+            defaults: vec![],
+        })
+    };
+
+    let config = AttributeConfig {
+        monotonic: true,
+        ..AttributeConfig::tx_time(InputSemantics::Raw)
+    };
+
+    run_cases_with(
+        vec![
+            Case {
+                description: "[:find ?e (count ?amount) :where [?e :amount ?amount]]",
+                plan: aggregate(AggregationFn::COUNT),
+                transactions: data.clone(),
+                expectations: vec![
+                    vec![
+                        (vec![Eid(1), Number(1)], 0, 1),
+                        (vec![Eid(2), Number(1)], 0, 1),
+                    ],
+                    vec![
+                        (vec![Eid(1), Number(1)], 1, -1),
+                        (vec![Eid(1), Number(3)], 1, 1),
+                    ],
+                ],
+            },
+            Case {
+                description: "[:find ?e (sum ?amount) :where [?e :amount ?amount]]",
+                plan: aggregate(AggregationFn::SUM),
+                transactions: data.clone(),
+                expectations: vec![
+                    vec![
+                        (vec![Eid(1), Number(5)], 0, 1),
+                        (vec![Eid(2), Number(10)], 0, 1),
+                    ],
+                    vec![
+                        (vec![Eid(1), Number(5)], 1, -1),
+                        (vec![Eid(1), Number(14)], 1, 1),
+                    ],
+                ],
+            },
+            Case {
+                description: "[:find ?e (min ?amount) :where [?e :amount ?amount]]",
+                plan: aggregate(AggregationFn::MIN),
+                transactions: data.clone(),
+                expectations: vec![
+                    vec![
+                        (vec![Eid(1), Number(5)], 0, 1),
+                        (vec![Eid(2), Number(10)], 0, 1),
+                    ],
+                    vec![
+                        (vec![Eid(1), Number(5)], 1, -1),
+                        (vec![Eid(1), Number(2)], 1, 1),
+                    ],
+                ],
+            },
+            Case {
+                description: "[:find ?e (max ?amount) :where [?e :amount ?amount]]",
+                plan: aggregate(AggregationFn::MAX),
+                transactions: data,
+                expectations: vec![
+                    vec![
+                        (vec![Eid(1), Number(5)], 0, 1),
+                        (vec![Eid(2), Number(10)], 0, 1),
+                    ],
+                    vec![
+                        (vec![Eid(1), Number(5)], 1, -1),
+                        (vec![Eid(1), Number(7)], 1, 1),
+                    ],
+                ],
+            },
+        ],
+        config,
+    );
+}
+
+#[test]
+fn downsample() {
+    let (r, sensor, at, value) = (0, 1, 2, 3);
+    let (min, max, avg, last) = (4, 5, 6, 7);
+
+    let reading = |e: u64, s: u64, t: u64, v: i64| {
+        vec![
+            Datom::add(e, ":reading/sensor", Eid(s)),
+            Datom::add(e, ":reading/at", Instant(t)),
+            Datom::add(e, ":reading/value", Number(v)),
+        ]
+    };
+
+    run_cases(vec![Case {
+        description: "downsampling readings per sensor into minutes",
+        plan: Plan::Downsample(Downsample {
+            plan: Box::new(Plan::Join(Join {
+                variables: vec![r],
+                left_plan: Box::new(Plan::Join(Join {
+                    variables: vec![r],
+                    left_plan: Box::new(Plan::match_a(r, ":reading/sensor", sensor)),
+                    right_plan: Box::new(Plan::match_a(r, ":reading/at", at)),
+                })),
+                right_plan: Box::new(Plan::match_a(r, ":reading/value", value)),
+            })),
+            key_variables: vec![sensor],
+            time_variable: at,
+            value_variable: value,
+            interval: 60_000,
+            downsample_fns: vec![
+                DownsampleFn::MIN,
+                DownsampleFn::MAX,
+                DownsampleFn::AVG,
+                DownsampleFn::LAST,
+            ],
+            result_variables: vec![min, max, avg, last],
+        }),
+        transactions: vec![
+            [
+                reading(1, 100, 1_000, 5),
+                reading(2, 100, 30_000, 3),
+                reading(3, 100, 59_999, 7),
+                reading(4, 100, 60_000, 10),
+                reading(5, 200, 10_000, 1),
+            ]
+            .concat(),
+            reading(6, 100, 20_000, 11),
+        ],
+        expectations: vec![
+            vec![
+                (
+                    vec![
+                        Eid(100),
+                        Instant(0),
+                        Number(3),
+                        Number(7),
+                        Rational32(Ratio::new(5, 1)),
+                        Number(7),
+                    ],
+                    0,
+                    1,
+                ),
+                (
+                    vec![
+                        Eid(100),
+                        Instant(60_000),
+                        Number(10),
+                        Number(10),
+                        Rational32(Ratio::new(10, 1)),
+                        Number(10),
+                    ],
+                    0,
+                    1,
+                ),
+                (
+                    vec![
+                        Eid(200),
+                        Instant(0),
+                        Number(1),
+                        Number(1),
+                        Rational32(Ratio::new(1, 1)),
+                        Number(1),
+                    ],
+                    0,
+                    1,
+                ),
+            ],
+            vec![
+                (
+                    vec![
+                        Eid(100),
+                        Instant(0),
+                        Number(3),
+                        Number(7),
+                        Rational32(Ratio::new(5, 1)),
+                        Number(7),
+                    ],
+                    1,
+                    -1,
+                ),
+                (
+                    vec![
+                        Eid(100),
+                        Instant(0),
+                        Number(3),
+                        Number(11),
+                        Rational32(Ratio::new(13, 2)),
+                        Number(7),
+                    ],
+                    1,
+                    1,
+                ),
+            ],
+        ],
+    }]);
+}
+
+#[test]
+fn sessionize() {
+    let (c, user, at) = (0, 1, 2);
+    let (start, end) = (3, 4);
+
+    let click = |e: u64, u: u64, t: u64| {
+        vec![
+            Datom::add(e, ":click/user", Eid(u)),
+            Datom::add(e, ":click/at", Instant(t)),
+        ]
+    };
+
+    run_cases(vec![Case {
+        description: "sessions of clicks by user, at most 30s apart",
+        plan: Plan::Sessionize(Sessionize {
+            plan: Box::new(Plan::Join(Join {
+                variables: vec![c],
+                left_plan: Box::new(Plan::match_a(c, ":click/user", user)),
+                right_plan: Box::new(Plan::match_a(c, ":click/at", at)),
+            })),
+            key_variables: vec![user],
+            time_variable: at,
+            gap: 30_000,
+            start_variable: start,
+            end_variable: end,
+        }),
+        transactions: vec![
+            [
+                click(1, 100, 0),
+                click(2, 100, 10_000),
+                click(3, 100, 50_000),
+                click(4, 100, 60_000),
+                click(5, 200, 5_000),
+            ]
+            .concat(),
+            click(6, 100, 30_000),
+        ],
+        expectations: vec![
+            vec![
+                (vec![Eid(100), Instant(0), Instant(10_000)], 0, 1),
+                (vec![Eid(100), Instant(50_000), Instant(60_000)], 0, 1),
+                (vec![Eid(200), Instant(5_000), Instant(5_000)], 0, 1),
+            ],
+            vec![
+                (vec![Eid(100), Instant(0), Instant(10_000)], 1, -1),
+                (vec![Eid(100), Instant(50_000), Instant(60_000)], 1, -1),
+                (vec![Eid(100), Instant(0), Instant(60_000)], 1, 1),
+            ],
+        ],
+    }]);
+}
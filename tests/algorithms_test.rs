@@ -0,0 +1,142 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::mpsc::channel;
+
+use declarative_dataflow::algorithms;
+use declarative_dataflow::server::{Register, Server};
+use declarative_dataflow::{Aid, AttributeConfig, Datom, InputSemantics, Rule, Value};
+use Value::{Eid, Number};
+
+/// Registers the rules of an algorithm over the given edges, and
+/// checks the resulting tuples.
+fn run(rules: Vec<Rule<Aid>>, edges: &[(u64, u64)], expected: Vec<Vec<Value>>) {
+    let edges: Vec<Datom<Aid>> = edges
+        .iter()
+        .map(|&(from, to)| Datom::add(from, ":edge", Eid(to)))
+        .collect();
+
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, ()>::new(Default::default());
+        let (send_results, results) = channel();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .create_attribute(
+                    scope,
+                    ":edge",
+                    AttributeConfig::tx_time(InputSemantics::Raw),
+                )
+                .unwrap();
+        });
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .register(Register {
+                    rules,
+                    publish: vec![],
+                })
+                .unwrap();
+
+            server
+                .interest("result".to_string(), scope)
+                .unwrap()
+                .inspect(move |x| send_results.send((x.0.clone(), x.2)).unwrap())
+                .probe_with(&mut server.probe);
+        });
+
+        server.transact(edges, 0, 0).unwrap();
+        server.advance_domain(None, 1).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        let mut tuples = BTreeMap::new();
+        while let Ok((tuple, diff)) = results.try_recv() {
+            *tuples.entry(tuple).or_insert(0) += diff;
+        }
+
+        let tuples: BTreeSet<Vec<Value>> = tuples
+            .into_iter()
+            .filter(|(_tuple, count)| *count != 0)
+            .map(|(tuple, _count)| tuple)
+            .collect();
+
+        assert_eq!(tuples, expected.into_iter().collect());
+    });
+}
+
+const EDGES: &[(u64, u64)] = &[(1, 2), (2, 3), (3, 4), (1, 3), (5, 6)];
+
+#[test]
+fn transitive_closure() {
+    run(
+        algorithms::transitive_closure("result", ":edge"),
+        EDGES,
+        vec![
+            vec![Eid(1), Eid(2)],
+            vec![Eid(1), Eid(3)],
+            vec![Eid(1), Eid(4)],
+            vec![Eid(2), Eid(3)],
+            vec![Eid(2), Eid(4)],
+            vec![Eid(3), Eid(4)],
+            vec![Eid(5), Eid(6)],
+        ],
+    );
+}
+
+#[test]
+fn connected_components() {
+    run(
+        algorithms::connected_components("result", ":edge"),
+        EDGES,
+        vec![
+            vec![Eid(1), Eid(1)],
+            vec![Eid(2), Eid(1)],
+            vec![Eid(3), Eid(1)],
+            vec![Eid(4), Eid(1)],
+            vec![Eid(5), Eid(5)],
+            vec![Eid(6), Eid(5)],
+        ],
+    );
+}
+
+#[test]
+fn shortest_paths() {
+    // 1 reaches 4 in three hops as well, but only two are needed.
+    run(
+        algorithms::shortest_paths("result", ":edge", 2),
+        EDGES,
+        vec![
+            vec![Eid(1), Eid(2), Number(1)],
+            vec![Eid(1), Eid(3), Number(1)],
+            vec![Eid(1), Eid(4), Number(2)],
+            vec![Eid(2), Eid(3), Number(1)],
+            vec![Eid(2), Eid(4), Number(2)],
+            vec![Eid(3), Eid(4), Number(1)],
+            vec![Eid(5), Eid(6), Number(1)],
+        ],
+    );
+
+    // Paths longer than allowed are left out.
+    run(
+        algorithms::shortest_paths("result", ":edge", 1),
+        &[(1, 2), (2, 3)],
+        vec![
+            vec![Eid(1), Eid(2), Number(1)],
+            vec![Eid(2), Eid(3), Number(1)],
+        ],
+    );
+}
+
+#[test]
+fn walk_counts() {
+    run(
+        algorithms::walk_counts("result", ":edge", 2),
+        EDGES,
+        vec![
+            vec![Eid(1), Number(1)],
+            vec![Eid(2), Number(2)],
+            vec![Eid(3), Number(4)],
+            vec![Eid(4), Number(4)],
+            vec![Eid(5), Number(1)],
+            vec![Eid(6), Number(2)],
+        ],
+    );
+}
@@ -0,0 +1,202 @@
+use std::collections::HashMap;
+use std::sync::mpsc::channel;
+
+use declarative_dataflow::plan::PlanBuilder;
+use declarative_dataflow::server::{Register, RegisterConstraint, Server};
+use declarative_dataflow::{Aid, AttributeConfig, Datom, InputSemantics, Plan, Rule, Value};
+use Value::{Eid, String};
+
+#[test]
+fn constraints() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, ()>::new(Default::default());
+        let (send_results, results) = channel();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            for name in [":order/customer", ":customer/name"].iter() {
+                server
+                    .create_attribute(scope, *name, AttributeConfig::tx_time(InputSemantics::Raw))
+                    .unwrap();
+            }
+        });
+
+        server
+            .register(Register {
+                rules: vec![
+                    Rule::named("customers", Plan::match_a(0, ":customer/name", 1)),
+                    Rule::named(
+                        "orphaned-orders",
+                        PlanBuilder::match_a(0, ":order/customer", 1)
+                            .antijoin(PlanBuilder::match_a(1, ":customer/name", 2), vec![1])
+                            .build()
+                            .unwrap(),
+                    ),
+                ],
+                publish: vec!["customers".to_string(), "orphaned-orders".to_string()],
+            })
+            .unwrap();
+
+        worker
+            .dataflow::<u64, _, _>(|scope| {
+                server.register_constraint(
+                    scope,
+                    RegisterConstraint {
+                        name: "orders-have-customers".to_string(),
+                        rule: "orphaned-orders".to_string(),
+                    },
+                )
+            })
+            .unwrap();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .interest("customers".to_string(), scope)
+                .unwrap()
+                .inspect(move |x| send_results.send((x.0.clone(), x.2)).unwrap())
+                .probe_with(&mut server.probe);
+        });
+
+        let mut transact = |server: &mut Server<Aid, u64, ()>, tx_data: Vec<Datom<Aid>>, t| {
+            server.transact(tx_data.clone(), 0, 0).unwrap();
+            let checked_at = server.check_constraints(tx_data);
+            server.advance_domain(None, t).unwrap();
+            worker.step_while(|| server.is_any_outdated());
+
+            let checked = server.checked_transactions();
+            assert_eq!(checked.len(), 1);
+            assert_eq!(Some(checked[0].0), checked_at);
+
+            checked[0].1.is_ok()
+        };
+
+        assert!(transact(
+            &mut server,
+            vec![
+                Datom::add(1, ":customer/name", String("Dipper".to_string())),
+                Datom::add(10, ":order/customer", Eid(1)),
+            ],
+            1,
+        ));
+
+        // Both datoms are rolled back, because the order violates
+        // the constraint.
+        assert!(!transact(
+            &mut server,
+            vec![
+                Datom::add(2, ":customer/name", String("Mabel".to_string())),
+                Datom::add(11, ":order/customer", Eid(3)),
+            ],
+            2,
+        ));
+
+        server.advance_domain(None, 3).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+        assert!(server.checked_transactions().is_empty());
+
+        let mut customers = HashMap::new();
+        while let Ok((tuple, diff)) = results.try_recv() {
+            *customers.entry(tuple[1].clone()).or_insert(0) += diff;
+        }
+        customers.retain(|_name, count| *count != 0);
+
+        assert_eq!(customers.len(), 1);
+        assert!(customers.contains_key(&String("Dipper".to_string())));
+    });
+}
+
+#[test]
+fn violations_never_visible() {
+    use declarative_dataflow::operators::Validated;
+
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, ()>::new(Default::default());
+        let (send_results, results) = channel();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            for name in [":order/customer", ":customer/name"].iter() {
+                server
+                    .create_attribute(scope, *name, AttributeConfig::tx_time(InputSemantics::Raw))
+                    .unwrap();
+            }
+        });
+
+        server
+            .register(Register {
+                rules: vec![
+                    Rule::named("customers", Plan::match_a(0, ":customer/name", 1)),
+                    Rule::named(
+                        "orphaned-orders",
+                        PlanBuilder::match_a(0, ":order/customer", 1)
+                            .antijoin(PlanBuilder::match_a(1, ":customer/name", 2), vec![1])
+                            .build()
+                            .unwrap(),
+                    ),
+                ],
+                publish: vec!["customers".to_string(), "orphaned-orders".to_string()],
+            })
+            .unwrap();
+
+        worker
+            .dataflow::<u64, _, _>(|scope| {
+                server.register_constraint(
+                    scope,
+                    RegisterConstraint {
+                        name: "orders-have-customers".to_string(),
+                        rule: "orphaned-orders".to_string(),
+                    },
+                )
+            })
+            .unwrap();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            let validation = server.validation();
+            server
+                .interest("customers".to_string(), scope)
+                .unwrap()
+                .inner
+                .validated(validation)
+                .inspect(move |x| send_results.send(x.clone()).unwrap())
+                .probe_with(&mut server.probe);
+        });
+
+        // Results are held back until transactions are checked, so
+        // the worker has to keep checking while it steps.
+        let mut step = |server: &mut Server<Aid, u64, ()>| {
+            for _ in 0..10 {
+                worker.step();
+                server.checked_transactions();
+            }
+        };
+
+        let txs = vec![
+            vec![
+                Datom::add(1, ":customer/name", String("Dipper".to_string())),
+                Datom::add(10, ":order/customer", Eid(1)),
+            ],
+            vec![
+                Datom::add(2, ":customer/name", String("Mabel".to_string())),
+                Datom::add(11, ":order/customer", Eid(3)),
+            ],
+        ];
+
+        for (t, tx_data) in txs.into_iter().enumerate() {
+            server.transact(tx_data.clone(), 0, 0).unwrap();
+            server.check_constraints(tx_data);
+            server.advance_domain(None, t as u64 + 1).unwrap();
+            step(&mut server);
+        }
+
+        server.advance_domain(None, 3).unwrap();
+        step(&mut server);
+
+        let results: Vec<_> = results.try_iter().collect();
+
+        // Mabel was rolled back, before anyone got to see her.
+        assert!(results
+            .iter()
+            .all(|(tuple, _t, _diff)| tuple[1] != String("Mabel".to_string())));
+        assert!(results
+            .iter()
+            .any(|(tuple, _t, diff)| tuple[1] == String("Dipper".to_string()) && *diff == 1));
+    });
+}
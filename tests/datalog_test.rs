@@ -0,0 +1,196 @@
+use std::collections::HashSet;
+use std::iter::FromIterator;
+use std::sync::mpsc::channel;
+use std::time::Duration;
+
+use timely::dataflow::channels::pact::Pipeline;
+use timely::dataflow::operators::{Operator, Probe};
+
+use declarative_dataflow::frontends::datalog;
+use declarative_dataflow::plan::{Aggregate, AggregationFn, Antijoin, Filter, Join};
+use declarative_dataflow::plan::{Predicate, Project};
+use declarative_dataflow::server::{RegisterQuery, Server};
+use declarative_dataflow::{Aid, AttributeConfig, Datom, InputSemantics, Plan, Value};
+use Value::{Eid, Number, String};
+
+#[test]
+fn compile_patterns() {
+    let plan: Plan<Aid> = datalog::parse("[:find ?n :where [?e :name ?n]]").unwrap();
+
+    assert_eq!(
+        plan,
+        Plan::Project(Project {
+            variables: vec![2],
+            plan: Box::new(Plan::match_a(1, ":name", 2)),
+        })
+    );
+
+    let plan: Plan<Aid> =
+        datalog::parse(r#"[:find ?e ?age :where [?e :name "Mabel"] [?e :age ?age]]"#).unwrap();
+
+    assert_eq!(
+        plan,
+        Plan::Project(Project {
+            variables: vec![1, 2],
+            plan: Box::new(Plan::Join(Join {
+                variables: vec![1],
+                left_plan: Box::new(Plan::match_av(1, ":name", String("Mabel".to_string()))),
+                right_plan: Box::new(Plan::match_a(1, ":age", 2)),
+            })),
+        })
+    );
+
+    let plan: Plan<Aid> =
+        datalog::parse(r#"[:find ?n :where [[:email "mabel@pines.com"] :name ?n]]"#).unwrap();
+
+    assert_eq!(
+        plan,
+        Plan::Project(Project {
+            variables: vec![1],
+            plan: Box::new(Plan::match_lookup_a(
+                (":email", "mabel@pines.com"),
+                ":name",
+                1
+            )),
+        })
+    );
+}
+
+#[test]
+fn compile_constraints() {
+    let plan: Plan<Aid> =
+        datalog::parse("[:find ?e :where [?e :age ?age] [(< ?age 18)] (not [?e :guardian _])]")
+            .unwrap();
+
+    assert_eq!(
+        plan,
+        Plan::Project(Project {
+            variables: vec![1],
+            plan: Box::new(Plan::Antijoin(Antijoin {
+                variables: vec![1],
+                left_plan: Box::new(Plan::Filter(Filter {
+                    variables: vec![2],
+                    predicate: Predicate::LT,
+                    plan: Box::new(Plan::match_a(1, ":age", 2)),
+                    constants: vec![None, Some(Number(18))],
+                })),
+                right_plan: Box::new(Plan::match_a(1, ":guardian", 3)),
+            })),
+        })
+    );
+}
+
+#[test]
+fn compile_aggregations() {
+    let plan: Plan<Aid> = datalog::parse(
+        "[:find ?e (sum ?amount) :with ?tx :where [?e :amount ?amount] [?tx :item ?e]]",
+    )
+    .unwrap();
+
+    assert_eq!(
+        plan,
+        Plan::Aggregate(Aggregate {
+            variables: vec![1, 2],
+            plan: Box::new(Plan::Project(Project {
+                variables: vec![1, 2, 3],
+                plan: Box::new(Plan::Join(Join {
+                    variables: vec![1],
+                    left_plan: Box::new(Plan::match_a(1, ":amount", 2)),
+                    right_plan: Box::new(Plan::match_a(3, ":item", 1)),
+                })),
+            })),
+            aggregation_fns: vec![AggregationFn::SUM],
+            key_variables: vec![1],
+            aggregation_variables: vec![2],
+            with_variables: vec![3],
+            key_universe: None,
+            defaults: vec![],
+        })
+    );
+}
+
+#[test]
+fn reject_invalid_queries() {
+    let invalid = vec![
+        "[:where [?e :name ?n]]",
+        "[:find ?x :where [?e :name ?n]]",
+        "[:find ?e :where [?e :name ?n] [?f :age ?a]]",
+        "[:find ?e :where [?e :name ?n] [(< ?a 10)]]",
+        "[:find (frobnicate ?e) :where [?e :name ?n]]",
+        "[:find ?e :where [?e :name ?n]",
+    ];
+
+    for query in invalid.iter() {
+        assert!(datalog::parse::<Aid>(query).is_err(), "{}", query);
+    }
+}
+
+#[test]
+fn register_query() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, u64>::new(Default::default());
+        let (send_results, results) = channel();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            for attribute in [":name", ":age"].iter() {
+                server
+                    .create_attribute(
+                        scope,
+                        *attribute,
+                        AttributeConfig::tx_time(InputSemantics::Raw),
+                    )
+                    .unwrap();
+            }
+
+            server
+                .register_query(RegisterQuery {
+                    name: "adults".to_string(),
+                    query: "[:find ?e ?n :where [?e :name ?n] [?e :age ?age] [(>= ?age 18)]]"
+                        .to_string(),
+                    language: Default::default(),
+                })
+                .unwrap();
+
+            server
+                .interest("adults".to_string(), scope)
+                .unwrap()
+                .inner
+                .probe_with(&mut server.probe)
+                .sink(Pipeline, "Results", move |input| {
+                    input.for_each(|_time, data| {
+                        for datum in data.iter() {
+                            send_results.send(datum.clone()).unwrap()
+                        }
+                    });
+                });
+        });
+
+        server
+            .transact(
+                vec![
+                    Datom::add(1, ":name", String("Dipper".to_string())),
+                    Datom::add(1, ":age", Number(12)),
+                    Datom::add(2, ":name", String("Stan".to_string())),
+                    Datom::add(2, ":age", Number(68)),
+                ],
+                0,
+                0,
+            )
+            .unwrap();
+
+        server.advance_domain(None, 1).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        let mut expected: HashSet<(Vec<Value>, u64, isize)> =
+            HashSet::from_iter(vec![(vec![Eid(2), String("Stan".to_string())], 0, 1)]);
+
+        for _i in 0..expected.len() {
+            let result = results.recv_timeout(Duration::from_millis(400)).unwrap();
+            if !expected.remove(&result) {
+                panic!("Unknown result {:?}.", result);
+            }
+        }
+
+        assert!(results.recv_timeout(Duration::from_millis(400)).is_err());
+    });
+}
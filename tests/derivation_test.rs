@@ -0,0 +1,106 @@
+use std::collections::HashMap;
+use std::sync::mpsc::channel;
+
+use declarative_dataflow::plan::{Join, Project};
+use declarative_dataflow::server::{DeriveAttribute, Register, Server};
+use declarative_dataflow::{Aid, AttributeConfig, Datom, InputSemantics, Plan, Rule, Value};
+use Value::Eid;
+
+#[test]
+fn derive_attribute() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, ()>::new(Default::default());
+        let (send_results, results) = channel();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .create_attribute(
+                    scope,
+                    ":parent",
+                    AttributeConfig::tx_time(InputSemantics::Raw),
+                )
+                .unwrap();
+        });
+
+        let (e, p, g) = (0, 1, 2);
+        server
+            .register(Register {
+                rules: vec![Rule::named(
+                    "grandparent",
+                    Plan::Project(Project {
+                        variables: vec![e, g],
+                        plan: Box::new(Plan::Join(Join {
+                            variables: vec![p],
+                            left_plan: Box::new(Plan::match_a(e, ":parent", p)),
+                            right_plan: Box::new(Plan::match_a(p, ":parent", g)),
+                        })),
+                    }),
+                )],
+                publish: vec!["grandparent".to_string()],
+            })
+            .unwrap();
+
+        let derive = |rule: &str| DeriveAttribute {
+            rule: rule.to_string(),
+            name: ":grandparent".to_string(),
+            config: None,
+        };
+
+        worker
+            .dataflow::<u64, _, _>(|scope| server.derive_attribute(scope, derive("grandparent")))
+            .unwrap();
+
+        // Neither unknown rules nor taken names can be derived.
+        assert!(worker
+            .dataflow::<u64, _, _>(|scope| server.derive_attribute(scope, derive("unknown")))
+            .is_err());
+        assert!(worker
+            .dataflow::<u64, _, _>(|scope| server.derive_attribute(scope, derive("grandparent")))
+            .is_err());
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .register(Register {
+                    rules: vec![Rule::named(
+                        "grandchildren",
+                        Plan::match_av(e, ":grandparent", Eid(300)),
+                    )],
+                    publish: vec!["grandchildren".to_string()],
+                })
+                .unwrap();
+
+            server
+                .interest("grandchildren".to_string(), scope)
+                .unwrap()
+                .inspect(move |x| send_results.send((x.0.clone(), x.2)).unwrap())
+                .probe_with(&mut server.probe);
+        });
+
+        server
+            .transact(
+                vec![
+                    Datom::add(100, ":parent", Eid(200)),
+                    Datom::add(101, ":parent", Eid(200)),
+                    Datom::add(102, ":parent", Eid(201)),
+                    Datom::add(200, ":parent", Eid(300)),
+                    Datom::add(201, ":parent", Eid(301)),
+                ],
+                0,
+                0,
+            )
+            .unwrap();
+
+        server.advance_domain(None, 1).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        let mut grandchildren = HashMap::new();
+        while let Ok((tuple, diff)) = results.try_recv() {
+            *grandchildren.entry(tuple[0].clone()).or_insert(0) += diff;
+        }
+        grandchildren.retain(|_e, count| *count != 0);
+
+        assert_eq!(grandchildren.len(), 2);
+        assert!(grandchildren.contains_key(&Eid(100)));
+        assert!(grandchildren.contains_key(&Eid(101)));
+    });
+}
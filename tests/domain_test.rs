@@ -2,9 +2,10 @@ use timely::dataflow::operators::UnorderedInput;
 use timely::progress::frontier::AntichainRef;
 
 use differential_dataflow::trace::TraceReader;
+use differential_dataflow::AsCollection;
 
 use declarative_dataflow::domain::{AsSingletonDomain, Domain};
-use declarative_dataflow::{Aid, Value};
+use declarative_dataflow::{Aid, AttributeConfig, Datom, Value};
 
 #[test]
 fn test_advance_epoch() {
@@ -192,3 +193,67 @@ fn test_advance_only_source() {
         );
     });
 }
+
+#[test]
+fn test_ingest() {
+    timely::execute_directly(move |worker| {
+        let mut domain: Domain<Aid, u64> = worker.dataflow::<u64, _, _>(|scope| {
+            scope
+                .new_unordered_input::<((Value, Value), u64, isize)>()
+                .as_singleton_domain("tx_test")
+                .into()
+        });
+
+        domain.advance_epoch(2).unwrap();
+
+        let datum = (Value::Eid(1), Value::Number(10));
+
+        assert!(domain
+            .ingest(
+                &"tx_test".to_string(),
+                vec![(datum.clone(), 3, 1), (datum.clone(), 1, 1)]
+            )
+            .is_err());
+        assert!(domain
+            .ingest(&"unknown".to_string(), vec![(datum.clone(), 3, 1)])
+            .is_err());
+        assert!(domain
+            .ingest(
+                &"tx_test".to_string(),
+                vec![(datum.clone(), 2, 1), (datum, 5, -1)]
+            )
+            .is_ok());
+    });
+}
+
+#[test]
+fn test_append_only() {
+    timely::execute_directly(move |worker| {
+        let mut domain: Domain<Aid, u64> = worker.dataflow::<u64, _, _>(|scope| {
+            let (input, pairs) = scope.new_unordered_input::<((Value, Value), u64, isize)>();
+
+            (input, pairs.as_collection())
+                .as_singleton_domain("tx_test")
+                .into()
+        });
+
+        domain.attributes.insert(
+            "tx_test".to_string(),
+            AttributeConfig {
+                append_only: true,
+                ..Default::default()
+            },
+        );
+
+        let added = Datom::add(1, "tx_test", Value::Number(10));
+
+        assert!(domain.transact(vec![added.clone()]).is_ok());
+        assert!(domain
+            .transact(vec![Datom::retract(1, "tx_test", Value::Number(10))])
+            .is_err());
+
+        // Transactions violating constraints are rolled back all the
+        // same.
+        assert!(domain.roll_back(vec![added]).is_ok());
+    });
+}
@@ -0,0 +1,177 @@
+use declarative_dataflow::frontends::edn::{read_one, Edn};
+use declarative_dataflow::ids::{IdAllocator, IdStrategy};
+use declarative_dataflow::server::edn::{self, format_instant, parse_instant};
+use declarative_dataflow::server::Request;
+use declarative_dataflow::timestamp::Time;
+use declarative_dataflow::{Aid, Datom, Output, Rational32, Uuid, Value};
+
+#[test]
+fn print_and_read() {
+    let form = Edn::Vector(vec![
+        Edn::Nil,
+        Edn::Ratio(-1, 3),
+        Edn::Float(0.5),
+        Edn::Float(std::f64::INFINITY),
+        Edn::String("\"quoted\"\n".to_string()),
+        Edn::Keyword("person/name".to_string()),
+        Edn::Map(vec![(
+            Edn::Symbol("?e".to_string()),
+            Edn::Tagged(
+                "inst".to_string(),
+                Box::new(Edn::String("2019".to_string())),
+            ),
+        )]),
+    ]);
+
+    let printed = form.to_string();
+    assert_eq!(
+        printed,
+        r#"[nil -1/3 0.5 ##Inf "\"quoted\"\n" :person/name {?e #inst "2019"}]"#
+    );
+    assert_eq!(read_one(&printed).unwrap(), form);
+
+    assert_eq!(read_one("42N").unwrap(), Edn::Integer(42));
+    assert_eq!(
+        read_one("18446744073709551615N").unwrap(),
+        Edn::BigInt(std::u64::MAX)
+    );
+    assert_eq!(
+        Edn::BigInt(std::u64::MAX).to_string(),
+        "18446744073709551615N"
+    );
+    assert!(read_one("18446744073709551616N").is_err());
+    assert_eq!(read_one("1e3").unwrap(), Edn::Float(1000.0));
+    assert!(read_one("1.5M").is_err());
+    assert!(read_one("1/0").is_err());
+}
+
+#[test]
+fn instants() {
+    assert_eq!(format_instant(0), "1970-01-01T00:00:00.000Z");
+    assert_eq!(format_instant(1552576166535), "2019-03-14T15:09:26.535Z");
+    assert_eq!(format_instant(951782400000), "2000-02-29T00:00:00.000Z");
+
+    assert_eq!(
+        parse_instant("2019-03-14T15:09:26.535Z"),
+        Some(1552576166535)
+    );
+    assert_eq!(
+        parse_instant("2019-03-14T15:09:26.535123Z"),
+        Some(1552576166535)
+    );
+    assert_eq!(
+        parse_instant("2019-03-14T17:09:26.535+02:00"),
+        Some(1552576166535)
+    );
+    assert_eq!(parse_instant("2000-02-29"), Some(951782400000));
+    assert_eq!(parse_instant("1969-12-31T23:59:59Z"), None);
+    assert_eq!(parse_instant("2019-13-01"), None);
+    assert_eq!(parse_instant("yesterday"), None);
+}
+
+#[test]
+fn values() {
+    let uuid = Uuid::parse_str("936DA01F9ABD4d9d80C702AF85C822A8").unwrap();
+    let values = vec![
+        Value::Aid(":person/name".to_string()),
+        Value::Aid("name".to_string()),
+        Value::String("Dipper".to_string()),
+        Value::Bool(true),
+        Value::Number(12),
+        Value::Rational32(Rational32::new(1, 3)),
+        Value::Eid(12),
+        Value::Instant(1552576166535),
+        Value::Uuid(uuid),
+        Value::lookup_ref(":person/name", "Mabel"),
+    ];
+
+    let printed = edn::to_string(&values).unwrap();
+    assert_eq!(
+        printed,
+        concat!(
+            r#"[:person/name #df/aid "name" "Dipper" true 12 1/3 #df/eid 12 "#,
+            r#"#inst "2019-03-14T15:09:26.535Z" #uuid "936da01f-9abd-4d9d-80c7-02af85c822a8" "#,
+            r#"{:LookupRef [":person/name" "Mabel"]}]"#
+        )
+    );
+    assert_eq!(edn::from_str::<Vec<Value>>(&printed), Ok(values));
+}
+
+#[test]
+fn allocated_eids() {
+    let mut ids = IdAllocator::new(IdStrategy::Partitioned, 0, 1);
+    let eid = ids.allocate(1).unwrap()[0];
+    assert!(eid > std::i64::MAX as u64);
+
+    let printed = edn::to_string(&Value::Eid(eid)).unwrap();
+    assert_eq!(printed, format!("#df/eid {}N", eid));
+    assert_eq!(edn::from_str::<Value>(&printed), Ok(Value::Eid(eid)));
+
+    let datom = Datom::add(eid, ":person/name", Value::String("Dipper".to_string()));
+    let printed = edn::to_string(&datom).unwrap();
+    assert_eq!(edn::from_str::<Datom<Aid>>(&printed), Ok(datom));
+}
+
+#[test]
+fn requests() {
+    let requests = edn::from_str::<Vec<Request<Aid>>>(
+        r#"[{:Transact [[#df/eid 1 :person/name "Dipper" nil 1]
+                        [#df/eid 2 :person/age 12 {:TxId 3} -1]]}
+            {:Uninterest "adults"}]"#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        requests,
+        vec![
+            Request::Transact(vec![
+                Datom::add(1, ":person/name", Value::String("Dipper".to_string())),
+                Datom::retract_at(2, ":person/age", Value::Number(12), Time::TxId(3)),
+            ]),
+            Request::Uninterest("adults".to_string()),
+        ]
+    );
+
+    assert!(edn::from_str::<Vec<Request<Aid>>>("[{:Transact [[1 2]]}]").is_err());
+    assert!(edn::from_str::<Vec<Request<Aid>>>("[{:Fly {}}]").is_err());
+}
+
+#[test]
+fn outputs() {
+    let out = Output::QueryDiff(
+        "adults".to_string(),
+        vec![(
+            vec![Value::Eid(1), Value::Rational32(Rational32::new(3, 2))],
+            Time::TxId(3),
+            1,
+        )],
+    );
+
+    assert_eq!(
+        edn::to_string(&out).unwrap(),
+        r#"{:QueryDiff ["adults" [[[#df/eid 1 3/2] {:TxId 3} 1]]]}"#
+    );
+}
+
+#[cfg(feature = "serde_json")]
+#[test]
+fn envelopes() {
+    use declarative_dataflow::ProtocolError;
+
+    let envelope = edn::parse_envelope::<Aid>(
+        read_one(r#"{:id 7 :requests [{:Uninterest "adults"}]}"#).unwrap(),
+    )
+    .unwrap();
+
+    assert_eq!(envelope.id, 7);
+    assert_eq!(
+        envelope.requests,
+        vec![Request::Uninterest("adults".to_string())]
+    );
+
+    let v1 = read_one("{:id 7 :version 1 :requests []}").unwrap();
+    assert_eq!(
+        edn::parse_envelope::<Aid>(v1).map(|envelope| envelope.id),
+        Err(ProtocolError::UnsupportedVersion(1))
+    );
+}
@@ -0,0 +1,66 @@
+use std::sync::mpsc::channel;
+
+use differential_dataflow::input::Input;
+
+use declarative_dataflow::server::{Register, Server};
+use declarative_dataflow::{Aid, AttributeConfig, InputSemantics, Plan, Rule, Value};
+use Value::{Eid, String};
+
+#[test]
+fn register_collection() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, ()>::new(Default::default());
+        let (send_results, results) = channel();
+
+        // The surrounding computation owns the input and decides what
+        // to feed into the attribute.
+        let mut input = worker.dataflow::<u64, _, _>(|scope| {
+            let (input, pairs) = scope.new_collection();
+
+            let names =
+                pairs.map(|(e, name): (u64, &'static str)| (Eid(e), String(name.to_string())));
+
+            server
+                .register_collection(
+                    ":name",
+                    AttributeConfig::tx_time(InputSemantics::Raw),
+                    &names,
+                )
+                .unwrap();
+
+            assert!(server
+                .register_collection(
+                    ":name",
+                    AttributeConfig::tx_time(InputSemantics::Raw),
+                    &names
+                )
+                .is_err());
+
+            server
+                .register(Register {
+                    rules: vec![Rule::named("names", Plan::match_a(0, ":name", 1))],
+                    publish: vec!["names".to_string()],
+                })
+                .unwrap();
+
+            server
+                .interest("names".to_string(), scope)
+                .unwrap()
+                .inspect(move |x| send_results.send((x.0.clone(), x.2)).unwrap())
+                .probe_with(&mut server.probe);
+
+            input
+        });
+
+        input.insert((1, "Dipper"));
+        input.advance_to(1);
+        input.flush();
+
+        worker.step_while(|| server.probe.less_than(input.time()));
+
+        assert_eq!(
+            results.recv().unwrap(),
+            (vec![Eid(1), String("Dipper".to_string())], 1)
+        );
+    });
+}
@@ -0,0 +1,201 @@
+use std::collections::HashMap;
+use std::sync::mpsc::channel;
+
+use declarative_dataflow::plan::Predicate;
+use declarative_dataflow::server::{Excise, Register, Request, Server};
+use declarative_dataflow::{Aid, AttributeConfig, Datom, InputSemantics, Plan, Rule, Value};
+use Value::{Eid, Number, String};
+
+#[test]
+fn excise() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, ()>::new(Default::default());
+        let (send_results, results) = channel();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            for name in [":name", ":age"].iter() {
+                server
+                    .create_attribute(scope, *name, AttributeConfig::tx_time(InputSemantics::Raw))
+                    .unwrap();
+            }
+
+            server
+                .register(Register {
+                    rules: vec![
+                        Rule::named("names", Plan::match_a(0, ":name", 1)),
+                        Rule::named("ages", Plan::match_a(0, ":age", 1)),
+                    ],
+                    publish: vec!["names".to_string(), "ages".to_string()],
+                })
+                .unwrap();
+
+            for name in ["names", "ages"].iter() {
+                let send_results = send_results.clone();
+
+                server
+                    .interest(name.to_string(), scope)
+                    .unwrap()
+                    .inspect(move |x| send_results.send((x.0.clone(), x.2)).unwrap())
+                    .probe_with(&mut server.probe);
+            }
+        });
+
+        server
+            .transact(
+                vec![
+                    Datom::add(1, ":name", String("Dipper".to_string())),
+                    Datom::add(1, ":age", Number(12)),
+                    Datom::add(2, ":name", String("Stan".to_string())),
+                    Datom::add(2, ":age", Number(60)),
+                    Datom::add(3, ":age", Number(13)),
+                ],
+                0,
+                0,
+            )
+            .unwrap();
+        server.advance_domain(None, 1).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        assert!(server.excise(Default::default()).is_err());
+
+        server
+            .excise(Excise {
+                entity: Some(Eid(1)),
+                ..Default::default()
+            })
+            .unwrap();
+
+        // Excisions only see data that has been indexed already.
+        server.advance_domain(None, 2).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        server
+            .excise(Excise {
+                attribute: Some(":age".to_string()),
+                value: Some((Predicate::LT, Number(18))),
+                ..Default::default()
+            })
+            .unwrap();
+
+        server.advance_domain(None, 3).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        let mut remaining = HashMap::new();
+        while let Ok((tuple, diff)) = results.try_recv() {
+            *remaining.entry(tuple).or_insert(0) += diff;
+        }
+        remaining.retain(|_tuple, count| *count != 0);
+
+        assert_eq!(remaining.len(), 2);
+        assert!(remaining.contains_key(&vec![Eid(2), String("Stan".to_string())]));
+        assert!(remaining.contains_key(&vec![Eid(2), Number(60)]));
+    });
+}
+
+#[test]
+fn excise_unindexed() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, ()>::new(Default::default());
+        let (send_results, results) = channel();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .create_attribute(
+                    scope,
+                    ":name",
+                    AttributeConfig::tx_time(InputSemantics::Raw),
+                )
+                .unwrap();
+
+            server
+                .register(Register {
+                    rules: vec![Rule::named("names", Plan::match_a(0, ":name", 1))],
+                    publish: vec!["names".to_string()],
+                })
+                .unwrap();
+
+            server
+                .interest("names".to_string(), scope)
+                .unwrap()
+                .inspect(move |x| send_results.send((x.0.clone(), x.2)).unwrap())
+                .probe_with(&mut server.probe);
+        });
+
+        server
+            .transact(
+                vec![
+                    Datom::add(1, ":name", String("Dipper".to_string())),
+                    Datom::add(2, ":name", String("Stan".to_string())),
+                ],
+                0,
+                0,
+            )
+            .unwrap();
+
+        // Nothing has been indexed yet.
+        server
+            .excise(Excise {
+                entity: Some(Eid(1)),
+                ..Default::default()
+            })
+            .unwrap();
+
+        for t in 1..4 {
+            server.advance_domain(None, t).unwrap();
+            worker.step_while(|| server.is_any_outdated());
+        }
+
+        let mut remaining = HashMap::new();
+        while let Ok((tuple, diff)) = results.try_recv() {
+            *remaining.entry(tuple).or_insert(0) += diff;
+        }
+        remaining.retain(|_tuple, count| *count != 0);
+
+        assert_eq!(remaining.len(), 1);
+        assert!(remaining.contains_key(&vec![Eid(2), String("Stan".to_string())]));
+    });
+}
+
+#[test]
+fn excise_requests() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, ()>::new(Default::default());
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            for name in [":name", ":age"].iter() {
+                server
+                    .create_attribute(scope, *name, AttributeConfig::tx_time(InputSemantics::Raw))
+                    .unwrap();
+            }
+        });
+
+        let mut requests = vec![
+            Request::Transact(vec![
+                Datom::add(1, ":name", String("Dipper".to_string())),
+                Datom::add(1, ":age", Number(12)),
+                Datom::add(2, ":age", Number(60)),
+            ]),
+            Request::Uninterest("ages".to_string()),
+            Request::Transact(vec![Datom::retract(1, ":age", Number(12))]),
+        ];
+
+        let excise = Excise {
+            entity: Some(Eid(1)),
+            attribute: Some(":age".to_string()),
+            value: None,
+        };
+
+        assert_eq!(server.excise_requests(&excise, &mut requests).ok(), Some(2));
+        assert_eq!(
+            requests,
+            vec![
+                Request::Transact(vec![
+                    Datom::add(1, ":name", String("Dipper".to_string())),
+                    Datom::add(2, ":age", Number(60)),
+                ]),
+                Request::Uninterest("ages".to_string()),
+                Request::Transact(vec![]),
+            ]
+        );
+    });
+}
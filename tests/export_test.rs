@@ -0,0 +1,108 @@
+use std::path::PathBuf;
+
+use declarative_dataflow::server::{Configuration, CreateAttribute, Register, Request, Server};
+use declarative_dataflow::{Aid, AttributeConfig, Datom, InputSemantics, Plan, Rule, Value};
+use Value::{Eid, String};
+
+#[test]
+fn export_state() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, ()>::new(Default::default());
+        let config = AttributeConfig::tx_time(InputSemantics::Raw);
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .create_attribute(scope, ":name", config.clone())
+                .unwrap();
+        });
+
+        let rule = Rule::named("names", Plan::match_a(0, ":name", 1));
+
+        server
+            .register(Register {
+                rules: vec![rule.clone()],
+                publish: vec![],
+            })
+            .unwrap();
+
+        server
+            .transact(
+                vec![
+                    Datom::add(1, ":name", String("Dipper".to_string())),
+                    Datom::add(2, ":name", String("Mabel".to_string())),
+                ],
+                0,
+                0,
+            )
+            .unwrap();
+        server.advance_domain(None, 1).unwrap();
+
+        server
+            .transact(
+                vec![Datom::retract(1, ":name", String("Dipper".to_string()))],
+                0,
+                0,
+            )
+            .unwrap();
+        server.advance_domain(None, 2).unwrap();
+
+        worker.step_while(|| server.is_any_outdated());
+
+        // History is collapsed, only the current state remains.
+        assert_eq!(
+            server.export_state(0),
+            vec![
+                Request::CreateAttribute(CreateAttribute {
+                    name: ":name".to_string(),
+                    config,
+                    backfill: None,
+                }),
+                Request::Register(Register {
+                    rules: vec![rule],
+                    publish: vec![],
+                }),
+                Request::Transact(vec![Datom(
+                    Eid(2),
+                    ":name".to_string(),
+                    String("Mabel".to_string()),
+                    None,
+                    1
+                )]),
+            ]
+        );
+
+        // Other workers only export their share of the data.
+        assert_eq!(server.export_state(1).len(), 1);
+
+        // Draining closes all inputs, but their contents are still
+        // exported, e.g. when rescaling.
+        let exported = server.export_state(0);
+        server.drain();
+        worker.step_while(|| server.is_any_outdated());
+        assert_eq!(server.export_state(0), exported);
+    });
+}
+
+#[test]
+fn export_paths() {
+    let server = Server::<Aid, u64, ()>::new(Default::default());
+    assert!(server.export_path("state").is_err());
+
+    let config = Configuration {
+        export_dir: Some("/var/lib/3df".to_string()),
+        ..Default::default()
+    };
+    let server = Server::<Aid, u64, ()>::new(config);
+
+    assert_eq!(
+        server.export_path("backups/state").ok(),
+        Some(PathBuf::from("/var/lib/3df/backups/state"))
+    );
+
+    for path in ["", "/etc/passwd", "../state", "backups/../../state"].iter() {
+        assert_eq!(
+            server.export_path(path).map_err(|error| error.category),
+            Err("df.error.category/incorrect".to_string())
+        );
+    }
+}
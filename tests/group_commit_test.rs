@@ -0,0 +1,52 @@
+use std::time::Duration;
+
+use declarative_dataflow::server::GroupCommit;
+
+#[test]
+fn group_commit_limits() {
+    let limits = GroupCommit {
+        max_delay: Duration::from_millis(5),
+        max_datoms: 100,
+        min_delay: None,
+    };
+
+    assert!(!limits.is_due(Duration::from_millis(0), 0));
+    assert!(!limits.is_due(Duration::from_millis(4), 99));
+
+    // Either limit commits a group.
+    assert!(limits.is_due(Duration::from_millis(5), 0));
+    assert!(limits.is_due(Duration::from_millis(0), 100));
+}
+
+#[test]
+fn adaptive_group_commit() {
+    let limits = GroupCommit {
+        max_delay: Duration::from_millis(8),
+        max_datoms: 100,
+        min_delay: Some(Duration::from_millis(1)),
+    };
+
+    let delay = limits.initial_delay();
+    assert_eq!(delay, Duration::from_millis(1));
+    assert!(limits.is_due_after(delay, Duration::from_millis(1), 0));
+
+    // Delays grow under load, up to the maximum.
+    let delay = limits.next_delay(delay, 10);
+    assert_eq!(delay, Duration::from_millis(2));
+    let delay = limits.next_delay(limits.next_delay(delay, 10), 10);
+    assert_eq!(delay, Duration::from_millis(8));
+    assert_eq!(limits.next_delay(delay, 10), Duration::from_millis(8));
+
+    // And shrink again as transactions trickle in.
+    let delay = limits.next_delay(delay, 1);
+    assert_eq!(delay, Duration::from_millis(4));
+    let delay = limits.next_delay(limits.next_delay(delay, 1), 0);
+    assert_eq!(delay, Duration::from_millis(1));
+
+    // Fixed delays don't adapt.
+    let fixed = GroupCommit {
+        min_delay: None,
+        ..limits
+    };
+    assert_eq!(fixed.next_delay(delay, 10), Duration::from_millis(8));
+}
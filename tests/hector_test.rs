@@ -8,11 +8,11 @@ use timely::dataflow::operators::Operator;
 
 use declarative_dataflow::binding::BinaryPredicate::LT;
 use declarative_dataflow::binding::{AsBinding, Binding};
-use declarative_dataflow::plan::hector::{plan_order, source_conflicts};
-use declarative_dataflow::plan::{Hector, Implementable};
+use declarative_dataflow::plan::hector::{plan_order, plan_order_by, source_conflicts};
+use declarative_dataflow::plan::{Hector, Implementable, VariableOrder};
 use declarative_dataflow::server::Server;
 use declarative_dataflow::timestamp::Time;
-use declarative_dataflow::{Aid, Datom, Plan, Rule, Value};
+use declarative_dataflow::{Aid, Datom, Plan, PlanError, Rule, Value};
 use declarative_dataflow::{AttributeConfig, IndexDirection, QuerySupport};
 use Value::{Bool, Eid, Number, String};
 
@@ -97,13 +97,22 @@ fn conflicts() {
         Binding::not(Binding::constant(c, String("Petr".to_string()))),
     ];
 
-    assert_eq!(source_conflicts(0, &bindings), Vec::<&Binding<Aid>>::new());
+    assert_eq!(
+        source_conflicts(0, &bindings),
+        Ok(Vec::<&Binding<Aid>>::new())
+    );
     assert_eq!(
         source_conflicts(2, &bindings),
-        vec![
+        Ok(vec![
             &Binding::constant(c, String("Ivan".to_string())),
             &Binding::not(Binding::constant(c, String("Petr".to_string()))),
-        ]
+        ])
+    );
+    assert_eq!(
+        source_conflicts(4, &bindings),
+        Err(PlanError::InvalidArgument(
+            "Source binding must be an attribute.".to_string()
+        ))
     );
 }
 
@@ -121,7 +130,7 @@ fn ordering() {
     ];
 
     {
-        let (variable_order, binding_order) = plan_order(0, &bindings);
+        let (variable_order, binding_order) = plan_order(0, &bindings).unwrap();
 
         assert_eq!(variable_order, vec![e2, a, e, n, c]);
         assert_eq!(
@@ -135,7 +144,7 @@ fn ordering() {
         );
     }
     {
-        let (variable_order, binding_order) = plan_order(1, &bindings);
+        let (variable_order, binding_order) = plan_order(1, &bindings).unwrap();
 
         assert_eq!(variable_order, vec![e, a, c, e2, n]);
         assert_eq!(
@@ -149,7 +158,7 @@ fn ordering() {
         );
     }
     {
-        let (variable_order, binding_order) = plan_order(2, &bindings);
+        let (variable_order, binding_order) = plan_order(2, &bindings).unwrap();
 
         assert_eq!(variable_order, vec![e, c, a, e2, n]);
         assert_eq!(
@@ -164,6 +173,52 @@ fn ordering() {
     }
 }
 
+/// Ensures that the cheapest variable is extended to next, as long as
+/// it is ready to be bound.
+#[test]
+fn ordering_by_cost() {
+    let (e, c, e2, a, n) = (0, 1, 2, 3, 4);
+    let bindings: Vec<Binding<Aid>> = vec![
+        Binding::attribute(e2, ":age", a),
+        Binding::attribute(e, ":age", a),
+        Binding::attribute(e, ":name", c),
+        Binding::attribute(e2, ":name", n),
+        Binding::constant(c, String("Ivan".to_string())),
+    ];
+
+    let explicit = vec![c, n, e];
+    let position = |_: &Binding<Aid>, target: u32| {
+        explicit
+            .iter()
+            .position(|x| *x == target)
+            .unwrap_or(explicit.len())
+    };
+
+    // Constants are ready to extend to their variable right away.
+    assert_eq!(
+        plan_order_by(0, &bindings, &position),
+        Ok(vec![e2, a, c, n, e])
+    );
+    assert_eq!(
+        plan_order_by(1, &bindings, &position),
+        Ok(vec![e, a, c, e2, n])
+    );
+
+    let size = |binding: &Binding<Aid>, _: u32| match binding {
+        Binding::Attribute(binding) if binding.source_attribute == ":age" => 10,
+        Binding::Attribute(_) => 1000,
+        _ => 0,
+    };
+
+    assert_eq!(plan_order_by(2, &bindings, &size), Ok(vec![e, c, a, e2, n]));
+    assert_eq!(plan_order_by(3, &bindings, &size), Ok(vec![e2, n, c, a, e]));
+
+    // Prefixes can only be sourced from attributes.
+    let error = PlanError::InvalidArgument("Source binding must be an attribute.".to_string());
+    assert_eq!(plan_order(4, &bindings), Err(error.clone()));
+    assert_eq!(plan_order_by(4, &bindings, &size), Err(error));
+}
+
 #[test]
 fn run_hector_cases() {
     let mut cases: Vec<Case> = vec![
@@ -172,6 +227,7 @@ fn run_hector_cases() {
             plan: Hector {
                 variables: vec![0, 1],
                 bindings: vec![Binding::attribute(0, ":name", 1)],
+                order: Default::default(),
             },
             transactions: vec![vec![
                 Datom::add(1, ":name", String("Dipper".to_string())),
@@ -192,6 +248,7 @@ fn run_hector_cases() {
                     Binding::attribute(0, ":name", 1),
                     Binding::constant(1, String("Dipper".to_string())),
                 ],
+                order: Default::default(),
             },
             transactions: vec![vec![
                 Datom::add(1, ":name", String("Dipper".to_string())),
@@ -210,6 +267,7 @@ fn run_hector_cases() {
                         Binding::attribute(e, ":name", n),
                         Binding::attribute(e, ":age", a),
                     ],
+                    order: Default::default(),
                 },
                 transactions: vec![vec![
                     Datom::add(1, ":name", String("Dipper".to_string())),
@@ -235,6 +293,55 @@ fn run_hector_cases() {
                         Binding::attribute(b, "edge", c),
                         Binding::attribute(a, "edge", c),
                     ],
+                    order: Default::default(),
+                },
+                transactions: vec![vec![
+                    Datom::add(100, "edge", Eid(200)),
+                    Datom::add(200, "edge", Eid(300)),
+                    Datom::add(100, "edge", Eid(300)),
+                    Datom::add(100, "edge", Eid(400)),
+                    Datom::add(400, "edge", Eid(500)),
+                    Datom::add(500, "edge", Eid(100)),
+                ]],
+                expectations: vec![vec![(vec![Eid(100), Eid(200), Eid(300)], 0, 1)]],
+            }
+        },
+        {
+            let (a, b, c) = (1, 2, 3);
+            Case {
+                description: "[?a :edge ?b] [?b :edge ?c] [?a :edge ?c] (explicit order)",
+                plan: Hector {
+                    variables: vec![a, b, c],
+                    bindings: vec![
+                        Binding::attribute(a, "edge", b),
+                        Binding::attribute(b, "edge", c),
+                        Binding::attribute(a, "edge", c),
+                    ],
+                    order: VariableOrder::Explicit(vec![c, b, a]),
+                },
+                transactions: vec![vec![
+                    Datom::add(100, "edge", Eid(200)),
+                    Datom::add(200, "edge", Eid(300)),
+                    Datom::add(100, "edge", Eid(300)),
+                    Datom::add(100, "edge", Eid(400)),
+                    Datom::add(400, "edge", Eid(500)),
+                    Datom::add(500, "edge", Eid(100)),
+                ]],
+                expectations: vec![vec![(vec![Eid(100), Eid(200), Eid(300)], 0, 1)]],
+            }
+        },
+        {
+            let (a, b, c) = (1, 2, 3);
+            Case {
+                description: "[?a :edge ?b] [?b :edge ?c] [?a :edge ?c] (statistics order)",
+                plan: Hector {
+                    variables: vec![a, b, c],
+                    bindings: vec![
+                        Binding::attribute(a, "edge", b),
+                        Binding::attribute(b, "edge", c),
+                        Binding::attribute(a, "edge", c),
+                    ],
+                    order: VariableOrder::Statistics,
                 },
                 transactions: vec![vec![
                     Datom::add(100, "edge", Eid(200)),
@@ -259,6 +366,7 @@ fn run_hector_cases() {
                         Binding::attribute(e, ":likes", c),
                         Binding::attribute(e, ":fears", d),
                     ],
+                    order: Default::default(),
                 },
                 transactions: vec![vec![
                     Datom::add(100, ":name", String("Dipper".to_string())),
@@ -291,6 +399,7 @@ fn run_hector_cases() {
                     Binding::attribute(0, ":num", 2),
                     Binding::binary_predicate(LT, 1, 2),
                 ],
+                order: Default::default(),
             },
             transactions: vec![vec![
                 Datom::add(100, ":num", Number(1)),
@@ -315,6 +424,7 @@ fn run_hector_cases() {
                     Binding::constant(1, Number(10)),
                     Binding::binary_predicate(LT, 2, 3),
                 ],
+                order: Default::default(),
             },
             transactions: vec![vec![
                 Datom::add(100, ":num", Number(1)),
@@ -340,6 +450,7 @@ fn run_hector_cases() {
                         Binding::attribute(e, ":admin?", admin),
                         Binding::constant(admin, Bool(true)),
                     ],
+                    order: Default::default(),
                 },
                 transactions: vec![vec![
                     Datom::add(100, ":name", String("Dipper".to_string())),
@@ -437,3 +548,78 @@ fn run_hector_cases() {
         });
     }
 }
+
+#[test]
+fn basic_query_support() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, u64>::new(Default::default());
+        let (send_results, results) = channel();
+
+        let (a, b, c) = (1, 2, 3);
+        let plan = Plan::Hector(Hector {
+            variables: vec![a, b, c],
+            bindings: vec![
+                Binding::attribute(a, "edge", b),
+                Binding::attribute(b, "edge", c),
+                Binding::attribute(a, "edge", c),
+            ],
+            order: Default::default(),
+        });
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            // Only the forward propose index is created up front.
+            let config = AttributeConfig {
+                trace_slack: Some(Time::TxId(1)),
+                ..Default::default()
+            };
+
+            server
+                .create_attribute(scope, "edge".into(), config)
+                .unwrap();
+        });
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .test_single(scope, Rule::named("hector", plan))
+                .inner
+                .sink(Pipeline, "Results", move |input| {
+                    input.for_each(|_time, data| {
+                        for datum in data.iter() {
+                            send_results.send(datum.clone()).unwrap()
+                        }
+                    });
+                });
+        });
+
+        assert_eq!(
+            server
+                .internal
+                .attribute_config(&"edge".into())
+                .unwrap()
+                .query_support,
+            QuerySupport::AdaptiveWCO
+        );
+
+        server
+            .transact(
+                vec![
+                    Datom::add(100, "edge", Eid(200)),
+                    Datom::add(200, "edge", Eid(300)),
+                    Datom::add(100, "edge", Eid(300)),
+                    Datom::add(100, "edge", Eid(400)),
+                ],
+                0,
+                0,
+            )
+            .unwrap();
+        server.advance_domain(None, 1).unwrap();
+
+        worker.step_while(|| server.is_any_outdated());
+
+        assert_eq!(
+            results.recv_timeout(Duration::from_millis(400)).unwrap(),
+            (vec![Eid(100), Eid(200), Eid(300)], 0, 1)
+        );
+        assert!(results.recv_timeout(Duration::from_millis(400)).is_err());
+    });
+}
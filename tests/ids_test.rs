@@ -0,0 +1,93 @@
+use declarative_dataflow::ids::{IdAllocator, IdStrategy};
+
+#[test]
+fn client_supplied() {
+    let mut ids = IdAllocator::new(IdStrategy::ClientSupplied, 0, 1);
+
+    assert!(ids.allocate(1).is_err());
+    assert!(ids.validate(100).is_ok());
+    assert!(ids.validate(1 << 63).is_err());
+}
+
+#[test]
+fn partitioned() {
+    let mut first = IdAllocator::new(IdStrategy::Partitioned, 0, 2);
+    let mut second = IdAllocator::new(IdStrategy::Partitioned, 1, 2);
+
+    let mut allocated = first.allocate(10).unwrap();
+    allocated.extend(second.allocate(10).unwrap());
+
+    for eid in allocated.iter() {
+        assert!(first.validate(*eid).is_ok());
+        assert!(second.validate(*eid).is_ok());
+    }
+
+    let mut deduplicated = allocated.clone();
+    deduplicated.sort();
+    deduplicated.dedup();
+    assert_eq!(deduplicated.len(), 20);
+
+    // Ids from the range of a third worker can't be valid.
+    let mut unknown = IdAllocator::new(IdStrategy::Partitioned, 2, 3);
+    assert!(first.validate(unknown.allocate(1).unwrap()[0]).is_err());
+
+    // Client-supplied ids remain valid.
+    assert!(first.validate(100).is_ok());
+}
+
+#[test]
+fn squuid() {
+    let mut first = IdAllocator::new(IdStrategy::Squuid, 0, 2);
+    let mut second = IdAllocator::new(IdStrategy::Squuid, 1, 2);
+
+    let earlier = first.allocate(5000).unwrap();
+    let later = second.allocate(10).unwrap();
+
+    // Ids are ordered by time of allocation, even when a single
+    // millisecond doesn't suffice.
+    let mut sorted = earlier.clone();
+    sorted.sort();
+    sorted.dedup();
+    assert_eq!(sorted, earlier);
+
+    for eid in earlier.iter().chain(later.iter()) {
+        assert!(first.validate(*eid).is_ok());
+    }
+
+    assert!(first.validate(std::u64::MAX).is_err());
+}
+
+#[test]
+fn observed() {
+    let mut before = IdAllocator::new(IdStrategy::Partitioned, 1, 2);
+    let allocated = before.allocate(3).unwrap();
+
+    // After a restart, allocation continues past the ids found in the
+    // restored data, but ids of other partitions don't matter.
+    let mut after = IdAllocator::new(IdStrategy::Partitioned, 1, 2);
+    let mut other = IdAllocator::new(IdStrategy::Partitioned, 0, 2);
+    for eid in allocated.iter() {
+        after.observe(*eid);
+        other.observe(*eid);
+    }
+
+    assert!(after.allocate(1).unwrap()[0] > allocated[2]);
+    assert_eq!(
+        other.allocate(1).unwrap(),
+        IdAllocator::new(IdStrategy::Partitioned, 0, 2)
+            .allocate(1)
+            .unwrap()
+    );
+
+    let mut before = IdAllocator::new(IdStrategy::Squuid, 0, 1);
+    let allocated = before.allocate(10).unwrap();
+
+    let mut after = IdAllocator::new(IdStrategy::Squuid, 0, 1);
+    after.observe(allocated[9]);
+    assert!(after.allocate(1).unwrap()[0] > allocated[9]);
+
+    // Client-supplied ids don't affect allocation.
+    let mut fresh = IdAllocator::new(IdStrategy::Partitioned, 0, 1);
+    fresh.observe(100);
+    assert_eq!(fresh.allocate(1).unwrap()[0], 1 << 63);
+}
@@ -7,10 +7,13 @@ use std::time::Duration;
 use timely::dataflow::channels::pact::Pipeline;
 use timely::dataflow::operators::Operator;
 
+use declarative_dataflow::plan::{Debounce, Delay, Join, Sample, Transitions};
 use declarative_dataflow::server::Server;
 use declarative_dataflow::timestamp::pair::Pair;
 use declarative_dataflow::timestamp::Time;
-use declarative_dataflow::{Aid, AttributeConfig, Datom, InputSemantics, Plan, Rule, Value};
+use declarative_dataflow::{
+    Aid, AttributeConfig, Datom, InputSemantics, Plan, Rule, TimeToLive, Value,
+};
 use Time::TxId;
 use Value::{Eid, Number};
 
@@ -295,6 +298,225 @@ fn last_write_wins_unordered() {
     .run();
 }
 
+#[test]
+fn transitions() {
+    vec![Case {
+        description: "transitions of last-write-wins values",
+        plan: Plan::Transitions(Transitions {
+            plan: Box::new(Plan::match_a(0, ":status", 1)),
+            key_variables: vec![0],
+            value_variable: 1,
+            previous_variable: 2,
+            time_variable: 3,
+        }),
+        transactions: vec![
+            vec![
+                Datom::add(100, ":status", Number(0)),
+                Datom::add(200, ":status", Number(0)),
+            ],
+            vec![Datom::add(100, ":status", Number(1))],
+            vec![
+                Datom::add(100, ":status", Number(2)),
+                Datom::add(200, ":status", Number(1)),
+            ],
+            vec![Datom::add(100, ":status", Number(1))],
+        ],
+        expectations: vec![
+            vec![],
+            vec![(vec![Eid(100), Number(0), Number(1), Number(1)], 1, 1)],
+            vec![
+                (vec![Eid(100), Number(1), Number(2), Number(2)], 2, 1),
+                (vec![Eid(200), Number(0), Number(1), Number(2)], 2, 1),
+            ],
+            vec![(vec![Eid(100), Number(2), Number(1), Number(3)], 3, 1)],
+        ],
+    }]
+    .run();
+}
+
+#[test]
+fn debounce() {
+    vec![Case {
+        description: "debouncing flapping last-write-wins values",
+        plan: Plan::Debounce(Debounce {
+            plan: Box::new(Plan::match_a(0, ":status", 1)),
+            period: TxId(2),
+        }),
+        transactions: vec![
+            vec![
+                Datom::add(100, ":status", Number(1)),
+                Datom::add(200, ":status", Number(5)),
+            ],
+            vec![Datom::add(100, ":status", Number(2))],
+            vec![],
+            vec![],
+            vec![Datom::add(200, ":status", Number(6))],
+        ],
+        expectations: vec![
+            vec![],
+            vec![],
+            vec![(vec![Eid(200), Number(5)], 2, 1)],
+            vec![(vec![Eid(100), Number(2)], 3, 1)],
+            vec![(vec![Eid(200), Number(5)], 4, -1)],
+        ],
+    }]
+    .run();
+}
+
+#[test]
+fn delay() {
+    vec![
+        Case {
+            description: "delaying last-write-wins values",
+            plan: Plan::Delay(Delay {
+                plan: Box::new(Plan::match_a(0, ":status", 1)),
+                offset: TxId(2),
+            }),
+            transactions: vec![
+                vec![Datom::add(100, ":status", Number(1))],
+                vec![Datom::add(100, ":status", Number(2))],
+                vec![],
+                vec![],
+            ],
+            expectations: vec![
+                vec![],
+                vec![],
+                vec![(vec![Eid(100), Number(1)], 2, 1)],
+                vec![
+                    (vec![Eid(100), Number(1)], 3, -1),
+                    (vec![Eid(100), Number(2)], 3, 1),
+                ],
+            ],
+        },
+        Case {
+            description: "relating values to the ones two transactions earlier",
+            plan: Plan::Join(Join {
+                variables: vec![0],
+                left_plan: Box::new(Plan::match_a(0, ":status", 1)),
+                right_plan: Box::new(Plan::Delay(Delay {
+                    plan: Box::new(Plan::match_a(0, ":status", 2)),
+                    offset: TxId(2),
+                })),
+            }),
+            transactions: vec![
+                vec![Datom::add(100, ":status", Number(1))],
+                vec![Datom::add(100, ":status", Number(2))],
+                vec![Datom::add(100, ":status", Number(3))],
+            ],
+            expectations: vec![
+                vec![],
+                vec![],
+                vec![(vec![Eid(100), Number(3), Number(1)], 2, 1)],
+            ],
+        },
+    ]
+    .run();
+}
+
+#[test]
+fn sample() {
+    vec![Case {
+        description: "sampling all buckets keeps every tuple",
+        plan: Plan::Sample(Sample {
+            plan: Box::new(Plan::match_a(0, ":status", 1)),
+            variables: vec![0],
+            numerator: 4,
+            denominator: 4,
+        }),
+        transactions: vec![
+            vec![
+                Datom::add(100, ":status", Number(1)),
+                Datom::add(200, ":status", Number(1)),
+            ],
+            vec![Datom::add(100, ":status", Number(2))],
+        ],
+        expectations: vec![
+            vec![
+                (vec![Eid(100), Number(1)], 0, 1),
+                (vec![Eid(200), Number(1)], 0, 1),
+            ],
+            vec![
+                (vec![Eid(100), Number(1)], 1, -1),
+                (vec![Eid(100), Number(2)], 1, 1),
+            ],
+        ],
+    }]
+    .run();
+}
+
+#[test]
+fn append_only() {
+    timely::execute_directly(|worker| {
+        let mut server = Server::<Aid, u64, u64>::new(Default::default());
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            let config = AttributeConfig {
+                append_only: true,
+                ..AttributeConfig::tx_time(InputSemantics::Raw)
+            };
+
+            server.create_attribute(scope, ":event", config).unwrap();
+
+            let conflicting = AttributeConfig {
+                append_only: true,
+                ..AttributeConfig::tx_time(InputSemantics::LastWriteWins)
+            };
+
+            assert!(server
+                .create_attribute(scope, ":status", conflicting)
+                .is_err());
+        });
+
+        server
+            .transact(vec![Datom::add(100, ":event", Number(1))], 0, 0)
+            .unwrap();
+        server.advance_domain(None, 1).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        assert!(server
+            .transact(vec![Datom::retract(100, ":event", Number(1))], 0, 0)
+            .is_err());
+
+        server
+            .transact(vec![Datom::add(100, ":event", Number(2))], 0, 0)
+            .unwrap();
+    });
+}
+
+#[test]
+fn monotonic() {
+    timely::execute_directly(|worker| {
+        let mut server = Server::<Aid, u64, u64>::new(Default::default());
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            let config = AttributeConfig {
+                monotonic: true,
+                ..AttributeConfig::tx_time(InputSemantics::Raw)
+            };
+
+            server.create_attribute(scope, ":click", config).unwrap();
+
+            let expiring = AttributeConfig {
+                monotonic: true,
+                time_to_live: Some(TimeToLive::TxTime(TxId(10))),
+                ..AttributeConfig::tx_time(InputSemantics::Raw)
+            };
+
+            assert!(server.create_attribute(scope, ":view", expiring).is_err());
+        });
+
+        server
+            .transact(vec![Datom::add(100, ":click", Number(1))], 0, 0)
+            .unwrap();
+        server.advance_domain(None, 1).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        assert!(server
+            .transact(vec![Datom::retract(100, ":click", Number(1))], 0, 0)
+            .is_err());
+    });
+}
+
 // #[test]
 // fn compare_and_swap() {
 //     use differential_dataflow::input::Input;
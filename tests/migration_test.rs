@@ -0,0 +1,281 @@
+use std::collections::HashMap;
+use std::sync::mpsc::channel;
+
+use declarative_dataflow::plan::Function;
+use declarative_dataflow::server::{
+    AliasAttribute, CopyAttribute, MigrateAttribute, Register, Server,
+};
+use declarative_dataflow::{Aid, AttributeConfig, Datom, InputSemantics, Plan, Rule, Value};
+use Value::{Number, String};
+
+#[test]
+fn alias_and_migrate() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, ()>::new(Default::default());
+        let (send_results, results) = channel();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .create_attribute(
+                    scope,
+                    ":name",
+                    AttributeConfig::tx_time(InputSemantics::Raw),
+                )
+                .unwrap();
+        });
+
+        server
+            .alias_attribute(AliasAttribute {
+                alias: ":nombre".to_string(),
+                name: ":name".to_string(),
+            })
+            .unwrap();
+
+        assert!(server
+            .alias_attribute(AliasAttribute {
+                alias: ":nombre".to_string(),
+                name: ":name".to_string(),
+            })
+            .is_err());
+
+        server
+            .transact(
+                vec![Datom::add(1, ":nombre", String("Dipper".to_string()))],
+                0,
+                0,
+            )
+            .unwrap();
+        server.advance_domain(None, 1).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        worker
+            .dataflow::<u64, _, _>(|scope| {
+                server.migrate_attribute(
+                    scope,
+                    MigrateAttribute {
+                        from: ":name".to_string(),
+                        to: ":person/name".to_string(),
+                    },
+                )
+            })
+            .unwrap();
+
+        // Both old names now refer to the new attribute.
+        server
+            .transact(
+                vec![
+                    Datom::add(2, ":name", String("Mabel".to_string())),
+                    Datom::add(3, ":nombre", String("Soos".to_string())),
+                ],
+                0,
+                0,
+            )
+            .unwrap();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .register(Register {
+                    rules: vec![Rule::named("names", Plan::match_a(0, ":name", 1))],
+                    publish: vec!["names".to_string()],
+                })
+                .unwrap();
+
+            server
+                .interest("names".to_string(), scope)
+                .unwrap()
+                .inspect(move |x| send_results.send((x.0.clone(), x.2)).unwrap())
+                .probe_with(&mut server.probe);
+        });
+
+        server.advance_domain(None, 2).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        let mut names = HashMap::new();
+        while let Ok((tuple, diff)) = results.try_recv() {
+            *names.entry(tuple[1].clone()).or_insert(0) += diff;
+        }
+        names.retain(|_name, count| *count != 0);
+
+        assert_eq!(names.len(), 3);
+        assert!(names.contains_key(&String("Dipper".to_string())));
+        assert!(names.contains_key(&String("Mabel".to_string())));
+        assert!(names.contains_key(&String("Soos".to_string())));
+    });
+}
+
+#[test]
+fn copy_attribute() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, ()>::new(Default::default());
+        let (send_results, results) = channel();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .create_attribute(
+                    scope,
+                    ":duration/secs",
+                    AttributeConfig::tx_time(InputSemantics::Raw),
+                )
+                .unwrap();
+        });
+
+        server
+            .transact(
+                vec![
+                    Datom::add(1, ":duration/secs", Number(3)),
+                    Datom::add(2, ":duration/secs", String("unknown".to_string())),
+                ],
+                0,
+                0,
+            )
+            .unwrap();
+        server.advance_domain(None, 1).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        // Copies include data that hasn't been indexed yet.
+        server
+            .transact(vec![Datom::add(3, ":duration/secs", Number(4))], 0, 0)
+            .unwrap();
+
+        let copy = |function| CopyAttribute {
+            from: ":duration/secs".to_string(),
+            to: ":duration/millis".to_string(),
+            config: None,
+            function: Some(function),
+            constants: vec![None, Some(Number(1000))],
+        };
+
+        // Constants are checked before anything is copied.
+        assert!(worker
+            .dataflow::<u64, _, _>(|scope| server.copy_attribute(scope, copy(Function::TRUNCATE)))
+            .is_err());
+
+        worker
+            .dataflow::<u64, _, _>(|scope| server.copy_attribute(scope, copy(Function::MULTIPLY)))
+            .unwrap();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .register(Register {
+                    rules: vec![Rule::named(
+                        "millis",
+                        Plan::match_a(0, ":duration/millis", 1),
+                    )],
+                    publish: vec!["millis".to_string()],
+                })
+                .unwrap();
+
+            server
+                .interest("millis".to_string(), scope)
+                .unwrap()
+                .inspect(move |x| send_results.send(x.clone()).unwrap())
+                .probe_with(&mut server.probe);
+        });
+
+        server.advance_domain(None, 2).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        // The copy is picked up like transactions fired by triggers.
+        assert!(results.try_iter().next().is_none());
+
+        let fired = server.fired_transactions();
+        assert_eq!(fired.len(), 1);
+
+        for tx_data in fired.into_iter() {
+            server.transact(tx_data, 0, 0).unwrap();
+        }
+
+        server.advance_domain(None, 3).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        // Values the function can't be applied to are left out.
+        let mut copied = results.try_iter().collect::<Vec<_>>();
+        copied.sort();
+
+        assert_eq!(
+            copied,
+            vec![
+                (vec![Value::Eid(1), Number(3000)], 2, 1),
+                (vec![Value::Eid(3), Number(4000)], 2, 1),
+            ]
+        );
+    });
+}
+
+#[test]
+fn migrate_unindexed() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, ()>::new(Default::default());
+        let (send_results, results) = channel();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .create_attribute(
+                    scope,
+                    ":name",
+                    AttributeConfig::tx_time(InputSemantics::Raw),
+                )
+                .unwrap();
+        });
+
+        server
+            .transact(
+                vec![Datom::add(1, ":name", String("Dipper".to_string()))],
+                0,
+                0,
+            )
+            .unwrap();
+        server.advance_domain(None, 1).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        // Neither sealed nor indexed yet.
+        server
+            .transact(
+                vec![Datom::add(2, ":name", String("Mabel".to_string()))],
+                0,
+                0,
+            )
+            .unwrap();
+
+        worker
+            .dataflow::<u64, _, _>(|scope| {
+                server.migrate_attribute(
+                    scope,
+                    MigrateAttribute {
+                        from: ":name".to_string(),
+                        to: ":person/name".to_string(),
+                    },
+                )
+            })
+            .unwrap();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .register(Register {
+                    rules: vec![Rule::named("names", Plan::match_a(0, ":person/name", 1))],
+                    publish: vec!["names".to_string()],
+                })
+                .unwrap();
+
+            server
+                .interest("names".to_string(), scope)
+                .unwrap()
+                .inspect(move |x| send_results.send(x.clone()).unwrap())
+                .probe_with(&mut server.probe);
+        });
+
+        server.advance_domain(None, 2).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        let mut names = results
+            .try_iter()
+            .map(|x| x.0[1].clone())
+            .collect::<Vec<_>>();
+        names.sort();
+
+        assert_eq!(
+            names,
+            vec![String("Dipper".to_string()), String("Mabel".to_string())]
+        );
+    });
+}
@@ -0,0 +1,289 @@
+use std::rc::Weak;
+use std::sync::mpsc::channel;
+use std::time::Duration;
+
+use timely::dataflow::channels::pact::Pipeline;
+use timely::dataflow::operators::{Input, Operator, Probe};
+
+use differential_dataflow::AsCollection;
+
+use declarative_dataflow::operators::{Coalesce, CoalescePolicy, CountBy, Expire, Snapshot};
+use declarative_dataflow::timestamp::Time;
+use declarative_dataflow::{ResultDiff, TimeToLive, Value};
+use Value::Number;
+
+#[test]
+fn snapshot() {
+    timely::execute_directly(move |worker| {
+        let (send_results, results) = channel();
+
+        let (mut input, probe) = worker.dataflow::<u64, _, _>(|scope| {
+            let (input, stream) = scope.new_input::<ResultDiff<u64>>();
+
+            let snapshot = stream.snapshot(2);
+            let probe = snapshot.probe();
+
+            snapshot.sink(Pipeline, "Results", move |input| {
+                input.for_each(|_time, data| {
+                    for datum in data.iter() {
+                        send_results.send(datum.clone()).unwrap()
+                    }
+                });
+            });
+
+            (input, probe)
+        });
+
+        input.send((vec![Number(1)], 0, 1));
+        input.send((vec![Number(2)], 0, 1));
+        input.advance_to(1);
+        input.send((vec![Number(1)], 1, -1));
+        input.send((vec![Number(3)], 1, 1));
+
+        for _i in 0..10 {
+            worker.step();
+        }
+
+        // Nothing is released before the snapshot is complete.
+        assert!(results.try_recv().is_err());
+
+        input.advance_to(2);
+        input.send((vec![Number(4)], 2, 1));
+        input.advance_to(3);
+        worker.step_while(|| probe.less_than(&3));
+
+        assert_eq!(
+            results.try_iter().collect::<Vec<_>>(),
+            vec![
+                (vec![Number(2)], 2, 1),
+                (vec![Number(3)], 2, 1),
+                (vec![Number(4)], 2, 1),
+            ]
+        );
+    });
+}
+
+#[test]
+fn coalesce() {
+    timely::execute_directly(move |worker| {
+        let (send_results, results) = channel();
+
+        let (mut input, probe) = worker.dataflow::<u64, _, _>(|scope| {
+            let (input, stream) = scope.new_input::<ResultDiff<u64>>();
+
+            let coalesced = stream.coalesce(&CoalescePolicy::Frontier, Weak::new());
+            let probe = coalesced.probe();
+
+            coalesced.sink(Pipeline, "Results", move |input| {
+                input.for_each(|_time, data| {
+                    for datum in data.iter() {
+                        send_results.send(datum.clone()).unwrap()
+                    }
+                });
+            });
+
+            (input, probe)
+        });
+
+        input.send((vec![Number(1)], 0, 1));
+        input.advance_to(1);
+        input.send((vec![Number(1)], 1, -1));
+        input.send((vec![Number(2)], 1, 1));
+        input.advance_to(2);
+        input.send((vec![Number(3)], 2, 1));
+        input.advance_to(3);
+
+        worker.step_while(|| probe.less_than(input.time()));
+
+        assert_eq!(
+            results.try_iter().collect::<Vec<_>>(),
+            vec![(vec![Number(2)], 2, 1), (vec![Number(3)], 2, 1)]
+        );
+    });
+}
+
+#[test]
+fn coalesce_incomplete() {
+    timely::execute_directly(move |worker| {
+        let (send_results, results) = channel();
+
+        let (mut input, probe) = worker.dataflow::<u64, _, _>(|scope| {
+            let (input, stream) = scope.new_input::<ResultDiff<u64>>();
+
+            let coalesced = stream.coalesce(&CoalescePolicy::Frontier, Weak::new());
+            let probe = coalesced.probe();
+
+            coalesced.sink(Pipeline, "Results", move |input| {
+                input.for_each(|_time, data| {
+                    for datum in data.iter() {
+                        send_results.send(datum.clone()).unwrap()
+                    }
+                });
+            });
+
+            (input, probe)
+        });
+
+        // Diffs at times the frontier hasn't passed yet are held back
+        // for a later release.
+        input.send((vec![Number(1)], 0, 1));
+        input.send((vec![Number(2)], 0, 1));
+        input.send((vec![Number(2)], 0, -1));
+        input.send((vec![Number(3)], 1, 1));
+        input.advance_to(1);
+        worker.step_while(|| probe.less_than(input.time()));
+
+        assert_eq!(
+            results.try_iter().collect::<Vec<_>>(),
+            vec![(vec![Number(1)], 0, 1)]
+        );
+
+        input.advance_to(2);
+        worker.step_while(|| probe.less_than(input.time()));
+
+        assert_eq!(
+            results.try_iter().collect::<Vec<_>>(),
+            vec![(vec![Number(3)], 1, 1)]
+        );
+    });
+}
+
+#[test]
+fn coalesce_window() {
+    timely::execute_directly(move |worker| {
+        let (send_results, results) = channel();
+
+        let (mut input, probe) = worker.dataflow::<u64, _, _>(|scope| {
+            let (input, stream) = scope.new_input::<ResultDiff<u64>>();
+
+            let window = CoalescePolicy::Window(Duration::from_secs(3600));
+            let coalesced = stream.coalesce(&window, Weak::new());
+            let probe = coalesced.probe();
+
+            coalesced.sink(Pipeline, "Results", move |input| {
+                input.for_each(|_time, data| {
+                    for datum in data.iter() {
+                        send_results.send(datum.clone()).unwrap()
+                    }
+                });
+            });
+
+            (input, probe)
+        });
+
+        input.send((vec![Number(1)], 0, 1));
+        input.advance_to(1);
+        worker.step_while(|| probe.less_than(input.time()));
+
+        // The first release happens right away.
+        assert_eq!(
+            results.try_iter().collect::<Vec<_>>(),
+            vec![(vec![Number(1)], 0, 1)]
+        );
+
+        input.send((vec![Number(1)], 1, -1));
+        input.send((vec![Number(2)], 1, 1));
+        input.advance_to(2);
+
+        for _i in 0..10 {
+            worker.step();
+        }
+
+        // Everything else is held back until the window has passed.
+        assert!(results.try_recv().is_err());
+    });
+}
+
+#[test]
+fn count_by() {
+    timely::execute_directly(move |worker| {
+        let (send_results, results) = channel();
+
+        let (mut input, probe) = worker.dataflow::<u64, _, _>(|scope| {
+            let (input, stream) = scope.new_input::<ResultDiff<u64>>();
+
+            let counts = stream.count_by(&[0]);
+            let probe = counts.probe();
+
+            counts.sink(Pipeline, "Results", move |input| {
+                input.for_each(|_time, data| {
+                    for datum in data.iter() {
+                        send_results.send(datum.clone()).unwrap()
+                    }
+                });
+            });
+
+            (input, probe)
+        });
+
+        input.send((vec![Number(1), Number(10)], 0, 1));
+        input.send((vec![Number(1), Number(11)], 0, 1));
+        input.send((vec![Number(2), Number(12)], 0, 1));
+        input.advance_to(1);
+        input.send((vec![Number(1), Number(10)], 1, -1));
+        input.advance_to(2);
+        worker.step_while(|| probe.less_than(&2));
+
+        let mut outputs = results.try_iter().collect::<Vec<_>>();
+        outputs.sort();
+
+        assert_eq!(
+            outputs,
+            vec![
+                (vec![Number(1), Number(1)], 1, 1),
+                (vec![Number(1), Number(2)], 0, 1),
+                (vec![Number(1), Number(2)], 1, -1),
+                (vec![Number(2), Number(1)], 0, 1),
+            ]
+        );
+    });
+}
+
+#[test]
+fn expire() {
+    timely::execute_directly(move |worker| {
+        let (send_results, results) = channel();
+
+        let (mut input, probe) = worker.dataflow::<u64, _, _>(|scope| {
+            let (input, stream) = scope.new_input::<((Value, Value), u64, isize)>();
+
+            let remaining = stream
+                .as_collection()
+                .expire(&TimeToLive::TxTime(Time::TxId(2)), Duration::from_secs(0))
+                .inner;
+            let probe = remaining.probe();
+
+            remaining.sink(Pipeline, "Results", move |input| {
+                input.for_each(|_time, data| {
+                    for datum in data.iter() {
+                        send_results.send(datum.clone()).unwrap()
+                    }
+                });
+            });
+
+            (input, probe)
+        });
+
+        input.send(((Number(1), Number(10)), 0, 1));
+        input.advance_to(1);
+        input.send(((Number(2), Number(20)), 1, 1));
+        input.advance_to(2);
+        // Retracted before it would have expired.
+        input.send(((Number(2), Number(20)), 2, -1));
+        input.advance_to(5);
+        worker.step_while(|| probe.less_than(&5));
+
+        let mut outputs = results.try_iter().collect::<Vec<_>>();
+        outputs.sort();
+
+        assert_eq!(
+            outputs,
+            vec![
+                ((Number(1), Number(10)), 0, 1),
+                ((Number(1), Number(10)), 2, -1),
+                ((Number(2), Number(20)), 1, 1),
+                ((Number(2), Number(20)), 2, -1),
+            ]
+        );
+    });
+}
@@ -7,7 +7,7 @@ use timely::dataflow::channels::pact::Pipeline;
 use timely::dataflow::operators::Operator;
 
 use declarative_dataflow::binding::Binding;
-use declarative_dataflow::plan::{Hector, Implementable, Union};
+use declarative_dataflow::plan::{Coalesce, Hector, Implementable, Union};
 use declarative_dataflow::server::Server;
 use declarative_dataflow::timestamp::Time;
 use declarative_dataflow::{Aid, Datom, Plan, Rule, Value};
@@ -24,7 +24,7 @@ struct Case {
 fn dependencies(case: &Case) -> HashSet<Aid> {
     let mut deps = HashSet::new();
 
-    for binding in case.plan.into_bindings().iter() {
+    for binding in case.plan.into_bindings().unwrap().iter() {
         if let Binding::Attribute(binding) = binding {
             deps.insert(binding.source_attribute.clone());
         }
@@ -141,6 +141,7 @@ fn or() {
                             Binding::attribute(0, ":name", 1),
                             Binding::constant(1, String("Oleg".to_string())),
                         ],
+                        order: Default::default(),
                     }),
                     Plan::Hector(Hector {
                         variables: vec![0],
@@ -148,8 +149,10 @@ fn or() {
                             Binding::attribute(0, ":age", 1),
                             Binding::constant(1, Number(10)),
                         ],
+                        order: Default::default(),
                     }),
                 ],
+                mappings: vec![],
             }),
             transactions: vec![data.clone()],
             expectations: vec![vec![
@@ -170,6 +173,7 @@ fn or() {
                             Binding::attribute(0, ":name", 1),
                             Binding::constant(1, String("Oleg".to_string())),
                         ],
+                        order: Default::default(),
                     }),
                     Plan::Hector(Hector {
                         variables: vec![0],
@@ -177,8 +181,10 @@ fn or() {
                             Binding::attribute(0, ":age", 1),
                             Binding::constant(1, Number(30)),
                         ],
+                        order: Default::default(),
                     }),
                 ],
+                mappings: vec![],
             }),
             transactions: vec![data.clone()],
             expectations: vec![vec![(vec![Eid(3)], 0, 1), (vec![Eid(4)], 0, 1)]],
@@ -194,6 +200,7 @@ fn or() {
                             Binding::attribute(0, ":name", 1),
                             Binding::constant(1, String("Petr".to_string())),
                         ],
+                        order: Default::default(),
                     }),
                     Plan::Hector(Hector {
                         variables: vec![0],
@@ -201,8 +208,10 @@ fn or() {
                             Binding::attribute(0, ":age", 1),
                             Binding::constant(1, Number(30)),
                         ],
+                        order: Default::default(),
                     }),
                 ],
+                mappings: vec![],
             }),
             transactions: vec![data.clone()],
             expectations: vec![vec![]],
@@ -258,6 +267,7 @@ fn or() {
                             Binding::attribute(3, ":age", 2),
                             Binding::constant(3, Eid(1)),
                         ],
+                        order: Default::default(),
                     }),
                     Plan::Hector(Hector {
                         variables: vec![0],
@@ -268,8 +278,10 @@ fn or() {
                             Binding::attribute(3, ":age", 2),
                             Binding::constant(3, Eid(2)),
                         ],
+                        order: Default::default(),
                     }),
                 ],
+                mappings: vec![],
             }),
             transactions: vec![data.clone()],
             expectations: vec![vec![
@@ -310,6 +322,7 @@ fn or_join() {
                 Plan::Hector(Hector {
                     variables: vec![0],
                     bindings: vec![Binding::attribute(0, ":name", 2)],
+                    order: Default::default(),
                 }),
                 Plan::Hector(Hector {
                     variables: vec![0],
@@ -317,8 +330,10 @@ fn or_join() {
                         Binding::attribute(0, ":age", 1),
                         Binding::attribute(0, ":name", 2),
                     ],
+                    order: Default::default(),
                 }),
             ],
+            mappings: vec![],
         }),
         transactions: vec![data.clone()],
         expectations: vec![vec![
@@ -331,3 +346,77 @@ fn or_join() {
         ]],
     }]);
 }
+
+#[test]
+fn union_mappings() {
+    let data = vec![
+        Datom::add(1, ":name", String("Ivan".to_string())),
+        Datom::add(1, ":age", Number(10)),
+        Datom::add(2, ":name", String("Ivan".to_string())),
+        Datom::add(2, ":age", Number(20)),
+        Datom::add(3, ":name", String("Oleg".to_string())),
+        Datom::add(3, ":age", Number(10)),
+        Datom::add(4, ":name", String("Oleg".to_string())),
+        Datom::add(4, ":age", Number(20)),
+        Datom::add(5, ":name", String("Petr".to_string())),
+        Datom::add(5, ":age", Number(30)),
+    ];
+
+    run_cases(vec![Case {
+        description: "union over inputs binding different variables",
+        plan: Plan::Union(Union {
+            variables: vec![0],
+            plans: vec![
+                Plan::match_av(1, ":age", Number(10)),
+                Plan::match_av(2, ":name", String("Oleg".to_string())),
+                Plan::match_av(3, ":age", Number(30)),
+            ],
+            mappings: vec![vec![1], vec![2], vec![3]],
+        }),
+        transactions: vec![data],
+        expectations: vec![vec![
+            (vec![Eid(1)], 0, 1),
+            (vec![Eid(3)], 0, 1),
+            (vec![Eid(4)], 0, 1),
+            (vec![Eid(5)], 0, 1),
+        ]],
+    }]);
+}
+
+#[test]
+fn coalesce() {
+    run_cases(vec![Case {
+        description: "display names, falling back to user names",
+        plan: Plan::Coalesce(Coalesce {
+            key_variables: vec![0],
+            value_variable: 1,
+            plans: vec![
+                Plan::match_a(0, ":display-name", 1),
+                Plan::match_a(0, ":username", 1),
+            ],
+        }),
+        transactions: vec![
+            vec![
+                Datom::add(1, ":username", String("ivan".to_string())),
+                Datom::add(2, ":username", String("oleg".to_string())),
+                Datom::add(2, ":display-name", String("Oleg P.".to_string())),
+            ],
+            vec![
+                Datom::add(1, ":display-name", String("Ivan K.".to_string())),
+                Datom::retract(2, ":display-name", String("Oleg P.".to_string())),
+            ],
+        ],
+        expectations: vec![
+            vec![
+                (vec![Eid(1), String("ivan".to_string())], 0, 1),
+                (vec![Eid(2), String("Oleg P.".to_string())], 0, 1),
+            ],
+            vec![
+                (vec![Eid(1), String("ivan".to_string())], 1, -1),
+                (vec![Eid(1), String("Ivan K.".to_string())], 1, 1),
+                (vec![Eid(2), String("Oleg P.".to_string())], 1, -1),
+                (vec![Eid(2), String("oleg".to_string())], 1, 1),
+            ],
+        ],
+    }]);
+}
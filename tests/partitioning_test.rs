@@ -0,0 +1,83 @@
+use std::sync::mpsc::channel;
+
+use declarative_dataflow::server::{Configuration, Register, Server};
+use declarative_dataflow::sources::{worker_for, Partitioning};
+use declarative_dataflow::{Aid, AttributeConfig, Datom, InputSemantics, Plan, Rule, Value};
+use Value::{Eid, Number};
+
+#[test]
+fn entity_partitioned_transactions() {
+    timely::execute_directly(move |worker| {
+        let config = Configuration {
+            tx_partitioning: Partitioning::ByEntity,
+            ..Default::default()
+        };
+
+        // Pretend to be the first of two workers.
+        let mut server = Server::<Aid, u64, ()>::new(config);
+        server.peers = 2;
+
+        let (send_results, results) = channel();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .create_attribute(scope, ":num", AttributeConfig::tx_time(InputSemantics::Raw))
+                .unwrap();
+
+            server
+                .register(Register {
+                    rules: vec![Rule::named("nums", Plan::match_a(0, ":num", 1))],
+                    publish: vec!["nums".to_string()],
+                })
+                .unwrap();
+
+            server
+                .interest("nums".to_string(), scope)
+                .unwrap()
+                .inspect(move |x| send_results.send(x.0.clone()).unwrap())
+                .probe_with(&mut server.probe);
+        });
+
+        let tx_data = (0..20)
+            .map(|e| Datom::add(e, ":num", Number(e as i64)))
+            .collect();
+
+        // Workers introduce their share, owners or not.
+        server.transact(tx_data, 1, 0).unwrap();
+        server.advance_domain(None, 1).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        let mut expected = (0..20)
+            .filter(|e| worker_for(&Eid(*e), 2) == 0)
+            .map(|e| vec![Eid(e), Number(e as i64)])
+            .collect::<Vec<_>>();
+        let mut actual = results.try_iter().collect::<Vec<_>>();
+
+        expected.sort();
+        actual.sort();
+
+        assert!(!expected.is_empty() && expected.len() < 20);
+        assert_eq!(actual, expected);
+
+        // Lookup refs resolve against local indices only.
+        let lookup = Value::lookup_ref(":num", Number(1));
+        assert!(server
+            .transact(
+                vec![Datom(lookup, ":num".to_string(), Number(2), None, 1)],
+                1,
+                0
+            )
+            .is_err());
+    });
+}
+
+#[test]
+fn record_partitioning() {
+    let owned = (0..10)
+        .filter(|position| Partitioning::ByRecord.is_responsible(0, *position, &Eid(0), 1, 3))
+        .collect::<Vec<_>>();
+
+    assert_eq!(owned, vec![1, 4, 7]);
+    assert!(Partitioning::Single.is_responsible(2, 0, &Eid(0), 2, 3));
+    assert!(!Partitioning::Single.is_responsible(2, 0, &Eid(0), 0, 3));
+}
@@ -0,0 +1,95 @@
+use declarative_dataflow::frontends::datalog;
+use declarative_dataflow::plan::{AggregationFn, Function, PlanBuilder, Predicate};
+use declarative_dataflow::{Aid, Plan, Value};
+
+#[test]
+fn matches_datalog() {
+    let expected: Plan<Aid> = datalog::parse(
+        "[:find ?n :where [?e :name ?n] [?e :age ?age] [(>= ?age 18)] (not [?e :guardian _])]",
+    )
+    .unwrap();
+
+    let plan: Plan<Aid> = PlanBuilder::match_a(1, ":name", 2)
+        .join(PlanBuilder::match_a(1, ":age", 3))
+        .filter_value(Predicate::GTE, 3, Value::Number(18))
+        .antijoin(PlanBuilder::match_a(1, ":guardian", 4), vec![1])
+        .project(vec![2])
+        .build()
+        .unwrap();
+
+    assert_eq!(plan, expected);
+}
+
+#[test]
+fn aggregate() {
+    let expected: Plan<Aid> = datalog::parse(
+        "[:find ?e (sum ?amount) :with ?tx :where [?e :amount ?amount] [?tx :item ?e]]",
+    )
+    .unwrap();
+
+    let plan: Plan<Aid> = PlanBuilder::match_a(1, ":amount", 2)
+        .join(PlanBuilder::match_a(3, ":item", 1))
+        .aggregate(vec![1], vec![(AggregationFn::SUM, 2)], vec![3])
+        .build()
+        .unwrap();
+
+    assert_eq!(plan, expected);
+}
+
+#[test]
+fn union() {
+    let builder: PlanBuilder<Aid> = PlanBuilder::match_a(1, ":name", 2)
+        .union(PlanBuilder::match_a(1, ":alias", 2))
+        .union(PlanBuilder::match_a(1, ":nickname", 2));
+
+    assert_eq!(builder.variables().unwrap(), &[1, 2]);
+
+    match builder.build().unwrap() {
+        Plan::Union(union) => assert_eq!(union.plans.len(), 3),
+        other => panic!("Unexpected plan {:?}.", other),
+    }
+}
+
+#[test]
+fn reject_unbound_variables() {
+    let invalid: Vec<PlanBuilder<Aid>> = vec![
+        PlanBuilder::match_a(1, ":name", 2).project(vec![3]),
+        PlanBuilder::match_a(1, ":name", 2).filter(Predicate::LT, 2, 3),
+        PlanBuilder::match_a(1, ":name", 2).join(PlanBuilder::match_a(3, ":age", 4)),
+        PlanBuilder::match_a(1, ":name", 2).antijoin(PlanBuilder::match_a(3, ":age", 4), vec![3]),
+        PlanBuilder::match_a(1, ":name", 2).union(PlanBuilder::match_a(1, ":age", 3)),
+        PlanBuilder::match_a(1, ":name", 2)
+            .project(vec![3])
+            .join(PlanBuilder::match_a(1, ":age", 4)),
+        PlanBuilder::match_a(1, ":age", 2).transform(
+            Function::ADD,
+            vec![3],
+            vec![None, Some(Value::Number(1))],
+            4,
+        ),
+        PlanBuilder::match_a(1, ":age", 2).transform(
+            Function::ADD,
+            vec![2],
+            vec![None, Some(Value::Number(1))],
+            1,
+        ),
+    ];
+
+    for builder in invalid.into_iter() {
+        assert!(builder.build().is_err());
+    }
+}
+
+#[test]
+fn from_plan() {
+    let plan: Plan<Aid> =
+        datalog::parse("[:find ?e ?n :where [?e :name ?n] [?e :age ?a]]").unwrap();
+
+    let builder = match plan {
+        Plan::Project(project) => PlanBuilder::from(*project.plan),
+        other => panic!("Unexpected plan {:?}.", other),
+    };
+
+    assert_eq!(builder.variables().unwrap(), &[1, 2, 3]);
+    assert!(builder.project(vec![3, 2]).build().is_ok());
+}
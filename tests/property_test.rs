@@ -0,0 +1,424 @@
+//! Evaluates randomly generated plans over random sequences of
+//! transactions, and compares their incrementally maintained results
+//! against those of a naive interpreter, re-evaluating each plan from
+//! scratch after every transaction.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::mpsc::channel;
+
+use declarative_dataflow::plan::{Aggregate, AggregationFn, Antijoin, Filter, Join};
+use declarative_dataflow::plan::{Predicate, Project, Union};
+use declarative_dataflow::server::Server;
+use declarative_dataflow::testing::Rng;
+use declarative_dataflow::{Aid, AttributeConfig, Datom, IndexDirection, InputSemantics};
+use declarative_dataflow::{Plan, QuerySupport, Rule, Value};
+use Value::{Eid, Number};
+
+const ATTRIBUTES: [&str; 3] = [":a", ":b", ":c"];
+const VARIABLES: u32 = 4;
+const ENTITIES: u64 = 4;
+const CASES: u64 = 40;
+const ROUNDS: usize = 6;
+
+type Bag = HashMap<Vec<Value>, isize>;
+type Db = HashSet<(u64, Aid, u64)>;
+
+/// Results of the reference interpreter, along with the variables
+/// their fields correspond to.
+struct Relation {
+    variables: Vec<u32>,
+    tuples: Bag,
+}
+
+impl Relation {
+    fn offset(&self, variable: u32) -> usize {
+        self.variables
+            .iter()
+            .position(|x| *x == variable)
+            .expect("variable not bound")
+    }
+
+    fn project(&self, variables: &[u32]) -> Bag {
+        let offsets: Vec<usize> = variables.iter().map(|x| self.offset(*x)).collect();
+        let mut projected = Bag::new();
+
+        for (tuple, diff) in self.tuples.iter() {
+            let key = offsets.iter().map(|i| tuple[*i].clone()).collect();
+            *projected.entry(key).or_insert(0) += diff;
+        }
+
+        projected.retain(|_tuple, diff| *diff != 0);
+        projected
+    }
+}
+
+fn shared(left: &[u32], right: &[u32]) -> Vec<u32> {
+    let mut shared: Vec<u32> = left.iter().filter(|x| right.contains(x)).cloned().collect();
+    shared.sort();
+    shared
+}
+
+/// Generates a random plan, along with the variables it binds.
+fn generate(rng: &mut Rng, depth: usize) -> (Plan<Aid>, Vec<u32>) {
+    let choice = if depth == 0 { 0 } else { rng.below(6) };
+
+    match choice {
+        1 => {
+            let (left, left_vars) = generate(rng, depth - 1);
+            let (right, right_vars) = generate(rng, depth - 1);
+            let variables = shared(&left_vars, &right_vars);
+
+            // Attribute joins only support a single join variable.
+            if variables.len() != 1 {
+                return (left, left_vars);
+            }
+
+            let mut bound = left_vars.clone();
+            bound.extend(right_vars.iter().filter(|x| !left_vars.contains(x)));
+
+            let plan = Plan::Join(Join {
+                variables,
+                left_plan: Box::new(left),
+                right_plan: Box::new(right),
+            });
+
+            (plan, bound)
+        }
+        2 => {
+            let (left, left_vars) = generate(rng, depth - 1);
+            let (right, right_vars) = generate(rng, depth - 1);
+            let variables = shared(&left_vars, &right_vars);
+
+            if variables.is_empty() {
+                return (left, left_vars);
+            }
+
+            let plan = Plan::Antijoin(Antijoin {
+                variables,
+                left_plan: Box::new(left),
+                right_plan: Box::new(right),
+            });
+
+            (plan, left_vars)
+        }
+        3 => {
+            let (left, left_vars) = generate(rng, depth - 1);
+            let (right, right_vars) = generate(rng, depth - 1);
+            let variables = shared(&left_vars, &right_vars);
+
+            if variables.is_empty() {
+                return (left, left_vars);
+            }
+
+            let plan = Plan::Union(Union {
+                variables: variables.clone(),
+                plans: vec![left, right],
+                mappings: vec![],
+            });
+
+            (plan, variables)
+        }
+        4 => {
+            let (plan, variables) = generate(rng, depth - 1);
+            let variable = variables[rng.below(variables.len() as u64) as usize];
+            let predicate = match rng.below(4) {
+                0 => Predicate::LT,
+                1 => Predicate::GT,
+                2 => Predicate::EQ,
+                _ => Predicate::NEQ,
+            };
+
+            let plan = Plan::Filter(Filter {
+                variables: vec![variable],
+                predicate,
+                plan: Box::new(plan),
+                constants: vec![None, Some(Eid(rng.below(ENTITIES)))],
+            });
+
+            (plan, variables)
+        }
+        5 => {
+            let (plan, variables) = generate(rng, depth - 1);
+            let projected: Vec<u32> = variables
+                .iter()
+                .filter(|_| rng.below(3) != 0)
+                .cloned()
+                .collect();
+
+            if projected.is_empty() {
+                return (plan, variables);
+            }
+
+            let plan = Plan::Project(Project {
+                variables: projected.clone(),
+                plan: Box::new(plan),
+            });
+
+            (plan, projected)
+        }
+        _ => {
+            let e = rng.below(u64::from(VARIABLES)) as u32;
+            let v = (e + 1 + rng.below(u64::from(VARIABLES) - 1) as u32) % VARIABLES;
+            let a = ATTRIBUTES[rng.below(ATTRIBUTES.len() as u64) as usize];
+
+            (Plan::match_a(e, a, v), vec![e, v])
+        }
+    }
+}
+
+/// Wraps a generated plan s.t. its results have a known layout, at
+/// times counting its tuples per value of one of its variables.
+fn complete(rng: &mut Rng, plan: Plan<Aid>, variables: Vec<u32>) -> Plan<Aid> {
+    if variables.len() >= 2 && rng.below(4) == 0 {
+        let (key, counted) = (variables[0], variables[1]);
+
+        Plan::Aggregate(Aggregate {
+            variables: vec![key, counted],
+            plan: Box::new(Plan::Project(Project {
+                variables: vec![key, counted],
+                plan: Box::new(plan),
+            })),
+            aggregation_fns: vec![AggregationFn::COUNT],
+            key_variables: vec![key],
+            aggregation_variables: vec![counted],
+            with_variables: vec![],
+            key_universe: None,
+            defaults: vec![],
+        })
+    } else {
+        let mut sorted = variables;
+        sorted.sort();
+
+        Plan::Project(Project {
+            variables: sorted,
+            plan: Box::new(plan),
+        })
+    }
+}
+
+/// Naively evaluates the plan against the specified database.
+fn evaluate(plan: &Plan<Aid>, db: &Db) -> Relation {
+    match *plan {
+        Plan::MatchA(e, ref a, v) => {
+            let mut tuples = Bag::new();
+            for (de, da, dv) in db.iter() {
+                if da == a {
+                    *tuples.entry(vec![Eid(*de), Eid(*dv)]).or_insert(0) += 1;
+                }
+            }
+
+            Relation {
+                variables: vec![e, v],
+                tuples,
+            }
+        }
+        Plan::Join(ref join) => {
+            let left = evaluate(&join.left_plan, db);
+            let right = evaluate(&join.right_plan, db);
+
+            let mut variables = left.variables.clone();
+            let mut right_offsets = Vec::new();
+            for (offset, variable) in right.variables.iter().enumerate() {
+                if !left.variables.contains(variable) {
+                    variables.push(*variable);
+                    right_offsets.push(offset);
+                }
+            }
+
+            let keys: Vec<(usize, usize)> = join
+                .variables
+                .iter()
+                .map(|x| (left.offset(*x), right.offset(*x)))
+                .collect();
+
+            let mut tuples = Bag::new();
+            for (l, l_diff) in left.tuples.iter() {
+                for (r, r_diff) in right.tuples.iter() {
+                    if keys.iter().all(|(li, ri)| l[*li] == r[*ri]) {
+                        let mut tuple = l.clone();
+                        tuple.extend(right_offsets.iter().map(|i| r[*i].clone()));
+                        *tuples.entry(tuple).or_insert(0) += l_diff * r_diff;
+                    }
+                }
+            }
+
+            Relation { variables, tuples }
+        }
+        Plan::Antijoin(ref antijoin) => {
+            let left = evaluate(&antijoin.left_plan, db);
+            let right = evaluate(&antijoin.right_plan, db);
+
+            let offsets: Vec<usize> = antijoin.variables.iter().map(|x| left.offset(*x)).collect();
+            let matches = right.project(&antijoin.variables);
+
+            let tuples = left
+                .tuples
+                .iter()
+                .filter(|(tuple, diff)| {
+                    let key: Vec<Value> = offsets.iter().map(|i| tuple[*i].clone()).collect();
+                    **diff > 0 && !matches.get(&key).map(|x| *x > 0).unwrap_or(false)
+                })
+                .map(|(tuple, _diff)| (tuple.clone(), 1))
+                .collect();
+
+            Relation {
+                variables: left.variables,
+                tuples,
+            }
+        }
+        Plan::Union(ref union) => {
+            let mut tuples = Bag::new();
+            for plan in union.plans.iter() {
+                for (tuple, diff) in evaluate(plan, db).project(&union.variables) {
+                    if diff > 0 {
+                        tuples.insert(tuple, 1);
+                    }
+                }
+            }
+
+            Relation {
+                variables: union.variables.clone(),
+                tuples,
+            }
+        }
+        Plan::Filter(ref filter) => {
+            let relation = evaluate(&filter.plan, db);
+            let offset = relation.offset(filter.variables[0]);
+            let constant = filter.constants[1].clone().unwrap();
+
+            let tuples = relation
+                .tuples
+                .iter()
+                .filter(|(tuple, _diff)| {
+                    let value = &tuple[offset];
+                    match filter.predicate {
+                        Predicate::LT => *value < constant,
+                        Predicate::LTE => *value <= constant,
+                        Predicate::GT => *value > constant,
+                        Predicate::GTE => *value >= constant,
+                        Predicate::EQ => *value == constant,
+                        Predicate::NEQ => *value != constant,
+                    }
+                })
+                .map(|(tuple, diff)| (tuple.clone(), *diff))
+                .collect();
+
+            Relation {
+                variables: relation.variables,
+                tuples,
+            }
+        }
+        Plan::Project(ref project) => Relation {
+            variables: project.variables.clone(),
+            tuples: evaluate(&project.plan, db).project(&project.variables),
+        },
+        Plan::Aggregate(ref aggregate) => {
+            let relation = evaluate(&aggregate.plan, db);
+            let mut counts = HashMap::new();
+
+            for (tuple, diff) in relation.tuples.iter() {
+                *counts.entry(tuple[0].clone()).or_insert(0) += diff;
+            }
+
+            let tuples = counts
+                .into_iter()
+                .filter(|(_key, count)| *count > 0)
+                .map(|(key, count)| (vec![key, Number(count as i64)], 1))
+                .collect();
+
+            Relation {
+                variables: aggregate.variables.clone(),
+                tuples,
+            }
+        }
+        _ => unreachable!(),
+    }
+}
+
+fn run(seed: u64) {
+    let mut rng = Rng::seeded(seed);
+    let (plan, variables) = generate(&mut rng, 3);
+    let plan = complete(&mut rng, plan, variables);
+
+    let mut updates: Vec<Vec<Datom<Aid>>> = Vec::with_capacity(ROUNDS);
+    let mut expectations = Vec::with_capacity(ROUNDS);
+    let mut db = Db::new();
+
+    for _round in 0..ROUNDS {
+        let mut next = db.clone();
+        for _change in 0..rng.below(8) {
+            let e = rng.below(ENTITIES);
+            let a = ATTRIBUTES[rng.below(ATTRIBUTES.len() as u64) as usize].to_string();
+            let v = rng.below(ENTITIES);
+
+            if !next.remove(&(e, a.clone(), v)) {
+                next.insert((e, a, v));
+            }
+        }
+
+        let mut tx_data = Vec::new();
+        for (e, a, v) in next.difference(&db) {
+            tx_data.push(Datom::add(*e, a.clone(), Eid(*v)));
+        }
+        for (e, a, v) in db.difference(&next) {
+            tx_data.push(Datom::retract(*e, a.clone(), Eid(*v)));
+        }
+
+        let mut expected = evaluate(&plan, &next).tuples;
+        expected.retain(|_tuple, diff| *diff != 0);
+
+        updates.push(tx_data);
+        expectations.push(expected);
+        db = next;
+    }
+
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, u64>::new(Default::default());
+        let (send_results, results) = channel();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            for name in ATTRIBUTES.iter() {
+                let config = AttributeConfig {
+                    index_direction: IndexDirection::Both,
+                    query_support: QuerySupport::AdaptiveWCO,
+                    ..AttributeConfig::tx_time(InputSemantics::Raw)
+                };
+
+                server.create_attribute(scope, *name, config).unwrap();
+            }
+
+            server
+                .test_single(scope, Rule::named("property", plan.clone()))
+                .inspect(move |x| {
+                    send_results.send((x.0.clone(), x.2)).unwrap();
+                });
+        });
+
+        let mut actual = Bag::new();
+
+        for (round, (tx_data, expected)) in updates.into_iter().zip(expectations).enumerate() {
+            server.transact(tx_data, 0, 0).unwrap();
+            server.advance_domain(None, round as u64 + 1).unwrap();
+
+            worker.step_while(|| server.is_any_outdated());
+
+            for (tuple, diff) in results.try_iter() {
+                *actual.entry(tuple).or_insert(0) += diff;
+            }
+            actual.retain(|_tuple, diff| *diff != 0);
+
+            assert_eq!(
+                actual, expected,
+                "seed {}, round {}, plan {:?}",
+                seed, round, plan
+            );
+        }
+    });
+}
+
+#[test]
+fn random_plans() {
+    for seed in 0..CASES {
+        run(seed);
+    }
+}
@@ -6,8 +6,8 @@ use std::time::Duration;
 use timely::dataflow::channels::pact::Pipeline;
 use timely::dataflow::operators::Operator;
 
-use declarative_dataflow::plan::{Implementable, PullLevel};
-use declarative_dataflow::server::Server;
+use declarative_dataflow::plan::{Implementable, Join, Project, PullLevel, PullPattern};
+use declarative_dataflow::server::{Register, Server};
 use declarative_dataflow::timestamp::Time;
 use declarative_dataflow::{Aid, Datom, Plan, Rule, Value};
 use declarative_dataflow::{AttributeConfig, IndexDirection, QuerySupport};
@@ -113,6 +113,7 @@ fn pull_level() {
             pull_variable: 0,
             plan: Box::new(Plan::match_av(0, "admin?", Bool(false))),
             pull_attributes: vec!["name".to_string(), "age".to_string()],
+            pull_rules: vec![],
             path_attributes: vec![],
             cardinality_many: false,
         }),
@@ -142,6 +143,160 @@ fn pull_level() {
     }]);
 }
 
+#[test]
+fn pull_all() {
+    run_cases(vec![Case {
+        description: "[:find (pull ?e [:name {:friend [:name]}]) :where [?e :admin? _]]",
+        plan: Plan::pull_all(
+            "admin?",
+            PullPattern::new(vec!["name"]).nest("friend", PullPattern::new(vec!["name"])),
+        ),
+        transactions: vec![vec![
+            Datom::add(100, "admin?", Bool(true)),
+            Datom::add(200, "admin?", Bool(false)),
+            Datom::add(100, "name", String("Mabel".to_string())),
+            Datom::add(200, "name", String("Dipper".to_string())),
+            Datom::add(300, "name", String("Soos".to_string())),
+            Datom::add(100, "friend", Eid(200)),
+            Datom::add(100, "friend", Eid(300)),
+        ]],
+        expectations: vec![vec![
+            (
+                vec![Eid(100), Value::aid("name"), String("Mabel".to_string())],
+                0,
+                1,
+            ),
+            (
+                vec![Eid(200), Value::aid("name"), String("Dipper".to_string())],
+                0,
+                1,
+            ),
+            (
+                vec![
+                    Eid(100),
+                    Value::aid("friend"),
+                    Eid(200),
+                    Value::aid("name"),
+                    String("Dipper".to_string()),
+                ],
+                0,
+                1,
+            ),
+            (
+                vec![
+                    Eid(100),
+                    Value::aid("friend"),
+                    Eid(300),
+                    Value::aid("name"),
+                    String("Soos".to_string()),
+                ],
+                0,
+                1,
+            ),
+        ]],
+    }]);
+}
+
+#[test]
+fn pull_rules() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, u64>::new(Default::default());
+        let (send_results, results) = channel();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            for aid in &["admin?", "name", "parent"] {
+                let config = AttributeConfig {
+                    trace_slack: Some(Time::TxId(1)),
+                    query_support: QuerySupport::AdaptiveWCO,
+                    index_direction: IndexDirection::Both,
+                    ..Default::default()
+                };
+
+                server.create_attribute(scope, *aid, config).unwrap();
+            }
+        });
+
+        let (e, p, g) = (0, 1, 2);
+        server
+            .register(Register {
+                rules: vec![Rule::named(
+                    "grandparent",
+                    Plan::Project(Project {
+                        variables: vec![e, g],
+                        plan: Box::new(Plan::Join(Join {
+                            variables: vec![p],
+                            left_plan: Box::new(Plan::match_a(e, "parent", p)),
+                            right_plan: Box::new(Plan::match_a(p, "parent", g)),
+                        })),
+                    }),
+                )],
+                publish: vec![],
+            })
+            .unwrap();
+
+        // Derived values are pulled alongside stored ones.
+        let plan = Plan::PullLevel(PullLevel {
+            variables: vec![],
+            pull_variable: e,
+            plan: Box::new(Plan::match_av(e, "admin?", Bool(false))),
+            pull_attributes: vec!["name".to_string()],
+            pull_rules: vec!["grandparent".to_string()],
+            path_attributes: vec![],
+            cardinality_many: false,
+        });
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .test_single(scope, Rule::named("query", plan))
+                .inner
+                .sink(Pipeline, "Results", move |input| {
+                    input.for_each(|_time, data| {
+                        for datum in data.iter() {
+                            send_results.send(datum.clone()).unwrap()
+                        }
+                    });
+                });
+        });
+
+        server
+            .transact(
+                vec![
+                    Datom::add(100, "admin?", Bool(false)),
+                    Datom::add(100, "name", String("Mabel".to_string())),
+                    Datom::add(100, "parent", Eid(200)),
+                    Datom::add(200, "parent", Eid(300)),
+                ],
+                0,
+                0,
+            )
+            .unwrap();
+        server.advance_domain(None, 1).unwrap();
+
+        worker.step_while(|| server.is_any_outdated());
+
+        let mut expected: HashSet<(Vec<Value>, u64, isize)> = HashSet::from_iter(vec![
+            (
+                vec![Eid(100), Value::aid("name"), String("Mabel".to_string())],
+                0,
+                1,
+            ),
+            (vec![Eid(100), Value::aid("grandparent"), Eid(300)], 0, 1),
+        ]);
+
+        for _i in 0..expected.len() {
+            let result = results
+                .recv_timeout(Duration::from_millis(400))
+                .expect("No result.");
+
+            if !expected.remove(&result) {
+                panic!("Unknown result {:?}.", result);
+            }
+        }
+
+        assert!(results.recv_timeout(Duration::from_millis(400)).is_err());
+    });
+}
+
 #[cfg(feature = "graphql")]
 #[test]
 #[rustfmt::skip]
@@ -211,6 +366,25 @@ fn graph_ql() {
                 expectations,
             }
         },
+        {
+            let q = "subscription { ...Hero } fragment Hero on Person { name bested { name } }";
+            
+            let expectations = vec![vec![
+                (vec![Eid(100), Value::aid("name"), Value::from("Alice")], 0, 1),
+                (vec![Eid(200), Value::aid("name"), Value::from("Bob")], 0, 1),
+                (vec![Eid(300), Value::aid("name"), Value::from("Mabel")], 0, 1),
+                (vec![Eid(400), Value::aid("name"), Value::from("Dipper")], 0, 1),
+                (vec![Eid(300), Value::aid("bested"), Eid(400), Value::aid("name"), Value::from("Dipper")], 0, 1),
+                (vec![Eid(200), Value::aid("bested"), Eid(100), Value::aid("name"), Value::from("Alice")], 0, 1),
+            ]];
+            
+            Case {
+                description: q,
+                plan: Plan::GraphQl(GraphQl::with_plan(root_plan.clone(), q.to_string())),
+                transactions: transactions.clone(),
+                expectations,
+            }
+        },
         {
             let q = "{bested(name: \"Dipper\") { age }}";
 
@@ -245,3 +419,15 @@ fn graph_ql() {
         }
     ]);
 }
+
+#[cfg(feature = "graphql")]
+#[test]
+fn graph_ql_parse() {
+    use declarative_dataflow::plan::GraphQl;
+
+    assert!(GraphQl::<Aid>::parse("query { name age }".to_string()).is_ok());
+    assert!(GraphQl::<Aid>::parse("subscription { name age }".to_string()).is_ok());
+    assert!(GraphQl::<Aid>::parse("mutation { name }".to_string()).is_err());
+    assert!(GraphQl::<Aid>::parse("{ ...Unknown }".to_string()).is_err());
+    assert!(GraphQl::<Aid>::parse("{ name ".to_string()).is_err());
+}
@@ -1,13 +1,16 @@
+use std::cell::RefCell;
 use std::collections::HashSet;
 use std::iter::FromIterator;
+use std::rc::Rc;
 use std::sync::mpsc::channel;
 use std::time::Duration;
 
 use timely::dataflow::channels::pact::Pipeline;
 use timely::dataflow::operators::Operator;
+use timely::logging::TimelyEvent;
 
 use declarative_dataflow::binding::Binding;
-use declarative_dataflow::plan::{Implementable, Join, Project};
+use declarative_dataflow::plan::{Antijoin, Implementable, Join, Project, Union};
 use declarative_dataflow::server::Server;
 use declarative_dataflow::timestamp::Time;
 use declarative_dataflow::{q, Aid, Datom, Plan, Rule, Value};
@@ -24,7 +27,7 @@ struct Case {
 fn dependencies(case: &Case) -> HashSet<Aid> {
     let mut deps = HashSet::new();
 
-    for binding in case.plan.into_bindings().iter() {
+    for binding in case.plan.into_bindings().unwrap().iter() {
         if let Binding::Attribute(binding) = binding {
             deps.insert(binding.source_attribute.clone());
         }
@@ -150,6 +153,35 @@ fn base_patterns() {
     ]);
 }
 
+#[test]
+fn lookup_refs() {
+    let data = vec![
+        Datom::add(100, ":email", String("dipper@pines.com".to_string())),
+        Datom::add(100, ":name", String("Dipper".to_string())),
+        Datom::add(200, ":email", String("mabel@pines.com".to_string())),
+        Datom::add(200, ":name", String("Mabel".to_string())),
+    ];
+
+    run_cases(vec![Case {
+        description: "[:find ?n :where [[:email mabel@pines.com] :name ?n]]",
+        plan: Plan::match_lookup_a((":email", "mabel@pines.com"), ":name", 0),
+        transactions: vec![
+            data,
+            vec![Datom(
+                Value::lookup_ref(":email", "mabel@pines.com"),
+                ":name".to_string(),
+                String("Mabel Pines".to_string()),
+                None,
+                1,
+            )],
+        ],
+        expectations: vec![
+            vec![(vec![String("Mabel".to_string())], 0, 1)],
+            vec![(vec![String("Mabel Pines".to_string())], 1, 1)],
+        ],
+    }]);
+}
+
 #[test]
 fn base_projections() {
     let data = vec![
@@ -286,6 +318,337 @@ fn joins() {
     }]);
 }
 
+#[test]
+fn composite_joins() {
+    let transactions = vec![vec![
+        Datom::add(1, ":name", String("Dipper".to_string())),
+        Datom::add(1, ":nickname", String("Dipper".to_string())),
+        Datom::add(1, ":age", Number(12)),
+        Datom::add(2, ":name", String("Mabel".to_string())),
+        Datom::add(2, ":nickname", String("Mabes".to_string())),
+        Datom::add(2, ":age", Number(12)),
+    ]];
+
+    run_cases(vec![
+        {
+            let (e, n) = (1, 2);
+            Case {
+                description: "[:find ?e ?n :where [?e :name ?n] [?e :nickname ?n]]",
+                plan: Plan::Project(Project {
+                    variables: vec![e, n],
+                    // Joins on ?n as well, even though only ?e is specified.
+                    plan: Box::new(Plan::Join(Join {
+                        variables: vec![e],
+                        left_plan: Box::new(Plan::match_a(e, ":name", n)),
+                        right_plan: Box::new(Plan::match_a(e, ":nickname", n)),
+                    })),
+                }),
+                transactions: transactions.clone(),
+                expectations: vec![vec![(vec![Eid(1), String("Dipper".to_string())], 0, 1)]],
+            }
+        },
+        {
+            let (e, n, a) = (1, 2, 3);
+            Case {
+                description: "[:find ?e ?n ?a :where [?e :name ?n] [?e :age ?a] [?e :nickname ?n]]",
+                plan: Plan::Project(Project {
+                    variables: vec![e, n, a],
+                    plan: Box::new(Plan::Join(Join {
+                        variables: vec![e],
+                        left_plan: Box::new(Plan::Join(Join {
+                            variables: vec![e],
+                            left_plan: Box::new(Plan::match_a(e, ":name", n)),
+                            right_plan: Box::new(Plan::match_a(e, ":age", a)),
+                        })),
+                        right_plan: Box::new(Plan::match_a(e, ":nickname", n)),
+                    })),
+                }),
+                transactions,
+                expectations: vec![vec![(
+                    vec![Eid(1), String("Dipper".to_string()), Number(12)],
+                    0,
+                    1,
+                )]],
+            }
+        },
+    ]);
+}
+
+#[test]
+fn self_joins() {
+    run_cases(vec![{
+        let (a, b, p) = (1, 2, 3);
+        Case {
+            description: "[:find ?a ?b :where [?a :parent ?p] [?b :parent ?p]]",
+            plan: Plan::Project(Project {
+                variables: vec![a, b],
+                plan: Box::new(Plan::Join(Join {
+                    variables: vec![p],
+                    left_plan: Box::new(Plan::match_a(a, ":parent", p)),
+                    right_plan: Box::new(Plan::match_a(b, ":parent", p)),
+                })),
+            }),
+            transactions: vec![vec![
+                Datom::add(1, ":parent", Eid(10)),
+                Datom::add(2, ":parent", Eid(10)),
+                Datom::add(3, ":parent", Eid(20)),
+            ]],
+            expectations: vec![vec![
+                (vec![Eid(1), Eid(1)], 0, 1),
+                (vec![Eid(1), Eid(2)], 0, 1),
+                (vec![Eid(2), Eid(1)], 0, 1),
+                (vec![Eid(2), Eid(2)], 0, 1),
+                (vec![Eid(3), Eid(3)], 0, 1),
+            ]],
+        }
+    }]);
+}
+
+#[test]
+fn self_join_bindings() {
+    use declarative_dataflow::binding::AsBinding;
+
+    let (a, b, p) = (1, 2, 3);
+    let side = |x| {
+        Plan::Project(Project {
+            variables: vec![x],
+            plan: Box::new(Plan::match_a(x, ":parent", p)),
+        })
+    };
+
+    let plan: Plan<Aid> = Plan::Join(Join {
+        variables: vec![],
+        left_plan: Box::new(side(a)),
+        right_plan: Box::new(side(b)),
+    });
+
+    // Both sides project away their own ?p, which must not be
+    // unified once the plan is flattened into bindings.
+    let bindings = plan.into_bindings().unwrap();
+    let left = bindings[0].variables();
+    let right = bindings[1].variables();
+
+    assert_eq!(left[0], a);
+    assert_eq!(right[0], b);
+    assert_ne!(left[1], p);
+    assert_ne!(right[1], p);
+    assert_ne!(left[1], right[1]);
+}
+
+#[test]
+fn shared_arrangements() {
+    run_cases(vec![{
+        let (e, n, m, x) = (1, 2, 3, 4);
+
+        // The same sub-plan is joined on ?e in both branches.
+        let named = Plan::Join(Join {
+            variables: vec![e],
+            left_plan: Box::new(Plan::match_a(e, ":name", n)),
+            right_plan: Box::new(Plan::match_a(e, ":email", m)),
+        });
+
+        Case {
+            description: "[:find ?e ?x :where [?e :name ?n] [?e :email ?m] (or [?e :age ?x] [?e :height ?x])]",
+            plan: Plan::Union(Union {
+                variables: vec![e, x],
+                plans: vec![
+                    Plan::Join(Join {
+                        variables: vec![e],
+                        left_plan: Box::new(named.clone()),
+                        right_plan: Box::new(Plan::match_a(e, ":age", x)),
+                    }),
+                    Plan::Join(Join {
+                        variables: vec![e],
+                        left_plan: Box::new(Plan::match_a(e, ":height", x)),
+                        right_plan: Box::new(named),
+                    }),
+                ],
+                mappings: vec![],
+            }),
+            transactions: vec![vec![
+                Datom::add(1, ":name", String("Dipper".to_string())),
+                Datom::add(1, ":email", String("dipper@pines.com".to_string())),
+                Datom::add(1, ":age", Number(12)),
+                Datom::add(1, ":height", Number(150)),
+                Datom::add(2, ":name", String("Soos".to_string())),
+                Datom::add(2, ":age", Number(22)),
+            ]],
+            expectations: vec![vec![
+                (vec![Eid(1), Number(12)], 0, 1),
+                (vec![Eid(1), Number(150)], 0, 1),
+            ]],
+        }
+    }]);
+}
+
+/// Returns the number of arrangements created by implementing the
+/// specified plan, on top of those of the attributes it depends on.
+fn count_arrangements(plan: Plan<Aid>, attributes: &[&str]) -> usize {
+    let attributes: Vec<Aid> = attributes.iter().map(|name| name.to_string()).collect();
+
+    timely::execute_directly(move |worker| {
+        let arrangements = Rc::new(RefCell::new(0));
+
+        {
+            let arrangements = arrangements.clone();
+            worker
+                .log_register()
+                .insert::<TimelyEvent, _>("timely", move |_time, data| {
+                    for (_time, _worker, event) in data.iter() {
+                        if let TimelyEvent::Operates(ref operates) = event {
+                            if operates.name.starts_with("Arrange") {
+                                *arrangements.borrow_mut() += 1;
+                            }
+                        }
+                    }
+                });
+        }
+
+        let mut server = Server::<Aid, u64, u64>::new(Default::default());
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            for name in attributes.iter() {
+                let config = AttributeConfig {
+                    query_support: QuerySupport::AdaptiveWCO,
+                    index_direction: IndexDirection::Both,
+                    ..Default::default()
+                };
+
+                server.create_attribute(scope, name, config).unwrap();
+            }
+        });
+
+        for _ in 0..10 {
+            worker.step();
+        }
+        let before = *arrangements.borrow();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server.test_single(scope, Rule::named("query", plan));
+        });
+
+        for _ in 0..10 {
+            worker.step();
+        }
+        let after = *arrangements.borrow();
+
+        after - before
+    })
+}
+
+#[test]
+fn arrangement_reuse() {
+    let (e, n, m, x) = (1, 2, 3, 4);
+
+    let union = |first: Plan<Aid>, second: Plan<Aid>| {
+        Plan::Union(Union {
+            variables: vec![e, x],
+            plans: vec![
+                Plan::Join(Join {
+                    variables: vec![e],
+                    left_plan: Box::new(first),
+                    right_plan: Box::new(Plan::match_a(e, ":age", x)),
+                }),
+                Plan::Join(Join {
+                    variables: vec![e],
+                    left_plan: Box::new(Plan::match_a(e, ":height", x)),
+                    right_plan: Box::new(second),
+                }),
+            ],
+        })
+    };
+
+    let named = Plan::Join(Join {
+        variables: vec![e],
+        left_plan: Box::new(Plan::match_a(e, ":name", n)),
+        right_plan: Box::new(Plan::match_a(e, ":email", m)),
+    });
+
+    // The same relation, but via a different plan.
+    let named_differently = Plan::Join(Join {
+        variables: vec![e],
+        left_plan: Box::new(Plan::match_a(e, ":email", m)),
+        right_plan: Box::new(Plan::match_a(e, ":name", n)),
+    });
+
+    let attributes = [":name", ":email", ":age", ":height"];
+    let shared = count_arrangements(union(named.clone(), named.clone()), &attributes);
+    let separate = count_arrangements(union(named, named_differently), &attributes);
+
+    // The second join re-uses the arrangement of the first.
+    assert!(shared > 0);
+    assert!(shared < separate);
+}
+
+#[test]
+fn antijoins() {
+    run_cases(vec![
+        {
+            let (e, n, x) = (1, 2, 3);
+            Case {
+                description: "[:find ?e ?n :where [?e :name ?n] (not [?e :nickname _])]",
+                plan: Plan::Antijoin(Antijoin {
+                    variables: vec![e],
+                    left_plan: Box::new(Plan::match_a(e, ":name", n)),
+                    right_plan: Box::new(Plan::match_a(e, ":nickname", x)),
+                }),
+                transactions: vec![
+                    vec![
+                        Datom::add(1, ":name", String("Dipper".to_string())),
+                        Datom::add(1, ":nickname", String("Dip".to_string())),
+                        Datom::add(1, ":nickname", String("Mason".to_string())),
+                        Datom::add(2, ":name", String("Mabel".to_string())),
+                    ],
+                    vec![
+                        Datom::retract(1, ":nickname", String("Dip".to_string())),
+                        Datom::retract(1, ":nickname", String("Mason".to_string())),
+                    ],
+                ],
+                expectations: vec![
+                    vec![(vec![Eid(2), String("Mabel".to_string())], 0, 1)],
+                    vec![(vec![Eid(1), String("Dipper".to_string())], 1, 1)],
+                ],
+            }
+        },
+        {
+            let (e, n, x) = (1, 2, 3);
+            Case {
+                description: "[:find ?n ?e :where [?e :name ?n] (not [_ :banned ?n])]",
+                plan: Plan::Antijoin(Antijoin {
+                    variables: vec![n],
+                    left_plan: Box::new(Plan::match_a(e, ":name", n)),
+                    right_plan: Box::new(Plan::match_a(x, ":banned", n)),
+                }),
+                transactions: vec![vec![
+                    Datom::add(1, ":name", String("Dipper".to_string())),
+                    Datom::add(2, ":name", String("Mabel".to_string())),
+                    Datom::add(10, ":banned", String("Dipper".to_string())),
+                    Datom::add(11, ":banned", String("Dipper".to_string())),
+                ]],
+                expectations: vec![vec![(vec![String("Mabel".to_string()), Eid(2)], 0, 1)]],
+            }
+        },
+        {
+            let (e, n) = (1, 2);
+            Case {
+                description: "[:find ?e ?n :where [?e :name ?n] (not [?e :alias ?n])]",
+                plan: Plan::Antijoin(Antijoin {
+                    variables: vec![e, n],
+                    left_plan: Box::new(Plan::match_a(e, ":name", n)),
+                    right_plan: Box::new(Plan::match_a(e, ":alias", n)),
+                }),
+                transactions: vec![vec![
+                    Datom::add(1, ":name", String("Dipper".to_string())),
+                    Datom::add(1, ":alias", String("Dipper".to_string())),
+                    Datom::add(2, ":name", String("Mabel".to_string())),
+                    Datom::add(2, ":alias", String("Mabes".to_string())),
+                ]],
+                expectations: vec![vec![(vec![Eid(2), String("Mabel".to_string())], 0, 1)]],
+            }
+        },
+    ]);
+}
+
 #[test]
 fn wco_joins() {
     let data = vec![
@@ -451,3 +814,75 @@ fn wco_join_many() {
 //         ],
 //     }
 // },
+
+#[test]
+fn seek_keys_across_workers() {
+    let (send_results, results) = channel();
+    let send_results = std::sync::Mutex::new(send_results);
+
+    timely::execute(timely::Configuration::Process(2), move |worker| {
+        let send_results = send_results.lock().unwrap().clone();
+
+        let mut server = Server::<Aid, u64, u64>::new(Default::default());
+        server.peers = worker.peers();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            let config = AttributeConfig {
+                index_direction: IndexDirection::Both,
+                ..Default::default()
+            };
+            server.create_attribute(scope, ":name", config).unwrap();
+
+            let rules = vec![
+                Rule::named("by-entity", Plan::match_ea(1, ":name", 0)),
+                Rule::named("by-value", Plan::match_av(0, ":name", "Mabel")),
+            ];
+
+            for rule in rules {
+                let send_results = send_results.clone();
+                let name = rule.name.clone();
+
+                server
+                    .test_single(scope, rule)
+                    .inner
+                    .sink(Pipeline, "Results", move |input| {
+                        input.for_each(|_time, data| {
+                            for datum in data.iter() {
+                                send_results.send((name.clone(), datum.clone())).unwrap()
+                            }
+                        });
+                    });
+            }
+        });
+
+        server
+            .transact(
+                vec![
+                    Datom::add(1, ":name", String("Dipper".to_string())),
+                    Datom::add(2, ":name", String("Mabel".to_string())),
+                ],
+                0,
+                worker.index(),
+            )
+            .unwrap();
+        server.advance_domain(None, 1).unwrap();
+
+        worker.step_while(|| server.is_any_outdated());
+    })
+    .unwrap();
+
+    let mut results: Vec<_> = results.try_iter().collect();
+    results.sort();
+
+    // Every match is found once, no matter the number of workers.
+    assert_eq!(
+        results,
+        vec![
+            (
+                "by-entity".to_string(),
+                (vec![String("Dipper".to_string())], 0, 1)
+            ),
+            ("by-value".to_string(), (vec![Eid(2)], 0, 1)),
+        ]
+    );
+}
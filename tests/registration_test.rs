@@ -1,10 +1,12 @@
+use std::collections::BTreeMap;
 use std::sync::mpsc::channel;
+use std::time::Duration;
 
-use declarative_dataflow::plan::{Join, Project};
-use declarative_dataflow::server::Server;
+use declarative_dataflow::plan::{Debounce, Delay, Join, Project};
+use declarative_dataflow::server::{Configure, Inspect, Interest, Register, Report, Server};
 use declarative_dataflow::timestamp::Time;
-use declarative_dataflow::{Aid, Datom, Plan, Rule, Value};
-use declarative_dataflow::{AttributeConfig, IndexDirection, QuerySupport};
+use declarative_dataflow::{Aid, Datom, Plan, Rule, TimeToLive, Value};
+use declarative_dataflow::{AttributeConfig, IndexDirection, InputSemantics, QuerySupport};
 use Value::{Eid, String};
 
 #[test]
@@ -156,3 +158,568 @@ fn join_after_input() {
         assert_eq!(results.recv().unwrap(), (vec![Eid(101), Eid(1)], 1));
     });
 }
+
+#[test]
+fn inspect() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, u64>::new(Default::default());
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .create_attribute(
+                    scope,
+                    ":name",
+                    AttributeConfig::tx_time(InputSemantics::Raw),
+                )
+                .unwrap();
+        });
+
+        server
+            .register(Register {
+                rules: vec![Rule::named("names", Plan::match_a(0, ":name", 1))],
+                publish: vec![],
+            })
+            .unwrap();
+
+        let tx_data = vec![
+            Datom::add(1, ":name", String("Dipper".to_string())),
+            Datom::add(2, ":name", String("Mabel".to_string())),
+        ];
+
+        server.transact(tx_data, 0, 0).unwrap();
+        server.advance_domain(None, 1).unwrap();
+
+        worker.step_while(|| server.is_any_outdated());
+
+        match server.inspect(&Inspect::Rules).unwrap() {
+            Report::Rules(rules) => {
+                assert_eq!(rules.len(), 1);
+                assert_eq!(rules[0].name, "names");
+            }
+            other => panic!("Unexpected report {:?}.", other),
+        }
+
+        match server.inspect(&Inspect::Attributes).unwrap() {
+            Report::Attributes(stats) => {
+                assert_eq!(stats.len(), 1);
+                assert_eq!(stats[0].name, ":name");
+                assert_eq!(stats[0].indices["forward_propose"].updates, 2);
+            }
+            other => panic!("Unexpected report {:?}.", other),
+        }
+
+        match server.inspect(&Inspect::Interests).unwrap() {
+            Report::Interests(stats) => assert!(stats.is_empty()),
+            other => panic!("Unexpected report {:?}.", other),
+        }
+
+        assert!(server.inspect(&Inspect::Clients).is_err());
+    });
+}
+
+#[test]
+fn drain() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, u64>::new(Default::default());
+        let (send_results, results) = channel();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .create_attribute(
+                    scope,
+                    ":name",
+                    AttributeConfig::tx_time(InputSemantics::Raw),
+                )
+                .unwrap();
+
+            server
+                .test_single(scope, Rule::named("names", Plan::match_a(0, ":name", 1)))
+                .inspect(move |x| {
+                    send_results.send((x.0.clone(), x.2)).unwrap();
+                });
+        });
+
+        let tx_data = vec![Datom::add(1, ":name", String("Dipper".to_string()))];
+        server.transact(tx_data, 0, 0).unwrap();
+
+        let upper = server.drain();
+        assert_eq!(upper, vec![0]);
+
+        let tx_data = vec![Datom::add(2, ":name", String("Mabel".to_string()))];
+        assert!(server.transact(tx_data, 0, 0).is_err());
+
+        worker.step_while(|| !server.is_drained(&upper));
+
+        assert_eq!(
+            results.recv().unwrap(),
+            (vec![Eid(1), String("Dipper".to_string())], 1)
+        );
+        assert!(results.try_recv().is_err());
+    });
+}
+
+#[test]
+fn configure() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, u64>::new(Default::default());
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .create_attribute(
+                    scope,
+                    ":name",
+                    AttributeConfig::tx_time(InputSemantics::Raw),
+                )
+                .unwrap();
+        });
+
+        let mut trace_slack = BTreeMap::new();
+        trace_slack.insert(":unknown".to_string(), Some(Time::TxId(10)));
+
+        let invalid = Configure {
+            tick: Some(Duration::from_secs(1)),
+            trace_slack,
+            ..Default::default()
+        };
+
+        assert!(server.configure(invalid).is_err());
+        assert_eq!(server.config.tick, None);
+
+        let mut trace_slack = BTreeMap::new();
+        trace_slack.insert(":name".to_string(), Some(Time::TxId(10)));
+
+        server
+            .configure(Configure {
+                tick: Some(Duration::from_secs(1)),
+                enable_optimizer: Some(true),
+                trace_slack,
+                ..Default::default()
+            })
+            .unwrap();
+
+        assert_eq!(server.config.tick, Some(Duration::from_secs(1)));
+        assert!(server.config.enable_optimizer);
+        assert_eq!(
+            server.internal.attributes[":name"].trace_slack,
+            Some(Time::TxId(10))
+        );
+
+        server
+            .configure(Configure {
+                tick: Some(Duration::from_secs(0)),
+                ..Default::default()
+            })
+            .unwrap();
+
+        assert_eq!(server.config.tick, None);
+    });
+}
+
+#[test]
+fn shared_dataflows() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, u64>::new(Default::default());
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .create_attribute(
+                    scope,
+                    ":name",
+                    AttributeConfig::tx_time(InputSemantics::Raw),
+                )
+                .unwrap();
+        });
+
+        server
+            .register(Register {
+                rules: vec![
+                    Rule::named("names", Plan::match_a(0, ":name", 1)),
+                    Rule::named("nombres", Plan::match_a(0, ":name", 1)),
+                    Rule::named("entities", Plan::match_a(0, ":name", 2)),
+                ],
+                publish: vec![],
+            })
+            .unwrap();
+
+        assert_eq!(server.shared_name(&"nombres".to_string()), "names");
+        assert_eq!(server.shared_name(&"entities".to_string()), "entities");
+
+        let interest = |name: &str| Interest {
+            name: name.to_string(),
+            ..Default::default()
+        };
+
+        let shared = server.share_interest(1, interest("names"));
+        assert_eq!(shared.name, "names");
+        assert!(server.output_names.is_empty());
+
+        let shared = server.share_interest(2, interest("nombres"));
+        assert_eq!(shared.name, "names");
+        assert_eq!(server.output_names["names"][&2], "nombres");
+
+        server
+            .interests
+            .entry("names".to_string())
+            .or_insert_with(Default::default)
+            .extend(vec![1, 2]);
+
+        server.uninterest(2, &"nombres".to_string()).unwrap();
+        assert!(server.output_names.is_empty());
+        assert_eq!(server.interests["names"].len(), 1);
+    });
+}
+
+#[test]
+fn late_joiners() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, u64>::new(Default::default());
+        let (send_results, results) = channel();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .create_attribute(
+                    scope,
+                    ":name",
+                    AttributeConfig::tx_time(InputSemantics::Raw),
+                )
+                .unwrap();
+        });
+
+        server
+            .register(Register {
+                rules: vec![Rule::named("names", Plan::match_a(0, ":name", 1))],
+                publish: vec![],
+            })
+            .unwrap();
+
+        let interest = |name: &str| Interest {
+            name: name.to_string(),
+            ..Default::default()
+        };
+
+        let first = server.share_interest(1, interest("names"));
+        assert_eq!(first.name, "names");
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .interest(first.name.clone(), scope)
+                .unwrap()
+                .probe_with(&mut server.probe);
+        });
+        server
+            .interests
+            .entry(first.name.clone())
+            .or_insert_with(Default::default)
+            .insert(1);
+
+        server
+            .transact(
+                vec![
+                    Datom::add(1, ":name", String("Dipper".to_string())),
+                    Datom::add(2, ":name", String("Mabel".to_string())),
+                ],
+                0,
+                0,
+            )
+            .unwrap();
+        server.advance_domain(None, 1).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+        server.compact_results();
+
+        // The second client is caught up on everything so far.
+        let late = server.share_interest(2, interest("names"));
+        assert_ne!(late.name, "names");
+        assert_eq!(server.output_names[&late.name][&2], "names");
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .interest(late.name.clone(), scope)
+                .unwrap()
+                .inspect(move |x| send_results.send(x.clone()).unwrap())
+                .probe_with(&mut server.probe);
+        });
+        server
+            .interests
+            .entry(late.name.clone())
+            .or_insert_with(Default::default)
+            .insert(2);
+
+        server
+            .transact(
+                vec![Datom::add(3, ":name", String("Soos".to_string()))],
+                0,
+                0,
+            )
+            .unwrap();
+        server.advance_domain(None, 2).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        let mut names: Vec<_> = results
+            .try_iter()
+            .map(|(tuple, _t, diff)| (tuple, diff))
+            .collect();
+        names.sort();
+
+        assert_eq!(
+            names,
+            vec![
+                (vec![Eid(1), String("Dipper".to_string())], 1),
+                (vec![Eid(2), String("Mabel".to_string())], 1),
+                (vec![Eid(3), String("Soos".to_string())], 1),
+            ]
+        );
+
+        // The relation keeps running until the late joiner is gone,
+        // too.
+        server.uninterest(1, &"names".to_string()).unwrap();
+        assert!(server.interests.contains_key(&late.name));
+        server.uninterest(2, &"names".to_string()).unwrap();
+        assert!(server.interests.is_empty());
+    });
+}
+
+#[test]
+fn times_to_live() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, u64>::new(Default::default());
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            let ttl = |time_to_live| AttributeConfig {
+                time_to_live: Some(time_to_live),
+                ..AttributeConfig::tx_time(InputSemantics::Raw)
+            };
+
+            assert!(server
+                .create_attribute(scope, ":session", ttl(TimeToLive::TxTime(Time::TxId(10))))
+                .is_ok());
+
+            // Neither can be expressed in logical time.
+            let real = ttl(TimeToLive::TxTime(Time::Real(Duration::from_secs(10))));
+            let instant = ttl(TimeToLive::Instant(Duration::from_secs(10)));
+
+            assert!(server.create_attribute(scope, ":token", real).is_err());
+            assert!(server.create_attribute(scope, ":login", instant).is_err());
+        });
+    });
+}
+
+#[test]
+fn invalid_plans() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, u64>::new(Default::default());
+        let (send_results, results) = channel();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .create_attribute(
+                    scope,
+                    ":name",
+                    AttributeConfig::tx_time(InputSemantics::Raw),
+                )
+                .unwrap();
+
+            // [:find ?unbound :where [?e :name ?n]]
+            let unbound = Plan::Project(Project {
+                variables: vec![2],
+                plan: Box::new(Plan::match_a(0, ":name", 1)),
+            });
+
+            // [:find ?e1 ?e2 :where [?e1 :name ?n] [?e2 :name ?n]]
+            // requires a reverse index, which :name doesn't maintain.
+            let unindexed = Plan::Join(Join {
+                variables: vec![1],
+                left_plan: Box::new(Plan::match_a(0, ":name", 1)),
+                right_plan: Box::new(Plan::match_a(2, ":name", 1)),
+            });
+
+            server
+                .register(Register {
+                    rules: vec![
+                        Rule::named("unbound", unbound),
+                        Rule::named("unindexed", unindexed),
+                    ],
+                    publish: vec!["unbound".to_string(), "unindexed".to_string()],
+                })
+                .unwrap();
+
+            match server.interest("unbound".to_string(), scope) {
+                Ok(_) => panic!("unbound variables must be rejected"),
+                Err(error) => {
+                    assert_eq!(error.category, "df.error.category/incorrect");
+                    assert_eq!(error.message, "Variable 2 is not bound.");
+                }
+            }
+
+            match server.interest("unindexed".to_string(), scope) {
+                Ok(_) => panic!("missing indices must be rejected"),
+                Err(error) => {
+                    assert_eq!(error.category, "df.error.category/not-found");
+                    assert_eq!(error.message, "Attribute :name has no reverse index.");
+                }
+            }
+
+            // Real time offsets can't be expressed in logical time.
+            let delayed = Plan::Delay(Delay {
+                plan: Box::new(Plan::match_a(0, ":name", 1)),
+                offset: Time::Real(Duration::from_secs(10)),
+            });
+
+            let debounced = Plan::Debounce(Debounce {
+                plan: Box::new(Plan::match_a(0, ":name", 1)),
+                period: Time::Real(Duration::from_secs(10)),
+            });
+
+            server
+                .register(Register {
+                    rules: vec![
+                        Rule::named("delayed", delayed),
+                        Rule::named("debounced", debounced),
+                    ],
+                    publish: vec!["delayed".to_string(), "debounced".to_string()],
+                })
+                .unwrap();
+
+            match server.interest("delayed".to_string(), scope) {
+                Ok(_) => panic!("inconvertible offsets must be rejected"),
+                Err(error) => {
+                    assert_eq!(error.category, "df.error.category/incorrect");
+                    assert_eq!(
+                        error.message,
+                        "Offset Real(10s) doesn't match the domain's timestamps."
+                    );
+                }
+            }
+
+            match server.interest("debounced".to_string(), scope) {
+                Ok(_) => panic!("inconvertible periods must be rejected"),
+                Err(error) => assert_eq!(error.category, "df.error.category/incorrect"),
+            }
+        });
+
+        // The worker survives and keeps accepting valid plans.
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .test_single(scope, Rule::named("names", Plan::match_a(0, ":name", 1)))
+                .inspect(move |x| {
+                    send_results.send((x.0.clone(), x.2)).unwrap();
+                });
+        });
+
+        server
+            .transact(
+                vec![Datom::add(1, ":name", String("Dipper".to_string()))],
+                0,
+                0,
+            )
+            .unwrap();
+        server.advance_domain(None, 1).unwrap();
+
+        worker.step_while(|| server.is_any_outdated());
+
+        assert_eq!(
+            results.recv_timeout(Duration::from_millis(400)),
+            Ok((vec![Eid(1), String("Dipper".to_string())], 1))
+        );
+    });
+}
+
+#[test]
+fn validate() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, u64>::new(Default::default());
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .create_attribute(
+                    scope,
+                    ":name",
+                    AttributeConfig::tx_time(InputSemantics::Raw),
+                )
+                .unwrap();
+        });
+
+        let valid = Register {
+            rules: vec![
+                Rule::named("names", Plan::match_a(0, ":name", 1)),
+                Rule::named(
+                    "entities",
+                    Plan::Project(Project {
+                        variables: vec![0],
+                        plan: Box::new(Plan::NameExpr(vec![0, 1], "names".to_string())),
+                    }),
+                ),
+            ],
+            publish: vec![],
+        };
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            assert!(server.validate(scope, &valid).is_empty());
+        });
+
+        let invalid = Register {
+            rules: vec![
+                Rule::named("emails", Plan::match_a(0, ":email", 1)),
+                Rule::named(
+                    "aliases",
+                    Plan::NameExpr(vec![0, 1], "nicknames".to_string()),
+                ),
+                Rule::named(
+                    "unbound",
+                    Plan::Project(Project {
+                        variables: vec![2],
+                        plan: Box::new(Plan::match_a(0, ":name", 1)),
+                    }),
+                ),
+            ],
+            publish: vec![],
+        };
+
+        let diagnostics = worker.dataflow::<u64, _, _>(|scope| server.validate(scope, &invalid));
+        let rules: Vec<_> = diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.rule.as_str())
+            .collect();
+
+        assert_eq!(rules, vec!["emails", "aliases", "unbound"]);
+
+        // Diagnostics match the errors implementing the rules would
+        // fail with.
+        let categories: Vec<_> = diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.error.category.as_str())
+            .collect();
+
+        assert_eq!(
+            categories,
+            vec![
+                "df.error.category/not-found",
+                "df.error.category/not-found",
+                "df.error.category/incorrect",
+            ]
+        );
+        assert_eq!(diagnostics[2].error.message, "Variable 2 is not bound.");
+
+        // Nothing was registered.
+        match server.inspect(&Inspect::Rules).unwrap() {
+            Report::Rules(rules) => assert!(rules.is_empty()),
+            other => panic!("Unexpected report {:?}.", other),
+        }
+    });
+}
+
+#[test]
+fn check_fields() {
+    let mut server = Server::<Aid, u64, u64>::new(Default::default());
+
+    server
+        .register(Register {
+            rules: vec![Rule::named("names", Plan::match_a(0, ":name", 1))],
+            publish: vec!["names".to_string()],
+        })
+        .unwrap();
+
+    assert!(server.check_fields(&"names".to_string(), &[1, 0]).is_ok());
+
+    let error = server.check_fields(&"names".to_string(), &[2]).unwrap_err();
+    assert_eq!(error.category, "df.error.category/incorrect");
+}
@@ -0,0 +1,62 @@
+use declarative_dataflow::server::{CreateAttribute, Interest, Register, Request};
+use declarative_dataflow::timestamp::Time;
+use declarative_dataflow::{Aid, AttributeConfig, Datom, InputSemantics, Plan, Rule, Value};
+
+#[test]
+fn replicated_requests() {
+    let requests: Vec<Request<Aid>> = vec![
+        Request::CreateAttribute(CreateAttribute {
+            name: ":name".to_string(),
+            config: AttributeConfig::tx_time(InputSemantics::Raw),
+            backfill: None,
+        }),
+        Request::Interest(Interest {
+            name: "names".to_string(),
+            ..Default::default()
+        }),
+        Request::Transact(vec![Datom::add(
+            1,
+            ":name",
+            Value::String("Dipper".to_string()),
+        )]),
+        Request::AdvanceDomain(None, Time::TxId(1)),
+        Request::Heartbeat,
+        Request::Promote,
+    ];
+
+    // Standbys only replay what changes state. Interests, sessions,
+    // and promotions concern the server they are sent to.
+    let journaled = requests
+        .iter()
+        .filter(|req| req.is_replicated())
+        .cloned()
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        journaled,
+        vec![
+            requests[0].clone(),
+            requests[2].clone(),
+            requests[3].clone()
+        ]
+    );
+}
+
+#[test]
+fn read_replica_writes() {
+    let register = Request::<Aid>::Register(Register {
+        rules: vec![Rule::named("names", Plan::match_a(0, ":name", 1))],
+        publish: vec![],
+    });
+    let retract = Request::Transact(vec![Datom::retract(
+        1,
+        ":name",
+        Value::String("Dipper".to_string()),
+    )]);
+
+    // Queries are replicated, but replicas may register their own.
+    assert!(register.is_replicated());
+    assert!(!register.is_write());
+    assert!(retract.is_write());
+    assert!(!Request::<Aid>::Heartbeat.is_write());
+}
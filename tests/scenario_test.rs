@@ -0,0 +1,85 @@
+use declarative_dataflow::plan::{Join, Project};
+use declarative_dataflow::testing::Scenario;
+use declarative_dataflow::{Datom, Plan, Value};
+use Value::{Eid, Number, String};
+
+fn name(e: u64, name: &str) -> Datom<String> {
+    Datom::add(e, ":name", String(name.to_string()))
+}
+
+#[test]
+fn join_with_retractions() {
+    let (e, n, a) = (1, 2, 3);
+
+    Scenario::new(
+        "[:find ?e ?n ?a :where [?e :name ?n] [?e :age ?a]]",
+        Plan::Project(Project {
+            variables: vec![e, n, a],
+            plan: Box::new(Plan::Join(Join {
+                variables: vec![e],
+                left_plan: Box::new(Plan::match_a(e, ":name", n)),
+                right_plan: Box::new(Plan::match_a(e, ":age", a)),
+            })),
+        }),
+    )
+    .transact(vec![name(100, "Dipper"), name(200, "Mabel")], vec![])
+    .transact(
+        vec![
+            Datom::add(100, ":age", Number(12)),
+            Datom::add(200, ":age", Number(12)),
+        ],
+        vec![
+            (
+                vec![Eid(200), String("Mabel".to_string()), Number(12)],
+                1,
+                1,
+            ),
+            (
+                vec![Eid(100), String("Dipper".to_string()), Number(12)],
+                1,
+                1,
+            ),
+        ],
+    )
+    .transact(
+        vec![
+            Datom::retract(100, ":age", Number(12)),
+            Datom::add(100, ":age", Number(13)),
+        ],
+        vec![
+            (
+                vec![Eid(100), String("Dipper".to_string()), Number(12)],
+                2,
+                -1,
+            ),
+            (
+                vec![Eid(100), String("Dipper".to_string()), Number(13)],
+                2,
+                1,
+            ),
+        ],
+    )
+    .run();
+}
+
+#[test]
+fn auxiliary_rules() {
+    Scenario::new("named", Plan::NameExpr(vec![0, 1], "names".to_string()))
+        .rule("names", Plan::match_a(0, ":name", 1))
+        .transact(
+            vec![name(100, "Dipper")],
+            vec![(vec![Eid(100), String("Dipper".to_string())], 0, 1)],
+        )
+        .run();
+}
+
+#[test]
+#[should_panic(expected = "unexpected outputs after transaction 0")]
+fn deviations() {
+    Scenario::new("names", Plan::match_a(0, ":name", 1))
+        .transact(
+            vec![name(100, "Dipper")],
+            vec![(vec![Eid(100), String("Mabel".to_string())], 0, 1)],
+        )
+        .run();
+}
@@ -0,0 +1,111 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::mpsc::channel;
+
+use declarative_dataflow::server::Server;
+use declarative_dataflow::{Aid, AttributeConfig, Datom, InputSemantics, Plan, Rule, Value};
+use Value::{Eid, String};
+
+/// Searches the :bio attribute after each transaction, and checks
+/// the entities matching at that point.
+fn run(query: &str, transactions: Vec<Vec<Datom<Aid>>>, expectations: Vec<Vec<u64>>) {
+    let query = query.to_string();
+
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, ()>::new(Default::default());
+        let (send_results, results) = channel();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            let config = AttributeConfig {
+                fulltext: true,
+                ..AttributeConfig::tx_time(InputSemantics::Raw)
+            };
+
+            server.create_attribute(scope, ":bio", config).unwrap();
+
+            server
+                .test_single(scope, Rule::named("search", Plan::search(0, ":bio", query)))
+                .inspect(move |x| send_results.send((x.0.clone(), x.2)).unwrap())
+                .probe_with(&mut server.probe);
+        });
+
+        let mut matches = BTreeMap::new();
+
+        for (tx, (tx_data, expected)) in transactions
+            .into_iter()
+            .zip(expectations.into_iter())
+            .enumerate()
+        {
+            server.transact(tx_data, 0, 0).unwrap();
+            server.advance_domain(None, tx as u64 + 1).unwrap();
+            worker.step_while(|| server.is_any_outdated());
+
+            while let Ok((tuple, diff)) = results.try_recv() {
+                *matches.entry(tuple).or_insert(0) += diff;
+            }
+
+            let current: BTreeSet<Vec<Value>> = matches
+                .iter()
+                .filter(|(_tuple, count)| **count != 0)
+                .map(|(tuple, _count)| tuple.clone())
+                .collect();
+
+            let expected: BTreeSet<Vec<Value>> =
+                expected.into_iter().map(|e| vec![Eid(e)]).collect();
+
+            assert_eq!(current, expected);
+        }
+    });
+}
+
+fn bios() -> Vec<Datom<Aid>> {
+    vec![
+        Datom::add(
+            1,
+            ":bio",
+            String("Dipper Pines, amateur sleuth.".to_string()),
+        ),
+        Datom::add(2, ":bio", String("Mabel Pines loves sweaters".to_string())),
+        Datom::add(
+            3,
+            ":bio",
+            String("Stanford PINES, author of the journals".to_string()),
+        ),
+    ]
+}
+
+#[test]
+fn tokens() {
+    run("pines", vec![bios()], vec![vec![1, 2, 3]]);
+    run("Sleuth", vec![bios()], vec![vec![1]]);
+    run("gnome", vec![bios()], vec![vec![]]);
+}
+
+#[test]
+fn conjunctions() {
+    run("pines sweaters", vec![bios()], vec![vec![2]]);
+    run("pines pines", vec![bios()], vec![vec![1, 2, 3]]);
+    run("mabel journals", vec![bios()], vec![vec![]]);
+}
+
+#[test]
+fn prefixes() {
+    run("s*", vec![bios()], vec![vec![1, 2, 3]]);
+    run("jour* stan*", vec![bios()], vec![vec![3]]);
+    run("sweat*", vec![bios()], vec![vec![2]]);
+}
+
+#[test]
+fn retractions() {
+    run(
+        "sweaters",
+        vec![
+            bios(),
+            vec![
+                Datom::retract(2, ":bio", String("Mabel Pines loves sweaters".to_string())),
+                Datom::add(2, ":bio", String("Mabel Pines loves Waddles".to_string())),
+                Datom::add(4, ":bio", String("Soos knits sweaters".to_string())),
+            ],
+        ],
+        vec![vec![2], vec![4]],
+    );
+}
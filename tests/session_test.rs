@@ -0,0 +1,71 @@
+use std::time::Duration;
+
+use declarative_dataflow::server::{Configuration, Register, Server};
+use declarative_dataflow::{Aid, AttributeConfig, InputSemantics, Plan, Rule};
+
+#[test]
+fn session_cleanup() {
+    timely::execute_directly(move |worker| {
+        let config = Configuration {
+            session_timeout: Some(Duration::from_millis(0)),
+            ..Default::default()
+        };
+        let mut server = Server::<Aid, u64, usize>::new(config);
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .create_attribute(
+                    scope,
+                    ":name",
+                    AttributeConfig::tx_time(InputSemantics::Raw),
+                )
+                .unwrap();
+        });
+
+        let names = |client: usize| format!("names-{}", client);
+
+        for client in [1, 2].iter() {
+            server.touch_session(*client, 0);
+            server
+                .register(Register {
+                    rules: vec![Rule::named(names(*client), Plan::match_a(0, ":name", 1))],
+                    publish: vec![names(*client)],
+                })
+                .unwrap();
+            server.claim_rules(*client, vec![names(*client)]);
+        }
+
+        // Client 2 subscribes to the rule registered by client 1.
+        worker.dataflow::<u64, _, _>(|scope| {
+            server.interest(names(1), scope).unwrap();
+        });
+        server
+            .interests
+            .entry(names(1))
+            .or_insert_with(Default::default)
+            .insert(2);
+
+        // Disconnecting doesn't clean up right away, while sessions
+        // are being timed out.
+        server.disconnect_client(1).unwrap();
+        assert!(server.internal.rules.contains_key(&names(1)));
+
+        // Sessions owned by other workers aren't expired here.
+        assert!(server.expired_sessions(1).is_empty());
+
+        let mut expired = server.expired_sessions(0);
+        expired.sort();
+        assert_eq!(expired, vec![1, 2]);
+        assert!(server.expired_sessions(0).is_empty());
+
+        // Client 2 is still interested in the rule of client 1.
+        server.end_session(1).unwrap();
+        assert!(server.internal.rules.contains_key(&names(1)));
+        assert!(server.internal.rules.contains_key(&names(2)));
+
+        server.end_session(2).unwrap();
+        assert!(server.interests.is_empty());
+        assert!(!server.internal.rules.contains_key(&names(1)));
+        assert!(!server.internal.rules.contains_key(&names(2)));
+    });
+}
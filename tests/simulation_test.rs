@@ -0,0 +1,841 @@
+use std::time::Duration;
+
+use declarative_dataflow::server::simulation::{Command, Simulation};
+use declarative_dataflow::server::{
+    Configuration, CreateAttribute, DeriveAttribute, Excise, Inspect, Interest, InterestGroup,
+    Limits, MemoryUsage, Register, Report, Request, TransactSync, Visibility,
+};
+use declarative_dataflow::sources::{Generated, Source};
+use declarative_dataflow::testing::{Distribution, Generator};
+use declarative_dataflow::timestamp::Time;
+use declarative_dataflow::{AttributeConfig, Datom, InputSemantics, Output, Plan, Rule, Value};
+use Value::{Eid, String};
+
+fn command(id: Option<u64>, requests: Vec<Request<String>>) -> Command {
+    Command {
+        client: 1,
+        id,
+        requests,
+    }
+}
+
+#[test]
+fn snapshot_markers() {
+    timely::execute_directly(move |worker| {
+        let mut simulation = Simulation::new(worker, Default::default());
+
+        let outputs = simulation.run(vec![
+            command(
+                Some(1),
+                vec![
+                    Request::CreateAttribute(CreateAttribute {
+                        name: ":name".to_string(),
+                        config: AttributeConfig::tx_time(InputSemantics::Raw),
+                        backfill: None,
+                    }),
+                    Request::Transact(vec![Datom::add(1, ":name", String("Dipper".to_string()))]),
+                ],
+            ),
+            command(
+                Some(2),
+                vec![
+                    Request::Register(Register {
+                        rules: vec![Rule::named("names", Plan::match_a(0, ":name", 1))],
+                        publish: vec!["names".to_string()],
+                    }),
+                    Request::Interest(Interest {
+                        name: "names".to_string(),
+                        snapshot: Some(true),
+                        ..Default::default()
+                    }),
+                ],
+            ),
+            command(
+                None,
+                vec![Request::Transact(vec![Datom::add(
+                    2,
+                    ":name",
+                    String("Mabel".to_string()),
+                )])],
+            ),
+        ]);
+
+        match outputs[0].as_slice() {
+            [Output::Ack(1, 1, 0)] => {}
+            other => panic!("Unexpected outputs {:?}", other),
+        }
+
+        // The snapshot arrives as a whole, at the time the interest
+        // was expressed, followed by its marker.
+        match outputs[1].as_slice() {
+            [Output::Ack(1, 2, 1), Output::QueryDiff(name, results), Output::InitialComplete(marked, Time::TxId(1))] =>
+            {
+                assert_eq!((name.as_str(), marked.as_str()), ("names", "names"));
+                assert_eq!(
+                    results,
+                    &vec![(vec![Eid(1), String("Dipper".to_string())], Time::TxId(1), 1)]
+                );
+            }
+            other => panic!("Unexpected outputs {:?}", other),
+        }
+
+        match outputs[2].as_slice() {
+            [Output::QueryDiff(_, results)] => {
+                assert_eq!(
+                    results,
+                    &vec![(vec![Eid(2), String("Mabel".to_string())], Time::TxId(2), 1)]
+                );
+            }
+            other => panic!("Unexpected outputs {:?}", other),
+        }
+    });
+}
+
+#[test]
+fn synchronous_acks() {
+    timely::execute_directly(move |worker| {
+        let mut simulation = Simulation::new(worker, Default::default());
+
+        let outputs = simulation.run(vec![
+            command(
+                None,
+                vec![
+                    Request::CreateAttribute(CreateAttribute {
+                        name: ":name".to_string(),
+                        config: AttributeConfig::tx_time(InputSemantics::Raw),
+                        backfill: None,
+                    }),
+                    Request::Register(Register {
+                        rules: vec![Rule::named("names", Plan::match_a(0, ":name", 1))],
+                        publish: vec!["names".to_string()],
+                    }),
+                    Request::Interest(Interest {
+                        name: "names".to_string(),
+                        ..Default::default()
+                    }),
+                ],
+            ),
+            command(
+                Some(3),
+                vec![Request::TransactSync(TransactSync {
+                    tx_data: vec![Datom::add(1, ":name", String("Dipper".to_string()))],
+                    visibility: Visibility::Incorporated,
+                })],
+            ),
+        ]);
+
+        // The acknowledgement follows the results it caused.
+        match outputs[1].as_slice() {
+            [Output::QueryDiff(_, results), Output::Ack(1, 3, 1)] => {
+                assert_eq!(
+                    results,
+                    &vec![(vec![Eid(1), String("Dipper".to_string())], Time::TxId(1), 1)]
+                );
+            }
+            other => panic!("Unexpected outputs {:?}", other),
+        }
+    });
+}
+
+#[test]
+fn causality_tokens() {
+    timely::execute_directly(move |worker| {
+        let mut simulation = Simulation::new(worker, Default::default());
+
+        let outputs = simulation.run(vec![
+            command(
+                Some(1),
+                vec![
+                    Request::CreateAttribute(CreateAttribute {
+                        name: ":name".to_string(),
+                        config: AttributeConfig::tx_time(InputSemantics::Raw),
+                        backfill: None,
+                    }),
+                    Request::Register(Register {
+                        rules: vec![Rule::named("names", Plan::match_a(0, ":name", 1))],
+                        publish: vec!["names".to_string()],
+                    }),
+                    Request::Transact(vec![Datom::add(1, ":name", String("Dipper".to_string()))]),
+                ],
+            ),
+            command(
+                None,
+                vec![Request::Interest(Interest {
+                    name: "names".to_string(),
+                    snapshot: Some(true),
+                    at_least: Some(2),
+                    ..Default::default()
+                })],
+            ),
+            command(
+                Some(2),
+                vec![Request::Transact(vec![Datom::add(
+                    2,
+                    ":name",
+                    String("Mabel".to_string()),
+                )])],
+            ),
+        ]);
+
+        // The interest is held back until the token has passed...
+        assert!(outputs[1].is_empty());
+
+        // ...and then includes the transaction it referenced.
+        match outputs[2].as_slice() {
+            [Output::Ack(1, 2, 2), Output::QueryDiff(_, results), Output::InitialComplete(_, _)] => {
+                assert_eq!(results.len(), 2);
+            }
+            other => panic!("Unexpected outputs {:?}", other),
+        }
+    });
+}
+
+#[test]
+fn causality_tokens_keep_request_ids() {
+    timely::execute_directly(move |worker| {
+        let mut simulation = Simulation::new(worker, Default::default());
+
+        let outputs = simulation.run(vec![
+            command(
+                Some(1),
+                vec![Request::CreateAttribute(CreateAttribute {
+                    name: ":name".to_string(),
+                    config: AttributeConfig::tx_time(InputSemantics::Raw),
+                    backfill: None,
+                })],
+            ),
+            command(
+                Some(2),
+                vec![Request::Interest(Interest {
+                    name: "unknown".to_string(),
+                    at_least: Some(2),
+                    ..Default::default()
+                })],
+            ),
+            command(
+                Some(3),
+                vec![Request::Transact(vec![Datom::add(
+                    1,
+                    ":name",
+                    String("Dipper".to_string()),
+                )])],
+            ),
+        ]);
+
+        // Held back interests are not acknowledged right away...
+        assert!(outputs[1].is_empty());
+
+        // ...and are answered under their original id once issued
+        // again.
+        let answers: Vec<&Output> = outputs[2]
+            .iter()
+            .filter(|out| match out {
+                Output::Ack(1, 2, _) | Output::Error(1, _, _, Some(2)) => true,
+                _ => false,
+            })
+            .collect();
+
+        match answers.as_slice() {
+            [Output::Error(1, _error, _, Some(2))] => {}
+            other => panic!("Unexpected outputs {:?}", other),
+        }
+    });
+}
+
+#[test]
+fn errors() {
+    timely::execute_directly(move |worker| {
+        let mut simulation = Simulation::new(worker, Default::default());
+
+        let outputs = simulation.handle(command(
+            Some(7),
+            vec![Request::Transact(vec![Datom::add(
+                1,
+                ":unknown",
+                String("Dipper".to_string()),
+            )])],
+        ));
+
+        // Failed commands are not acknowledged.
+        match outputs.as_slice() {
+            [Output::Error(1, _error, 0, Some(7))] => {}
+            other => panic!("Unexpected outputs {:?}", other),
+        }
+    });
+}
+
+#[test]
+fn backfills() {
+    timely::execute_directly(move |worker| {
+        let mut simulation = Simulation::new(worker, Default::default());
+
+        let backfill = Source::Generated(Generated {
+            name: ":age".to_string(),
+            generator: Generator::Numbers {
+                entities: 10,
+                values: 10,
+                distribution: Distribution::Uniform,
+            },
+            seed: 0,
+            initial: 10,
+            batch: 0,
+            interval: None,
+            rounds: Some(0),
+        });
+
+        let outputs = simulation.run(vec![
+            command(
+                Some(1),
+                vec![Request::CreateAttribute(CreateAttribute {
+                    name: ":age".to_string(),
+                    config: AttributeConfig::tx_time(InputSemantics::Raw),
+                    backfill: Some(backfill),
+                })],
+            ),
+            command(
+                Some(2),
+                vec![Request::Transact(vec![Datom::add(
+                    1,
+                    ":age",
+                    Value::Number(12),
+                )])],
+            ),
+        ]);
+
+        // Simulations run on logical time, which sources can't
+        // introduce data at...
+        match outputs[0].as_slice() {
+            [Output::Error(1, error, 0, Some(1))] => {
+                assert_eq!(error.category, "df.error.category/unsupported");
+                assert_eq!(
+                    error.message,
+                    "Attribute :age can't be backfilled on logical time."
+                );
+            }
+            other => panic!("Unexpected outputs {:?}", other),
+        }
+
+        // ...and the attribute isn't created without its backfill.
+        match outputs[1].as_slice() {
+            [Output::Error(1, error, 1, Some(2))] => {
+                assert_eq!(error.category, "df.error.category/not-found");
+            }
+            other => panic!("Unexpected outputs {:?}", other),
+        }
+    });
+}
+
+#[test]
+fn interest_groups() {
+    timely::execute_directly(move |worker| {
+        let mut simulation = Simulation::new(worker, Default::default());
+
+        let outputs = simulation.run(vec![
+            command(
+                Some(1),
+                vec![
+                    Request::CreateAttribute(CreateAttribute {
+                        name: ":name".to_string(),
+                        config: AttributeConfig::tx_time(InputSemantics::Raw),
+                        backfill: None,
+                    }),
+                    Request::Register(Register {
+                        rules: vec![Rule::named("names", Plan::match_a(0, ":name", 1))],
+                        publish: vec!["names".to_string()],
+                    }),
+                    Request::InterestGroup(InterestGroup {
+                        name: "group".to_string(),
+                        members: vec!["names".to_string()],
+                    }),
+                ],
+            ),
+            command(
+                Some(2),
+                vec![Request::Transact(vec![Datom::add(
+                    1,
+                    ":name",
+                    String("Dipper".to_string()),
+                )])],
+            ),
+            command(Some(3), vec![Request::Subscribe(":name".to_string())]),
+        ]);
+
+        // Groups are served by the same handlers as in the server
+        // binary...
+        match outputs[1].as_slice() {
+            [Output::Ack(1, 2, 1), Output::GroupDiff(name, member, results), Output::Commit(committed, Time::TxId(1))] =>
+            {
+                assert_eq!((name.as_str(), member.as_str()), ("group", "names"));
+                assert_eq!(committed, "group");
+                assert_eq!(
+                    results,
+                    &vec![(vec![Eid(1), String("Dipper".to_string())], Time::TxId(1), 1)]
+                );
+            }
+            other => panic!("Unexpected outputs {:?}", other),
+        }
+
+        // ...whereas requests concerning the server process are
+        // rejected.
+        match outputs[2].as_slice() {
+            [Output::Error(1, error, 2, Some(3))] => {
+                assert_eq!(error.category, "df.error.category/unsupported");
+            }
+            other => panic!("Unexpected outputs {:?}", other),
+        }
+    });
+}
+
+#[test]
+fn late_binding() {
+    timely::execute_directly(move |worker| {
+        let config = Configuration {
+            late_binding: true,
+            ..Default::default()
+        };
+        let mut simulation = Simulation::new(worker, config);
+
+        let outputs = simulation.run(vec![
+            command(
+                Some(1),
+                vec![
+                    Request::Register(Register {
+                        rules: vec![Rule::named("names", Plan::match_a(0, ":name", 1))],
+                        publish: vec!["names".to_string()],
+                    }),
+                    Request::Interest(Interest {
+                        name: "names".to_string(),
+                        ..Default::default()
+                    }),
+                ],
+            ),
+            command(
+                Some(2),
+                vec![
+                    Request::CreateAttribute(CreateAttribute {
+                        name: ":name".to_string(),
+                        config: AttributeConfig::tx_time(InputSemantics::Raw),
+                        backfill: None,
+                    }),
+                    Request::Transact(vec![Datom::add(1, ":name", String("Dipper".to_string()))]),
+                ],
+            ),
+        ]);
+
+        // The interest is held back until :name exists...
+        match outputs[0].as_slice() {
+            [Output::Ack(1, 1, 0)] => {}
+            other => panic!("Unexpected outputs {:?}", other),
+        }
+
+        // ...and bound as soon as it is created.
+        match outputs[1].as_slice() {
+            [Output::Ack(1, 2, 1), Output::QueryDiff(name, results)] => {
+                assert_eq!(name, "names");
+                assert_eq!(results.len(), 1);
+                assert_eq!(results[0].0, vec![Eid(1), String("Dipper".to_string())]);
+            }
+            other => panic!("Unexpected outputs {:?}", other),
+        }
+    });
+}
+
+#[test]
+fn hot_swap() {
+    timely::execute_directly(move |worker| {
+        let mut simulation = Simulation::new(worker, Default::default());
+
+        let outputs = simulation.run(vec![
+            command(
+                Some(1),
+                vec![
+                    Request::CreateAttribute(CreateAttribute {
+                        name: ":name".to_string(),
+                        config: AttributeConfig::tx_time(InputSemantics::Raw),
+                        backfill: None,
+                    }),
+                    Request::CreateAttribute(CreateAttribute {
+                        name: ":alias".to_string(),
+                        config: AttributeConfig::tx_time(InputSemantics::Raw),
+                        backfill: None,
+                    }),
+                    Request::Transact(vec![
+                        Datom::add(1, ":name", String("Dipper".to_string())),
+                        Datom::add(2, ":name", String("Mabel".to_string())),
+                        Datom::add(1, ":alias", String("Dipper".to_string())),
+                    ]),
+                ],
+            ),
+            command(
+                Some(2),
+                vec![
+                    Request::Register(Register {
+                        rules: vec![Rule::named("names", Plan::match_a(0, ":name", 1))],
+                        publish: vec!["names".to_string()],
+                    }),
+                    Request::Interest(Interest {
+                        name: "names".to_string(),
+                        ..Default::default()
+                    }),
+                ],
+            ),
+            command(
+                Some(3),
+                vec![Request::SwapRule(Rule::named(
+                    "names",
+                    Plan::match_a(0, ":alias", 1),
+                ))],
+            ),
+            command(
+                Some(4),
+                vec![Request::Transact(vec![
+                    Datom::add(3, ":alias", String("Stan".to_string())),
+                    Datom::add(4, ":name", String("Wendy".to_string())),
+                ])],
+            ),
+        ]);
+
+        match outputs[1].as_slice() {
+            [Output::Ack(1, 2, 1), Output::QueryDiff(_, results)] => assert_eq!(results.len(), 2),
+            other => panic!("Unexpected outputs {:?}", other),
+        }
+
+        // Once switched over, the client only learns about the
+        // differences between both versions...
+        match outputs[2].as_slice() {
+            [Output::Ack(1, 3, 2), Output::QueryDiff(name, results)] => {
+                assert_eq!(name, "names");
+                assert_eq!(results.len(), 1);
+                assert_eq!(results[0].0, vec![Eid(2), String("Mabel".to_string())]);
+                assert_eq!(results[0].2, -1);
+            }
+            other => panic!("Unexpected outputs {:?}", other),
+        }
+
+        // ...and is served by the new version from then on. Switching
+        // over took two commands of its own.
+        match outputs[3].as_slice() {
+            [Output::Ack(1, 4, 5), Output::QueryDiff(_, results)] => {
+                assert_eq!(
+                    results,
+                    &vec![(vec![Eid(3), String("Stan".to_string())], Time::TxId(5), 1)]
+                );
+            }
+            other => panic!("Unexpected outputs {:?}", other),
+        }
+    });
+}
+
+#[test]
+fn hibernation() {
+    timely::execute_directly(move |worker| {
+        let config = Configuration {
+            session_timeout: Some(Duration::from_secs(3600)),
+            hibernate_after: Some(Duration::from_secs(0)),
+            ..Default::default()
+        };
+        let mut simulation = Simulation::new(worker, config);
+
+        simulation.handle(command(
+            Some(1),
+            vec![
+                Request::CreateAttribute(CreateAttribute {
+                    name: ":name".to_string(),
+                    config: AttributeConfig::tx_time(InputSemantics::Raw),
+                    backfill: None,
+                }),
+                Request::Transact(vec![Datom::add(1, ":name", String("Dipper".to_string()))]),
+                Request::Register(Register {
+                    rules: vec![Rule::named("names", Plan::match_a(0, ":name", 1))],
+                    publish: vec!["names".to_string()],
+                }),
+                Request::Interest(Interest {
+                    name: "names".to_string(),
+                    ..Default::default()
+                }),
+            ],
+        ));
+
+        assert!(simulation.server.interests.contains_key("names"));
+
+        // The dataflow is torn down once its only subscriber is gone,
+        // even though the session lives on...
+        simulation.handle(command(None, vec![Request::Disconnect]));
+
+        assert!(!simulation.server.interests.contains_key("names"));
+        assert!(simulation.server.internal.rules.contains_key("names"));
+
+        // ...and implemented again for the next subscriber.
+        let outputs = simulation.handle(Command {
+            client: 2,
+            id: None,
+            requests: vec![Request::Interest(Interest {
+                name: "names".to_string(),
+                ..Default::default()
+            })],
+        });
+
+        match outputs.as_slice() {
+            [Output::QueryDiff(_, results)] => {
+                assert_eq!(results.len(), 1);
+                assert_eq!(results[0].0, vec![Eid(1), String("Dipper".to_string())]);
+            }
+            other => panic!("Unexpected outputs {:?}", other),
+        }
+    });
+}
+
+#[test]
+fn memory_budgets() {
+    timely::execute_directly(move |worker| {
+        let mut simulation = Simulation::new(worker, Default::default());
+
+        let outputs = simulation.handle(command(
+            Some(1),
+            vec![
+                Request::CreateAttribute(CreateAttribute {
+                    name: ":name".to_string(),
+                    config: AttributeConfig::tx_time(InputSemantics::Raw),
+                    backfill: None,
+                }),
+                Request::Transact(vec![Datom::add(1, ":name", String("Dipper".to_string()))]),
+                Request::Register(Register {
+                    rules: vec![Rule::named("names", Plan::match_a(0, ":name", 1))],
+                    publish: vec!["names".to_string()],
+                }),
+                Request::Interest(Interest {
+                    name: "names".to_string(),
+                    limits: Some(Limits {
+                        max_memory: Some(1),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                }),
+            ],
+        ));
+
+        // Results are delivered, until the dataflow holding them is
+        // found to exceed its budget.
+        match outputs.as_slice() {
+            [Output::Ack(1, 1, 0), Output::QueryDiff(_, _), Output::Error(1, error, _, None)] => {
+                assert_eq!(error.category, "df.error.category/interrupted");
+            }
+            other => panic!("Unexpected outputs {:?}", other),
+        }
+
+        assert!(!simulation.server.interests.contains_key("names"));
+    });
+}
+
+#[test]
+fn memory_budgets_across_workers() {
+    timely::execute_directly(move |worker| {
+        let mut simulation = Simulation::new(worker, Default::default());
+
+        simulation.handle(command(
+            Some(1),
+            vec![
+                Request::CreateAttribute(CreateAttribute {
+                    name: ":name".to_string(),
+                    config: AttributeConfig::tx_time(InputSemantics::Raw),
+                    backfill: None,
+                }),
+                Request::Transact(vec![Datom::add(1, ":name", String("Dipper".to_string()))]),
+                Request::Register(Register {
+                    rules: vec![Rule::named("names", Plan::match_a(0, ":name", 1))],
+                    publish: vec!["names".to_string()],
+                }),
+                Request::Interest(Interest {
+                    name: "names".to_string(),
+                    limits: Some(Limits {
+                        max_memory: Some(1 << 20),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                }),
+            ],
+        ));
+
+        // The indices of attributes read count against the budget.
+        match simulation.server.inspect(&Inspect::Memory) {
+            Ok(Report::Memory(stats)) => {
+                assert_eq!(stats.len(), 1);
+                assert!(stats[0].attributes.bytes > 0);
+            }
+            other => panic!("Unexpected report {:?}", other),
+        }
+
+        assert!(simulation.server.exceeded_budgets(0).is_empty());
+
+        // Memory held on other workers counts as well.
+        simulation.server.note_memory_usage(MemoryUsage {
+            worker: 1,
+            bytes: vec![("names".to_string(), 1 << 20)],
+        });
+
+        assert_eq!(
+            simulation.server.exceeded_budgets(0),
+            vec!["names".to_string()]
+        );
+    });
+}
+
+#[cfg(feature = "store")]
+#[test]
+fn stored_attributes() {
+    let path = std::env::temp_dir().join(format!("df-store-{}", std::process::id()));
+
+    timely::execute_directly(move |worker| {
+        let config = Configuration {
+            store: Some(path.to_string_lossy().into_owned()),
+            ..Default::default()
+        };
+        let mut simulation = Simulation::new(worker, config);
+        simulation
+            .server
+            .open_store(0)
+            .expect("failed to open store");
+
+        simulation.handle(command(
+            Some(1),
+            vec![
+                Request::CreateAttribute(CreateAttribute {
+                    name: ":name".to_string(),
+                    config: AttributeConfig {
+                        stored: true,
+                        ..AttributeConfig::tx_time(InputSemantics::Raw)
+                    },
+                    backfill: None,
+                }),
+                Request::Transact(vec![Datom::add(1, ":name", String("Dipper".to_string()))]),
+            ],
+        ));
+
+        // Nothing is indexed until queried...
+        assert!(!simulation
+            .server
+            .internal
+            .has_attribute(&":name".to_string()));
+
+        // ...at which point the attribute is hydrated from the store.
+        let outputs = simulation.handle(command(
+            Some(2),
+            vec![
+                Request::Register(Register {
+                    rules: vec![Rule::named("names", Plan::match_a(0, ":name", 1))],
+                    publish: vec!["names".to_string()],
+                }),
+                Request::Interest(Interest {
+                    name: "names".to_string(),
+                    ..Default::default()
+                }),
+            ],
+        ));
+
+        match outputs.as_slice() {
+            [Output::Ack(1, 2, 1), Output::QueryDiff(_, results)] => {
+                assert_eq!(results.len(), 1);
+                assert_eq!(results[0].0, vec![Eid(1), String("Dipper".to_string())]);
+            }
+            other => panic!("Unexpected outputs {:?}", other),
+        }
+    });
+}
+
+#[cfg(feature = "store")]
+#[test]
+fn stored_attributes_excised() {
+    let path = std::env::temp_dir().join(format!("df-store-excised-{}", std::process::id()));
+    let store = path.to_string_lossy().into_owned();
+
+    let setup = vec![
+        Request::CreateAttribute(CreateAttribute {
+            name: ":name".to_string(),
+            config: AttributeConfig {
+                stored: true,
+                ..AttributeConfig::tx_time(InputSemantics::Raw)
+            },
+            backfill: None,
+        }),
+        Request::Transact(vec![
+            Datom::add(1, ":name", String("Dipper".to_string())),
+            Datom::add(2, ":name", String("Mabel".to_string())),
+        ]),
+        Request::Excise(Excise {
+            entity: Some(Eid(1)),
+            attribute: Some(":name".to_string()),
+            value: None,
+        }),
+    ];
+
+    let names = vec![
+        Request::Register(Register {
+            rules: vec![Rule::named("names", Plan::match_a(0, ":name", 1))],
+            publish: vec!["names".to_string()],
+        }),
+        Request::Interest(Interest {
+            name: "names".to_string(),
+            ..Default::default()
+        }),
+    ];
+
+    timely::execute_directly(move |worker| {
+        let config = Configuration {
+            store: Some(store),
+            ..Default::default()
+        };
+        let mut simulation = Simulation::new(worker, config);
+        simulation
+            .server
+            .open_store(0)
+            .expect("failed to open store");
+
+        simulation.handle(command(Some(1), setup));
+
+        // Attributes are hydrated by anything reading them, not only
+        // by interests.
+        simulation.handle(command(
+            Some(2),
+            vec![
+                Request::Register(Register {
+                    rules: vec![Rule::named("named", Plan::match_a(0, ":name", 1))],
+                    publish: vec!["named".to_string()],
+                }),
+                Request::DeriveAttribute(DeriveAttribute {
+                    rule: "named".to_string(),
+                    name: ":named".to_string(),
+                    config: None,
+                }),
+            ],
+        ));
+
+        assert!(simulation
+            .server
+            .internal
+            .has_attribute(&":name".to_string()));
+    });
+
+    // Excised datoms are gone from the store, and don't come back
+    // when hydrating the attribute again after a restart.
+    timely::execute_directly(move |worker| {
+        let config = Configuration {
+            store: Some(path.to_string_lossy().into_owned()),
+            ..Default::default()
+        };
+        let mut simulation = Simulation::new(worker, config);
+        simulation
+            .server
+            .open_store(0)
+            .expect("failed to open store");
+
+        let outputs = simulation.handle(command(Some(3), names));
+
+        match outputs.as_slice() {
+            [Output::Ack(1, 3, 0), Output::QueryDiff(_, results)] => {
+                assert_eq!(results.len(), 1);
+                assert_eq!(results[0].0, vec![Eid(2), String("Mabel".to_string())]);
+            }
+            other => panic!("Unexpected outputs {:?}", other),
+        }
+    });
+}
@@ -0,0 +1,531 @@
+use std::sync::mpsc::channel;
+use std::time::Duration;
+
+use timely::dataflow::channels::pact::Pipeline;
+use timely::dataflow::operators::{Input, Operator, Probe};
+use timely::progress::frontier::AntichainRef;
+
+use declarative_dataflow::server::Limits;
+use declarative_dataflow::sinks::{Limit, Limiter, ProgressTracker, Transactional};
+use declarative_dataflow::timestamp::Time;
+use declarative_dataflow::{Output, ResultDiff, Value};
+use Value::Number;
+
+#[test]
+fn progress_tracker() {
+    let mut tracker = ProgressTracker::<u64>::new("q".to_string(), Some(2), true);
+
+    assert!(tracker.observe(AntichainRef::new(&[0])).is_empty());
+
+    match tracker.observe(AntichainRef::new(&[2])).as_slice() {
+        [Output::Progress(name, frontier)] => {
+            assert_eq!(name, "q");
+            assert_eq!(frontier, &vec![Time::TxId(2)]);
+        }
+        other => panic!("Unexpected outputs {:?}", other),
+    }
+
+    match tracker.observe(AntichainRef::new(&[3])).as_slice() {
+        [Output::InitialComplete(name, Time::TxId(2)), Output::Progress(_, frontier)] => {
+            assert_eq!(name, "q");
+            assert_eq!(frontier, &vec![Time::TxId(3)]);
+        }
+        other => panic!("Unexpected outputs {:?}", other),
+    }
+
+    assert!(tracker.observe(AntichainRef::new(&[3])).is_empty());
+
+    match tracker.observe(AntichainRef::new(&[])).as_slice() {
+        [Output::Progress(_, frontier)] => assert!(frontier.is_empty()),
+        other => panic!("Unexpected outputs {:?}", other),
+    }
+}
+
+#[test]
+fn transactional() {
+    timely::execute_directly(move |worker| {
+        let (send_results, results) = channel();
+
+        let (mut input, probe) = worker.dataflow::<u64, _, _>(|scope| {
+            let (input, stream) = scope.new_input::<(String, ResultDiff<u64>)>();
+
+            let batched = stream.transactional(Pipeline, "g".to_string());
+            let probe = batched.probe();
+
+            batched.sink(Pipeline, "Results", move |input| {
+                input.for_each(|_time, data| {
+                    for datum in data.iter() {
+                        send_results.send(datum.clone()).unwrap()
+                    }
+                });
+            });
+
+            (input, probe)
+        });
+
+        input.send(("b".to_string(), (vec![Number(2)], 0, 1)));
+        input.send(("a".to_string(), (vec![Number(1)], 0, 1)));
+        input.advance_to(1);
+        input.send(("a".to_string(), (vec![Number(1)], 1, -1)));
+        input.advance_to(2);
+        worker.step_while(|| probe.less_than(input.time()));
+
+        let outputs = results.try_iter().collect::<Vec<_>>();
+
+        match outputs.as_slice() {
+            [Output::GroupDiff(g0, a, a_diffs), Output::GroupDiff(_, b, b_diffs), Output::Commit(_, Time::TxId(0)), Output::GroupDiff(_, a1, a1_diffs), Output::Commit(g1, Time::TxId(1))] =>
+            {
+                assert_eq!((g0.as_str(), g1.as_str()), ("g", "g"));
+                assert_eq!((a.as_str(), b.as_str(), a1.as_str()), ("a", "b", "a"));
+                assert_eq!(a_diffs, &vec![(vec![Number(1)], Time::TxId(0), 1)]);
+                assert_eq!(b_diffs, &vec![(vec![Number(2)], Time::TxId(0), 1)]);
+                assert_eq!(a1_diffs, &vec![(vec![Number(1)], Time::TxId(1), -1)]);
+            }
+            other => panic!("Unexpected outputs {:?}", other),
+        }
+    });
+}
+
+#[test]
+fn transactional_update_times() {
+    timely::execute_directly(move |worker| {
+        let (send_results, results) = channel();
+
+        let (mut input, probe) = worker.dataflow::<u64, _, _>(|scope| {
+            let (input, stream) = scope.new_input::<(String, ResultDiff<u64>)>();
+
+            let batched = stream.transactional(Pipeline, "g".to_string());
+            let probe = batched.probe();
+
+            batched.sink(Pipeline, "Results", move |input| {
+                input.for_each(|_time, data| {
+                    for datum in data.iter() {
+                        send_results.send(datum.clone()).unwrap()
+                    }
+                });
+            });
+
+            (input, probe)
+        });
+
+        // Both updates arrive in a batch at time 0, but the second
+        // one belongs to the transaction at time 1.
+        input.send(("a".to_string(), (vec![Number(1)], 0, 1)));
+        input.send(("a".to_string(), (vec![Number(2)], 1, 1)));
+        input.advance_to(2);
+        worker.step_while(|| probe.less_than(input.time()));
+
+        let outputs = results.try_iter().collect::<Vec<_>>();
+
+        match outputs.as_slice() {
+            [Output::GroupDiff(_, _, diffs0), Output::Commit(_, Time::TxId(0)), Output::GroupDiff(_, _, diffs1), Output::Commit(_, Time::TxId(1))] =>
+            {
+                assert_eq!(diffs0, &vec![(vec![Number(1)], Time::TxId(0), 1)]);
+                assert_eq!(diffs1, &vec![(vec![Number(2)], Time::TxId(1), 1)]);
+            }
+            other => panic!("Unexpected outputs {:?}", other),
+        }
+    });
+}
+
+#[test]
+fn limiter() {
+    let limits = Limits {
+        max_runtime: None,
+        max_output: Some(3),
+        ..Default::default()
+    };
+    let mut limiter = Limiter::<u64>::new(limits, 1);
+
+    assert!(limiter.observe(2, AntichainRef::new(&[0])).is_ok());
+    assert!(limiter.observe(1, AntichainRef::new(&[1])).is_ok());
+    assert!(limiter.observe(1, AntichainRef::new(&[2])).is_err());
+
+    let limits = Limits {
+        max_runtime: Some(Duration::from_millis(0)),
+        max_output: None,
+        ..Default::default()
+    };
+    let mut limiter = Limiter::<u64>::new(limits, 1);
+
+    // Runtime limits only apply to the initial computation.
+    assert!(limiter.observe(1, AntichainRef::new(&[2])).is_ok());
+
+    std::thread::sleep(Duration::from_millis(1));
+    assert!(limiter.observe(1, AntichainRef::new(&[1])).is_err());
+}
+
+#[test]
+fn sort_by_fields() {
+    let mut results: Vec<ResultDiff<u64>> = vec![
+        (vec![Number(2), Number(1)], 0, 1),
+        (vec![Number(1), Number(3)], 1, -1),
+        (vec![Number(3), Number(1)], 0, 1),
+        (vec![Number(1), Number(3)], 0, 1),
+    ];
+
+    declarative_dataflow::sinks::sort_by_fields(&mut results, &[1]);
+
+    assert_eq!(
+        results,
+        vec![
+            (vec![Number(2), Number(1)], 0, 1),
+            (vec![Number(3), Number(1)], 0, 1),
+            (vec![Number(1), Number(3)], 0, 1),
+            (vec![Number(1), Number(3)], 1, -1),
+        ]
+    );
+}
+
+#[test]
+fn keyed_changes() {
+    use declarative_dataflow::sinks::KeyedChange::{Remove, Upsert};
+
+    let results: Vec<ResultDiff<u64>> = vec![
+        // Replaced within the batch.
+        (vec![Number(1), Number(20)], 1, 1),
+        (vec![Number(1), Number(10)], 1, -1),
+        // Added and removed again.
+        (vec![Number(2), Number(10)], 1, 1),
+        (vec![Number(2), Number(10)], 2, -1),
+        // Removed.
+        (vec![Number(3), Number(10)], 0, -1),
+        // Added.
+        (vec![Number(4), Number(10)], 0, 1),
+    ];
+
+    assert_eq!(
+        declarative_dataflow::sinks::keyed_changes(results, &[0]),
+        vec![
+            Upsert(vec![Number(1)], vec![Number(1), Number(20)]),
+            Remove(vec![Number(2)]),
+            Remove(vec![Number(3)]),
+            Upsert(vec![Number(4)], vec![Number(4), Number(10)]),
+        ]
+    );
+}
+
+#[cfg(feature = "serde_json")]
+#[test]
+fn json_values() {
+    use serde_json::{json, Value as JValue};
+
+    let uuid = Value::uuid_str("936DA01F9ABD4d9d80C702AF85C822A8");
+
+    assert_eq!(JValue::from(Value::Eid(12)), json!("12"));
+    assert_eq!(
+        JValue::from(Value::Instant(1552576166535)),
+        json!(1552576166535u64)
+    );
+    assert_eq!(
+        JValue::from(uuid),
+        json!("936da01f-9abd-4d9d-80c7-02af85c822a8")
+    );
+    assert_eq!(
+        JValue::from(Value::lookup_ref(":person/name", "Mabel")),
+        json!([":person/name", "Mabel"])
+    );
+    assert_eq!(
+        JValue::from(Value::geo_point(47.5, -8.25)),
+        json!([47.5, -8.25])
+    );
+}
+
+#[cfg(feature = "serde_json")]
+#[test]
+fn named() {
+    use declarative_dataflow::sinks::{Named, Sinkable, SinkingContext};
+    use timely::dataflow::ProbeHandle;
+
+    timely::execute_directly(move |worker| {
+        let (send_results, results) = channel();
+        let mut probe = ProbeHandle::new();
+
+        let mut input = worker.dataflow::<u64, _, _>(|scope| {
+            let (input, stream) = scope.new_input::<ResultDiff<u64>>();
+
+            let sink = Named {
+                fields: vec!["name".to_string(), "age".to_string()],
+            };
+
+            let context = SinkingContext {
+                name: "q".to_string(),
+                granularity: None,
+            };
+
+            sink.sink(&stream, Pipeline, &mut probe, context)
+                .unwrap()
+                .unwrap()
+                .probe_with(&mut probe)
+                .sink(Pipeline, "Results", move |input| {
+                    input.for_each(|_time, data| {
+                        for datum in data.iter() {
+                            send_results.send(datum.clone()).unwrap()
+                        }
+                    });
+                });
+
+            input
+        });
+
+        input.send((vec![Value::String("Mabel".to_string()), Number(12)], 0, 1));
+        input.advance_to(1);
+        worker.step_while(|| probe.less_than(input.time()));
+
+        match results.try_iter().collect::<Vec<_>>().as_slice() {
+            [Output::Json(name, object, Time::TxId(0), 1)] => {
+                assert_eq!(name, "q");
+                assert_eq!(object, &serde_json::json!({"name": "Mabel", "age": 12}));
+            }
+            other => panic!("Unexpected outputs {:?}", other),
+        }
+    });
+}
+
+#[cfg(feature = "serde_json")]
+#[test]
+fn assoc_in() {
+    use declarative_dataflow::sinks::{AssocIn, Sinkable, SinkingContext};
+    use timely::dataflow::ProbeHandle;
+
+    timely::execute_directly(move |worker| {
+        let (send_results, results) = channel();
+        let mut probe = ProbeHandle::new();
+
+        let mut input = worker.dataflow::<u64, _, _>(|scope| {
+            let (input, stream) = scope.new_input::<ResultDiff<u64>>();
+
+            let sink = AssocIn {
+                stateful: Some(1),
+                cardinality_one: vec!["name".to_string()],
+            };
+
+            let context = SinkingContext {
+                name: "q".to_string(),
+                granularity: None,
+            };
+
+            sink.sink(&stream, Pipeline, &mut probe, context)
+                .unwrap()
+                .unwrap()
+                .probe_with(&mut probe)
+                .sink(Pipeline, "Results", move |input| {
+                    input.for_each(|_time, data| {
+                        for datum in data.iter() {
+                            send_results.send(datum.clone()).unwrap()
+                        }
+                    });
+                });
+
+            input
+        });
+
+        let path = |a: &str, v: Value| vec![Value::Eid(1), Value::aid(a), v];
+
+        input.send((path("name", Value::from("Mabel")), 0, 1));
+        input.send((path("age", Number(12)), 0, 1));
+        input.advance_to(1);
+        worker.step_while(|| probe.less_than(input.time()));
+
+        match results.try_iter().collect::<Vec<_>>().as_slice() {
+            [Output::Json(_, object, Time::TxId(0), 1)] => {
+                let expected = serde_json::json!({"name": "Mabel", "age": 12});
+                assert_eq!(object, &expected);
+            }
+            other => panic!("Unexpected outputs {:?}", other),
+        }
+
+        input.send((path("name", Value::from("Dipper")), 1, 1));
+        input.send((path("age", Number(12)), 1, -1));
+        input.advance_to(2);
+        worker.step_while(|| probe.less_than(input.time()));
+
+        match results.try_iter().collect::<Vec<_>>().as_slice() {
+            [Output::Json(_, object, Time::TxId(1), 1)] => {
+                let expected = serde_json::json!({"name": "Dipper"});
+                assert_eq!(object, &expected);
+            }
+            other => panic!("Unexpected outputs {:?}", other),
+        }
+
+        // Cardinality one attributes resolve to their latest value,
+        // which retracting a value it replaced doesn't affect.
+        input.send((path("name", Value::from("Mabel")), 2, -1));
+        input.advance_to(3);
+        worker.step_while(|| probe.less_than(input.time()));
+
+        match results.try_iter().collect::<Vec<_>>().as_slice() {
+            [Output::Json(_, object, Time::TxId(2), 1)] => {
+                let expected = serde_json::json!({"name": "Dipper"});
+                assert_eq!(object, &expected);
+            }
+            other => panic!("Unexpected outputs {:?}", other),
+        }
+    });
+}
+
+#[test]
+fn format() {
+    use declarative_dataflow::sinks::{format_fields, format_tuple, Format};
+
+    let formats = vec![
+        Format::IsoInstants,
+        Format::StringEids,
+        Format::Rename("age".to_string(), "years".to_string()),
+    ];
+
+    assert_eq!(
+        format_tuple(
+            &formats,
+            vec![Value::Eid(7), Value::Instant(951_827_696_789), Number(12)]
+        ),
+        vec![
+            Value::String("7".to_string()),
+            Value::String("2000-02-29T12:34:56.789Z".to_string()),
+            Number(12),
+        ]
+    );
+
+    let mut fields = vec!["name".to_string(), "age".to_string()];
+    format_fields(&formats, &mut fields);
+    assert_eq!(fields, vec!["name".to_string(), "years".to_string()]);
+}
+
+#[test]
+fn limit() {
+    timely::execute_directly(move |worker| {
+        let (send_results, results) = channel();
+        let (send_errors, errors) = channel();
+
+        let (mut input, probe) = worker.dataflow::<u64, _, _>(|scope| {
+            let (input, stream) = scope.new_input::<ResultDiff<u64>>();
+
+            let limits = Limits {
+                max_output: Some(3),
+                ..Default::default()
+            };
+            let limiter = Limiter::new(limits, 0);
+
+            let limited = stream.limit(Pipeline, Some(limiter), move |error| {
+                send_errors.send(error).unwrap()
+            });
+            let probe = limited.probe();
+
+            limited.sink(Pipeline, "Results", move |input| {
+                input.for_each(|_time, data| {
+                    for datum in data.iter() {
+                        send_results.send(datum.clone()).unwrap()
+                    }
+                });
+            });
+
+            (input, probe)
+        });
+
+        input.send((vec![Number(1)], 0, 1));
+        input.send((vec![Number(2)], 0, 1));
+        input.advance_to(1);
+        worker.step_while(|| probe.less_than(input.time()));
+
+        // A batch that would exceed the limit is not forwarded at all.
+        input.send((vec![Number(3)], 1, 1));
+        input.send((vec![Number(4)], 1, 1));
+        input.advance_to(2);
+        worker.step_while(|| probe.less_than(input.time()));
+
+        input.send((vec![Number(5)], 2, 1));
+        input.advance_to(3);
+        worker.step_while(|| probe.less_than(input.time()));
+
+        assert_eq!(
+            results.try_iter().collect::<Vec<_>>(),
+            vec![(vec![Number(1)], 0, 1), (vec![Number(2)], 0, 1)]
+        );
+        assert_eq!(errors.try_iter().count(), 1);
+    });
+}
+
+#[cfg(feature = "serde_json")]
+#[test]
+fn backpressure() {
+    use std::collections::VecDeque;
+
+    use declarative_dataflow::server::backpressure::{Backpressure, Overflow, OverflowPolicy};
+
+    let diff = |name: &str, n: i64, t: u64| {
+        Output::QueryDiff(name.to_string(), vec![(vec![Number(n)], Time::TxId(t), 1)])
+    };
+
+    // Slow clients are disconnected by default.
+    let backpressure = Backpressure {
+        max_pending: 2,
+        policy: OverflowPolicy::Disconnect,
+    };
+    let mut queue = VecDeque::new();
+
+    assert_eq!(
+        backpressure.enqueue(&mut queue, diff("a", 1, 0), 1, 0),
+        Overflow::Within(vec![])
+    );
+    assert_eq!(
+        backpressure.enqueue(&mut queue, diff("a", 2, 1), 1, 1),
+        Overflow::Within(vec![])
+    );
+    assert_eq!(
+        backpressure.enqueue(&mut queue, diff("a", 3, 2), 1, 2),
+        Overflow::Exceeded
+    );
+
+    // Coalescing merges pending diffs on the same query.
+    let backpressure = Backpressure {
+        max_pending: 2,
+        policy: OverflowPolicy::Coalesce,
+    };
+    let mut queue = VecDeque::new();
+
+    backpressure.enqueue(&mut queue, diff("a", 1, 0), 1, 0);
+    backpressure.enqueue(&mut queue, Output::Ack(1, 7, 0), 1, 0);
+    backpressure.enqueue(&mut queue, diff("a", 2, 1), 1, 1);
+
+    assert_eq!(
+        backpressure.enqueue(&mut queue, diff("a", 3, 1), 1, 1),
+        Overflow::Within(vec![])
+    );
+
+    match queue.iter().collect::<Vec<_>>().as_slice() {
+        [Output::QueryDiff(_, results), Output::Ack(1, 7, 0)] => {
+            assert_eq!(
+                results,
+                &vec![
+                    (vec![Number(1)], Time::TxId(0), 1),
+                    (vec![Number(2)], Time::TxId(1), 1),
+                    (vec![Number(3)], Time::TxId(1), 1),
+                ]
+            );
+        }
+        other => panic!("Unexpected outputs {:?}", other),
+    }
+
+    // Pausing drops the subscription responsible for most outputs.
+    let backpressure = Backpressure {
+        max_pending: 2,
+        policy: OverflowPolicy::Pause,
+    };
+    let mut queue = VecDeque::new();
+
+    backpressure.enqueue(&mut queue, diff("a", 1, 0), 1, 0);
+    backpressure.enqueue(&mut queue, diff("b", 1, 0), 1, 0);
+
+    assert_eq!(
+        backpressure.enqueue(&mut queue, diff("b", 2, 1), 1, 1),
+        Overflow::Within(vec!["b".to_string()])
+    );
+
+    match queue.iter().collect::<Vec<_>>().as_slice() {
+        [Output::QueryDiff(name, _), Output::Error(1, error, 1, None)] => {
+            assert_eq!(name, "a");
+            assert_eq!(error.category, "df.error.category/interrupted");
+        }
+        other => panic!("Unexpected outputs {:?}", other),
+    }
+}
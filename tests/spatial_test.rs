@@ -0,0 +1,141 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::mpsc::channel;
+
+use declarative_dataflow::plan::{Region, Within};
+use declarative_dataflow::server::Server;
+use declarative_dataflow::{Aid, AttributeConfig, Datom, InputSemantics, Plan, Rule, Value};
+use Value::Eid;
+
+/// Looks for :location values within the region after each
+/// transaction, both with and without a spatial index, and checks
+/// the entities found at that point.
+fn run(region: Region, transactions: Vec<Vec<Datom<Aid>>>, expectations: Vec<Vec<u64>>) {
+    for spatial in [false, true].iter().cloned() {
+        let region = region.clone();
+        let transactions = transactions.clone();
+        let expectations = expectations.clone();
+
+        timely::execute_directly(move |worker| {
+            let mut server = Server::<Aid, u64, ()>::new(Default::default());
+            let (send_results, results) = channel();
+
+            let plan = Plan::Within(Within {
+                variable: 1,
+                region,
+                plan: Box::new(Plan::match_a(0, ":location", 1)),
+            });
+
+            worker.dataflow::<u64, _, _>(|scope| {
+                let config = AttributeConfig {
+                    spatial,
+                    ..AttributeConfig::tx_time(InputSemantics::Raw)
+                };
+
+                server.create_attribute(scope, ":location", config).unwrap();
+
+                server
+                    .test_single(scope, Rule::named("nearby", plan))
+                    .inspect(move |x| send_results.send((x.0[0].clone(), x.2)).unwrap())
+                    .probe_with(&mut server.probe);
+            });
+
+            let mut found = BTreeMap::new();
+
+            for (tx, (tx_data, expected)) in transactions
+                .into_iter()
+                .zip(expectations.into_iter())
+                .enumerate()
+            {
+                server.transact(tx_data, 0, 0).unwrap();
+                server.advance_domain(None, tx as u64 + 1).unwrap();
+                worker.step_while(|| server.is_any_outdated());
+
+                while let Ok((e, diff)) = results.try_recv() {
+                    *found.entry(e).or_insert(0) += diff;
+                }
+
+                let current: BTreeSet<Value> = found
+                    .iter()
+                    .filter(|(_e, count)| **count != 0)
+                    .map(|(e, _count)| e.clone())
+                    .collect();
+
+                let expected: BTreeSet<Value> = expected.into_iter().map(Eid).collect();
+
+                assert_eq!(current, expected, "spatial index: {}", spatial);
+            }
+        });
+    }
+}
+
+fn places() -> Vec<Datom<Aid>> {
+    vec![
+        // Berlin
+        Datom::add(1, ":location", Value::geo_point(52.52, 13.405)),
+        // Potsdam
+        Datom::add(2, ":location", Value::geo_point(52.3906, 13.0645)),
+        // Munich
+        Datom::add(3, ":location", Value::geo_point(48.1351, 11.582)),
+        // Suva, Fiji
+        Datom::add(4, ":location", Value::geo_point(-18.1416, 178.4419)),
+        // Apia, Samoa
+        Datom::add(5, ":location", Value::geo_point(-13.8507, -171.7514)),
+    ]
+}
+
+#[test]
+fn within_radius() {
+    let berlin = Value::geo_point(52.52, 13.405);
+
+    run(
+        Region::WithinRadius(berlin.clone(), 10_000),
+        vec![places()],
+        vec![vec![1]],
+    );
+    run(
+        Region::WithinRadius(berlin.clone(), 50_000),
+        vec![places()],
+        vec![vec![1, 2]],
+    );
+    run(
+        Region::WithinRadius(berlin, 600_000),
+        vec![places()],
+        vec![vec![1, 2, 3]],
+    );
+}
+
+#[test]
+fn within_bbox() {
+    run(
+        Region::WithinBbox(Value::geo_point(47.0, 5.0), Value::geo_point(55.0, 15.0)),
+        vec![places()],
+        vec![vec![1, 2, 3]],
+    );
+    run(
+        Region::WithinBbox(
+            Value::geo_point(-20.0, 170.0),
+            Value::geo_point(-10.0, -170.0),
+        ),
+        vec![places()],
+        vec![vec![4, 5]],
+    );
+}
+
+#[test]
+fn moving_points() {
+    let berlin = Value::geo_point(52.52, 13.405);
+
+    run(
+        Region::WithinRadius(berlin, 50_000),
+        vec![
+            places(),
+            vec![
+                Datom::retract(3, ":location", Value::geo_point(48.1351, 11.582)),
+                Datom::add(3, ":location", Value::geo_point(52.5, 13.4)),
+                Datom::retract(2, ":location", Value::geo_point(52.3906, 13.0645)),
+                Datom::add(2, ":location", Value::geo_point(51.3397, 12.3731)),
+            ],
+        ],
+        vec![vec![1, 2], vec![1, 3]],
+    );
+}
@@ -0,0 +1,105 @@
+use declarative_dataflow::frontends::sql;
+use declarative_dataflow::plan::{Aggregate, AggregationFn, Filter, Join, Predicate, Project};
+use declarative_dataflow::{Aid, Plan, Value};
+use Value::Number;
+
+#[test]
+fn compile_select() {
+    let plan: Plan<Aid> = sql::parse("SELECT p.name FROM person p WHERE p.age >= 18").unwrap();
+
+    assert_eq!(
+        plan,
+        Plan::Project(Project {
+            variables: vec![2],
+            plan: Box::new(Plan::Filter(Filter {
+                variables: vec![3],
+                predicate: Predicate::GTE,
+                plan: Box::new(Plan::Join(Join {
+                    variables: vec![1],
+                    left_plan: Box::new(Plan::match_a(1, ":person/name", 2)),
+                    right_plan: Box::new(Plan::match_a(1, ":person/age", 3)),
+                })),
+                constants: vec![None, Some(Number(18))],
+            })),
+        })
+    );
+}
+
+#[test]
+fn compile_group_by() {
+    let aggregate = Plan::Aggregate(Aggregate {
+        variables: vec![2, 1],
+        plan: Box::new(Plan::Project(Project {
+            variables: vec![2, 1],
+            plan: Box::new(Plan::Join(Join {
+                variables: vec![1],
+                left_plan: Box::new(Plan::match_a(1, ":customer/name", 2)),
+                right_plan: Box::new(Plan::match_a(3, ":orders/customer", 1)),
+            })),
+        })),
+        aggregation_fns: vec![AggregationFn::COUNT],
+        key_variables: vec![2],
+        aggregation_variables: vec![1],
+        with_variables: vec![],
+        key_universe: None,
+        defaults: vec![],
+    });
+
+    let plan: Plan<Aid> = sql::parse(
+        "SELECT c.name, COUNT(*) FROM customer c JOIN orders o ON o.customer = c.id GROUP BY c.name",
+    )
+    .unwrap();
+
+    assert_eq!(plan, aggregate);
+
+    let plan: Plan<Aid> = sql::parse(
+        "SELECT c.name, COUNT(*) FROM customer c JOIN orders o ON o.customer = c.id
+         GROUP BY c.name HAVING COUNT(*) > 1",
+    )
+    .unwrap();
+
+    assert_eq!(
+        plan,
+        Plan::Filter(Filter {
+            variables: vec![1],
+            predicate: Predicate::GT,
+            plan: Box::new(aggregate),
+            constants: vec![None, Some(Number(1))],
+        })
+    );
+}
+
+#[test]
+fn compile_relations() {
+    let plan: Plan<Aid> =
+        sql::parse("SELECT e.dst FROM edges AS e(src, dst) WHERE e.src = 'a'").unwrap();
+
+    assert_eq!(
+        plan,
+        Plan::Project(Project {
+            variables: vec![2],
+            plan: Box::new(Plan::Filter(Filter {
+                variables: vec![1],
+                predicate: Predicate::EQ,
+                plan: Box::new(Plan::NameExpr(vec![1, 2], "edges".to_string())),
+                constants: vec![None, Some(Value::String("a".to_string()))],
+            })),
+        })
+    );
+}
+
+#[test]
+fn reject_invalid_queries() {
+    let invalid = vec![
+        "SELECT * FROM person",
+        "SELECT name FROM person p, company c",
+        "SELECT p.name, COUNT(*) FROM person p",
+        "SELECT p.name FROM person p WHERE p.age > 1 OR p.age < 0",
+        "SELECT p.name FROM person p ORDER BY p.name",
+        "SELECT p.name FROM person p WHERE",
+    ];
+
+    for query in invalid.iter() {
+        assert!(sql::parse::<Aid>(query).is_err(), "{}", query);
+    }
+}
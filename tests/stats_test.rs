@@ -0,0 +1,102 @@
+use std::collections::HashMap;
+use std::sync::mpsc::channel;
+
+use declarative_dataflow::server::{Register, Server, ATTRIBUTE_STATS, RULE_STATS};
+use declarative_dataflow::{Aid, AttributeConfig, Datom, InputSemantics, Plan, Rule, Value};
+use Value::Number;
+
+// Accumulates a stream of diffs into the current set of tuples.
+fn accumulate(diffs: Vec<(Vec<Value>, u64, isize)>) -> Vec<Vec<Value>> {
+    let mut counts: HashMap<Vec<Value>, isize> = HashMap::new();
+    for (tuple, _t, diff) in diffs.into_iter() {
+        *counts.entry(tuple).or_insert(0) += diff;
+    }
+
+    let mut tuples: Vec<Vec<Value>> = counts
+        .into_iter()
+        .filter(|(_, count)| *count > 0)
+        .map(|(tuple, _)| tuple)
+        .collect();
+    tuples.sort();
+    tuples
+}
+
+#[test]
+fn statistics() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, ()>::new(Default::default());
+        let (send_attributes, attributes) = channel();
+        let (send_rules, rules) = channel();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .create_attribute(
+                    scope,
+                    ":name",
+                    AttributeConfig::tx_time(InputSemantics::Raw),
+                )
+                .unwrap();
+        });
+
+        server
+            .register(Register {
+                rules: vec![Rule::named("names", Plan::match_a(0, ":name", 1))],
+                publish: vec!["names".to_string()],
+            })
+            .unwrap();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .interest("names".to_string(), scope)
+                .unwrap()
+                .probe_with(&mut server.probe);
+        });
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .interest(ATTRIBUTE_STATS.to_string(), scope)
+                .unwrap()
+                .inspect(move |x| send_attributes.send(x.clone()).unwrap())
+                .probe_with(&mut server.probe);
+
+            server
+                .interest(RULE_STATS.to_string(), scope)
+                .unwrap()
+                .inspect(move |x| send_rules.send(x.clone()).unwrap())
+                .probe_with(&mut server.probe);
+        });
+
+        server
+            .transact(
+                vec![
+                    Datom::add(1, ":name", Value::String("Dipper".to_string())),
+                    Datom::add(1, ":name", Value::String("Mason".to_string())),
+                    Datom::add(2, ":name", Value::String("Mabel".to_string())),
+                ],
+                0,
+                0,
+            )
+            .unwrap();
+        server.advance_domain(None, 1).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        let attributes: Vec<_> = attributes.try_iter().collect();
+        let rules: Vec<_> = rules.try_iter().collect();
+
+        for (stats, name) in vec![
+            (attributes, Value::aid(":name")),
+            (rules, Value::aid("names")),
+        ] {
+            match accumulate(stats).as_slice() {
+                [tuple] => {
+                    assert_eq!(&tuple[0..3], &[name, Number(3), Number(2)][..]);
+                    if let Value::Instant(_) = tuple[3] {
+                    } else {
+                        panic!("Expected an instant, got {:?}", tuple[3]);
+                    }
+                }
+                other => panic!("Unexpected statistics {:?}", other),
+            }
+        }
+    });
+}
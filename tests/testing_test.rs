@@ -0,0 +1,155 @@
+use std::sync::mpsc::channel;
+
+use declarative_dataflow::plan::{Aggregate, AggregationFn, Project};
+use declarative_dataflow::server::Server;
+use declarative_dataflow::testing::{
+    into_datoms, Dataset, Distribution, Generator, MAX_SKEWED_VALUES,
+};
+use declarative_dataflow::{Aid, AttributeConfig, InputSemantics, Plan, Rational32, Rule, Value};
+use Value::Number;
+
+fn numbers(distribution: Distribution) -> Generator {
+    Generator::Numbers {
+        entities: 1000,
+        values: 100,
+        distribution,
+    }
+}
+
+#[test]
+fn deterministic() {
+    let mut first = Dataset::new(numbers(Distribution::Uniform), 42).unwrap();
+    let mut second = Dataset::new(numbers(Distribution::Uniform), 42).unwrap();
+    let mut other = Dataset::new(numbers(Distribution::Uniform), 43).unwrap();
+
+    let loaded = first.load(100);
+    assert_eq!(loaded, second.load(100));
+    assert_ne!(loaded, other.load(100));
+
+    assert_eq!(first.update(10), second.update(10));
+}
+
+#[test]
+fn zipf() {
+    let mut uniform = Dataset::new(numbers(Distribution::Uniform), 0).unwrap();
+    let mut skewed = Dataset::new(numbers(Distribution::Zipf(Rational32::new(3, 2))), 0).unwrap();
+
+    let most_frequent = |updates: Vec<((Value, Value), isize)>| {
+        updates
+            .iter()
+            .filter(|((_e, v), _diff)| *v == Number(0))
+            .count()
+    };
+
+    // The most frequent value makes up about 1/100th of uniform
+    // values, but about 40% of values with an exponent of 1.5.
+    assert!(most_frequent(uniform.load(1000)) < 50);
+    assert!(most_frequent(skewed.load(1000)) > 250);
+}
+
+#[test]
+fn invalid_generators() {
+    let empty = Generator::Numbers {
+        entities: 0,
+        values: 100,
+        distribution: Distribution::Uniform,
+    };
+
+    let huge = Generator::Graph {
+        nodes: MAX_SKEWED_VALUES + 1,
+        distribution: Distribution::Zipf(Rational32::new(1, 1)),
+    };
+
+    assert!(Dataset::new(empty, 0).is_err());
+    assert!(Dataset::new(huge, 0).is_err());
+
+    // Uniform distributions don't keep per-value state.
+    let uniform = Generator::Graph {
+        nodes: std::u64::MAX,
+        distribution: Distribution::Uniform,
+    };
+
+    assert!(Dataset::new(uniform, 0).is_ok());
+}
+
+#[test]
+fn updates() {
+    let mut dataset = Dataset::new(
+        Generator::Graph {
+            nodes: 100,
+            distribution: Distribution::Uniform,
+        },
+        7,
+    )
+    .unwrap();
+
+    let loaded = dataset.load(500);
+    assert_eq!(loaded.len(), 500);
+
+    let updates = dataset.update(50);
+    assert_eq!(updates.len(), 100);
+    assert_eq!(dataset.live().len(), 500);
+
+    // Each update retracts a previously present edge.
+    for (tuple, diff) in updates.iter() {
+        if *diff == -1 {
+            assert!(loaded.iter().any(|(other, _)| other == tuple));
+        }
+    }
+}
+
+#[test]
+fn transact_generated() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, u64>::new(Default::default());
+        let (send_results, results) = channel();
+
+        let (e, v) = (1, 2);
+        let plan = Plan::Aggregate(Aggregate {
+            variables: vec![e],
+            plan: Box::new(Plan::Project(Project {
+                variables: vec![e],
+                plan: Box::new(Plan::match_a(e, ":age", v)),
+            })),
+            aggregation_fns: vec![AggregationFn::COUNT],
+            key_variables: vec![],
+            aggregation_variables: vec![e],
+            with_variables: vec![],
+            key_universe: None,
+            defaults: vec![],
+        });
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .create_attribute(scope, ":age", AttributeConfig::tx_time(InputSemantics::Raw))
+                .unwrap();
+
+            server
+                .test_single(scope, Rule::named("count", plan))
+                .inspect(move |x| {
+                    send_results.send((x.0.clone(), x.2)).unwrap();
+                });
+        });
+
+        let mut dataset = Dataset::new(numbers(Distribution::Uniform), 0).unwrap();
+        let a: Aid = ":age".to_string();
+
+        server
+            .transact(into_datoms(dataset.load(1000), &a), 0, 0)
+            .unwrap();
+        server.advance_domain(None, 1).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        assert_eq!(results.recv().unwrap(), (vec![Number(1000)], 1));
+
+        // Updates replace values, leaving the number of entities
+        // unchanged.
+        server
+            .transact(into_datoms(dataset.update(100), &a), 0, 0)
+            .unwrap();
+        server.advance_domain(None, 2).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        assert!(results.try_recv().is_err());
+    });
+}
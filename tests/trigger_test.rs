@@ -0,0 +1,199 @@
+use std::sync::mpsc::channel;
+
+use declarative_dataflow::plan::{PlanBuilder, Predicate};
+use declarative_dataflow::server::{DatomTemplate, InsertInto, Register, RegisterTrigger, Server};
+use declarative_dataflow::server::{TemplateValue, TriggerEvent};
+use declarative_dataflow::{Aid, AttributeConfig, Datom, InputSemantics, Plan, Rule, Value};
+use Value::{Bool, Number};
+
+#[test]
+fn triggers() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, ()>::new(Default::default());
+        let (send_results, results) = channel();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            for name in [":temperature", ":alert/raised"].iter() {
+                server
+                    .create_attribute(scope, *name, AttributeConfig::tx_time(InputSemantics::Raw))
+                    .unwrap();
+            }
+        });
+
+        server
+            .register(Register {
+                rules: vec![
+                    Rule::named(
+                        "overheated",
+                        PlanBuilder::match_a(0, ":temperature", 1)
+                            .filter_value(Predicate::GT, 1, Number(100))
+                            .build()
+                            .unwrap(),
+                    ),
+                    Rule::named("alerts", Plan::match_a(0, ":alert/raised", 1)),
+                ],
+                publish: vec!["overheated".to_string(), "alerts".to_string()],
+            })
+            .unwrap();
+
+        let raise = |name: &str, rule: &str| RegisterTrigger {
+            name: name.to_string(),
+            rule: rule.to_string(),
+            on: TriggerEvent::Appear,
+            template: vec![DatomTemplate {
+                e: TemplateValue::Field(0),
+                a: ":alert/raised".to_string(),
+                v: TemplateValue::Constant(Bool(true)),
+                retract: false,
+            }],
+        };
+
+        worker
+            .dataflow::<u64, _, _>(|scope| {
+                server.register_trigger(scope, raise("raise-alert", "overheated"))
+            })
+            .unwrap();
+
+        // Triggers may not feed into their own rule.
+        assert!(worker
+            .dataflow::<u64, _, _>(|scope| server.register_trigger(scope, raise("loop", "alerts")))
+            .is_err());
+
+        // Triggers have to fire on registered rules.
+        assert!(worker
+            .dataflow::<u64, _, _>(|scope| server.register_trigger(scope, raise("lost", "unknown")))
+            .is_err());
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .interest("alerts".to_string(), scope)
+                .unwrap()
+                .inspect(move |x| send_results.send(x.clone()).unwrap())
+                .probe_with(&mut server.probe);
+        });
+
+        server
+            .transact(
+                vec![
+                    Datom::add(1, ":temperature", Number(80)),
+                    Datom::add(2, ":temperature", Number(120)),
+                ],
+                0,
+                0,
+            )
+            .unwrap();
+        server.advance_domain(None, 1).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        let fired = server.fired_transactions();
+        assert_eq!(
+            fired,
+            vec![vec![Datom::add(2, ":alert/raised", Bool(true))]]
+        );
+
+        for tx_data in fired {
+            server.transact(tx_data, 0, 0).unwrap();
+        }
+        server.advance_domain(None, 2).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        assert!(server.fired_transactions().is_empty());
+        assert_eq!(
+            results.try_iter().collect::<Vec<_>>(),
+            vec![(vec![Value::Eid(2), Bool(true)], 1, 1)]
+        );
+    });
+}
+
+#[test]
+fn insert_into() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, ()>::new(Default::default());
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            for name in [":temperature", ":overheated"].iter() {
+                server
+                    .create_attribute(scope, *name, AttributeConfig::tx_time(InputSemantics::Raw))
+                    .unwrap();
+            }
+        });
+
+        server
+            .register(Register {
+                rules: vec![Rule::named(
+                    "overheated",
+                    PlanBuilder::match_a(0, ":temperature", 1)
+                        .filter_value(Predicate::GT, 1, Number(100))
+                        .build()
+                        .unwrap(),
+                )],
+                publish: vec!["overheated".to_string()],
+            })
+            .unwrap();
+
+        let insert = |name: &str, attribute: &str| InsertInto {
+            name: name.to_string(),
+            rule: "overheated".to_string(),
+            attribute: attribute.to_string(),
+        };
+
+        // Insertions may not feed into their own rule.
+        assert!(
+            worker
+                .dataflow::<u64, _, _>(
+                    |scope| server.insert_into(scope, insert("loop", ":temperature"))
+                )
+                .is_err()
+        );
+
+        worker
+            .dataflow::<u64, _, _>(|scope| server.insert_into(scope, insert("etl", ":overheated")))
+            .unwrap();
+
+        server
+            .transact(
+                vec![
+                    Datom::add(1, ":temperature", Number(80)),
+                    Datom::add(2, ":temperature", Number(120)),
+                ],
+                0,
+                0,
+            )
+            .unwrap();
+        server.advance_domain(None, 1).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        assert_eq!(
+            server.fired_transactions(),
+            vec![vec![Datom::add(2, ":overheated", Number(120))]]
+        );
+
+        // Retractions are inserted as retractions.
+        server
+            .transact(
+                vec![Datom(
+                    Value::Eid(2),
+                    ":temperature".to_string(),
+                    Number(120),
+                    None,
+                    -1,
+                )],
+                0,
+                0,
+            )
+            .unwrap();
+        server.advance_domain(None, 2).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        assert_eq!(
+            server.fired_transactions(),
+            vec![vec![Datom(
+                Value::Eid(2),
+                ":overheated".to_string(),
+                Number(120),
+                None,
+                -1
+            )]]
+        );
+    });
+}
@@ -0,0 +1,84 @@
+#[cfg(feature = "serde_json")]
+#[test]
+fn current_version() {
+    use declarative_dataflow::server::versioning::{parse_envelope, CURRENT_VERSION};
+    use declarative_dataflow::server::{Envelope, Request};
+    use declarative_dataflow::{Aid, Datom, Value};
+
+    let envelope: Envelope<Aid> = Envelope {
+        id: 1,
+        version: Some(CURRENT_VERSION),
+        requests: vec![Request::Transact(vec![Datom::add(
+            1,
+            ":name",
+            Value::String("Dipper".to_string()),
+        )])],
+    };
+
+    let serialized = serde_json::to_string(&envelope).unwrap();
+    assert_eq!(parse_envelope::<Aid>(&serialized), Ok(envelope.clone()));
+
+    // Envelopes without a version are in the current format.
+    let unversioned = serialized.replace(&format!(",\"version\":{}", CURRENT_VERSION), "");
+    assert_eq!(
+        parse_envelope::<Aid>(&unversioned).map(|envelope| envelope.requests),
+        Ok(envelope.requests)
+    );
+}
+
+#[cfg(feature = "serde_json")]
+#[test]
+fn upgrade_v1() {
+    use declarative_dataflow::server::versioning::{parse_envelope, CURRENT_VERSION};
+    use declarative_dataflow::server::{CreateAttribute, Envelope, Request};
+    use declarative_dataflow::{Aid, AttributeConfig, Datom, InputSemantics, Time, Value};
+
+    let v1 = r#"{"id": 9, "version": 1, "requests": [
+        {"CreateAttribute": {"name": ":name", "semantics": "Raw"}},
+        {"Transact": [[1, {"Eid": 1}, ":name", {"String": "Dipper"}],
+                      [-1, {"Eid": 2}, ":name", {"String": "Mabel"}, {"TxId": 3}]]}
+    ]}"#;
+
+    let expected: Envelope<Aid> = Envelope {
+        id: 9,
+        version: Some(CURRENT_VERSION),
+        requests: vec![
+            Request::CreateAttribute(CreateAttribute {
+                name: ":name".to_string(),
+                config: AttributeConfig::tx_time(InputSemantics::Raw),
+                backfill: None,
+            }),
+            Request::Transact(vec![
+                Datom::add(1, ":name", Value::String("Dipper".to_string())),
+                Datom(
+                    Value::Eid(2),
+                    ":name".to_string(),
+                    Value::String("Mabel".to_string()),
+                    Some(Time::TxId(3)),
+                    -1,
+                ),
+            ]),
+        ],
+    };
+
+    assert_eq!(parse_envelope::<Aid>(v1), Ok(expected));
+}
+
+#[cfg(feature = "serde_json")]
+#[test]
+fn unsupported_version() {
+    use declarative_dataflow::server::versioning::parse_envelope;
+    use declarative_dataflow::{Aid, ProtocolError};
+
+    let future = r#"{"id": 1, "version": 99, "requests": []}"#;
+    assert_eq!(
+        parse_envelope::<Aid>(future),
+        Err(ProtocolError::UnsupportedVersion(99))
+    );
+
+    let v1 = r#"{"id": 1, "version": 1, "requests": [{"Transact": [[1, {"Eid": 1}]]}]}"#;
+    match parse_envelope::<Aid>(v1) {
+        Err(ProtocolError::Malformed(_)) => {}
+        other => panic!("expected a malformed request, got {:?}", other),
+    }
+}
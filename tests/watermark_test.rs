@@ -0,0 +1,137 @@
+use std::time::Duration;
+
+use declarative_dataflow::sources::{LateData, LatePolicy, Watermark};
+
+#[test]
+fn bounded_lateness() {
+    let mut watermark = Watermark::new(Duration::from_secs(5), Duration::from_secs(0));
+
+    // Nothing moves until the watermark is advanced.
+    assert_eq!(
+        watermark.observe(Duration::from_secs(10)),
+        Duration::from_secs(10)
+    );
+    assert_eq!(
+        watermark.observe(Duration::from_secs(3)),
+        Duration::from_secs(3)
+    );
+    assert_eq!(watermark.current(), Duration::from_secs(0));
+
+    assert_eq!(watermark.advance(), Duration::from_secs(5));
+    assert!(watermark.is_late(Duration::from_secs(4)));
+    assert!(!watermark.is_late(Duration::from_secs(5)));
+
+    // Late records are moved up to the watermark.
+    assert_eq!(
+        watermark.observe(Duration::from_secs(4)),
+        Duration::from_secs(5)
+    );
+
+    // The watermark never moves backwards.
+    assert_eq!(
+        watermark.observe(Duration::from_secs(7)),
+        Duration::from_secs(7)
+    );
+    assert_eq!(watermark.advance(), Duration::from_secs(5));
+}
+
+#[test]
+fn late_data_defaults() {
+    // Attributes without a policy keep their late datoms.
+    assert_eq!(LatePolicy::<String>::default(), LatePolicy::Correct);
+
+    let late_data = LateData::default();
+    assert_eq!(late_data.corrected, 0);
+    assert_eq!(late_data.dropped, 0);
+    assert_eq!(late_data.dead_lettered, 0);
+}
+
+#[cfg(all(feature = "csv-source", feature = "real-time"))]
+#[test]
+fn csv_event_times() {
+    use std::io::Write;
+    use std::sync::mpsc::channel;
+    use std::time::Instant;
+
+    use timely::dataflow::operators::Inspect;
+
+    use declarative_dataflow::clock::since_unix_epoch;
+    use declarative_dataflow::server::Server;
+    use declarative_dataflow::sources::CsvFile;
+    use declarative_dataflow::{Aid, Plan, Rule, Value};
+
+    let path = std::env::temp_dir().join(format!("event_times_{}.csv", std::process::id()));
+
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, Duration, u64>::new(Default::default());
+        let (send_results, results) = channel();
+
+        // Both records happen shortly after the server started.
+        let now = since_unix_epoch().as_millis() as u64;
+        let mut file = std::fs::File::create(&path).unwrap();
+        writeln!(file, "1,{},10", now + 1000).unwrap();
+        writeln!(file, "2,{},20", now + 2000).unwrap();
+        drop(file);
+
+        let source = CsvFile {
+            path: path.to_string_lossy().to_string(),
+            has_headers: false,
+            delimiter: b',',
+            comment: None,
+            flexible: false,
+            eid_offset: 0,
+            timestamp_offset: Some(1),
+            max_lateness: None,
+            late_policies: vec![],
+            schema: vec![(":amount".to_string(), (2, Value::Number(0)))],
+            fuel: None,
+            interval: None,
+            partitioning: Default::default(),
+        };
+
+        worker.dataflow::<Duration, _, _>(|scope| {
+            server.register_source(Box::new(source), scope).unwrap();
+
+            server
+                .test_single(
+                    scope,
+                    Rule::named("amounts", Plan::match_a(0, ":amount", 1)),
+                )
+                .inner
+                .inspect(move |x| send_results.send(x.clone()).unwrap());
+        });
+
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let mut received = Vec::new();
+        while received.len() < 2 {
+            assert!(Instant::now() < deadline, "received only {:?}", received);
+            worker.step();
+            received.extend(results.try_iter());
+        }
+
+        received.sort_by_key(|(_tuple, t, _diff)| *t);
+
+        let expected = vec![
+            (
+                vec![Value::Eid(1), Value::Number(10)],
+                Duration::from_secs(1),
+            ),
+            (
+                vec![Value::Eid(2), Value::Number(20)],
+                Duration::from_secs(2),
+            ),
+        ];
+
+        for ((tuple, t, diff), (expected_tuple, expected_t)) in received.into_iter().zip(expected) {
+            assert_eq!(tuple, expected_tuple);
+            assert_eq!(diff, 1);
+
+            // Records are introduced relative to the start of the
+            // server, which happened just before reading the clock.
+            assert!(t >= expected_t - Duration::from_millis(1), "{:?}", t);
+            assert!(t < expected_t + Duration::from_millis(500), "{:?}", t);
+        }
+
+        std::fs::remove_file(&path).unwrap();
+    });
+}